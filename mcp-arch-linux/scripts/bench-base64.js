@@ -0,0 +1,54 @@
+#!/usr/bin/env node
+// Compares inline and chunked base64 encoding of a capture-sized buffer: total time
+// and the longest event-loop stall, which is what other tool calls wait on.
+//   node scripts/bench-base64.js [sizeMB] [runs]
+import crypto from 'crypto';
+import fs from 'fs-extra';
+import os from 'os';
+import path from 'path';
+import { encodeBase64, encodeFileBase64 } from '../src/system/base64.js';
+
+const sizeMB = Number(process.argv[2] || 30);
+const runs = Number(process.argv[3] || 5);
+const data = crypto.randomBytes(sizeMB * 1024 * 1024);
+const file = path.join(os.tmpdir(), `bench-base64-${process.pid}.bin`);
+await fs.writeFile(file, data);
+
+async function measure(name, encode) {
+  const expected = data.toString('base64');
+  const times = [];
+  let maxStall = 0;
+
+  for (let run = 0; run < runs; run++) {
+    // A 1 ms ticker stands in for other requests; its longest gap is the stall they see
+    let last = performance.now();
+    const ticker = setInterval(() => {
+      const now = performance.now();
+      maxStall = Math.max(maxStall, now - last);
+      last = now;
+    }, 1);
+
+    const start = performance.now();
+    const encoded = await encode();
+    times.push(performance.now() - start);
+    await new Promise(resolve => setTimeout(resolve, 5));
+    clearInterval(ticker);
+
+    if (encoded !== expected) {
+      throw new Error(`${name} produced a different encoding`);
+    }
+  }
+
+  const mean = times.reduce((sum, time) => sum + time, 0) / times.length;
+  console.log(`${name.padEnd(22)} mean ${mean.toFixed(1).padStart(7)} ms   max event-loop stall ${maxStall.toFixed(1).padStart(7)} ms`);
+}
+
+console.log(`${sizeMB} MB, ${runs} runs`);
+try {
+  await measure('inline toString', async () => data.toString('base64'));
+  await measure('readFile + toString', async () => (await fs.readFile(file)).toString('base64'));
+  await measure('encodeBase64', () => encodeBase64(data));
+  await measure('encodeFileBase64', () => encodeFileBase64(file));
+} finally {
+  await fs.remove(file);
+}
@@ -140,8 +140,11 @@ Requires=dbus.service
 Documentation=https://github.com/Grow-Myelin/architect
 
 [Service]
-Type=simple
+Type=notify
+NotifyAccess=all
+WatchdogSec=60
 ExecStart=$INSTALL_PREFIX/bin/mcp-arch-server --config $CONFIG_DIR/server.yaml
+ExecReload=/bin/kill -HUP \$MAINPID
 Restart=always
 RestartSec=10
 TimeoutStopSec=20
@@ -191,6 +194,20 @@ SyslogIdentifier=mcp-arch-linux
 
 [Install]
 WantedBy=multi-user.target
+EOF
+    
+    # Optional on-demand activation: enable mcp-arch-linux.socket instead of the service
+    cat > "$SYSTEMD_DIR/mcp-arch-linux.socket" << EOF
+[Unit]
+Description=MCP Arch Linux Server socket
+Documentation=https://github.com/Grow-Myelin/architect
+
+[Socket]
+ListenStream=127.0.0.1:8080
+NoDelay=yes
+
+[Install]
+WantedBy=sockets.target
 EOF
     
     # Reload systemd
@@ -255,6 +272,7 @@ main() {
     echo "1. Review configuration: $CONFIG_DIR/server.yaml"
     echo "2. Enable the service: systemctl enable mcp-arch-linux"
     echo "3. Start the service: systemctl start mcp-arch-linux"
+    echo "   (or enable mcp-arch-linux.socket to start the server on first connection)"
     echo "4. Check status: systemctl status mcp-arch-linux"
     echo "5. View logs: journalctl -u mcp-arch-linux -f"
     echo
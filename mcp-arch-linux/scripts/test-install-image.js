@@ -0,0 +1,104 @@
+#!/usr/bin/env node
+// Runs the install pipeline against a throwaway disk image instead of a real disk:
+// attach a loop device, partition, format, mount under a temporary directory and,
+// with --pacstrap, install a base system. Everything is torn down at the end.
+// Needs root (or passwordless sudo) for losetup, sgdisk, mkfs and mount.
+//   node scripts/test-install-image.js [--scheme uefi|bios] [--size 4G] [--pacstrap]
+//     [--mirror http://localhost:8080/$repo/os/$arch] [--packages base,linux] [--image file] [--keep]
+import fs from 'fs-extra';
+import os from 'os';
+import path from 'path';
+import { Config } from '../src/core/config.js';
+import { Logger } from '../src/core/logger.js';
+import { ArchInstallPlugin } from '../src/plugins/arch-install-plugin.js';
+import { SecurityManager } from '../src/security/security-manager.js';
+
+function parseArgs(argv) {
+  const options = { scheme: 'uefi', size: '4G', pacstrap: false, mirror: null, packages: 'base', image: null, keep: false };
+  for (let i = 0; i < argv.length; i++) {
+    const name = argv[i].replace(/^--/, '');
+    if (name === 'pacstrap' || name === 'keep') {
+      options[name] = true;
+    } else if (name in options) {
+      options[name] = argv[++i];
+    } else {
+      throw new Error(`Unknown option: ${argv[i]}`);
+    }
+  }
+  return options;
+}
+
+const options = parseArgs(process.argv.slice(2));
+const image = options.image || path.join(os.tmpdir(), `arch-install-test-${process.pid}.img`);
+
+const config = new Config();
+await config.load({ readOnly: true });
+const logger = new Logger({ ...config.get('logging'), level: 'warn', logDir: path.join(os.tmpdir(), 'mcp-arch-linux-install-test') });
+const security = new SecurityManager({
+  ...config.get('security'),
+  autoSnapshot: false,
+  autoInhibitIdle: false,
+  transcript: { enabled: false },
+  history: { enabled: false },
+  etcVersioning: { enabled: false },
+  snapshotDir: path.join(os.tmpdir(), 'mcp-arch-linux-install-test', 'snapshots'),
+  retention: config.get('plugins.system.retention'),
+  storage: config.get('storage')
+}, logger);
+
+const plugin = new ArchInstallPlugin(config, logger, security);
+plugin.requireDeviceConfirmation = false;
+await plugin.initialize();
+
+async function step(tool, args) {
+  const start = Date.now();
+  const result = await plugin.executeTool(tool, args);
+  const text = result.content?.[0]?.text || '';
+  if (result.isError) {
+    throw new Error(`${tool} failed: ${text}`);
+  }
+  console.log(`ok    ${tool.padEnd(24)} ${((Date.now() - start) / 1000).toFixed(1)}s`);
+  return text;
+}
+
+// pacstrap reads the host's mirrorlist, which arch_install_base rewrites for --mirror
+const mirrorlist = '/etc/pacman.d/mirrorlist';
+const savedMirrorlist = options.mirror && await fs.pathExists(mirrorlist) ? await fs.readFile(mirrorlist) : null;
+
+let failed = false;
+try {
+  const { device, targetMount } = JSON.parse(await step('arch_install_target', { type: 'image', image, size: options.size }));
+  console.log(`      ${image} on ${device}, mounted at ${targetMount}`);
+
+  await step('arch_partition_disk', { device, scheme: options.scheme, swapSize: '256M' });
+  await step('arch_mount_system', { device, scheme: options.scheme });
+
+  if (options.pacstrap) {
+    await step('arch_install_base', {
+      packages: options.packages.split(','),
+      ...(options.mirror ? { mirror: options.mirror } : {})
+    });
+    if (!await fs.pathExists(path.join(targetMount, 'etc/fstab'))) {
+      throw new Error('pacstrap finished but the target has no /etc/fstab');
+    }
+  }
+} catch (error) {
+  failed = true;
+  console.error(`FAIL  ${error.message}`);
+} finally {
+  try {
+    await step('arch_install_target', { type: 'disk' });
+  } catch (error) {
+    failed = true;
+    console.error(`FAIL  teardown: ${error.message}`);
+  }
+  if (savedMirrorlist) {
+    await fs.writeFile(mirrorlist, savedMirrorlist);
+  }
+  if (!options.keep && !options.image) {
+    await fs.remove(image);
+  }
+}
+
+console.log(failed ? 'FAILED' : 'PASSED');
+process.exit(failed ? 1 : 0);
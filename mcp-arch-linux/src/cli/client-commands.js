@@ -0,0 +1,197 @@
+import readline from 'readline';
+import { MCPClient } from '../core/mcp-client.js';
+import { Config } from '../core/config.js';
+
+// Values are parsed as JSON when possible so numbers, booleans and arrays survive the shell
+export function parseArgValue(raw) {
+  try {
+    return JSON.parse(raw);
+  } catch {
+    return raw;
+  }
+}
+
+export function parseArgPairs(pairs) {
+  const args = {};
+  for (const pair of pairs) {
+    const index = pair.indexOf('=');
+    if (index <= 0) {
+      throw new Error(`Invalid argument "${pair}", expected key=value`);
+    }
+    args[pair.slice(0, index)] = parseArgValue(pair.slice(index + 1));
+  }
+  return args;
+}
+
+// Split a REPL line on whitespace, keeping quoted values (e.g. description="two words") together
+export function tokenize(line) {
+  return (line.match(/(?:[^\s"']+|"[^"]*"|'[^']*')+/g) || [])
+    .map(token => token.replace(/(["'])(.*?)\1/g, '$2'));
+}
+
+export async function resolveServerUrl(options) {
+  if (options.url) {
+    return options.url;
+  }
+
+  const config = new Config(options.config);
+  await config.load({ readOnly: true });
+  const host = config.get('server.host') || 'localhost';
+  if (host.startsWith('unix://')) {
+    return host;
+  }
+  const port = config.get('server.port') || 8080;
+  return `http://${host}:${port}/mcp`;
+}
+
+export function formatResult(result) {
+  const lines = [];
+
+  for (const item of Array.isArray(result.content) ? result.content : []) {
+    switch (item.type) {
+      case 'text':
+        lines.push(item.text);
+        break;
+      case 'image':
+        lines.push(`[image ${item.mimeType}, ${Math.round((item.data?.length || 0) * 3 / 4)} bytes]`);
+        break;
+      case 'resource':
+        lines.push(`[resource ${item.uri}]`);
+        break;
+      default:
+        lines.push(JSON.stringify(item, null, 2));
+    }
+  }
+
+  // resources/read returns text or base64 blob contents
+  for (const item of Array.isArray(result.contents) ? result.contents : []) {
+    lines.push(item.text ?? `[blob ${item.mimeType}, ${Math.round((item.blob?.length || 0) * 3 / 4)} bytes]`);
+  }
+
+  return lines.join('\n');
+}
+
+function printError(error) {
+  console.error(`Error${error.code ? ` ${error.code}` : ''}: ${error.message}`);
+  if (error.data) {
+    console.error(JSON.stringify(error.data, null, 2));
+  }
+}
+
+export async function runCall(tool, options) {
+  const client = new MCPClient(await resolveServerUrl(options), { timeout: options.timeout });
+
+  try {
+    await client.connect();
+    const args = { ...(options.json ? JSON.parse(options.json) : {}), ...parseArgPairs(options.arg) };
+    const result = await client.callTool(tool, args);
+
+    console.log(options.raw ? JSON.stringify(result, null, 2) : formatResult(result));
+    return result.isError ? 1 : 0;
+  } catch (error) {
+    printError(error);
+    return 1;
+  }
+}
+
+const REPL_HELP = `Commands:
+  tools                       list tools
+  describe <tool>             show a tool's input schema
+  call <tool> [key=value...]  call a tool
+  resources                   list resources and resource templates
+  read <uri>                  read a resource
+  raw on|off                  print raw JSON results
+  help, exit`;
+
+export async function runRepl(options) {
+  const client = new MCPClient(await resolveServerUrl(options), { timeout: options.timeout });
+
+  try {
+    await client.connect();
+  } catch (error) {
+    printError(error);
+    return 1;
+  }
+
+  let tools = await client.listTools();
+  let raw = false;
+  console.log(`Connected to ${client.serverInfo?.name} ${client.serverInfo?.version} at ${client.url}`);
+  console.log('Type "help" for commands.');
+
+  const rl = readline.createInterface({
+    input: process.stdin,
+    output: process.stdout,
+    prompt: 'mcp> ',
+    completer: (line) => {
+      const words = ['tools', 'describe', 'call', 'resources', 'read', 'raw', 'help', 'exit'];
+      const [command, partial = ''] = line.split(/\s+/);
+      const candidates = ['call', 'describe'].includes(command) ?
+        tools.map(tool => `${command} ${tool.name}`).filter(entry => entry.startsWith(`${command} ${partial}`)) :
+        words.filter(word => word.startsWith(line));
+      return [candidates, line];
+    }
+  });
+
+  const handlers = {
+    help: () => console.log(REPL_HELP),
+    tools: async () => {
+      tools = await client.listTools();
+      for (const tool of tools) {
+        console.log(`${tool.name.padEnd(28)} ${tool.description}`);
+      }
+    },
+    describe: (name) => {
+      const tool = tools.find(candidate => candidate.name === name);
+      if (!tool) throw new Error(`Unknown tool: ${name}`);
+      console.log(JSON.stringify(tool.inputSchema, null, 2));
+    },
+    call: async (name, ...pairs) => {
+      if (!name) throw new Error('Usage: call <tool> [key=value...]');
+      const result = await client.callTool(name, parseArgPairs(pairs));
+      console.log(raw ? JSON.stringify(result, null, 2) : formatResult(result));
+    },
+    resources: async () => {
+      for (const resource of await client.listResources()) {
+        console.log(`${resource.uri.padEnd(28)} ${resource.description || resource.name}`);
+      }
+      for (const template of await client.listResourceTemplates()) {
+        console.log(`${template.uriTemplate.padEnd(28)} ${template.description || template.name}`);
+      }
+    },
+    read: async (uri) => {
+      if (!uri) throw new Error('Usage: read <uri>');
+      const result = await client.readResource(uri);
+      console.log(raw ? JSON.stringify(result, null, 2) : formatResult(result));
+    },
+    raw: (mode) => {
+      raw = mode !== 'off';
+      console.log(`Raw output ${raw ? 'on' : 'off'}`);
+    }
+  };
+
+  rl.prompt();
+
+  for await (const line of rl) {
+    const [command, ...rest] = tokenize(line.trim());
+
+    if (command === 'exit' || command === 'quit') {
+      break;
+    }
+
+    if (command) {
+      try {
+        if (!handlers[command]) {
+          throw new Error(`Unknown command: ${command} (try "help")`);
+        }
+        await handlers[command](...rest);
+      } catch (error) {
+        printError(error);
+      }
+    }
+
+    rl.prompt();
+  }
+
+  rl.close();
+  return 0;
+}
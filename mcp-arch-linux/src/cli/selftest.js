@@ -0,0 +1,194 @@
+import os from 'os';
+import path from 'path';
+import { Config } from '../core/config.js';
+import { Logger } from '../core/logger.js';
+import { PluginManager } from '../core/plugin-manager.js';
+import { configureSimulation, createPluginFactories, selectPlugins } from '../core/mcp-server.js';
+import { SecurityManager } from '../security/security-manager.js';
+import { getToolResolver } from '../system/tool-resolver.js';
+import { getTargetRegistry } from '../system/target.js';
+
+const TOOL_TIMEOUT = 20000;
+const JSON_TYPES = ['object', 'array', 'string', 'number', 'integer', 'boolean', 'null'];
+
+// Structural checks standing in for a JSON Schema compiler: the shapes clients and
+// validateArgs rely on, and defaults that contradict their own enum
+export function checkSchema(schema) {
+  const problems = [];
+  if (!schema || schema.type !== 'object') {
+    return ['inputSchema must be an object schema'];
+  }
+
+  const properties = schema.properties || {};
+  for (const field of schema.required || []) {
+    if (!properties[field]) {
+      problems.push(`required field ${field} is not in properties`);
+    }
+  }
+
+  const walk = (property, name) => {
+    if (property.type && !JSON_TYPES.includes(property.type)) {
+      problems.push(`${name}: unknown type ${property.type}`);
+    }
+    if (property.enum && (!Array.isArray(property.enum) || property.enum.length === 0)) {
+      problems.push(`${name}: enum must be a non-empty array`);
+    }
+    if (property.enum && property.default !== undefined && !property.enum.includes(property.default)) {
+      problems.push(`${name}: default ${JSON.stringify(property.default)} is not in enum`);
+    }
+    for (const [child, value] of Object.entries(property.properties || {})) {
+      walk(value, `${name}.${child}`);
+    }
+    if (property.items) {
+      walk(property.items, `${name}[]`);
+    }
+  };
+  for (const [name, property] of Object.entries(properties)) {
+    walk(property, name);
+  }
+  return problems;
+}
+
+// Arguments that keep a call side-effect free: dry-run where the tool has it, and
+// captures that are returned rather than saved. null when required input is missing.
+export function probeArgs(tool) {
+  const properties = tool.inputSchema?.properties || {};
+  const args = {};
+  if (properties.dryRun) {
+    args.dryRun = true;
+  }
+  if (properties.save) {
+    args.save = false;
+  }
+  return (tool.inputSchema?.required || []).every(field => field in args) ? args : null;
+}
+
+function withTimeout(promise, ms) {
+  let timer;
+  return Promise.race([
+    promise,
+    new Promise((resolve, reject) => {
+      timer = setTimeout(() => reject(new Error(`timed out after ${ms} ms`)), ms);
+    })
+  ]).finally(() => clearTimeout(timer));
+}
+
+function createLogger(config, debug) {
+  const logging = { ...config.get('logging'), level: debug ? 'debug' : 'error' };
+  try {
+    return new Logger(logging);
+  } catch {
+    // Unprivileged runs usually cannot write the server's log directory
+    return new Logger({ ...logging, logDir: path.join(os.tmpdir(), 'mcp-arch-linux-selftest') });
+  }
+}
+
+async function probeTool(pluginManager, tool, plugin, run) {
+  const row = { tool: tool.name, plugin: plugin.name, schema: 'ok', run: 'skip', notes: [] };
+
+  const problems = checkSchema(tool.inputSchema);
+  if (problems.length > 0) {
+    row.schema = 'fail';
+    row.notes.push(...problems);
+  }
+
+  const args = probeArgs(tool);
+  if (!run) {
+    row.notes.push('not run (--no-run)');
+  } else if (!args) {
+    row.notes.push('needs arguments');
+  } else if (!args.dryRun && !(plugin.isIdempotent && plugin.isIdempotent(tool.name, args))) {
+    row.notes.push('no dry-run or read-only mode');
+  } else {
+    try {
+      const result = await withTimeout(pluginManager.executeTool(tool.name, args, { operationId: 'selftest' }), TOOL_TIMEOUT);
+      row.run = result?.isError ? 'fail' : 'ok';
+      if (result?.isError) {
+        row.notes.push((result.content?.[0]?.text || 'error result').split('\n')[0]);
+      }
+    } catch (error) {
+      row.run = 'fail';
+      row.notes.push(error.message.split('\n')[0]);
+    }
+  }
+  return row;
+}
+
+export async function runSelftest(options = {}) {
+  const config = new Config(options.config);
+  await config.load({ readOnly: true, overrides: options.simulate ? { simulation: { enabled: true } } : {} });
+
+  const logger = createLogger(config, options.debug);
+  const security = new SecurityManager({
+    ...config.get('security'),
+    autoSnapshot: false,
+    autoInhibitIdle: false,
+    transcript: { enabled: false },
+    history: { enabled: false },
+    etcVersioning: { enabled: false },
+    snapshotDir: config.get('plugins.system.snapshotDir'),
+    retention: config.get('plugins.system.retention'),
+    storage: config.get('storage')
+  }, logger);
+
+  getToolResolver().configure(config.get('tools.paths') || {});
+  await configureSimulation(config.get('simulation'), logger);
+  getTargetRegistry().configure(config.get('targets') || {});
+  const pluginManager = new PluginManager(logger, security);
+  const plugins = [];
+
+  const { enabled, disabled } = selectPlugins(config, createPluginFactories(config, logger, security));
+  for (const factory of enabled) {
+    const plugin = factory.create();
+    try {
+      await pluginManager.register(plugin);
+      const checks = plugin.getHealthChecks ? await plugin.getHealthChecks() : [];
+      const status = checks.some(check => check.status === 'fail') ? 'fail' :
+        checks.some(check => check.status === 'warn') ? 'warn' : 'ok';
+      plugins.push({ plugin: factory.name, status, checks });
+    } catch (error) {
+      plugins.push({ plugin: factory.name, status: 'fail', checks: [], error: error.message });
+    }
+  }
+  for (const factory of disabled) {
+    plugins.push({ plugin: factory.name, status: 'disabled', checks: [] });
+  }
+
+  const tools = [];
+  for (const { tool, plugin } of pluginManager.tools.values()) {
+    const row = await probeTool(pluginManager, tool, plugin, options.run !== false);
+    row.binaries = plugins.find(entry => entry.plugin === plugin.name)?.status || 'ok';
+    tools.push(row);
+  }
+
+  await pluginManager.cleanup();
+  await security.cleanup();
+
+  const failed = plugins.some(entry => entry.status === 'fail') ||
+    tools.some(row => row.schema === 'fail' || row.run === 'fail');
+
+  if (options.json) {
+    console.log(JSON.stringify({ ready: !failed, plugins, tools }, null, 2));
+  } else {
+    printMatrix(plugins, tools);
+    console.log(`\n${failed ? 'NOT READY' : 'READY'}: ${tools.length} tools, ` +
+      `${tools.filter(row => row.run === 'ok').length} exercised, ` +
+      `${tools.filter(row => row.schema === 'fail' || row.run === 'fail').length} failing`);
+  }
+  return failed ? 1 : 0;
+}
+
+function printMatrix(plugins, tools) {
+  console.log('Plugins');
+  for (const entry of plugins) {
+    const missing = entry.checks.flatMap(check => [...(check.missing?.required || []), ...(check.missing?.optional || [])]);
+    const detail = entry.error || (missing.length > 0 ? `missing: ${missing.join(', ')}` : '');
+    console.log(`  ${entry.status.padEnd(8)} ${entry.plugin.padEnd(16)} ${detail}`);
+  }
+
+  const width = Math.max(4, ...tools.map(row => row.tool.length));
+  console.log(`\n  ${'TOOL'.padEnd(width)}  SCHEMA  BINARIES  RUN   NOTES`);
+  for (const row of tools) {
+    console.log(`  ${row.tool.padEnd(width)}  ${row.schema.padEnd(6)}  ${row.binaries.padEnd(8)}  ${row.run.padEnd(4)}  ${row.notes.join('; ')}`);
+  }
+}
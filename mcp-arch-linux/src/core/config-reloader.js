@@ -0,0 +1,121 @@
+// Settings that can change without a restart, keyed by config path prefix
+const LIVE_SETTINGS = [
+  'logging.level',
+  'security.allowedCommands',
+  'security.commandTimeout',
+  'security.retry',
+  'security.maxConcurrentOperations',
+  'security.lockWaitTimeout',
+  'security.autoSnapshot',
+  'security.autoInhibitIdle',
+  'security.readOnly',
+  'plugins.system.retention',
+  /^plugins\.[^.]+\.enabled$/
+];
+
+export class ConfigReloader {
+  constructor({ config, logger, security, pluginManager, protocol, pluginFactories }) {
+    this.config = config;
+    this.logger = logger;
+    this.security = security;
+    this.pluginManager = pluginManager;
+    this.protocol = protocol;
+    this.pluginFactories = pluginFactories;
+    this.reloading = null;
+  }
+
+  isLive(keyPath) {
+    return LIVE_SETTINGS.some(setting => typeof setting === 'string' ?
+      keyPath === setting || keyPath.startsWith(`${setting}.`) :
+      setting.test(keyPath));
+  }
+
+  async reload() {
+    // Coalesce a SIGHUP arriving while a config/reload request is in flight
+    if (!this.reloading) {
+      this.reloading = this.applyReload().finally(() => {
+        this.reloading = null;
+      });
+    }
+    return this.reloading;
+  }
+
+  async applyReload() {
+    this.logger.info('Reloading configuration');
+
+    const changed = await this.config.reload();
+    const applied = changed.filter(keyPath => this.isLive(keyPath));
+    const requiresRestart = changed.filter(keyPath => !this.isLive(keyPath));
+
+    if (applied.some(keyPath => keyPath === 'logging.level')) {
+      this.logger.setLevel(this.config.get('logging.level'));
+    }
+
+    if (applied.some(keyPath => keyPath.startsWith('security.'))) {
+      this.applySecurity(this.config.get('security'));
+    }
+
+    if (applied.some(keyPath => keyPath.startsWith('plugins.system.retention'))) {
+      this.security.rollback.retention = { ...this.security.rollback.retention, ...this.config.get('plugins.system.retention') };
+      this.security.rollback.stopPruning();
+      this.security.rollback.startPruning();
+    }
+
+    const toggled = applied.filter(keyPath => /^plugins\.[^.]+\.enabled$/.test(keyPath));
+    for (const keyPath of toggled) {
+      await this.applyPluginToggle(keyPath.split('.')[1]);
+    }
+    if (toggled.length > 0 || applied.includes('security.readOnly')) {
+      this.protocol.notify('notifications/tools/list_changed');
+      this.protocol.notify('notifications/resources/list_changed');
+    }
+
+    const result = {
+      files: this.config.loadedFiles,
+      applied,
+      requiresRestart
+    };
+
+    this.logger.audit('config_reloaded', result);
+    if (requiresRestart.length > 0) {
+      this.logger.warn('Some configuration changes require a restart', { requiresRestart });
+    }
+
+    return result;
+  }
+
+  applySecurity(settings) {
+    this.security.maxConcurrentOperations = settings.maxConcurrentOperations;
+    this.security.autoSnapshot = settings.autoSnapshot;
+    this.security.autoInhibitIdle = settings.autoInhibitIdle;
+    this.security.readOnly = settings.readOnly;
+    this.security.locks.waitTimeout = settings.lockWaitTimeout;
+
+    const executors = [this.security.rollback.packageState.executor, this.security.idle.commandExecutor];
+    for (const plugin of this.pluginManager.plugins.values()) {
+      if (plugin.commandExecutor) {
+        executors.push(plugin.commandExecutor);
+      }
+    }
+
+    for (const executor of executors) {
+      executor.updateConfig({ ...executor.config, ...settings });
+    }
+  }
+
+  async applyPluginToggle(key) {
+    const factory = this.pluginFactories[key];
+    if (!factory) {
+      return;
+    }
+
+    const enabled = this.config.get(`plugins.${key}.enabled`);
+    const registered = this.pluginManager.getPlugin(factory.name);
+
+    if (enabled && !registered) {
+      await this.pluginManager.register(factory.create());
+    } else if (!enabled && registered) {
+      await this.pluginManager.unregister(factory.name);
+    }
+  }
+}
@@ -1,20 +1,80 @@
 import fs from 'fs-extra';
+import os from 'os';
 import path from 'path';
 import YAML from 'yaml';
+import { parse as parseToml } from 'smol-toml';
 import Joi from 'joi';
+import { WINDOW_PATTERN } from '../security/maintenance-window.js';
+import { EVENT_FIELDS } from '../system/automation-rules.js';
+
+const ENV_PREFIX = 'MCP_ARCH_LINUX__';
+const CONFIG_NAMES = ['server.yaml', 'config.yaml', 'config.yml', 'config.toml'];
+
+// Every binary the bundled plugins invoke; sudo itself is implied for allowed commands
+const DEFAULT_ALLOWED_COMMANDS = [
+  'pacman', 'pacstrap', 'genfstab', 'arch-chroot', 'checkupdates',
+  'systemctl', 'systemd-analyze', 'systemd-run', 'machinectl', 'journalctl', 'mount', 'umount', 'swapon', 'swapoff', 'findmnt', 'mkdir', 'reboot',
+  'sgdisk', 'fdisk', 'mkfs.ext4', 'mkfs.btrfs', 'mkfs.xfs', 'mkfs.f2fs', 'mkfs.fat', 'mkswap', 'mdadm', 'wipefs', 'lsblk', 'blkid', 'smartctl', 'dd', 'pacman-key', 'gpg',
+  'blkdiscard', 'shred', 'nvme', 'sfdisk', 'partx', 'resize2fs', 'e2fsck', 'btrfs', 'xfs_growfs', 'resize.f2fs',
+  'losetup', 'truncate', 'qemu-img', 'zstd', 'xz', 'gzip',
+  'hyprctl', 'grim', 'maim', 'slurp', 'wf-recorder', 'pactl', 'hyprpicker', 'zbarimg',
+  'ps', 'kill', 'hostname', 'uptime', 'free', 'df', 'which', 'systemd-inhibit', 'loginctl', 'useradd', 'usermod', 'timedatectl',
+  'sysctl', 'bootctl', 'grub-mkconfig', 'mkinitcpio', 'modprobe', 'udevadm', 'powerprofilesctl', 'pacman-conf', 'repo-add', 'vulkaninfo',
+  'git', 'runuser', 'gsettings', 'nvim', 'code', 'fc-cache', 'fc-list', 'fc-scan', 'fc-match', 'xdg-mime', 'script',
+  'ip', 'ping', 'iw', 'mtr'
+];
+
+// System-wide files first so per-user files override them
+export function defaultConfigPaths() {
+  const xdgConfig = process.env.XDG_CONFIG_HOME || path.join(os.homedir(), '.config');
+  return [
+    ...CONFIG_NAMES.map(name => path.join('/etc/mcp-arch-linux', name)),
+    ...CONFIG_NAMES.map(name => path.join(xdgConfig, 'mcp-arch-linux', name))
+  ];
+}
 
 const configSchema = Joi.object({
   server: Joi.object({
     host: Joi.string().default('localhost'),
     port: Joi.number().integer().min(1).max(65535).default(8080),
+    transport: Joi.string().valid('http', 'stdio').default('http'),
+    socket: Joi.object({
+      mode: Joi.string().pattern(/^0?[0-7]{3}$/).default('0660'),
+      group: Joi.alternatives().try(Joi.string(), Joi.number()).allow(null).default(null),
+      allowedUsers: Joi.array().items(Joi.string(), Joi.number()).default([]),
+      allowedGroups: Joi.array().items(Joi.string(), Joi.number()).default([])
+    }).default(),
     cors: Joi.object({
       origin: Joi.alternatives().try(Joi.boolean(), Joi.string(), Joi.array().items(Joi.string())).default(true),
       credentials: Joi.boolean().default(true)
+    }).default(),
+    limits: Joi.object({
+      maxConnections: Joi.number().integer().min(1).default(100),
+      headersTimeout: Joi.number().integer().min(0).default(10000),
+      requestTimeout: Joi.number().integer().min(0).default(30000),
+      idleTimeout: Joi.number().integer().min(0).default(360000),
+      keepAliveTimeout: Joi.number().integer().min(0).default(5000),
+      bodyLimit: Joi.alternatives().try(Joi.string(), Joi.number()).default('1MB'),
+      maxMessageSize: Joi.alternatives().try(Joi.string(), Joi.number()).default('1MB'),
+      maxBufferedBytes: Joi.alternatives().try(Joi.string(), Joi.number()).default('8MB'),
+      maxRequestSize: Joi.alternatives().try(Joi.string(), Joi.number()).default('1MB'),
+      maxResponseSize: Joi.alternatives().try(Joi.string(), Joi.number()).default('4MB'),
+      resultPreviewSize: Joi.alternatives().try(Joi.string(), Joi.number()).default('16KB'),
+      resultTtl: Joi.number().integer().min(0).default(900000),
+      socketIdleTimeout: Joi.number().integer().min(0).default(900000)
     }).default()
   }).default(),
 
+  split: Joi.object({
+    role: Joi.string().valid('standalone', 'helper', 'user').default('standalone'),
+    socketPath: Joi.string().default('/run/mcp-arch-linux/helper.sock'),
+    tokenFile: Joi.string().default('/etc/mcp-arch-linux/helper.token'),
+    socketGroup: Joi.alternatives().try(Joi.string(), Joi.number()).allow(null).default(null)
+  }).default(),
+
   logging: Joi.object({
     level: Joi.string().valid('error', 'warn', 'info', 'debug').default('info'),
+    format: Joi.string().valid('text', 'json').default('text'),
     logDir: Joi.string().default('/var/log/mcp-arch-linux'),
     maxFiles: Joi.string().default('14d'),
     maxSize: Joi.string().default('20m')
@@ -22,126 +82,680 @@ const configSchema = Joi.object({
 
   security: Joi.object({
     requireAuth: Joi.boolean().default(true),
-    allowedCommands: Joi.array().items(Joi.string()).default([
-      'pacman', 'systemctl', 'hyprctl', 'grim', 'wf-recorder', 
-      'sgdisk', 'mkfs.ext4', 'mkfs.fat', 'mkswap', 'mount', 'umount',
-      'arch-chroot', 'pacstrap', 'genfstab'
-    ]),
+    allowedCommands: Joi.array().items(Joi.string()).default(DEFAULT_ALLOWED_COMMANDS),
     maxConcurrentOperations: Joi.number().integer().min(1).default(10),
+    autoSnapshot: Joi.boolean().default(true),
+    autoInhibitIdle: Joi.boolean().default(true),
+    snapshotEncryption: Joi.object({
+      enabled: Joi.boolean().default(false),
+      keyFile: Joi.string().allow(null).default(null),
+      keyEnv: Joi.string().default('MCP_SNAPSHOT_KEY'),
+      allowUnsigned: Joi.boolean().default(false)
+    }).default(),
+    etcVersioning: Joi.object({
+      enabled: Joi.boolean().default(true),
+      repoDir: Joi.string().default('/etc'),
+      ignore: Joi.array().items(Joi.string())
+    }).default(),
+    transcript: Joi.object({
+      enabled: Joi.boolean().default(true),
+      file: Joi.string().default('/var/lib/mcp-arch-linux/transcript.jsonl'),
+      maxEntries: Joi.number().integer().min(1).default(1000)
+    }).default(),
+    history: Joi.object({
+      enabled: Joi.boolean().default(true),
+      file: Joi.string().default('/var/lib/mcp-arch-linux/history.jsonl'),
+      maxEntries: Joi.number().integer().min(1).default(5000)
+    }).default(),
+    lockWaitTimeout: Joi.number().integer().min(0).default(30000),
     commandTimeout: Joi.number().integer().min(1000).default(300000), // 5 minutes
-    auditAll: Joi.boolean().default(true)
+    auditAll: Joi.boolean().default(true),
+    readOnly: Joi.boolean().default(false),
+    presence: Joi.object({
+      guard: Joi.boolean().default(false),
+      idleThreshold: Joi.number().integer().min(0).default(300),
+      tools: Joi.array().items(Joi.string()).default([])
+    }).default(),
+    maintenance: Joi.object({
+      enabled: Joi.boolean().default(false),
+      windows: Joi.array().items(Joi.string().pattern(WINDOW_PATTERN)).default([]),
+      requireNoActiveSession: Joi.boolean().default(false),
+      outsideWindow: Joi.string().valid('reject', 'queue').default('reject'),
+      tools: Joi.array().items(Joi.string()).default([]),
+      jobsFile: Joi.string().default('/var/lib/mcp-arch-linux/jobs.json'),
+      checkInterval: Joi.number().integer().min(1000).default(60000)
+    }).default(),
+    retry: Joi.object({
+      maxAttempts: Joi.number().integer().min(1).default(3),
+      initialDelay: Joi.number().integer().min(0).default(1000),
+      maxDelay: Joi.number().integer().min(0).default(15000),
+      backoffFactor: Joi.number().min(1).default(2)
+    }).default()
+  }).default(),
+
+  storage: Joi.object({
+    minFreePercent: Joi.number().min(0).max(100).default(5),
+    warnPercent: Joi.number().min(0).max(100).default(80),
+    quotas: Joi.object({
+      snapshots: Joi.string().default('1GB'),
+      captures: Joi.string().default('2GB')
+    }).default()
+  }).default(),
+
+  tools: Joi.object({
+    // Absolute paths overriding PATH lookup, e.g. { grim: '/opt/grim/bin/grim' }
+    paths: Joi.object().pattern(Joi.string(), Joi.string()).default({})
+  }).default(),
+
+  fleet: Joi.object({
+    hosts: Joi.array().items(Joi.object({
+      name: Joi.string().pattern(/^[a-zA-Z0-9][a-zA-Z0-9_.-]*$/).required(),
+      url: Joi.string().uri({ scheme: ['http', 'https'] }).required(),
+      token: Joi.string().pattern(/^(env|file):.+$/),
+      tags: Joi.array().items(Joi.string()).default([])
+    })).unique('name').default([]),
+    inventoryFile: Joi.string().allow(null).default('/var/lib/mcp-arch-linux/inventory.json'),
+    timeout: Joi.number().integer().min(1000).default(300000)
+  }).default(),
+
+  targets: Joi.object({
+    default: Joi.string().default('host'),
+    named: Joi.object().pattern(Joi.string(), Joi.string()).default({})
+  }).default(),
+
+  simulation: Joi.object({
+    enabled: Joi.boolean().default(false),
+    rulesFile: Joi.string().allow(null).default(null),
+    rules: Joi.array().items(Joi.object({
+      match: Joi.string().required(),
+      stdout: Joi.string().allow(''),
+      stderr: Joi.string().allow(''),
+      exitCode: Joi.number().integer().min(0).max(255),
+      delay: Joi.number().integer().min(0)
+    })).default([]),
+    default: Joi.object({
+      stdout: Joi.string().allow(''),
+      stderr: Joi.string().allow(''),
+      exitCode: Joi.number().integer().min(0).max(255)
+    }).default({})
   }).default(),
 
   plugins: Joi.object({
     system: Joi.object({
       enabled: Joi.boolean().default(true),
-      snapshotDir: Joi.string().default('/var/lib/mcp-arch-linux/snapshots')
+      snapshotDir: Joi.string().default('/var/lib/mcp-arch-linux/snapshots'),
+      retention: Joi.object({
+        keepLast: Joi.number().integer().min(1).default(20),
+        keepDaily: Joi.number().integer().min(0).default(7),
+        keepWeekly: Joi.number().integer().min(0).default(4),
+        maxTotalSize: Joi.string().allow(null).default(null),
+        pruneInterval: Joi.number().integer().min(0).default(3600000) // 1 hour, 0 disables
+      }).default(),
+      newsFeed: Joi.string().uri().default('https://archlinux.org/feeds/news/')
     }).default(),
     
     archInstall: Joi.object({
       enabled: Joi.boolean().default(true),
-      allowDiskOperations: Joi.boolean().default(true)
+      allowDiskOperations: Joi.boolean().default(true),
+      requireDeviceConfirmation: Joi.boolean().default(false),
+      isoMirror: Joi.string().uri().default('https://geo.mirror.pkgbuild.com/iso/latest/'),
+      isoReleaseUrl: Joi.string().uri().default('https://archlinux.org/iso/latest/'),
+      isoDir: Joi.string().default('/var/lib/mcp-arch-linux/iso'),
+      stateFile: Joi.string().allow(null).default('/var/lib/mcp-arch-linux/install-state.json'),
+      localMirror: Joi.string().allow(null).default(null),
+      packageCacheDirs: Joi.array().items(Joi.string()).default([]),
+      offline: Joi.boolean().default(false)
     }).default(),
     
     hyprland: Joi.object({
       enabled: Joi.boolean().default(true),
-      socketPath: Joi.string().allow(null).default(null) // Auto-detect
+      socketPath: Joi.string().allow(null).default(null), // Auto-detect
+      automation: Joi.object({
+        enabled: Joi.boolean().default(true),
+        rulesFile: Joi.string().default('/var/lib/mcp-arch-linux/hyprland-rules.json'),
+        rules: Joi.array().items(Joi.object({
+          name: Joi.string().required(),
+          on: Joi.string().valid(...Object.keys(EVENT_FIELDS)).required(),
+          match: Joi.object().pattern(Joi.string(), Joi.string()).default({}),
+          actions: Joi.array().items(Joi.object({
+            type: Joi.string().valid('dispatch', 'keyword', 'tool').required(),
+            name: Joi.string().required(),
+            args: Joi.string(),
+            arguments: Joi.object()
+          })).min(1).required()
+        })).default([])
+      }).default()
     }).default(),
     
+    compositor: Joi.object({
+      enabled: Joi.boolean().default(true),
+      type: Joi.string().valid('auto', 'hyprland', 'sway').default('auto'),
+      socketPath: Joi.string().allow(null).default(null) // Auto-detect
+    }).default(),
+
+    dotfiles: Joi.object({
+      enabled: Joi.boolean().default(true),
+      repoPath: Joi.string().default('.local/share/dotfiles'), // Relative to the user's home
+      mode: Joi.string().valid('symlink', 'copy').default('symlink'),
+      ignore: Joi.array().items(Joi.string()).default(['README.md', 'LICENSE', '.gitignore']),
+      templateVars: Joi.object().pattern(Joi.string(), Joi.alternatives().try(Joi.string(), Joi.number(), Joi.boolean())).default({})
+    }).default(),
+
+    git: Joi.object({
+      enabled: Joi.boolean().default(true),
+      allowedPaths: Joi.array().items(Joi.string()).default(['/etc', '/home/*/.local/share/dotfiles'])
+    }).default(),
+
+    editor: Joi.object({
+      enabled: Joi.boolean().default(true)
+    }).default(),
+
+    session: Joi.object({
+      enabled: Joi.boolean().default(true)
+    }).default(),
+
+    shell: Joi.object({
+      enabled: Joi.boolean().default(true)
+    }).default(),
+
+    theme: Joi.object({
+      enabled: Joi.boolean().default(true),
+      user: Joi.string().allow(null).default(null)
+    }).default(),
+
+    fonts: Joi.object({
+      enabled: Joi.boolean().default(true),
+      user: Joi.string().allow(null).default(null)
+    }).default(),
+
+    mime: Joi.object({
+      enabled: Joi.boolean().default(true)
+    }).default(),
+
+    nightLight: Joi.object({
+      enabled: Joi.boolean().default(true),
+      user: Joi.string().allow(null).default(null),
+      backend: Joi.string().valid('auto', 'gammastep', 'wlsunset').default('auto')
+    }).default(),
+
+    power: Joi.object({
+      enabled: Joi.boolean().default(true)
+    }).default(),
+
+    laptop: Joi.object({
+      enabled: Joi.boolean().default(true)
+    }).default(),
+
+    gaming: Joi.object({
+      enabled: Joi.boolean().default(true)
+    }).default(),
+
+    fleet: Joi.object({
+      enabled: Joi.boolean().default(true)
+    }).default(),
+
+    nspawn: Joi.object({
+      enabled: Joi.boolean().default(true),
+      defaultPackages: Joi.array().items(Joi.string()).default(['base']),
+      startTimeout: Joi.number().integer().min(1000).default(30000),
+      registerTargets: Joi.boolean().default(true)
+    }).default(),
+
+    sysctl: Joi.object({
+      enabled: Joi.boolean().default(true),
+      dropInFile: Joi.string().default('/etc/sysctl.d/90-mcp-arch-linux.conf'),
+      bootloader: Joi.string().valid('auto', 'grub', 'systemd-boot', 'uki').default('auto')
+    }).default(),
+
+    devices: Joi.object({
+      enabled: Joi.boolean().default(true),
+      partitionBackupDir: Joi.string().default('/var/lib/mcp-arch-linux/partition-tables')
+    }).default(),
+
+    sensors: Joi.object({
+      enabled: Joi.boolean().default(true),
+      interval: Joi.number().integer().min(500).default(5000),
+      hysteresis: Joi.number().min(0).default(5),
+      limits: Joi.object().pattern(Joi.string(), Joi.number()).default({ default: 85 })
+    }).default(),
+
+    network: Joi.object({
+      enabled: Joi.boolean().default(true),
+      targets: Joi.array().items(Joi.string()).default(['1.1.1.1', '9.9.9.9']),
+      dnsNames: Joi.array().items(Joi.string()).default(['archlinux.org']),
+      portalUrl: Joi.string().uri().default('http://ping.archlinux.org/nm-check.txt'),
+      portalExpect: Joi.string().default('NetworkManager is online')
+    }).default(),
+
     screenCapture: Joi.object({
       enabled: Joi.boolean().default(true),
       captureDir: Joi.string().default('/var/lib/mcp-arch-linux/captures'),
       maxFileSize: Joi.string().default('50MB'),
-      allowRecording: Joi.boolean().default(true)
+      allowRecording: Joi.boolean().default(true),
+      // Seconds; recordings stop on their own after this long
+      maxRecordingDuration: Joi.number().integer().min(1).default(3600)
+    }).default(),
+
+    transcript: Joi.object({
+      enabled: Joi.boolean().default(true)
     }).default()
   }).default()
 });
 
 export class Config {
-  constructor(configPath = './config/server.yaml') {
+  constructor(configPath = null) {
     this.configPath = configPath;
     this.config = {};
+    this.sources = {};
+    this.loadedFiles = [];
+    this.overrides = {};
   }
 
-  async load() {
+  // Layers, lowest precedence first: defaults, config files, MCP_ARCH_LINUX__* env vars, CLI overrides
+  // readOnly skips creating the config file and data directories (used by client commands)
+  async load({ overrides = {}, readOnly = false } = {}) {
+    this.overrides = overrides;
+
     try {
       // Load default config
       this.config = {
         server: {
           host: 'localhost',
           port: 8080,
+          transport: 'http',
+          socket: {
+            mode: '0660',
+            group: null,
+            allowedUsers: [],
+            allowedGroups: []
+          },
           cors: {
             origin: true,
             credentials: true
+          },
+          limits: {
+            maxConnections: 100,
+            headersTimeout: 10000,
+            requestTimeout: 30000,
+            idleTimeout: 360000,
+            keepAliveTimeout: 5000,
+            bodyLimit: '1MB',
+            maxMessageSize: '1MB',
+            maxBufferedBytes: '8MB',
+            maxRequestSize: '1MB',
+            maxResponseSize: '4MB',
+            resultPreviewSize: '16KB',
+            resultTtl: 900000,
+            socketIdleTimeout: 900000
           }
         },
+        split: {
+          role: 'standalone',
+          socketPath: '/run/mcp-arch-linux/helper.sock',
+          tokenFile: '/etc/mcp-arch-linux/helper.token',
+          socketGroup: null
+        },
         logging: {
           level: 'info',
+          format: 'text',
           logDir: '/var/log/mcp-arch-linux',
           maxFiles: '14d',
           maxSize: '20m'
         },
         security: {
           requireAuth: true,
-          allowedCommands: [
-            'pacman', 'systemctl', 'hyprctl', 'grim', 'wf-recorder',
-            'sgdisk', 'mkfs.ext4', 'mkfs.fat', 'mkswap', 'mount', 'umount',
-            'arch-chroot', 'pacstrap', 'genfstab'
-          ],
+          allowedCommands: [...DEFAULT_ALLOWED_COMMANDS],
           maxConcurrentOperations: 10,
+          autoSnapshot: true,
+          autoInhibitIdle: true,
+          snapshotEncryption: {
+            enabled: false,
+            keyFile: null,
+            keyEnv: 'MCP_SNAPSHOT_KEY',
+            allowUnsigned: false
+          },
+          etcVersioning: {
+            enabled: true,
+            repoDir: '/etc'
+          },
+          transcript: {
+            enabled: true,
+            file: '/var/lib/mcp-arch-linux/transcript.jsonl',
+            maxEntries: 1000
+          },
+          history: {
+            enabled: true,
+            file: '/var/lib/mcp-arch-linux/history.jsonl',
+            maxEntries: 5000
+          },
+          lockWaitTimeout: 30000,
           commandTimeout: 300000,
-          auditAll: true
+          auditAll: true,
+          readOnly: false,
+          presence: {
+            guard: false,
+            idleThreshold: 300,
+            tools: []
+          },
+          maintenance: {
+            enabled: false,
+            windows: [],
+            requireNoActiveSession: false,
+            outsideWindow: 'reject',
+            tools: [],
+            jobsFile: '/var/lib/mcp-arch-linux/jobs.json',
+            checkInterval: 60000
+          },
+          retry: {
+            maxAttempts: 3,
+            initialDelay: 1000,
+            maxDelay: 15000,
+            backoffFactor: 2
+          }
+        },
+        storage: {
+          minFreePercent: 5,
+          warnPercent: 80,
+          quotas: {
+            snapshots: '1GB',
+            captures: '2GB'
+          }
+        },
+        tools: {
+          paths: {}
+        },
+        fleet: {
+          hosts: [],
+          inventoryFile: '/var/lib/mcp-arch-linux/inventory.json',
+          timeout: 300000
+        },
+        targets: {
+          default: 'host',
+          named: {}
+        },
+        simulation: {
+          enabled: false,
+          rulesFile: null,
+          rules: [],
+          default: {}
         },
         plugins: {
           system: {
             enabled: true,
-            snapshotDir: '/var/lib/mcp-arch-linux/snapshots'
+            snapshotDir: '/var/lib/mcp-arch-linux/snapshots',
+            retention: {
+              keepLast: 20,
+              keepDaily: 7,
+              keepWeekly: 4,
+              maxTotalSize: null,
+              pruneInterval: 3600000
+            },
+            newsFeed: 'https://archlinux.org/feeds/news/'
           },
           archInstall: {
             enabled: true,
-            allowDiskOperations: true
+            allowDiskOperations: true,
+            requireDeviceConfirmation: false,
+            isoMirror: 'https://geo.mirror.pkgbuild.com/iso/latest/',
+            isoReleaseUrl: 'https://archlinux.org/iso/latest/',
+            isoDir: '/var/lib/mcp-arch-linux/iso',
+            stateFile: '/var/lib/mcp-arch-linux/install-state.json',
+            localMirror: null,
+            packageCacheDirs: [],
+            offline: false
           },
           hyprland: {
             enabled: true,
+            socketPath: null,
+            automation: {
+              enabled: true,
+              rulesFile: '/var/lib/mcp-arch-linux/hyprland-rules.json',
+              rules: []
+            }
+          },
+          compositor: {
+            enabled: true,
+            type: 'auto',
             socketPath: null
           },
+          dotfiles: {
+            enabled: true,
+            repoPath: '.local/share/dotfiles',
+            mode: 'symlink',
+            ignore: ['README.md', 'LICENSE', '.gitignore'],
+            templateVars: {}
+          },
+          git: {
+            enabled: true,
+            allowedPaths: ['/etc', '/home/*/.local/share/dotfiles']
+          },
+          editor: {
+            enabled: true
+          },
+          session: {
+            enabled: true
+          },
+          shell: {
+            enabled: true
+          },
+          theme: {
+            enabled: true,
+            user: null
+          },
+          fonts: {
+            enabled: true,
+            user: null
+          },
+          mime: {
+            enabled: true
+          },
+          nightLight: {
+            enabled: true,
+            user: null,
+            backend: 'auto'
+          },
+          power: {
+            enabled: true
+          },
+          laptop: {
+            enabled: true
+          },
+          gaming: {
+            enabled: true
+          },
+          fleet: {
+            enabled: true
+          },
+          nspawn: {
+            enabled: true,
+            defaultPackages: ['base'],
+            startTimeout: 30000,
+            registerTargets: true
+          },
+          sysctl: {
+            enabled: true,
+            dropInFile: '/etc/sysctl.d/90-mcp-arch-linux.conf',
+            bootloader: 'auto'
+          },
+          devices: {
+            enabled: true,
+            partitionBackupDir: '/var/lib/mcp-arch-linux/partition-tables'
+          },
+          sensors: {
+            enabled: true,
+            interval: 5000,
+            hysteresis: 5,
+            limits: { default: 85 }
+          },
+          network: {
+            enabled: true,
+            targets: ['1.1.1.1', '9.9.9.9'],
+            dnsNames: ['archlinux.org'],
+            portalUrl: 'http://ping.archlinux.org/nm-check.txt',
+            portalExpect: 'NetworkManager is online'
+          },
           screenCapture: {
             enabled: true,
             captureDir: '/var/lib/mcp-arch-linux/captures',
             maxFileSize: '50MB',
-            allowRecording: true
+            allowRecording: true,
+            maxRecordingDuration: 3600
+          },
+          transcript: {
+            enabled: true
           }
         }
       };
 
-      // Try to load config file if it exists
-      if (await fs.pathExists(this.configPath)) {
-        const configContent = await fs.readFile(this.configPath, 'utf8');
-        const fileConfig = YAML.parse(configContent);
-        
-        // Merge with defaults
-        this.config = this.mergeDeep(this.config, fileConfig);
-      } else {
+      this.sources = {};
+      this.loadedFiles = [];
+
+      // An explicit --config replaces the search path
+      const candidates = this.configPath ? [this.configPath] : defaultConfigPaths();
+      for (const file of candidates) {
+        if (await fs.pathExists(file)) {
+          this.applyLayer(await this.readConfigFile(file), file);
+          this.loadedFiles.push(file);
+        }
+      }
+
+      // Fall back to the bundled config when running from a checkout
+      if (!this.configPath && this.loadedFiles.length === 0 && await fs.pathExists('./config/server.yaml')) {
+        this.applyLayer(await this.readConfigFile('./config/server.yaml'), './config/server.yaml');
+        this.loadedFiles.push('./config/server.yaml');
+      }
+
+      if (this.configPath && this.loadedFiles.length === 0 && !readOnly) {
         // Create default config file
         await this.save();
       }
 
+      this.applyLayer(this.readEnvOverrides(), 'environment');
+      this.applyLayer(overrides, 'command line');
+
       // Validate config
-      const { error, value } = configSchema.validate(this.config);
+      const { error, value } = configSchema.validate(this.config, { abortEarly: false });
       if (error) {
-        throw new Error(`Config validation error: ${error.message}`);
+        throw new Error(`Config validation error:\n${this.formatValidationError(error)}`);
       }
 
       this.config = value;
 
       // Ensure directories exist
-      await this.ensureDirectories();
+      if (!readOnly) {
+        await this.ensureDirectories();
+      }
 
     } catch (error) {
       throw new Error(`Failed to load config: ${error.message}`);
     }
   }
 
+  // Re-read all layers; on failure the current config stays in effect
+  async reload() {
+    const previous = this.config;
+    const previousSources = this.sources;
+    const previousFiles = this.loadedFiles;
+
+    try {
+      await this.load({ overrides: this.overrides });
+    } catch (error) {
+      this.config = previous;
+      this.sources = previousSources;
+      this.loadedFiles = previousFiles;
+      throw error;
+    }
+
+    return this.diff(previous, this.config);
+  }
+
+  diff(before, after) {
+    const keys = new Set([...this.leafPaths(before), ...this.leafPaths(after)]);
+    return [...keys].filter(keyPath =>
+      JSON.stringify(this.getNestedValue(before, keyPath)) !== JSON.stringify(this.getNestedValue(after, keyPath)));
+  }
+
+  async readConfigFile(file) {
+    const content = await fs.readFile(file, 'utf8');
+
+    try {
+      const parsed = path.extname(file) === '.toml' ? parseToml(content) : YAML.parse(content);
+      return parsed || {};
+    } catch (error) {
+      throw new Error(`Cannot parse ${file}: ${error.message}`);
+    }
+  }
+
+  applyLayer(layer, source) {
+    if (!this.isObject(layer)) {
+      return;
+    }
+
+    for (const keyPath of this.leafPaths(layer)) {
+      this.sources[keyPath] = source;
+    }
+    this.config = this.mergeDeep(this.config, layer);
+  }
+
+  leafPaths(obj, prefix = '') {
+    return Object.entries(obj).flatMap(([key, value]) => {
+      const keyPath = prefix ? `${prefix}.${key}` : key;
+      return this.isObject(value) ? this.leafPaths(value, keyPath) : [keyPath];
+    });
+  }
+
+  // MCP_ARCH_LINUX__SECURITY__LOCK_WAIT_TIMEOUT=60000 sets security.lockWaitTimeout
+  readEnvOverrides(env = process.env) {
+    const overrides = {};
+
+    for (const [name, raw] of Object.entries(env)) {
+      if (!name.startsWith(ENV_PREFIX) || raw === undefined) continue;
+
+      const keys = [];
+      let known = this.config;
+      for (const segment of name.slice(ENV_PREFIX.length).split('__')) {
+        const key = this.matchKey(known, segment);
+        keys.push(key);
+        known = this.isObject(known) ? known[key] : undefined;
+      }
+
+      let value;
+      try {
+        value = YAML.parse(raw);
+      } catch {
+        value = raw;
+      }
+      this.setNestedValue(overrides, keys.join('.'), value);
+    }
+
+    return overrides;
+  }
+
+  matchKey(known, segment) {
+    const normalized = segment.replace(/_/g, '').toLowerCase();
+    const existing = this.isObject(known) ?
+      Object.keys(known).find(key => key.toLowerCase() === normalized) : null;
+
+    return existing || segment.toLowerCase().replace(/_([a-z0-9])/g, (match, char) => char.toUpperCase());
+  }
+
+  formatValidationError(error) {
+    return error.details.map(detail => {
+      const keyPath = detail.path.join('.');
+      const source = this.findSource(keyPath);
+      return `  ${keyPath || '(root)'}: ${detail.message}${source ? ` (set in ${source})` : ''}`;
+    }).join('\n');
+  }
+
+  findSource(keyPath) {
+    if (this.sources[keyPath]) {
+      return this.sources[keyPath];
+    }
+
+    const nested = Object.keys(this.sources).find(key => key.startsWith(`${keyPath}.`));
+    return nested ? this.sources[nested] : null;
+  }
+
   async save() {
     try {
       await fs.ensureDir(path.dirname(this.configPath));
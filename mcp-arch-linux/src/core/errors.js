@@ -0,0 +1,125 @@
+// Stable failure kinds; each has its own JSON-RPC error code and is sent as data.kind
+// so clients can branch on the kind instead of parsing messages
+export const ERROR_CODES = {
+  invalid_params: -32602,
+  internal: -32603,
+  tool_failed: -30000,
+  resource_locked: -30001,
+  quota_exceeded: -30002,
+  command_failed: -30003,
+  timeout: -30004,
+  not_found: -30005,
+  not_allowed: -31001
+};
+
+export function errorKindForCode(code) {
+  return Object.keys(ERROR_CODES).find(kind => ERROR_CODES[kind] === code) || null;
+}
+
+export class MCPError extends Error {
+  constructor(message, code = -32603, data = undefined) {
+    super(message);
+    this.name = 'MCPError';
+    this.code = code;
+    this.data = data;
+    this.kind = data?.kind || errorKindForCode(code) || 'internal';
+  }
+
+  toJSON() {
+    return { name: this.name, kind: this.kind, code: this.code, message: this.message, ...(this.data !== undefined ? { data: this.data } : {}) };
+  }
+}
+
+export class ResourceLockedError extends MCPError {
+  constructor(resource, holder) {
+    super(
+      `Resource locked: ${resource} is held by ${holder?.tool || 'another operation'}`,
+      ERROR_CODES.resource_locked,
+      { kind: 'resource_locked', resource, holder, retryable: true }
+    );
+    this.name = 'ResourceLockedError';
+    this.resource = resource;
+    this.holder = holder;
+  }
+}
+
+export class CommandNotAllowedError extends MCPError {
+  constructor(message, command) {
+    super(message, ERROR_CODES.not_allowed, { kind: 'not_allowed', command });
+    this.name = 'CommandNotAllowedError';
+  }
+}
+
+export class CommandTimeoutError extends MCPError {
+  constructor(command, timeout) {
+    super(`Command timed out after ${timeout}ms: ${command}`, ERROR_CODES.timeout, { kind: 'timeout', command, timeout });
+    this.name = 'CommandTimeoutError';
+  }
+}
+
+const PROGRAMMING_ERRORS = [TypeError, ReferenceError, RangeError, SyntaxError];
+
+// A plugin's own Error is a tool failure; a TypeError and the like is a bug in the server
+export function errorKind(error) {
+  if (error instanceof MCPError) {
+    return error.kind;
+  }
+  if (error?.data?.kind && ERROR_CODES[error.data.kind]) {
+    return error.data.kind;
+  }
+  if (Number.isInteger(error?.code) && errorKindForCode(error.code)) {
+    return errorKindForCode(error.code);
+  }
+  return PROGRAMMING_ERRORS.some(type => error instanceof type) ? 'internal' : 'tool_failed';
+}
+
+// The last failed command is only blamed when the error message refers to it; plugins
+// often run probes that are expected to fail before failing for another reason
+function blames(error, failure) {
+  if (!failure) {
+    return false;
+  }
+  const firstLine = failure.stderr.split('\n').find(line => line.trim());
+  return error.message.includes(failure.command) || Boolean(firstLine && error.message.includes(firstLine.trim()));
+}
+
+// Turns any error from a tool call into an MCPError with structured data: kind, the
+// failing command and its stderr tail, whether a retry may succeed, and the snapshot
+// taken before the call
+export function toolError(error, { commandFailure = null, snapshotId = null, idempotent = false } = {}) {
+  const failure = blames(error, commandFailure) ? commandFailure : null;
+  let kind = errorKind(error);
+  if (kind === 'tool_failed' && failure) {
+    kind = 'command_failed';
+  }
+
+  const data = error?.data && typeof error.data === 'object' && !Array.isArray(error.data) ? error.data : {};
+  const retryable = data.retryable ?? (
+    kind === 'resource_locked' ||
+    (kind === 'timeout' && idempotent) ||
+    Boolean(failure?.retryable)
+  );
+  const code = Number.isInteger(error?.code) && !errorKindForCode(error.code) ? error.code : ERROR_CODES[kind];
+
+  return new MCPError(`Tool execution failed: ${error.message}`, code, {
+    ...data,
+    kind,
+    ...(failure ? {
+      command: failure.command,
+      args: failure.args,
+      exitCode: failure.exitCode,
+      stderr: failure.stderr
+    } : {}),
+    retryable,
+    ...(snapshotId ? { snapshotId } : {})
+  });
+}
+
+// What audit, history and transcript entries keep of a failure; plain data, unlike the error
+export function errorOutcome(error) {
+  return {
+    kind: errorKind(error),
+    message: error?.message ?? String(error),
+    ...(Number.isInteger(error?.code) ? { code: error.code } : {})
+  };
+}
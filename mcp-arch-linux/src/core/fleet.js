@@ -0,0 +1,164 @@
+import fs from 'fs-extra';
+import path from 'path';
+import { MCPClient } from './mcp-client.js';
+import { resolveSecret } from '../security/secrets.js';
+
+export const HOST_NAME_PATTERN = /^[a-zA-Z0-9][a-zA-Z0-9_.-]*$/;
+
+// Other machines running this server, reachable over their JSON-RPC endpoint. Hosts come
+// from fleet.hosts in the config (read-only) and from the inventory file that the
+// fleet tools edit.
+export class Fleet {
+  constructor() {
+    this.hosts = new Map();
+    this.clients = new Map();
+    this.inventoryFile = null;
+    this.timeout = 300000;
+    this.logger = null;
+  }
+
+  async configure(options = {}, logger = null) {
+    this.logger = logger;
+    this.inventoryFile = options.inventoryFile || null;
+    this.timeout = options.timeout ?? 300000;
+    this.hosts.clear();
+    this.clients.clear();
+
+    for (const host of options.hosts || []) {
+      this.hosts.set(host.name, { ...host, tags: host.tags || [], source: 'config' });
+    }
+
+    if (this.inventoryFile && await fs.pathExists(this.inventoryFile)) {
+      for (const host of (await fs.readJson(this.inventoryFile)).hosts || []) {
+        if (this.hosts.has(host.name)) {
+          logger?.warn(`Inventory host ${host.name} is shadowed by fleet.hosts in the config`);
+          continue;
+        }
+        this.hosts.set(host.name, { ...host, tags: host.tags || [], source: 'inventory' });
+      }
+    }
+  }
+
+  list({ tag = null } = {}) {
+    return Array.from(this.hosts.values())
+      .filter(host => !tag || host.tags.includes(tag))
+      .map(({ name, url, tags, source, token }) => ({ name, url, tags, source, credentials: token ? token.split(':')[0] : null }));
+  }
+
+  // "web1", "tag:web" or "all"
+  select(spec) {
+    let hosts;
+    if (spec === 'all') {
+      hosts = Array.from(this.hosts.values());
+    } else if (spec.startsWith('tag:')) {
+      hosts = Array.from(this.hosts.values()).filter(host => host.tags.includes(spec.slice(4)));
+    } else {
+      hosts = this.hosts.has(spec) ? [this.hosts.get(spec)] : [];
+    }
+
+    if (hosts.length === 0) {
+      throw new Error(`No host matches ${spec}; known hosts: ${Array.from(this.hosts.keys()).join(', ') || 'none'}`);
+    }
+    return hosts;
+  }
+
+  async add({ name, url, token = null, tags = [] }) {
+    if (!HOST_NAME_PATTERN.test(name)) {
+      throw new Error(`Invalid host name: ${name}`);
+    }
+    if (this.hosts.get(name)?.source === 'config') {
+      throw new Error(`${name} is defined in the config file; edit it there`);
+    }
+    if (token) {
+      await resolveSecret(token);
+    }
+
+    this.hosts.set(name, { name, url, ...(token ? { token } : {}), tags, source: 'inventory' });
+    this.clients.delete(name);
+    await this.save();
+  }
+
+  async remove(name) {
+    const host = this.hosts.get(name);
+    if (!host) {
+      throw new Error(`Unknown host: ${name}`);
+    }
+    if (host.source === 'config') {
+      throw new Error(`${name} is defined in the config file; edit it there`);
+    }
+
+    this.hosts.delete(name);
+    this.clients.delete(name);
+    await this.save();
+  }
+
+  async save() {
+    if (!this.inventoryFile) {
+      throw new Error('fleet.inventoryFile is not set; hosts can only be defined in the config');
+    }
+    const hosts = Array.from(this.hosts.values())
+      .filter(host => host.source === 'inventory')
+      .map(({ source, ...host }) => host);
+    await fs.ensureDir(path.dirname(this.inventoryFile));
+    await fs.writeFile(this.inventoryFile, JSON.stringify({ hosts }, null, 2), { mode: 0o600 });
+  }
+
+  async client(name) {
+    if (!this.clients.has(name)) {
+      const host = this.select(name)[0];
+      const client = new MCPClient(host.url, { timeout: this.timeout, token: await resolveSecret(host.token) });
+      await client.connect();
+      this.clients.set(name, client);
+    }
+    return this.clients.get(name);
+  }
+
+  async ping(name) {
+    const startTime = Date.now();
+    this.clients.delete(name);
+    try {
+      const client = await this.client(name);
+      return { name, reachable: true, server: client.serverInfo, latency: Date.now() - startTime };
+    } catch (error) {
+      return { name, reachable: false, error: error.message };
+    }
+  }
+
+  // One host returns its result unchanged; several are called concurrently and their
+  // results merged, each item labelled with the host it came from
+  async callTool(hosts, tool, args) {
+    const call = async (host) => {
+      try {
+        return await (await this.client(host.name)).callTool(tool, args);
+      } catch (error) {
+        // Drop the connection so the next call reconnects (the remote may have restarted)
+        this.clients.delete(host.name);
+        if (hosts.length === 1) {
+          throw new Error(`${host.name}: ${error.message}`);
+        }
+        return { content: [{ type: 'text', text: error.message }], isError: true };
+      }
+    };
+
+    if (hosts.length === 1) {
+      return call(hosts[0]);
+    }
+
+    const results = await Promise.all(hosts.map(call));
+    return {
+      content: results.flatMap((result, index) => (result.content || []).map(item =>
+        item.type === 'text' ? { ...item, text: `[${hosts[index].name}] ${item.text}` } : item)),
+      isError: results.some(result => result.isError),
+      hosts: results.map((result, index) => ({ name: hosts[index].name, isError: Boolean(result.isError) }))
+    };
+  }
+}
+
+let sharedFleet = null;
+
+export function getFleet() {
+  if (!sharedFleet) {
+    sharedFleet = new Fleet();
+  }
+  return sharedFleet;
+}
@@ -0,0 +1,56 @@
+import fs from 'fs-extra';
+
+const SEVERITY = { pass: 0, warn: 1, fail: 2 };
+
+export class HealthCheck {
+  constructor({ config, security, pluginManager }) {
+    this.config = config;
+    this.security = security;
+    this.pluginManager = pluginManager;
+    this.startedAt = new Date();
+  }
+
+  // Liveness only says the event loop is turning; it must not depend on the host
+  live() {
+    return {
+      status: 'pass',
+      pid: process.pid,
+      startedAt: this.startedAt.toISOString(),
+      uptime: Math.round(process.uptime())
+    };
+  }
+
+  async ready() {
+    const checks = [
+      await this.checkWritable('audit-log', this.config.get('logging.logDir')),
+      await this.checkWritable('snapshot-dir', this.security.rollback.snapshotDir)
+    ];
+
+    for (const plugin of this.pluginManager.plugins.values()) {
+      if (plugin.getHealthChecks) {
+        for (const check of await plugin.getHealthChecks()) {
+          checks.push({ plugin: plugin.name, ...check });
+        }
+      }
+    }
+
+    const status = checks.reduce((worst, check) =>
+      SEVERITY[check.status] > SEVERITY[worst] ? check.status : worst, 'pass');
+
+    return {
+      status,
+      ready: status !== 'fail',
+      checkedAt: new Date().toISOString(),
+      checks
+    };
+  }
+
+  async checkWritable(name, dir) {
+    try {
+      await fs.access(dir, fs.constants.W_OK);
+      return { name, status: 'pass', path: dir };
+    } catch (error) {
+      return { name, status: 'fail', path: dir, detail: error.code === 'ENOENT' ? 'missing' : 'not writable' };
+    }
+  }
+}
@@ -0,0 +1,188 @@
+import http from 'http';
+import crypto from 'crypto';
+import fs from 'fs-extra';
+import path from 'path';
+import { MCPProtocol } from './mcp-protocol.js';
+
+// Shared secret for the helper socket; the file's group decides which users may delegate
+export async function ensureHelperToken(tokenFile) {
+  if (await fs.pathExists(tokenFile)) {
+    return (await fs.readFile(tokenFile, 'utf8')).trim();
+  }
+
+  const token = crypto.randomBytes(32).toString('hex');
+  await fs.ensureDir(path.dirname(tokenFile));
+  await fs.writeFile(tokenFile, `${token}\n`, { mode: 0o640 });
+  return token;
+}
+
+function tokensMatch(expected, header) {
+  const provided = Buffer.from((header || '').replace(/^Bearer\s+/i, ''));
+  const wanted = Buffer.from(expected);
+  return provided.length === wanted.length && crypto.timingSafeEqual(provided, wanted);
+}
+
+// Privileged side of split mode: JSON-RPC over a local unix socket, token authenticated
+export class HelperServer {
+  constructor({ pluginManager, logger, security, socketPath, tokenFile, socketGroup = null, maxRequestSize = null }) {
+    this.protocol = new MCPProtocol(pluginManager, logger, security, { maxRequestSize });
+    this.logger = logger;
+    this.socketPath = socketPath;
+    this.tokenFile = tokenFile;
+    this.socketGroup = socketGroup;
+    this.maxRequestSize = maxRequestSize ?? 1024 * 1024;
+    this.server = null;
+  }
+
+  async start() {
+    const token = await ensureHelperToken(this.tokenFile);
+
+    await fs.ensureDir(path.dirname(this.socketPath));
+    await fs.remove(this.socketPath);
+
+    this.server = http.createServer((request, response) => {
+      const reply = (status, body) => {
+        response.writeHead(status, { 'content-type': 'application/json' });
+        response.end(JSON.stringify(body));
+      };
+
+      if (request.method !== 'POST' || !tokensMatch(token, request.headers.authorization)) {
+        this.logger.audit('helper_auth_failed', { method: request.method });
+        return reply(401, { jsonrpc: '2.0', error: { code: -32001, message: 'Unauthorized' }, id: null });
+      }
+
+      // Bodies are read whole before parsing, so a client may only send so much; past that
+      // it is answered and the connection dropped
+      const chunks = [];
+      let size = 0;
+      request.on('data', (chunk) => {
+        size += chunk.length;
+        if (size > this.maxRequestSize) {
+          request.removeAllListeners('data');
+          request.removeAllListeners('end');
+          this.logger.audit('helper_request_too_large', { limit: this.maxRequestSize });
+          response.once('finish', () => request.socket.destroy());
+          response.setHeader('connection', 'close');
+          return reply(413, { jsonrpc: '2.0', error: { code: -32600, message: `Request exceeds ${this.maxRequestSize} bytes` }, id: null });
+        }
+        chunks.push(chunk);
+      });
+      request.on('end', async () => {
+        try {
+          const message = JSON.parse(Buffer.concat(chunks).toString('utf8'));
+          reply(200, await this.protocol.handleRequest(message, { sessionId: request.headers['mcp-session-id'] }));
+        } catch (error) {
+          reply(400, { jsonrpc: '2.0', error: { code: -32700, message: 'Parse error' }, id: null });
+        }
+      });
+    });
+
+    await new Promise((resolve, reject) => {
+      this.server.once('error', reject);
+      this.server.listen(this.socketPath, resolve);
+    });
+
+    await fs.chmod(this.socketPath, 0o660);
+    if (this.socketGroup !== null) {
+      try {
+        const gid = await this.resolveGroup(this.socketGroup);
+        await fs.chown(this.socketPath, process.getuid(), gid);
+        await fs.chown(this.tokenFile, process.getuid(), gid);
+      } catch (error) {
+        this.logger.warn(`Could not set helper socket group: ${error.message}`);
+      }
+    }
+
+    this.logger.info(`Privileged helper listening on ${this.socketPath}`);
+  }
+
+  async resolveGroup(group) {
+    if (typeof group === 'number') {
+      return group;
+    }
+
+    const entry = (await fs.readFile('/etc/group', 'utf8'))
+      .split('\n')
+      .map(line => line.split(':'))
+      .find(([name]) => name === group);
+
+    if (!entry) {
+      throw new Error(`Unknown group: ${group}`);
+    }
+    return parseInt(entry[2], 10);
+  }
+
+  async stop() {
+    if (this.server) {
+      await new Promise(resolve => this.server.close(resolve));
+      await fs.remove(this.socketPath);
+      this.server = null;
+    }
+  }
+}
+
+// Unprivileged side: talks to the helper socket (fetch cannot use unix sockets)
+export class HelperClient {
+  constructor({ socketPath, tokenFile, timeout = 300000 }) {
+    this.socketPath = socketPath;
+    this.tokenFile = tokenFile;
+    this.timeout = timeout;
+    this.token = null;
+    this.nextId = 1;
+  }
+
+  async request(method, params = {}) {
+    if (!this.token) {
+      this.token = (await fs.readFile(this.tokenFile, 'utf8')).trim();
+    }
+
+    const payload = JSON.stringify({ jsonrpc: '2.0', method, params, id: this.nextId++ });
+
+    const body = await new Promise((resolve, reject) => {
+      const request = http.request({
+        socketPath: this.socketPath,
+        path: '/',
+        method: 'POST',
+        timeout: this.timeout,
+        headers: {
+          'content-type': 'application/json',
+          'content-length': Buffer.byteLength(payload),
+          authorization: `Bearer ${this.token}`
+        }
+      }, (response) => {
+        let data = '';
+        response.setEncoding('utf8');
+        response.on('data', chunk => { data += chunk; });
+        response.on('end', () => {
+          try {
+            resolve(JSON.parse(data));
+          } catch {
+            reject(new Error(`Invalid response from helper (HTTP ${response.statusCode})`));
+          }
+        });
+      });
+
+      request.on('timeout', () => request.destroy(new Error('Helper request timed out')));
+      request.on('error', reject);
+      request.end(payload);
+    });
+
+    if (body.error) {
+      const error = new Error(body.error.message);
+      error.code = body.error.code;
+      error.data = body.error.data;
+      throw error;
+    }
+
+    return body.result;
+  }
+
+  async connect() {
+    await this.request('initialize', {
+      protocolVersion: '2024-11-05',
+      capabilities: {},
+      clientInfo: { name: 'mcp-arch-linux-user', version: '1.0.0' }
+    });
+    await this.request('initialized');
+  }
+}
@@ -2,6 +2,18 @@ import winston from 'winston';
 import DailyRotateFile from 'winston-daily-rotate-file';
 import path from 'path';
 import fs from 'fs-extra';
+import { getContext } from './request-context.js';
+
+// Stamp each entry with the active request context (requestId, sessionId, tool, ...)
+const withRequestContext = winston.format((info) => {
+  const context = getContext();
+  for (const [key, value] of Object.entries(context)) {
+    if (info[key] === undefined && typeof value !== 'function') {
+      info[key] = value;
+    }
+  }
+  return info;
+});
 
 export class Logger {
   constructor(config = {}) {
@@ -10,6 +22,7 @@ export class Logger {
       logDir: '/var/log/mcp-arch-linux',
       maxFiles: '14d',
       maxSize: '20m',
+      format: 'text',
       ...config
     };
 
@@ -21,16 +34,22 @@ export class Logger {
     fs.ensureDirSync(this.config.logDir);
 
     const transports = [
-      // Console transport
+      // Console transport; all of it on stderr when stdout carries the stdio transport
       new winston.transports.Console({
-        format: winston.format.combine(
-          winston.format.colorize(),
-          winston.format.timestamp(),
-          winston.format.printf(({ timestamp, level, message, ...meta }) => {
-            const metaStr = Object.keys(meta).length ? ` ${JSON.stringify(meta)}` : '';
-            return `${timestamp} [${level}]: ${message}${metaStr}`;
-          })
-        )
+        stderrLevels: this.config.stderr ? Object.keys(winston.config.npm.levels) : [],
+        format: this.config.format === 'json' ?
+          winston.format.combine(
+            winston.format.timestamp(),
+            winston.format.json()
+          ) :
+          winston.format.combine(
+            winston.format.colorize(),
+            winston.format.timestamp(),
+            winston.format.printf(({ timestamp, level, message, ...meta }) => {
+              const metaStr = Object.keys(meta).length ? ` ${JSON.stringify(meta)}` : '';
+              return `${timestamp} [${level}]: ${message}${metaStr}`;
+            })
+          )
       }),
 
       // Rotating file transport for general logs
@@ -63,6 +82,7 @@ export class Logger {
 
     return winston.createLogger({
       level: this.config.level,
+      format: withRequestContext(),
       transports,
       exceptionHandlers: [
         new winston.transports.File({
@@ -77,6 +97,11 @@ export class Logger {
     });
   }
 
+  setLevel(level) {
+    this.config.level = level;
+    this.logger.level = level;
+  }
+
   debug(message, meta = {}) {
     this.logger.debug(message, meta);
   }
@@ -103,6 +128,32 @@ export class Logger {
     });
   }
 
+  // Entries logged while an operation ran (stamped with its operationId by withRequestContext)
+  async findOperationLogs(operationId, { limit = 500 } = {}) {
+    const files = (await fs.readdir(this.config.logDir).catch(() => []))
+      .filter(file => /^app-.*\.log$/.test(file))
+      .sort();
+
+    const entries = [];
+    for (const file of files) {
+      const text = await fs.readFile(path.join(this.config.logDir, file), 'utf8').catch(() => '');
+      for (const line of text.split('\n')) {
+        if (!line.includes(operationId)) {
+          continue;
+        }
+        try {
+          const entry = JSON.parse(line);
+          if (entry.operationId === operationId) {
+            entries.push(entry);
+          }
+        } catch {
+          // Partial line from a concurrent write
+        }
+      }
+    }
+    return entries.slice(-limit);
+  }
+
   // Performance logging
   time(label) {
     console.time(label);
@@ -0,0 +1,93 @@
+import http from 'http';
+import { parseUnixAddress } from './unix-socket.js';
+
+// fetch cannot use unix sockets; this posts to /mcp on one and resolves like fetch
+function postUnix(socketPath, { headers, body, signal }) {
+  return new Promise((resolve, reject) => {
+    const request = http.request({ socketPath, path: '/mcp', method: 'POST', headers, signal }, (response) => {
+      let data = '';
+      response.setEncoding('utf8');
+      response.on('data', chunk => { data += chunk; });
+      response.on('end', () => resolve({ status: response.statusCode, json: async () => JSON.parse(data) }));
+    });
+    request.on('error', error => reject(Object.assign(new Error(error.message), { cause: error })));
+    request.end(body);
+  });
+}
+
+// Minimal MCP client over the server's JSON-RPC HTTP endpoint, used by the call/repl commands;
+// a unix:///path url talks to a server listening on a local socket
+export class MCPClient {
+  constructor(url, options = {}) {
+    this.url = url;
+    this.timeout = options.timeout ?? 300000;
+    this.token = options.token || null;
+    this.nextId = 1;
+    this.serverInfo = null;
+  }
+
+  async request(method, params = {}) {
+    const id = this.nextId++;
+    const controller = new AbortController();
+    const timer = setTimeout(() => controller.abort(), this.timeout);
+
+    let response;
+    try {
+      const socketPath = parseUnixAddress(this.url);
+      const post = socketPath ? options => postUnix(socketPath, options) : options => fetch(this.url, { method: 'POST', ...options });
+      response = await post({
+        headers: {
+          'content-type': 'application/json',
+          ...(this.token ? { authorization: `Bearer ${this.token}` } : {})
+        },
+        body: JSON.stringify({ jsonrpc: '2.0', method, params, id }),
+        signal: controller.signal
+      });
+    } catch (error) {
+      throw new Error(`Cannot reach MCP server at ${this.url}: ${error.cause?.message || error.message}`);
+    } finally {
+      clearTimeout(timer);
+    }
+
+    const body = await response.json();
+    if (body.error) {
+      const error = new Error(body.error.message);
+      error.code = body.error.code;
+      error.data = body.error.data;
+      throw error;
+    }
+
+    return body.result;
+  }
+
+  async connect() {
+    const result = await this.request('initialize', {
+      protocolVersion: '2024-11-05',
+      capabilities: {},
+      clientInfo: { name: 'mcp-arch-server-cli', version: '1.0.0' }
+    });
+    this.serverInfo = result.serverInfo;
+    await this.request('initialized');
+    return result;
+  }
+
+  async listTools() {
+    return (await this.request('tools/list')).tools;
+  }
+
+  async callTool(name, args = {}) {
+    return this.request('tools/call', { name, arguments: args });
+  }
+
+  async listResources() {
+    return (await this.request('resources/list')).resources;
+  }
+
+  async listResourceTemplates() {
+    return (await this.request('resources/templates/list')).resourceTemplates;
+  }
+
+  async readResource(uri) {
+    return this.request('resources/read', { uri });
+  }
+}
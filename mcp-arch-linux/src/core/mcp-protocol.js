@@ -1,14 +1,158 @@
-export class MCPProtocol {
-  constructor(pluginManager, logger, security) {
+import { EventEmitter } from 'events';
+import { randomUUID } from 'crypto';
+import { runWithContext, getContext, newRequestId } from './request-context.js';
+import { rootPaths } from '../security/roots.js';
+import { digestResult } from '../security/history.js';
+import { redact } from '../security/transcript.js';
+import { MCPError, ERROR_CODES } from './errors.js';
+
+const CLIENT_REQUEST_TIMEOUT = 30000;
+
+// Added to every tool while fleet hosts are configured
+const TARGET_HOST_PROPERTY = {
+  type: 'string',
+  description: 'Run on a fleet host instead of this machine: a host name, "tag:<tag>" or "all"'
+};
+
+export class MCPProtocol extends EventEmitter {
+  constructor(pluginManager, logger, security, options = {}) {
+    super();
     this.pluginManager = pluginManager;
     this.logger = logger;
     this.security = security;
     this.initialized = false;
     this.clientInfo = null;
+    this.reloader = null;
+    this.maxRequestSize = options.maxRequestSize || null;
+    this.spill = options.spill || null;
+    this.fleet = options.fleet || null;
+    this.subscriptions = new Map();
+    // Per-session client state and outbound channels for server-initiated requests
+    this.clients = new Map();
+    this.connections = new Map();
+    this.pendingRequests = new Map();
+    // Calls still running, by idempotency key
+    this.inflight = new Map();
+  }
+
+  // Transports that can carry server-to-client requests register a sender per session
+  attach(sessionId, send) {
+    this.connections.set(sessionId, send);
+    return () => {
+      this.connections.delete(sessionId);
+      this.clients.delete(sessionId);
+      for (const pending of this.pendingRequests.values()) {
+        if (pending.sessionId === sessionId) {
+          pending.reject(new Error('Client disconnected'));
+        }
+      }
+      for (const uri of this.subscriptions.keys()) {
+        this.unsubscribeSession(uri, sessionId).catch(error => {
+          this.logger.warn(`Unsubscribing ${uri} failed:`, error.message);
+        });
+      }
+    };
   }
 
-  async handleRequest(request) {
+  // What a tool call may ask of its own client: capabilities plus a request channel
+  clientContext(sessionId = getContext().sessionId) {
+    const client = this.clients.get(sessionId);
+    return {
+      capabilities: client?.capabilities || {},
+      request: (method, params, options = {}) => this.sendRequest(method, params, { ...options, sessionId })
+    };
+  }
+
+  clientState(sessionId = getContext().sessionId) {
+    if (!this.clients.has(sessionId)) {
+      this.clients.set(sessionId, { capabilities: {}, roots: null });
+    }
+    return this.clients.get(sessionId);
+  }
+
+  // Sends a JSON-RPC request to the client and resolves with its result
+  sendRequest(method, params = {}, { sessionId = getContext().sessionId, timeout = CLIENT_REQUEST_TIMEOUT } = {}) {
+    const send = this.connections.get(sessionId);
+    if (!send) {
+      return Promise.reject(new Error(`Session ${sessionId || '(none)'} cannot receive ${method} requests`));
+    }
+
+    // Unguessable, so no other client can answer in this session's name
+    const id = `srv-${randomUUID()}`;
+    return new Promise((resolve, reject) => {
+      const timer = setTimeout(() => {
+        this.pendingRequests.delete(id);
+        reject(new Error(`Client did not answer ${method} within ${timeout} ms`));
+      }, timeout);
+
+      const settle = (callback) => (value) => {
+        clearTimeout(timer);
+        this.pendingRequests.delete(id);
+        callback(value);
+      };
+      this.pendingRequests.set(id, { sessionId, method, resolve: settle(resolve), reject: settle(reject) });
+      send({ jsonrpc: '2.0', id, method, params });
+    });
+  }
+
+  handleClientResponse(message) {
+    const pending = this.pendingRequests.get(message.id);
+    if (!pending) {
+      this.logger.warn('Response for unknown server request', { id: message.id });
+      return null;
+    }
+    // Only the session the request went to may answer it (roots, sampling, elicitation)
+    if (pending.sessionId !== getContext().sessionId) {
+      this.logger.audit('client_response_rejected', { id: message.id, method: pending.method, sessionId: getContext().sessionId });
+      return null;
+    }
+
+    if (message.error) {
+      pending.reject(new Error(`${pending.method} failed: ${message.error.message}`));
+    } else {
+      pending.resolve(message.result);
+    }
+    return null;
+  }
+
+  // Roots narrow the paths file tools may touch for this session
+  async refreshRoots(sessionId = getContext().sessionId) {
+    const client = this.clientState(sessionId);
+    if (!client.capabilities.roots || !this.connections.has(sessionId)) {
+      return null;
+    }
+
+    try {
+      const result = await this.sendRequest('roots/list', {}, { sessionId });
+      client.roots = rootPaths(result?.roots);
+      this.logger.info('Client roots updated', { roots: client.roots });
+    } catch (error) {
+      this.logger.warn('Could not list client roots:', error.message);
+    }
+    return client.roots;
+  }
+
+  // Every request runs in its own context so logs down to CommandExecutor share its requestId
+  async handleRequest(request, session = {}) {
+    return runWithContext({
+      requestId: newRequestId(),
+      sessionId: session.sessionId,
+      method: request?.method
+    }, () => this.dispatchRequest(request));
+  }
+
+  async dispatchRequest(request) {
     try {
+      // Answers to requests this server sent (roots/list, ...)
+      if (request?.jsonrpc === '2.0' && !request.method && request.id !== undefined && ('result' in request || 'error' in request)) {
+        return this.handleClientResponse(request);
+      }
+
+      // Transports bound raw bodies; this also covers the helper socket
+      if (this.maxRequestSize && Buffer.byteLength(JSON.stringify(request)) > this.maxRequestSize) {
+        return this.createError(-32600, `Request exceeds ${this.maxRequestSize} bytes`, request?.id ?? null);
+      }
+
       // Validate JSON-RPC format
       if (!request || request.jsonrpc !== '2.0' || !request.method) {
         return this.createError(-32600, 'Invalid Request', request?.id || null);
@@ -22,8 +166,13 @@ export class MCPProtocol {
           return this.handleInitialize(params, id);
         
         case 'initialized':
+        case 'notifications/initialized':
           return this.handleInitialized(params, id);
         
+        case 'notifications/roots/list_changed':
+          this.refreshRoots();
+          return null;
+        
         case 'tools/list':
           return this.handleToolsList(params, id);
         
@@ -36,9 +185,33 @@ export class MCPProtocol {
         case 'resources/read':
           return this.handleResourceRead(params, id);
         
+        case 'resources/templates/list':
+          return this.handleResourceTemplatesList(params, id);
+        
+        case 'resources/subscribe':
+          return this.handleResourceSubscribe(params, id);
+        
+        case 'resources/unsubscribe':
+          return this.handleResourceUnsubscribe(params, id);
+        
         case 'completion/complete':
           return this.handleCompletion(params, id);
         
+        case 'config/reload':
+          return this.handleConfigReload(params, id);
+        
+        case 'history/list':
+          return this.handleHistoryList(params, id);
+        
+        case 'history/replay':
+          return this.handleHistoryReplay(params, id);
+        
+        case 'jobs/list':
+          return this.handleJobsList(params, id);
+        
+        case 'jobs/cancel':
+          return this.handleJobCancel(params, id);
+        
         default:
           return this.createError(-32601, 'Method not found', id);
       }
@@ -51,6 +224,7 @@ export class MCPProtocol {
   async handleInitialize(params, id) {
     try {
       this.clientInfo = params.clientInfo;
+      this.clientState().capabilities = params.capabilities || {};
       this.logger.info(`Client initialized: ${this.clientInfo?.name} v${this.clientInfo?.version}`);
 
       const result = {
@@ -61,7 +235,8 @@ export class MCPProtocol {
             subscribe: true,
             listChanged: true 
           },
-          prompts: { listChanged: true }
+          prompts: { listChanged: true },
+          logging: {}
         },
         serverInfo: {
           name: 'mcp-arch-linux',
@@ -79,6 +254,9 @@ export class MCPProtocol {
   async handleInitialized(params, id) {
     this.initialized = true;
     this.logger.info('Client initialization complete');
+
+    // Not awaited: the client answers roots/list only after it has this response
+    this.refreshRoots();
     return this.createResponse({}, id);
   }
 
@@ -88,7 +266,16 @@ export class MCPProtocol {
         return this.createError(-32002, 'Server not initialized', id);
       }
 
-      const tools = await this.pluginManager.getAllTools();
+      let tools = await this.pluginManager.getAllTools();
+      if (this.fleet?.hosts.size > 0) {
+        tools = tools.map(tool => ({
+          ...tool,
+          inputSchema: {
+            ...tool.inputSchema,
+            properties: { ...tool.inputSchema?.properties, targetHost: TARGET_HOST_PROPERTY }
+          }
+        }));
+      }
       return this.createResponse({ tools }, id);
     } catch (error) {
       this.logger.error('Tools list error:', error);
@@ -107,19 +294,219 @@ export class MCPProtocol {
         return this.createError(-32602, 'Missing tool name', id);
       }
 
-      // Execute tool with security audit
-      const result = await this.security.executeWithAudit(
-        'tool_call',
-        { tool: name, arguments: toolArgs },
-        async () => {
-          return await this.pluginManager.executeTool(name, toolArgs || {});
-        }
-      );
+      // Progress goes only to the connection that made the call
+      const progressToken = params._meta?.progressToken;
+      const sessionId = getContext().sessionId;
+      const onProgress = progressToken === undefined ? undefined :
+        (update) => this.notify('notifications/progress', { progressToken, ...update }, { sessionId });
 
+      // Run even though someone is using the seat (security.presence.guard)
+      const ignorePresence = params._meta?.ignorePresence === true;
+      const idempotencyKey = params._meta?.idempotencyKey;
+      const result = idempotencyKey === undefined
+        ? await this.callTool(name, toolArgs, { onProgress, ignorePresence })
+        : await this.callToolOnce(idempotencyKey, name, toolArgs, { onProgress, ignorePresence });
       return this.createResponse(result, id);
     } catch (error) {
       this.logger.error('Tool call error:', error);
-      return this.createError(-32603, error.message, id);
+      return this.createError(error.code ?? -32603, error.message, id, error.data);
+    }
+  }
+
+  // A retried call with the same key gets the first call's result instead of running again;
+  // failed calls are not remembered, so retrying those runs the tool again
+  async callToolOnce(idempotencyKey, name, toolArgs, options = {}) {
+    const history = this.security.history;
+    if (typeof idempotencyKey !== 'string' || idempotencyKey.length === 0 || idempotencyKey.length > 256) {
+      throw new MCPError('idempotencyKey must be a string of 1 to 256 characters', ERROR_CODES.invalid_params);
+    }
+    if (!history.enabled) {
+      throw new MCPError('idempotencyKey needs the tool history (security.history.enabled)', ERROR_CODES.invalid_params);
+    }
+
+    const running = this.inflight.get(idempotencyKey);
+    const previous = running || history.findByIdempotencyKey(idempotencyKey);
+    if (previous && !history.matchesCall(previous, name, toolArgs)) {
+      throw new MCPError(`idempotencyKey ${idempotencyKey} was already used for a different call to ${previous.tool}`,
+        ERROR_CODES.invalid_params, { kind: 'invalid_params', operationId: previous.id ?? null });
+    }
+    if (running) {
+      return running.promise;
+    }
+    if (previous && !previous.error) {
+      this.logger.info(`Returning the recorded result of ${name} for idempotency key ${idempotencyKey}`, { operationId: previous.id });
+      const result = { ...previous.result, _meta: { ...previous.result?._meta, idempotentReplay: true, operationId: previous.id } };
+      return this.spill?.exceeds(result) ? this.spill.spill(result) : result;
+    }
+
+    const promise = this.callTool(name, toolArgs, { ...options, idempotencyKey });
+    this.inflight.set(idempotencyKey, { tool: name, arguments: redact(toolArgs || {}), promise });
+    try {
+      return await promise;
+    } finally {
+      this.inflight.delete(idempotencyKey);
+    }
+  }
+
+  // Execute a tool with security audit; shared by tools/call, history/replay and maintenance jobs
+  async callTool(name, toolArgs, { onProgress, replayOf, idempotencyKey, jobId, ignorePresence = false } = {}) {
+    // targetHost forwards the call; the audit entry names every host it reached
+    const { targetHost, ...localArgs } = toolArgs || {};
+    const hosts = targetHost && this.fleet ? this.fleet.select(targetHost) : null;
+    if (targetHost && !this.fleet) {
+      throw new Error('targetHost requires fleet hosts');
+    }
+
+    // Outside the maintenance window destructive calls wait as jobs; in reject mode the
+    // plugin manager refuses them instead
+    const maintenance = this.security.maintenance;
+    if (!hosts && !jobId && maintenance.enabled && maintenance.outsideWindow === 'queue' &&
+        !this.security.readOnly && this.pluginManager.isDestructive(name, localArgs)) {
+      const window = await maintenance.status();
+      if (!window.open) {
+        const job = maintenance.enqueue(name, localArgs, { sessionId: getContext().sessionId, reason: window.reason });
+        return {
+          content: [{ type: 'text', text: `${name} was queued as job ${job.id} and runs once the maintenance window opens (${window.reason})` }],
+          _meta: { queued: true, jobId: job.id, ...(window.opensAt ? { opensAt: window.opensAt } : {}) }
+        };
+      }
+    }
+
+    const result = await this.security.executeWithAudit(
+      'tool_call',
+      {
+        tool: name,
+        arguments: toolArgs,
+        ...(hosts ? { hosts: hosts.map(host => host.name) } : {}),
+        ...(replayOf ? { replayOf } : {}),
+        ...(idempotencyKey ? { idempotencyKey } : {}),
+        ...(jobId ? { jobId } : {}),
+        ...(ignorePresence ? { ignorePresence } : {})
+      },
+      async (operationId) => {
+        if (hosts) {
+          return this.fleet.callTool(hosts, name, localArgs);
+        }
+        const roots = this.clients.get(getContext().sessionId)?.roots;
+        const client = this.clientContext();
+        return await runWithContext({ tool: name, operationId, onProgress, roots, client }, () =>
+          this.pluginManager.executeTool(name, localArgs, { operationId, ignorePresence }));
+      }
+    );
+
+    // Oversized results become a preview plus results:// URIs instead of one huge message
+    return this.spill?.exceeds(result) ? this.spill.spill(result) : result;
+  }
+
+  async handleHistoryList(params = {}, id) {
+    try {
+      if (!this.initialized) {
+        return this.createError(-32002, 'Server not initialized', id);
+      }
+
+      const { tool, sessionId, since, until, status, errorKind, limit = 50 } = params;
+      for (const [key, value] of Object.entries({ since, until })) {
+        if (value !== undefined && Number.isNaN(Date.parse(value))) {
+          return this.createError(-32602, `Invalid ${key} timestamp: ${value}`, id);
+        }
+      }
+
+      const entries = this.security.history.list({ tool, sessionId, since, until, status, errorKind, limit });
+      return this.createResponse({
+        // Results kept for idempotency keys can be large; history/list only shows their digest
+        entries: entries.map(({ result, ...entry }) => ({
+          ...entry,
+          replayable: this.security.history.isReplayable(entry) && this.pluginManager.isIdempotent(entry.tool, entry.arguments)
+        }))
+      }, id);
+    } catch (error) {
+      this.logger.error('History list error:', error);
+      return this.createError(-32603, 'Failed to list history', id);
+    }
+  }
+
+  // Idempotent calls run again with the recorded arguments; anything else returns the
+  // recorded outcome and the log lines of the original operation instead
+  async handleHistoryReplay(params = {}, id) {
+    try {
+      if (!this.initialized) {
+        return this.createError(-32002, 'Server not initialized', id);
+      }
+
+      const entry = params.id ? this.security.history.get(params.id) : null;
+      if (!entry) {
+        return this.createError(-32602, `Unknown history entry: ${params.id}`, id);
+      }
+
+      const reason = !this.pluginManager.isIdempotent(entry.tool, entry.arguments)
+        ? `${entry.tool} is not idempotent; call it again explicitly to repeat it`
+        : !this.security.history.isReplayable(entry)
+          ? 'The recorded arguments contain redacted values'
+          : null;
+
+      if (reason) {
+        const logs = this.logger.findOperationLogs ? await this.logger.findOperationLogs(entry.id) : [];
+        return this.createResponse({ replayed: false, reason, entry, logs }, id);
+      }
+
+      const result = await this.callTool(entry.tool, entry.arguments, { replayOf: entry.id });
+      return this.createResponse({
+        replayed: true,
+        entry,
+        result,
+        // A spilled preview hashes differently from the full result
+        changed: entry.digest && !result.spilled ? digestResult(result) !== entry.digest : null
+      }, id);
+    } catch (error) {
+      this.logger.error('History replay error:', error);
+      return this.createError(error.code ?? -32603, error.message, id, error.data);
+    }
+  }
+
+  // A queued job runs in its own request context, attributed to the session that queued it
+  async runJob(job) {
+    return runWithContext({
+      requestId: newRequestId(),
+      sessionId: job.sessionId,
+      method: 'jobs/run'
+    }, () => this.callTool(job.tool, job.args ?? job.arguments, { jobId: job.id }));
+  }
+
+  async handleJobsList(params = {}, id) {
+    try {
+      if (!this.initialized) {
+        return this.createError(-32002, 'Server not initialized', id);
+      }
+
+      const jobs = this.security.maintenance.list({ status: params.status });
+      return this.createResponse({
+        window: this.security.maintenance.enabled ? await this.security.maintenance.status() : null,
+        // Finished jobs point at the history entry holding their outcome
+        jobs: jobs.map(job => ({
+          ...job,
+          operationId: this.security.history.entries.findLast(entry => entry.jobId === job.id)?.id ?? null
+        }))
+      }, id);
+    } catch (error) {
+      this.logger.error('Jobs list error:', error);
+      return this.createError(-32603, 'Failed to list jobs', id);
+    }
+  }
+
+  async handleJobCancel(params = {}, id) {
+    try {
+      if (!this.initialized) {
+        return this.createError(-32002, 'Server not initialized', id);
+      }
+
+      const job = this.security.maintenance.get(params.id);
+      if (!job) {
+        return this.createError(-32602, `Unknown job: ${params.id}`, id);
+      }
+      return this.createResponse({ job: this.security.maintenance.cancel(job.id) }, id);
+    } catch (error) {
+      this.logger.error('Job cancel error:', error);
+      return this.createError(-32602, error.message, id);
     }
   }
 
@@ -137,6 +524,20 @@ export class MCPProtocol {
     }
   }
 
+  async handleResourceTemplatesList(params, id) {
+    try {
+      if (!this.initialized) {
+        return this.createError(-32002, 'Server not initialized', id);
+      }
+
+      const resourceTemplates = await this.pluginManager.getAllResourceTemplates();
+      return this.createResponse({ resourceTemplates }, id);
+    } catch (error) {
+      this.logger.error('Resource templates list error:', error);
+      return this.createError(-32603, 'Failed to list resource templates', id);
+    }
+  }
+
   async handleResourceRead(params, id) {
     try {
       if (!this.initialized) {
@@ -152,7 +553,8 @@ export class MCPProtocol {
         'resource_read',
         { uri },
         async () => {
-          return await this.pluginManager.readResource(uri);
+          const roots = this.clients.get(getContext().sessionId)?.roots;
+          return await runWithContext({ roots }, () => this.pluginManager.readResource(uri));
         }
       );
 
@@ -163,6 +565,81 @@ export class MCPProtocol {
     }
   }
 
+  // One plugin subscription per URI, shared by every session that subscribed to it
+  async handleResourceSubscribe(params, id) {
+    try {
+      if (!this.initialized) {
+        return this.createError(-32002, 'Server not initialized', id);
+      }
+
+      const uri = params?.uri;
+      if (!uri) {
+        return this.createError(-32602, 'Missing resource URI', id);
+      }
+
+      let subscription = this.subscriptions.get(uri);
+      if (!subscription) {
+        // Registered before awaiting so concurrent subscribers share one plugin subscription
+        subscription = { sessions: new Set() };
+        subscription.unsubscribe = this.pluginManager.subscribeResource(uri, (update = {}) => {
+          this.notifyResourceUpdated(uri, update);
+        });
+        this.subscriptions.set(uri, subscription);
+        try {
+          await subscription.unsubscribe;
+        } catch (error) {
+          this.subscriptions.delete(uri);
+          throw error;
+        }
+      }
+      subscription.sessions.add(getContext().sessionId);
+
+      return this.createResponse({}, id);
+    } catch (error) {
+      this.logger.error('Resource subscribe error:', error);
+      return this.createError(-32603, error.message, id);
+    }
+  }
+
+  async handleResourceUnsubscribe(params, id) {
+    await this.unsubscribeSession(params?.uri, getContext().sessionId);
+    return this.createResponse({}, id);
+  }
+
+  // The plugin stops watching once the last session lets go
+  async unsubscribeSession(uri, sessionId) {
+    const subscription = this.subscriptions.get(uri);
+    if (!subscription || !subscription.sessions.delete(sessionId) || subscription.sessions.size > 0) {
+      return;
+    }
+    this.subscriptions.delete(uri);
+    const unsubscribe = await subscription.unsubscribe;
+    await unsubscribe();
+  }
+
+  notifyResourceUpdated(uri, update = {}) {
+    for (const sessionId of this.subscriptions.get(uri)?.sessions || []) {
+      this.notify('notifications/resources/updated', { uri }, { sessionId });
+      if (update.message) {
+        this.notify('notifications/message', { level: update.level || 'info', logger: uri, data: update.message }, { sessionId });
+      }
+    }
+  }
+
+  async handleConfigReload(params, id) {
+    try {
+      if (!this.reloader) {
+        return this.createError(-32601, 'Config reload not available', id);
+      }
+
+      const result = await this.security.executeWithAudit('config_reload', {}, () => this.reloader.reload());
+      return this.createResponse(result, id);
+    } catch (error) {
+      this.logger.error('Config reload error:', error);
+      return this.createError(-32603, error.message, id);
+    }
+  }
+
   async handleCompletion(params, id) {
     // Simple completion implementation
     return this.createResponse({
@@ -174,6 +651,23 @@ export class MCPProtocol {
     }, id);
   }
 
+  // Server-initiated notifications: to one session, or broadcast to every connection
+  notify(method, params = {}, { sessionId } = {}) {
+    const notification = {
+      jsonrpc: '2.0',
+      method,
+      params
+    };
+
+    const targets = sessionId === undefined
+      ? [...this.connections.values()]
+      : [this.connections.get(sessionId)].filter(Boolean);
+    for (const send of targets) {
+      send(notification);
+    }
+    this.emit('notification', notification, sessionId);
+  }
+
   createResponse(result, id) {
     return {
       jsonrpc: '2.0',
@@ -182,13 +676,15 @@ export class MCPProtocol {
     };
   }
 
-  createError(code, message, id) {
+  createError(code, message, id, data = undefined) {
+    const error = { code, message };
+    if (data !== undefined) {
+      error.data = data;
+    }
+
     return {
       jsonrpc: '2.0',
-      error: {
-        code,
-        message
-      },
+      error,
       id
     };
   }
@@ -1,37 +1,218 @@
 import Fastify from 'fastify';
 import cors from '@fastify/cors';
+import { v4 as uuidv4 } from 'uuid';
+import websocket from '@fastify/websocket';
 import { MCPProtocol } from './mcp-protocol.js';
 import { PluginManager } from './plugin-manager.js';
+import { ConfigReloader } from './config-reloader.js';
+import { HealthCheck } from './health-check.js';
+import { HelperServer } from './helper-channel.js';
+import { getToolResolver } from '../system/tool-resolver.js';
+import { getSimulator } from '../system/simulator.js';
+import { getTargetRegistry } from '../system/target.js';
+import { getFleet } from './fleet.js';
+import { parseSize } from '../system/disk-quota.js';
 
 // Import plugins
 import { SystemPlugin } from '../plugins/system-plugin.js';
 import { ArchInstallPlugin } from '../plugins/arch-install-plugin.js';
+import { NspawnPlugin } from '../plugins/nspawn-plugin.js';
+import { SysctlPlugin } from '../plugins/sysctl-plugin.js';
+import { DevicesPlugin } from '../plugins/devices-plugin.js';
+import { FleetPlugin } from '../plugins/fleet-plugin.js';
 import { HyprlandPlugin } from '../plugins/hyprland-plugin.js';
+import { CompositorPlugin } from '../plugins/compositor-plugin.js';
+import { DotfilesPlugin } from '../plugins/dotfiles-plugin.js';
+import { GitPlugin } from '../plugins/git-plugin.js';
+import { EditorPlugin } from '../plugins/editor-plugin.js';
+import { SessionPlugin } from '../plugins/session-plugin.js';
+import { ShellPlugin } from '../plugins/shell-plugin.js';
+import { ThemePlugin } from '../plugins/theme-plugin.js';
+import { FontsPlugin } from '../plugins/fonts-plugin.js';
+import { MimePlugin } from '../plugins/mime-plugin.js';
+import { NightLightPlugin } from '../plugins/night-light-plugin.js';
+import { PowerPlugin } from '../plugins/power-plugin.js';
+import { LaptopPlugin } from '../plugins/laptop-plugin.js';
+import { GamingPlugin } from '../plugins/gaming-plugin.js';
+import { SensorsPlugin } from '../plugins/sensors-plugin.js';
+import { NetworkPlugin } from '../plugins/network-plugin.js';
 import { ScreenCapturePlugin } from '../plugins/screen-capture-plugin.js';
+import { TranscriptPlugin } from '../plugins/transcript-plugin.js';
+import { HealthPlugin } from '../plugins/health-plugin.js';
+import { RemotePlugin } from '../plugins/remote-plugin.js';
+import { ResultsPlugin } from '../plugins/results-plugin.js';
+import { ResultSpill } from './result-spill.js';
+
+// Simulation replaces every command with scripted results; see src/system/simulator.js
+export async function configureSimulation(options = {}, logger = null) {
+  await getSimulator().configure(options || {}, logger);
+  getToolResolver().simulated = getSimulator().enabled;
+}
+
+// Plugins keyed by their plugins.<key> config section
+export function createPluginFactories(config, logger, security) {
+  return {
+    system: { name: 'system', create: () => new SystemPlugin(config, logger, security) },
+    archInstall: { name: 'arch-install', create: () => new ArchInstallPlugin(config, logger, security) },
+    hyprland: { name: 'hyprland', create: () => new HyprlandPlugin(config, logger, security) },
+    compositor: { name: 'compositor', create: () => new CompositorPlugin(config, logger, security) },
+    dotfiles: { name: 'dotfiles', create: () => new DotfilesPlugin(config, logger, security) },
+    git: { name: 'git', create: () => new GitPlugin(config, logger, security) },
+    editor: { name: 'editor', create: () => new EditorPlugin(config, logger, security) },
+    session: { name: 'session', create: () => new SessionPlugin(config, logger, security) },
+    shell: { name: 'shell', create: () => new ShellPlugin(config, logger, security) },
+    theme: { name: 'theme', create: () => new ThemePlugin(config, logger, security) },
+    fonts: { name: 'fonts', create: () => new FontsPlugin(config, logger, security) },
+    mime: { name: 'mime', create: () => new MimePlugin(config, logger, security) },
+    nightLight: { name: 'night-light', create: () => new NightLightPlugin(config, logger, security) },
+    power: { name: 'power', create: () => new PowerPlugin(config, logger, security) },
+    laptop: { name: 'laptop', create: () => new LaptopPlugin(config, logger, security) },
+    gaming: { name: 'gaming', create: () => new GamingPlugin(config, logger, security) },
+    nspawn: { name: 'nspawn', create: () => new NspawnPlugin(config, logger, security) },
+    sysctl: { name: 'sysctl', create: () => new SysctlPlugin(config, logger, security) },
+    devices: { name: 'devices', create: () => new DevicesPlugin(config, logger, security) },
+    fleet: { name: 'fleet', create: () => new FleetPlugin(config, logger, security) },
+    sensors: { name: 'sensors', create: () => new SensorsPlugin(config, logger, security) },
+    network: { name: 'network', create: () => new NetworkPlugin(config, logger, security) },
+    screenCapture: { name: 'screen-capture', create: () => new ScreenCapturePlugin(config, logger, security) },
+    transcript: { name: 'transcript', create: () => new TranscriptPlugin(config, logger, security) }
+  };
+}
+
+// Splits the factories by plugins.<key>.enabled. A config section with no factory behind
+// it would otherwise be ignored without a word, so it is an error
+export function selectPlugins(config, factories) {
+  const known = Object.keys(factories);
+  const unknown = Object.keys(config.get('plugins') || {}).filter(key => !known.includes(key));
+  if (unknown.length > 0) {
+    throw new Error(`Unknown plugin${unknown.length > 1 ? 's' : ''} in config: ${unknown.join(', ')} (known: ${known.join(', ')})`);
+  }
+
+  const enabled = [];
+  const disabled = [];
+  for (const [key, factory] of Object.entries(factories)) {
+    (config.get(`plugins.${key}.enabled`) === false ? disabled : enabled).push({ key, ...factory });
+  }
+  return { enabled, disabled };
+}
 
 export async function createServer(config, logger, security) {
+  const limits = config.get('server.limits') || {};
+  const maxBufferedBytes = parseSize(limits.maxBufferedBytes ?? '8MB');
+
   const fastify = Fastify({
     logger: false, // We use our own logger
-    trustProxy: true
+    trustProxy: true,
+    bodyLimit: parseSize(limits.bodyLimit ?? '1MB'),
+    connectionTimeout: limits.idleTimeout ?? 360000,
+    keepAliveTimeout: limits.keepAliveTimeout ?? 5000,
+    requestTimeout: limits.requestTimeout ?? 30000
   });
 
+  // Bound concurrent connections and how long a client may dribble request headers
+  fastify.server.maxConnections = limits.maxConnections ?? 100;
+  fastify.server.headersTimeout = limits.headersTimeout ?? 10000;
+
   // Register CORS
   await fastify.register(cors, {
     origin: true,
     credentials: true
   });
 
+  await fastify.register(websocket, {
+    options: { maxPayload: parseSize(limits.maxMessageSize ?? '1MB') }
+  });
+
+  // Apply configured absolute tool paths before plugins resolve their dependencies
+  getToolResolver().configure(config.get('tools.paths') || {});
+  await configureSimulation(config.get('simulation'), logger);
+  getTargetRegistry().configure(config.get('targets') || {});
+  await getFleet().configure(config.get('fleet') || {}, logger);
+
   // Initialize plugin manager
   const pluginManager = new PluginManager(logger, security);
   
-  // Register plugins
-  await pluginManager.register(new SystemPlugin(config, logger, security));
-  await pluginManager.register(new ArchInstallPlugin(config, logger, security));
-  await pluginManager.register(new HyprlandPlugin(config, logger, security));
-  await pluginManager.register(new ScreenCapturePlugin(config, logger, security));
+  const pluginFactories = createPluginFactories(config, logger, security);
+
+  // Register enabled plugins
+  const { enabled, disabled } = selectPlugins(config, pluginFactories);
+  for (const factory of enabled) {
+    await pluginManager.register(factory.create());
+  }
+  if (disabled.length > 0) {
+    logger.info('Plugins disabled in config', { plugins: disabled.map(factory => factory.name) });
+  }
+
+  const split = config.get('split') || { role: 'standalone' };
+
+  // User instance: merge the privileged helper's tools into this registry
+  if (split.role === 'user') {
+    await pluginManager.register(new RemotePlugin(config, logger, security));
+  }
+
+  // Helper instance: serve local plugins to user instances over the helper socket
+  if (split.role === 'helper') {
+    const helper = new HelperServer({
+      pluginManager,
+      logger,
+      security,
+      socketPath: split.socketPath,
+      tokenFile: split.tokenFile,
+      socketGroup: split.socketGroup,
+      maxRequestSize: parseSize(limits.maxRequestSize ?? '1MB')
+    });
+    await helper.start();
+    fastify.addHook('onClose', async () => helper.stop());
+  }
+
+  const healthCheck = new HealthCheck({ config, security, pluginManager });
+  await pluginManager.register(new HealthPlugin(config, logger, security, healthCheck));
+
+  const spill = new ResultSpill({
+    maxResponseSize: parseSize(limits.maxResponseSize ?? '4MB'),
+    previewSize: parseSize(limits.resultPreviewSize ?? '16KB'),
+    ttl: limits.resultTtl ?? 900000
+  }, logger);
+  await pluginManager.register(new ResultsPlugin(config, logger, security, spill));
 
   // Initialize MCP protocol handler
-  const mcpProtocol = new MCPProtocol(pluginManager, logger, security);
+  const mcpProtocol = new MCPProtocol(pluginManager, logger, security, {
+    maxRequestSize: parseSize(limits.maxRequestSize ?? '1MB'),
+    spill,
+    fleet: getFleet()
+  });
+
+  // Re-read configuration on SIGHUP or a config/reload request
+  const reloader = new ConfigReloader({ config, logger, security, pluginManager, protocol: mcpProtocol, pluginFactories });
+  mcpProtocol.reloader = reloader;
+  fastify.decorate('reloadConfig', () => reloader.reload());
+  // The stdio transport dispatches through the same protocol handler as /mcp and /mcp/ws
+  fastify.decorate('mcpProtocol', mcpProtocol);
+
+  security.toolRunner = (name, args, options) => mcpProtocol.callTool(name, args, options);
+
+  // Destructive calls queued outside the maintenance window run once it opens
+  security.maintenance.start(job => mcpProtocol.runJob(job));
+
+  // Let clients refresh system://snapshots when snapshots are created, deleted or pruned
+  security.rollback.on('changed', () => {
+    mcpProtocol.notify('notifications/resources/list_changed');
+    mcpProtocol.notifyResourceUpdated('system://snapshots');
+  });
+
+  // Drop clients that stop reading instead of buffering without bound; the close
+  // handler then detaches the session
+  const send = (socket, message) => {
+    if (socket.readyState !== socket.OPEN) {
+      return;
+    }
+    if (socket.bufferedAmount + Buffer.byteLength(message) > maxBufferedBytes) {
+      logger.warn('Closing slow WebSocket client', { bufferedAmount: socket.bufferedAmount });
+      socket.terminate();
+      return;
+    }
+    socket.send(message);
+  };
 
   // Health check endpoint
   fastify.get('/health', async (request, reply) => {
@@ -43,10 +224,25 @@ export async function createServer(config, logger, security) {
     };
   });
 
+  // Probes for systemd, container orchestrators and monitoring
+  fastify.get('/health/live', async () => healthCheck.live());
+
+  fastify.get('/health/ready', async (request, reply) => {
+    const readiness = await healthCheck.ready();
+    reply.code(readiness.ready ? 200 : 503);
+    return readiness;
+  });
+
   // MCP protocol endpoint (JSON-RPC over HTTP)
   fastify.post('/mcp', async (request, reply) => {
     try {
-      const response = await mcpProtocol.handleRequest(request.body);
+      const response = await mcpProtocol.handleRequest(request.body, {
+        sessionId: request.headers['mcp-session-id']
+      });
+      if (!response) {
+        reply.code(202);
+        return '';
+      }
       reply.type('application/json');
       return response;
     } catch (error) {
@@ -67,16 +263,35 @@ export async function createServer(config, logger, security) {
   // WebSocket endpoint for MCP protocol
   fastify.register(async function (fastify) {
     fastify.get('/mcp/ws', { websocket: true }, (connection, request) => {
-      logger.info('New WebSocket connection established');
+      const socket = connection.socket || connection;
+      const sessionId = uuidv4();
+      const detach = mcpProtocol.attach(sessionId, (message) => send(socket, JSON.stringify(message)));
+      logger.info('New WebSocket connection established', { sessionId });
+
+      let idleTimer = null;
+      const resetIdleTimer = () => {
+        clearTimeout(idleTimer);
+        if (limits.socketIdleTimeout) {
+          idleTimer = setTimeout(() => {
+            logger.info('Closing idle WebSocket connection', { sessionId });
+            socket.close(1000, 'Idle timeout');
+          }, limits.socketIdleTimeout);
+        }
+      };
+      resetIdleTimer();
 
-      connection.on('message', async (message) => {
+      socket.on('message', async (message) => {
+        resetIdleTimer();
         try {
           const data = JSON.parse(message.toString());
-          const response = await mcpProtocol.handleRequest(data);
-          connection.send(JSON.stringify(response));
+          const response = await mcpProtocol.handleRequest(data, { sessionId });
+          // Notifications and answers to server requests get no reply
+          if (response) {
+            send(socket, JSON.stringify(response));
+          }
         } catch (error) {
           logger.error('WebSocket message error:', error);
-          connection.send(JSON.stringify({
+          send(socket, JSON.stringify({
             jsonrpc: '2.0',
             error: {
               code: -32603,
@@ -88,11 +303,13 @@ export async function createServer(config, logger, security) {
         }
       });
 
-      connection.on('close', () => {
+      socket.on('close', () => {
+        clearTimeout(idleTimer);
+        detach();
         logger.info('WebSocket connection closed');
       });
 
-      connection.on('error', (error) => {
+      socket.on('error', (error) => {
         logger.error('WebSocket error:', error);
       });
     });
@@ -1,3 +1,8 @@
+import { MCPError, ERROR_CODES, toolError } from './errors.js';
+import { getContext, updateContext } from './request-context.js';
+import { compileUriTemplate } from './uri-template.js';
+import { redact } from '../security/transcript.js';
+
 export class PluginManager {
   constructor(logger, security) {
     this.logger = logger;
@@ -5,6 +10,7 @@ export class PluginManager {
     this.plugins = new Map();
     this.tools = new Map();
     this.resources = new Map();
+    this.templates = new Map();
   }
 
   async register(plugin) {
@@ -40,6 +46,15 @@ export class PluginManager {
         this.resources.set(resource.uri, { resource, plugin });
       }
 
+      // Register parameterized resources (RFC 6570 URI templates)
+      const templates = plugin.getResourceTemplates ? await plugin.getResourceTemplates() : [];
+      for (const template of templates) {
+        if (this.templates.has(template.uriTemplate)) {
+          throw new Error(`Resource template ${template.uriTemplate} is already registered by another plugin`);
+        }
+        this.templates.set(template.uriTemplate, { template, plugin, match: compileUriTemplate(template.uriTemplate) });
+      }
+
       this.plugins.set(plugin.name, plugin);
       this.logger.info(`Registered plugin: ${plugin.name}`);
 
@@ -69,6 +84,13 @@ export class PluginManager {
       }
     }
 
+    // Remove resource templates
+    for (const [uriTemplate, { plugin: templatePlugin }] of this.templates.entries()) {
+      if (templatePlugin === plugin) {
+        this.templates.delete(uriTemplate);
+      }
+    }
+
     // Cleanup plugin
     if (plugin.cleanup) {
       await plugin.cleanup();
@@ -78,9 +100,13 @@ export class PluginManager {
     this.logger.info(`Unregistered plugin: ${pluginName}`);
   }
 
+  // In read-only mode tools that can only change the system are left out
   async getAllTools() {
     const tools = [];
-    for (const [name, { tool }] of this.tools.entries()) {
+    for (const [name, { tool, plugin }] of this.tools.entries()) {
+      if (this.security.readOnly && !plugin.hasReadOnlyCalls?.(name)) {
+        continue;
+      }
       tools.push(tool);
     }
     return tools;
@@ -94,44 +120,173 @@ export class PluginManager {
     return resources;
   }
 
-  async executeTool(toolName, args) {
+  async getAllResourceTemplates() {
+    return [...this.templates.values()].map(({ template }) => template);
+  }
+
+  // Fixed URIs win; otherwise the first template that matches owns the URI
+  matchResourceTemplate(uri) {
+    for (const { template, plugin, match } of this.templates.values()) {
+      const params = match(uri);
+      if (params) {
+        return { template, plugin, params };
+      }
+    }
+    return null;
+  }
+
+  async executeTool(toolName, args, context = {}) {
     const toolData = this.tools.get(toolName);
     if (!toolData) {
-      throw new Error(`Tool not found: ${toolName}`);
+      throw new MCPError(`Tool not found: ${toolName}`, ERROR_CODES.not_found, { kind: 'not_found', tool: toolName });
     }
 
-    const { plugin } = toolData;
+    const { plugin, tool } = toolData;
+    if (this.security.readOnly && !this.isReadOnly(toolName, args)) {
+      throw new MCPError(`${toolName} can change the system and the server is in read-only mode`,
+        ERROR_CODES.not_allowed, { kind: 'not_allowed', readOnly: true });
+    }
+    if (this.security.presence.guard && !context.ignorePresence && this.disruptsUser(toolName, args)) {
+      const presence = await this.security.presence.status();
+      if (presence.present) {
+        throw new MCPError(`${toolName} would interrupt the user at the seat (${presence.users.join(', ')}); pass _meta.ignorePresence to run it anyway`,
+          ERROR_CODES.not_allowed, { kind: 'not_allowed', presence: { users: presence.users, idleThreshold: presence.idleThreshold } });
+      }
+    }
+    if (this.security.maintenance.enabled && this.isDestructive(toolName, args)) {
+      const window = await this.security.maintenance.status();
+      if (!window.open) {
+        throw new MCPError(`${toolName} may only run in a maintenance window: ${window.reason}`,
+          ERROR_CODES.not_allowed, { kind: 'not_allowed', maintenanceWindow: window });
+      }
+    }
+
+    // Serialize tools contending for the same device, mount target or database
+    const lockKeys = plugin.getToolLocks ? plugin.getToolLocks(toolName, args) : [];
+    const releaseLocks = await this.security.locks.acquire(lockKeys, {
+      tool: toolName,
+      plugin: plugin.name
+    });
+    let inhibitor = null;
+    let snapshotId = null;
+    let overrun = null;
     
     try {
-      this.logger.debug(`Executing tool: ${toolName}`, { args });
-      const result = await plugin.executeTool(toolName, args);
-      this.logger.debug(`Tool execution completed: ${toolName}`);
+      // Snapshot whatever the tool declares it will modify so the call can be undone
+      const affectedPaths = plugin.getAffectedPaths ? await plugin.getAffectedPaths(toolName, args) : [];
+      const packages = plugin.affectsPackages ? plugin.affectsPackages(toolName, args) : false;
+      if (affectedPaths.length > 0 || packages) {
+        snapshotId = await this.security.createOperationSnapshot(context.operationId, toolName, affectedPaths, { packages });
+      }
+
+      // Keep the machine from sleeping or locking halfway through long operations
+      if (this.security.autoInhibitIdle && (packages || (plugin.inhibitsIdle && plugin.inhibitsIdle(toolName, args)))) {
+        inhibitor = await this.security.idle.acquire(`Running ${toolName}`).catch(error => {
+          this.logger.warn(`Could not inhibit idle for ${toolName}: ${error.message}`);
+          return null;
+        });
+      }
+
+      // Install passwords and other secrets in the arguments never reach the log
+      this.logger.debug(`Executing tool: ${toolName}`, { args: redact(args) });
+      const startTime = Date.now();
+      updateContext({ deadline: tool.timeout ? startTime + tool.timeout : null });
+      const execution = plugin.executeTool(toolName, args);
+      const result = tool.timeout ? await this.withinBudget(execution, tool.timeout, toolName).catch(error => {
+        if (error.data?.budget) {
+          overrun = execution;
+        }
+        throw error;
+      }) : await execution;
+      this.logger.info(`Tool execution completed: ${toolName}`, { duration: Date.now() - startTime });
+
+      if (affectedPaths.length > 0) {
+        await this.security.commitEtcChanges(context.operationId, toolName, affectedPaths);
+      }
       return result;
     } catch (error) {
       this.logger.error(`Tool execution failed: ${toolName}`, error);
-      throw new Error(`Tool execution failed: ${error.message}`);
+      throw toolError(error, {
+        commandFailure: getContext().commandFailure,
+        snapshotId,
+        idempotent: this.isIdempotent(toolName, args)
+      });
+    } finally {
+      const release = () => {
+        if (inhibitor) {
+          this.security.idle.release(inhibitor);
+        }
+        releaseLocks();
+      };
+      // The call has been answered, but its work still holds the locks until it ends
+      if (overrun) {
+        overrun.catch(() => {}).finally(() => {
+          this.logger.warn(`${toolName} finished after its time budget ran out`);
+          release();
+        });
+      } else {
+        release();
+      }
     }
   }
 
+  // Fails the call once its time budget is spent; the work itself cannot be interrupted
+  withinBudget(execution, budget, toolName) {
+    let timer;
+    const expired = new Promise((resolve, reject) => {
+      timer = setTimeout(() => reject(new MCPError(
+        `${toolName} exceeded its time budget of ${budget} ms`,
+        ERROR_CODES.timeout,
+        { kind: 'timeout', budget }
+      )), budget);
+    });
+    return Promise.race([execution, expired]).finally(() => clearTimeout(timer));
+  }
+
   async readResource(uri) {
     const resourceData = this.resources.get(uri);
-    if (!resourceData) {
+    const templateData = resourceData ? null : this.matchResourceTemplate(uri);
+    if (!resourceData && !templateData) {
       throw new Error(`Resource not found: ${uri}`);
     }
 
-    const { plugin } = resourceData;
+    const { plugin } = resourceData || templateData;
+    const mimeType = (resourceData?.resource || templateData.template).mimeType;
     
     try {
       this.logger.debug(`Reading resource: ${uri}`);
-      const result = await plugin.readResource(uri);
+      const result = resourceData
+        ? await plugin.readResource(uri)
+        : await plugin.readResourceTemplate(templateData.template.uriTemplate, templateData.params, uri);
       this.logger.debug(`Resource read completed: ${uri}`);
-      return result;
+      return this.toResourceContents(uri, result, mimeType);
     } catch (error) {
       this.logger.error(`Resource read failed: ${uri}`, error);
       throw new Error(`Resource read failed: ${error.message}`);
     }
   }
 
+  // Plugins return { content: text } or { blob, mimeType }; clients get MCP resource contents
+  toResourceContents(uri, result, mimeType) {
+    if (Array.isArray(result?.contents)) {
+      return result;
+    }
+    if (result?.blob !== undefined) {
+      return { contents: [{ uri, mimeType: result.mimeType || mimeType, blob: result.blob }] };
+    }
+
+    const text = typeof result?.content === 'string' ? result.content : JSON.stringify(result?.content ?? result, null, 2);
+    return { contents: [{ uri, mimeType: result?.mimeType || mimeType, text }] };
+  }
+
+  async subscribeResource(uri, listener) {
+    const resourceData = this.resources.get(uri);
+    if (!resourceData) {
+      throw new Error(`Resource not found: ${uri}`);
+    }
+    return resourceData.plugin.subscribeResource(uri, listener);
+  }
+
   async getPluginList() {
     const plugins = [];
     for (const [name, plugin] of this.plugins.entries()) {
@@ -146,6 +301,30 @@ export class PluginManager {
     return plugins;
   }
 
+  isIdempotent(toolName, args) {
+    const plugin = this.tools.get(toolName)?.plugin;
+    return Boolean(plugin?.isIdempotent && plugin.isIdempotent(toolName, args));
+  }
+
+  isReadOnly(toolName, args) {
+    const plugin = this.tools.get(toolName)?.plugin;
+    return Boolean(plugin?.isReadOnly && plugin.isReadOnly(toolName, args));
+  }
+
+  // Plugins mark their own destructive calls; security.maintenance.tools adds more by name
+  isDestructive(toolName, args) {
+    const plugin = this.tools.get(toolName)?.plugin;
+    return this.security.maintenance.tools.includes(toolName) ||
+      Boolean(plugin?.isDestructive && plugin.isDestructive(toolName, args));
+  }
+
+  // Plugins mark their own disruptive calls; security.presence.tools adds more by name
+  disruptsUser(toolName, args) {
+    const plugin = this.tools.get(toolName)?.plugin;
+    return this.security.presence.tools.includes(toolName) ||
+      Boolean(plugin?.disruptsUser && plugin.disruptsUser(toolName, args));
+  }
+
   getPlugin(name) {
     return this.plugins.get(name);
   }
@@ -164,5 +343,6 @@ export class PluginManager {
     this.plugins.clear();
     this.tools.clear();
     this.resources.clear();
+    this.templates.clear();
   }
 }
\ No newline at end of file
@@ -0,0 +1,48 @@
+import { AsyncLocalStorage } from 'async_hooks';
+import { v4 as uuidv4 } from 'uuid';
+
+// Carries request/session/tool identifiers across awaits so every log line can be correlated
+const storage = new AsyncLocalStorage();
+
+export function runWithContext(fields, operation) {
+  const parent = storage.getStore() || {};
+  return storage.run({ ...parent, ...fields }, operation);
+}
+
+export function getContext() {
+  return storage.getStore() || {};
+}
+
+// Streams progress for the current tool call when the client sent a progressToken; calls
+// with a time budget also report how much of it is left. details (structured state such as
+// a phase or the current item) travel in _meta next to it
+export function reportProgress(progress, { total, message, details = null } = {}) {
+  const { onProgress, deadline } = getContext();
+  const meta = {
+    ...(details || {}),
+    ...(deadline ? { remainingMs: Math.max(0, deadline - Date.now()) } : {})
+  };
+  onProgress?.({
+    progress,
+    total,
+    message,
+    ...(Object.keys(meta).length > 0 ? { _meta: meta } : {})
+  });
+}
+
+// Sets fields on the current context in place, so code further up the same call sees them
+export function updateContext(fields) {
+  const store = storage.getStore();
+  if (store) {
+    Object.assign(store, fields);
+  }
+}
+
+// The last command that exited non-zero during this tool call; see toolError in errors.js
+export function recordCommandFailure(failure) {
+  updateContext({ commandFailure: failure });
+}
+
+export function newRequestId() {
+  return uuidv4();
+}
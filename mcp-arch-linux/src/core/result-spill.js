@@ -0,0 +1,97 @@
+import fs from 'fs-extra';
+import os from 'os';
+import path from 'path';
+import { v4 as uuidv4 } from 'uuid';
+import { encodeFileBase64 } from '../system/base64.js';
+
+const EXTENSIONS = { 'image/png': '.png', 'image/jpeg': '.jpg', 'image/webp': '.webp' };
+
+// Tool results too large for one JSON-RPC response are written to disk and served as
+// results://{id} resources; the response keeps a preview and the URIs
+export class ResultSpill {
+  constructor(options = {}, logger) {
+    this.logger = logger;
+    this.dir = options.dir || path.join(os.tmpdir(), `mcp-arch-linux-results-${process.pid}`);
+    this.maxResponseSize = options.maxResponseSize || 4 * 1024 * 1024;
+    this.previewSize = options.previewSize || 16 * 1024;
+    this.ttl = options.ttl ?? 15 * 60 * 1000;
+    this.entries = new Map();
+  }
+
+  exceeds(result) {
+    return Buffer.byteLength(JSON.stringify(result)) > this.maxResponseSize;
+  }
+
+  async store(data, mimeType) {
+    const id = uuidv4();
+    const file = path.join(this.dir, `${id}${EXTENSIONS[mimeType] || '.txt'}`);
+    await fs.ensureDir(this.dir, { mode: 0o700 });
+    await fs.writeFile(file, data, { mode: 0o600 });
+    this.entries.set(id, { file, mimeType, size: data.length, expires: Date.now() + this.ttl });
+    return { uri: `results://${id}`, size: data.length };
+  }
+
+  async spill(result) {
+    await this.prune();
+
+    const items = result.content || [];
+    const text = items.filter(item => item.type === 'text').map(item => item.text).join('\n');
+    const stored = [];
+
+    if (text) {
+      stored.push({ ...await this.store(Buffer.from(text), 'text/plain'), mimeType: 'text/plain' });
+    }
+    for (const item of items.filter(candidate => candidate.type === 'image')) {
+      stored.push({ ...await this.store(Buffer.from(item.data, 'base64'), item.mimeType), mimeType: item.mimeType });
+    }
+
+    const preview = Buffer.from(text).subarray(0, this.previewSize).toString('utf8');
+    const notice = [
+      `[Result exceeded ${this.maxResponseSize} bytes and was truncated. Full output (kept ${Math.round(this.ttl / 60000)} min):`,
+      ...stored.map(entry => `  ${entry.uri} (${entry.mimeType}, ${entry.size} bytes)`),
+      ']'
+    ].join('\n');
+
+    this.logger.info('Spilled oversized tool result', { uris: stored.map(entry => entry.uri) });
+    return {
+      ...result,
+      content: [{ type: 'text', text: preview ? `${preview}\n\n${notice}` : notice }],
+      spilled: stored
+    };
+  }
+
+  async read(id) {
+    const entry = this.entries.get(id);
+    if (!entry || entry.expires < Date.now()) {
+      throw new Error(`Result not found or expired: results://${id}`);
+    }
+
+    return entry.mimeType === 'text/plain'
+      ? { content: await fs.readFile(entry.file, 'utf8') }
+      : { blob: await encodeFileBase64(entry.file), mimeType: entry.mimeType };
+  }
+
+  list() {
+    return [...this.entries].map(([id, entry]) => ({
+      uri: `results://${id}`,
+      mimeType: entry.mimeType,
+      size: entry.size,
+      expires: new Date(entry.expires).toISOString()
+    }));
+  }
+
+  async prune() {
+    const now = Date.now();
+    for (const [id, entry] of this.entries) {
+      if (entry.expires < now) {
+        this.entries.delete(id);
+        await fs.remove(entry.file).catch(() => {});
+      }
+    }
+  }
+
+  async cleanup() {
+    this.entries.clear();
+    await fs.remove(this.dir).catch(() => {});
+  }
+}
@@ -0,0 +1,109 @@
+import { v4 as uuidv4 } from 'uuid';
+
+// MCP over the server's own stdin and stdout, for clients that spawn the server: one
+// JSON-RPC message per line in each direction, all of it a single session. Nothing else
+// may write to stdout meanwhile, so logs and uncaptured command output go to stderr
+export class StdioTransport {
+  constructor({ protocol, logger, input = process.stdin, output = process.stdout, maxRequestSize = null }) {
+    this.protocol = protocol;
+    this.logger = logger;
+    this.input = input;
+    this.output = output;
+    this.maxRequestSize = maxRequestSize;
+    this.sessionId = uuidv4();
+    this.buffer = '';
+    this.discarding = false;
+    this.detach = null;
+    this.open = false;
+    this.closed = new Promise(resolve => { this.onClosed = resolve; });
+  }
+
+  start() {
+    this.detach = this.protocol.attach(this.sessionId, message => this.send(message));
+    this.open = true;
+
+    this.input.setEncoding('utf8');
+    this.input.on('data', chunk => this.receive(chunk));
+    // The client closing our stdin (or exiting) ends the session and the server
+    this.input.once('end', () => this.close());
+    this.input.once('error', error => {
+      this.logger.error('stdin error:', error.message);
+      this.close();
+    });
+    this.output.on('error', error => {
+      this.logger.error('stdout error:', error.message);
+      this.close();
+    });
+
+    this.logger.info('Serving MCP on stdin/stdout', { sessionId: this.sessionId });
+  }
+
+  receive(chunk) {
+    this.buffer += chunk;
+    let newline;
+    while ((newline = this.buffer.indexOf('\n')) !== -1) {
+      const line = this.buffer.slice(0, newline);
+      this.buffer = this.buffer.slice(newline + 1);
+      // The rest of a line already answered as too large
+      if (this.discarding) {
+        this.discarding = false;
+        continue;
+      }
+      if (line.trim()) {
+        this.handleLine(line);
+      }
+    }
+
+    // An unterminated line is bounded like a request body on the other transports
+    if (this.maxRequestSize && Buffer.byteLength(this.buffer) > this.maxRequestSize) {
+      if (!this.discarding) {
+        this.send(this.protocol.createError(-32600, `Request exceeds ${this.maxRequestSize} bytes`, null));
+      }
+      this.discarding = true;
+      this.buffer = '';
+    }
+  }
+
+  // Requests run concurrently, as on a WebSocket; responses carry their id
+  handleLine(line) {
+    let message;
+    try {
+      message = JSON.parse(line);
+    } catch {
+      this.send({ jsonrpc: '2.0', error: { code: -32700, message: 'Parse error' }, id: null });
+      return;
+    }
+
+    this.protocol.handleRequest(message, { sessionId: this.sessionId })
+      .then(response => {
+        // Notifications and answers to server requests get no reply
+        if (response) {
+          this.send(response);
+        }
+      })
+      .catch(error => {
+        this.logger.error('stdio message error:', error);
+        this.send({
+          jsonrpc: '2.0',
+          error: { code: -32603, message: 'Internal error', data: error.message },
+          id: message?.id ?? null
+        });
+      });
+  }
+
+  send(message) {
+    if (this.open) {
+      this.output.write(`${JSON.stringify(message)}\n`);
+    }
+  }
+
+  close() {
+    if (!this.open) {
+      return;
+    }
+    this.open = false;
+    this.detach();
+    this.logger.info('stdin closed, ending the stdio session');
+    this.onClosed();
+  }
+}
@@ -0,0 +1,173 @@
+import { execFile } from 'child_process';
+import fs from 'fs-extra';
+import path from 'path';
+
+// "unix:///run/mcp-arch-linux/server.sock" as server.host (or --host) binds a local socket
+export function parseUnixAddress(address) {
+  const match = /^unix:\/\/(\/.+)$/.exec(address || '');
+  return match ? match[1] : null;
+}
+
+// Pids holding the other end of the socket with this inode, from ss lines for established
+// sockets (which leave out the state column) like
+// "u_str 0 0 * 113380 * 113381 users:(("curl",pid=22922,fd=4))"
+export function parsePeerPids(output, inode) {
+  for (const line of output.split('\n')) {
+    const fields = line.trim().split(/\s+/);
+    if (fields[0] === 'u_str' && fields[6] === String(inode)) {
+      return [...line.matchAll(/pid=(\d+)/g)].map(([, pid]) => Number(pid));
+    }
+  }
+  return [];
+}
+
+async function lookup(file, entry, field) {
+  if (typeof entry === 'number') {
+    return entry;
+  }
+  const found = (await fs.readFile(file, 'utf8'))
+    .split('\n')
+    .map(line => line.split(':'))
+    .find(([name]) => name === entry);
+  if (!found) {
+    throw new Error(`Unknown ${field}: ${entry}`);
+  }
+  return parseInt(found[2], 10);
+}
+
+export const resolveUser = user => lookup('/etc/passwd', user, 'user');
+export const resolveGroup = group => lookup('/etc/group', group, 'group');
+
+// Effective uid and gid plus supplementary groups of a process
+async function processCredentials(pid) {
+  const status = await fs.readFile(`/proc/${pid}/status`, 'utf8');
+  const field = name => (new RegExp(`^${name}:[ \\t]*(.*)$`, 'm').exec(status)?.[1] || '').split(/\s+/).filter(Boolean).map(Number);
+  return { pid, uid: field('Uid')[1], gid: field('Gid')[1], groups: field('Groups') };
+}
+
+// Who is on the other end of an accepted unix connection. Node cannot read SO_PEERCRED,
+// so the peer socket is found through the kernel's socket diagnostics (ss, which needs
+// root to see other users' processes) and its owner read from /proc. A connection whose
+// peer cannot be found, or is held by processes of several users, is not identified
+export async function peerCredentials(socket) {
+  const fd = socket._handle?.fd;
+  const link = fd >= 0 ? await fs.readlink(`/proc/self/fd/${fd}`).catch(() => '') : '';
+  const inode = /^socket:\[(\d+)\]$/.exec(link)?.[1];
+  if (!inode) {
+    return null;
+  }
+
+  const output = await new Promise((resolve) => {
+    execFile('ss', ['-x', '-n', '-p', '-H', 'state', 'established'], { timeout: 5000 }, (error, stdout) => resolve(error ? '' : stdout));
+  });
+  const holders = [];
+  for (const pid of new Set(parsePeerPids(output, inode))) {
+    const credentials = await processCredentials(pid).catch(() => null);
+    if (credentials) holders.push(credentials);
+  }
+  if (holders.length === 0 || holders.some(entry => entry.uid !== holders[0].uid)) {
+    return null;
+  }
+  return holders[0];
+}
+
+// Serves the fastify instance on a unix socket whose file mode and group decide who may
+// open it; allowedUsers and allowedGroups additionally check each connecting process
+export class UnixSocketListener {
+  constructor(fastify, { socketPath, mode = '0660', group = null, allowedUsers = [], allowedGroups = [], logger }) {
+    this.fastify = fastify;
+    this.socketPath = socketPath;
+    this.mode = typeof mode === 'number' ? mode : parseInt(mode, 8);
+    this.group = group;
+    this.allowedUsers = allowedUsers;
+    this.allowedGroups = allowedGroups;
+    this.logger = logger;
+    this.uids = null;
+    this.gids = null;
+  }
+
+  get restricted() {
+    return this.allowedUsers.length > 0 || this.allowedGroups.length > 0;
+  }
+
+  // Hooks must be in place before the server is ready. Checks apply to every unix
+  // connection, so also to a socket-activated unix socket
+  async prepare() {
+    if (!this.restricted) {
+      return;
+    }
+    this.uids = await Promise.all(this.allowedUsers.map(resolveUser));
+    this.gids = await Promise.all(this.allowedGroups.map(resolveGroup));
+
+    this.fastify.server.on('connection', (socket) => {
+      // Unix connections have no remote address
+      if (socket.remoteAddress !== undefined) {
+        return;
+      }
+      socket.peer = peerCredentials(socket)
+        .catch(() => null)
+        .then((peer) => {
+          if (!this.allows(peer)) {
+            this.logger.audit('socket_peer_refused', { socket: this.socketPath, ...(peer || { peer: 'unidentified' }) });
+          } else {
+            this.logger.debug('Socket peer allowed', peer);
+          }
+          return peer;
+        });
+    });
+
+    // Every request waits for its connection's check; refused ones are answered and the
+    // connection closed
+    this.fastify.addHook('onRequest', async (request, reply) => {
+      const pending = request.raw.socket?.peer;
+      if (!pending) {
+        return;
+      }
+      const peer = await pending;
+      if (!this.allows(peer)) {
+        reply.code(403).header('connection', 'close').send({ jsonrpc: '2.0', error: { code: -32001, message: 'Forbidden' }, id: null });
+        return reply;
+      }
+      request.peer = peer;
+    });
+  }
+
+  allows(peer) {
+    if (!peer) {
+      return false;
+    }
+    return this.uids.includes(peer.uid) ||
+      this.gids.includes(peer.gid) ||
+      peer.groups.some(gid => this.gids.includes(gid));
+  }
+
+  async listen() {
+    await fs.ensureDir(path.dirname(this.socketPath));
+    // A socket left behind by a server that did not shut down cleanly; anything else is not ours
+    const existing = await fs.lstat(this.socketPath).catch(() => null);
+    if (existing) {
+      if (!existing.isSocket()) {
+        throw new Error(`${this.socketPath} exists and is not a socket`);
+      }
+      await fs.remove(this.socketPath);
+    }
+
+    // Created owner-only so nobody can connect before the configured mode and group are
+    // in place
+    const umask = process.umask(0o177);
+    try {
+      await this.fastify.listen({ path: this.socketPath });
+    } finally {
+      process.umask(umask);
+    }
+
+    await fs.chmod(this.socketPath, this.mode);
+    if (this.group !== null) {
+      try {
+        await fs.chown(this.socketPath, process.getuid(), await resolveGroup(this.group));
+      } catch (error) {
+        this.logger.warn(`Could not set server socket group: ${error.message}`);
+      }
+    }
+  }
+}
@@ -0,0 +1,30 @@
+const escapeRegExp = text => text.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
+
+// RFC 6570 level 1 and 2 templates: {name} matches one path segment, {+name} may span
+// several. Returns a matcher yielding the decoded variables, or null when the URI differs.
+export function compileUriTemplate(template) {
+  const names = [];
+  let pattern = '';
+  let last = 0;
+
+  for (const match of template.matchAll(/\{(\+?)([A-Za-z0-9_]+)\}/g)) {
+    pattern += escapeRegExp(template.slice(last, match.index));
+    pattern += match[1] === '+' ? '(.+)' : '([^/?#]+)';
+    names.push(match[2]);
+    last = match.index + match[0].length;
+  }
+  pattern += escapeRegExp(template.slice(last));
+
+  const regex = new RegExp(`^${pattern}$`);
+  return (uri) => {
+    const match = uri.match(regex);
+    if (!match) {
+      return null;
+    }
+    try {
+      return Object.fromEntries(names.map((name, index) => [name, decodeURIComponent(match[index + 1])]));
+    } catch {
+      return null;
+    }
+  };
+}
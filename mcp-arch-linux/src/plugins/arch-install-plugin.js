@@ -1,25 +1,89 @@
-import { BasePlugin } from './base-plugin.js';
+import { BasePlugin, pluginConfig } from './base-plugin.js';
 import { CommandExecutor } from '../system/command-executor.js';
+import { getEnvironmentInfo } from '../system/environment-info.js';
+import { LockKeys } from '../security/lock-manager.js';
+import { IsoMedia, sha256 } from '../system/iso-media.js';
+import { DeviceWipe, WIPE_METHODS } from '../system/device-wipe.js';
+import { LoopImage, partitionPath } from '../system/loop-image.js';
+import { parseSize } from '../system/disk-quota.js';
+import {
+  buildPartitionPlan, buildRaidPlan, defaultLayout, customLayout, mountOrder, renderPartitionPlan, commandLine, mountArgs,
+  FILESYSTEMS, DATA_FILESYSTEMS, RAID_LEVELS
+} from '../system/partition-plan.js';
+import { addHook, parseHooks } from '../system/mkinitcpio.js';
+import { PacmanProgress } from '../system/pacman-progress.js';
+import { PACMAN_CONF, localInstallConf } from '../system/pacman-conf.js';
+import { sha512Crypt, setShadowPassword, hasUsablePassword, CRYPT_HASH } from '../security/password-hash.js';
+import { InstallProgress } from '../system/install-progress.js';
+import { LC_VARIABLES, enableLocales, renderLocaleConf, renderVconsole, renderX11Keyboard, setXkbEnvironment, findKbdFile, checkLocaleSettings } from '../system/locale-config.js';
+import { hibernationSwapSize, parseFilefragOffset } from '../system/hibernation.js';
+import { BootCmdline, parseCmdline } from '../system/kernel-params.js';
+import { DEFAULT_INSTALL_ROOT, getTargetRegistry } from '../system/target.js';
+import { reportProgress } from '../core/request-context.js';
 import fs from 'fs-extra';
+import os from 'os';
 import path from 'path';
+import YAML from 'yaml';
+
+// What build_image installs when the profile leaves a field out
+const DEFAULT_IMAGE_PROFILE = {
+  size: '8G',
+  scheme: 'uefi',
+  swapSize: '512M',
+  filesystem: 'ext4',
+  packages: ['base', 'linux', 'linux-firmware', 'networkmanager'],
+  hostname: 'archlinux',
+  timezone: 'UTC',
+  locale: 'en_US.UTF-8',
+  keymap: 'us',
+  users: []
+};
 
 export class ArchInstallPlugin extends BasePlugin {
   constructor(config, logger, security) {
     super('arch-install', config, logger, security);
     this.description = 'Arch Linux installation automation';
-    this.allowDiskOperations = config.plugins?.archInstall?.allowDiskOperations || true;
+    const settings = pluginConfig(config, 'archInstall');
+    this.allowDiskOperations = settings.allowDiskOperations ?? true;
+    this.requireDeviceConfirmation = settings.requireDeviceConfirmation ?? false;
+    this.packageSource = {
+      localMirror: settings.localMirror || null,
+      cacheDirs: settings.packageCacheDirs || [],
+      offline: settings.offline ?? false
+    };
     
     this.commandExecutor = new CommandExecutor(
-      config.security || {},
+      config.get?.('security') || config.security || {},
       logger,
       security
     );
+    this.environment = getEnvironmentInfo(logger);
+    this.isoMedia = new IsoMedia(this.commandExecutor, logger, settings);
+    this.loopImage = new LoopImage(this.commandExecutor, logger);
+    this.deviceWipe = new DeviceWipe(this.commandExecutor, logger);
+    this.dependencies = [
+      'sgdisk', 'fdisk', 'wipefs', 'mkfs.fat', 'mkfs.ext4', 'mkfs.btrfs', 'mkfs.xfs', 'mkfs.f2fs', 'mkswap',
+      'mount', 'umount', 'swapon', 'lsblk', 'findmnt', 'modprobe', 'mdadm',
+      'pacstrap', 'genfstab', 'arch-chroot', 'losetup', 'qemu-img', 'blkdiscard', 'shred', 'nvme'
+    ];
+    // arch-install-scripts is only present on the live ISO or when installed explicitly;
+    // losetup and qemu-img are only needed for image targets, the btrfs, xfs and f2fs
+    // tools only for those filesystems, mdadm only for RAID and nvme-cli only for NVMe formats
+    this.optionalDependencies = ['pacstrap', 'genfstab', 'arch-chroot', 'losetup', 'qemu-img', 'mkfs.btrfs', 'mkfs.xfs', 'mkfs.f2fs', 'mdadm', 'nvme'];
     
     this.installState = {
-      currentStep: null,
-      targetMount: '/mnt',
-      lastSnapshot: null
+      targetMount: DEFAULT_INSTALL_ROOT,
+      lastSnapshot: null,
+      // The partition plan last carried out: { id, device, scheme, partitions, packages },
+      // plus { devices, raid } for a RAID install
+      partitionPlan: null,
+      // Set while installing into a disk image: { file, device, mount, temporaryMount }
+      image: null,
+      // What arch_configure_hibernation set up: { swap, device, offset, params }
+      hibernation: null
     };
+    // Step-level status of the install, persisted across restarts
+    this.progress = new InstallProgress(settings.stateFile || null, logger);
     
     this.initializeTools();
     this.initializeResources();
@@ -27,6 +91,8 @@ export class ArchInstallPlugin extends BasePlugin {
 
   async initialize() {
     await super.initialize();
+    await this.progress.load();
+    this.setTargetMount(this.progress.state.context.targetMount || this.installState.targetMount);
     
     if (!this.allowDiskOperations) {
       this.logger.warn('Disk operations are disabled for Arch install plugin');
@@ -37,14 +103,18 @@ export class ArchInstallPlugin extends BasePlugin {
     this.tools = [
       this.createTool(
         'arch_partition_disk',
-        'Partition a disk for Arch Linux installation',
+        'Partition a disk for Arch Linux installation. Without confirmPlan this only returns the plan: the resulting partition table and the exact commands',
         {
           type: 'object',
           properties: {
             device: {
               type: 'string',
-              description: 'Device path (e.g., /dev/sda)',
-              pattern: '^/dev/[a-z]+$'
+              description: 'Device path (e.g., /dev/sda, or the loop device from arch_install_target)',
+              pattern: '^/dev/([a-z]+|loop[0-9]+)$'
+            },
+            confirmDevice: {
+              type: 'string',
+              description: 'The disk\'s "confirmation" text (model and size) from arch_list_disks; checked against the device before anything is written'
             },
             scheme: {
               type: 'string',
@@ -61,6 +131,45 @@ export class ArchInstallPlugin extends BasePlugin {
               description: 'Root partition size (e.g., 50G, or "remaining" for all space)',
               default: 'remaining'
             },
+            filesystem: {
+              type: 'string',
+              enum: DATA_FILESYSTEMS,
+              description: 'Root filesystem; its mkfs tool and kernel support are checked in the plan',
+              default: 'ext4'
+            },
+            layout: {
+              type: 'array',
+              description: 'Mount points with sizes instead of swapSize/rootSize, e.g. / 60G, /home remaining, /var 20G, swap 8G. Must include /; one entry may take the remaining space; filesystem defaults to the filesystem argument',
+              items: {
+                type: 'object',
+                properties: {
+                  mountpoint: { type: 'string', description: 'Absolute path, or "swap"' },
+                  size: { type: 'string', description: 'e.g. 20G, or "remaining"' },
+                  filesystem: { type: 'string', enum: DATA_FILESYSTEMS }
+                },
+                required: ['mountpoint', 'size']
+              }
+            },
+            hibernate: {
+              type: 'boolean',
+              description: 'Size swap to hold all of RAM (when swapSize is not given) and check the layout can hibernate',
+              default: false
+            },
+            raidDevices: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Further disks to build an mdadm array with, together with device; the layout is then created on /dev/md0'
+            },
+            raidLevel: {
+              type: 'string',
+              enum: RAID_LEVELS,
+              description: 'RAID level when raidDevices is given',
+              default: 'raid1'
+            },
+            confirmPlan: {
+              type: 'string',
+              description: 'The id of the plan returned by a previous call; the disk is only written when the plan is still the same'
+            },
             dryRun: {
               type: 'boolean',
               description: 'Preview operations without executing',
@@ -79,8 +188,7 @@ export class ArchInstallPlugin extends BasePlugin {
           properties: {
             target: {
               type: 'string',
-              description: 'Mount point for installation',
-              default: '/mnt'
+              description: 'Mount point for installation (default: the current install target)'
             },
             packages: {
               type: 'array',
@@ -91,9 +199,23 @@ export class ArchInstallPlugin extends BasePlugin {
             mirror: {
               type: 'string',
               description: 'Pacman mirror URL'
+            },
+            localMirror: {
+              type: 'string',
+              description: 'Directory with a mirror copy laid out as $repo/os/$arch (USB stick, NFS share); default: plugins.archInstall.localMirror'
+            },
+            cacheDirs: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Package cache directories to reuse before downloading; default: plugins.archInstall.packageCacheDirs'
+            },
+            offline: {
+              type: 'boolean',
+              description: 'Never touch the network: without localMirror, install from a repository built from cacheDirs'
             }
           }
-        }
+        },
+        { timeout: 2700000 } // 45 minutes: pacstrap alone may take 30
       ),
 
       this.createTool(
@@ -113,14 +235,38 @@ export class ArchInstallPlugin extends BasePlugin {
             },
             locale: {
               type: 'string',
-              description: 'System locale',
+              description: 'System locale (LANG)',
               default: 'en_US.UTF-8'
             },
+            locales: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Further locales to generate, e.g. ["de_DE.UTF-8", "en_GB.UTF-8"]'
+            },
+            localeOverrides: {
+              type: 'object',
+              description: `locale.conf variables besides LANG, e.g. {"LC_TIME": "en_GB.UTF-8"}; their locales are generated too. Supported: ${LC_VARIABLES.join(', ')}`
+            },
             keymap: {
               type: 'string',
-              description: 'Console keymap',
+              description: 'Console keymap (vconsole.conf KEYMAP)',
               default: 'us'
             },
+            font: {
+              type: 'string',
+              description: 'Console font from /usr/share/kbd/consolefonts (vconsole.conf FONT), e.g. ter-v22n with terminus-font installed'
+            },
+            x11Keymap: {
+              type: 'object',
+              description: 'Keyboard for X11 and Wayland sessions: {layout, model, variant, options}, e.g. {"layout": "de", "options": "ctrl:nocaps"}',
+              properties: {
+                layout: { type: 'string' },
+                model: { type: 'string' },
+                variant: { type: 'string' },
+                options: { type: 'string' }
+              },
+              required: ['layout']
+            },
             users: {
               type: 'array',
               items: {
@@ -132,11 +278,21 @@ export class ArchInstallPlugin extends BasePlugin {
                     items: { type: 'string' },
                     default: ['wheel']
                   },
-                  shell: { type: 'string', default: '/bin/bash' }
+                  shell: { type: 'string', default: '/bin/bash' },
+                  password: { type: 'string', description: 'Hashed here (SHA-512-crypt) and written to the target\'s /etc/shadow' },
+                  passwordHash: { type: 'string', description: 'A ready crypt(3) hash instead, e.g. from mkpasswd' }
                 },
                 required: ['username']
               },
               description: 'Users to create'
+            },
+            rootPassword: {
+              type: 'string',
+              description: 'Password for root, hashed here; without it root stays locked'
+            },
+            rootPasswordHash: {
+              type: 'string',
+              description: 'A ready crypt(3) hash for root instead'
             }
           },
           required: ['hostname', 'timezone']
@@ -152,7 +308,7 @@ export class ArchInstallPlugin extends BasePlugin {
             type: {
               type: 'string',
               enum: ['grub', 'systemd-boot'],
-              description: 'Bootloader type'
+              description: 'Bootloader type (defaults to systemd-boot on UEFI, grub on BIOS)'
             },
             device: {
               type: 'string',
@@ -160,11 +316,9 @@ export class ArchInstallPlugin extends BasePlugin {
             },
             target: {
               type: 'string',
-              description: 'Target mount point',
-              default: '/mnt'
+              description: 'Target mount point (default: the current install target)'
             }
-          },
-          required: ['type']
+          }
         }
       ),
 
@@ -185,8 +339,7 @@ export class ArchInstallPlugin extends BasePlugin {
             },
             target: {
               type: 'string',
-              description: 'Mount point',
-              default: '/mnt'
+              description: 'Mount point (default: the current install target)'
             }
           },
           required: ['device', 'scheme']
@@ -225,8 +378,7 @@ export class ArchInstallPlugin extends BasePlugin {
           properties: {
             target: {
               type: 'string',
-              description: 'Installation target',
-              default: '/mnt'
+              description: 'Installation target (default: the current install target)'
             },
             reboot: {
               type: 'boolean',
@@ -235,6 +387,281 @@ export class ArchInstallPlugin extends BasePlugin {
             }
           }
         }
+      ),
+
+      this.createTool(
+        'arch_download_iso',
+        'Download the latest Arch ISO and its signature, then verify checksum and PGP signature',
+        {
+          type: 'object',
+          properties: {}
+        },
+        { timeout: 3600000 }
+      ),
+
+      this.createTool(
+        'arch_verify_iso',
+        'Verify an ISO against the published SHA-256 checksum and its PGP signature',
+        {
+          type: 'object',
+          properties: {
+            path: {
+              type: 'string',
+              description: 'ISO file (signature expected at <path>.sig)'
+            },
+            sha256: {
+              type: 'string',
+              description: 'Expected checksum (default: looked up in the current sha256sums.txt)',
+              pattern: '^[0-9a-f]{64}$'
+            }
+          },
+          required: ['path']
+        }
+      ),
+
+      this.createTool(
+        'arch_write_usb',
+        'Write a verified ISO to a USB device, destroying its contents',
+        {
+          type: 'object',
+          properties: {
+            iso: {
+              type: 'string',
+              description: 'ISO file to write'
+            },
+            device: {
+              type: 'string',
+              description: 'Whole-disk device path (e.g., /dev/sdb)',
+              pattern: '^/dev/[a-z]+$'
+            },
+            confirmDevice: {
+              type: 'string',
+              description: 'The disk\'s "confirmation" text (model and size) from arch_list_disks; checked against the device before anything is written'
+            },
+            allowFixedDisk: {
+              type: 'boolean',
+              description: 'Permit devices that are neither removable nor on USB',
+              default: false
+            },
+            verify: {
+              type: 'boolean',
+              description: 'Read the device back and compare it with the ISO',
+              default: true
+            },
+            dryRun: {
+              type: 'boolean',
+              description: 'Preview operations without executing',
+              default: false
+            }
+          },
+          required: ['iso', 'device']
+        },
+        { timeout: 3600000 }
+      ),
+
+      this.createTool(
+        'arch_install_target',
+        'Choose what the install tools write to: a real disk mounted at /mnt, or a disk image file attached as a loop device',
+        {
+          type: 'object',
+          properties: {
+            type: {
+              type: 'string',
+              enum: ['disk', 'image'],
+              description: 'disk: install to a real disk; image: create (if missing) and attach an image file, then install into it'
+            },
+            image: {
+              type: 'string',
+              description: 'Image file (required for type image)'
+            },
+            size: {
+              type: 'string',
+              description: 'Size of a newly created image (e.g., 8G); the file is sparse',
+              default: '8G'
+            },
+            target: {
+              type: 'string',
+              description: 'Mount point (default: /mnt for disk, a new temporary directory for image)'
+            }
+          },
+          required: ['type']
+        }
+      ),
+
+      this.createTool(
+        'build_image',
+        'Build a bootable raw or qcow2 disk image from a declarative profile, for libvirt or cloud platforms',
+        {
+          type: 'object',
+          properties: {
+            output: {
+              type: 'string',
+              description: 'Image file to write (before any compression suffix)'
+            },
+            profile: {
+              type: 'object',
+              description: 'What to install; every field is optional',
+              properties: {
+                size: { type: 'string', description: 'Disk size (default: 8G)' },
+                scheme: { type: 'string', enum: ['uefi', 'bios'], description: 'Partition scheme (default: uefi)' },
+                swapSize: { type: 'string', description: 'Swap partition size (default: 512M)' },
+                filesystem: { type: 'string', enum: DATA_FILESYSTEMS, description: 'Root filesystem (default: ext4)' },
+                layout: {
+                  type: 'array',
+                  items: { type: 'object' },
+                  description: 'Mount points with sizes, as for arch_partition_disk; replaces swapSize'
+                },
+                bootloader: { type: 'string', enum: ['grub', 'systemd-boot'], description: 'Default: systemd-boot for uefi, grub for bios' },
+                packages: { type: 'array', items: { type: 'string' }, description: 'Packages for pacstrap' },
+                mirror: { type: 'string', description: 'Pacman mirror URL' },
+                hostname: { type: 'string' },
+                timezone: { type: 'string' },
+                locale: { type: 'string' },
+                locales: { type: 'array', items: { type: 'string' } },
+                localeOverrides: { type: 'object' },
+                keymap: { type: 'string' },
+                font: { type: 'string' },
+                x11Keymap: { type: 'object' },
+                users: {
+                  type: 'array',
+                  items: {
+                    type: 'object',
+                    properties: {
+                      username: { type: 'string' },
+                      groups: { type: 'array', items: { type: 'string' } },
+                      shell: { type: 'string' },
+                      password: { type: 'string' },
+                      passwordHash: { type: 'string' }
+                    },
+                    required: ['username']
+                  }
+                },
+                rootPassword: { type: 'string' },
+                rootPasswordHash: { type: 'string' }
+              }
+            },
+            profileFile: {
+              type: 'string',
+              description: 'YAML file with the same fields; inline profile fields override it'
+            },
+            format: {
+              type: 'string',
+              enum: ['raw', 'qcow2'],
+              default: 'qcow2'
+            },
+            compress: {
+              type: 'string',
+              enum: ['none', 'zstd', 'xz', 'gzip'],
+              default: 'none'
+            },
+            checksum: {
+              type: 'boolean',
+              description: 'Write a <image>.sha256 file next to the image',
+              default: true
+            },
+            overwrite: {
+              type: 'boolean',
+              description: 'Replace an existing output file',
+              default: false
+            },
+            dryRun: {
+              type: 'boolean',
+              description: 'Show the resolved profile and build steps without building',
+              default: false
+            }
+          },
+          required: ['output']
+        }
+      ),
+
+      this.createTool(
+        'arch_configure_hibernation',
+        'Set up hibernation in the installed system: resume hook, resume= (and resume_offset= for a swap file) kernel parameters, then check the result',
+        {
+          type: 'object',
+          properties: {
+            swap: {
+              type: 'string',
+              enum: ['auto', 'partition', 'file'],
+              description: 'Resume from the swap partition in fstab, or from /swapfile (created when missing); auto prefers the partition',
+              default: 'auto'
+            },
+            swapfileSize: {
+              type: 'string',
+              description: 'Size of a new /swapfile (default: the size of RAM)'
+            },
+            target: {
+              type: 'string',
+              description: 'Target mount point (default: the current install target)'
+            }
+          }
+        },
+        { timeout: 1800000 } // writing a swap file the size of RAM can take a while
+      ),
+
+      this.createTool(
+        'arch_install_verify',
+        'Check the installed system before rebooting into it: initramfs per kernel, bootloader entries, fstab, network service, users and locale',
+        {
+          type: 'object',
+          properties: {
+            target: {
+              type: 'string',
+              description: 'Target mount point (default: the current install target)'
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'wipe_device',
+        'Erase a disk or partition with blkdiscard, shred or an NVMe format. Refuses devices in use (mounted in any mount namespace, active swap, ' +
+          'holding an open mapping or array) and the system disk. Run with dryRun first: it classifies the device and returns its confirmation text',
+        {
+          type: 'object',
+          properties: {
+            device: {
+              type: 'string',
+              description: 'Disk, partition or NVMe namespace (e.g., /dev/sdb, /dev/sdb2, /dev/nvme1n1)',
+              pattern: '^/dev/[A-Za-z0-9/_.:-]+$'
+            },
+            method: {
+              type: 'string',
+              enum: WIPE_METHODS,
+              description: 'discard: TRIM the whole device (fast; flash may still hold the data); secure-discard: secure TRIM where supported; ' +
+                'overwrite: shred with random data (slow, any device); nvme-user-data / nvme-crypto: NVMe format with user-data or cryptographic erase',
+              default: 'discard'
+            },
+            passes: {
+              type: 'integer',
+              description: 'Random-data passes for overwrite',
+              minimum: 1,
+              maximum: 7,
+              default: 1
+            },
+            zero: {
+              type: 'boolean',
+              description: 'Finish an overwrite with a pass of zeros, so the device reads back blank',
+              default: true
+            },
+            confirmDevice: {
+              type: 'string',
+              description: 'The "confirmation" text (model and size) from a dryRun; required to wipe, checked against the device first'
+            },
+            allowFixedDisk: {
+              type: 'boolean',
+              description: 'Permit devices that are neither removable nor on USB',
+              default: false
+            },
+            dryRun: {
+              type: 'boolean',
+              description: 'Classify the device and show the command without wiping',
+              default: false
+            }
+          },
+          required: ['device']
+        },
+        { timeout: 172800000 } // a few overwrite passes of a large spinning disk take a day or more
       )
     ];
   }
@@ -244,7 +671,7 @@ export class ArchInstallPlugin extends BasePlugin {
       this.createResource(
         'arch://installation/status',
         'Installation Status',
-        'Current Arch Linux installation progress',
+        'Each install step as pending, running, succeeded or failed, with times and errors; kept across restarts, subscribe for changes',
         'application/json'
       ),
       this.createResource(
@@ -264,29 +691,54 @@ export class ArchInstallPlugin extends BasePlugin {
 
   async executeTool(toolName, args) {
     return this.withErrorHandling(async () => {
-      switch (toolName) {
-        case 'arch_partition_disk':
-          return this.handlePartitionDisk(args);
-        case 'arch_install_base':
-          return this.handleInstallBase(args);
-        case 'arch_configure_system':
-          return this.handleConfigureSystem(args);
-        case 'arch_install_bootloader':
-          return this.handleInstallBootloader(args);
-        case 'arch_mount_system':
-          return this.handleMountSystem(args);
-        case 'arch_list_disks':
-          return this.handleListDisks(args);
-        case 'arch_installation_status':
-          return this.handleInstallationStatus(args);
-        case 'arch_complete_installation':
-          return this.handleCompleteInstallation(args);
-        default:
-          throw new Error(`Unknown tool: ${toolName}`);
+      try {
+        return await this.runTool(toolName, args);
+      } catch (error) {
+        this.progress.failRunning(error);
+        throw error;
       }
     }, toolName);
   }
 
+  async runTool(toolName, args) {
+    switch (toolName) {
+      case 'arch_partition_disk':
+        return this.handlePartitionDisk(args);
+      case 'arch_install_base':
+        return this.handleInstallBase(args);
+      case 'arch_configure_system':
+        return this.handleConfigureSystem(args);
+      case 'arch_install_bootloader':
+        return this.handleInstallBootloader(args);
+      case 'arch_mount_system':
+        return this.handleMountSystem(args);
+      case 'arch_list_disks':
+        return this.handleListDisks(args);
+      case 'arch_installation_status':
+        return this.handleInstallationStatus(args);
+      case 'arch_complete_installation':
+        return this.handleCompleteInstallation(args);
+      case 'arch_download_iso':
+        return this.handleDownloadIso(args);
+      case 'arch_verify_iso':
+        return this.handleVerifyIso(args);
+      case 'arch_write_usb':
+        return this.handleWriteUsb(args);
+      case 'arch_install_target':
+        return this.handleInstallTarget(args);
+      case 'build_image':
+        return this.handleBuildImage(args);
+      case 'arch_configure_hibernation':
+        return this.handleConfigureHibernation(args);
+      case 'arch_install_verify':
+        return this.handleInstallVerify(args);
+      case 'wipe_device':
+        return this.handleWipeDevice(args);
+      default:
+        throw new Error(`Unknown tool: ${toolName}`);
+    }
+  }
+
   async readResource(uri) {
     return this.withErrorHandling(async () => {
       switch (uri) {
@@ -302,6 +754,92 @@ export class ArchInstallPlugin extends BasePlugin {
     }, 'readResource');
   }
 
+  async subscribeResource(uri, listener) {
+    if (uri !== 'arch://installation/status') {
+      return () => {};
+    }
+    return this.progress.subscribe(listener);
+  }
+
+  getToolLocks(toolName, args = {}) {
+    const target = args.target || this.installState.targetMount;
+
+    switch (toolName) {
+      case 'arch_partition_disk':
+        return [args.device, ...(args.raidDevices || [])].filter(Boolean).map(device => LockKeys.device(device));
+      case 'arch_write_usb':
+      case 'wipe_device':
+        return args.device ? [LockKeys.device(args.device)] : [];
+      case 'arch_mount_system':
+        return [
+          ...(args.device ? [LockKeys.device(args.device)] : []),
+          LockKeys.mount(target)
+        ];
+      case 'arch_install_bootloader':
+        return [
+          ...(args.device ? [LockKeys.device(args.device)] : []),
+          LockKeys.mount(target)
+        ];
+      case 'arch_install_base':
+      case 'arch_configure_system':
+      case 'arch_configure_hibernation':
+      case 'arch_install_verify':
+      case 'arch_complete_installation':
+        return [LockKeys.mount(target)];
+      case 'arch_install_target':
+      case 'build_image':
+        return [
+          ...(this.installState.image ? [LockKeys.device(this.installState.image.device)] : []),
+          LockKeys.mount(this.installState.targetMount)
+        ];
+      default:
+        return [];
+    }
+  }
+
+  isIdempotent(toolName) {
+    return ['arch_list_disks', 'arch_installation_status', 'arch_verify_iso', 'arch_install_verify'].includes(toolName);
+  }
+
+  // arch_partition_disk without confirmPlan only returns the plan
+  isReadOnly(toolName, args = {}) {
+    return ['arch_list_disks', 'arch_installation_status', 'arch_verify_iso', 'arch_install_verify'].includes(toolName) ||
+      (toolName === 'arch_partition_disk' && !args.confirmPlan) || super.isReadOnly(toolName, args);
+  }
+
+  disruptsUser(toolName, args = {}) {
+    return toolName === 'arch_complete_installation' && Boolean(args.reboot);
+  }
+
+  // These overwrite whole disks
+  isDestructive(toolName, args = {}) {
+    return !this.isReadOnly(toolName, args) && ['arch_partition_disk', 'arch_write_usb', 'wipe_device'].includes(toolName);
+  }
+
+  inhibitsIdle(toolName, args = {}) {
+    return !this.isReadOnly(toolName, args) && ['arch_partition_disk', 'arch_install_base', 'arch_configure_system',
+      'arch_install_bootloader', 'arch_configure_hibernation', 'arch_complete_installation', 'arch_download_iso', 'arch_write_usb',
+      'build_image', 'wipe_device'].includes(toolName);
+  }
+
+  getAffectedPaths(toolName, args = {}) {
+    const target = args.target || this.installState.targetMount;
+
+    switch (toolName) {
+      case 'arch_install_base':
+        return ['/etc/pacman.d/mirrorlist', path.join(target, 'etc/fstab')];
+      case 'arch_configure_system':
+        return ['localtime', 'locale.gen', 'locale.conf', 'vconsole.conf', 'hostname', 'hosts', 'sudoers',
+          ...(args.x11Keymap ? ['X11/xorg.conf.d/00-keyboard.conf', 'environment'] : []),
+          ...(args.rootPassword || args.rootPasswordHash || (args.users || []).some(user => user.password || user.passwordHash) ? ['shadow'] : [])]
+          .map(file => path.join(target, 'etc', file));
+      case 'arch_configure_hibernation':
+        return ['fstab', 'mkinitcpio.conf', 'default/grub', 'kernel/cmdline'].map(file => path.join(target, 'etc', file));
+      default:
+        return [];
+    }
+  }
+
   async handlePartitionDisk(args) {
     if (!this.allowDiskOperations) {
       throw new Error('Disk operations are disabled');
@@ -309,13 +847,52 @@ export class ArchInstallPlugin extends BasePlugin {
 
     await this.validateArgs(args, this.tools[0].inputSchema);
     
-    const { device, scheme, swapSize = '4G', rootSize = 'remaining', dryRun = false } = args;
+    const {
+      device, scheme, hibernate = false, rootSize = 'remaining', filesystem = 'ext4', layout,
+      raidDevices, raidLevel = 'raid1', confirmDevice, confirmPlan, dryRun = false
+    } = args;
+    const swapSize = args.swapSize || (hibernate ? hibernationSwapSize(os.totalmem()) : '4G');
+    const execute = confirmPlan && !dryRun;
+    const devices = raidDevices ? [device, ...raidDevices] : [device];
     
     // Safety checks
-    await this.validateDevice(device);
+    for (const member of devices) {
+      if (!/^\/dev\/([a-z]+|loop[0-9]+)$/.test(member)) {
+        throw new Error(`Invalid RAID member: ${member}`);
+      }
+      await this.validateDevice(member);
+    }
+    if (execute || confirmDevice) {
+      await this.confirmDevice(device, confirmDevice);
+    }
     
-    if (dryRun) {
-      return this.createTextResult(this.previewPartitionOperations(device, scheme, swapSize, rootSize));
+    // With RAID the EFI partitions live on the member disks, so the array's own layout
+    // is built without one
+    const layoutScheme = raidDevices ? null : scheme;
+    const plan = await this.partitionPlan(device, scheme, layout
+      ? customLayout(layoutScheme, layout, { filesystem })
+      : defaultLayout(layoutScheme, { swapSize, rootSize, filesystem }), raidDevices ? { devices, level: raidLevel } : null);
+    if (hibernate) {
+      const swapBytes = plan.partitions.filter(partition => partition.filesystem === 'swap')
+        .reduce((total, partition) => total + (partition.bytes || 0), 0);
+      plan.checks.push({
+        name: 'swap holds RAM for hibernation',
+        ok: swapBytes >= os.totalmem(),
+        detail: `swap is ${swapBytes} bytes but RAM is ${os.totalmem()}; make swap at least ${hibernationSwapSize(os.totalmem())}, or use a swap file (arch_configure_hibernation)`
+      });
+    }
+    if (!execute) {
+      return this.createTextResult(
+        `${renderPartitionPlan(plan)}\n\nNothing has been written. To carry out this plan, call arch_partition_disk again with the same arguments and confirmPlan: "${plan.id}"`,
+        { plan }
+      );
+    }
+    if (confirmPlan !== plan.id) {
+      throw new Error(`Plan ${confirmPlan} no longer matches ${device} (now ${plan.id}); the disk or the arguments changed, so preview it again`);
+    }
+    const failed = plan.checks.filter(check => !check.ok);
+    if (failed.length > 0) {
+      throw new Error(`Cannot carry out plan ${plan.id}: ${failed.map(check => `${check.name}: ${check.detail}`).join('; ')}`);
     }
     
     // Create snapshot before partitioning
@@ -325,141 +902,493 @@ export class ArchInstallPlugin extends BasePlugin {
     );
     this.installState.lastSnapshot = snapshotId;
     
-    this.installState.currentStep = 'partitioning';
-    
-    // Wipe existing partition table
-    await this.commandExecutor.executeWithSudo('wipefs', ['-a', device]);
+    this.progress.start('partition', { device, scheme, plan: plan.id, ...(plan.raid ? { raid: plan.raid } : {}) });
     
-    if (scheme === 'uefi') {
-      await this.createUEFIPartitions(device, swapSize, rootSize);
-    } else {
-      await this.createBIOSPartitions(device, swapSize, rootSize);
+    // Wipe, partition and format, exactly as previewed
+    for (const [index, step] of plan.commands.entries()) {
+      reportProgress(index, { total: plan.commands.length, message: commandLine(step).split('\n')[0] });
+      const result = await this.commandExecutor.executeWithSudo(step.command, step.args, step.input ? { input: step.input } : {});
+      if (!result.success) {
+        throw new Error(`${commandLine(step)} failed: ${result.stderr}`);
+      }
     }
     
-    // Format partitions
-    await this.formatPartitions(device, scheme);
-    
-    this.installState.currentStep = 'partitioned';
+    this.installState.partitionPlan = {
+      id: plan.id,
+      device: plan.device,
+      scheme,
+      partitions: plan.partitions,
+      packages: plan.packages,
+      ...(plan.raid ? { devices: plan.devices, raid: plan.raid } : {})
+    };
+    this.progress.succeed('partition', { partitions: plan.partitions.map(entry => entry.path) });
     
-    return this.createTextResult(`Successfully partitioned ${device} with ${scheme} scheme`, {
-      device,
+    return this.createTextResult(plan.raid
+      ? `Successfully built ${plan.raid.level} array ${plan.device} over ${devices.join(', ')} with ${scheme} scheme`
+      : `Successfully partitioned ${device} with ${scheme} scheme`, {
+      device: plan.device,
+      ...(plan.raid ? { raid: plan.raid } : {}),
       scheme,
-      swapSize,
+      ...(layout ? { layout } : { swapSize, filesystem }),
+      plan: plan.id,
+      partitions: plan.partitions,
       snapshotId
     });
   }
 
-  async createUEFIPartitions(device, swapSize, rootSize) {
-    // Create GPT partition table
-    await this.commandExecutor.executeWithSudo('sgdisk', ['-o', device]);
-    
-    // EFI partition (512MB)
-    await this.commandExecutor.executeWithSudo('sgdisk', [
-      '-n', '1:0:+512M',
-      '-t', '1:ef00',
-      '-c', '1:EFI',
-      device
-    ]);
-    
-    // Swap partition
-    await this.commandExecutor.executeWithSudo('sgdisk', [
-      '-n', `2:0:+${swapSize}`,
-      '-t', '2:8200',
-      '-c', '2:swap',
-      device
-    ]);
-    
-    // Root partition
-    const rootArgs = rootSize === 'remaining' ? 
-      ['-n', '3:0:0'] : 
-      ['-n', `3:0:+${rootSize}`];
-    
-    await this.commandExecutor.executeWithSudo('sgdisk', [
-      ...rootArgs,
-      '-t', '3:8300',
-      '-c', '3:root',
-      device
-    ]);
-  }
-
-  async createBIOSPartitions(device, swapSize, rootSize) {
-    // Create MBR partition table using fdisk
-    const commands = [
-      'o', // Create new empty DOS partition table
-      'n', 'p', '1', '', `+${swapSize}`, // Swap partition
-      't', '82', // Set type to swap
-      'n', 'p', '2', '', rootSize === 'remaining' ? '' : `+${rootSize}`, // Root partition
-      'w' // Write changes
-    ];
-    
-    const fdiskInput = commands.join('\n') + '\n';
-    
-    await this.commandExecutor.executeWithSudo('fdisk', [device], {
-      input: fdiskInput
-    });
+  // Disk size and identity make a plan specific to the disk it was previewed for
+  async partitionPlan(device, scheme, layout, raid = null) {
+    const plan = raid
+      ? buildRaidPlan(raid.devices, scheme, raid.level, layout, {
+        diskSizes: Object.fromEntries(await Promise.all(raid.devices.map(async member => [member, await this.diskSize(member)]))),
+        identities: Object.fromEntries(await Promise.all(raid.devices.map(async member => [member, await this.deviceConfirmation(member)])))
+      })
+      : buildPartitionPlan(device, scheme, layout, { diskSize: await this.diskSize(device), identity: await this.deviceConfirmation(device) });
+    plan.checks = await this.filesystemChecks(plan.partitions.map(partition => partition.filesystem).filter(Boolean));
+    if (plan.raid) {
+      plan.checks.push({
+        name: 'mdadm installed',
+        ok: Boolean(await this.commandExecutor.toolResolver.resolve('mdadm')),
+        detail: 'mdadm not found; install mdadm'
+      });
+    }
+    return plan;
   }
 
-  async formatPartitions(device, scheme) {
-    if (scheme === 'uefi') {
-      // Format EFI partition
-      await this.commandExecutor.executeWithSudo('mkfs.fat', ['-F32', `${device}1`]);
-      
-      // Format swap
-      await this.commandExecutor.executeWithSudo('mkswap', [`${device}2`]);
-      
-      // Format root
-      await this.commandExecutor.executeWithSudo('mkfs.ext4', ['-F', `${device}3`]);
-    } else {
-      // Format swap
-      await this.commandExecutor.executeWithSudo('mkswap', [`${device}1`]);
-      
-      // Format root
-      await this.commandExecutor.executeWithSudo('mkfs.ext4', ['-F', `${device}2`]);
+  async diskSize(device) {
+    const result = await this.commandExecutor.execute('lsblk', ['-b', '-d', '-n', '-o', 'SIZE', device]);
+    return result.success && /^\d+$/.test(result.stdout.trim()) ? Number(result.stdout.trim()) : null;
+  }
+
+  // Each filesystem needs its mkfs tool here, and a kernel that can mount it for the rest
+  // of the install; xfs and f2fs are modules the live kernel may not have loaded yet
+  async filesystemChecks(filesystems) {
+    const supported = await fs.readFile('/proc/filesystems', 'utf8').catch(() => '');
+    const checks = [];
+    for (const filesystem of new Set(filesystems)) {
+      const { mkfs, package: pkg, module } = FILESYSTEMS[filesystem];
+      checks.push({
+        name: `${mkfs} installed`,
+        ok: Boolean(await this.commandExecutor.toolResolver.resolve(mkfs)),
+        detail: `${mkfs} not found${pkg ? `; install ${pkg}` : ''}`
+      });
+      if (module) {
+        const loaded = supported.split('\n').some(line => line.split('\t').pop() === module);
+        checks.push({
+          name: `kernel supports ${filesystem}`,
+          ok: loaded || (await this.commandExecutor.execute('modprobe', ['-n', '-q', module])).success,
+          detail: `the running kernel has no ${module} module to mount it with`
+        });
+      }
     }
+    return checks;
+  }
+
+  // The options the filesystem is mounted with end up in fstab through genfstab
+  async mountOptionArgs(partition) {
+    const planned = this.installState.partitionPlan?.partitions.find(entry => entry.path === partition);
+    if (planned) {
+      return mountArgs(planned.filesystem);
+    }
+    const result = await this.commandExecutor.execute('lsblk', ['-n', '-o', 'FSTYPE', partition]);
+    return result.success ? mountArgs(result.stdout.trim()) : [];
   }
 
   async handleMountSystem(args) {
     await this.validateArgs(args, this.tools[4].inputSchema);
     
-    const { device, scheme, target = '/mnt' } = args;
+    const { device, scheme, target = this.installState.targetMount } = args;
+    // Swap inside an image would be swap for the host, and would keep the loop device busy
+    const enableSwap = this.installState.image?.device !== device;
+    
+    this.progress.start('mount', { device, target });
     
     // Create mount point
     await this.commandExecutor.executeWithSudo('mkdir', ['-p', target]);
     
+    // A disk partitioned here is mounted as its plan laid it out
+    const planned = this.installState.partitionPlan;
+    const plan = planned?.device === device || planned?.devices?.includes(device) ? planned : null;
+    if (plan) {
+      const mounted = await this.mountPlan(plan, target, { enableSwap });
+      this.setTargetMount(target);
+      this.progress.succeed('mount');
+      return this.createTextResult(`Successfully mounted ${device} to ${target}`, { mounts: mounted });
+    }
+    
     if (scheme === 'uefi') {
       // Mount root
-      await this.commandExecutor.executeWithSudo('mount', [`${device}3`, target]);
+      await this.commandExecutor.executeWithSudo('mount', [...await this.mountOptionArgs(partitionPath(device, 3)), partitionPath(device, 3), target]);
       
       // Create and mount EFI
       await this.commandExecutor.executeWithSudo('mkdir', ['-p', `${target}/boot/efi`]);
-      await this.commandExecutor.executeWithSudo('mount', [`${device}1`, `${target}/boot/efi`]);
+      await this.commandExecutor.executeWithSudo('mount', [...await this.mountOptionArgs(partitionPath(device, 1)), partitionPath(device, 1), `${target}/boot/efi`]);
       
       // Enable swap
-      await this.commandExecutor.executeWithSudo('swapon', [`${device}2`]);
+      if (enableSwap) {
+        await this.commandExecutor.executeWithSudo('swapon', [partitionPath(device, 2)]);
+      }
     } else {
       // Mount root
-      await this.commandExecutor.executeWithSudo('mount', [`${device}2`, target]);
+      await this.commandExecutor.executeWithSudo('mount', [...await this.mountOptionArgs(partitionPath(device, 2)), partitionPath(device, 2), target]);
       
       // Enable swap
-      await this.commandExecutor.executeWithSudo('swapon', [`${device}1`]);
+      if (enableSwap) {
+        await this.commandExecutor.executeWithSudo('swapon', [partitionPath(device, 1)]);
+      }
     }
     
-    this.installState.targetMount = target;
-    this.installState.currentStep = 'mounted';
+    this.setTargetMount(target);
+    this.progress.succeed('mount');
     
     return this.createTextResult(`Successfully mounted ${device} to ${target}`);
   }
 
-  async handleInstallBase(args) {
-    await this.validateArgs(args, this.tools[1].inputSchema);
+  async mountPlan(plan, target, { enableSwap }) {
+    // After a restart of the live system the array has to be assembled again
+    if (plan.raid && !await fs.pathExists(plan.raid.device)) {
+      const result = await this.commandExecutor.executeWithSudo('mdadm', ['--assemble', plan.raid.device, ...plan.raid.members]);
+      if (!result.success) {
+        throw new Error(`Failed to assemble ${plan.raid.device}: ${result.stderr}`);
+      }
+    }
     
-    const { 
-      target = '/mnt', 
+    const mounted = [];
+    for (const partition of mountOrder(plan.partitions)) {
+      const mountpoint = partition.mountpoint === '/' ? target : path.join(target, partition.mountpoint);
+      await this.commandExecutor.executeWithSudo('mkdir', ['-p', mountpoint]);
+      const result = await this.commandExecutor.executeWithSudo('mount', [...mountArgs(partition.filesystem, partition.mountOptions), partition.path, mountpoint]);
+      if (!result.success) {
+        throw new Error(`Failed to mount ${partition.path} on ${mountpoint}: ${result.stderr}`);
+      }
+      mounted.push({ device: partition.path, mountpoint: partition.mountpoint, filesystem: partition.filesystem });
+    }
+    if (enableSwap) {
+      for (const partition of plan.partitions.filter(entry => entry.filesystem === 'swap')) {
+        await this.commandExecutor.executeWithSudo('swapon', [partition.path]);
+        mounted.push({ device: partition.path, mountpoint: '[swap]', filesystem: 'swap' });
+      }
+    }
+    return mounted;
+  }
+
+  // genfstab writes what is mounted, so check every partition of the plan made it into
+  // fstab; swap that was not switched on (as for images) is added by UUID
+  async completeFstab(plan, fstab) {
+    const entries = this.fstabEntries(fstab);
+    const missing = mountOrder(plan.partitions)
+      .filter(partition => !entries.some(fields => fields[1] === partition.mountpoint))
+      .map(partition => partition.mountpoint);
+    if (missing.length > 0) {
+      throw new Error(`${missing.join(', ')} not mounted under the install target; run arch_mount_system first`);
+    }
+
+    const added = [];
+    for (const partition of plan.partitions.filter(entry => entry.filesystem === 'swap')) {
+      const result = await this.commandExecutor.execute('blkid', ['-s', 'UUID', '-o', 'value', partition.path]);
+      const uuid = result.stdout.trim();
+      if (result.success && uuid && !entries.some(fields => fields[0] === `UUID=${uuid}`)) {
+        added.push(`# ${partition.path}\nUUID=${uuid}\tnone\tswap\tdefaults\t0 0\n`);
+      }
+    }
+    return added.length > 0 ? `${fstab.replace(/\n*$/, '\n')}\n${added.join('\n')}` : fstab;
+  }
+
+  // A temporary pacman.conf for pacstrap (-C) when packages come from local media; the
+  // target keeps the stock pacman.conf and the host's mirrorlist. -c makes pacstrap use the
+  // cache directories from that file instead of the target's own cache
+  async localPackageSource({ localMirror, cacheDirs, offline }) {
+    if (!localMirror && cacheDirs.length === 0) {
+      if (offline) {
+        throw new Error('An offline install needs localMirror or cacheDirs');
+      }
+      return null;
+    }
+    if (localMirror && !await fs.pathExists(path.join(localMirror, 'core/os/x86_64/core.db'))) {
+      throw new Error(`${localMirror} is not a mirror copy: core/os/x86_64/core.db is missing`);
+    }
+    for (const dir of cacheDirs) {
+      if (!await fs.pathExists(dir)) {
+        throw new Error(`Package cache not found: ${dir}`);
+      }
+    }
+    
+    const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'mcp-pacstrap-'));
+    try {
+      let offlineRepo = null;
+      if (offline && !localMirror) {
+        offlineRepo = await this.buildCacheRepo(dir, cacheDirs);
+      }
+      const confPath = path.join(dir, 'pacman.conf');
+      await fs.writeFile(confPath, localInstallConf(await fs.readFile(PACMAN_CONF, 'utf8'), { mirror: localMirror, cacheDirs, offlineRepo }));
+      
+      return {
+        dir,
+        args: ['-C', confPath, ...(cacheDirs.length > 0 ? ['-c'] : [])],
+        description: {
+          ...(localMirror ? { localMirror } : {}),
+          ...(cacheDirs.length > 0 ? { cacheDirs } : {}),
+          offline: Boolean(offline),
+          ...(offlineRepo ? { cacheRepoPackages: offlineRepo.packages } : {})
+        }
+      };
+    } catch (error) {
+      await fs.remove(dir);
+      throw error;
+    }
+  }
+  
+  // A repository made of whatever the caches hold, so an air-gapped install needs nothing
+  // but a copied /var/cache/pacman/pkg. Packages keep their detached signatures where the
+  // cache has them; ones without are installed unverified
+  async buildCacheRepo(dir, cacheDirs) {
+    const packages = [];
+    for (const cacheDir of cacheDirs) {
+      for (const name of await fs.readdir(cacheDir)) {
+        if (/\.pkg\.tar\.(zst|xz|gz)$/.test(name)) {
+          packages.push(path.join(cacheDir, name));
+        }
+      }
+    }
+    if (packages.length === 0) {
+      throw new Error(`No packages found in ${cacheDirs.join(', ')}`);
+    }
+    
+    reportProgress(0, { message: `Indexing ${packages.length} cached packages` });
+    // -p keeps the newest version when the caches hold several
+    const result = await this.commandExecutor.execute('repo-add', ['-q', '-p', path.join(dir, 'offline.db.tar.gz'), ...packages], {
+      timeout: 600000
+    });
+    if (!result.success) {
+      throw new Error(`repo-add failed: ${result.stderr}`);
+    }
+    return { name: 'offline', dir, sigLevel: 'Optional TrustedOnly', packages: packages.length };
+  }
+
+  // The fields of each fstab line: spec, mount point, type, options
+  fstabEntries(fstab) {
+    return fstab.split('\n')
+      .filter(line => line.trim() && !line.trim().startsWith('#'))
+      .map(line => line.trim().split(/\s+/));
+  }
+
+  // The initramfs assembles the arrays mdadm.conf lists with the mdadm_udev hook, which
+  // has to run before the root filesystem is mounted
+  async configureRaid(target) {
+    const scan = await this.commandExecutor.executeWithSudo('mdadm', ['--detail', '--scan']);
+    if (!scan.success) {
+      throw new Error(`mdadm --detail --scan failed: ${scan.stderr}`);
+    }
+    const confPath = path.join(target, 'etc/mdadm.conf');
+    const conf = await fs.readFile(confPath, 'utf8').catch(() => '');
+    const arrays = scan.stdout.split('\n').map(line => line.trim()).filter(line => line.startsWith('ARRAY') && !conf.includes(line));
+    if (arrays.length > 0) {
+      await fs.writeFile(confPath, `${conf.replace(/\n*$/, conf ? '\n' : '')}${arrays.join('\n')}\n`);
+    }
+
+    await this.addInitramfsHook(target, 'mdadm_udev');
+  }
+
+  // Adds a hook before filesystems in the target's mkinitcpio.conf and rebuilds the
+  // initramfs when that changed anything
+  async addInitramfsHook(target, hook) {
+    const mkinitcpioPath = path.join(target, 'etc/mkinitcpio.conf');
+    const mkinitcpio = await fs.readFile(mkinitcpioPath, 'utf8');
+    const updated = addHook(mkinitcpio, hook);
+    if (updated !== mkinitcpio) {
+      await fs.writeFile(mkinitcpioPath, updated);
+      const result = await this.archChroot(target, 'mkinitcpio -P');
+      if (!result.success) {
+        throw new Error(`mkinitcpio -P failed: ${result.stderr}`);
+      }
+    }
+  }
+
+  async handleConfigureHibernation(args) {
+    await this.validateArgs(args, this.tools[13].inputSchema);
+    
+    const { swap = 'auto', swapfileSize = hibernationSwapSize(os.totalmem()), target = this.installState.targetMount } = args;
+    
+    if (target === this.installState.targetMount) {
+      this.progress.start('hibernation', { swap });
+    }
+    const fstab = await fs.readFile(path.join(target, 'etc/fstab'), 'utf8');
+    const partition = this.fstabEntries(fstab).find(fields => fields[2] === 'swap' && fields[0].startsWith('UUID='));
+    if (swap === 'partition' && !partition) {
+      throw new Error('The target has no swap partition in fstab; use swap: "file"');
+    }
+    
+    const hibernation = swap === 'file' || !partition
+      ? await this.prepareSwapfile(target, swapfileSize, fstab)
+      : { swap: 'partition', device: partition[0], offset: null, params: [`resume=${partition[0]}`] };
+    
+    // An initramfs built with the systemd hook resumes on its own from resume=
+    const hooks = parseHooks(await fs.readFile(path.join(target, 'etc/mkinitcpio.conf'), 'utf8'));
+    if (!hooks.includes('systemd')) {
+      await this.addInitramfsHook(target, 'resume');
+    }
+    
+    await this.setTargetKernelParams(target, {
+      add: hibernation.params,
+      remove: hibernation.offset === null ? ['resume_offset'] : []
+    });
+    
+    this.installState.hibernation = hibernation;
+    const checks = await this.hibernationChecks(target);
+    const failed = checks.filter(check => !check.ok);
+    if (failed.length > 0) {
+      this.progress.fail('hibernation', new Error(`${failed.length} check(s) failed: ${failed.map(check => check.name).join(', ')}`));
+    } else {
+      this.progress.succeed('hibernation', { swap: hibernation.swap });
+    }
+    
+    return this.createTextResult([
+      failed.length === 0
+        ? `Hibernation configured: resume from ${hibernation.swap === 'file' ? '/swapfile' : hibernation.device}`
+        : `Hibernation configured, but ${failed.length} check(s) failed`,
+      ...checks.map(check => `${check.ok ? 'ok' : 'FAILED'}: ${check.name}${check.ok ? '' : ` (${check.detail})`}`)
+    ].join('\n'), { hibernation, checks });
+  }
+
+  // A swap file on the root filesystem; resume= names that filesystem and resume_offset=
+  // where the file starts on it
+  async prepareSwapfile(target, size, fstab) {
+    const root = await this.commandExecutor.execute('findmnt', ['-n', '-o', 'FSTYPE,UUID', target]);
+    const [fstype, uuid] = root.stdout.trim().split(/\s+/);
+    if (!root.success || !uuid) {
+      throw new Error(`Cannot find the filesystem mounted on ${target}`);
+    }
+    
+    // btrfs swap files have to be NOCOW and uncompressed, which mkswapfile takes care of
+    if (!await fs.pathExists(path.join(target, 'swapfile'))) {
+      const create = fstype === 'btrfs'
+        ? `btrfs filesystem mkswapfile --size ${size.toLowerCase()} --uuid clear /swapfile`
+        : `dd if=/dev/zero of=/swapfile bs=1M count=${Math.ceil(parseSize(size) / 1024 ** 2)} status=none && chmod 600 /swapfile && mkswap /swapfile`;
+      const result = await this.archChroot(target, create);
+      if (!result.success) {
+        throw new Error(`Failed to create /swapfile: ${result.stderr}`);
+      }
+    }
+    if (!this.fstabEntries(fstab).some(fields => fields[0] === '/swapfile')) {
+      await fs.appendFile(path.join(target, 'etc/fstab'), '\n/swapfile\tnone\tswap\tdefaults\t0 0\n');
+    }
+    
+    const offset = await this.swapfileOffset(target, fstype);
+    return { swap: 'file', device: `UUID=${uuid}`, offset, params: [`resume=UUID=${uuid}`, `resume_offset=${offset}`] };
+  }
+
+  async swapfileOffset(target, fstype) {
+    const result = await this.archChroot(target, fstype === 'btrfs'
+      ? 'btrfs inspect-internal map-swapfile -r /swapfile'
+      : 'filefrag -v /swapfile');
+    if (!result.success) {
+      throw new Error(`Cannot find the offset of /swapfile: ${result.stderr}`);
+    }
+    return fstype === 'btrfs' ? Number(result.stdout.trim()) : parseFilefragOffset(result.stdout);
+  }
+
+  // Edits the kernel command line of the target's bootloader and regenerates what it reads
+  async setTargetKernelParams(target, edit) {
+    const cmdline = new BootCmdline(this.commandExecutor, this.logger, { root: target });
+    const current = await cmdline.read().catch(() => {
+      throw new Error('No bootloader configuration found in the target; run arch_install_bootloader first');
+    });
+    for (const change of cmdline.plan(current, edit)) {
+      await fs.writeFile(change.path, change.after);
+    }
+    
+    const regenerate = { grub: 'grub-mkconfig -o /boot/grub/grub.cfg', uki: 'mkinitcpio -P' }[current.type];
+    if (regenerate) {
+      const result = await this.archChroot(target, regenerate);
+      if (!result.success) {
+        throw new Error(`${regenerate} failed: ${result.stderr}`);
+      }
+    }
+    // The other RAID members boot from copies of the loader entries
+    const raid = this.installState.partitionPlan?.raid && this.installState.partitionPlan;
+    if (current.type === 'systemd-boot') {
+      for (const esp of this.secondaryEsps(raid)) {
+        await this.archChroot(target, `cp -r /boot/efi/loader/. ${esp}/loader/`);
+      }
+    }
+  }
+
+  // Whether the target will resume: swap in fstab and large enough, the resume hook in
+  // a rebuilt initramfs, the parameters in what the bootloader reads, and a current offset
+  async hibernationChecks(target) {
+    const hibernation = this.installState.hibernation;
+    if (!hibernation) {
+      return [{ name: 'hibernation configured', ok: false, detail: 'run arch_configure_hibernation' }];
+    }
+    const checks = [];
+    const check = (name, ok, detail) => checks.push({ name, ok: Boolean(ok), detail });
+    
+    const fstab = this.fstabEntries(await fs.readFile(path.join(target, 'etc/fstab'), 'utf8').catch(() => ''));
+    const spec = hibernation.swap === 'file' ? '/swapfile' : hibernation.device;
+    check('swap in fstab', fstab.some(fields => fields[0] === spec && fields[2] === 'swap'), `${spec} has no swap line in /etc/fstab`);
+    
+    let swapBytes = 0;
+    if (hibernation.swap === 'file') {
+      swapBytes = (await fs.stat(path.join(target, 'swapfile')).catch(() => ({ size: 0 }))).size;
+    } else {
+      const device = await this.commandExecutor.execute('blkid', ['-U', hibernation.device.slice('UUID='.length)]);
+      const size = device.success ? await this.diskSize(device.stdout.trim()) : null;
+      swapBytes = size || 0;
+    }
+    check('swap holds RAM', swapBytes >= os.totalmem(), `swap is ${swapBytes} bytes, RAM ${os.totalmem()}`);
+    
+    const mkinitcpioPath = path.join(target, 'etc/mkinitcpio.conf');
+    const hooks = parseHooks(await fs.readFile(mkinitcpioPath, 'utf8').catch(() => ''));
+    check('resume hook', hooks.includes('resume') || hooks.includes('systemd'), 'HOOKS has neither resume nor systemd');
+    
+    const configured = (await fs.stat(mkinitcpioPath).catch(() => null))?.mtimeMs || 0;
+    const images = (await fs.readdir(path.join(target, 'boot')).catch(() => [])).filter(name => /^initramfs-.*\.img$/.test(name));
+    const stale = [];
+    for (const image of images) {
+      if ((await fs.stat(path.join(target, 'boot', image))).mtimeMs < configured) {
+        stale.push(image);
+      }
+    }
+    check('initramfs rebuilt', images.length > 0 && stale.length === 0,
+      images.length === 0 ? 'no initramfs in /boot' : `older than mkinitcpio.conf: ${stale.join(', ')}`);
+    
+    const cmdline = new BootCmdline(this.commandExecutor, this.logger, { root: target });
+    const current = await cmdline.read().catch(() => ({ type: null, files: [] }));
+    const missing = current.files.filter(file => !hibernation.params.every(param => file.params.includes(param)));
+    check('kernel parameters', current.files.length > 0 && missing.length === 0,
+      current.files.length === 0 ? 'no bootloader configuration found' : `${hibernation.params.join(' ')} missing from ${missing.map(file => file.path).join(', ')}`);
+    if (current.type === 'grub') {
+      const grubCfg = await fs.readFile(path.join(target, 'boot/grub/grub.cfg'), 'utf8').catch(() => '');
+      check('grub.cfg regenerated', hibernation.params.every(param => grubCfg.includes(param)), 'grub.cfg lacks the resume parameters; run grub-mkconfig');
+    }
+    
+    if (hibernation.swap === 'file') {
+      const root = await this.commandExecutor.execute('findmnt', ['-n', '-o', 'FSTYPE', target]);
+      const offset = await this.swapfileOffset(target, root.stdout.trim()).catch(() => null);
+      check('resume_offset current', offset === hibernation.offset, `/swapfile now starts at ${offset}, configured ${hibernation.offset}`);
+    }
+    return checks;
+  }
+
+  // onProgress gets each pacstrap progress update; build_image folds them into its own steps
+  async handleInstallBase(args, { onProgress = null } = {}) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+    
+    const { 
+      target = this.installState.targetMount, 
       packages = ['base', 'base-devel', 'linux', 'linux-firmware', 'networkmanager', 'vim'],
-      mirror 
+      mirror,
+      localMirror = this.packageSource.localMirror,
+      cacheDirs = this.packageSource.cacheDirs,
+      offline = this.packageSource.offline
     } = args;
     
-    this.installState.currentStep = 'installing_base';
+    if (target === this.installState.targetMount) {
+      this.progress.start('base', { packages });
+    }
+    
+    // Tools to check and repair the filesystems the disk was formatted with
+    const fsPackages = (this.installState.partitionPlan?.packages || []).filter(pkg => !packages.includes(pkg));
     
     // Update pacman mirrors if specified
     if (mirror) {
@@ -467,19 +1396,40 @@ export class ArchInstallPlugin extends BasePlugin {
       await fs.writeFile('/etc/pacman.d/mirrorlist', mirrorlist);
     }
     
-    // Install base system
-    await this.commandExecutor.executeWithSudo('pacstrap', [target, ...packages], {
-      timeout: 1800000 // 30 minutes
-    });
+    // Install base system, reporting the download and install phases as they go
+    const progress = new PacmanProgress();
+    const report = onProgress || (update => reportProgress(update.percent, { total: 100, message: update.message, details: update }));
+    const source = await this.localPackageSource({ localMirror, cacheDirs, offline });
+    let pacstrap;
+    try {
+      pacstrap = await this.commandExecutor.executeWithSudo('pacstrap', [...(source?.args || []), target, ...packages, ...fsPackages], {
+        timeout: 1800000, // 30 minutes
+        onOutput: text => progress.feed(text).forEach(report)
+      });
+    } finally {
+      if (source) {
+        await fs.remove(source.dir);
+      }
+    }
+    if (!pacstrap.success) {
+      throw new Error(`pacstrap failed during ${progress.phase}${progress.package ? ` (${progress.package})` : ''}: ${pacstrap.stderr.trim().split('\n').pop()}`);
+    }
     
     // Generate fstab
     const fstabResult = await this.commandExecutor.executeWithSudo('genfstab', ['-U', target]);
-    await fs.writeFile(`${target}/etc/fstab`, fstabResult.stdout);
-    
-    this.installState.currentStep = 'base_installed';
+    const plan = target === this.installState.targetMount ? this.installState.partitionPlan : null;
+    await fs.writeFile(`${target}/etc/fstab`, plan ? await this.completeFstab(plan, fstabResult.stdout) : fstabResult.stdout);
+    if (plan?.raid) {
+      await this.configureRaid(target);
+    }
     
-    return this.createTextResult(`Successfully installed base system with ${packages.length} packages`, {
-      packages,
+    const transaction = progress.summary();
+    this.progress.succeed('base', { transaction });
+    return this.createTextResult(`Successfully installed base system with ${packages.length + fsPackages.length} packages` +
+      (transaction.packages ? ` (${transaction.packages} with dependencies, ${transaction.installSize || 'unknown size'})` : ''), {
+      packages: [...packages, ...fsPackages],
+      transaction,
+      ...(source ? { source: source.description } : {}),
       target
     });
   }
@@ -491,24 +1441,64 @@ export class ArchInstallPlugin extends BasePlugin {
       hostname, 
       timezone, 
       locale = 'en_US.UTF-8', 
+      locales = [],
+      localeOverrides = {},
       keymap = 'us',
-      users = []
+      font = null,
+      x11Keymap = null,
+      users = [],
+      rootPassword = null,
+      rootPasswordHash = null
     } = args;
     
     const target = this.installState.targetMount;
-    this.installState.currentStep = 'configuring';
+    const etc = path.join(target, 'etc');
+    
+    // Everything is rendered and checked before the first change, so a typo does not
+    // leave the target half configured
+    const localeConf = renderLocaleConf(locale, localeOverrides);
+    const vconsole = renderVconsole({ keymap, font });
+    const x11Keyboard = x11Keymap ? renderX11Keyboard(x11Keymap) : null;
+    const generate = [...new Set([locale, ...locales, ...Object.values(localeOverrides)])];
+    await this.checkLocaleSettings(target, { timezone, locales: generate });
+    const localeGen = enableLocales(await fs.readFile(path.join(etc, 'locale.gen'), 'utf8').catch(() => ''), generate);
+    // systemd-vconsole-setup quietly keeps the default for a keymap or font it cannot find
+    if (!await findKbdFile(target, 'keymaps', keymap)) {
+      throw new Error(`Console keymap ${keymap} not found under ${path.join(target, 'usr/share/kbd/keymaps')}`);
+    }
+    if (font && !await findKbdFile(target, 'consolefonts', font)) {
+      throw new Error(`Console font ${font} not found under ${path.join(target, 'usr/share/kbd/consolefonts')}${font.startsWith('ter-') ? '; install terminus-font first' : ''}`);
+    }
+    const passwords = [
+      ['root', rootPassword, rootPasswordHash],
+      ...users.map(user => [user.username, user.password, user.passwordHash])
+    ].filter(([, password, hash]) => password || hash).map(([user, password, hash]) => {
+      if (hash && !CRYPT_HASH.test(hash)) {
+        throw new Error(`passwordHash for ${user} is not a crypt(3) hash ($y$, $6$, ...)`);
+      }
+      return [user, hash || sha512Crypt(password)];
+    });
+    
+    this.progress.start('configure', { hostname, timezone, locale });
     
     // Set timezone
-    await this.archChroot(target, `ln -sf /usr/share/zoneinfo/${timezone} /etc/localtime`);
+    await fs.remove(path.join(etc, 'localtime'));
+    await fs.symlink(`/usr/share/zoneinfo/${timezone}`, path.join(etc, 'localtime'));
     await this.archChroot(target, 'hwclock --systohc');
     
-    // Configure locale
-    await this.archChroot(target, `echo '${locale} UTF-8' >> /etc/locale.gen`);
+    // Configure locales
+    await fs.writeFile(path.join(etc, 'locale.gen'), localeGen);
     await this.archChroot(target, 'locale-gen');
-    await this.archChroot(target, `echo 'LANG=${locale}' > /etc/locale.conf`);
-    
-    // Set keymap
-    await this.archChroot(target, `echo 'KEYMAP=${keymap}' > /etc/vconsole.conf`);
+    await fs.writeFile(path.join(etc, 'locale.conf'), localeConf);
+    
+    // Console keymap and font, and the keyboard for graphical sessions
+    await fs.writeFile(path.join(etc, 'vconsole.conf'), vconsole);
+    if (x11Keyboard) {
+      await fs.ensureDir(path.join(etc, 'X11/xorg.conf.d'));
+      await fs.writeFile(path.join(etc, 'X11/xorg.conf.d/00-keyboard.conf'), x11Keyboard);
+      const environment = await fs.readFile(path.join(etc, 'environment'), 'utf8').catch(() => '');
+      await fs.writeFile(path.join(etc, 'environment'), setXkbEnvironment(environment, x11Keymap));
+    }
     
     // Set hostname
     await this.archChroot(target, `echo '${hostname}' > /etc/hostname`);
@@ -526,38 +1516,70 @@ export class ArchInstallPlugin extends BasePlugin {
       this.logger.info(`Created user: ${user.username}`);
     }
     
+    // Written straight into the target's shadow file: chpasswd or passwd would need the
+    // password on a command line or piped through a shell
+    if (passwords.length > 0) {
+      const shadowPath = path.join(etc, 'shadow');
+      let shadow = await fs.readFile(shadowPath, 'utf8');
+      for (const [user, hash] of passwords) {
+        shadow = setShadowPassword(shadow, user, hash);
+      }
+      await fs.writeFile(shadowPath, shadow);
+      this.logger.info(`Set passwords for: ${passwords.map(([user]) => user).join(', ')}`);
+    }
+    
     // Enable sudo for wheel group
     await this.archChroot(target, "sed -i 's/# %wheel ALL=(ALL:ALL) ALL/%wheel ALL=(ALL:ALL) ALL/' /etc/sudoers");
     
-    this.installState.currentStep = 'configured';
+    this.progress.succeed('configure', { users: users.map(user => user.username) });
     
     return this.createTextResult(`System configured: ${hostname}`, {
       hostname,
       timezone,
       locale,
-      usersCreated: users.length
+      locales: generate,
+      keymap,
+      font,
+      x11Keymap,
+      usersCreated: users.length,
+      passwordsSet: passwords.map(([user]) => user)
     });
   }
 
+  // Timezone and locales against the target's tzdata and glibc, or the host's when the
+  // target has none yet, all reported at once
+  async checkLocaleSettings(target, settings) {
+    const root = await fs.pathExists(path.join(target, 'usr/share/i18n/SUPPORTED')) ? target : '/';
+    const problems = await checkLocaleSettings(root, settings);
+    if (problems.length > 0) {
+      throw new Error(problems.join('\n'));
+    }
+  }
+
   async handleInstallBootloader(args) {
     await this.validateArgs(args, this.tools[3].inputSchema);
     
-    const { type, device, target = '/mnt' } = args;
+    const { device = this.installState.image?.device, target = this.installState.targetMount } = args;
+    const type = args.type || await this.environment.preferredBootloader();
     
-    this.installState.currentStep = 'installing_bootloader';
+    if (target === this.installState.targetMount) {
+      this.progress.start('bootloader', { type });
+    }
     
+    // A RAID install boots from any member disk, so each one gets the bootloader
+    const raid = target === this.installState.targetMount ? this.installState.partitionPlan?.raid && this.installState.partitionPlan : null;
     if (type === 'grub') {
-      await this.installGRUB(target, device);
+      await this.installGRUB(target, device, raid);
     } else if (type === 'systemd-boot') {
-      await this.installSystemdBoot(target);
+      await this.installSystemdBoot(target, raid);
     }
     
-    this.installState.currentStep = 'bootloader_installed';
+    this.progress.succeed('bootloader');
     
     return this.createTextResult(`Successfully installed ${type} bootloader`);
   }
 
-  async installGRUB(target, device) {
+  async installGRUB(target, device, raid = null) {
     // Install GRUB package
     await this.archChroot(target, 'pacman -S --noconfirm grub');
     
@@ -567,6 +1589,13 @@ export class ArchInstallPlugin extends BasePlugin {
       // UEFI installation
       await this.archChroot(target, 'pacman -S --noconfirm efibootmgr');
       await this.archChroot(target, 'grub-install --target=x86_64-efi --efi-directory=/boot/efi --bootloader-id=GRUB');
+      for (const [index, esp] of this.secondaryEsps(raid).entries()) {
+        await this.archChroot(target, `grub-install --target=x86_64-efi --efi-directory=${esp} --bootloader-id=GRUB-${index + 2}`);
+      }
+    } else if (raid) {
+      for (const member of raid.devices) {
+        await this.archChroot(target, `grub-install --target=i386-pc ${member}`);
+      }
     } else {
       // BIOS installation
       if (!device) {
@@ -579,9 +1608,17 @@ export class ArchInstallPlugin extends BasePlugin {
     await this.archChroot(target, 'grub-mkconfig -o /boot/grub/grub.cfg');
   }
 
-  async installSystemdBoot(target) {
-    // Install systemd-boot
-    await this.archChroot(target, 'bootctl --path=/boot/efi install');
+  // Mount points of the EFI partitions on the other RAID members
+  secondaryEsps(raid) {
+    return (raid?.partitions || [])
+      .filter(partition => partition.type === 'efi' && partition.mountpoint !== '/boot/efi')
+      .map(partition => partition.mountpoint);
+  }
+
+  async installSystemdBoot(target, raid = null) {
+    // Install systemd-boot; an image must not add entries to the host's firmware
+    const variables = this.installState.image ? ' --no-variables' : '';
+    await this.archChroot(target, `bootctl --path=/boot/efi install${variables}`);
     
     // Create loader configuration
     const loaderConf = `default arch\ntimeout 5\nconsole-mode max\neditor no`;
@@ -594,13 +1631,18 @@ export class ArchInstallPlugin extends BasePlugin {
     const archConf = `title Arch Linux\nlinux /vmlinuz-linux\ninitrd /initramfs-linux.img\noptions root=UUID=${rootUuid} rw`;
     await this.archChroot(target, `mkdir -p /boot/efi/loader/entries`);
     await this.archChroot(target, `echo '${archConf}' > /boot/efi/loader/entries/arch.conf`);
+    
+    for (const esp of this.secondaryEsps(raid)) {
+      await this.archChroot(target, `bootctl --path=${esp} install${variables}`);
+      await this.archChroot(target, `cp -r /boot/efi/loader/. ${esp}/loader/`);
+    }
   }
 
   async handleListDisks(args) {
     const { detailed = false } = args;
     
     const result = await this.commandExecutor.execute('lsblk', ['-J', '-o', 'NAME,SIZE,TYPE,MOUNTPOINT,MODEL']);
-    const disks = JSON.parse(result.stdout);
+    const disks = this.withConfirmation(JSON.parse(result.stdout));
     
     if (detailed) {
       // Add additional disk information
@@ -619,32 +1661,225 @@ export class ArchInstallPlugin extends BasePlugin {
     return this.createTextResult(JSON.stringify(disks, null, 2));
   }
 
+  async handleInstallVerify(args) {
+    await this.validateArgs(args, this.tools[14].inputSchema);
+    
+    const { target = this.installState.targetMount } = args;
+    if (!await this.isMounted(target)) {
+      throw new Error(`Nothing is mounted on ${target}; verify before arch_complete_installation unmounts it`);
+    }
+    if (target === this.installState.targetMount) {
+      this.progress.start('verify');
+    }
+    
+    const sections = {
+      initramfs: await this.verifyInitramfs(target),
+      bootloader: await this.verifyBootloader(target),
+      fstab: await this.verifyFstab(target),
+      network: await this.verifyNetwork(target),
+      users: await this.verifyUsers(target),
+      locale: await this.verifyLocale(target),
+      ...(this.installState.hibernation ? { hibernation: await this.hibernationChecks(target) } : {})
+    };
+    const checks = Object.entries(sections).flatMap(([section, list]) => list.map(check => ({ section, ...check })));
+    const failed = checks.filter(check => !check.ok);
+    if (failed.length > 0) {
+      this.progress.fail('verify', new Error(`${failed.length} of ${checks.length} checks failed: ${failed.map(check => `[${check.section}] ${check.name}`).join(', ')}`));
+    } else {
+      this.progress.succeed('verify', { checks: checks.length });
+    }
+    
+    return this.createTextResult([
+      failed.length === 0
+        ? `PASS: all ${checks.length} checks passed; ${target} is ready to boot`
+        : `FAIL: ${failed.length} of ${checks.length} checks failed; fix these before rebooting`,
+      ...checks.map(check => `${check.ok ? 'ok' : 'FAILED'} [${check.section}] ${check.name}${check.ok ? '' : `: ${check.detail}`}`)
+    ].join('\n'), { passed: failed.length === 0, checks });
+  }
+
+  // Every installed kernel (a module tree with a pkgbase file) needs its image and initramfs
+  async verifyInitramfs(target) {
+    const modulesDir = path.join(target, 'usr/lib/modules');
+    const kernels = [];
+    for (const release of await fs.readdir(modulesDir).catch(() => [])) {
+      const pkgbase = await fs.readFile(path.join(modulesDir, release, 'pkgbase'), 'utf8').catch(() => null);
+      if (pkgbase) {
+        kernels.push(pkgbase.trim());
+      }
+    }
+    if (kernels.length === 0) {
+      return [{ name: 'kernel installed', ok: false, detail: 'no kernel package found in /usr/lib/modules' }];
+    }
+    
+    const checks = [];
+    for (const kernel of kernels) {
+      const missing = [];
+      for (const file of [`vmlinuz-${kernel}`, `initramfs-${kernel}.img`]) {
+        const stats = await fs.stat(path.join(target, 'boot', file)).catch(() => null);
+        if (!stats?.size) {
+          missing.push(`/boot/${file}`);
+        }
+      }
+      checks.push({ name: `${kernel} kernel and initramfs`, ok: missing.length === 0, detail: `missing or empty: ${missing.join(', ')}` });
+    }
+    return checks;
+  }
+
+  // UUID=, PARTUUID=, LABEL= and PARTLABEL= through blkid, device paths as they are
+  async resolveSpec(spec) {
+    const match = spec.match(/^(UUID|PARTUUID|LABEL|PARTLABEL)=(.+)$/);
+    if (!match) {
+      return spec.startsWith('/dev/') && await fs.pathExists(spec);
+    }
+    const result = await this.commandExecutor.execute('blkid', ['-t', `${match[1]}=${match[2]}`, '-o', 'device']);
+    return result.success && result.stdout.trim() !== '';
+  }
+
+  // The root= each boot entry passes has to name a filesystem that exists, and
+  // systemd-boot can only load kernels from the ESP it runs from
+  async verifyBootloader(target) {
+    const checks = [];
+    const checkRoot = async (name, params) => {
+      const root = params.find(param => param.startsWith('root='))?.slice('root='.length);
+      checks.push({
+        name: `${name} root=`,
+        ok: Boolean(root) && await this.resolveSpec(root),
+        detail: root ? `${root} does not match any filesystem` : 'no root= parameter'
+      });
+    };
+    
+    const grubCfg = await fs.readFile(path.join(target, 'boot/grub/grub.cfg'), 'utf8').catch(() => null);
+    if (grubCfg !== null) {
+      const entries = [...grubCfg.matchAll(/^\s*linux\s+\S+\s+(.*)$/gm)].map(match => parseCmdline(match[1]));
+      checks.push({ name: 'grub.cfg has boot entries', ok: entries.length > 0, detail: 'no linux lines in /boot/grub/grub.cfg; run grub-mkconfig' });
+      const roots = new Set(entries.map(params => params.find(param => param.startsWith('root='))).filter(Boolean));
+      for (const root of roots) {
+        await checkRoot('GRUB entry', [root]);
+      }
+      for (const uuid of new Set([...grubCfg.matchAll(/--fs-uuid\s+(?:--set=\w+\s+)?([\w-]+)/g)].map(match => match[1]))) {
+        checks.push({ name: `GRUB search ${uuid}`, ok: await this.resolveSpec(`UUID=${uuid}`), detail: `UUID ${uuid} does not match any filesystem` });
+      }
+    }
+    
+    for (const esp of ['boot', 'efi', ...(await fs.readdir(path.join(target, 'boot')).catch(() => [])).filter(name => /^efi(-\d+)?$/.test(name)).map(name => `boot/${name}`)]) {
+      const dir = path.join(target, esp, 'loader/entries');
+      for (const name of (await fs.readdir(dir).catch(() => [])).filter(file => file.endsWith('.conf')).sort()) {
+        const content = await fs.readFile(path.join(dir, name), 'utf8');
+        const params = parseCmdline([...content.matchAll(/^options\s+(.*)$/gm)].map(match => match[1]).join(' '));
+        await checkRoot(`/${esp}/loader/entries/${name}`, params);
+        for (const [, key, file] of content.matchAll(/^(linux|initrd)\s+(\S+)/gm)) {
+          checks.push({
+            name: `/${esp}/loader/entries/${name} ${key}`,
+            ok: await fs.pathExists(path.join(target, esp, file)),
+            detail: `${file} is not on the ESP mounted at /${esp}`
+          });
+        }
+      }
+    }
+    
+    const kernelCmdline = await fs.readFile(path.join(target, 'etc/kernel/cmdline'), 'utf8').catch(() => null);
+    if (kernelCmdline !== null) {
+      await checkRoot('/etc/kernel/cmdline', parseCmdline(kernelCmdline.trim()));
+    }
+    
+    return checks.length > 0 ? checks : [{ name: 'bootloader configured', ok: false, detail: 'no grub.cfg, systemd-boot entries or /etc/kernel/cmdline; run arch_install_bootloader' }];
+  }
+
+  async verifyFstab(target) {
+    const fstab = await fs.readFile(path.join(target, 'etc/fstab'), 'utf8').catch(() => null);
+    if (fstab === null) {
+      return [{ name: 'fstab exists', ok: false, detail: '/etc/fstab is missing' }];
+    }
+    const entries = this.fstabEntries(fstab);
+    const checks = [{ name: 'fstab mounts /', ok: entries.some(fields => fields[1] === '/'), detail: 'no entry for /' }];
+    for (const [spec, mountpoint, type] of entries) {
+      const name = `${mountpoint === 'none' ? type : mountpoint} (${spec})`;
+      if (spec.startsWith('/') && !spec.startsWith('/dev/')) {
+        // Swap files live in the target
+        checks.push({ name, ok: await fs.pathExists(path.join(target, spec)), detail: `${spec} does not exist in the target` });
+      } else if (/^(UUID|PARTUUID|LABEL|PARTLABEL)=|^\/dev\//.test(spec)) {
+        checks.push({ name, ok: await this.resolveSpec(spec), detail: `${spec} does not match any filesystem` });
+      }
+    }
+    return checks;
+  }
+
+  async verifyNetwork(target) {
+    const services = ['NetworkManager.service', 'systemd-networkd.service', 'iwd.service', 'connman.service'];
+    // Unknown units print nothing to stdout, so ask one at a time
+    const result = await this.archChroot(target,
+      `for unit in ${services.join(' ')}; do echo "$unit $(systemctl is-enabled $unit 2>/dev/null)"; done`);
+    const enabled = result.stdout.split('\n').map(line => line.trim().split(' '))
+      .filter(([, state]) => state === 'enabled').map(([unit]) => unit);
+    return [{ name: 'network service enabled', ok: enabled.length > 0, detail: `none of ${services.join(', ')} is enabled`, ...(enabled.length ? { services: enabled } : {}) }];
+  }
+
+  // A regular account to log in with, besides root
+  async verifyUsers(target) {
+    const result = await this.archChroot(target, 'getent passwd');
+    const users = result.stdout.split('\n')
+      .map(line => line.split(':'))
+      .filter(fields => Number(fields[2]) >= 1000 && Number(fields[2]) < 60000 && !/(nologin|false)$/.test(fields[6] || ''));
+    const shadow = (await fs.readFile(path.join(target, 'etc/shadow'), 'utf8').catch(() => ''))
+      .split('\n').map(line => line.split(':'));
+    const canLogIn = ['root', ...users.map(fields => fields[0])]
+      .filter(name => hasUsablePassword(shadow.find(fields => fields[0] === name)?.[1]));
+    return [
+      { name: 'user account exists', ok: users.length > 0, detail: 'no regular user with a login shell', ...(users.length ? { users: users.map(fields => fields[0]) } : {}) },
+      { name: 'password login possible', ok: canLogIn.length > 0, detail: 'root and every user are locked; set a password with arch_configure_system', ...(canLogIn.length ? { users: canLogIn } : {}) }
+    ];
+  }
+
+  // locale -a lists en_US.UTF-8 as en_US.utf8
+  async verifyLocale(target) {
+    const conf = await fs.readFile(path.join(target, 'etc/locale.conf'), 'utf8').catch(() => '');
+    const lang = conf.match(/^LANG=["']?([^"'\s]+)/m)?.[1];
+    if (!lang) {
+      return [{ name: 'LANG set', ok: false, detail: '/etc/locale.conf sets no LANG' }];
+    }
+    const normalize = name => name.toLowerCase().replace(/utf-?8/, 'utf8');
+    const result = await this.archChroot(target, 'locale -a');
+    const generated = result.stdout.split('\n').map(line => normalize(line.trim()));
+    return [{ name: `locale ${lang} generated`, ok: lang === 'C' || lang === 'POSIX' || generated.includes(normalize(lang)), detail: `${lang} is not in locale -a; check /etc/locale.gen and run locale-gen` }];
+  }
+
   async handleInstallationStatus(args) {
     const status = {
-      currentStep: this.installState.currentStep,
+      ...this.progress.snapshot(),
       targetMount: this.installState.targetMount,
+      image: this.installState.image,
       lastSnapshot: this.installState.lastSnapshot,
       nextSteps: this.getNextSteps()
     };
     
-    return this.createTextResult(JSON.stringify(status, null, 2));
+    return this.createTextResult(JSON.stringify(status, null, 2), { currentStep: status.currentStep, completed: status.completed });
   }
 
   async handleCompleteInstallation(args) {
-    const { target = '/mnt', reboot = false } = args;
+    const { target = this.installState.targetMount, reboot = false } = args;
     
-    this.installState.currentStep = 'finalizing';
+    if (target === this.installState.targetMount) {
+      this.progress.start('complete', { reboot });
+    }
     
     // Final steps
     await this.archChroot(target, 'systemctl enable NetworkManager');
     
-    // Unmount filesystems
-    await this.commandExecutor.executeWithSudo('umount', ['-R', target]);
+    // Unmount filesystems, treating an already unmounted target as done
+    await this.commandExecutor.executeWithSudo('umount', ['-R', target], {
+      isApplied: async () => !(await this.isMounted(target))
+    });
     
-    this.installState.currentStep = 'completed';
+    this.progress.succeed('complete');
     
     let message = 'Arch Linux installation completed successfully!';
     
+    if (this.installState.image) {
+      const { file } = this.installState.image;
+      await this.releaseImage();
+      return this.createTextResult(`${message} Disk image ready: ${file}`, { image: file });
+    }
+    
     if (reboot) {
       message += ' Rebooting system...';
       // Give some time for response before reboot
@@ -656,11 +1891,434 @@ export class ArchInstallPlugin extends BasePlugin {
     return this.createTextResult(message);
   }
 
+  async handleDownloadIso(args) {
+    await this.validateArgs(args, this.tools[8].inputSchema);
+    
+    const iso = await this.isoMedia.fetchIso({
+      onProgress: (received, total) => reportProgress(received, { total, message: 'downloading ISO' })
+    });
+    const verification = await this.isoMedia.verifyIso(iso.path, { expectedSha256: iso.sha256 });
+    
+    if (!verification.checksum.valid || !verification.signature.valid) {
+      throw new Error(`Downloaded ISO failed verification: ${JSON.stringify(verification, null, 2)}`);
+    }
+    
+    return this.createTextResult(JSON.stringify({ ...iso, verification }, null, 2), { path: iso.path });
+  }
+
+  async handleVerifyIso(args) {
+    await this.validateArgs(args, this.tools[9].inputSchema);
+    
+    const verification = await this.isoMedia.verifyIso(args.path, { expectedSha256: args.sha256 });
+    const valid = verification.checksum.valid && verification.signature.valid;
+    
+    return this.createTextResult(JSON.stringify({ path: args.path, valid, ...verification }, null, 2), { valid });
+  }
+
+  async handleWriteUsb(args) {
+    if (!this.allowDiskOperations) {
+      throw new Error('Disk operations are disabled');
+    }
+
+    await this.validateArgs(args, this.tools[10].inputSchema);
+    
+    const { iso, device, allowFixedDisk = false, verify = true, confirmDevice, dryRun = false } = args;
+    
+    // Safety checks
+    await this.validateDevice(device);
+    if (!dryRun || confirmDevice) {
+      await this.confirmDevice(device, confirmDevice);
+    }
+    const target = await this.inspectWriteTarget(device, iso, { allowFixedDisk });
+    
+    // Never write media that has not been verified
+    const verification = await this.isoMedia.verifyIso(iso);
+    if (!verification.checksum.valid || !verification.signature.valid) {
+      throw new Error(`Refusing to write an unverified ISO: ${JSON.stringify(verification, null, 2)}`);
+    }
+    
+    if (dryRun) {
+      return this.createTextResult([
+        `Write ${path.basename(iso)} to ${device} (${target.model || 'unknown model'}, ${target.transport || 'unknown transport'}, ${target.size} bytes):\n`,
+        `1. dd if=${iso} of=${device} bs=4M conv=fsync oflag=direct`,
+        verify ? '2. Read back and compare SHA-256 with the ISO' : null,
+        `\nWARNING: This will destroy all data on ${device}`
+      ].filter(Boolean).join('\n'));
+    }
+    
+    const { bytes } = await this.isoMedia.writeImage(iso, device, {
+      onProgress: (written, total) => reportProgress(written, { total, message: `writing ${device}` })
+    });
+    
+    let readBack = null;
+    if (verify) {
+      readBack = await sha256(device, {
+        length: bytes,
+        onProgress: (done) => reportProgress(done, { total: bytes, message: 'verifying' })
+      });
+      if (readBack !== verification.checksum.actual) {
+        throw new Error(`Verification failed: ${device} does not match ${iso}; the device may be faulty`);
+      }
+    }
+    
+    this.logger.audit('arch_write_usb', { iso, device, bytes, verified: verify });
+    return this.createTextResult(`Wrote ${path.basename(iso)} to ${device}${verify ? ' and verified it' : ''}`, {
+      device,
+      bytes,
+      verified: verify
+    });
+  }
+
+  async handleWipeDevice(args) {
+    if (!this.allowDiskOperations) {
+      throw new Error('Disk operations are disabled');
+    }
+
+    await this.validateArgs(args, this.tools[15].inputSchema);
+
+    const { device, method = 'discard', passes = 1, zero = true, confirmDevice, allowFixedDisk = false, dryRun = false } = args;
+
+    const info = await this.deviceWipe.classify(device);
+    const plan = await this.deviceWipe.plan(info, { method, passes, zero });
+
+    // Everything the command erases must be free, including namespaces an NVMe format takes along
+    const refusals = [...info.inUse];
+    for (const other of plan.affected.filter(entry => entry !== info.device)) {
+      refusals.push(...(await this.deviceWipe.classify(other)).inUse);
+    }
+    if (info.system) {
+      refusals.push(`${info.device} is ${info.type === 'disk' ? 'the' : 'on the'} system disk`);
+    }
+    if (!info.removable && !allowFixedDisk) {
+      refusals.push(`${info.device} is not on a removable or USB disk; set allowFixedDisk to wipe it anyway`);
+    }
+
+    const confirmation = await this.deviceConfirmation(info.device);
+    if (dryRun) {
+      return this.createTextResult(JSON.stringify({
+        ...info,
+        command: commandLine(plan),
+        erases: plan.affected,
+        refused: refusals,
+        confirmation,
+        nextSteps: refusals.length > 0
+          ? ['Resolve the refusals above, then preview again']
+          : [`wipe_device with device ${device}, method ${method} and confirmDevice "${confirmation}"`]
+      }, null, 2), { device: info.device, refused: refusals.length > 0 });
+    }
+
+    if (refusals.length > 0) {
+      throw new Error(`Refusing to wipe ${device}: ${refusals.join('; ')}`);
+    }
+    if (!confirmDevice) {
+      throw new Error(`Confirmation required for ${device}: preview it with dryRun and pass its "confirmation" text as confirmDevice`);
+    }
+    await this.confirmDevice(info.device, confirmDevice);
+
+    this.logger.info(`Wiping ${plan.affected.join(', ')} with ${method}`);
+    reportProgress(0, { total: plan.size, message: `${method} ${info.device}` });
+    await this.deviceWipe.run(plan, {
+      onProgress: ({ progress, total, message }) => reportProgress(progress, { total, message })
+    });
+
+    this.logger.audit('wipe_device', { device: info.device, method, erased: plan.affected, passes: method === 'overwrite' ? passes : undefined });
+    return this.createTextResult(`Wiped ${plan.affected.join(', ')} (${method})`, {
+      device: info.device,
+      method,
+      erased: plan.affected
+    });
+  }
+
+  async handleInstallTarget(args) {
+    await this.validateArgs(args, this.tools[11].inputSchema);
+    
+    const { type, image, size = '8G', target } = args;
+    if (type === 'image' && !image) {
+      throw new Error('image is required for type image');
+    }
+    
+    // Only one target at a time: switching always releases an attached image
+    if (this.installState.image) {
+      await this.releaseImage();
+    }
+    
+    if (type === 'disk') {
+      this.progress.reset({ type });
+      this.setTargetMount(target || DEFAULT_INSTALL_ROOT);
+      return this.createTextResult(`Installing to disk, mounted at ${this.installState.targetMount}`, {
+        targetMount: this.installState.targetMount
+      });
+    }
+    
+    if (!this.allowDiskOperations) {
+      throw new Error('Disk operations are disabled');
+    }
+    
+    const file = path.resolve(image);
+    const created = !await fs.pathExists(file);
+    if (created) {
+      await this.loopImage.create(file, size);
+    }
+    
+    const device = await this.loopImage.attach(file);
+    const mount = target || await fs.mkdtemp(path.join(os.tmpdir(), 'arch-image-'));
+    this.installState.image = { file, device, mount, temporaryMount: !target };
+    this.progress.reset({ type, image: file, device });
+    this.setTargetMount(mount);
+    
+    return this.createTextResult(JSON.stringify({
+      image: file,
+      created,
+      device,
+      targetMount: mount,
+      confirmation: await this.deviceConfirmation(device),
+      nextSteps: [`arch_partition_disk with device ${device}`, `arch_mount_system with device ${device}`]
+    }, null, 2), { device, targetMount: mount });
+  }
+
+  async handleBuildImage(args) {
+    await this.validateArgs(args, this.tools[12].inputSchema);
+    
+    const { output, format = 'qcow2', compress = 'none', checksum = true, overwrite = false, dryRun = false } = args;
+    const profile = await this.resolveImageProfile(args);
+    const bootloader = profile.bootloader || (profile.scheme === 'uefi' ? 'systemd-boot' : 'grub');
+    const imageFile = path.resolve(output);
+    const rawFile = format === 'raw' ? imageFile : `${imageFile}.raw`;
+    
+    const steps = [
+      `Create a ${profile.size} sparse image and attach it as a loop device`,
+      `Partition (${profile.scheme}) and format root as ${profile.filesystem}`,
+      `pacstrap ${profile.packages.join(' ')}${profile.mirror ? ` from ${profile.mirror}` : ''}`,
+      `Configure ${profile.hostname} (${profile.timezone}, ${profile.locale}, ${profile.users.length} users)`,
+      `Install ${bootloader}`,
+      'Unmount and detach',
+      format === 'qcow2' ? `Convert to qcow2: ${imageFile}` : null,
+      compress !== 'none' ? `Compress with ${compress}` : null,
+      checksum ? 'Write SHA-256 checksum' : null
+    ].filter(Boolean);
+    
+    // Checked against the host's databases now rather than the image's after pacstrap
+    await this.checkLocaleSettings('/', {
+      timezone: profile.timezone,
+      locales: [profile.locale, ...(profile.locales || []), ...Object.values(profile.localeOverrides || {})]
+    });
+    
+    if (dryRun) {
+      return this.createTextResult(JSON.stringify({ output: imageFile, format, compress, profile: { ...profile, bootloader }, steps }, null, 2));
+    }
+    
+    if (!overwrite && (await fs.pathExists(imageFile) || await fs.pathExists(rawFile))) {
+      throw new Error(`${imageFile} already exists; set overwrite to replace it`);
+    }
+    await fs.remove(rawFile);
+    
+    const progress = (step) => reportProgress(step, { total: steps.length, message: steps[step] });
+    try {
+      progress(0);
+      await this.handleInstallTarget({ type: 'image', image: rawFile, size: profile.size });
+      const { device } = this.installState.image;
+      
+      progress(1);
+      const plan = await this.partitionPlan(device, profile.scheme, profile.layout
+        ? customLayout(profile.scheme, profile.layout, { filesystem: profile.filesystem })
+        : defaultLayout(profile.scheme, { swapSize: profile.swapSize, filesystem: profile.filesystem }));
+      await this.handlePartitionDisk({
+        device,
+        scheme: profile.scheme,
+        swapSize: profile.swapSize,
+        filesystem: profile.filesystem,
+        ...(profile.layout ? { layout: profile.layout } : {}),
+        confirmDevice: await this.deviceConfirmation(device),
+        confirmPlan: plan.id
+      });
+      await this.handleMountSystem({ device, scheme: profile.scheme });
+      
+      progress(2);
+      await this.handleInstallBase({ packages: profile.packages, ...(profile.mirror ? { mirror: profile.mirror } : {}) }, {
+        onProgress: update => reportProgress(2 + update.percent / 100, { total: steps.length, message: `${steps[2]}: ${update.message}`, details: update })
+      });
+      
+      progress(3);
+      await this.handleConfigureSystem({
+        hostname: profile.hostname,
+        timezone: profile.timezone,
+        locale: profile.locale,
+        keymap: profile.keymap,
+        ...(profile.locales ? { locales: profile.locales } : {}),
+        ...(profile.localeOverrides ? { localeOverrides: profile.localeOverrides } : {}),
+        ...(profile.font ? { font: profile.font } : {}),
+        ...(profile.x11Keymap ? { x11Keymap: profile.x11Keymap } : {}),
+        ...(profile.rootPassword ? { rootPassword: profile.rootPassword } : {}),
+        ...(profile.rootPasswordHash ? { rootPasswordHash: profile.rootPasswordHash } : {}),
+        users: profile.users.map(user => ({ groups: ['wheel'], shell: '/bin/bash', ...user }))
+      });
+      
+      progress(4);
+      await this.handleInstallBootloader({ type: bootloader, device });
+      
+      progress(5);
+      await this.handleCompleteInstallation({});
+    } catch (error) {
+      if (this.installState.image) {
+        await this.releaseImage().catch(releaseError => this.logger.warn(`Could not release image: ${releaseError.message}`));
+      }
+      await fs.remove(rawFile);
+      throw error;
+    }
+    
+    let file = rawFile;
+    if (format === 'qcow2') {
+      progress(6);
+      await this.loopImage.convert(rawFile, imageFile, 'qcow2');
+      await fs.remove(rawFile);
+      file = imageFile;
+    }
+    
+    if (compress !== 'none') {
+      progress(steps.indexOf(`Compress with ${compress}`));
+      file = await this.loopImage.compress(file, compress);
+    }
+    
+    let digest = null;
+    if (checksum) {
+      progress(steps.length - 1);
+      digest = await sha256(file);
+      await fs.writeFile(`${file}.sha256`, `${digest}  ${path.basename(file)}\n`);
+    }
+    
+    const { size } = await fs.stat(file);
+    this.logger.audit('build_image', { file, format, compress, bytes: size });
+    return this.createTextResult(JSON.stringify({
+      file,
+      format,
+      compress,
+      bytes: size,
+      sha256: digest,
+      profile: { ...profile, bootloader }
+    }, null, 2), { file, sha256: digest });
+  }
+  
+  // Defaults, then the profile file, then inline fields
+  async resolveImageProfile({ profile = {}, profileFile }) {
+    let fileProfile = {};
+    if (profileFile) {
+      if (!await fs.pathExists(profileFile)) {
+        throw new Error(`Profile not found: ${profileFile}`);
+      }
+      fileProfile = YAML.parse(await fs.readFile(profileFile, 'utf8')) || {};
+    }
+    return { ...DEFAULT_IMAGE_PROFILE, ...fileProfile, ...profile };
+  }
+
+  // Unmount whatever is still mounted from the image and detach its loop device; the
+  // image file itself is kept
+  async releaseImage() {
+    const { device, mount, temporaryMount } = this.installState.image;
+    
+    if (await this.isMounted(mount)) {
+      await this.commandExecutor.executeWithSudo('umount', ['-R', mount], {
+        isApplied: async () => !(await this.isMounted(mount))
+      });
+    }
+    await this.loopImage.detach(device);
+    if (temporaryMount) {
+      await fs.rmdir(mount).catch(() => {});
+    }
+    
+    this.installState.image = null;
+    this.setTargetMount(DEFAULT_INSTALL_ROOT);
+  }
+
+  // Only whole, unmounted, removable disks large enough for the image
+  async inspectWriteTarget(device, iso, { allowFixedDisk = false } = {}) {
+    const result = await this.commandExecutor.execute('lsblk', ['-J', '-b', '-o', 'NAME,PATH,SIZE,TYPE,RM,TRAN,MODEL,MOUNTPOINT', device]);
+    if (!result.success) {
+      throw new Error(`Invalid device: ${device}`);
+    }
+    
+    const [disk] = JSON.parse(result.stdout).blockdevices || [];
+    if (!disk || disk.type !== 'disk') {
+      throw new Error(`${device} is not a whole disk`);
+    }
+    
+    const mounted = [];
+    const walk = (node) => {
+      if (node.mountpoint) mounted.push(`${node.path || node.name} on ${node.mountpoint}`);
+      (node.children || []).forEach(walk);
+    };
+    walk(disk);
+    if (mounted.length > 0) {
+      throw new Error(`${device} is in use: ${mounted.join(', ')}`);
+    }
+    
+    const removable = disk.rm === true || disk.rm === '1' || disk.rm === 1 || disk.tran === 'usb';
+    if (!removable && !allowFixedDisk) {
+      throw new Error(`${device} is not a removable or USB disk; set allowFixedDisk to write it anyway`);
+    }
+    
+    const { size: isoSize } = await fs.stat(iso);
+    if (Number(disk.size) < isoSize) {
+      throw new Error(`${device} (${disk.size} bytes) is smaller than ${path.basename(iso)} (${isoSize} bytes)`);
+    }
+    
+    return { size: Number(disk.size), transport: disk.tran, model: disk.model?.trim() || null, removable };
+  }
+
   // Helper methods
+
+  // System tools reach the system being installed through the "install" target
+  setTargetMount(mount) {
+    this.installState.targetMount = mount;
+    getTargetRegistry().define('install', `chroot:${path.resolve(mount)}`);
+    this.progress.setContext({ targetMount: mount });
+  }
+
   async archChroot(target, command) {
     return this.commandExecutor.executeWithSudo('arch-chroot', [target, 'bash', '-c', command]);
   }
 
+  // Kernel names like /dev/sda can move between boots; model and size identify the disk
+  // the client actually looked at
+  describeDevice(disk) {
+    return `${disk.model?.trim() || 'unknown model'} ${disk.size}`;
+  }
+
+  async deviceConfirmation(device) {
+    const result = await this.commandExecutor.execute('lsblk', ['-J', '-d', '-o', 'NAME,SIZE,MODEL', device]);
+    const [disk] = result.success ? JSON.parse(result.stdout).blockdevices || [] : [];
+    return disk ? this.describeDevice(disk) : null;
+  }
+
+  withConfirmation(disks) {
+    for (const disk of disks.blockdevices || []) {
+      if (disk.type === 'disk') {
+        disk.confirmation = this.describeDevice(disk);
+      }
+    }
+    return disks;
+  }
+
+  async confirmDevice(device, confirmation) {
+    if (!confirmation) {
+      if (this.requireDeviceConfirmation) {
+        throw new Error(`Confirmation required for ${device}: pass confirmDevice with the disk's "confirmation" text from arch_list_disks`);
+      }
+      return;
+    }
+    
+    const result = await this.commandExecutor.execute('lsblk', ['-J', '-d', '-o', 'NAME,SIZE,MODEL', device]);
+    const [disk] = result.success ? JSON.parse(result.stdout).blockdevices || [] : [];
+    if (!disk) {
+      throw new Error(`Invalid device: ${device}`);
+    }
+    
+    const normalize = text => text.trim().replace(/\s+/g, ' ').toLowerCase();
+    const actual = this.describeDevice(disk);
+    if (normalize(actual) !== normalize(confirmation)) {
+      throw new Error(`${device} is "${actual}", not "${confirmation}"; device names may have changed, list disks again`);
+    }
+  }
+
   async validateDevice(device) {
     // Check if device exists
     if (!await fs.pathExists(device)) {
@@ -674,28 +2332,13 @@ export class ArchInstallPlugin extends BasePlugin {
     }
   }
 
-  previewPartitionOperations(device, scheme, swapSize, rootSize) {
-    let preview = `Partition operations for ${device} (${scheme}):\n\n`;
-    
-    if (scheme === 'uefi') {
-      preview += `1. Create GPT partition table\n`;
-      preview += `2. Create EFI partition (512MB)\n`;
-      preview += `3. Create swap partition (${swapSize})\n`;
-      preview += `4. Create root partition (${rootSize})\n`;
-      preview += `5. Format EFI as FAT32\n`;
-      preview += `6. Format swap\n`;
-      preview += `7. Format root as ext4\n`;
-    } else {
-      preview += `1. Create MBR partition table\n`;
-      preview += `2. Create swap partition (${swapSize})\n`;
-      preview += `3. Create root partition (${rootSize})\n`;
-      preview += `4. Format swap\n`;
-      preview += `5. Format root as ext4\n`;
+  async isMounted(target) {
+    try {
+      const result = await this.commandExecutor.execute('findmnt', ['-n', target]);
+      return result.success;
+    } catch {
+      return false;
     }
-    
-    preview += `\nWARNING: This will destroy all data on ${device}`;
-    
-    return preview;
   }
 
   async getRootUUID(target) {
@@ -704,30 +2347,33 @@ export class ArchInstallPlugin extends BasePlugin {
   }
 
   getNextSteps() {
-    switch (this.installState.currentStep) {
-      case null:
+    const current = this.progress.current();
+    if (!current) {
+      return ['Installation complete'];
+    }
+    if (current.status === 'running') {
+      return [`Wait for ${current.tool} to finish`];
+    }
+    if (current.status === 'failed') {
+      return [`Retry ${current.title.toLowerCase()} (${current.tool}) after fixing: ${current.error?.message}`];
+    }
+    switch (current.id) {
+      case 'partition':
         return ['List available disks', 'Partition disk'];
-      case 'partitioned':
-        return ['Mount system', 'Install base system'];
-      case 'mounted':
-        return ['Install base system'];
-      case 'base_installed':
-        return ['Configure system'];
-      case 'configured':
-        return ['Install bootloader'];
-      case 'bootloader_installed':
-        return ['Complete installation'];
-      case 'completed':
-        return ['Installation complete'];
+      case 'complete':
+        return this.progress.step('verify').status === 'succeeded'
+          ? ['Complete installation']
+          : ['Verify installation', 'Complete installation'];
       default:
-        return ['Check installation status'];
+        return [current.title];
     }
   }
 
   async getInstallationStatus() {
     const status = {
-      currentStep: this.installState.currentStep,
+      ...this.progress.snapshot(),
       targetMount: this.installState.targetMount,
+      image: this.installState.image,
       nextSteps: this.getNextSteps(),
       timestamp: new Date().toISOString()
     };
@@ -746,7 +2392,7 @@ export class ArchInstallPlugin extends BasePlugin {
   }
 
   async getAvailableDisks() {
-    const result = await this.commandExecutor.execute('lsblk', ['-J', '-o', 'NAME,SIZE,TYPE,MOUNTPOINT']);
-    return { content: result.stdout };
+    const result = await this.commandExecutor.execute('lsblk', ['-J', '-o', 'NAME,SIZE,TYPE,MOUNTPOINT,MODEL']);
+    return { content: JSON.stringify(this.withConfirmation(JSON.parse(result.stdout)), null, 2) };
   }
 }
\ No newline at end of file
@@ -1,3 +1,15 @@
+import { getToolResolver } from '../system/tool-resolver.js';
+import { getContext } from '../core/request-context.js';
+import { MCPError } from '../core/errors.js';
+
+const ELICITABLE_TYPES = ['string', 'number', 'integer', 'boolean'];
+
+// A plugins.<key> section from the Config the server passes, or from a plain object
+// (the install image test builds one)
+export function pluginConfig(config, key) {
+  return config?.get?.(`plugins.${key}`) || config?.plugins?.[key] || {};
+}
+
 export class BasePlugin {
   constructor(name, config, logger, security) {
     this.name = name;
@@ -8,10 +20,56 @@ export class BasePlugin {
     this.description = 'Base plugin';
     this.tools = [];
     this.resources = [];
+    this.resourceTemplates = [];
+    this.dependencies = [];
+    this.optionalDependencies = [];
+    this.resolvedDependencies = {};
   }
 
   async initialize() {
     this.logger.debug(`Initializing plugin: ${this.name}`);
+
+    if (this.dependencies.length > 0) {
+      this.resolvedDependencies = await this.resolveDependencies(this.dependencies);
+    }
+  }
+
+  // Resolve external binaries this plugin relies on (reported via tools://dependencies)
+  async resolveDependencies(commands) {
+    const resolver = getToolResolver();
+    const resolved = {};
+    for (const command of commands) {
+      resolved[command] = await resolver.resolveFor(this.name, command);
+    }
+    return resolved;
+  }
+
+  hasDependency(command) {
+    return Boolean(this.resolvedDependencies[command]);
+  }
+
+  // Readiness checks for health://ready; missing required binaries fail, optional ones warn
+  async getHealthChecks() {
+    if (this.dependencies.length === 0) {
+      return [];
+    }
+
+    const resolver = getToolResolver();
+    const missing = [];
+    for (const command of this.dependencies) {
+      if (!await resolver.resolve(command)) {
+        missing.push(command);
+      }
+    }
+
+    const required = missing.filter(command => !this.optionalDependencies.includes(command));
+    const optional = missing.filter(command => this.optionalDependencies.includes(command));
+
+    return [{
+      name: 'binaries',
+      status: required.length > 0 ? 'fail' : optional.length > 0 ? 'warn' : 'pass',
+      missing: { required, optional }
+    }];
   }
 
   async cleanup() {
@@ -26,6 +84,10 @@ export class BasePlugin {
     return this.resources;
   }
 
+  async getResourceTemplates() {
+    return this.resourceTemplates;
+  }
+
   async executeTool(toolName, args) {
     throw new Error(`Tool not implemented: ${toolName}`);
   }
@@ -34,7 +96,99 @@ export class BasePlugin {
     throw new Error(`Resource not implemented: ${uri}`);
   }
 
-  createTool(name, description, inputSchema, handler) {
+  // Read a URI matching one of resourceTemplates; params holds the template variables
+  async readResourceTemplate(uriTemplate, params, uri) {
+    throw new Error(`Resource template not implemented: ${uriTemplate}`);
+  }
+
+  // Watch a resource for resources/subscribe; call listener(update) on changes and return an unsubscribe function
+  async subscribeResource(uri, listener) {
+    return () => {};
+  }
+
+  // Resource lock keys a tool call needs (see LockKeys); unrelated tools run concurrently
+  getToolLocks(toolName, args) {
+    return [];
+  }
+
+  // Paths a tool call may modify; PluginManager snapshots them before execution
+  getAffectedPaths(toolName, args) {
+    return [];
+  }
+
+  // Whether a tool call changes installed packages, so its snapshot records package state
+  affectsPackages(toolName, args) {
+    return false;
+  }
+
+  // Whether running a call again with the same arguments is safe (history/replay re-executes only these)
+  isIdempotent(toolName, args) {
+    return false;
+  }
+
+  // Whether a call only inspects the system; with security.readOnly nothing else runs.
+  // A dry run of a tool that offers one never changes anything
+  isReadOnly(toolName, args = {}) {
+    return Boolean(args.dryRun) && Boolean(this.tools.find(tool => tool.name === toolName)?.inputSchema?.properties?.dryRun);
+  }
+
+  // Whether any call to the tool is read-only, i.e. whether read-only mode still lists it
+  hasReadOnlyCalls(toolName) {
+    return this.isReadOnly(toolName, {}) || this.isReadOnly(toolName, { dryRun: true });
+  }
+
+  // Whether a call destroys data or disrupts the machine enough that security.maintenance
+  // only lets it run inside a maintenance window
+  isDestructive(toolName, args) {
+    return false;
+  }
+
+  // Whether a call steals input, rearranges windows or reboots, so that security.presence
+  // can refuse it while someone is using the seat
+  disruptsUser(toolName, args) {
+    return false;
+  }
+
+  // Whether a tool call runs long enough that the machine must not sleep meanwhile (package tools always do)
+  inhibitsIdle(toolName, args) {
+    return false;
+  }
+
+  // Ask the connected client's model for a completion (MCP sampling); the client asks the user to approve it
+  async createMessage(params, { timeout = 300000 } = {}) {
+    const client = getContext().client;
+    if (!client?.capabilities?.sampling) {
+      throw new Error('The connected client does not support sampling');
+    }
+    return client.request('sampling/createMessage', { maxTokens: 4096, ...params }, { timeout });
+  }
+
+  canSample() {
+    return Boolean(getContext().client?.capabilities?.sampling);
+  }
+
+  // Ask the user for values through the client (MCP elicitation); properties use the flat,
+  // primitive-only JSON schema subset elicitation allows
+  async elicit(message, properties, required = Object.keys(properties)) {
+    const client = getContext().client;
+    if (!client?.capabilities?.elicitation) {
+      throw new MCPError(`Missing ${required.join(', ')} and the client cannot ask the user for it`, -32602);
+    }
+
+    const response = await client.request('elicitation/create', {
+      message,
+      requestedSchema: { type: 'object', properties, required }
+    }, { timeout: 600000 });
+
+    if (response?.action !== 'accept') {
+      throw new MCPError(`The user ${response?.action === 'decline' ? 'declined' : 'cancelled'} the request for ${required.join(', ')}`, -32602);
+    }
+    return response.content || {};
+  }
+
+  // timeout: the longest the whole call is expected to take (ms); the plugin manager fails
+  // the call once it is spent, independent of the per-command timeout
+  createTool(name, description, inputSchema, { timeout = null } = {}) {
     return {
       name,
       description,
@@ -43,7 +197,7 @@ export class BasePlugin {
         properties: {},
         required: []
       },
-      handler
+      ...(timeout ? { timeout } : {})
     };
   }
 
@@ -56,6 +210,23 @@ export class BasePlugin {
     };
   }
 
+  createResourceTemplate(uriTemplate, name, description, mimeType = 'text/plain') {
+    return {
+      uriTemplate,
+      name,
+      description,
+      mimeType
+    };
+  }
+
+  // Binary resource contents; PluginManager wraps them in the MCP contents shape
+  createBlobContent(data, mimeType) {
+    return {
+      blob: Buffer.from(data).toString('base64'),
+      mimeType
+    };
+  }
+
   createContent(type, content, metadata = {}) {
     const baseContent = {
       type,
@@ -108,12 +279,9 @@ export class BasePlugin {
 
   async validateArgs(args, schema) {
     // Basic validation
-    if (schema.required) {
-      for (const field of schema.required) {
-        if (args[field] === undefined) {
-          throw new Error(`Required argument missing: ${field}`);
-        }
-      }
+    const missing = (schema.required || []).filter(field => args[field] === undefined);
+    if (missing.length > 0) {
+      await this.elicitMissing(args, schema, missing);
     }
 
     if (schema.properties) {
@@ -121,8 +289,8 @@ export class BasePlugin {
         const value = args[key];
         
         if (value !== undefined) {
-          if (prop.type && typeof value !== prop.type) {
-            throw new Error(`Invalid type for argument ${key}: expected ${prop.type}, got ${typeof value}`);
+          if (prop.type && !this.matchesSchemaType(value, prop.type)) {
+            throw new Error(`Invalid type for argument ${key}: expected ${prop.type}, got ${Array.isArray(value) ? 'array' : typeof value}`);
           }
 
           if (prop.enum && !prop.enum.includes(value)) {
@@ -139,6 +307,46 @@ export class BasePlugin {
     return true;
   }
 
+  // Fill missing required primitives by asking the user; anything else is invalid params
+  async elicitMissing(args, schema, missing) {
+    const properties = Object.fromEntries(missing.map(field => [field, schema.properties?.[field] || {}]));
+    const elicitable = Object.values(properties).every(prop => ELICITABLE_TYPES.includes(prop.type));
+    if (!elicitable || !getContext().client?.capabilities?.elicitation) {
+      throw new MCPError(`Required argument missing: ${missing[0]}`, -32602);
+    }
+
+    const requested = Object.fromEntries(Object.entries(properties).map(([field, prop]) => [field, {
+      type: prop.type,
+      title: field,
+      ...(prop.description ? { description: prop.description } : {}),
+      ...(prop.enum ? { enum: prop.enum } : {}),
+      ...(prop.pattern ? { pattern: prop.pattern } : {}),
+      ...(prop.default !== undefined ? { default: prop.default } : {})
+    }]));
+
+    const tool = getContext().tool;
+    const content = await this.elicit(`${tool || this.name} needs: ${missing.join(', ')}`, requested, missing);
+    for (const field of missing) {
+      if (content[field] === undefined) {
+        throw new MCPError(`Required argument missing: ${field}`, -32602);
+      }
+      args[field] = content[field];
+    }
+  }
+
+  matchesSchemaType(value, type) {
+    switch (type) {
+      case 'array':
+        return Array.isArray(value);
+      case 'integer':
+        return Number.isInteger(value);
+      case 'object':
+        return typeof value === 'object' && value !== null && !Array.isArray(value);
+      default:
+        return typeof value === type;
+    }
+  }
+
   async withErrorHandling(operation, operationName) {
     try {
       const result = await operation();
@@ -0,0 +1,210 @@
+import { BasePlugin, pluginConfig } from './base-plugin.js';
+import { LockKeys } from '../security/lock-manager.js';
+import { detectCompositor } from '../system/compositor.js';
+
+// Compositor-neutral window management; works on Hyprland and Sway
+export class CompositorPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('compositor', config, logger, security);
+    this.description = 'Window, workspace and monitor control for Hyprland and Sway';
+    const settings = pluginConfig(config, 'compositor');
+    this.type = settings.type || 'auto';
+    this.socketPath = settings.socketPath || null;
+    this.compositor = null;
+
+    this.initializeTools();
+    this.initializeResources();
+  }
+
+  async initialize() {
+    await super.initialize();
+
+    this.compositor = await detectCompositor({ type: this.type, socketPath: this.socketPath });
+
+    if (!this.compositor) {
+      this.logger.warn('No supported compositor detected - compositor tools unavailable');
+    } else {
+      this.logger.info(`Compositor plugin using ${this.compositor.name} at ${this.compositor.socketPath}`);
+    }
+  }
+
+  async getHealthChecks() {
+    const available = this.compositor ? await this.compositor.isAvailable() : false;
+    return [{
+      name: 'compositor-ipc',
+      status: available ? 'pass' : 'warn',
+      compositor: this.compositor?.name || null,
+      path: this.compositor?.socketPath || null
+    }];
+  }
+
+  initializeTools() {
+    this.tools = [
+      this.createTool(
+        'compositor_windows',
+        'List windows with class, title, workspace and geometry',
+        {
+          type: 'object',
+          properties: {}
+        }
+      ),
+
+      this.createTool(
+        'compositor_workspaces',
+        'List workspaces and the monitor each is on',
+        {
+          type: 'object',
+          properties: {}
+        }
+      ),
+
+      this.createTool(
+        'compositor_monitors',
+        'List monitors with geometry, scale and active workspace',
+        {
+          type: 'object',
+          properties: {}
+        }
+      ),
+
+      this.createTool(
+        'compositor_window_control',
+        'Focus, move, resize, close, float or fullscreen a window',
+        {
+          type: 'object',
+          properties: {
+            action: {
+              type: 'string',
+              enum: ['focus', 'move', 'resize', 'close', 'float', 'fullscreen'],
+              description: 'Window action'
+            },
+            target: {
+              type: 'string',
+              description: 'Direction (left, right, up, down), window id/class/title for focus, or workspace for move',
+              default: ''
+            },
+            args: {
+              type: 'string',
+              description: 'Resize amount as "<width> <height>"',
+              default: ''
+            }
+          },
+          required: ['action']
+        }
+      ),
+
+      this.createTool(
+        'compositor_reload',
+        'Reload the compositor configuration',
+        {
+          type: 'object',
+          properties: {}
+        }
+      )
+    ];
+  }
+
+  initializeResources() {
+    this.resources = [
+      this.createResource(
+        'compositor://status',
+        'Compositor Status',
+        'Detected compositor, version and focused window',
+        'application/json'
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    if (!this.compositor) {
+      throw new Error('No supported compositor is available');
+    }
+
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'compositor_windows':
+          return this.createTextResult(JSON.stringify(await this.compositor.windows(), null, 2));
+        case 'compositor_workspaces':
+          return this.createTextResult(JSON.stringify(await this.compositor.workspaces(), null, 2));
+        case 'compositor_monitors':
+          return this.createTextResult(JSON.stringify(await this.compositor.monitors(), null, 2));
+        case 'compositor_window_control':
+          return this.handleWindowControl(args);
+        case 'compositor_reload':
+          await this.compositor.reload();
+          return this.createTextResult(`${this.compositor.name} configuration reloaded`);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  async readResource(uri) {
+    return this.withErrorHandling(async () => {
+      switch (uri) {
+        case 'compositor://status':
+          return this.getStatus();
+        default:
+          throw new Error(`Unknown resource: ${uri}`);
+      }
+    }, 'readResource');
+  }
+
+  isIdempotent(toolName) {
+    return ['compositor_windows', 'compositor_workspaces', 'compositor_monitors'].includes(toolName);
+  }
+
+  isReadOnly(toolName) {
+    return ['compositor_windows', 'compositor_workspaces', 'compositor_monitors'].includes(toolName);
+  }
+
+  disruptsUser(toolName) {
+    return ['compositor_window_control', 'compositor_reload'].includes(toolName);
+  }
+
+  getToolLocks(toolName) {
+    if (toolName === 'compositor_reload' && this.compositor?.name === 'hyprland') {
+      return [LockKeys.hyprlandConfig()];
+    }
+    return [];
+  }
+
+  async handleWindowControl(args) {
+    await this.validateArgs(args, this.tools[3].inputSchema);
+
+    const { action, target = '', args: actionArgs = '' } = args;
+    const result = await this.compositor.windowAction(action, target, actionArgs);
+    return this.createTextResult(result || `Window ${action} executed`);
+  }
+
+  async getStatus() {
+    if (!this.compositor) {
+      return { content: JSON.stringify({ available: false }, null, 2) };
+    }
+
+    try {
+      const [version, activeWindow] = await Promise.all([
+        this.compositor.version(),
+        this.compositor.activeWindow()
+      ]);
+
+      return {
+        content: JSON.stringify({
+          available: true,
+          compositor: this.compositor.name,
+          version,
+          activeWindow,
+          socketPath: this.compositor.socketPath
+        }, null, 2)
+      };
+    } catch (error) {
+      return {
+        content: JSON.stringify({
+          available: false,
+          compositor: this.compositor.name,
+          error: error.message
+        }, null, 2)
+      };
+    }
+  }
+}
@@ -0,0 +1,789 @@
+import { BasePlugin, pluginConfig } from './base-plugin.js';
+import { CommandExecutor } from '../system/command-executor.js';
+import {
+  MODPROBE_FILE,
+  MODULES_LOAD_FILE,
+  editConfLines,
+  loadedModules,
+  normalizeModuleName,
+  persistedModuleConfig
+} from '../system/kernel-modules.js';
+import { RULE_PREFIX, UDEV_RULES_DIR, describeTemplates, renderRule, ruleFile } from '../system/udev-rules.js';
+import { previewDiff } from '../system/kernel-params.js';
+import {
+  PartitionTable, FILESYSTEM_RESIZE, NAMED_PARTITION_TYPES, btrfsDevid, filesystemResizeCommands, parentDisk, resolvePartitionType
+} from '../system/partition-table.js';
+import { commandLine } from '../system/partition-plan.js';
+import { LockKeys } from '../security/lock-manager.js';
+import fs from 'fs-extra';
+import os from 'os';
+import path from 'path';
+
+const OPTION_KEY = /^[a-zA-Z0-9_]+$/;
+const OPTION_VALUE = /^[a-zA-Z0-9_.,:+-]+$/;
+
+// Kernel modules (modprobe, with modprobe.d and modules-load.d persistence), udev
+// rules generated from validated templates, and GPT/MBR partition tables
+export class DevicesPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('devices', config, logger, security);
+    this.description = 'Kernel modules, udev rules and partition tables';
+    this.commandExecutor = new CommandExecutor(
+      config.get?.('security') || config.security || {},
+      logger,
+      security
+    );
+    this.partitionTable = new PartitionTable(this.commandExecutor, logger, {
+      backupDir: pluginConfig(config, 'devices').partitionBackupDir || null
+    });
+    this.dependencies = ['modprobe', 'udevadm', 'mkinitcpio', 'sfdisk', 'partx', 'resize2fs', 'e2fsck', 'btrfs', 'xfs_growfs', 'resize.f2fs'];
+    // Each filesystem's resize tool is only needed to resize that filesystem
+    this.optionalDependencies = ['mkinitcpio', 'resize2fs', 'e2fsck', 'btrfs', 'xfs_growfs', 'resize.f2fs'];
+
+    this.initializeTools();
+    this.initializeResources();
+  }
+
+  initializeTools() {
+    this.tools = [
+      this.createTool(
+        'module_list',
+        'List loaded kernel modules with their users, and what modprobe.d and modules-load.d configure',
+        {
+          type: 'object',
+          properties: {
+            filter: {
+              type: 'string',
+              description: 'Only modules whose name contains this'
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'module_load',
+        'Load a kernel module, optionally with options, and optionally load it at every boot',
+        {
+          type: 'object',
+          properties: {
+            name: { type: 'string', pattern: '^[a-zA-Z0-9_-]+$' },
+            options: {
+              type: 'object',
+              description: 'Module parameters, e.g. {"power_save": "1"}; persisted as an options line'
+            },
+            persist: {
+              type: 'boolean',
+              description: 'Add it to modules-load.d (and its options to modprobe.d)',
+              default: false
+            },
+            dryRun: {
+              type: 'boolean',
+              default: false
+            }
+          },
+          required: ['name']
+        }
+      ),
+
+      this.createTool(
+        'module_blacklist',
+        'Blacklist a kernel module in modprobe.d, or lift a blacklist added here',
+        {
+          type: 'object',
+          properties: {
+            name: { type: 'string', pattern: '^[a-zA-Z0-9_-]+$' },
+            action: {
+              type: 'string',
+              enum: ['add', 'remove'],
+              default: 'add'
+            },
+            hard: {
+              type: 'boolean',
+              description: 'Also refuse to load it as another module\'s dependency (install <name> /bin/false)',
+              default: false
+            },
+            unload: {
+              type: 'boolean',
+              description: 'Unload it now if nothing is using it',
+              default: false
+            },
+            regenerateInitramfs: {
+              type: 'boolean',
+              description: 'Run mkinitcpio -P so early boot honours the change',
+              default: false
+            },
+            dryRun: {
+              type: 'boolean',
+              default: false
+            }
+          },
+          required: ['name']
+        }
+      ),
+
+      this.createTool(
+        'udev_rule_create',
+        'Write a udev rule from a template (usb-device, serial-device, hidraw, network-name), reload udev and trigger it',
+        {
+          type: 'object',
+          properties: {
+            name: {
+              type: 'string',
+              description: `Rule name; the file is ${UDEV_RULES_DIR}/${RULE_PREFIX}<name>.rules`,
+              pattern: '^[a-zA-Z0-9][a-zA-Z0-9_-]{0,63}$'
+            },
+            template: {
+              type: 'string',
+              enum: ['usb-device', 'serial-device', 'hidraw', 'network-name']
+            },
+            params: {
+              type: 'object',
+              description: 'Template fields: vendorId, productId, mode, group, uaccess, symlink, mac, interfaceName (see udev_rules)'
+            },
+            overwrite: {
+              type: 'boolean',
+              default: false
+            },
+            dryRun: {
+              type: 'boolean',
+              default: false
+            }
+          },
+          required: ['name', 'template', 'params']
+        }
+      ),
+
+      this.createTool(
+        'udev_rule_remove',
+        'Remove a udev rule created with udev_rule_create and reload udev',
+        {
+          type: 'object',
+          properties: {
+            name: { type: 'string', pattern: '^[a-zA-Z0-9][a-zA-Z0-9_-]{0,63}$' }
+          },
+          required: ['name']
+        }
+      ),
+
+      this.createTool(
+        'udev_rules',
+        'List udev rules created here and the available templates with their fields',
+        {
+          type: 'object',
+          properties: {}
+        }
+      ),
+
+      this.createTool(
+        'partition_list',
+        'Partition tables (GPT or MBR) of one or all disks: each partition\'s number, start, size, type and name, its filesystem and mounts, and the free gaps',
+        {
+          type: 'object',
+          properties: {
+            device: {
+              type: 'string',
+              description: 'Disk (default: every disk)',
+              pattern: '^/dev/[A-Za-z0-9/_.:-]+$'
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'partition_resize',
+        'Grow or shrink a partition in place, resizing its filesystem along (ext2/3/4, btrfs, swap; xfs and f2fs grow only). ' +
+          'The table is backed up first; use dryRun to see the steps',
+        {
+          type: 'object',
+          properties: {
+            device: {
+              type: 'string',
+              description: 'Partition (e.g., /dev/sda3, /dev/nvme0n1p2)',
+              pattern: '^/dev/[A-Za-z0-9/_.:-]+$'
+            },
+            size: {
+              type: 'string',
+              description: 'New size (e.g., 40G), a change (+10G, -5G) or "max" for all the space up to the next partition',
+              pattern: '^(max|[+-]?\\d+(\\.\\d+)?[KMGT]B?)$'
+            },
+            resizeFilesystem: {
+              type: 'boolean',
+              description: 'Resize the filesystem with the partition. Only growing may turn this off, leaving the filesystem (or LUKS, LVM) to be grown separately',
+              default: true
+            },
+            dryRun: {
+              type: 'boolean',
+              default: false
+            }
+          },
+          required: ['device', 'size']
+        },
+        { timeout: 3600000 } // fsck and resize2fs of a large, full filesystem
+      ),
+
+      this.createTool(
+        'partition_set',
+        'Change a partition\'s type and, on GPT, its name (PARTLABEL); data is untouched. The table is backed up first',
+        {
+          type: 'object',
+          properties: {
+            device: {
+              type: 'string',
+              description: 'Partition (e.g., /dev/sda3)',
+              pattern: '^/dev/[A-Za-z0-9/_.:-]+$'
+            },
+            type: {
+              type: 'string',
+              description: `Type name (${Object.keys(NAMED_PARTITION_TYPES).join(', ')}), a GPT type GUID, or an MBR id such as 83`
+            },
+            name: {
+              type: 'string',
+              description: 'GPT partition name, up to 36 characters; empty to clear it',
+              pattern: '^[^"\\\\\\n]{0,36}$'
+            },
+            dryRun: {
+              type: 'boolean',
+              default: false
+            }
+          },
+          required: ['device']
+        }
+      ),
+
+      this.createTool(
+        'partition_rescan',
+        'Make the kernel re-read a disk\'s partition table (partx), e.g. after another tool changed it; in-use partitions are updated in place where possible',
+        {
+          type: 'object',
+          properties: {
+            device: {
+              type: 'string',
+              description: 'Disk (e.g., /dev/sdb)',
+              pattern: '^/dev/[A-Za-z0-9/_.:-]+$'
+            }
+          },
+          required: ['device']
+        }
+      )
+    ];
+  }
+
+  initializeResources() {
+    this.resources = [
+      this.createResource(
+        'devices://modules',
+        'Kernel Modules',
+        'Loaded modules, their users, and persisted blacklist, options and boot-time loading',
+        'application/json'
+      ),
+      this.createResource(
+        'devices://partitions',
+        'Partition Tables',
+        'Partition tables of every disk, with filesystems, mounts and free gaps',
+        'application/json'
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'module_list':
+          return this.handleModuleList(args);
+        case 'module_load':
+          return this.handleModuleLoad(args);
+        case 'module_blacklist':
+          return this.handleModuleBlacklist(args);
+        case 'udev_rule_create':
+          return this.handleRuleCreate(args);
+        case 'udev_rule_remove':
+          return this.handleRuleRemove(args);
+        case 'udev_rules':
+          return this.handleRules(args);
+        case 'partition_list':
+          return this.handlePartitionList(args);
+        case 'partition_resize':
+          return this.handlePartitionResize(args);
+        case 'partition_set':
+          return this.handlePartitionSet(args);
+        case 'partition_rescan':
+          return this.handlePartitionRescan(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  async readResource(uri) {
+    return this.withErrorHandling(async () => {
+      switch (uri) {
+        case 'devices://modules':
+          return { content: JSON.stringify(await this.moduleReport(), null, 2) };
+        case 'devices://partitions':
+          return { content: JSON.stringify(await this.partitionTables(), null, 2) };
+        default:
+          throw new Error(`Unknown resource: ${uri}`);
+      }
+    }, 'readResource');
+  }
+
+  isIdempotent(toolName, args = {}) {
+    return ['module_list', 'udev_rules', 'partition_list', 'partition_rescan', 'partition_set'].includes(toolName) || Boolean(args.dryRun);
+  }
+
+  isReadOnly(toolName, args = {}) {
+    return ['module_list', 'udev_rules', 'partition_list'].includes(toolName) || super.isReadOnly(toolName, args);
+  }
+
+  // Partition edits hold the whole disk, like the installer's disk tools
+  getToolLocks(toolName, args = {}) {
+    if (!args.device) {
+      return [];
+    }
+    switch (toolName) {
+      case 'partition_resize':
+      case 'partition_set':
+        return [LockKeys.device(parentDisk(args.device))];
+      case 'partition_rescan':
+        return [LockKeys.device(args.device)];
+      default:
+        return [];
+    }
+  }
+
+  // A failed shrink can cut off data
+  isDestructive(toolName, args = {}) {
+    return toolName === 'partition_resize' && !this.isReadOnly(toolName, args);
+  }
+
+  inhibitsIdle(toolName, args = {}) {
+    return toolName === 'partition_resize' && !this.isReadOnly(toolName, args);
+  }
+
+  async getAffectedPaths(toolName, args = {}) {
+    if (args.dryRun || !args.name) {
+      return [];
+    }
+    switch (toolName) {
+      case 'module_load':
+        return args.persist ? [MODULES_LOAD_FILE, MODPROBE_FILE] : [];
+      case 'module_blacklist':
+        return [MODPROBE_FILE];
+      case 'udev_rule_create':
+      case 'udev_rule_remove':
+        return [ruleFile(args.name)];
+      default:
+        return [];
+    }
+  }
+
+  async handleModuleList(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const report = await this.moduleReport(args.filter);
+    return this.createTextResult(JSON.stringify(report, null, 2), { count: report.loaded.length });
+  }
+
+  async handleModuleLoad(args) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+
+    const { options = {}, persist = false, dryRun = false } = args;
+    const name = normalizeModuleName(args.name);
+    const optionArgs = Object.entries(options).map(([key, value]) => {
+      if (!OPTION_KEY.test(key) || !OPTION_VALUE.test(String(value))) {
+        throw new Error(`Invalid module option: ${key}=${value}`);
+      }
+      return `${key}=${value}`;
+    });
+
+    const config = await persistedModuleConfig();
+    if (config.blacklisted[name]) {
+      throw new Error(`${name} is blacklisted in ${config.blacklisted[name]}; lift it with module_blacklist action remove first`);
+    }
+
+    const edits = persist ? [
+      await this.planEdit(MODULES_LOAD_FILE, { add: [name] }),
+      optionArgs.length > 0
+        ? await this.planEdit(MODPROBE_FILE, {
+          add: [`options ${name} ${optionArgs.join(' ')}`],
+          remove: line => line.startsWith(`options ${name} `)
+        })
+        : null
+    ].filter(edit => edit && edit.before !== edit.after) : [];
+
+    const loaded = (await loadedModules()).some(module => module.name === name);
+    const steps = [
+      loaded ? `${name} is already loaded${optionArgs.length > 0 ? '; new options apply after it is reloaded' : ''}` : `modprobe ${name} ${optionArgs.join(' ')}`.trim(),
+      ...edits.map(edit => previewDiff(edit.file, edit.before, edit.after))
+    ];
+
+    if (dryRun) {
+      return this.createTextResult(steps.join('\n'), { dryRun, loaded });
+    }
+
+    if (!loaded) {
+      const result = await this.commandExecutor.executeWithSudo('modprobe', [name, ...optionArgs], { retry: false });
+      if (!result.success) {
+        throw new Error(`modprobe ${name} failed: ${result.stderr.trim()}`);
+      }
+    }
+    await this.writeEdits(edits);
+
+    this.logger.audit('module_load', { name, options, persist });
+    return this.createTextResult(steps.join('\n'), { loaded: true, persisted: persist });
+  }
+
+  async handleModuleBlacklist(args) {
+    await this.validateArgs(args, this.tools[2].inputSchema);
+
+    const { action = 'add', hard = false, unload = false, regenerateInitramfs = false, dryRun = false } = args;
+    const name = normalizeModuleName(args.name);
+    const ours = line => line === `blacklist ${name}` || line === `install ${name} /bin/false`;
+
+    const edit = action === 'add'
+      ? await this.planEdit(MODPROBE_FILE, { add: [`blacklist ${name}`, ...(hard ? [`install ${name} /bin/false`] : [])] })
+      : await this.planEdit(MODPROBE_FILE, { remove: ours });
+
+    if (action === 'remove' && edit.before === edit.after) {
+      const config = await persistedModuleConfig();
+      throw new Error(config.blacklisted[name]
+        ? `${name} is blacklisted in ${config.blacklisted[name]}, not by this server; edit that file instead`
+        : `${name} is not blacklisted`);
+    }
+
+    const module = (await loadedModules()).find(candidate => candidate.name === name);
+    if (action === 'add' && unload && module && module.refcount > 0) {
+      throw new Error(`${name} is in use (${module.refcount} references${module.usedBy.length > 0 ? `, used by ${module.usedBy.join(', ')}` : ''}); blacklist without unload and reboot`);
+    }
+
+    const steps = [
+      previewDiff(edit.file, edit.before, edit.after),
+      action === 'add' && unload && module ? `modprobe -r ${name}` : null,
+      regenerateInitramfs ? 'mkinitcpio -P' : null
+    ].filter(Boolean);
+
+    if (dryRun) {
+      return this.createTextResult(steps.join('\n'), { dryRun });
+    }
+
+    await this.writeEdits([edit]);
+    if (action === 'add' && unload && module) {
+      const result = await this.commandExecutor.executeWithSudo('modprobe', ['-r', name], { retry: false });
+      if (!result.success) {
+        throw new Error(`Blacklisted, but modprobe -r ${name} failed: ${result.stderr.trim()}`);
+      }
+    }
+    if (regenerateInitramfs) {
+      const result = await this.commandExecutor.executeWithSudo('mkinitcpio', ['-P'], { timeout: 900000 });
+      if (!result.success) {
+        throw new Error(`Blacklist written, but mkinitcpio -P failed: ${result.stderr.trim().split('\n').slice(-5).join('\n')}`);
+      }
+    }
+
+    this.logger.audit('module_blacklist', { name, action, hard, unload });
+    const note = action === 'add' && module && !unload ? `\n${name} stays loaded until it is unloaded or the system reboots` : '';
+    return this.createTextResult(`${steps.join('\n')}${note}`, { name, action });
+  }
+
+  async handleRuleCreate(args) {
+    await this.validateArgs(args, this.tools[3].inputSchema);
+
+    const { name, template, params, overwrite = false, dryRun = false } = args;
+    const file = ruleFile(name);
+    const rule = await renderRule(template, params);
+    const before = await fs.readFile(file, 'utf8').catch(() => null);
+
+    if (before !== null && !overwrite && before !== rule.content) {
+      throw new Error(`${file} already exists; set overwrite to replace it`);
+    }
+    const diff = previewDiff(file, before, rule.content);
+
+    if (dryRun) {
+      return this.createTextResult(diff || rule.content, { file, dryRun });
+    }
+
+    await fs.ensureDir(UDEV_RULES_DIR);
+    await fs.writeFile(file, rule.content, { mode: 0o644 });
+
+    const verify = await this.verifyRule(file);
+    if (verify && !verify.success) {
+      if (before === null) {
+        await fs.remove(file);
+      } else {
+        await fs.writeFile(file, before);
+      }
+      throw new Error(`udevadm verify rejected the rule: ${verify.stderr.trim() || verify.stdout.trim()}`);
+    }
+
+    await this.reloadUdev(rule.subsystem);
+    this.logger.audit('udev_rule_create', { name, template, params });
+    return this.createTextResult(`${file}:\n${rule.content}\nReloaded udev and triggered ${rule.subsystem} devices; replug the device if it was already connected`, {
+      file,
+      subsystem: rule.subsystem
+    });
+  }
+
+  async handleRuleRemove(args) {
+    await this.validateArgs(args, this.tools[4].inputSchema);
+
+    const file = ruleFile(args.name);
+    if (!await fs.pathExists(file)) {
+      throw new Error(`No rule ${file}`);
+    }
+    await fs.remove(file);
+    await this.reloadUdev(null);
+
+    this.logger.audit('udev_rule_remove', { name: args.name });
+    return this.createTextResult(`Removed ${file} and reloaded udev`);
+  }
+
+  async handleRules() {
+    const names = (await fs.readdir(UDEV_RULES_DIR).catch(() => []))
+      .filter(file => file.startsWith(RULE_PREFIX) && file.endsWith('.rules'))
+      .sort();
+
+    const rules = [];
+    for (const file of names) {
+      rules.push({
+        name: file.slice(RULE_PREFIX.length, -'.rules'.length),
+        file: path.join(UDEV_RULES_DIR, file),
+        content: await fs.readFile(path.join(UDEV_RULES_DIR, file), 'utf8')
+      });
+    }
+    return this.createTextResult(JSON.stringify({ rules, templates: describeTemplates() }, null, 2));
+  }
+
+  async handlePartitionList(args) {
+    await this.validateArgs(args, this.tools[6].inputSchema);
+
+    const tables = await this.partitionTables(args.device);
+    return this.createTextResult(JSON.stringify(args.device ? tables[0] : tables, null, 2), { disks: tables.length });
+  }
+
+  async handlePartitionResize(args) {
+    await this.validateArgs(args, this.tools[7].inputSchema);
+
+    const { device, size, resizeFilesystem = true, dryRun = false } = args;
+    const located = await this.partitionTable.locate(device);
+    const { disk, number, table, partition } = located;
+
+    if (table.label === 'dos' && (number > 4 || ['5', '05', 'f', '0f', '85'].includes(String(partition.type).toLowerCase()))) {
+      throw new Error(`${device} is an extended or logical MBR partition; only primary partitions can be resized here`);
+    }
+
+    const { sectors } = this.partitionTable.sizeFor(located, size);
+    const bytes = sectors * table.sectorSize;
+    if (sectors === partition.sectors) {
+      return this.createTextResult(`${device} is already ${bytes} bytes`, { device, size: bytes, changed: false });
+    }
+    const grow = sectors > partition.sectors;
+
+    // What the filesystem needs: only growing may leave it as it is
+    const { fstype } = partition;
+    const mountpoint = partition.mountpoints.find(entry => entry !== '[SWAP]') || null;
+    const active = partition.mountpoints.length > 0;
+    let needs = null;
+    if (resizeFilesystem && fstype) {
+      needs = FILESYSTEM_RESIZE[fstype]?.[grow ? 'grow' : 'shrink'];
+      if (!needs) {
+        throw new Error(`${fstype} on ${device} cannot be ${grow ? 'grown' : 'shrunk'} here` +
+          (grow ? '; set resizeFilesystem to false to grow only the partition and resize the filesystem (or LUKS, LVM) yourself' : ''));
+      }
+      if (needs === 'unmounted' && active) {
+        throw new Error(`${fstype} on ${device} must not be in use to ${grow ? 'grow' : 'shrink'}; ${fstype === 'swap' ? 'swapoff' : 'unmount'} it first`);
+      }
+    } else if (!grow) {
+      throw new Error(`Shrinking ${device} needs a filesystem that is shrunk along with it${fstype ? '' : '; none was found on it'}`);
+    }
+
+    // btrfs and xfs resize through a mount point; an unmounted one is mounted for the resize
+    const temporaryMount = needs === 'mounted' && !mountpoint;
+    const fsMount = temporaryMount ? path.join(os.tmpdir(), `partition-resize-${path.basename(device)}`) : mountpoint;
+    const devid = fstype === 'btrfs' && needs ? await this.btrfsDevid(device, mountpoint) : 1;
+    const fsSteps = needs
+      ? filesystemResizeCommands(fstype, {
+        device, mountpoint: needs === 'unmounted' ? null : fsMount, bytes: grow ? null : bytes, devid, uuid: partition.fsUuid, label: partition.fsLabel
+      })
+      : [];
+    const partitionStep = { command: 'sfdisk', args: ['--no-reread', '--no-tell-kernel', '-N', String(number), disk], input: `size=${sectors}\n` };
+    const steps = [
+      ...(temporaryMount ? [{ command: 'mount', args: [device, fsMount] }] : []),
+      ...(grow ? [partitionStep, { command: 'partx', args: ['--update', '--nr', String(number), disk] }, ...fsSteps]
+        : [...fsSteps, partitionStep, { command: 'partx', args: ['--update', '--nr', String(number), disk] }]),
+      ...(temporaryMount ? [{ command: 'umount', args: [fsMount] }] : [])
+    ];
+    const plan = {
+      device,
+      disk,
+      number,
+      from: partition.size,
+      to: bytes,
+      filesystem: fstype,
+      steps: steps.map(commandLine)
+    };
+
+    if (dryRun) {
+      return this.createTextResult(JSON.stringify(plan, null, 2), { dryRun, device });
+    }
+
+    const backup = await this.partitionTable.backup(disk);
+    if (temporaryMount) {
+      await fs.ensureDir(fsMount);
+      await this.runStep({ command: 'mount', args: [device, fsMount] });
+    }
+    try {
+      if (!grow) {
+        for (const step of fsSteps) await this.runStep(step);
+      }
+      await this.partitionTable.sfdisk(['-N', String(number), disk], partitionStep.input);
+      const rescan = await this.partitionTable.rescan(disk, { number, partition: device });
+      if (!rescan.success) {
+        throw new Error(`The partition table was changed, but the kernel still sees the old size (${rescan.stderr.trim()}); ` +
+          `reboot or run partition_rescan before using ${device}${grow && fsSteps.length > 0 ? ', then grow the filesystem' : ''}`);
+      }
+      if (grow) {
+        for (const step of fsSteps) await this.runStep(step);
+      }
+    } finally {
+      if (temporaryMount) {
+        await this.commandExecutor.executeWithSudo('umount', [fsMount], { retry: false });
+        await fs.rmdir(fsMount).catch(() => {});
+      }
+    }
+
+    this.logger.audit('partition_resize', { device, from: partition.size, to: bytes, filesystem: fstype, backup });
+    return this.createTextResult(JSON.stringify({ ...plan, backup }, null, 2), { device, size: bytes, backup });
+  }
+
+  async handlePartitionSet(args) {
+    await this.validateArgs(args, this.tools[8].inputSchema);
+
+    const { device, dryRun = false } = args;
+    if (args.type === undefined && args.name === undefined) {
+      throw new Error('Nothing to change: pass type, name or both');
+    }
+    const { disk, number, table, partition } = await this.partitionTable.locate(device);
+    if (args.name !== undefined && table.label !== 'gpt') {
+      throw new Error(`${disk} has an MBR partition table, which has no partition names`);
+    }
+
+    const changes = [];
+    if (args.type !== undefined) {
+      const type = resolvePartitionType(table.label, args.type);
+      if (type.toLowerCase() !== String(partition.type).toLowerCase()) {
+        changes.push({ field: 'type', from: partition.type, to: type, args: ['--part-type', disk, String(number), type] });
+      }
+    }
+    if (args.name !== undefined && args.name !== partition.name) {
+      changes.push({ field: 'name', from: partition.name, to: args.name, args: ['--part-label', disk, String(number), args.name] });
+    }
+
+    const summary = changes.map(({ field, from, to }) => ({ field, from, to }));
+    if (dryRun || changes.length === 0) {
+      return this.createTextResult(changes.length === 0 ? `${device} already has that type and name` : JSON.stringify({ device, changes: summary }, null, 2), {
+        device, dryRun, changed: false
+      });
+    }
+
+    const backup = await this.partitionTable.backup(disk);
+    for (const change of changes) {
+      await this.partitionTable.sfdisk(change.args);
+    }
+    await this.partitionTable.rescan(disk, { number, partition: device });
+
+    this.logger.audit('partition_set', { device, changes: summary, backup });
+    return this.createTextResult(JSON.stringify({ device, changes: summary, backup }, null, 2), { device, changed: true, backup });
+  }
+
+  async handlePartitionRescan(args) {
+    await this.validateArgs(args, this.tools[9].inputSchema);
+
+    const { device } = args;
+    const result = await this.partitionTable.rescan(device);
+    if (!result.success) {
+      throw new Error(`partx --update ${device} failed: ${result.stderr.trim()}; partitions in use keep their old geometry until they are released or the system reboots`);
+    }
+    const table = await this.partitionTable.withFilesystems(await this.partitionTable.read(device));
+    return this.createTextResult(JSON.stringify(table, null, 2), { device, partitions: table.partitions.length });
+  }
+
+  async partitionTables(device = null) {
+    const disks = device ? [device] : await this.partitionTable.listDisks();
+    const tables = [];
+    for (const disk of disks) {
+      tables.push(await this.partitionTable.withFilesystems(await this.partitionTable.read(disk)));
+    }
+    return tables;
+  }
+
+  async btrfsDevid(device, mountpoint) {
+    const result = await this.commandExecutor.executeWithSudo('btrfs', ['filesystem', 'show', mountpoint || device], { retry: false });
+    return (result.success && btrfsDevid(result.stdout, device)) || 1;
+  }
+
+  async runStep({ command, args, okExitCodes = [0] }) {
+    const result = await this.commandExecutor.executeWithSudo(command, args, { retry: false, timeout: 3600000 });
+    if (!result.success && !okExitCodes.includes(result.exitCode)) {
+      throw new Error(`${commandLine({ command, args })} failed: ${(result.stderr || result.stdout).trim().split('\n').slice(-5).join('\n')}`);
+    }
+    return result;
+  }
+
+  async moduleReport(filter = null) {
+    const config = await persistedModuleConfig();
+    const loaded = (await loadedModules())
+      .filter(module => !filter || module.name.includes(filter))
+      .map(module => ({
+        ...module,
+        ...(config.options[module.name] ? { options: config.options[module.name] } : {}),
+        ...(config.loadAtBoot[module.name] ? { loadAtBoot: config.loadAtBoot[module.name] } : {})
+      }));
+    return {
+      loaded,
+      blacklisted: config.blacklisted,
+      loadAtBoot: config.loadAtBoot
+    };
+  }
+
+  async planEdit(file, edit) {
+    const before = await fs.readFile(file, 'utf8').catch(() => null);
+    const after = editConfLines(before, edit);
+    return { file, before, after: after || (before === null ? null : '') };
+  }
+
+  async writeEdits(edits) {
+    for (const edit of edits) {
+      if (edit.before === edit.after) {
+        continue;
+      }
+      if (!edit.after) {
+        await fs.remove(edit.file);
+      } else {
+        await fs.ensureDir(path.dirname(edit.file));
+        await fs.writeFile(edit.file, edit.after, { mode: 0o644 });
+      }
+    }
+  }
+
+  // udevadm verify needs systemd 255; older versions have no way to check a rule offline
+  async verifyRule(file) {
+    const result = await this.commandExecutor.execute('udevadm', ['verify', file], { retry: false });
+    if (!result.success && /unknown command|invalid option|unrecognized/i.test(result.stderr)) {
+      return null;
+    }
+    return result;
+  }
+
+  async reloadUdev(subsystem) {
+    const reload = await this.commandExecutor.executeWithSudo('udevadm', ['control', '--reload'], { retry: false });
+    if (!reload.success) {
+      throw new Error(`udevadm control --reload failed: ${reload.stderr.trim()}`);
+    }
+    if (subsystem) {
+      const trigger = await this.commandExecutor.executeWithSudo('udevadm', ['trigger', '--action=add', `--subsystem-match=${subsystem}`], { retry: false });
+      if (!trigger.success) {
+        throw new Error(`udevadm trigger failed: ${trigger.stderr.trim()}`);
+      }
+    }
+  }
+}
@@ -0,0 +1,421 @@
+import { BasePlugin, pluginConfig } from './base-plugin.js';
+import { CommandExecutor } from '../system/command-executor.js';
+import { Git } from '../system/git.js';
+import { lookupUser, chownToUser } from '../system/user-account.js';
+import { assertWithinRoots, isWithinRoots } from '../security/roots.js';
+import fs from 'fs-extra';
+import os from 'os';
+import path from 'path';
+
+const TEMPLATE_SUFFIX = '.tmpl';
+
+export class DotfilesPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('dotfiles', config, logger, security);
+    this.description = 'Clone, apply, diff and commit a dotfiles repository';
+    const settings = pluginConfig(config, 'dotfiles');
+    this.repoPath = settings.repoPath || '.local/share/dotfiles';
+    this.defaultMode = settings.mode || 'symlink';
+    this.ignore = settings.ignore || ['README.md', 'LICENSE', '.gitignore'];
+    this.templateVars = settings.templateVars || {};
+    this.dependencies = ['git'];
+
+    this.commandExecutor = new CommandExecutor(
+      config.get?.('security') || config.security || {},
+      logger,
+      security
+    );
+    this.git = new Git(this.commandExecutor, logger);
+
+    this.initializeTools();
+  }
+
+  initializeTools() {
+    const user = {
+      type: 'string',
+      description: 'User whose home directory the dotfiles belong to'
+    };
+
+    this.tools = [
+      this.createTool(
+        'dotfiles_clone',
+        'Clone a dotfiles repository for a user, or fast-forward it if already cloned',
+        {
+          type: 'object',
+          properties: {
+            user,
+            url: {
+              type: 'string',
+              description: 'Repository URL'
+            },
+            branch: {
+              type: 'string',
+              description: 'Branch to check out'
+            }
+          },
+          required: ['user', 'url']
+        }
+      ),
+
+      this.createTool(
+        'dotfiles_apply',
+        'Install the repository into the user\'s home (dot_ prefixes become ".", *.tmpl files are rendered)',
+        {
+          type: 'object',
+          properties: {
+            user,
+            mode: {
+              type: 'string',
+              enum: ['symlink', 'copy'],
+              description: 'Link files into the repository or copy them (templates are always copied)'
+            },
+            force: {
+              type: 'boolean',
+              description: 'Overwrite files that differ from the repository (they are snapshotted first)',
+              default: false
+            },
+            dryRun: {
+              type: 'boolean',
+              description: 'Report what would change without touching the home directory',
+              default: false
+            }
+          },
+          required: ['user']
+        }
+      ),
+
+      this.createTool(
+        'dotfiles_diff',
+        'Compare the user\'s home directory against the repository',
+        {
+          type: 'object',
+          properties: {
+            user,
+            includeUnchanged: {
+              type: 'boolean',
+              description: 'List files that already match',
+              default: false
+            }
+          },
+          required: ['user']
+        }
+      ),
+
+      this.createTool(
+        'dotfiles_commit',
+        'Copy local edits back into the repository, commit them and optionally push',
+        {
+          type: 'object',
+          properties: {
+            user,
+            message: {
+              type: 'string',
+              description: 'Commit message'
+            },
+            capture: {
+              type: 'boolean',
+              description: 'Copy modified files from the home directory into the repository first',
+              default: true
+            },
+            push: {
+              type: 'boolean',
+              description: 'Push after committing',
+              default: false
+            }
+          },
+          required: ['user', 'message']
+        }
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'dotfiles_clone':
+          return this.handleClone(args);
+        case 'dotfiles_apply':
+          return this.handleApply(args);
+        case 'dotfiles_diff':
+          return this.handleDiff(args);
+        case 'dotfiles_commit':
+          return this.handleCommit(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  // Applying links or copies the same files again
+  isIdempotent(toolName) {
+    return ['dotfiles_apply', 'dotfiles_diff'].includes(toolName);
+  }
+
+  isReadOnly(toolName, args = {}) {
+    return ['dotfiles_diff'].includes(toolName) || super.isReadOnly(toolName, args);
+  }
+
+  async getAffectedPaths(toolName, args) {
+    if (toolName !== 'dotfiles_apply' || args.dryRun || !args.user) {
+      return [];
+    }
+
+    try {
+      const account = await lookupUser(args.user);
+      const entries = await this.collectEntries(account);
+      return entries.map(entry => entry.target).filter(target => isWithinRoots(target));
+    } catch {
+      return [];
+    }
+  }
+
+  async resolveRepo(userName) {
+    const account = await lookupUser(userName);
+    const repoDir = path.join(account.home, this.repoPath);
+    assertWithinRoots(repoDir);
+    return { account, repoDir };
+  }
+
+  async handleClone(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const { user, url, branch } = args;
+    const { account, repoDir } = await this.resolveRepo(user);
+
+    if (await fs.pathExists(path.join(repoDir, '.git'))) {
+      await this.git.pull(repoDir, { account });
+    } else {
+      await fs.ensureDir(path.dirname(repoDir));
+      await this.git.clone(url, repoDir, { branch, account });
+    }
+
+    const entries = await this.collectEntries(account);
+    return this.createTextResult(JSON.stringify({
+      repoDir,
+      head: (await this.git.run(repoDir, ['rev-parse', 'HEAD'], { account })).stdout,
+      managedFiles: entries.length
+    }, null, 2));
+  }
+
+  async handleApply(args) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+
+    const { user, mode = this.defaultMode, force = false, dryRun = false } = args;
+    const { account } = await this.resolveRepo(user);
+    const entries = await this.collectEntries(account);
+
+    const results = [];
+    for (const entry of entries) {
+      if (!isWithinRoots(entry.target)) {
+        results.push({ target: entry.target, action: 'outside-roots' });
+        continue;
+      }
+
+      const status = await this.entryStatus(entry, account, mode);
+      let action;
+
+      if (status === 'identical' || status === 'linked') {
+        action = 'unchanged';
+      } else if (status === 'modified' && !force) {
+        action = 'conflict';
+      } else {
+        action = status === 'missing' ? 'create' : 'overwrite';
+        if (!dryRun) {
+          await this.installEntry(entry, account, mode);
+        }
+      }
+
+      results.push({ target: entry.target, action });
+    }
+
+    const conflicts = results.filter(result => result.action === 'conflict');
+    return this.createTextResult(JSON.stringify({
+      dryRun,
+      mode,
+      changed: results.filter(result => ['create', 'overwrite'].includes(result.action)).length,
+      conflicts: conflicts.length,
+      ...(conflicts.length > 0 ? { hint: 'Run dotfiles_diff to inspect conflicts, then apply with force or commit them' } : {}),
+      files: results.filter(result => result.action !== 'unchanged')
+    }, null, 2));
+  }
+
+  async handleDiff(args) {
+    await this.validateArgs(args, this.tools[2].inputSchema);
+
+    const { user, includeUnchanged = false } = args;
+    const { account } = await this.resolveRepo(user);
+    const entries = await this.collectEntries(account);
+
+    const files = [];
+    for (const entry of entries.filter(candidate => isWithinRoots(candidate.target))) {
+      const status = await this.entryStatus(entry, account, this.defaultMode);
+      if (!includeUnchanged && (status === 'identical' || status === 'linked')) {
+        continue;
+      }
+
+      const file = { target: entry.target, source: entry.relative, status };
+      if (status === 'modified') {
+        file.diff = await this.renderDiff(entry, account);
+      }
+      files.push(file);
+    }
+
+    return this.createTextResult(JSON.stringify({ managed: entries.length, files }, null, 2));
+  }
+
+  async handleCommit(args) {
+    await this.validateArgs(args, this.tools[3].inputSchema);
+
+    const { user, message, capture = true, push = false } = args;
+    const { account, repoDir } = await this.resolveRepo(user);
+
+    const captured = [];
+    if (capture) {
+      for (const entry of await this.collectEntries(account)) {
+        // Rendered templates cannot be reversed into their source
+        if (entry.template || !isWithinRoots(entry.target) || await this.entryStatus(entry, account, 'copy') !== 'modified') {
+          continue;
+        }
+        const stat = await fs.lstat(entry.target);
+        if (stat.isSymbolicLink()) {
+          continue;
+        }
+        await fs.copy(entry.target, entry.source);
+        await chownToUser(entry.source, account);
+        captured.push(entry.relative);
+      }
+    }
+
+    const commit = await this.git.commit(repoDir, message, { account });
+    if (commit && push) {
+      await this.git.push(repoDir, { account });
+    }
+
+    return this.createTextResult(JSON.stringify({
+      captured,
+      commit: commit || null,
+      pushed: Boolean(commit && push),
+      ...(commit ? {} : { message: 'Nothing to commit' })
+    }, null, 2));
+  }
+
+  // Maps repository files to home paths: "dot_config/foo.tmpl" -> "~/.config/foo" (rendered)
+  async collectEntries(account) {
+    const repoDir = path.join(account.home, this.repoPath);
+    if (!await fs.pathExists(repoDir)) {
+      throw new Error(`No dotfiles repository for ${account.name} at ${repoDir}; run dotfiles_clone first`);
+    }
+
+    const entries = [];
+    const walk = async (dir, relative) => {
+      for (const name of await fs.readdir(dir)) {
+        const rel = relative ? path.join(relative, name) : name;
+        if (name === '.git' || (!relative && this.ignore.includes(name))) {
+          continue;
+        }
+
+        const source = path.join(dir, name);
+        const stat = await fs.lstat(source);
+        if (stat.isDirectory()) {
+          await walk(source, rel);
+          continue;
+        }
+
+        const template = name.endsWith(TEMPLATE_SUFFIX);
+        const targetRel = rel
+          .split(path.sep)
+          .map(part => part.startsWith('dot_') ? `.${part.slice(4)}` : part)
+          .join(path.sep);
+
+        entries.push({
+          source,
+          relative: rel,
+          template,
+          target: path.join(account.home, template ? targetRel.slice(0, -TEMPLATE_SUFFIX.length) : targetRel)
+        });
+      }
+    };
+
+    await walk(repoDir, '');
+    return entries;
+  }
+
+  async renderTemplate(entry, account) {
+    const vars = {
+      user: account.name,
+      home: account.home,
+      hostname: os.hostname(),
+      ...this.templateVars
+    };
+
+    const content = await fs.readFile(entry.source, 'utf8');
+    return content.replace(/\{\{\s*\.?(\w+)\s*\}\}/g, (match, name) => {
+      if (!(name in vars)) {
+        throw new Error(`Unknown template variable "${name}" in ${entry.relative}`);
+      }
+      return String(vars[name]);
+    });
+  }
+
+  async desiredContent(entry, account) {
+    return entry.template ? Buffer.from(await this.renderTemplate(entry, account)) : fs.readFile(entry.source);
+  }
+
+  async entryStatus(entry, account, mode) {
+    let stat;
+    try {
+      stat = await fs.lstat(entry.target);
+    } catch {
+      return 'missing';
+    }
+
+    if (stat.isSymbolicLink()) {
+      const link = path.resolve(path.dirname(entry.target), await fs.readlink(entry.target));
+      if (link === entry.source) {
+        return !entry.template && mode === 'symlink' ? 'linked' : 'modified';
+      }
+    }
+
+    try {
+      const [current, desired] = await Promise.all([fs.readFile(entry.target), this.desiredContent(entry, account)]);
+      if (!current.equals(desired)) {
+        return 'modified';
+      }
+    } catch {
+      return 'modified';
+    }
+
+    // Same content but copied where a link was requested still needs converting
+    return !entry.template && mode === 'symlink' && !stat.isSymbolicLink() ? 'copied' : 'identical';
+  }
+
+  async installEntry(entry, account, mode) {
+    await fs.ensureDir(path.dirname(entry.target));
+    await fs.remove(entry.target);
+
+    if (mode === 'symlink' && !entry.template) {
+      await fs.symlink(entry.source, entry.target);
+    } else {
+      await fs.writeFile(entry.target, await this.desiredContent(entry, account));
+      const { mode: fileMode } = await fs.stat(entry.source);
+      await fs.chmod(entry.target, fileMode & 0o777);
+    }
+
+    // Created parent directories and the file itself belong to the user
+    for (let dir = path.dirname(entry.target); dir.startsWith(account.home) && dir !== account.home; dir = path.dirname(dir)) {
+      await chownToUser(dir, account);
+    }
+    await chownToUser(entry.target, account);
+  }
+
+  async renderDiff(entry, account) {
+    const desired = path.join(os.tmpdir(), `mcp-dotfiles-${process.pid}-${path.basename(entry.target)}`);
+    try {
+      await fs.writeFile(desired, await this.desiredContent(entry, account));
+      const result = await this.git.run(null, ['diff', '--no-index', '--no-color', '--', desired, entry.target], { allowFailure: true });
+      return result.stdout;
+    } finally {
+      await fs.remove(desired);
+    }
+  }
+}
@@ -0,0 +1,408 @@
+import { BasePlugin, pluginConfig } from './base-plugin.js';
+import { CommandExecutor } from '../system/command-executor.js';
+import { Git } from '../system/git.js';
+import { LockKeys } from '../security/lock-manager.js';
+import { getToolResolver } from '../system/tool-resolver.js';
+import { lookupUser, chownToUser } from '../system/user-account.js';
+import { writeUserFile } from '../system/user-files.js';
+import { previewDiff } from '../system/kernel-params.js';
+import {
+  EDITORS,
+  LANGUAGES,
+  PRESETS,
+  renderNeovimConfig,
+  vscodeSettings,
+  vscodeExtensions
+} from '../system/editor-presets.js';
+import fs from 'fs-extra';
+import os from 'os';
+import path from 'path';
+
+const REPO_PATH = /^[A-Za-z0-9_.][A-Za-z0-9_./-]*$/;
+
+// Editors, language servers, and editor configs from a preset or a dotfiles repository
+export class EditorPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('editor', config, logger, security);
+    this.description = 'Editor and language server installation and config provisioning';
+    this.dotfilesRepoPath = pluginConfig(config, 'dotfiles').repoPath || '.local/share/dotfiles';
+    this.dependencies = ['pacman', 'git'];
+
+    this.commandExecutor = new CommandExecutor(
+      config.get?.('security') || config.security || {},
+      logger,
+      security
+    );
+    this.git = new Git(this.commandExecutor, logger);
+
+    this.initializeTools();
+  }
+
+  initializeTools() {
+    const editor = {
+      type: 'string',
+      enum: Object.keys(EDITORS)
+    };
+    const languages = {
+      type: 'array',
+      items: { type: 'string' },
+      description: `Languages: ${Object.keys(LANGUAGES).join(', ')}`
+    };
+
+    this.tools = [
+      this.createTool(
+        'editor_install',
+        'Install editors and the language servers for the given languages',
+        {
+          type: 'object',
+          properties: {
+            editors: {
+              type: 'array',
+              items: { type: 'string' },
+              description: `Editors: ${Object.keys(EDITORS).join(', ')} (vscode is Code - OSS from the repositories)`
+            },
+            languages,
+            dryRun: {
+              type: 'boolean',
+              default: false
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'editor_config_apply',
+        'Write an editor config from a preset or a dotfiles repository; existing files are shown as a diff and only replaced with overwrite',
+        {
+          type: 'object',
+          properties: {
+            user: {
+              type: 'string',
+              description: 'User whose editor is configured'
+            },
+            editor,
+            source: {
+              type: 'string',
+              enum: ['preset', 'dotfiles'],
+              default: 'preset'
+            },
+            preset: {
+              type: 'string',
+              enum: PRESETS,
+              description: 'minimal: options and LSP keymaps; ide: adds completion, inlay hints and format on save',
+              default: 'minimal'
+            },
+            languages,
+            repo: {
+              type: 'string',
+              description: 'source dotfiles: repository URL (default: the user\'s dotfiles checkout from dotfiles_clone)'
+            },
+            branch: { type: 'string' },
+            path: {
+              type: 'string',
+              description: 'source dotfiles: directory in the repository holding the config, e.g. nvim or .config/nvim'
+            },
+            overwrite: {
+              type: 'boolean',
+              description: 'Replace existing files that differ',
+              default: false
+            },
+            dryRun: {
+              type: 'boolean',
+              default: false
+            }
+          },
+          required: ['user', 'editor']
+        }
+      ),
+
+      this.createTool(
+        'editor_status',
+        'Show installed editors, language servers and existing editor configs',
+        {
+          type: 'object',
+          properties: {
+            user: { type: 'string' }
+          }
+        }
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'editor_install':
+          return this.handleInstall(args);
+        case 'editor_config_apply':
+          return this.handleConfigApply(args);
+        case 'editor_status':
+          return this.handleStatus(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  getToolLocks(toolName, args = {}) {
+    return this.affectsPackages(toolName, args) ? [LockKeys.pacmanDb()] : [];
+  }
+
+  affectsPackages(toolName, args = {}) {
+    return toolName === 'editor_install' && !args.dryRun;
+  }
+
+  // Re-running with the same arguments changes nothing
+  isIdempotent() {
+    return true;
+  }
+
+  isReadOnly(toolName, args = {}) {
+    return ['editor_status'].includes(toolName) || super.isReadOnly(toolName, args);
+  }
+
+  async getAffectedPaths(toolName, args = {}) {
+    if (toolName !== 'editor_config_apply' || args.dryRun || !EDITORS[args.editor]) {
+      return [];
+    }
+    try {
+      return [path.join((await lookupUser(args.user)).home, EDITORS[args.editor].configDir)];
+    } catch {
+      return [];
+    }
+  }
+
+  checkNames(values, known, kind) {
+    const unknown = values.filter(value => !known[value]);
+    if (unknown.length > 0) {
+      throw new Error(`Unknown ${kind}: ${unknown.join(', ')} (available: ${Object.keys(known).join(', ')})`);
+    }
+  }
+
+  async handleInstall(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const { editors = [], languages = [], dryRun = false } = args;
+    this.checkNames(editors, EDITORS, 'editors');
+    this.checkNames(languages, LANGUAGES, 'languages');
+    const packages = Array.from(new Set([
+      ...editors.map(name => EDITORS[name].package),
+      ...languages.map(name => LANGUAGES[name].package)
+    ]));
+    if (packages.length === 0) {
+      throw new Error('Give editors, languages or both');
+    }
+
+    if (dryRun) {
+      return this.createTextResult(`pacman -S --needed --noconfirm ${packages.join(' ')}`, { dryRun });
+    }
+    const result = await this.commandExecutor.executeWithSudo('pacman', ['-S', '--needed', '--noconfirm', ...packages], { timeout: 900000 });
+    if (!result.success) {
+      throw new Error(`pacman failed: ${result.stderr.trim().split('\n').slice(-5).join('\n')}`);
+    }
+
+    this.logger.audit('editor_install', { packages });
+    return this.createTextResult(`Installed ${packages.join(', ')}`, { packages });
+  }
+
+  async handleConfigApply(args) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+
+    const { editor, source = 'preset', preset = 'minimal', languages = [], overwrite = false, dryRun = false } = args;
+    this.checkNames(languages, LANGUAGES, 'languages');
+    const account = await lookupUser(args.user);
+    const configDir = path.join(account.home, EDITORS[editor].configDir);
+
+    const { files, extensions, cleanup } = source === 'preset'
+      ? await this.presetFiles(editor, preset, languages, configDir)
+      : await this.dotfilesFiles(account, args, configDir);
+
+    try {
+      const changes = [];
+      for (const file of files) {
+        const before = await fs.readFile(file.path, 'utf8').catch(() => null);
+        if (before !== file.content) {
+          changes.push({ ...file, before, diff: previewDiff(file.path, before, file.content) });
+        }
+      }
+
+      // Neovim refuses to start with both init.lua and init.vim
+      const initVim = path.join(configDir, 'init.vim');
+      if (editor === 'neovim' && files.some(file => file.path.endsWith('init.lua')) && await fs.pathExists(initVim)) {
+        throw new Error(`${initVim} exists; Neovim will not load it together with init.lua. Move it away first`);
+      }
+
+      const conflicts = changes.filter(change => change.before !== null && !change.merged);
+      const missingExtensions = dryRun ? extensions : await this.missingExtensions(account, extensions);
+      const report = {
+        editor,
+        source,
+        configDir,
+        unchanged: files.length - changes.length,
+        changes: changes.map(change => change.diff),
+        ...(extensions.length > 0 ? { extensions: missingExtensions } : {})
+      };
+
+      if (dryRun) {
+        return this.createTextResult(JSON.stringify({ ...report, ...(conflicts.length > 0 ? { conflicts: conflicts.map(change => change.path) } : {}) }, null, 2), { dryRun });
+      }
+      if (conflicts.length > 0 && !overwrite) {
+        throw new Error(`These files exist and differ; review with dryRun and set overwrite to replace them: ${conflicts.map(change => change.path).join(', ')}`);
+      }
+
+      for (const change of changes) {
+        await writeUserFile(change.path, change.content, account);
+      }
+      for (const extension of missingExtensions) {
+        const result = await this.runAsUser(account, 'code', ['--install-extension', extension]);
+        if (!result.success) {
+          throw new Error(`Config written, but installing the ${extension} extension failed: ${result.stderr.trim()}`);
+        }
+      }
+
+      this.logger.audit('editor_config_apply', { user: account.name, editor, source, preset, languages, files: changes.length });
+      const missingServers = editor === 'neovim' ? await this.missingServers(languages) : [];
+      return this.createTextResult(JSON.stringify({
+        ...report,
+        ...(missingServers.length > 0 ? { note: `Language servers not installed yet: ${missingServers.join(', ')} (see editor_install)` } : {})
+      }, null, 2));
+    } finally {
+      await cleanup?.();
+    }
+  }
+
+  async presetFiles(editor, preset, languages, configDir) {
+    if (editor === 'neovim') {
+      return {
+        files: [{ path: path.join(configDir, 'init.lua'), content: renderNeovimConfig(preset, languages) }],
+        extensions: []
+      };
+    }
+
+    // settings.json is merged rather than replaced, so it never counts as a conflict
+    const file = path.join(configDir, 'settings.json');
+    const existing = await fs.readFile(file, 'utf8').catch(() => null);
+    let current = {};
+    if (existing !== null && existing.trim()) {
+      try {
+        current = JSON.parse(existing);
+      } catch {
+        throw new Error(`${file} is not plain JSON (comments or trailing commas?); merge the preset by hand or remove the file`);
+      }
+    }
+    return {
+      files: [{ path: file, content: `${JSON.stringify({ ...current, ...vscodeSettings(preset) }, null, 2)}\n`, merged: true }],
+      extensions: vscodeExtensions(languages)
+    };
+  }
+
+  // Every file below the given repository directory maps to the same relative path in the config dir
+  async dotfilesFiles(account, args, configDir) {
+    const { repo, branch, path: repoPath } = args;
+    if (!repoPath || !REPO_PATH.test(repoPath) || repoPath.split('/').includes('..')) {
+      throw new Error('source dotfiles needs path, a directory inside the repository');
+    }
+
+    let repoDir = path.join(account.home, this.dotfilesRepoPath);
+    let cleanup = null;
+    if (repo) {
+      repoDir = await fs.mkdtemp(path.join(os.tmpdir(), 'mcp-editor-'));
+      cleanup = () => fs.remove(repoDir);
+    } else if (!await fs.pathExists(repoDir)) {
+      throw new Error(`No dotfiles checkout at ${repoDir}; give repo or run dotfiles_clone first`);
+    }
+
+    try {
+      if (repo) {
+        await chownToUser(repoDir, account);
+        await this.git.clone(repo, repoDir, { branch, depth: 1, account });
+      }
+      return { files: await this.readTree(path.join(repoDir, repoPath), repoPath, configDir), extensions: [], cleanup };
+    } catch (error) {
+      await cleanup?.();
+      throw error;
+    }
+  }
+
+  async readTree(sourceDir, repoPath, configDir) {
+    if (!(await fs.stat(sourceDir).catch(() => null))?.isDirectory()) {
+      throw new Error(`${repoPath} is not a directory in the repository`);
+    }
+
+    const files = [];
+    const walk = async (dir) => {
+      for (const entry of await fs.readdir(dir, { withFileTypes: true })) {
+        const full = path.join(dir, entry.name);
+        if (entry.name === '.git') continue;
+        if (entry.isDirectory()) {
+          await walk(full);
+        } else if (entry.isFile()) {
+          files.push({ path: path.join(configDir, path.relative(sourceDir, full)), content: await fs.readFile(full, 'utf8') });
+        }
+      }
+    };
+    await walk(sourceDir);
+    if (files.length === 0) {
+      throw new Error(`${repoPath} in the repository is empty`);
+    }
+    return files;
+  }
+
+  async missingExtensions(account, extensions) {
+    if (extensions.length === 0) {
+      return [];
+    }
+    const result = await this.runAsUser(account, 'code', ['--list-extensions']);
+    const installed = new Set(result.success ? result.stdout.toLowerCase().split('\n').map(line => line.trim()) : []);
+    return extensions.filter(extension => !installed.has(extension.toLowerCase()));
+  }
+
+  async missingServers(languages) {
+    const missing = [];
+    for (const language of languages) {
+      if (!await getToolResolver().resolve(LANGUAGES[language].cmd[0])) {
+        missing.push(LANGUAGES[language].package);
+      }
+    }
+    return missing;
+  }
+
+  async handleStatus(args) {
+    await this.validateArgs(args, this.tools[2].inputSchema);
+
+    const account = args.user ? await lookupUser(args.user) : null;
+    const editors = {};
+    for (const [name, editor] of Object.entries(EDITORS)) {
+      const binary = await getToolResolver().resolve(editor.binary);
+      const version = binary ? await this.commandExecutor.execute(editor.binary, ['--version']) : null;
+      const configDir = account ? path.join(account.home, editor.configDir) : null;
+      editors[name] = {
+        installed: Boolean(binary),
+        ...(version?.success ? { version: version.stdout.split('\n')[0].trim() } : {}),
+        ...(configDir ? { configDir, configured: await fs.pathExists(configDir) } : {})
+      };
+    }
+
+    const languages = {};
+    for (const [name, language] of Object.entries(LANGUAGES)) {
+      languages[name] = {
+        server: language.cmd[0],
+        installed: Boolean(await getToolResolver().resolve(language.cmd[0])),
+        package: language.package
+      };
+    }
+    return this.createTextResult(JSON.stringify({ editors, languages }, null, 2));
+  }
+
+  async runAsUser(account, command, args) {
+    const env = {
+      ...process.env,
+      HOME: account.home
+    };
+
+    if (process.getuid && process.getuid() === 0 && account.uid !== 0) {
+      return this.commandExecutor.execute('runuser', ['-u', account.name, '--', command, ...args], { env, timeout: 300000 });
+    }
+    return this.commandExecutor.execute(command, args, { env, timeout: 300000 });
+  }
+}
@@ -0,0 +1,157 @@
+import { BasePlugin } from './base-plugin.js';
+import { getFleet } from '../core/fleet.js';
+
+// Inventory of other hosts running this server. Any tool call with targetHost runs there.
+export class FleetPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('fleet', config, logger, security);
+    this.description = 'Host inventory for targetHost tool calls';
+    this.fleet = getFleet();
+
+    this.initializeTools();
+    this.initializeResources();
+  }
+
+  initializeTools() {
+    this.tools = [
+      this.createTool(
+        'fleet_hosts',
+        'List inventory hosts, optionally checking that each one answers',
+        {
+          type: 'object',
+          properties: {
+            tag: {
+              type: 'string',
+              description: 'Only hosts with this tag'
+            },
+            ping: {
+              type: 'boolean',
+              description: 'Connect to each host and report its server version and latency',
+              default: false
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'fleet_add_host',
+        'Add or update an inventory host',
+        {
+          type: 'object',
+          properties: {
+            name: {
+              type: 'string',
+              description: 'Name used as targetHost',
+              pattern: '^[a-zA-Z0-9][a-zA-Z0-9_.-]*$'
+            },
+            url: {
+              type: 'string',
+              description: 'JSON-RPC endpoint (e.g., http://web1.lan:3000/mcp)'
+            },
+            token: {
+              type: 'string',
+              description: 'Bearer token reference: env:<NAME> or file:<path>',
+              pattern: '^(env|file):.+$'
+            },
+            tags: {
+              type: 'array',
+              items: { type: 'string' },
+              default: []
+            }
+          },
+          required: ['name', 'url']
+        }
+      ),
+
+      this.createTool(
+        'fleet_remove_host',
+        'Remove a host added with fleet_add_host',
+        {
+          type: 'object',
+          properties: {
+            name: { type: 'string' }
+          },
+          required: ['name']
+        }
+      )
+    ];
+  }
+
+  initializeResources() {
+    this.resources = [
+      this.createResource(
+        'fleet://hosts',
+        'Fleet Hosts',
+        'Inventory hosts, their tags and where they are defined',
+        'application/json'
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'fleet_hosts':
+          return this.handleHosts(args);
+        case 'fleet_add_host':
+          return this.handleAddHost(args);
+        case 'fleet_remove_host':
+          return this.handleRemoveHost(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  async readResource(uri) {
+    return this.withErrorHandling(async () => {
+      switch (uri) {
+        case 'fleet://hosts':
+          return { content: JSON.stringify(this.fleet.list(), null, 2) };
+        default:
+          throw new Error(`Unknown resource: ${uri}`);
+      }
+    }, 'readResource');
+  }
+
+  isIdempotent(toolName, args = {}) {
+    return toolName === 'fleet_hosts' && !args.ping;
+  }
+
+  isReadOnly(toolName) {
+    return ['fleet_hosts'].includes(toolName);
+  }
+
+  async handleHosts(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const { tag = null, ping = false } = args;
+    let hosts = this.fleet.list({ tag });
+    if (ping) {
+      const status = await Promise.all(hosts.map(host => this.fleet.ping(host.name)));
+      hosts = hosts.map((host, index) => ({ ...host, ...status[index] }));
+    }
+    return this.createTextResult(JSON.stringify(hosts, null, 2), { count: hosts.length });
+  }
+
+  async handleAddHost(args) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+
+    const { name, url, token = null, tags = [] } = args;
+    if (!/^https?:\/\//.test(url)) {
+      throw new Error(`url must be http(s): ${url}`);
+    }
+
+    await this.fleet.add({ name, url, token, tags });
+    this.logger.audit('fleet_add_host', { name, url, tags });
+    return this.createTextResult(`Added ${name} (${url}); call tools with targetHost "${name}"`);
+  }
+
+  async handleRemoveHost(args) {
+    await this.validateArgs(args, this.tools[2].inputSchema);
+
+    await this.fleet.remove(args.name);
+    this.logger.audit('fleet_remove_host', { name: args.name });
+    return this.createTextResult(`Removed ${args.name}`);
+  }
+}
@@ -0,0 +1,416 @@
+import { BasePlugin, pluginConfig } from './base-plugin.js';
+import { CommandExecutor } from '../system/command-executor.js';
+import { LockKeys } from '../security/lock-manager.js';
+import { assertWithinRoots } from '../security/roots.js';
+import { lookupUser, currentUser, chownToUser } from '../system/user-account.js';
+import { writeUserFile } from '../system/user-files.js';
+import { GENERIC_FAMILIES, fontconfigPath, parseFontAliases, renderFontAliases } from '../system/fontconfig.js';
+import fs from 'fs-extra';
+import path from 'path';
+
+const PACKAGE_NAME = /^[a-z0-9@._+-]+$/;
+const FONT_EXTENSIONS = ['.ttf', '.otf', '.ttc', '.otc', '.woff', '.woff2', '.pcf', '.pcf.gz', '.otb', '.bdf'];
+const SYSTEM_FONT_DIR = '/usr/local/share/fonts';
+
+function isFontFile(file) {
+  return FONT_EXTENSIONS.some(extension => file.toLowerCase().endsWith(extension));
+}
+
+// Directory name for a family: lower case, no spaces
+function familyDir(family) {
+  return family.toLowerCase().replace(/[^a-z0-9]+/g, '-').replace(/^-|-$/g, '') || 'other';
+}
+
+// Installing fonts from pacman or files, listing families, and fontconfig defaults
+export class FontsPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('fonts', config, logger, security);
+    this.description = 'Font installation, listing and fontconfig defaults';
+    this.defaultUser = pluginConfig(config, 'fonts').user || pluginConfig(config, 'theme').user || null;
+    this.dependencies = ['fc-list', 'fc-cache', 'fc-scan', 'fc-match', 'pacman'];
+
+    this.commandExecutor = new CommandExecutor(
+      config.get?.('security') || config.security || {},
+      logger,
+      security
+    );
+
+    this.initializeTools();
+    this.initializeResources();
+  }
+
+  initializeTools() {
+    this.tools = [
+      this.createTool(
+        'font_install',
+        'Install fonts from the repositories or from font files, and rebuild the font cache',
+        {
+          type: 'object',
+          properties: {
+            packages: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Font packages (e.g. ttf-jetbrains-mono-nerd, noto-fonts-emoji)'
+            },
+            files: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Font files or directories of them (.ttf, .otf, .ttc, .woff2, .pcf, ...)'
+            },
+            scope: {
+              type: 'string',
+              enum: ['user', 'system'],
+              description: `files: ~/.local/share/fonts of user, or ${SYSTEM_FONT_DIR}`,
+              default: 'user'
+            },
+            user: {
+              type: 'string',
+              description: 'User for scope user (default: plugins.fonts.user or the server user)'
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'font_list',
+        'List installed font families with their styles and where they come from',
+        {
+          type: 'object',
+          properties: {
+            family: {
+              type: 'string',
+              description: 'Only families containing this (case-insensitive)'
+            },
+            monospace: {
+              type: 'boolean',
+              description: 'Only monospaced families',
+              default: false
+            },
+            user: {
+              type: 'string',
+              description: 'Include this user\'s fonts'
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'font_defaults',
+        'Set the preferred families for sans-serif, serif, monospace and emoji in a user\'s fontconfig',
+        {
+          type: 'object',
+          properties: {
+            user: { type: 'string' },
+            sansSerif: { type: 'string' },
+            serif: { type: 'string' },
+            monospace: { type: 'string' },
+            emoji: { type: 'string' }
+          }
+        }
+      ),
+
+      this.createTool(
+        'font_refresh_cache',
+        'Rebuild the fontconfig cache, e.g. after copying fonts by hand',
+        {
+          type: 'object',
+          properties: {
+            user: {
+              type: 'string',
+              description: 'Rebuild this user\'s cache instead of the system cache'
+            }
+          }
+        }
+      )
+    ];
+  }
+
+  initializeResources() {
+    this.resources = [
+      this.createResource(
+        'fonts://families',
+        'Font Families',
+        'Installed families with styles, plus what each generic family resolves to',
+        'application/json'
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'font_install':
+          return this.handleInstall(args);
+        case 'font_list':
+          return this.handleList(args);
+        case 'font_defaults':
+          return this.handleDefaults(args);
+        case 'font_refresh_cache':
+          return this.handleRefreshCache(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  async readResource(uri) {
+    return this.withErrorHandling(async () => {
+      switch (uri) {
+        case 'fonts://families': {
+          const account = await this.resolveAccount();
+          return {
+            content: JSON.stringify({
+              user: account.name,
+              defaults: await this.resolvedDefaults(account),
+              families: await this.listFamilies(account)
+            }, null, 2)
+          };
+        }
+        default:
+          throw new Error(`Unknown resource: ${uri}`);
+      }
+    }, 'readResource');
+  }
+
+  getToolLocks(toolName, args = {}) {
+    return this.affectsPackages(toolName, args) ? [LockKeys.pacmanDb()] : [];
+  }
+
+  affectsPackages(toolName, args = {}) {
+    return toolName === 'font_install' && (args.packages || []).length > 0;
+  }
+
+  isIdempotent(toolName) {
+    return ['font_list', 'font_defaults', 'font_refresh_cache'].includes(toolName);
+  }
+
+  isReadOnly(toolName) {
+    return ['font_list'].includes(toolName);
+  }
+
+  async getAffectedPaths(toolName, args = {}) {
+    if (toolName !== 'font_defaults') {
+      return [];
+    }
+    try {
+      return [fontconfigPath(await this.resolveAccount(args.user))];
+    } catch {
+      return [];
+    }
+  }
+
+  async handleInstall(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const { packages = [], files = [], scope = 'user' } = args;
+    if (packages.length === 0 && files.length === 0) {
+      throw new Error('Give packages, files or both');
+    }
+    const invalid = packages.filter(name => !PACKAGE_NAME.test(name));
+    if (invalid.length > 0) {
+      throw new Error(`Invalid package names: ${invalid.join(', ')}`);
+    }
+
+    const report = {};
+    if (packages.length > 0) {
+      // pacman's fontconfig hook rebuilds the system cache itself
+      const result = await this.commandExecutor.executeWithSudo('pacman', ['-S', '--needed', '--noconfirm', ...packages]);
+      if (!result.success) {
+        throw new Error(`Installing fonts failed: ${result.stderr}`);
+      }
+      report.packages = packages;
+    }
+
+    if (files.length > 0) {
+      const account = scope === 'user' ? await this.resolveAccount(args.user) : null;
+      report.files = await this.installFiles(files, account);
+    }
+
+    this.logger.audit('font_install', { packages, files, scope });
+    return this.createTextResult(JSON.stringify(report, null, 2));
+  }
+
+  // Every file is checked with fc-scan first, so only real fonts land in a font directory
+  async installFiles(sources, account) {
+    const fontFiles = [];
+    for (const source of sources) {
+      const resolved = path.resolve(source);
+      assertWithinRoots(resolved);
+      const stat = await fs.stat(resolved).catch(() => null);
+      if (!stat) {
+        throw new Error(`No such file: ${resolved}`);
+      }
+      fontFiles.push(...(stat.isDirectory() ? await this.findFontFiles(resolved) : [resolved]));
+    }
+    if (fontFiles.length === 0) {
+      throw new Error(`No font files found (extensions: ${FONT_EXTENSIONS.join(', ')})`);
+    }
+
+    const baseDir = account ? path.join(account.home, '.local', 'share', 'fonts') : SYSTEM_FONT_DIR;
+    const installed = [];
+    for (const file of fontFiles) {
+      const scan = await this.commandExecutor.execute('fc-scan', ['--format', '%{family[0]}\\n', file]);
+      const family = scan.success ? scan.stdout.split('\n')[0].trim() : '';
+      if (!family) {
+        throw new Error(`${file} is not a font fontconfig can read`);
+      }
+
+      const target = path.join(baseDir, familyDir(family), path.basename(file));
+      await fs.ensureDir(path.dirname(target));
+      await fs.copy(file, target);
+      await fs.chmod(target, 0o644);
+      if (account) {
+        for (let dir = path.dirname(target); dir.startsWith(account.home) && dir !== account.home; dir = path.dirname(dir)) {
+          await chownToUser(dir, account);
+        }
+        await chownToUser(target, account);
+      }
+      installed.push({ file: target, family });
+    }
+
+    await this.refreshCache(account, baseDir);
+    return { directory: baseDir, installed };
+  }
+
+  async findFontFiles(dir) {
+    const found = [];
+    for (const entry of await fs.readdir(dir, { withFileTypes: true })) {
+      const file = path.join(dir, entry.name);
+      if (entry.isDirectory()) {
+        found.push(...await this.findFontFiles(file));
+      } else if (isFontFile(entry.name)) {
+        found.push(file);
+      }
+    }
+    return found;
+  }
+
+  async handleList(args) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+
+    const { family = null, monospace = false } = args;
+    const account = await this.resolveAccount(args.user);
+    const families = (await this.listFamilies(account))
+      .filter(entry => !family || entry.family.toLowerCase().includes(family.toLowerCase()))
+      .filter(entry => !monospace || entry.monospace);
+
+    return this.createTextResult(JSON.stringify(families, null, 2), { count: families.length });
+  }
+
+  async handleDefaults(args) {
+    await this.validateArgs(args, this.tools[2].inputSchema);
+
+    const account = await this.resolveAccount(args.user);
+    const requested = Object.fromEntries(Object.keys(GENERIC_FAMILIES)
+      .filter(key => args[key])
+      .map(key => [key, args[key]]));
+    if (Object.keys(requested).length === 0) {
+      throw new Error(`Give at least one of ${Object.keys(GENERIC_FAMILIES).join(', ')}`);
+    }
+
+    // fontconfig silently falls back for unknown families, so check they are installed
+    const installed = new Set((await this.listFamilies(account)).map(entry => entry.family.toLowerCase()));
+    for (const [key, family] of Object.entries(requested)) {
+      if (/[\r\n<>&]/.test(family)) {
+        throw new Error(`Invalid ${key}: ${family}`);
+      }
+      if (!installed.has(family.toLowerCase())) {
+        throw new Error(`${family} is not installed; see font_list or install it with font_install`);
+      }
+    }
+
+    const file = fontconfigPath(account);
+    const aliases = parseFontAliases(await fs.readFile(file, 'utf8').catch(() => ''));
+    for (const [key, family] of Object.entries(requested)) {
+      aliases[GENERIC_FAMILIES[key]] = family;
+    }
+    await writeUserFile(file, renderFontAliases(aliases), account);
+
+    const resolved = await this.resolvedDefaults(account);
+    const overridden = Object.entries(requested)
+      .filter(([key, family]) => resolved[GENERIC_FAMILIES[key]] && resolved[GENERIC_FAMILIES[key]].toLowerCase() !== family.toLowerCase())
+      .map(([key]) => GENERIC_FAMILIES[key]);
+
+    return this.createTextResult(JSON.stringify({
+      user: account.name,
+      file,
+      aliases,
+      resolved,
+      ...(overridden.length > 0 ? { warning: `Another fontconfig file wins for ${overridden.join(', ')}; check ~/.config/fontconfig/fonts.conf and conf.d files sorting after ${path.basename(file)}` } : {}),
+      note: 'Running applications pick the change up after a restart'
+    }, null, 2));
+  }
+
+  async handleRefreshCache(args) {
+    await this.validateArgs(args, this.tools[3].inputSchema);
+
+    const account = args.user ? await lookupUser(args.user) : null;
+    await this.refreshCache(account);
+    return this.createTextResult(`Rebuilt the ${account ? `${account.name} user` : 'system'} font cache`);
+  }
+
+  async resolveAccount(user) {
+    if (user) {
+      return lookupUser(user);
+    }
+    return this.defaultUser ? lookupUser(this.defaultUser) : currentUser();
+  }
+
+  // fc-list as the user, so ~/.local/share/fonts and their fontconfig are included
+  async listFamilies(account) {
+    const result = await this.runAsUser(account, 'fc-list', ['--format', '%{family[0]}\\t%{style[0]}\\t%{spacing}\\t%{file}\\n']);
+    if (!result.success) {
+      throw new Error(`fc-list failed: ${result.stderr.trim()}`);
+    }
+
+    const families = new Map();
+    for (const line of result.stdout.split('\n').filter(Boolean)) {
+      const [family, style, spacing, file] = line.split('\t');
+      if (!family) {
+        continue;
+      }
+      const entry = families.get(family) || { family, styles: new Set(), monospace: false, files: 0, sources: new Set() };
+      if (style) entry.styles.add(style);
+      entry.monospace = entry.monospace || spacing === '100';
+      entry.files++;
+      entry.sources.add(file?.startsWith(account.home) ? 'user' : file?.startsWith(SYSTEM_FONT_DIR) ? 'local' : 'package');
+      families.set(family, entry);
+    }
+
+    return Array.from(families.values())
+      .sort((a, b) => a.family.localeCompare(b.family))
+      .map(entry => ({ ...entry, styles: Array.from(entry.styles).sort(), sources: Array.from(entry.sources) }));
+  }
+
+  async resolvedDefaults(account) {
+    const resolved = {};
+    for (const generic of Object.values(GENERIC_FAMILIES)) {
+      const result = await this.runAsUser(account, 'fc-match', ['--format', '%{family[0]}', generic]);
+      resolved[generic] = result.success ? result.stdout.trim() : null;
+    }
+    return resolved;
+  }
+
+  async refreshCache(account, dir = null) {
+    const args = ['-f', ...(dir ? [dir] : [])];
+    const result = account
+      ? await this.runAsUser(account, 'fc-cache', args)
+      : await this.commandExecutor.executeWithSudo('fc-cache', args);
+    if (!result.success) {
+      throw new Error(`fc-cache failed: ${result.stderr.trim()}`);
+    }
+  }
+
+  // fontconfig finds the user's fonts, config and cache through HOME
+  async runAsUser(account, command, args) {
+    const env = {
+      ...process.env,
+      HOME: account.home
+    };
+
+    if (process.getuid && process.getuid() === 0 && account.uid !== 0) {
+      return this.commandExecutor.execute('runuser', ['-u', account.name, '--', command, ...args], { env });
+    }
+    return this.commandExecutor.execute(command, args, { env });
+  }
+}
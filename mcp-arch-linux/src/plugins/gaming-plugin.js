@@ -0,0 +1,370 @@
+import { BasePlugin } from './base-plugin.js';
+import { CommandExecutor } from '../system/command-executor.js';
+import { LockKeys } from '../security/lock-manager.js';
+import { lookupUser } from '../system/user-account.js';
+import { PACMAN_CONF, parseRepos, enableRepo } from '../system/pacman-conf.js';
+import { previewDiff } from '../system/kernel-params.js';
+import { UDEV_RULES_DIR, renderRuleSet, ruleFile } from '../system/udev-rules.js';
+import { listGpus, listIcds, parseVulkanSummary } from '../system/vulkan.js';
+import fs from 'fs-extra';
+import os from 'os';
+import path from 'path';
+
+const DEFAULT_PACKAGES = ['steam', 'gamemode', 'lib32-gamemode', 'mangohud', 'lib32-mangohud'];
+const PACKAGE_NAME = /^[a-z0-9@._+-]+$/;
+const CONTROLLER_RULE = 'game-controllers';
+
+// USB IDs of controllers that need raw HID access for Steam Input and browser gamepad APIs
+const CONTROLLERS = {
+  dualshock4: [['054c', '05c4'], ['054c', '09cc'], ['054c', '0ba0']],
+  dualsense: [['054c', '0ce6'], ['054c', '0df2']],
+  'switch-pro': [['057e', '2009']],
+  joycon: [['057e', '2006'], ['057e', '2007']],
+  'steam-controller': [['28de', '1102'], ['28de', '1142']],
+  'steam-deck': [['28de', '1205']]
+};
+
+// Steam, gamemode and MangoHud on top of multilib, controller access, and Vulkan drivers
+export class GamingPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('gaming', config, logger, security);
+    this.description = 'Multilib, Steam/gamemode/MangoHud, controller rules and Vulkan status';
+    this.commandExecutor = new CommandExecutor(
+      config.get?.('security') || config.security || {},
+      logger,
+      security
+    );
+    this.dependencies = ['pacman', 'pacman-conf', 'udevadm'];
+    this.optionalDependencies = ['vulkaninfo'];
+
+    this.initializeTools();
+  }
+
+  initializeTools() {
+    this.tools = [
+      this.createTool(
+        'multilib_enable',
+        'Enable the multilib repository in pacman.conf (needed for Steam and 32-bit drivers)',
+        {
+          type: 'object',
+          properties: {
+            upgrade: {
+              type: 'boolean',
+              description: 'Sync and upgrade with pacman -Syu afterwards, as Arch requires after adding a repository',
+              default: true
+            },
+            dryRun: {
+              type: 'boolean',
+              default: false
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'gaming_install',
+        'Install Steam, gamemode and MangoHud plus the 32-bit Vulkan drivers for the GPUs present',
+        {
+          type: 'object',
+          properties: {
+            packages: {
+              type: 'array',
+              items: { type: 'string' },
+              description: `Packages to install (default: ${DEFAULT_PACKAGES.join(', ')})`
+            },
+            drivers: {
+              type: 'boolean',
+              description: 'Add missing Vulkan driver packages (64- and 32-bit) for the detected GPUs',
+              default: true
+            },
+            user: {
+              type: 'string',
+              description: 'Add this user to the gamemode group'
+            },
+            dryRun: {
+              type: 'boolean',
+              default: false
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'controller_rules',
+        'Give the logged-in user access to game controllers through a udev rule',
+        {
+          type: 'object',
+          properties: {
+            controllers: {
+              type: 'array',
+              items: { type: 'string' },
+              description: `Known controllers: ${Object.keys(CONTROLLERS).join(', ')}`
+            },
+            devices: {
+              type: 'array',
+              items: { type: 'object' },
+              description: 'Other controllers as { vendorId, productId } (see lsusb)'
+            },
+            remove: {
+              type: 'boolean',
+              description: 'Remove the rule file instead',
+              default: false
+            },
+            dryRun: {
+              type: 'boolean',
+              default: false
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'vulkan_status',
+        'Report GPUs, their kernel drivers, installed Vulkan drivers (64- and 32-bit) and what is missing',
+        {
+          type: 'object',
+          properties: {}
+        }
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'multilib_enable':
+          return this.handleMultilib(args);
+        case 'gaming_install':
+          return this.handleInstall(args);
+        case 'controller_rules':
+          return this.handleControllerRules(args);
+        case 'vulkan_status':
+          return this.createTextResult(JSON.stringify(await this.vulkanStatus(), null, 2));
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  getToolLocks(toolName, args = {}) {
+    return this.affectsPackages(toolName, args) ? [LockKeys.pacmanDb()] : [];
+  }
+
+  affectsPackages(toolName, args = {}) {
+    if (args.dryRun) {
+      return false;
+    }
+    return toolName === 'gaming_install' || (toolName === 'multilib_enable' && args.upgrade !== false);
+  }
+
+  isIdempotent() {
+    return true;
+  }
+
+  isReadOnly(toolName, args = {}) {
+    return ['vulkan_status'].includes(toolName) || super.isReadOnly(toolName, args);
+  }
+
+  async getAffectedPaths(toolName, args = {}) {
+    if (args.dryRun) {
+      return [];
+    }
+    switch (toolName) {
+      case 'multilib_enable':
+        return [PACMAN_CONF];
+      case 'controller_rules':
+        return [ruleFile(CONTROLLER_RULE)];
+      case 'gaming_install':
+        return args.user ? ['/etc/group', '/etc/gshadow'] : [];
+      default:
+        return [];
+    }
+  }
+
+  async multilibEnabled() {
+    const content = await fs.readFile(PACMAN_CONF, 'utf8');
+    return Boolean(parseRepos(content).find(repo => repo.name === 'multilib')?.enabled);
+  }
+
+  async handleMultilib(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const { upgrade = true, dryRun = false } = args;
+    if (os.arch() !== 'x64') {
+      throw new Error(`multilib only exists for x86_64 (this machine is ${os.arch()})`);
+    }
+
+    const before = await fs.readFile(PACMAN_CONF, 'utf8');
+    const after = enableRepo(before, 'multilib');
+    if (before === after) {
+      return this.createTextResult('multilib is already enabled', { changed: false });
+    }
+    const steps = [previewDiff(PACMAN_CONF, before, after), ...(upgrade ? ['pacman -Syu --noconfirm'] : [])];
+    if (dryRun) {
+      return this.createTextResult(steps.join('\n'), { dryRun });
+    }
+
+    // pacman-conf parses the edited file exactly as pacman would before it replaces the real one
+    const candidate = path.join(os.tmpdir(), `pacman-${process.pid}-${Date.now()}.conf`);
+    await fs.writeFile(candidate, after);
+    try {
+      const check = await this.commandExecutor.execute('pacman-conf', ['--config', candidate, '--repo-list'], { retry: false });
+      if (!check.success || !check.stdout.split('\n').includes('multilib')) {
+        throw new Error(`The edited pacman.conf does not parse: ${check.stderr.trim() || 'multilib missing from the repository list'}`);
+      }
+    } finally {
+      await fs.remove(candidate);
+    }
+    await fs.writeFile(PACMAN_CONF, after);
+
+    if (upgrade) {
+      const result = await this.commandExecutor.executeWithSudo('pacman', ['-Syu', '--noconfirm'], { retry: false, timeout: 1800000 });
+      if (!result.success) {
+        throw new Error(`multilib enabled, but pacman -Syu failed: ${result.stderr.trim().split('\n').slice(-5).join('\n')}`);
+      }
+    }
+
+    this.logger.audit('multilib_enable', { upgrade });
+    return this.createTextResult(`${steps.join('\n')}\nmultilib enabled${upgrade ? ' and the system upgraded' : '; run a full upgrade (pacman -Syu) before installing from it'}`, { changed: true });
+  }
+
+  async handleInstall(args) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+
+    const { packages = DEFAULT_PACKAGES, drivers = true, user, dryRun = false } = args;
+    const invalid = packages.filter(name => !PACKAGE_NAME.test(name));
+    if (invalid.length > 0) {
+      throw new Error(`Invalid package names: ${invalid.join(', ')}`);
+    }
+    const account = user ? await lookupUser(user) : null;
+
+    const driverPackages = drivers ? (await this.vulkanStatus()).missing : [];
+    const wanted = Array.from(new Set([...packages, ...driverPackages]));
+    const needsMultilib = wanted.filter(name => name === 'steam' || name.startsWith('lib32-'));
+    if (needsMultilib.length > 0 && !await this.multilibEnabled()) {
+      throw new Error(`${needsMultilib.join(', ')} come from multilib, which is not enabled; run multilib_enable first`);
+    }
+
+    const steps = [
+      `pacman -S --needed --noconfirm ${wanted.join(' ')}`,
+      ...(account ? [`usermod --append --groups gamemode ${account.name}`] : [])
+    ];
+    if (dryRun) {
+      return this.createTextResult(steps.join('\n'), { dryRun, drivers: driverPackages });
+    }
+
+    const result = await this.commandExecutor.executeWithSudo('pacman', ['-S', '--needed', '--noconfirm', ...wanted], { retry: false, timeout: 1800000 });
+    if (!result.success) {
+      throw new Error(`pacman failed: ${result.stderr.trim().split('\n').slice(-5).join('\n')}`);
+    }
+    if (account) {
+      const usermod = await this.commandExecutor.executeWithSudo('usermod', ['--append', '--groups', 'gamemode', account.name], { retry: false });
+      if (!usermod.success) {
+        throw new Error(`Installed, but adding ${account.name} to gamemode failed: ${usermod.stderr.trim()}`);
+      }
+    }
+
+    this.logger.audit('gaming_install', { packages: wanted, user: account?.name });
+    return this.createTextResult(JSON.stringify({
+      installed: wanted,
+      ...(driverPackages.length > 0 ? { drivers: driverPackages } : {}),
+      ...(account ? { note: `${account.name} joins the gamemode group at next login` } : {}),
+      usage: 'Steam launch options: gamemoderun mangohud %command%'
+    }, null, 2));
+  }
+
+  async handleControllerRules(args) {
+    await this.validateArgs(args, this.tools[2].inputSchema);
+
+    const { controllers = [], devices = [], remove = false, dryRun = false } = args;
+    const file = ruleFile(CONTROLLER_RULE);
+    const before = await fs.readFile(file, 'utf8').catch(() => null);
+
+    if (remove) {
+      if (before === null) {
+        throw new Error(`No rule ${file}`);
+      }
+      if (!dryRun) {
+        await fs.remove(file);
+        await this.reloadUdev(false);
+      }
+      return this.createTextResult(previewDiff(file, before, null), { dryRun });
+    }
+
+    const unknown = controllers.filter(name => !CONTROLLERS[name]);
+    if (unknown.length > 0) {
+      throw new Error(`Unknown controllers: ${unknown.join(', ')} (known: ${Object.keys(CONTROLLERS).join(', ')}; use devices for others)`);
+    }
+    const ids = [
+      ...controllers.flatMap(name => CONTROLLERS[name]).map(([vendorId, productId]) => ({ vendorId, productId })),
+      ...devices.map(({ vendorId, productId }) => ({ vendorId, productId }))
+    ];
+    if (ids.length === 0) {
+      throw new Error('Give controllers or devices');
+    }
+
+    // uaccess hands the device to whoever sits at the seat, so no group membership is needed
+    const rule = await renderRuleSet('hidraw', ids.map(id => ({ ...id, uaccess: true })));
+    const diff = previewDiff(file, before, rule.content);
+    if (dryRun || !diff) {
+      return this.createTextResult(diff || 'Rule already up to date', { file, dryRun });
+    }
+
+    await fs.ensureDir(UDEV_RULES_DIR);
+    await fs.writeFile(file, rule.content, { mode: 0o644 });
+    await this.reloadUdev(true);
+
+    this.logger.audit('controller_rules', { controllers, devices: devices.length });
+    return this.createTextResult(`${diff}\nReloaded udev; reconnect controllers that were already plugged in`, { file });
+  }
+
+  async reloadUdev(trigger) {
+    const reload = await this.commandExecutor.executeWithSudo('udevadm', ['control', '--reload'], { retry: false });
+    if (!reload.success) {
+      throw new Error(`udevadm control --reload failed: ${reload.stderr.trim()}`);
+    }
+    if (trigger) {
+      await this.commandExecutor.executeWithSudo('udevadm', ['trigger', '--action=add', '--subsystem-match=hidraw'], { retry: false });
+    }
+  }
+
+  async vulkanStatus() {
+    const gpus = await listGpus();
+    const icds = await listIcds();
+    const multilib = await this.multilibEnabled().catch(() => false);
+
+    const packages = Array.from(new Set(gpus.flatMap(gpu => gpu.vulkanPackages)));
+    const installed = {};
+    for (const name of [...packages, 'amdvlk', 'lib32-amdvlk', 'vulkan-icd-loader', 'lib32-vulkan-icd-loader']) {
+      installed[name] = (await this.commandExecutor.execute('pacman', ['-Q', name])).success;
+    }
+    const missing = packages.filter(name => !installed[name] && (multilib || !name.startsWith('lib32-')));
+
+    const warnings = [];
+    if (installed.amdvlk && installed['vulkan-radeon']) {
+      warnings.push('amdvlk and vulkan-radeon are both installed; games may pick either (set AMD_VULKAN_ICD=RADV to force RADV)');
+    }
+    if (gpus.some(gpu => gpu.driver === 'nouveau') && gpus.some(gpu => gpu.vendor === 'nvidia')) {
+      warnings.push('An NVIDIA GPU runs on nouveau; for gaming, the nvidia driver is usually much faster');
+    }
+    if (!multilib) {
+      warnings.push('multilib is disabled, so no 32-bit drivers can be installed (see multilib_enable)');
+    }
+
+    let devices = null;
+    const summary = await this.commandExecutor.execute('vulkaninfo', ['--summary']);
+    if (summary.success) {
+      devices = parseVulkanSummary(summary.stdout);
+    }
+
+    return {
+      gpus,
+      icds,
+      installed,
+      missing,
+      multilib,
+      ...(devices ? { devices } : { note: 'Install vulkan-tools for the devices the Vulkan loader actually sees' }),
+      ...(warnings.length > 0 ? { warnings } : {})
+    };
+  }
+}
@@ -0,0 +1,271 @@
+import { BasePlugin, pluginConfig } from './base-plugin.js';
+import { CommandExecutor } from '../system/command-executor.js';
+import { LockKeys } from '../security/lock-manager.js';
+import { Git } from '../system/git.js';
+import { lookupUid } from '../system/user-account.js';
+import { matchesGlob } from '../system/glob.js';
+import { assertWithinRoots } from '../security/roots.js';
+import fs from 'fs-extra';
+import path from 'path';
+
+// ext:: and friends run arbitrary commands; only plain transports are accepted
+const ALLOWED_URL = /^(https?:\/\/|ssh:\/\/|git:\/\/|[\w.-]+@[\w.-]+:)/;
+
+export class GitPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('git', config, logger, security);
+    this.description = 'Version control for repositories under configured paths';
+    this.allowedPaths = pluginConfig(config, 'git').allowedPaths || ['/etc'];
+    this.dependencies = ['git'];
+
+    this.commandExecutor = new CommandExecutor(
+      config.get?.('security') || config.security || {},
+      logger,
+      security
+    );
+    this.git = new Git(this.commandExecutor, logger);
+
+    this.initializeTools();
+  }
+
+  initializeTools() {
+    const repoPath = {
+      type: 'string',
+      description: 'Repository path (must be under plugins.git.allowedPaths)'
+    };
+
+    this.tools = [
+      this.createTool(
+        'git_clone',
+        'Clone a repository into an allowed path',
+        {
+          type: 'object',
+          properties: {
+            url: {
+              type: 'string',
+              description: 'Repository URL (https, ssh, git or scp-style)'
+            },
+            path: repoPath,
+            branch: {
+              type: 'string',
+              description: 'Branch to check out'
+            }
+          },
+          required: ['url', 'path']
+        }
+      ),
+
+      this.createTool(
+        'git_pull',
+        'Fast-forward a repository from its upstream',
+        {
+          type: 'object',
+          properties: {
+            path: repoPath
+          },
+          required: ['path']
+        }
+      ),
+
+      this.createTool(
+        'git_status',
+        'Show branch and changed files',
+        {
+          type: 'object',
+          properties: {
+            path: repoPath
+          },
+          required: ['path']
+        }
+      ),
+
+      this.createTool(
+        'git_diff',
+        'Show uncommitted changes',
+        {
+          type: 'object',
+          properties: {
+            path: repoPath,
+            staged: {
+              type: 'boolean',
+              description: 'Diff the index instead of the working tree',
+              default: false
+            },
+            files: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Limit the diff to these files'
+            }
+          },
+          required: ['path']
+        }
+      ),
+
+      this.createTool(
+        'git_commit',
+        'Commit changes and optionally push',
+        {
+          type: 'object',
+          properties: {
+            path: repoPath,
+            message: {
+              type: 'string',
+              description: 'Commit message'
+            },
+            files: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Files to commit (default: all changes)'
+            },
+            push: {
+              type: 'boolean',
+              description: 'Push after committing',
+              default: false
+            }
+          },
+          required: ['path', 'message']
+        }
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'git_clone':
+          return this.handleClone(args);
+        case 'git_pull':
+          return this.handlePull(args);
+        case 'git_status':
+          return this.handleStatus(args);
+        case 'git_diff':
+          return this.handleDiff(args);
+        case 'git_commit':
+          return this.handleCommit(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  isIdempotent(toolName) {
+    return ['git_status', 'git_diff'].includes(toolName);
+  }
+
+  isReadOnly(toolName) {
+    return ['git_status', 'git_diff'].includes(toolName);
+  }
+
+  getToolLocks(toolName, args) {
+    if (['git_clone', 'git_pull', 'git_commit'].includes(toolName) && args.path) {
+      return [LockKeys.gitRepo(args.path)];
+    }
+    return [];
+  }
+
+  // Resolves symlinks so a link inside an allowed path cannot point outside it
+  async resolveAllowed(repoPath, { mustExist = true } = {}) {
+    if (!path.isAbsolute(repoPath)) {
+      throw new Error('Repository path must be absolute');
+    }
+
+    let resolved = path.resolve(repoPath);
+    if (mustExist) {
+      resolved = await fs.realpath(resolved);
+    } else {
+      // Resolve the deepest existing ancestor; the rest does not exist yet
+      let existing = resolved;
+      while (!await fs.pathExists(existing)) {
+        existing = path.dirname(existing);
+      }
+      resolved = path.join(await fs.realpath(existing), path.relative(existing, resolved));
+    }
+
+    for (let candidate = resolved; ; candidate = path.dirname(candidate)) {
+      if (this.allowedPaths.some(pattern => matchesGlob(candidate, pattern))) {
+        assertWithinRoots(resolved);
+        return resolved;
+      }
+      if (candidate === path.dirname(candidate)) {
+        break;
+      }
+    }
+
+    throw new Error(`Path not allowed for git operations: ${repoPath}`);
+  }
+
+  // Work as the repository owner so root does not leave root-owned objects in user repos
+  async openRepo(repoPath) {
+    const repoDir = await this.resolveAllowed(repoPath);
+    if (!await fs.pathExists(path.join(repoDir, '.git'))) {
+      throw new Error(`Not a git repository: ${repoDir}`);
+    }
+
+    const { uid } = await fs.stat(repoDir);
+    return { repoDir, account: await lookupUid(uid) };
+  }
+
+  async handleClone(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const { url, path: target, branch } = args;
+    if (!ALLOWED_URL.test(url)) {
+      throw new Error(`Unsupported repository URL: ${url}`);
+    }
+
+    const repoDir = await this.resolveAllowed(target, { mustExist: false });
+    await fs.ensureDir(path.dirname(repoDir));
+    if (await fs.pathExists(repoDir) && (await fs.readdir(repoDir)).length > 0) {
+      throw new Error(`Destination is not empty: ${repoDir}`);
+    }
+
+    const { uid } = await fs.stat(path.dirname(repoDir));
+    await this.git.clone(url, repoDir, { branch, account: await lookupUid(uid) });
+
+    return this.createTextResult(`Cloned ${url} into ${repoDir}`);
+  }
+
+  async handlePull(args) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+
+    const { repoDir, account } = await this.openRepo(args.path);
+    const result = await this.git.pull(repoDir, { account });
+    return this.createTextResult(result.stdout || 'Already up to date.');
+  }
+
+  async handleStatus(args) {
+    await this.validateArgs(args, this.tools[2].inputSchema);
+
+    const { repoDir, account } = await this.openRepo(args.path);
+    const status = await this.git.status(repoDir, { account });
+    return this.createTextResult(JSON.stringify({ path: repoDir, ...status }, null, 2));
+  }
+
+  async handleDiff(args) {
+    await this.validateArgs(args, this.tools[3].inputSchema);
+
+    const { staged = false, files = [] } = args;
+    const { repoDir, account } = await this.openRepo(args.path);
+    const diff = await this.git.diff(repoDir, { staged, paths: files, account });
+    return this.createTextResult(diff || 'No changes');
+  }
+
+  async handleCommit(args) {
+    await this.validateArgs(args, this.tools[4].inputSchema);
+
+    const { message, files = [], push = false } = args;
+    const { repoDir, account } = await this.openRepo(args.path);
+
+    const commit = await this.git.commit(repoDir, message, { paths: files, account });
+    if (!commit) {
+      return this.createTextResult('Nothing to commit');
+    }
+
+    if (push) {
+      await this.git.push(repoDir, { account });
+    }
+
+    this.logger.audit('git_commit', { repo: repoDir, commit, push });
+    return this.createTextResult(JSON.stringify({ path: repoDir, commit, pushed: push }, null, 2));
+  }
+}
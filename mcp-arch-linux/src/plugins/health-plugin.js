@@ -0,0 +1,41 @@
+import { BasePlugin } from './base-plugin.js';
+
+export class HealthPlugin extends BasePlugin {
+  constructor(config, logger, security, healthCheck) {
+    super('health', config, logger, security);
+    this.description = 'Liveness and readiness reporting';
+    this.healthCheck = healthCheck;
+
+    this.initializeResources();
+  }
+
+  initializeResources() {
+    this.resources = [
+      this.createResource(
+        'health://live',
+        'Liveness',
+        'Whether the server process is running and responsive',
+        'application/json'
+      ),
+      this.createResource(
+        'health://ready',
+        'Readiness',
+        'Audit log and snapshot directories, Hyprland socket and plugin binaries',
+        'application/json'
+      )
+    ];
+  }
+
+  async readResource(uri) {
+    return this.withErrorHandling(async () => {
+      switch (uri) {
+        case 'health://live':
+          return { content: JSON.stringify(this.healthCheck.live(), null, 2) };
+        case 'health://ready':
+          return { content: JSON.stringify(await this.healthCheck.ready(), null, 2) };
+        default:
+          throw new Error(`Unknown resource: ${uri}`);
+      }
+    }, 'readResource');
+  }
+}
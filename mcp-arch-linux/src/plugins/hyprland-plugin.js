@@ -1,14 +1,29 @@
-import { BasePlugin } from './base-plugin.js';
-import { Socket } from 'net';
+import { BasePlugin, pluginConfig } from './base-plugin.js';
+import { LockKeys } from '../security/lock-manager.js';
 import fs from 'fs-extra';
 import path from 'path';
+import { HyprlandCompositor, hyprlandCommandLine } from '../system/compositor.js';
+import { HyprlandEventStream } from '../system/hyprland-events.js';
+import { AutomationRules, EVENT_FIELDS } from '../system/automation-rules.js';
+import { lookupUser } from '../system/user-account.js';
+import { readLines, writeUserFile, upsertLine } from '../system/user-files.js';
+
+const MAX_BATCH_COMMANDS = 100;
+const SCRATCHPAD_NAME = /^[A-Za-z0-9_-]+$/;
+const SCRATCHPAD_RULE = /^\s*workspace\s*=\s*special:([A-Za-z0-9_-]+)\s*,\s*on-created-empty:\s*(.+)$/;
+// Rules and scratchpad definitions are files; they can be edited while Hyprland is down
+const OFFLINE_TOOLS = ['hyprland_rules_list', 'hyprland_rule_add', 'hyprland_rule_remove', 'hyprland_scratchpad_define', 'hyprland_scratchpad_remove'];
 
 export class HyprlandPlugin extends BasePlugin {
   constructor(config, logger, security) {
     super('hyprland', config, logger, security);
     this.description = 'Hyprland window manager integration';
-    this.socketPath = config.plugins?.hyprland?.socketPath || null;
+    const settings = pluginConfig(config, 'hyprland');
+    this.socketPath = settings.socketPath || null;
     this.isAvailable = false;
+    this.automation = settings.automation || {};
+    this.rules = new AutomationRules(this.automation, logger);
+    this.events = null;
     
     this.initializeTools();
     this.initializeResources();
@@ -29,17 +44,24 @@ export class HyprlandPlugin extends BasePlugin {
     } else {
       this.logger.info(`Hyprland plugin initialized with socket: ${this.socketPath}`);
     }
+
+    await this.rules.load().catch(error => {
+      this.logger.warn('Could not load Hyprland automation rules:', error.message);
+    });
+    if (this.isAvailable && (this.automation.enabled ?? true)) {
+      this.events = new HyprlandEventStream(this.socketPath, this.logger);
+      this.events.on('event', event => this.runRules(event));
+      this.events.start();
+    }
+  }
+
+  async cleanup() {
+    this.events?.stop();
+    await super.cleanup();
   }
 
   async detectHyprlandSocket() {
-    const runtimeDir = process.env.XDG_RUNTIME_DIR;
-    const instance = process.env.HYPRLAND_INSTANCE_SIGNATURE;
-    
-    if (runtimeDir && instance) {
-      return path.join(runtimeDir, 'hypr', instance, '.socket.sock');
-    }
-    
-    return null;
+    return HyprlandCompositor.detectSocket();
   }
 
   async checkHyprlandAvailable() {
@@ -55,7 +77,29 @@ export class HyprlandPlugin extends BasePlugin {
     }
   }
 
+  async getHealthChecks() {
+    // A missing compositor degrades the plugin rather than the whole server
+    this.isAvailable = await this.checkHyprlandAvailable();
+    return [
+      ...await super.getHealthChecks(),
+      {
+        name: 'hyprland-socket',
+        status: this.isAvailable ? 'pass' : 'warn',
+        path: this.socketPath
+      }
+    ];
+  }
+
   initializeTools() {
+    const scratchpadName = {
+      type: 'string',
+      description: 'Scratchpad name (letters, digits, "_", "-"); the workspace is special:<name>'
+    };
+    const scratchpadUser = {
+      type: 'string',
+      description: 'User whose hyprland.conf holds the definition'
+    };
+
     this.tools = [
       this.createTool(
         'hyprland_dispatch',
@@ -196,6 +240,198 @@ export class HyprlandPlugin extends BasePlugin {
           },
           required: ['action']
         }
+      ),
+
+      this.createTool(
+        'hyprland_batch',
+        'Run dispatchers and keywords in order in one socket round trip (hyprctl --batch), with a result per item',
+        {
+          type: 'object',
+          properties: {
+            commands: {
+              type: 'array',
+              description: 'Commands in the order they run',
+              items: {
+                type: 'object',
+                properties: {
+                  type: {
+                    type: 'string',
+                    enum: ['dispatch', 'keyword']
+                  },
+                  name: {
+                    type: 'string',
+                    description: 'Dispatcher (e.g., movetoworkspacesilent) or keyword (e.g., general:gaps_in)'
+                  },
+                  args: {
+                    type: 'string',
+                    description: 'Dispatcher arguments or keyword value',
+                    default: ''
+                  }
+                },
+                required: ['type', 'name']
+              }
+            }
+          },
+          required: ['commands']
+        }
+      ),
+
+      this.createTool(
+        'hyprland_rules_list',
+        'List automation rules with how often each fired and its last error',
+        {
+          type: 'object',
+          properties: {}
+        }
+      ),
+
+      this.createTool(
+        'hyprland_rule_add',
+        'Add or replace an automation rule that runs actions when a Hyprland event matches',
+        {
+          type: 'object',
+          properties: {
+            name: {
+              type: 'string',
+              description: 'Rule name (letters, digits, ".", "_", "-")'
+            },
+            on: {
+              type: 'string',
+              enum: Object.keys(EVENT_FIELDS),
+              description: 'Hyprland event'
+            },
+            match: {
+              type: 'object',
+              description: 'Event field -> regular expression, e.g. { "class": "^firefox$" }; fields: address, workspace, class, title, monitor, state'
+            },
+            actions: {
+              type: 'array',
+              description: 'Run in order; {field} in args or arguments is replaced with the event value, e.g. "2,address:{address}"',
+              items: {
+                type: 'object',
+                properties: {
+                  type: {
+                    type: 'string',
+                    enum: ['dispatch', 'keyword', 'tool']
+                  },
+                  name: {
+                    type: 'string',
+                    description: 'Dispatcher, keyword or tool name'
+                  },
+                  args: {
+                    type: 'string',
+                    description: 'Dispatcher arguments or keyword value'
+                  },
+                  arguments: {
+                    type: 'object',
+                    description: 'Tool arguments'
+                  }
+                },
+                required: ['type', 'name']
+              }
+            }
+          },
+          required: ['name', 'on', 'actions']
+        }
+      ),
+
+      this.createTool(
+        'hyprland_rule_remove',
+        'Remove an automation rule added with hyprland_rule_add',
+        {
+          type: 'object',
+          properties: {
+            name: {
+              type: 'string',
+              description: 'Rule name'
+            }
+          },
+          required: ['name']
+        }
+      ),
+
+      this.createTool(
+        'hyprland_scratchpad_define',
+        'Define a scratchpad: a special workspace that starts a command when summoned empty, saved in the user\'s hyprland.conf',
+        {
+          type: 'object',
+          properties: {
+            user: scratchpadUser,
+            name: scratchpadName,
+            command: {
+              type: 'string',
+              description: 'Command started when the scratchpad is shown empty, e.g. "[float; size 60% 60%] kitty"'
+            }
+          },
+          required: ['user', 'name', 'command']
+        }
+      ),
+
+      this.createTool(
+        'hyprland_scratchpad_remove',
+        'Remove a scratchpad definition from the user\'s hyprland.conf',
+        {
+          type: 'object',
+          properties: {
+            user: scratchpadUser,
+            name: scratchpadName
+          },
+          required: ['user', 'name']
+        }
+      ),
+
+      this.createTool(
+        'hyprland_scratchpad_toggle',
+        'Show or hide a scratchpad (special workspace) on the focused monitor',
+        {
+          type: 'object',
+          properties: {
+            name: scratchpadName,
+            action: {
+              type: 'string',
+              enum: ['toggle', 'show', 'hide'],
+              description: 'show summons it and leaves it alone when already shown',
+              default: 'toggle'
+            }
+          },
+          required: ['name']
+        }
+      ),
+
+      this.createTool(
+        'hyprland_scratchpad_send',
+        'Move a window (the active one by default) into a scratchpad',
+        {
+          type: 'object',
+          properties: {
+            name: scratchpadName,
+            window: {
+              type: 'string',
+              description: 'Window address (0x...), Hyprland window selector or "active"',
+              default: 'active'
+            },
+            follow: {
+              type: 'boolean',
+              description: 'Show the scratchpad afterwards instead of moving the window silently',
+              default: false
+            }
+          },
+          required: ['name']
+        }
+      ),
+
+      this.createTool(
+        'hyprland_scratchpad_list',
+        'List scratchpads: live special workspaces with their windows and where they are shown, plus the user\'s definitions',
+        {
+          type: 'object',
+          properties: {
+            user: {
+              ...scratchpadUser,
+              description: 'Also list definitions from this user\'s hyprland.conf'
+            }
+          }
+        }
       )
     ];
   }
@@ -224,7 +460,8 @@ export class HyprlandPlugin extends BasePlugin {
   }
 
   async executeTool(toolName, args) {
-    if (!this.isAvailable) {
+    // Rules and scratchpads can be managed while Hyprland is down; they apply once it is back
+    if (!this.isAvailable && !OFFLINE_TOOLS.includes(toolName) && !(toolName === 'hyprland_scratchpad_list' && args?.user)) {
       throw new Error('Hyprland is not available');
     }
 
@@ -246,6 +483,24 @@ export class HyprlandPlugin extends BasePlugin {
           return this.handleLayout(args);
         case 'hyprland_window_control':
           return this.handleWindowControl(args);
+        case 'hyprland_batch':
+          return this.handleBatch(args);
+        case 'hyprland_rules_list':
+          return this.handleRulesList(args);
+        case 'hyprland_rule_add':
+          return this.handleRuleAdd(args);
+        case 'hyprland_rule_remove':
+          return this.handleRuleRemove(args);
+        case 'hyprland_scratchpad_define':
+          return this.handleScratchpadDefine(args);
+        case 'hyprland_scratchpad_remove':
+          return this.handleScratchpadRemove(args);
+        case 'hyprland_scratchpad_toggle':
+          return this.handleScratchpadToggle(args);
+        case 'hyprland_scratchpad_send':
+          return this.handleScratchpadSend(args);
+        case 'hyprland_scratchpad_list':
+          return this.handleScratchpadList(args);
         default:
           throw new Error(`Unknown tool: ${toolName}`);
       }
@@ -267,32 +522,54 @@ export class HyprlandPlugin extends BasePlugin {
     }, 'readResource');
   }
 
-  async sendHyprlandCommand(command) {
-    return new Promise((resolve, reject) => {
-      const socket = new Socket();
-      let data = '';
+  // Setting a keyword to the same value again changes nothing; adding a rule replaces one of the same name
+  isIdempotent(toolName) {
+    return ['hyprland_windows', 'hyprland_workspaces', 'hyprland_monitors', 'hyprland_keyword', 'hyprland_rules_list',
+      'hyprland_rule_add', 'hyprland_scratchpad_define', 'hyprland_scratchpad_list'].includes(toolName);
+  }
 
-      socket.connect(this.socketPath, () => {
-        socket.write(command);
-      });
+  isReadOnly(toolName) {
+    return ['hyprland_windows', 'hyprland_workspaces', 'hyprland_monitors', 'hyprland_rules_list', 'hyprland_scratchpad_list'].includes(toolName);
+  }
 
-      socket.on('data', (chunk) => {
-        data += chunk.toString();
-      });
+  disruptsUser(toolName) {
+    return ['hyprland_dispatch', 'hyprland_keyword', 'hyprland_reload', 'hyprland_layout', 'hyprland_window_control', 'hyprland_batch',
+      'hyprland_scratchpad_toggle', 'hyprland_scratchpad_send'].includes(toolName);
+  }
 
-      socket.on('end', () => {
-        resolve(data.trim());
-      });
+  async getAffectedPaths(toolName, args = {}) {
+    if (!['hyprland_scratchpad_define', 'hyprland_scratchpad_remove'].includes(toolName) || !args.user) {
+      return [];
+    }
+    try {
+      return [this.userConfigPath(await lookupUser(args.user))];
+    } catch {
+      return [];
+    }
+  }
 
-      socket.on('error', (error) => {
-        reject(new Error(`Hyprland socket error: ${error.message}`));
-      });
+  getToolLocks(toolName, args = {}) {
+    if (['hyprland_keyword', 'hyprland_reload', 'hyprland_layout', 'hyprland_scratchpad_define', 'hyprland_scratchpad_remove'].includes(toolName) ||
+        (toolName === 'hyprland_batch' && (args.commands || []).some(command => command?.type === 'keyword'))) {
+      return [LockKeys.hyprlandConfig()];
+    }
+    return [];
+  }
 
-      socket.setTimeout(5000, () => {
-        socket.destroy();
-        reject(new Error('Hyprland command timeout'));
-      });
-    });
+  ipc() {
+    return new HyprlandCompositor(this.socketPath);
+  }
+
+  async sendHyprlandCommand(command) {
+    return this.ipc().send(command);
+  }
+
+  // JSON goes through the typed query; text is hyprctl's human-readable listing
+  async queryResult(request, format) {
+    if (format === 'json') {
+      return this.createTextResult(JSON.stringify(await this.ipc().query(request), null, 2));
+    }
+    return this.createTextResult(await this.sendHyprlandCommand(request));
   }
 
   async handleDispatch(args) {
@@ -319,60 +596,21 @@ export class HyprlandPlugin extends BasePlugin {
     await this.validateArgs(args, this.tools[2].inputSchema);
     
     const { format = 'json' } = args;
-    const command = format === 'json' ? 'j/clients' : 'clients';
-    
-    const result = await this.sendHyprlandCommand(command);
-    
-    if (format === 'json') {
-      try {
-        const windows = JSON.parse(result);
-        return this.createTextResult(JSON.stringify(windows, null, 2));
-      } catch {
-        return this.createTextResult(result);
-      }
-    } else {
-      return this.createTextResult(result);
-    }
+    return this.queryResult('clients', format);
   }
 
   async handleWorkspaces(args) {
     await this.validateArgs(args, this.tools[3].inputSchema);
     
     const { format = 'json' } = args;
-    const command = format === 'json' ? 'j/workspaces' : 'workspaces';
-    
-    const result = await this.sendHyprlandCommand(command);
-    
-    if (format === 'json') {
-      try {
-        const workspaces = JSON.parse(result);
-        return this.createTextResult(JSON.stringify(workspaces, null, 2));
-      } catch {
-        return this.createTextResult(result);
-      }
-    } else {
-      return this.createTextResult(result);
-    }
+    return this.queryResult('workspaces', format);
   }
 
   async handleMonitors(args) {
     await this.validateArgs(args, this.tools[4].inputSchema);
     
     const { format = 'json' } = args;
-    const command = format === 'json' ? 'j/monitors' : 'monitors';
-    
-    const result = await this.sendHyprlandCommand(command);
-    
-    if (format === 'json') {
-      try {
-        const monitors = JSON.parse(result);
-        return this.createTextResult(JSON.stringify(monitors, null, 2));
-      } catch {
-        return this.createTextResult(result);
-      }
-    } else {
-      return this.createTextResult(result);
-    }
+    return this.queryResult('monitors', format);
   }
 
   async handleReload(args) {
@@ -392,7 +630,7 @@ export class HyprlandPlugin extends BasePlugin {
         break;
       case 'set':
         if (!layout) throw new Error('Layout name required for set action');
-        command = `dispatch exec hyprctl keyword general:layout ${layout}`;
+        command = `keyword general:layout ${layout}`;
         break;
       default:
         throw new Error(`Unknown layout action: ${action}`);
@@ -406,35 +644,231 @@ export class HyprlandPlugin extends BasePlugin {
     await this.validateArgs(args, this.tools[7].inputSchema);
     
     const { action, target = '', args: actionArgs = '' } = args;
-    let command;
-    
-    switch (action) {
-      case 'focus':
-        command = target ? `dispatch focuswindow ${target}` : 'dispatch focuswindow';
-        break;
-      case 'move':
-        command = target ? `dispatch movewindow ${target}` : 'dispatch movewindow';
-        break;
-      case 'resize':
-        command = actionArgs ? `dispatch resizeactive ${actionArgs}` : 'dispatch resizeactive 10 10';
-        break;
-      case 'close':
-        command = 'dispatch killactive';
-        break;
-      case 'float':
-        command = 'dispatch togglefloating';
-        break;
-      case 'fullscreen':
-        command = 'dispatch fullscreen';
-        break;
-      default:
-        throw new Error(`Unknown window action: ${action}`);
-    }
-    
-    const result = await this.sendHyprlandCommand(command);
+    const result = await this.ipc().windowAction(action, target, actionArgs);
     return this.createTextResult(result || `Window ${action} executed`);
   }
 
+  async handleBatch(args) {
+    await this.validateArgs(args, this.tools[8].inputSchema);
+
+    const { commands } = args;
+    if (commands.length === 0 || commands.length > MAX_BATCH_COMMANDS) {
+      throw new Error(`commands must hold 1 to ${MAX_BATCH_COMMANDS} items`);
+    }
+
+    const lines = commands.map((command, index) => hyprlandCommandLine(command, `commands[${index}]`));
+    const results = await this.ipc().batch(lines);
+    const failed = results.filter(result => result.ok === false).length;
+    const unknown = results.filter(result => result.ok === null).length;
+    return this.createTextResult(JSON.stringify({
+      ok: failed === 0 && unknown === 0,
+      results: results.map((result, index) => ({ index, ...result }))
+    }, null, 2));
+  }
+
+  async handleRulesList(args) {
+    return this.createTextResult(JSON.stringify({
+      listening: Boolean(this.events && !this.events.stopped),
+      rules: this.rules.list()
+    }, null, 2));
+  }
+
+  async handleRuleAdd(args) {
+    await this.validateArgs(args, this.tools[10].inputSchema);
+
+    const rule = await this.rules.add({ name: args.name, on: args.on, match: args.match || {}, actions: args.actions });
+    const note = this.events ? '' : ' (Hyprland events are not being received, so it will not fire yet)';
+    return this.createTextResult(`Rule ${rule.name} runs ${rule.actions.length} action${rule.actions.length > 1 ? 's' : ''} on ${rule.on}${note}`);
+  }
+
+  async handleRuleRemove(args) {
+    await this.validateArgs(args, this.tools[11].inputSchema);
+
+    await this.rules.remove(args.name);
+    return this.createTextResult(`Removed rule ${args.name}`);
+  }
+
+  userConfigPath(account) {
+    return path.join(account.home, '.config', 'hypr', 'hyprland.conf');
+  }
+
+  checkScratchpadName(name) {
+    if (!SCRATCHPAD_NAME.test(name)) {
+      throw new Error(`Invalid scratchpad name: ${name}`);
+    }
+  }
+
+  async handleScratchpadDefine(args) {
+    await this.validateArgs(args, this.tools[12].inputSchema);
+
+    const { name, command } = args;
+    this.checkScratchpadName(name);
+    if (/[\r\n]/.test(command)) {
+      throw new Error('command must be a single line');
+    }
+
+    const account = await lookupUser(args.user);
+    const file = this.userConfigPath(account);
+    const rule = `special:${name}, on-created-empty:${command}`;
+    const lines = upsertLine(await readLines(file), new RegExp(`^\\s*workspace\\s*=\\s*special:${name}\\s*,`), `workspace = ${rule}`);
+    await writeUserFile(file, lines.join('\n'), account);
+
+    // The running session picks it up without a reload
+    let applied = false;
+    if (this.isAvailable) {
+      applied = (await this.sendHyprlandCommand(`keyword workspace ${rule}`)) === 'ok';
+    }
+    return this.createTextResult(`Scratchpad ${name} saved in ${file}${applied ? ' and applied' : ''}`);
+  }
+
+  async handleScratchpadRemove(args) {
+    await this.validateArgs(args, this.tools[13].inputSchema);
+
+    const { name } = args;
+    this.checkScratchpadName(name);
+    const account = await lookupUser(args.user);
+    const file = this.userConfigPath(account);
+    const lines = await readLines(file);
+    const kept = lines.filter(line => line.match(SCRATCHPAD_RULE)?.[1] !== name);
+    if (kept.length === lines.length) {
+      throw new Error(`No scratchpad named ${name} in ${file}`);
+    }
+    await writeUserFile(file, kept.join('\n'), account);
+    return this.createTextResult(`Removed scratchpad ${name} from ${file}; it stays defined in the running session until Hyprland reloads`);
+  }
+
+  // Names of the special workspaces shown on each monitor
+  async shownScratchpads() {
+    const monitors = await this.ipc().query('monitors');
+    return Object.fromEntries((monitors || [])
+      .filter(monitor => monitor.specialWorkspace?.name)
+      .map(monitor => [monitor.name, monitor.specialWorkspace.name.replace(/^special:/, '')]));
+  }
+
+  async handleScratchpadToggle(args) {
+    await this.validateArgs(args, this.tools[14].inputSchema);
+
+    const { name, action = 'toggle' } = args;
+    this.checkScratchpadName(name);
+    if (action !== 'toggle') {
+      const focused = ((await this.ipc().query('monitors')) || []).find(monitor => monitor.focused);
+      const shown = focused?.specialWorkspace?.name === `special:${name}`;
+      if (shown === (action === 'show')) {
+        return this.createTextResult(`Scratchpad ${name} is already ${shown ? 'shown' : 'hidden'}`);
+      }
+    }
+
+    const reply = await this.sendHyprlandCommand(`dispatch togglespecialworkspace ${name}`);
+    if (reply !== 'ok') {
+      throw new Error(`Hyprland rejected togglespecialworkspace ${name}: ${reply}`);
+    }
+    return this.createTextResult(`Scratchpad ${name} toggled`);
+  }
+
+  async handleScratchpadSend(args) {
+    await this.validateArgs(args, this.tools[15].inputSchema);
+
+    const { name, window = 'active', follow = false } = args;
+    this.checkScratchpadName(name);
+    const target = await this.ipc().findWindow(window);
+    if (!target) {
+      throw new Error(`No window matches ${window}`);
+    }
+
+    const dispatcher = follow ? 'movetoworkspace' : 'movetoworkspacesilent';
+    const reply = await this.sendHyprlandCommand(`dispatch ${dispatcher} special:${name},address:${target.id}`);
+    if (reply !== 'ok') {
+      throw new Error(`Hyprland rejected ${dispatcher}: ${reply}`);
+    }
+    return this.createTextResult(`Moved ${target.class || target.id} to scratchpad ${name}`);
+  }
+
+  async handleScratchpadList(args) {
+    await this.validateArgs(args, this.tools[16].inputSchema);
+
+    const definitions = {};
+    if (args.user) {
+      const file = this.userConfigPath(await lookupUser(args.user));
+      for (const line of await readLines(file)) {
+        const match = line.match(SCRATCHPAD_RULE);
+        if (match) {
+          definitions[match[1]] = match[2].trim();
+        }
+      }
+    }
+
+    const live = {};
+    if (this.isAvailable) {
+      const [workspaces, shown] = await Promise.all([this.ipc().query('workspaces'), this.shownScratchpads()]);
+      for (const workspace of (workspaces || []).filter(item => item.name?.startsWith('special:'))) {
+        const name = workspace.name.replace(/^special:/, '');
+        live[name] = {
+          windows: workspace.windows,
+          shownOn: Object.keys(shown).filter(monitor => shown[monitor] === name)
+        };
+      }
+    }
+
+    const names = [...new Set([...Object.keys(definitions), ...Object.keys(live)])].sort();
+    return this.createTextResult(JSON.stringify(names.map(name => ({
+      name,
+      command: definitions[name] ?? null,
+      open: Boolean(live[name]),
+      windows: live[name]?.windows ?? 0,
+      shownOn: live[name]?.shownOn ?? []
+    })), null, 2));
+  }
+
+  // Each matching rule runs as one audited operation; consecutive dispatchers and keywords
+  // go out as a single batch, tools run through the same path as client calls
+  runRules(event) {
+    for (const matched of this.rules.match(event)) {
+      this.runRule(matched);
+    }
+  }
+
+  async runRule({ rule, fields, actions }) {
+    if (this.security.readOnly) {
+      this.logger.debug(`Automation rule ${rule.name} skipped in read-only mode`);
+      return;
+    }
+
+    try {
+      await this.security.executeWithAudit('automation_rule', { rule: rule.name, event: rule.on, fields, actions }, async () => {
+        let batch = [];
+        const flush = async () => {
+          if (batch.length === 0) return;
+          const failed = (await this.ipc().batch(batch)).find(result => result.ok === false);
+          batch = [];
+          if (failed) {
+            throw new Error(`${failed.command}: ${failed.reply}`);
+          }
+        };
+
+        for (const [index, action] of actions.entries()) {
+          if (action.type !== 'tool') {
+            batch.push(hyprlandCommandLine(action, `actions[${index}]`));
+            continue;
+          }
+          await flush();
+          if (!this.security.toolRunner) {
+            throw new Error('Tool actions need the running server');
+          }
+          // The user set the rule up, so presence does not hold it back
+          const result = await this.security.toolRunner(action.name, action.arguments || {}, { ignorePresence: true });
+          if (result?.isError) {
+            throw new Error(`${action.name}: ${result.content?.[0]?.text || 'failed'}`);
+          }
+        }
+        await flush();
+      });
+      this.rules.recordOutcome(rule.name);
+    } catch (error) {
+      this.rules.recordOutcome(rule.name, error);
+      this.logger.warn(`Automation rule ${rule.name} failed:`, error.message);
+    }
+  }
+
   async getConfig() {
     const configPath = process.env.HOME ? 
       path.join(process.env.HOME, '.config', 'hypr', 'hyprland.conf') :
@@ -459,17 +893,18 @@ export class HyprlandPlugin extends BasePlugin {
     }
 
     try {
+      const ipc = this.ipc();
       const [version, activeWindow, workspaces] = await Promise.all([
-        this.sendHyprlandCommand('version'),
-        this.sendHyprlandCommand('j/activewindow'),
-        this.sendHyprlandCommand('j/workspaces')
+        ipc.version(),
+        ipc.query('activewindow'),
+        ipc.query('workspaces')
       ]);
 
       const status = {
         available: true,
         version,
-        activeWindow: JSON.parse(activeWindow || '{}'),
-        workspaceCount: JSON.parse(workspaces || '[]').length,
+        activeWindow: activeWindow || {},
+        workspaceCount: (workspaces || []).length,
         socketPath: this.socketPath
       };
 
@@ -486,16 +921,17 @@ export class HyprlandPlugin extends BasePlugin {
 
   async getLayout() {
     try {
+      const ipc = this.ipc();
       const [windows, workspaces, monitors] = await Promise.all([
-        this.sendHyprlandCommand('j/clients'),
-        this.sendHyprlandCommand('j/workspaces'),
-        this.sendHyprlandCommand('j/monitors')
+        ipc.query('clients'),
+        ipc.query('workspaces'),
+        ipc.query('monitors')
       ]);
 
       const layout = {
-        windows: JSON.parse(windows || '[]'),
-        workspaces: JSON.parse(workspaces || '[]'),
-        monitors: JSON.parse(monitors || '[]')
+        windows: windows || [],
+        workspaces: workspaces || [],
+        monitors: monitors || []
       };
 
       return { content: JSON.stringify(layout, null, 2) };
@@ -0,0 +1,423 @@
+import { BasePlugin } from './base-plugin.js';
+import { CommandExecutor } from '../system/command-executor.js';
+import { LockKeys } from '../security/lock-manager.js';
+import { previewDiff } from '../system/kernel-params.js';
+import {
+  POWER_BACKENDS,
+  CONFLICTING_SERVICES,
+  LID_ACTIONS,
+  LOGIND_KEYS,
+  LOGIND_DROP_IN,
+  CHARGE_RULE_FILE,
+  listBatteries,
+  checkBatteryName,
+  writeThresholds,
+  renderChargeRule,
+  readLogindSettings,
+  renderLogindDropIn
+} from '../system/laptop-power.js';
+import fs from 'fs-extra';
+import path from 'path';
+
+const PROFILES = ['power-saver', 'balanced', 'performance'];
+
+// Laptop power management: TLP or power-profiles-daemon, battery charge thresholds,
+// and lid/power key handling in logind
+export class LaptopPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('laptop', config, logger, security);
+    this.description = 'Laptop power backends, charge thresholds and lid behaviour';
+    this.commandExecutor = new CommandExecutor(
+      config.get?.('security') || config.security || {},
+      logger,
+      security
+    );
+    this.dependencies = ['pacman', 'systemctl', 'udevadm'];
+    this.optionalDependencies = ['powerprofilesctl'];
+
+    this.initializeTools();
+    this.initializeResources();
+  }
+
+  initializeTools() {
+    const lidAction = (description) => ({
+      type: 'string',
+      enum: LID_ACTIONS,
+      description
+    });
+
+    this.tools = [
+      this.createTool(
+        'laptop_power_status',
+        'Show the power backend, conflicting daemons, batteries with charge thresholds, and lid settings',
+        {
+          type: 'object',
+          properties: {}
+        }
+      ),
+
+      this.createTool(
+        'power_backend_set',
+        'Install and enable TLP or power-profiles-daemon, disabling and masking the other',
+        {
+          type: 'object',
+          properties: {
+            backend: {
+              type: 'string',
+              enum: Object.keys(POWER_BACKENDS)
+            },
+            profile: {
+              type: 'string',
+              enum: PROFILES,
+              description: 'power-profiles-daemon only: profile to switch to'
+            },
+            removeOther: {
+              type: 'boolean',
+              description: 'Uninstall the other backend instead of only masking it',
+              default: false
+            },
+            dryRun: {
+              type: 'boolean',
+              default: false
+            }
+          },
+          required: ['backend']
+        }
+      ),
+
+      this.createTool(
+        'charge_thresholds_set',
+        'Limit battery charging to extend battery life, now and at every boot',
+        {
+          type: 'object',
+          properties: {
+            battery: {
+              type: 'string',
+              description: 'e.g. BAT0 (default: the first battery that supports thresholds)'
+            },
+            start: {
+              type: 'number',
+              description: 'Start charging below this percentage'
+            },
+            end: {
+              type: 'number',
+              description: 'Stop charging at this percentage'
+            },
+            persist: {
+              type: 'boolean',
+              description: `Reapply at boot with ${CHARGE_RULE_FILE}`,
+              default: true
+            },
+            dryRun: {
+              type: 'boolean',
+              default: false
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'lid_behavior_set',
+        'Set what closing the lid, the power key and idling do, in a logind.conf drop-in',
+        {
+          type: 'object',
+          properties: {
+            lidSwitch: lidAction('Lid closed on battery'),
+            lidSwitchExternalPower: lidAction('Lid closed on AC power'),
+            lidSwitchDocked: lidAction('Lid closed with an external display or dock'),
+            powerKey: lidAction('Power key pressed'),
+            suspendKey: lidAction('Suspend key pressed'),
+            idleAction: lidAction('When the session has been idle for idleActionSec'),
+            idleActionSec: {
+              type: 'string',
+              pattern: '^[0-9]+(s|min|h)?$',
+              description: 'e.g. 30min'
+            },
+            dryRun: {
+              type: 'boolean',
+              default: false
+            }
+          }
+        }
+      )
+    ];
+  }
+
+  initializeResources() {
+    this.resources = [
+      this.createResource(
+        'laptop://power',
+        'Laptop Power',
+        'Power backend state, conflicts, batteries and logind lid settings',
+        'application/json'
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'laptop_power_status':
+          return this.createTextResult(JSON.stringify(await this.powerStatus(), null, 2));
+        case 'power_backend_set':
+          return this.handleBackendSet(args);
+        case 'charge_thresholds_set':
+          return this.handleThresholds(args);
+        case 'lid_behavior_set':
+          return this.handleLidBehavior(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  async readResource(uri) {
+    return this.withErrorHandling(async () => {
+      switch (uri) {
+        case 'laptop://power':
+          return { content: JSON.stringify(await this.powerStatus(), null, 2) };
+        default:
+          throw new Error(`Unknown resource: ${uri}`);
+      }
+    }, 'readResource');
+  }
+
+  getToolLocks(toolName, args = {}) {
+    return this.affectsPackages(toolName, args) ? [LockKeys.pacmanDb()] : [];
+  }
+
+  affectsPackages(toolName, args = {}) {
+    return toolName === 'power_backend_set' && !args.dryRun;
+  }
+
+  // Every tool converges on the requested state
+  isIdempotent() {
+    return true;
+  }
+
+  isReadOnly(toolName, args = {}) {
+    return ['laptop_power_status'].includes(toolName) || super.isReadOnly(toolName, args);
+  }
+
+  async getAffectedPaths(toolName, args = {}) {
+    if (args.dryRun) {
+      return [];
+    }
+    switch (toolName) {
+      case 'power_backend_set':
+        return ['/etc/systemd/system'];
+      case 'charge_thresholds_set':
+        return args.persist === false ? [] : [CHARGE_RULE_FILE];
+      case 'lid_behavior_set':
+        return [LOGIND_DROP_IN];
+      default:
+        return [];
+    }
+  }
+
+  async powerStatus() {
+    const backends = {};
+    for (const [name, backend] of Object.entries(POWER_BACKENDS)) {
+      backends[name] = {
+        installed: await this.isInstalled(backend.package),
+        ...await this.serviceState(backend.service)
+      };
+    }
+    const others = {};
+    for (const service of CONFLICTING_SERVICES) {
+      const state = await this.serviceState(service);
+      if (state.enabled !== 'not-found') {
+        others[service] = state;
+      }
+    }
+
+    const running = [
+      ...Object.entries(backends).filter(([, state]) => state.enabled === 'enabled' || state.active === 'active').map(([name]) => name),
+      ...Object.entries(others).filter(([, state]) => state.enabled === 'enabled' || state.active === 'active').map(([name]) => name)
+    ];
+
+    let profile = null;
+    if (backends['power-profiles-daemon'].active === 'active') {
+      const result = await this.commandExecutor.execute('powerprofilesctl', ['get']);
+      profile = result.success ? result.stdout.trim() : null;
+    }
+
+    const logind = await readLogindSettings();
+    return {
+      backend: running.find(name => POWER_BACKENDS[name]) || null,
+      ...(profile ? { profile } : {}),
+      backends,
+      ...(Object.keys(others).length > 0 ? { otherDaemons: others } : {}),
+      conflicts: running.length > 1 ? running : [],
+      batteries: (await listBatteries()).map(({ dir, ...battery }) => battery),
+      logind: Object.fromEntries(Object.values(LOGIND_KEYS).map(key => [key, logind[key] || null]))
+    };
+  }
+
+  async handleBackendSet(args) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+
+    const { backend, profile, removeOther = false, dryRun = false } = args;
+    if (profile && backend !== 'power-profiles-daemon') {
+      throw new Error('profile only applies to power-profiles-daemon; TLP is configured in /etc/tlp.conf');
+    }
+    const chosen = POWER_BACKENDS[backend];
+    const [otherName, other] = Object.entries(POWER_BACKENDS).find(([name]) => name !== backend);
+
+    const steps = [];
+    const otherInstalled = await this.isInstalled(other.package);
+    if (otherInstalled) {
+      steps.push(['systemctl', ['disable', '--now', other.service]]);
+      steps.push(removeOther
+        ? ['pacman', ['-Rns', '--noconfirm', other.package]]
+        : ['systemctl', ['mask', other.service]]);
+    }
+    for (const service of CONFLICTING_SERVICES) {
+      if ((await this.serviceState(service)).enabled === 'enabled') {
+        steps.push(['systemctl', ['disable', '--now', service]]);
+      }
+    }
+    if (other.mask.length > 0) {
+      steps.push(['systemctl', ['unmask', ...other.mask]]);
+    }
+    steps.push(['pacman', ['-S', '--needed', '--noconfirm', chosen.package]]);
+    steps.push(['systemctl', ['unmask', chosen.service]]);
+    if (chosen.mask.length > 0) {
+      steps.push(['systemctl', ['mask', ...chosen.mask]]);
+    }
+    steps.push(['systemctl', ['enable', '--now', chosen.service]]);
+    if (profile) {
+      steps.push(['powerprofilesctl', ['set', profile]]);
+    }
+
+    const plan = steps.map(([command, commandArgs]) => `${command} ${commandArgs.join(' ')}`);
+    if (dryRun) {
+      return this.createTextResult(plan.join('\n'), { dryRun });
+    }
+
+    const done = [];
+    for (const [command, commandArgs] of steps) {
+      const result = command === 'powerprofilesctl'
+        ? await this.commandExecutor.execute(command, commandArgs, { retry: false })
+        : await this.commandExecutor.executeWithSudo(command, commandArgs, { retry: false });
+      if (!result.success) {
+        const hint = command === 'pacman' && /conflict/i.test(result.stderr) && !removeOther ? '; retry with removeOther to uninstall the other backend' : '';
+        throw new Error(`${command} ${commandArgs.join(' ')} failed after ${done.length} steps: ${result.stderr.trim()}${hint}`);
+      }
+      done.push(`${command} ${commandArgs.join(' ')}`);
+    }
+
+    this.logger.audit('power_backend_set', { backend, profile, removeOther, replaced: otherInstalled ? otherName : null });
+    return this.createTextResult(JSON.stringify({
+      backend,
+      steps: done,
+      status: await this.powerStatus()
+    }, null, 2));
+  }
+
+  async handleThresholds(args) {
+    await this.validateArgs(args, this.tools[2].inputSchema);
+
+    const { start, end, persist = true, dryRun = false } = args;
+    if (start === undefined && end === undefined) {
+      throw new Error('Give start, end or both');
+    }
+    for (const [name, value] of [['start', start], ['end', end]]) {
+      if (value !== undefined && (!Number.isInteger(value) || value < 0 || value > 100)) {
+        throw new Error(`${name} must be a whole percentage between 0 and 100`);
+      }
+    }
+    if (start !== undefined && end !== undefined && start >= end) {
+      throw new Error('start must be below end');
+    }
+
+    const batteries = await listBatteries();
+    const battery = args.battery
+      ? batteries.find(candidate => candidate.name === checkBatteryName(args.battery))
+      : batteries.find(candidate => candidate.thresholds.supported);
+    if (!battery) {
+      throw new Error(args.battery
+        ? `No battery ${args.battery} (found: ${batteries.map(candidate => candidate.name).join(', ') || 'none'})`
+        : batteries.length > 0 ? 'No battery supports charge thresholds on this kernel/firmware' : 'No battery found');
+    }
+    if (!battery.thresholds.supported || (start !== undefined && battery.thresholds.start === null)) {
+      throw new Error(`${battery.name} does not support ${start !== undefined && battery.thresholds.supported ? 'a start threshold' : 'charge thresholds'}`);
+    }
+
+    const before = persist ? await fs.readFile(CHARGE_RULE_FILE, 'utf8').catch(() => null) : null;
+    const after = persist ? renderChargeRule(before, battery.name, { start, end }) : null;
+    const steps = [
+      `${battery.name}: ${[start !== undefined ? `start ${battery.thresholds.start} -> ${start}` : null, end !== undefined ? `end ${battery.thresholds.end} -> ${end}` : null].filter(Boolean).join(', ')}`,
+      ...(persist ? [previewDiff(CHARGE_RULE_FILE, before, after)] : [])
+    ].filter(Boolean);
+
+    if (dryRun) {
+      return this.createTextResult(steps.join('\n'), { dryRun });
+    }
+
+    await writeThresholds(battery, { start, end });
+    if (persist && before !== after) {
+      await fs.ensureDir(path.dirname(CHARGE_RULE_FILE));
+      await fs.writeFile(CHARGE_RULE_FILE, after, { mode: 0o644 });
+      const reload = await this.commandExecutor.executeWithSudo('udevadm', ['control', '--reload'], { retry: false });
+      if (!reload.success) {
+        throw new Error(`Thresholds set and ${CHARGE_RULE_FILE} written, but udevadm control --reload failed: ${reload.stderr.trim()}`);
+      }
+    }
+
+    const tlp = await this.serviceState(POWER_BACKENDS.tlp.service);
+    this.logger.audit('charge_thresholds_set', { battery: battery.name, start, end, persist });
+    return this.createTextResult(steps.join('\n'), {
+      battery: battery.name,
+      ...(tlp.enabled === 'enabled' ? { note: 'TLP is enabled; START/STOP_CHARGE_THRESH_* in /etc/tlp.conf override these at boot when set' } : {})
+    });
+  }
+
+  async handleLidBehavior(args) {
+    await this.validateArgs(args, this.tools[3].inputSchema);
+
+    const { dryRun = false } = args;
+    const values = Object.fromEntries(Object.entries(LOGIND_KEYS)
+      .filter(([param]) => args[param] !== undefined)
+      .map(([param, key]) => [key, args[param]]));
+    if (Object.keys(values).length === 0) {
+      throw new Error(`Give at least one of ${Object.keys(LOGIND_KEYS).join(', ')}`);
+    }
+
+    const edit = await renderLogindDropIn(values);
+    const diff = previewDiff(edit.file, edit.before, edit.after);
+    if (dryRun || !diff) {
+      return this.createTextResult(diff || 'Already set', { dryRun });
+    }
+
+    await fs.ensureDir(path.dirname(edit.file));
+    await fs.writeFile(edit.file, edit.after, { mode: 0o644 });
+
+    // logind re-reads its configuration on SIGHUP; restarting it would end every session
+    const reload = await this.commandExecutor.executeWithSudo('systemctl', ['kill', '-s', 'HUP', 'systemd-logind.service'], { retry: false });
+
+    // A drop-in sorting after ours still wins
+    const effective = await readLogindSettings();
+    const shadowed = Object.keys(values).filter(key => effective[key]?.file !== edit.file);
+
+    this.logger.audit('lid_behavior_set', values);
+    return this.createTextResult([
+      diff,
+      reload.success ? 'systemd-logind reloaded' : `Written; reloading systemd-logind failed (${reload.stderr.trim()}), the settings apply after a reboot`,
+      ...(shadowed.length > 0 ? [`Overridden elsewhere: ${shadowed.map(key => `${key} in ${effective[key].file}`).join(', ')}`] : [])
+    ].join('\n'), { file: edit.file });
+  }
+
+  async isInstalled(pkg) {
+    return (await this.commandExecutor.execute('pacman', ['-Q', pkg])).success;
+  }
+
+  async serviceState(service) {
+    const enabled = await this.commandExecutor.execute('systemctl', ['is-enabled', service]);
+    const active = await this.commandExecutor.execute('systemctl', ['is-active', service]);
+    return {
+      enabled: enabled.stdout.trim() || 'not-found',
+      active: active.stdout.trim() || 'unknown'
+    };
+  }
+}
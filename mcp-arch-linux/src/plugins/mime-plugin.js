@@ -0,0 +1,368 @@
+import { BasePlugin } from './base-plugin.js';
+import { CommandExecutor } from '../system/command-executor.js';
+import { getToolResolver } from '../system/tool-resolver.js';
+import { assertWithinRoots } from '../security/roots.js';
+import { lookupUser } from '../system/user-account.js';
+import { readLines, writeUserFile } from '../system/user-files.js';
+import {
+  DESKTOP_ID_PATTERN,
+  normalizeMimeType,
+  listDesktopEntries,
+  entryExecutable,
+  readMimeapps,
+  mimeappsFiles,
+  resolveDefault,
+  handlersFor,
+  setMimeappsEntries
+} from '../system/desktop-entries.js';
+import fs from 'fs-extra';
+import path from 'path';
+
+const WILDCARD = /^([a-z0-9][a-z0-9.+-]*)\/\*$/;
+
+// Default applications per MIME type and URL scheme, via the user's mimeapps.list
+export class MimePlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('mime', config, logger, security);
+    this.description = 'Default applications for MIME types and URL schemes';
+    this.optionalDependencies = ['xdg-mime'];
+
+    this.commandExecutor = new CommandExecutor(
+      config.get?.('security') || config.security || {},
+      logger,
+      security
+    );
+
+    this.initializeTools();
+  }
+
+  initializeTools() {
+    const user = {
+      type: 'string',
+      description: 'User whose defaults are read or changed'
+    };
+    const types = {
+      type: 'array',
+      items: { type: 'string' },
+      description: 'MIME types, e.g. image/png or image/* (set only: every image type the handler supports)'
+    };
+    const schemes = {
+      type: 'array',
+      items: { type: 'string' },
+      description: 'URL schemes, e.g. https, mailto, magnet'
+    };
+
+    this.tools = [
+      this.createTool(
+        'desktop_entries',
+        'List installed applications (.desktop entries) with the MIME types they handle',
+        {
+          type: 'object',
+          properties: {
+            user,
+            mimeType: {
+              type: 'string',
+              description: 'Only applications handling this MIME type or URL scheme'
+            },
+            search: {
+              type: 'string',
+              description: 'Case-insensitive match on desktop ID or name'
+            },
+            includeHidden: {
+              type: 'boolean',
+              description: 'Include NoDisplay entries (helpers not shown in menus)',
+              default: false
+            }
+          },
+          required: ['user']
+        }
+      ),
+
+      this.createTool(
+        'mime_default_get',
+        'Show the default application and all candidate handlers for MIME types, URL schemes or files',
+        {
+          type: 'object',
+          properties: {
+            user,
+            types,
+            schemes,
+            files: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Files whose type is detected with xdg-mime query filetype'
+            }
+          },
+          required: ['user']
+        }
+      ),
+
+      this.createTool(
+        'mime_default_set',
+        'Make an application the default for MIME types or URL schemes',
+        {
+          type: 'object',
+          properties: {
+            user,
+            handler: {
+              type: 'string',
+              description: 'Desktop ID, e.g. imv.desktop or org.mozilla.firefox.desktop (".desktop" may be omitted)'
+            },
+            types,
+            schemes
+          },
+          required: ['user', 'handler']
+        }
+      ),
+
+      this.createTool(
+        'mime_validate',
+        'Check that every configured default and association points to an installed, runnable application',
+        {
+          type: 'object',
+          properties: {
+            user,
+            fix: {
+              type: 'boolean',
+              description: 'Remove broken entries from the user\'s ~/.config/mimeapps.list',
+              default: false
+            }
+          },
+          required: ['user']
+        }
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'desktop_entries':
+          return this.handleDesktopEntries(args);
+        case 'mime_default_get':
+          return this.handleGet(args);
+        case 'mime_default_set':
+          return this.handleSet(args);
+        case 'mime_validate':
+          return this.handleValidate(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  isIdempotent() {
+    return true;
+  }
+
+  isReadOnly(toolName, args = {}) {
+    return ['desktop_entries', 'mime_default_get'].includes(toolName) || (toolName === 'mime_validate' && !args.fix);
+  }
+
+  async getAffectedPaths(toolName, args = {}) {
+    if (!args.user || !(toolName === 'mime_default_set' || (toolName === 'mime_validate' && args.fix))) {
+      return [];
+    }
+    try {
+      return [mimeappsFiles(await lookupUser(args.user))[0]];
+    } catch {
+      return [];
+    }
+  }
+
+  async handleDesktopEntries(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const { search = null, includeHidden = false } = args;
+    const type = args.mimeType ? normalizeMimeType(args.mimeType) : null;
+    const account = await lookupUser(args.user);
+
+    const entries = Array.from((await listDesktopEntries(account)).values())
+      .filter(entry => includeHidden || !entry.noDisplay)
+      .filter(entry => !type || entry.mimeTypes.includes(type))
+      .filter(entry => !search || `${entry.id} ${entry.name}`.toLowerCase().includes(search.toLowerCase()))
+      .sort((a, b) => a.id.localeCompare(b.id))
+      .map(entry => ({
+        id: entry.id,
+        name: entry.name,
+        exec: entry.exec,
+        mimeTypes: type ? undefined : entry.mimeTypes,
+        file: entry.file
+      }));
+
+    return this.createTextResult(JSON.stringify(entries, null, 2), { count: entries.length });
+  }
+
+  async handleGet(args) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+
+    const account = await lookupUser(args.user);
+    const requested = [...(args.types || []), ...(args.schemes || [])].map(normalizeMimeType);
+    const fileTypes = {};
+    for (const file of args.files || []) {
+      const type = await this.detectFileType(file);
+      fileTypes[type] = [...(fileTypes[type] || []), file];
+      requested.push(type);
+    }
+    if (requested.length === 0) {
+      throw new Error('Give types, schemes or files');
+    }
+
+    const entries = await listDesktopEntries(account);
+    const mimeapps = await readMimeapps(account);
+    const report = Array.from(new Set(requested)).map(type => ({
+      type,
+      ...(fileTypes[type] ? { files: fileTypes[type] } : {}),
+      default: resolveDefault(type, mimeapps, entries),
+      handlers: handlersFor(type, entries)
+    }));
+
+    return this.createTextResult(JSON.stringify(report, null, 2));
+  }
+
+  async handleSet(args) {
+    await this.validateArgs(args, this.tools[2].inputSchema);
+
+    const account = await lookupUser(args.user);
+    const handler = args.handler.endsWith('.desktop') ? args.handler : `${args.handler}.desktop`;
+    if (!DESKTOP_ID_PATTERN.test(handler)) {
+      throw new Error(`Invalid desktop ID: ${args.handler}`);
+    }
+
+    const entries = await listDesktopEntries(account);
+    const entry = entries.get(handler);
+    if (!entry) {
+      const similar = Array.from(entries.keys()).filter(id => id.toLowerCase().includes(handler.replace(/\.desktop$/, '').toLowerCase()));
+      throw new Error(`No installed application ${handler}${similar.length > 0 ? ` (did you mean ${similar.slice(0, 5).join(', ')}?)` : '; see desktop_entries'}`);
+    }
+    const problem = await this.checkRunnable(entry);
+    if (problem) {
+      throw new Error(`${handler} cannot be used: ${problem}`);
+    }
+
+    const types = this.expandTypes([...(args.types || []), ...(args.schemes || [])], entry);
+    if (types.length === 0) {
+      throw new Error('Give types or schemes');
+    }
+    const unsupported = types.filter(type => !entry.mimeTypes.includes(type));
+
+    const file = mimeappsFiles(account)[0];
+    const lines = setMimeappsEntries(await readLines(file), 'Default Applications',
+      Object.fromEntries(types.map(type => [type, [handler]])));
+    await writeUserFile(file, lines.join('\n'), account);
+
+    // A handler listed under Removed Associations would still be skipped
+    const mimeapps = await readMimeapps(account);
+    const effective = Object.fromEntries(types.map(type => [type, resolveDefault(type, mimeapps, entries).handler]));
+    const overridden = types.filter(type => effective[type] !== handler);
+    const desktopSpecific = (await fs.readdir(path.dirname(file)).catch(() => [])).filter(name => name.endsWith('-mimeapps.list'));
+
+    this.logger.audit('mime_default_set', { user: account.name, handler, types });
+    return this.createTextResult(JSON.stringify({
+      handler,
+      file,
+      types,
+      ...(unsupported.length > 0 ? { warning: `${handler} does not list ${unsupported.join(', ')} in its MimeType; it may not open them` } : {}),
+      ...(overridden.length > 0 ? { overridden: Object.fromEntries(overridden.map(type => [type, effective[type]])) } : {}),
+      ...(desktopSpecific.length > 0 ? { note: `Desktop-specific files take precedence where that desktop runs: ${desktopSpecific.join(', ')}` } : {})
+    }, null, 2));
+  }
+
+  async handleValidate(args) {
+    await this.validateArgs(args, this.tools[3].inputSchema);
+
+    const { fix = false } = args;
+    const account = await lookupUser(args.user);
+    const entries = await listDesktopEntries(account);
+    const mimeapps = await readMimeapps(account);
+    const userFile = mimeappsFiles(account)[0];
+
+    const problems = [];
+    const runnable = new Map();
+    for (const file of mimeapps) {
+      for (const [section, name] of [['defaults', 'Default Applications'], ['added', 'Added Associations']]) {
+        for (const [type, ids] of Object.entries(file[section])) {
+          for (const id of ids) {
+            const entry = entries.get(id);
+            if (!runnable.has(id)) {
+              runnable.set(id, entry ? await this.checkRunnable(entry) : 'not installed');
+            }
+            if (runnable.get(id)) {
+              problems.push({ file: file.file, section: name, type, handler: id, problem: runnable.get(id) });
+            }
+          }
+        }
+      }
+    }
+
+    let fixed = 0;
+    const userProblems = problems.filter(problem => problem.file === userFile);
+    if (fix && userProblems.length > 0) {
+      let lines = await readLines(userFile);
+      const userMimeapps = mimeapps.find(file => file.file === userFile);
+      for (const [section, name] of [['defaults', 'Default Applications'], ['added', 'Added Associations']]) {
+        const broken = userProblems.filter(problem => problem.section === name);
+        const updates = {};
+        for (const problem of broken) {
+          updates[problem.type] = (updates[problem.type] || userMimeapps[section][problem.type])
+            .filter(id => id !== problem.handler);
+        }
+        lines = setMimeappsEntries(lines, name, updates);
+      }
+      await writeUserFile(userFile, lines.join('\n'), account);
+      fixed = userProblems.length;
+    }
+
+    return this.createTextResult(JSON.stringify({
+      files: mimeapps.map(file => file.file),
+      valid: problems.length === 0,
+      problems,
+      ...(fix ? { fixed } : {}),
+      ...(problems.length > fixed && fix ? { note: 'Entries in system files are left alone; override them with mime_default_set' } : {})
+    }, null, 2), { problems: problems.length });
+  }
+
+  // Entries can outlive their package, or point at a program that is not on PATH
+  async checkRunnable(entry) {
+    const executable = entryExecutable(entry);
+    if (!executable) {
+      return 'no Exec line';
+    }
+    if (!await getToolResolver().resolve(executable)) {
+      return `${executable} not found`;
+    }
+    return null;
+  }
+
+  // image/* means every image type the handler lists
+  expandTypes(values, entry) {
+    const types = [];
+    for (const value of values) {
+      const wildcard = value.match(WILDCARD);
+      if (wildcard) {
+        const matching = entry.mimeTypes.filter(type => type.startsWith(`${wildcard[1]}/`));
+        if (matching.length === 0) {
+          throw new Error(`${entry.id} lists no ${value} types`);
+        }
+        types.push(...matching);
+      } else {
+        types.push(normalizeMimeType(value));
+      }
+    }
+    return Array.from(new Set(types));
+  }
+
+  async detectFileType(file) {
+    const resolved = path.resolve(file);
+    assertWithinRoots(resolved);
+    if (!await fs.pathExists(resolved)) {
+      throw new Error(`No such file: ${resolved}`);
+    }
+    const result = await this.commandExecutor.execute('xdg-mime', ['query', 'filetype', resolved]);
+    const type = result.stdout.trim().split(';')[0];
+    if (!result.success || !type) {
+      throw new Error(`Could not detect the type of ${resolved}: ${result.stderr.trim() || 'xdg-mime returned nothing'}`);
+    }
+    return normalizeMimeType(type);
+  }
+}
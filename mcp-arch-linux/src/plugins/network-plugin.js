@@ -0,0 +1,96 @@
+import { BasePlugin, pluginConfig } from './base-plugin.js';
+import { CommandExecutor } from '../system/command-executor.js';
+import { NetworkDiagnostics } from '../system/network-diagnostics.js';
+
+const HOST = /^[A-Za-z0-9][A-Za-z0-9.:-]*$/;
+
+export class NetworkPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('network', config, logger, security);
+    this.description = 'Connectivity diagnostics';
+    this.dependencies = ['ip', 'ping', 'iw', 'mtr'];
+    this.optionalDependencies = ['iw', 'mtr'];
+
+    this.commandExecutor = new CommandExecutor(
+      config.get?.('security') || config.security || {},
+      logger,
+      security
+    );
+    this.diagnostics = new NetworkDiagnostics(this.commandExecutor, pluginConfig(config, 'network'));
+
+    this.initializeTools();
+  }
+
+  initializeTools() {
+    this.tools = [
+      this.createTool(
+        'diagnose_network',
+        'Check link, Wi-Fi quality, drivers, routing, gateway and internet reachability, DNS and captive portals, and report suspected causes',
+        {
+          type: 'object',
+          properties: {
+            targets: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Hosts to ping (default: plugins.network.targets)'
+            },
+            dnsNames: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Names to resolve (default: plugins.network.dnsNames)'
+            },
+            trace: {
+              type: 'boolean',
+              description: 'Run mtr to the first target for per-hop loss',
+              default: false
+            }
+          }
+        }
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'diagnose_network':
+          return this.handleDiagnose(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  isIdempotent(toolName) {
+    return toolName === 'diagnose_network';
+  }
+
+  isReadOnly(toolName) {
+    return ['diagnose_network'].includes(toolName);
+  }
+
+  async handleDiagnose(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const { targets, dnsNames, trace = false } = args;
+    for (const host of [...(targets || []), ...(dnsNames || [])]) {
+      if (!HOST.test(host)) {
+        throw new Error(`Invalid host: ${host}`);
+      }
+    }
+    if (trace && !this.hasDependency('mtr')) {
+      throw new Error('mtr is not installed - install with: sudo pacman -S mtr');
+    }
+
+    const report = await this.diagnostics.diagnose({
+      ...(targets ? { targets } : {}),
+      ...(dnsNames ? { dnsNames } : {}),
+      trace
+    });
+
+    return this.createTextResult(JSON.stringify(report, null, 2), {
+      healthy: report.healthy,
+      suspectedCauses: report.suspectedCauses
+    });
+  }
+}
@@ -0,0 +1,369 @@
+import { BasePlugin, pluginConfig } from './base-plugin.js';
+import { CommandExecutor } from '../system/command-executor.js';
+import { lookupUser, currentUser } from '../system/user-account.js';
+import { readLines, writeUserFile } from '../system/user-files.js';
+import fs from 'fs-extra';
+import path from 'path';
+
+const UNIT_NAME = 'mcp-night-light.service';
+const SETTINGS_MARKER = '# mcp-arch-linux night-light:';
+const TIME = '^([01]\\d|2[0-3]):[0-5]\\d$';
+
+// Colour temperature via gammastep or wlsunset, run as a systemd user service so
+// the setting survives logout and reboot
+export class NightLightPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('night-light', config, logger, security);
+    this.description = 'Display colour temperature, gamma and night-light schedule';
+    const settings = pluginConfig(config, 'nightLight');
+    this.defaultUser = settings.user || null;
+    this.defaultBackend = settings.backend || 'auto';
+    this.dependencies = ['systemctl', 'gammastep', 'wlsunset'];
+    this.optionalDependencies = ['gammastep', 'wlsunset'];
+
+    this.commandExecutor = new CommandExecutor(
+      config.get?.('security') || config.security || {},
+      logger,
+      security
+    );
+
+    this.initializeTools();
+    this.initializeResources();
+  }
+
+  initializeTools() {
+    const user = {
+      type: 'string',
+      description: 'User whose graphical session is adjusted'
+    };
+
+    this.tools = [
+      this.createTool(
+        'night_light_set',
+        'Set display colour temperature and gamma, fixed or scheduled by location or time of day',
+        {
+          type: 'object',
+          properties: {
+            user,
+            schedule: {
+              type: 'string',
+              enum: ['always', 'location', 'times'],
+              description: 'Apply the night temperature permanently, from sunset to sunrise at a location, or between fixed times',
+              default: 'location'
+            },
+            temperature: {
+              type: 'number',
+              description: 'Night (or fixed) colour temperature in Kelvin',
+              default: 4000
+            },
+            dayTemperature: {
+              type: 'number',
+              description: 'Daytime colour temperature in Kelvin',
+              default: 6500
+            },
+            gamma: {
+              type: 'number',
+              description: 'Gamma correction (1.0 is neutral)'
+            },
+            latitude: {
+              type: 'number',
+              description: 'Latitude for the location schedule'
+            },
+            longitude: {
+              type: 'number',
+              description: 'Longitude for the location schedule'
+            },
+            sunrise: {
+              type: 'string',
+              pattern: TIME,
+              description: 'End of the night period for the times schedule (HH:MM)'
+            },
+            sunset: {
+              type: 'string',
+              pattern: TIME,
+              description: 'Start of the night period for the times schedule (HH:MM)'
+            },
+            backend: {
+              type: 'string',
+              enum: ['auto', 'gammastep', 'wlsunset'],
+              description: 'Gamma control program (auto prefers gammastep)'
+            }
+          },
+          required: ['user']
+        }
+      ),
+
+      this.createTool(
+        'night_light_off',
+        'Restore neutral colour temperature and stop the night-light service',
+        {
+          type: 'object',
+          properties: { user },
+          required: ['user']
+        }
+      )
+    ];
+  }
+
+  initializeResources() {
+    this.resources = [
+      this.createResource(
+        'night-light://status',
+        'Night Light Status',
+        'Persisted night-light settings and whether the service is running',
+        'application/json'
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'night_light_set':
+          return this.handleSet(args);
+        case 'night_light_off':
+          return this.handleOff(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  async readResource(uri) {
+    return this.withErrorHandling(async () => {
+      switch (uri) {
+        case 'night-light://status':
+          return this.getStatus();
+        default:
+          throw new Error(`Unknown resource: ${uri}`);
+      }
+    }, 'readResource');
+  }
+
+  isIdempotent(toolName) {
+    return ['night_light_set', 'night_light_off'].includes(toolName);
+  }
+
+  async getAffectedPaths(toolName, args = {}) {
+    if (!args.user) {
+      return [];
+    }
+
+    try {
+      return Object.values(this.paths(await lookupUser(args.user)));
+    } catch {
+      return [];
+    }
+  }
+
+  paths(account) {
+    const configHome = path.join(account.home, '.config');
+    return {
+      unit: path.join(configHome, 'systemd', 'user', UNIT_NAME),
+      gammastepConfig: path.join(configHome, 'gammastep', 'mcp-arch-linux.ini')
+    };
+  }
+
+  resolveBackend(requested) {
+    const backend = requested || this.defaultBackend;
+    if (backend !== 'auto') {
+      if (!this.hasDependency(backend)) {
+        throw new Error(`${backend} is not installed`);
+      }
+      return backend;
+    }
+
+    const available = ['gammastep', 'wlsunset'].find(command => this.hasDependency(command));
+    if (!available) {
+      throw new Error('Neither gammastep nor wlsunset is installed');
+    }
+    return available;
+  }
+
+  checkSettings(settings) {
+    const { schedule, temperature, dayTemperature, gamma, latitude, longitude, sunrise, sunset } = settings;
+
+    for (const [field, value] of [['temperature', temperature], ['dayTemperature', dayTemperature]]) {
+      if (value < 1000 || value > 25000) {
+        throw new Error(`${field} must be between 1000 and 25000 K`);
+      }
+    }
+    if (gamma !== undefined && (gamma < 0.1 || gamma > 10)) {
+      throw new Error('gamma must be between 0.1 and 10');
+    }
+
+    if (schedule === 'location') {
+      if (latitude === undefined || longitude === undefined) {
+        throw new Error('latitude and longitude are required for the location schedule');
+      }
+      if (Math.abs(latitude) > 90 || Math.abs(longitude) > 180) {
+        throw new Error('latitude/longitude out of range');
+      }
+    }
+    if (schedule === 'times' && (!sunrise || !sunset)) {
+      throw new Error('sunrise and sunset are required for the times schedule');
+    }
+  }
+
+  // Returns ExecStart plus any config file the backend needs
+  buildCommand(backend, settings) {
+    const { schedule, temperature, dayTemperature, gamma, latitude, longitude, sunrise, sunset } = settings;
+    const binary = this.resolvedDependencies[backend];
+
+    if (backend === 'wlsunset') {
+      if (schedule === 'always') {
+        throw new Error('wlsunset cannot hold a fixed temperature; use backend gammastep');
+      }
+      if (dayTemperature <= temperature) {
+        throw new Error('wlsunset needs dayTemperature above temperature');
+      }
+
+      const args = ['-t', temperature, '-T', dayTemperature];
+      if (gamma !== undefined) args.push('-g', gamma);
+      if (schedule === 'location') {
+        args.push('-l', latitude, '-L', longitude);
+      } else {
+        args.push('-S', sunrise, '-s', sunset);
+      }
+      return { execStart: [binary, ...args].join(' ') };
+    }
+
+    if (schedule === 'always') {
+      const args = ['-m', 'wayland', '-P', '-O', temperature];
+      if (gamma !== undefined) args.push('-g', gamma);
+      return { execStart: [binary, ...args].join(' ') };
+    }
+
+    // Dawn/dusk times are only configurable through gammastep's config file
+    const general = [
+      `temp-day=${dayTemperature}`,
+      `temp-night=${temperature}`,
+      'adjustment-method=wayland',
+      'fade=1'
+    ];
+    if (gamma !== undefined) general.push(`gamma=${gamma}`);
+
+    const sections = ['[general]', ...general];
+    if (schedule === 'location') {
+      sections.splice(1, 0, 'location-provider=manual');
+      sections.push('', '[manual]', `lat=${latitude}`, `lon=${longitude}`);
+    } else {
+      sections.push(`dawn-time=${sunrise}`, `dusk-time=${sunset}`);
+    }
+
+    return { execStart: `${binary} -c %h/.config/gammastep/mcp-arch-linux.ini`, config: [...sections, ''].join('\n') };
+  }
+
+  renderUnit(execStart, settings) {
+    return [
+      `${SETTINGS_MARKER} ${JSON.stringify(settings)}`,
+      '[Unit]',
+      'Description=Night light (managed by mcp-arch-linux)',
+      'PartOf=graphical-session.target',
+      'After=graphical-session.target',
+      '',
+      '[Service]',
+      `ExecStart=${execStart}`,
+      'Restart=on-failure',
+      'RestartSec=5',
+      '',
+      '[Install]',
+      'WantedBy=graphical-session.target',
+      ''
+    ].join('\n');
+  }
+
+  // Root reaches the user's manager through --machine=<user>@
+  async systemctlUser(account, args) {
+    const scope = process.getuid && process.getuid() === account.uid
+      ? ['--user']
+      : ['--user', `--machine=${account.name}@`];
+    return this.commandExecutor.execute('systemctl', [...scope, ...args]);
+  }
+
+  async handleSet(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const settings = {
+      schedule: args.schedule || 'location',
+      temperature: args.temperature ?? 4000,
+      dayTemperature: args.dayTemperature ?? 6500,
+      gamma: args.gamma,
+      latitude: args.latitude,
+      longitude: args.longitude,
+      sunrise: args.sunrise,
+      sunset: args.sunset
+    };
+    this.checkSettings(settings);
+
+    const backend = this.resolveBackend(args.backend);
+    const account = await lookupUser(args.user);
+    const paths = this.paths(account);
+    const { execStart, config } = this.buildCommand(backend, settings);
+
+    if (config) {
+      await writeUserFile(paths.gammastepConfig, config, account);
+    }
+    await writeUserFile(paths.unit, this.renderUnit(execStart, { backend, ...settings }), account);
+
+    await this.systemctlUser(account, ['daemon-reload']);
+    const result = await this.systemctlUser(account, ['enable', UNIT_NAME]);
+    if (!result.success) {
+      throw new Error(`Enabling ${UNIT_NAME} failed: ${result.stderr}`);
+    }
+
+    // Without a running session the unit starts at next login
+    const restart = await this.systemctlUser(account, ['restart', UNIT_NAME]);
+
+    this.logger.audit('night_light_set', { user: account.name, backend, schedule: settings.schedule });
+    return this.createTextResult(JSON.stringify({
+      user: account.name,
+      backend,
+      ...settings,
+      running: restart.success,
+      ...(restart.success ? {} : { note: 'No graphical session is running; the setting applies at next login' })
+    }, null, 2));
+  }
+
+  async handleOff(args) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+
+    const account = await lookupUser(args.user);
+    const paths = this.paths(account);
+
+    // Both backends restore neutral gamma when they exit
+    await this.systemctlUser(account, ['disable', '--now', UNIT_NAME]);
+    await fs.remove(paths.unit);
+    await fs.remove(paths.gammastepConfig);
+    await this.systemctlUser(account, ['daemon-reload']);
+
+    this.logger.audit('night_light_off', { user: account.name });
+    return this.createTextResult(`Night light disabled for ${account.name}`);
+  }
+
+  async getStatus() {
+    const account = this.defaultUser ? await lookupUser(this.defaultUser) : await currentUser();
+    const paths = this.paths(account);
+
+    const marker = (await readLines(paths.unit)).find(line => line.startsWith(SETTINGS_MARKER));
+    let settings = null;
+    if (marker) {
+      try {
+        settings = JSON.parse(marker.slice(SETTINGS_MARKER.length));
+      } catch {
+        settings = null;
+      }
+    }
+
+    const active = await this.systemctlUser(account, ['is-active', UNIT_NAME]).catch(() => null);
+    return {
+      content: JSON.stringify({
+        user: account.name,
+        configured: Boolean(marker),
+        active: active?.stdout?.trim() === 'active',
+        settings,
+        available: ['gammastep', 'wlsunset'].filter(command => this.hasDependency(command))
+      }, null, 2)
+    };
+  }
+}
@@ -0,0 +1,423 @@
+import { BasePlugin, pluginConfig } from './base-plugin.js';
+import { CommandExecutor } from '../system/command-executor.js';
+import { LockKeys } from '../security/lock-manager.js';
+import { getTargetRegistry, MACHINES_DIR } from '../system/target.js';
+import { reportProgress } from '../core/request-context.js';
+import fs from 'fs-extra';
+import path from 'path';
+
+const NAME_PATTERN = '^[a-zA-Z0-9][a-zA-Z0-9_.-]{0,63}$';
+
+// Arch containers under /var/lib/machines: a sandbox for experiments that should not
+// touch the host. Each container is also a system tool target of the same name.
+export class NspawnPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('nspawn', config, logger, security);
+    this.description = 'systemd-nspawn Arch containers';
+    const settings = pluginConfig(config, 'nspawn');
+    this.defaultPackages = settings.defaultPackages || ['base'];
+    this.startTimeout = settings.startTimeout || 30000;
+    this.registerTargets = settings.registerTargets ?? true;
+
+    this.commandExecutor = new CommandExecutor(
+      config.get?.('security') || config.security || {},
+      logger,
+      security
+    );
+    this.dependencies = ['machinectl', 'systemd-nspawn', 'systemd-run', 'pacstrap'];
+    this.optionalDependencies = ['pacstrap'];
+
+    this.initializeTools();
+    this.initializeResources();
+  }
+
+  async initialize() {
+    await super.initialize();
+
+    if (this.registerTargets) {
+      try {
+        for (const image of await this.listImages()) {
+          this.registerTarget(image.name);
+        }
+      } catch (error) {
+        this.logger.debug(`No containers registered as targets: ${error.message}`);
+      }
+    }
+  }
+
+  initializeTools() {
+    this.tools = [
+      this.createTool(
+        'container_create',
+        'Create an Arch container by pacstrapping into /var/lib/machines/<name>',
+        {
+          type: 'object',
+          properties: {
+            name: {
+              type: 'string',
+              description: 'Machine name',
+              pattern: NAME_PATTERN
+            },
+            packages: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Packages to install (default: plugins.nspawn.defaultPackages)'
+            },
+            start: {
+              type: 'boolean',
+              description: 'Start the container once created',
+              default: false
+            },
+            dryRun: {
+              type: 'boolean',
+              description: 'Show what would be created',
+              default: false
+            }
+          },
+          required: ['name']
+        },
+        { timeout: 2700000 }
+      ),
+
+      this.createTool(
+        'container_start',
+        'Boot a container with machinectl and wait until it is running',
+        {
+          type: 'object',
+          properties: {
+            name: { type: 'string', pattern: NAME_PATTERN }
+          },
+          required: ['name']
+        }
+      ),
+
+      this.createTool(
+        'container_stop',
+        'Shut a container down cleanly, or terminate it',
+        {
+          type: 'object',
+          properties: {
+            name: { type: 'string', pattern: NAME_PATTERN },
+            force: {
+              type: 'boolean',
+              description: 'Kill the container instead of powering it off',
+              default: false
+            }
+          },
+          required: ['name']
+        }
+      ),
+
+      this.createTool(
+        'container_exec',
+        'Run a command inside a running container',
+        {
+          type: 'object',
+          properties: {
+            name: { type: 'string', pattern: NAME_PATTERN },
+            command: {
+              type: 'string',
+              description: 'Command to run (must be in allowedCommands)'
+            },
+            args: {
+              type: 'array',
+              items: { type: 'string' },
+              default: []
+            },
+            timeout: {
+              type: 'number',
+              description: 'Timeout in milliseconds',
+              default: 300000
+            }
+          },
+          required: ['name', 'command']
+        }
+      ),
+
+      this.createTool(
+        'container_list',
+        'List container images and their state',
+        {
+          type: 'object',
+          properties: {}
+        }
+      ),
+
+      this.createTool(
+        'container_remove',
+        'Delete a stopped container and its directory',
+        {
+          type: 'object',
+          properties: {
+            name: { type: 'string', pattern: NAME_PATTERN }
+          },
+          required: ['name']
+        }
+      )
+    ];
+  }
+
+  initializeResources() {
+    this.resources = [
+      this.createResource(
+        'nspawn://containers',
+        'Containers',
+        'Container images, whether they are running, and their target names',
+        'application/json'
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'container_create':
+          return this.handleCreate(args);
+        case 'container_start':
+          return this.handleStart(args);
+        case 'container_stop':
+          return this.handleStop(args);
+        case 'container_exec':
+          return this.handleExec(args);
+        case 'container_list':
+          return this.handleList(args);
+        case 'container_remove':
+          return this.handleRemove(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  async readResource(uri) {
+    return this.withErrorHandling(async () => {
+      switch (uri) {
+        case 'nspawn://containers':
+          return { content: JSON.stringify(await this.listContainers(), null, 2) };
+        default:
+          throw new Error(`Unknown resource: ${uri}`);
+      }
+    }, 'readResource');
+  }
+
+  getToolLocks(toolName, args = {}) {
+    if (!args.name || ['container_list', 'container_exec'].includes(toolName)) {
+      return [];
+    }
+    const dir = this.machineDir(args.name);
+    return toolName === 'container_create' ? [LockKeys.mount(dir), LockKeys.pacmanDb(dir)] : [LockKeys.mount(dir)];
+  }
+
+  isIdempotent(toolName) {
+    return toolName === 'container_list';
+  }
+
+  isReadOnly(toolName, args = {}) {
+    return ['container_list'].includes(toolName) || super.isReadOnly(toolName, args);
+  }
+
+  isDestructive(toolName, args = {}) {
+    return toolName === 'container_remove' && !args.dryRun;
+  }
+
+  inhibitsIdle(toolName, args = {}) {
+    return toolName === 'container_create' && !args.dryRun;
+  }
+
+  async handleCreate(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const { name, packages = this.defaultPackages, start = false, dryRun = false } = args;
+    const dir = this.machineDir(name);
+
+    if (await fs.pathExists(dir)) {
+      throw new Error(`Container ${name} already exists at ${dir}`);
+    }
+
+    if (dryRun) {
+      return this.createTextResult([
+        `Create container ${name}:`,
+        `1. pacstrap -c ${dir} ${packages.join(' ')}`,
+        '2. Enable systemd-networkd and systemd-resolved for the veth link',
+        `3. Register target "${name}" (container:${name})`,
+        start ? `4. machinectl start ${name}` : null
+      ].filter(Boolean).join('\n'));
+    }
+
+    await this.commandExecutor.executeWithSudo('mkdir', ['-p', dir]);
+    reportProgress(0, { total: 2, message: `pacstrap ${packages.join(' ')}` });
+
+    // -c uses the host's package cache, so repeated containers do not re-download
+    const result = await this.commandExecutor.executeWithSudo('pacstrap', ['-c', dir, ...packages], {
+      timeout: 1800000
+    });
+    if (!result.success) {
+      await this.commandExecutor.executeWithSudo('machinectl', ['remove', name], { retry: false }).catch(() => {});
+      throw new Error(`pacstrap failed for ${name}: ${result.stderr.split('\n').slice(-5).join('\n')}`);
+    }
+
+    // systemd-nspawn@.service gives the container a veth link, which networkd configures
+    await this.commandExecutor.executeWithSudo('systemctl', ['--root', dir, 'enable', 'systemd-networkd', 'systemd-resolved']);
+    reportProgress(1, { total: 2, message: 'configured' });
+
+    this.registerTarget(name);
+    this.logger.audit('container_create', { name, packages });
+
+    if (start) {
+      await this.startContainer(name);
+    }
+
+    return this.createTextResult(`Created container ${name} at ${dir}${start ? ' and started it' : ''}; use target "${name}" with system tools`, {
+      name,
+      path: dir,
+      target: name,
+      running: start
+    });
+  }
+
+  async handleStart(args) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+
+    await this.requireContainer(args.name);
+    await this.startContainer(args.name);
+    return this.createTextResult(`Container ${args.name} is running`);
+  }
+
+  async handleStop(args) {
+    await this.validateArgs(args, this.tools[2].inputSchema);
+
+    const { name, force = false } = args;
+    if (!await this.isRunning(name)) {
+      return this.createTextResult(`Container ${name} is not running`);
+    }
+
+    const result = await this.commandExecutor.executeWithSudo('machinectl', [force ? 'terminate' : 'poweroff', name]);
+    if (!result.success) {
+      throw new Error(`Failed to stop ${name}: ${result.stderr}`);
+    }
+    await this.waitFor(name, false);
+    return this.createTextResult(`Container ${name} ${force ? 'terminated' : 'stopped'}`);
+  }
+
+  async handleExec(args) {
+    await this.validateArgs(args, this.tools[3].inputSchema);
+
+    const { name, command, args: commandArgs = [], timeout = 300000 } = args;
+    if (!await this.isRunning(name)) {
+      throw new Error(`Container ${name} is not running; start it with container_start`);
+    }
+
+    const result = await this.commandExecutor.execute(command, commandArgs, {
+      timeout,
+      target: getTargetRegistry().resolve(`container:${name}`)
+    });
+
+    return this.createTextResult(result.success ? result.stdout : result.stderr, {
+      exitCode: result.exitCode,
+      success: result.success,
+      duration: result.duration
+    });
+  }
+
+  async handleList() {
+    return this.createTextResult(JSON.stringify(await this.listContainers(), null, 2));
+  }
+
+  async handleRemove(args) {
+    await this.validateArgs(args, this.tools[5].inputSchema);
+
+    const { name } = args;
+    await this.requireContainer(name);
+    if (await this.isRunning(name)) {
+      throw new Error(`Container ${name} is running; stop it first`);
+    }
+
+    const result = await this.commandExecutor.executeWithSudo('machinectl', ['remove', name], { retry: false });
+    if (!result.success) {
+      throw new Error(`Failed to remove ${name}: ${result.stderr}`);
+    }
+
+    const registry = getTargetRegistry();
+    if (registry.named.get(name)?.machine === name) {
+      registry.remove(name);
+    }
+    this.logger.audit('container_remove', { name });
+    return this.createTextResult(`Removed container ${name}`);
+  }
+
+  machineDir(name) {
+    return path.join(MACHINES_DIR, name);
+  }
+
+  // Names that clash with a built-in or differently defined target stay reachable as container:<name>
+  registerTarget(name) {
+    const registry = getTargetRegistry();
+    const existing = registry.named.get(name);
+    if (!this.registerTargets || name === 'install' || (existing && existing.machine !== name)) {
+      return;
+    }
+    try {
+      registry.define(name, `container:${name}`);
+    } catch (error) {
+      this.logger.debug(`Container ${name} not registered as a target: ${error.message}`);
+    }
+  }
+
+  async requireContainer(name) {
+    if (!await fs.pathExists(this.machineDir(name))) {
+      throw new Error(`No container named ${name} in ${MACHINES_DIR}`);
+    }
+  }
+
+  async startContainer(name) {
+    if (await this.isRunning(name)) {
+      return;
+    }
+    const result = await this.commandExecutor.executeWithSudo('machinectl', ['start', name]);
+    if (!result.success) {
+      throw new Error(`Failed to start ${name}: ${result.stderr}`);
+    }
+    await this.waitFor(name, true);
+  }
+
+  async isRunning(name) {
+    const result = await this.commandExecutor.execute('machinectl', ['show', name, '--property=State', '--value']);
+    return result.success && result.stdout.trim() === 'running';
+  }
+
+  // machinectl start returns once the unit is queued; commands need systemd inside to be up
+  async waitFor(name, running) {
+    const deadline = Date.now() + this.startTimeout;
+    while (Date.now() < deadline) {
+      if (await this.isRunning(name) === running) {
+        return;
+      }
+      await new Promise(resolve => setTimeout(resolve, 500));
+    }
+    throw new Error(`Container ${name} did not ${running ? 'start' : 'stop'} within ${this.startTimeout / 1000}s`);
+  }
+
+  async listImages() {
+    const result = await this.commandExecutor.execute('machinectl', ['list-images', '--output=json']);
+    if (!result.success) {
+      throw new Error(result.stderr || 'machinectl list-images failed');
+    }
+    return JSON.parse(result.stdout || '[]');
+  }
+
+  async listContainers() {
+    const running = await this.commandExecutor.execute('machinectl', ['list', '--output=json']);
+    const machines = running.success ? JSON.parse(running.stdout || '[]') : [];
+    const registry = getTargetRegistry();
+
+    return (await this.listImages()).map(image => ({
+      name: image.name,
+      type: image.type,
+      path: this.machineDir(image.name),
+      running: machines.some(machine => machine.machine === image.name),
+      target: registry.named.get(image.name)?.machine === image.name ? image.name : `container:${image.name}`,
+      usage: image.usage ?? null,
+      modified: image.mtime ?? null
+    }));
+  }
+}
@@ -0,0 +1,152 @@
+import { BasePlugin } from './base-plugin.js';
+
+// Sleep and idle control; inhibitors are shared with tools that take them automatically
+export class PowerPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('power', config, logger, security);
+    this.description = 'Idle and sleep inhibitors';
+    this.dependencies = ['systemd-inhibit'];
+    this.timers = new Map();
+
+    this.initializeTools();
+    this.initializeResources();
+  }
+
+  initializeTools() {
+    this.tools = [
+      this.createTool(
+        'inhibit_idle',
+        'Keep the machine from idling, locking or sleeping until released',
+        {
+          type: 'object',
+          properties: {
+            reason: {
+              type: 'string',
+              description: 'Shown by systemd-inhibit --list'
+            },
+            what: {
+              type: 'string',
+              enum: ['idle', 'sleep', 'idle:sleep', 'idle:sleep:shutdown', 'handle-lid-switch'],
+              description: 'Operations to block',
+              default: 'idle:sleep'
+            },
+            duration: {
+              type: 'number',
+              description: 'Release automatically after this many seconds'
+            }
+          },
+          required: ['reason']
+        }
+      ),
+
+      this.createTool(
+        'release_idle',
+        'Release an inhibitor taken with inhibit_idle',
+        {
+          type: 'object',
+          properties: {
+            id: {
+              type: 'string',
+              description: 'Inhibitor id returned by inhibit_idle'
+            }
+          },
+          required: ['id']
+        }
+      )
+    ];
+  }
+
+  initializeResources() {
+    this.resources = [
+      this.createResource(
+        'power://inhibitors',
+        'Active Inhibitors',
+        'Inhibitors held by this server (including automatic ones) and all logind inhibitors',
+        'application/json'
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'inhibit_idle':
+          return this.handleInhibit(args);
+        case 'release_idle':
+          return this.handleRelease(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  async readResource(uri) {
+    return this.withErrorHandling(async () => {
+      switch (uri) {
+        case 'power://inhibitors':
+          return this.getInhibitors();
+        default:
+          throw new Error(`Unknown resource: ${uri}`);
+      }
+    }, 'readResource');
+  }
+
+  async handleInhibit(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const { reason, what = 'idle:sleep', duration } = args;
+    if (/[\r\n]/.test(reason)) {
+      throw new Error('reason must be a single line');
+    }
+    if (duration !== undefined && duration <= 0) {
+      throw new Error('duration must be positive');
+    }
+
+    const id = await this.security.idle.acquire(reason, { what });
+    if (duration) {
+      const timer = setTimeout(() => {
+        this.timers.delete(id);
+        this.security.idle.release(id);
+      }, duration * 1000);
+      timer.unref();
+      this.timers.set(id, timer);
+    }
+
+    this.logger.audit('inhibit_idle', { id, reason, what, duration });
+    return this.createTextResult(JSON.stringify({
+      id,
+      what,
+      ...(duration ? { expiresAt: new Date(Date.now() + duration * 1000).toISOString() } : {})
+    }, null, 2), { id });
+  }
+
+  async handleRelease(args) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+
+    clearTimeout(this.timers.get(args.id));
+    this.timers.delete(args.id);
+
+    if (!this.security.idle.release(args.id)) {
+      throw new Error(`No active inhibitor with id ${args.id}`);
+    }
+    return this.createTextResult(`Released inhibitor ${args.id}`);
+  }
+
+  async getInhibitors() {
+    const system = await this.security.idle.listSystem().catch(error => ({ error: error.message }));
+    return {
+      content: JSON.stringify({
+        server: this.security.idle.list(),
+        system
+      }, null, 2)
+    };
+  }
+
+  async cleanup() {
+    for (const timer of this.timers.values()) {
+      clearTimeout(timer);
+    }
+    this.timers.clear();
+    await super.cleanup();
+  }
+}
@@ -0,0 +1,68 @@
+import { BasePlugin } from './base-plugin.js';
+import { HelperClient } from '../core/helper-channel.js';
+
+// Exposes the privileged helper's tools and resources as if they were local
+export class RemotePlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('remote', config, logger, security);
+    this.description = 'Tools delegated to the privileged helper instance';
+
+    const split = config.get?.('split') || {};
+    this.client = new HelperClient({
+      socketPath: split.socketPath,
+      tokenFile: split.tokenFile,
+      timeout: config.get?.('security.commandTimeout')
+    });
+    this.connected = false;
+    this.lastError = null;
+  }
+
+  async initialize() {
+    await super.initialize();
+
+    try {
+      await this.client.connect();
+      this.tools = (await this.client.request('tools/list')).tools;
+      this.resources = (await this.client.request('resources/list')).resources;
+      this.resourceTemplates = (await this.client.request('resources/templates/list')).resourceTemplates;
+      this.connected = true;
+      this.logger.info(`Connected to privileged helper: ${this.tools.length} tools, ${this.resources.length} resources`);
+    } catch (error) {
+      this.lastError = error.message;
+      this.logger.warn(`Privileged helper unavailable, delegated tools disabled: ${error.message}`);
+    }
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      return this.client.request('tools/call', { name: toolName, arguments: args });
+    }, toolName);
+  }
+
+  async readResource(uri) {
+    return this.withErrorHandling(async () => {
+      return this.client.request('resources/read', { uri });
+    }, 'readResource');
+  }
+
+  async readResourceTemplate(uriTemplate, params, uri) {
+    return this.readResource(uri);
+  }
+
+  async getHealthChecks() {
+    let status = 'pass';
+    try {
+      await this.client.request('tools/list');
+    } catch (error) {
+      status = 'fail';
+      this.lastError = error.message;
+    }
+
+    return [{
+      name: 'helper-connection',
+      status,
+      socketPath: this.client.socketPath,
+      ...(status === 'fail' ? { detail: this.lastError } : {})
+    }];
+  }
+}
@@ -0,0 +1,59 @@
+import { BasePlugin } from './base-plugin.js';
+
+// Serves tool results that were too large for a single response (see ResultSpill)
+export class ResultsPlugin extends BasePlugin {
+  constructor(config, logger, security, spill) {
+    super('results', config, logger, security);
+    this.description = 'Full output of truncated tool results';
+    this.spill = spill;
+
+    this.initializeResources();
+  }
+
+  initializeResources() {
+    this.resources = [
+      this.createResource(
+        'results://list',
+        'Spilled Results',
+        'Oversized tool results still available, with their size and expiry',
+        'application/json'
+      )
+    ];
+
+    this.resourceTemplates = [
+      this.createResourceTemplate(
+        'results://{id}',
+        'Spilled Result',
+        'Full text or image of a tool result that exceeded server.limits.maxResponseSize',
+        'text/plain'
+      )
+    ];
+  }
+
+  async readResource(uri) {
+    return this.withErrorHandling(async () => {
+      switch (uri) {
+        case 'results://list':
+          return { content: JSON.stringify(this.spill.list(), null, 2) };
+        default:
+          throw new Error(`Unknown resource: ${uri}`);
+      }
+    }, 'readResource');
+  }
+
+  async readResourceTemplate(uriTemplate, params) {
+    return this.withErrorHandling(async () => {
+      switch (uriTemplate) {
+        case 'results://{id}':
+          return this.spill.read(params.id);
+        default:
+          throw new Error(`Unknown resource template: ${uriTemplate}`);
+      }
+    }, 'readResourceTemplate');
+  }
+
+  async cleanup() {
+    await this.spill.cleanup();
+    await super.cleanup();
+  }
+}
@@ -1,24 +1,101 @@
-import { BasePlugin } from './base-plugin.js';
+import { BasePlugin, pluginConfig } from './base-plugin.js';
 import { CommandExecutor } from '../system/command-executor.js';
+import { getEnvironmentInfo } from '../system/environment-info.js';
+import { DiskQuota, getQuotaRegistry, parseSize } from '../system/disk-quota.js';
+import { detectCompositor } from '../system/compositor.js';
+import { processImage, stitchImages, readPpmPixel, describeColor } from '../system/image.js';
+import { encodeBase64, encodeFileBase64 } from '../system/base64.js';
+import { AudioSources } from '../system/audio-sources.js';
+import { parseZbarXml, parseWifiPayload } from '../system/barcodes.js';
+import { assertWithinRoots, isWithinRoots } from '../security/roots.js';
+import { reportProgress } from '../core/request-context.js';
 import fs from 'fs-extra';
+import os from 'os';
 import path from 'path';
 
+// Post-processing shared by the screenshot tools; applied to the returned image only
+const PROCESSING_PROPERTIES = {
+  maxWidth: {
+    type: 'integer',
+    minimum: 16,
+    description: 'Scale the returned image down to at most this width (keeps aspect ratio)'
+  },
+  crop: {
+    type: 'object',
+    properties: {
+      x: { type: 'integer', minimum: 0 },
+      y: { type: 'integer', minimum: 0 },
+      width: { type: 'integer', minimum: 1 },
+      height: { type: 'integer', minimum: 1 }
+    },
+    required: ['x', 'y', 'width', 'height'],
+    description: 'Crop the returned image to this rectangle, in image pixels'
+  }
+};
+
+// slurp's output format, which grim -g and wf-recorder -g take as well
+const GEOMETRY_PATTERN = /^-?\d+,-?\d+ \d+x\d+$/;
+
+// Thumbnails are re-captured once older than this, or when the window moved or resized
+const THUMBNAIL_MAX_AGE = 5000;
+const THUMBNAIL_WIDTH = 320;
+const SERIES_LABEL = /^[A-Za-z0-9_-]+$/;
+const MAX_SERIES_FRAMES = 1000;
+const MAX_CAPTURE_DELAY = 60;
+
+const QUALITY_PROPERTY = {
+  type: 'integer',
+  minimum: 1,
+  maximum: 100,
+  description: 'Image quality for lossy formats',
+  default: 90
+};
+
 export class ScreenCapturePlugin extends BasePlugin {
   constructor(config, logger, security) {
     super('screen-capture', config, logger, security);
     this.description = 'Screen capture and recording functionality';
-    this.captureDir = config.plugins?.screenCapture?.captureDir || '/var/lib/mcp-arch-linux/captures';
-    this.maxFileSize = config.plugins?.screenCapture?.maxFileSize || '50MB';
-    this.allowRecording = config.plugins?.screenCapture?.allowRecording || true;
+    const settings = pluginConfig(config, 'screenCapture');
+    this.captureDir = settings.captureDir || '/var/lib/mcp-arch-linux/captures';
+    this.maxFileSize = settings.maxFileSize || '50MB';
+    this.allowRecording = settings.allowRecording ?? true;
+    this.maxRecordingDuration = settings.maxRecordingDuration || 3600;
+    this.recordingTimer = null;
+    this.listeners = new Set();
+    // Interval captures running in this process, by label
+    this.series = new Map();
+    // Window id -> { data, geometry, capturedAt }
+    this.thumbnails = new Map();
     
     this.commandExecutor = new CommandExecutor(
-      config.security || {},
+      config.get?.('security') || config.security || {},
       logger,
       security
     );
+    this.environment = getEnvironmentInfo(logger);
+    this.audioSources = new AudioSources(this.commandExecutor);
+    this.backend = 'grim';
+    this.dependencies = ['grim', 'maim', 'slurp', 'wf-recorder', 'pactl', 'hyprpicker', 'zbarimg'];
+    // Which of these is needed depends on the session type
+    this.optionalDependencies = ['grim', 'maim', 'slurp', 'wf-recorder', 'pactl', 'hyprpicker', 'zbarimg'];
+
+    const storage = config.get?.('storage') || {};
+    this.quota = getQuotaRegistry().register(new DiskQuota(
+      'captures',
+      this.captureDir,
+      {
+        maxSize: storage.quotas?.captures,
+        minFreePercent: storage.minFreePercent,
+        warnPercent: storage.warnPercent
+      },
+      logger
+    ));
     
     this.availableTools = {
+      hyprpicker: false,
+      zbarimg: false,
       grim: false,
+      maim: false,
       wfRecorder: false,
       slurp: false
     };
@@ -34,12 +111,25 @@ export class ScreenCapturePlugin extends BasePlugin {
     await fs.ensureDir(this.captureDir);
     
     // Check available tools
-    this.availableTools.grim = await this.commandExecutor.checkCommandExists('grim');
-    this.availableTools.wfRecorder = await this.commandExecutor.checkCommandExists('wf-recorder');
-    this.availableTools.slurp = await this.commandExecutor.checkCommandExists('slurp');
+    this.availableTools.grim = this.hasDependency('grim');
+    this.availableTools.wfRecorder = this.hasDependency('wf-recorder');
+    this.availableTools.slurp = this.hasDependency('slurp');
+    this.availableTools.maim = this.hasDependency('maim');
+    this.availableTools.hyprpicker = this.hasDependency('hyprpicker');
+    this.availableTools.zbarimg = this.hasDependency('zbarimg');
+    
+    // Pick the screenshot backend matching the session type
+    this.backend = await this.environment.preferredScreenshotBackend();
+
+    // A recording started before a restart still gets stopped on time
+    const recordingInfoPath = path.join(this.captureDir, '.recording.json');
+    if (await fs.pathExists(recordingInfoPath)) {
+      this.scheduleRecordingStop(await fs.readJson(recordingInfoPath).catch(() => ({})));
+    }
     
     this.logger.info('Screen capture plugin initialized', {
       captureDir: this.captureDir,
+      backend: this.backend,
       availableTools: this.availableTools
     });
   }
@@ -82,9 +172,16 @@ export class ScreenCapturePlugin extends BasePlugin {
             filename: {
               type: 'string',
               description: 'Custom filename (without extension)'
-            }
+            },
+            save: {
+              type: 'boolean',
+              description: 'Keep the capture in the capture directory; false streams the image straight back without a file',
+              default: true
+            },
+            ...PROCESSING_PROPERTIES
           }
-        }
+        },
+        { timeout: 30000 }
       ),
 
       this.createTool(
@@ -95,7 +192,7 @@ export class ScreenCapturePlugin extends BasePlugin {
           properties: {
             selector: {
               type: 'string',
-              description: 'Window selector (class, title, or "active" for current window)',
+              description: 'Window selector (id, class, title substring, or "active" for current window)',
               default: 'active'
             },
             format: {
@@ -104,12 +201,15 @@ export class ScreenCapturePlugin extends BasePlugin {
               description: 'Image format',
               default: 'png'
             },
+            quality: QUALITY_PROPERTY,
             filename: {
               type: 'string',
               description: 'Custom filename (without extension)'
-            }
+            },
+            ...PROCESSING_PROPERTIES
           }
-        }
+        },
+        { timeout: 30000 }
       ),
 
       this.createTool(
@@ -124,10 +224,12 @@ export class ScreenCapturePlugin extends BasePlugin {
               description: 'Image format',
               default: 'png'
             },
+            quality: QUALITY_PROPERTY,
             filename: {
               type: 'string',
               description: 'Custom filename (without extension)'
-            }
+            },
+            ...PROCESSING_PROPERTIES
           }
         }
       ),
@@ -142,11 +244,29 @@ export class ScreenCapturePlugin extends BasePlugin {
               type: 'string',
               description: 'Output name to record'
             },
+            region: {
+              type: 'string',
+              pattern: GEOMETRY_PATTERN.source,
+              description: 'Region to record in slurp format, "X,Y WxH"'
+            },
+            window: {
+              type: 'string',
+              description: 'Record the area of this window (address, class, title substring or "active"); the area stays fixed if the window moves'
+            },
+            maxDuration: {
+              type: 'integer',
+              minimum: 1,
+              description: 'Stop the recording automatically after this many seconds (default and upper bound: plugins.screenCapture.maxRecordingDuration)'
+            },
             audio: {
               type: 'boolean',
-              description: 'Include audio in recording',
+              description: 'Include audio from the default source in recording',
               default: false
             },
+            audioSource: {
+              type: 'string',
+              description: 'Record audio from "desktop" (what plays on the default output), "microphone" (the default input), "mix" (both mixed) or a source name from list_audio_sources'
+            },
             format: {
               type: 'string',
               enum: ['mp4', 'webm', 'mkv'],
@@ -227,6 +347,192 @@ export class ScreenCapturePlugin extends BasePlugin {
           },
           required: ['filename']
         }
+      ),
+
+      this.createTool(
+        'capture_all_outputs',
+        'Capture every monitor concurrently, as one image per output or one composite following the monitor layout',
+        {
+          type: 'object',
+          properties: {
+            mode: {
+              type: 'string',
+              enum: ['stitched', 'separate'],
+              description: 'One composite image laid out like the monitors, or one image per output',
+              default: 'stitched'
+            },
+            format: {
+              type: 'string',
+              enum: ['png', 'jpg', 'webp'],
+              description: 'Image format',
+              default: 'png'
+            },
+            quality: QUALITY_PROPERTY,
+            save: {
+              type: 'boolean',
+              description: 'Keep the images in the capture directory',
+              default: true
+            },
+            ...PROCESSING_PROPERTIES
+          }
+        },
+        { timeout: 60000 }
+      ),
+
+      this.createTool(
+        'capture_after_delay',
+        'Take a screenshot after a countdown, e.g. to open a menu or tooltip first',
+        {
+          type: 'object',
+          properties: {
+            delay: {
+              type: 'integer',
+              minimum: 1,
+              maximum: MAX_CAPTURE_DELAY,
+              description: 'Seconds to wait before capturing'
+            },
+            output: {
+              type: 'string',
+              description: 'Output name (monitor) to capture, or "all" for all outputs'
+            },
+            region: {
+              type: 'object',
+              description: 'Specific region to capture (x,y,width,height)'
+            },
+            format: {
+              type: 'string',
+              enum: ['png', 'jpg', 'webp'],
+              description: 'Image format',
+              default: 'png'
+            },
+            quality: QUALITY_PROPERTY,
+            filename: {
+              type: 'string',
+              description: 'Custom filename (without extension)'
+            },
+            ...PROCESSING_PROPERTIES
+          },
+          required: ['delay']
+        },
+        { timeout: (MAX_CAPTURE_DELAY + 30) * 1000 }
+      ),
+
+      this.createTool(
+        'capture_interval',
+        'Take a screenshot every few seconds for a while into a labeled series, e.g. to catch flaky UI behavior; frames are read through capture://series/{label}',
+        {
+          type: 'object',
+          properties: {
+            label: {
+              type: 'string',
+              pattern: SERIES_LABEL.source,
+              description: 'Series name (letters, digits, "_", "-")'
+            },
+            interval: {
+              type: 'integer',
+              minimum: 1,
+              description: 'Seconds between screenshots'
+            },
+            duration: {
+              type: 'number',
+              exclusiveMinimum: 0,
+              description: `Minutes to keep capturing; at most ${MAX_SERIES_FRAMES} frames are taken`
+            },
+            output: {
+              type: 'string',
+              description: 'Output name (monitor) to capture'
+            },
+            region: {
+              type: 'object',
+              description: 'Specific region to capture (x,y,width,height)'
+            },
+            format: {
+              type: 'string',
+              enum: ['png', 'jpg'],
+              description: 'Image format',
+              default: 'png'
+            }
+          },
+          required: ['label', 'interval', 'duration']
+        }
+      ),
+
+      this.createTool(
+        'capture_interval_stop',
+        'Stop a running interval capture early; the frames taken so far are kept',
+        {
+          type: 'object',
+          properties: {
+            label: {
+              type: 'string',
+              description: 'Series name'
+            }
+          },
+          required: ['label']
+        }
+      ),
+
+      this.createTool(
+        'list_audio_sources',
+        'List audio sources a recording can use: output monitors (desktop sound) and microphones',
+        {
+          type: 'object',
+          properties: {}
+        }
+      ),
+
+      this.createTool(
+        'pick_color',
+        'Get the color of a screen pixel as hex, RGB and HSL: sampled at given coordinates, or picked by the user with hyprpicker',
+        {
+          type: 'object',
+          properties: {
+            mode: {
+              type: 'string',
+              enum: ['point', 'interactive'],
+              description: 'point samples x,y from a fresh capture; interactive lets the user click a pixel',
+              default: 'point'
+            },
+            x: {
+              type: 'integer',
+              description: 'Horizontal position in layout coordinates (point mode)'
+            },
+            y: {
+              type: 'integer',
+              description: 'Vertical position in layout coordinates (point mode)'
+            }
+          }
+        },
+        { timeout: 120000 }
+      ),
+
+      this.createTool(
+        'decode_qr',
+        'Find QR codes and barcodes on the screen (or in a saved capture) and return their payloads; Wi-Fi QR codes are also broken down into network settings',
+        {
+          type: 'object',
+          properties: {
+            filename: {
+              type: 'string',
+              description: 'Scan this file from the capture directory instead of taking a screenshot'
+            },
+            output: {
+              type: 'string',
+              description: 'Output name (monitor) to scan'
+            },
+            region: {
+              type: 'object',
+              properties: {
+                x: { type: 'integer' },
+                y: { type: 'integer' },
+                width: { type: 'integer' },
+                height: { type: 'integer' }
+              },
+              description: 'Only scan this region (x,y,width,height), e.g. a screen-shared window'
+            }
+          }
+        },
+        { timeout: 30000 }
       )
     ];
   }
@@ -250,6 +556,39 @@ export class ScreenCapturePlugin extends BasePlugin {
         'Capture Status',
         'Current capture system status',
         'application/json'
+      ),
+      this.createResource(
+        'capture://series',
+        'Capture Series',
+        'Interval capture series with their state and frame counts',
+        'application/json'
+      )
+    ];
+
+    this.resourceTemplates = [
+      this.createResourceTemplate(
+        'capture://file/{id}',
+        'Capture File',
+        'The capture itself as a base64 blob; {id} is a filename from capture://list',
+        'application/octet-stream'
+      ),
+      this.createResourceTemplate(
+        'capture://series/{label}',
+        'Capture Series Frames',
+        'One interval capture series: settings, state and each frame with its time and URI',
+        'application/json'
+      ),
+      this.createResourceTemplate(
+        'capture://series/{label}/{frame}',
+        'Capture Series Frame',
+        'One frame of a series as a base64 blob; {frame} is a file name from capture://series/{label}',
+        'application/octet-stream'
+      ),
+      this.createResourceTemplate(
+        'capture://window/{address}/thumbnail',
+        'Window Thumbnail',
+        `A ${THUMBNAIL_WIDTH}px wide JPEG preview of a window; {address} is a window id from compositor_windows or hyprland_windows`,
+        'image/jpeg'
       )
     ];
   }
@@ -273,6 +612,20 @@ export class ScreenCapturePlugin extends BasePlugin {
           return this.handleDeleteCapture(args);
         case 'get_capture':
           return this.handleGetCapture(args);
+        case 'capture_all_outputs':
+          return this.handleAllOutputs(args);
+        case 'capture_after_delay':
+          return this.handleCaptureAfterDelay(args);
+        case 'capture_interval':
+          return this.handleCaptureInterval(args);
+        case 'capture_interval_stop':
+          return this.handleCaptureIntervalStop(args);
+        case 'list_audio_sources':
+          return this.handleListAudioSources(args);
+        case 'pick_color':
+          return this.handlePickColor(args);
+        case 'decode_qr':
+          return this.handleDecodeQr(args);
         default:
           throw new Error(`Unknown tool: ${toolName}`);
       }
@@ -288,18 +641,69 @@ export class ScreenCapturePlugin extends BasePlugin {
           return this.getLatestCapture();
         case 'capture://status':
           return this.getCaptureStatus();
+        case 'capture://series':
+          return { content: JSON.stringify(await this.listSeries(), null, 2) };
         default:
           throw new Error(`Unknown resource: ${uri}`);
       }
     }, 'readResource');
   }
 
+  async readResourceTemplate(uriTemplate, params) {
+    return this.withErrorHandling(async () => {
+      switch (uriTemplate) {
+        case 'capture://file/{id}':
+          return this.getCaptureFile(params.id);
+        case 'capture://series/{label}':
+          return { content: JSON.stringify(await this.readSeries(params.label), null, 2) };
+        case 'capture://series/{label}/{frame}':
+          return this.getSeriesFrame(params.label, params.frame);
+        case 'capture://window/{address}/thumbnail':
+          return this.getWindowThumbnail(params.address);
+        default:
+          throw new Error(`Unknown resource template: ${uriTemplate}`);
+      }
+    }, 'readResourceTemplate');
+  }
+
+  // Drop captures larger than maxFileSize so they never reach the client
+  async enforceFileSize(filepath) {
+    const limit = parseSize(this.maxFileSize);
+    const { size } = await fs.stat(filepath);
+
+    if (limit && size > limit) {
+      await fs.remove(filepath);
+      throw new Error(`Capture exceeds maximum file size of ${this.maxFileSize} (${size} bytes)`);
+    }
+  }
+
   generateFilename(prefix, format) {
     const timestamp = new Date().toISOString().replace(/[:.]/g, '-');
     return `${prefix}_${timestamp}.${format}`;
   }
 
+  // A replayed capture writes a new file; interactive selection and recordings are not repeated
+  isIdempotent(toolName, args = {}) {
+    return ['capture_screenshot', 'capture_window', 'capture_all_outputs', 'list_captures', 'get_capture', 'list_audio_sources',
+      'capture_after_delay', 'decode_qr'].includes(toolName) || (toolName === 'pick_color' && args.mode !== 'interactive');
+  }
+
+  // Captures only write into the capture directory
+  isReadOnly(toolName) {
+    return ['capture_screenshot', 'capture_window', 'capture_selection', 'capture_all_outputs', 'list_captures', 'get_capture',
+      'list_audio_sources', 'capture_after_delay', 'capture_interval', 'capture_interval_stop', 'pick_color', 'decode_qr'].includes(toolName);
+  }
+
+  // slurp and hyprpicker grab the pointer until the user picks something
+  disruptsUser(toolName, args = {}) {
+    return toolName === 'capture_selection' || (toolName === 'pick_color' && args.mode === 'interactive');
+  }
+
   async handleScreenshot(args) {
+    if (this.backend === 'maim') {
+      return this.handleX11Screenshot(args);
+    }
+
     if (!this.availableTools.grim) {
       throw new Error('grim is not available - install with: sudo pacman -S grim');
     }
@@ -311,7 +715,8 @@ export class ScreenCapturePlugin extends BasePlugin {
       region, 
       format = 'png', 
       quality = 90, 
-      filename 
+      filename,
+      save = true
     } = args;
     
     const finalFilename = filename ? 
@@ -319,7 +724,8 @@ export class ScreenCapturePlugin extends BasePlugin {
       this.generateFilename('screenshot', format);
     const filepath = path.join(this.captureDir, finalFilename);
     
-    const grimArgs = [];
+    // grim infers the type from the file name, so stdout needs it spelled out
+    const grimArgs = save ? [] : ['-t', format === 'jpg' ? 'jpeg' : format];
     
     // Add output selection
     if (output && output !== 'all') {
@@ -337,22 +743,66 @@ export class ScreenCapturePlugin extends BasePlugin {
       grimArgs.push('-q', quality.toString());
     }
     
-    grimArgs.push(filepath);
-    
-    const result = await this.commandExecutor.execute('grim', grimArgs);
+    const imageData = await this.runCapture('grim', grimArgs, save ? filepath : '-');
     
+    return this.toImageResult(imageData, format, args, {
+      ...(save ? { filename: finalFilename } : {}),
+      format
+    });
+  }
+
+  // Runs the capture into the capture directory, or pipes the image back when there is no
+  // file to write (target '-' or null), skipping the quota check, temp file and re-read
+  async runCapture(command, args, target) {
+    const toFile = target && target !== '-';
+    if (toFile) {
+      await this.quota.ensureCapacity();
+    }
+
+    const result = await this.commandExecutor.execute(command, target ? [...args, target] : args, { binary: !toFile });
     if (!result.success) {
       throw new Error(`Screenshot failed: ${result.stderr}`);
     }
-    
-    // Read and return image as base64
-    const imageData = await fs.readFile(filepath);
-    const base64Data = imageData.toString('base64');
-    
-    return this.createImageResult(base64Data, `image/${format}`, {
-      filename: finalFilename,
-      size: imageData.length,
-      format
+
+    if (!toFile) {
+      return result.stdout;
+    }
+    await this.enforceFileSize(target);
+    return fs.readFile(target);
+  }
+
+  async handleX11Screenshot(args) {
+    if (!this.availableTools.maim) {
+      throw new Error('maim is not available - install with: sudo pacman -S maim');
+    }
+
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const { region, format = 'png', quality = 90, filename, save = true } = args;
+
+    const finalFilename = filename ?
+      `${filename}.${format}` :
+      this.generateFilename('screenshot', format);
+    const filepath = path.join(this.captureDir, finalFilename);
+
+    const maimArgs = ['-f', format === 'jpg' ? 'jpg' : 'png'];
+
+    if (region) {
+      const { x, y, width, height } = region;
+      maimArgs.push('-g', `${width}x${height}+${x}+${y}`);
+    }
+
+    if (format === 'jpg') {
+      maimArgs.push('-m', Math.max(1, Math.round(quality / 10)).toString());
+    }
+
+    // maim writes to stdout when no file is given
+    const imageData = await this.runCapture('maim', maimArgs, save ? filepath : null);
+
+    return this.toImageResult(imageData, format, args, {
+      ...(save ? { filename: finalFilename } : {}),
+      format,
+      backend: 'maim'
     });
   }
 
@@ -363,46 +813,133 @@ export class ScreenCapturePlugin extends BasePlugin {
 
     await this.validateArgs(args, this.tools[1].inputSchema);
     
-    const { selector = 'active', format = 'png', filename } = args;
+    const { selector = 'active', format = 'png', quality = 90, filename } = args;
     
     const finalFilename = filename ? 
       `${filename}.${format}` : 
       this.generateFilename('window', format);
     const filepath = path.join(this.captureDir, finalFilename);
     
-    let grimArgs;
-    
-    if (selector === 'active') {
-      // Capture active window using hyprctl
-      try {
-        const windowInfo = await this.commandExecutor.execute('hyprctl', ['activewindow', '-j']);
-        const window = JSON.parse(windowInfo.stdout);
-        const { at, size } = window;
-        grimArgs = ['-g', `${at[0]},${at[1]} ${size[0]}x${size[1]}`, filepath];
-      } catch {
-        throw new Error('Failed to get active window information');
-      }
-    } else {
-      // Use window class or title selector
-      grimArgs = ['-w', selector, filepath];
+    // grim only captures regions, so resolve the window's geometry through the compositor
+    const compositor = await detectCompositor({
+      type: pluginConfig(this.config, 'compositor').type || 'auto',
+      socketPath: pluginConfig(this.config, 'compositor').socketPath || null
+    });
+    if (!compositor) {
+      throw new Error('Window capture requires Hyprland or Sway');
+    }
+
+    const window = await compositor.findWindow(selector);
+    if (!window) {
+      throw new Error(`No window matches: ${selector}`);
     }
+
+    const { x, y, width, height } = window.geometry;
+    const grimArgs = ['-g', `${x},${y} ${width}x${height}`, ...(format === 'jpg' ? ['-q', String(quality)] : []), filepath];
     
+    await this.quota.ensureCapacity();
     const result = await this.commandExecutor.execute('grim', grimArgs);
     
     if (!result.success) {
       throw new Error(`Window capture failed: ${result.stderr}`);
     }
     
+    await this.enforceFileSize(filepath);
     const imageData = await fs.readFile(filepath);
-    const base64Data = imageData.toString('base64');
     
-    return this.createImageResult(base64Data, `image/${format}`, {
+    return this.toImageResult(imageData, format, args, {
       filename: finalFilename,
-      size: imageData.length,
-      selector
+      selector,
+      window: { id: window.id, class: window.class, title: window.title },
+      compositor: compositor.name
+    });
+  }
+
+  async handleAllOutputs(args) {
+    if (!this.availableTools.grim) {
+      throw new Error('grim is not available - install with: sudo pacman -S grim');
+    }
+
+    await this.validateArgs(args, this.tools[8].inputSchema);
+
+    const { mode = 'stitched', format = 'png', quality = 90, save = true } = args;
+
+    // The layout comes from the compositor; grim alone does not report output positions
+    const compositor = await detectCompositor({
+      type: pluginConfig(this.config, 'compositor').type || 'auto',
+      socketPath: pluginConfig(this.config, 'compositor').socketPath || null
+    });
+    if (!compositor) {
+      throw new Error('Capturing every output requires Hyprland or Sway');
+    }
+
+    const monitors = (await compositor.monitors())
+      .filter(monitor => monitor.geometry.width > 0 && monitor.geometry.height > 0);
+    if (monitors.length === 0) {
+      throw new Error('The compositor reports no active outputs');
+    }
+
+    const grimArgs = ['-t', format === 'jpg' ? 'jpeg' : format, ...(format === 'jpg' ? ['-q', String(quality)] : [])];
+    const captures = await Promise.all(monitors.map(async (monitor) => ({
+      name: monitor.name,
+      geometry: monitor.geometry,
+      scale: monitor.scale || 1,
+      data: await this.runCapture('grim', [...grimArgs, '-o', monitor.name], '-')
+    })));
+
+    const outputs = captures.map(({ name, geometry, scale, data }) => ({ name, geometry, scale, size: data.length }));
+
+    if (mode === 'separate') {
+      const images = [];
+      for (const [index, capture] of captures.entries()) {
+        if (save) {
+          outputs[index].filename = await this.saveCapture(capture.data, `screenshot_${capture.name}`, format);
+        }
+        const image = await this.postProcess(capture.data, format, args);
+        outputs[index].returned = { width: image.width, height: image.height, size: image.data.length };
+        images.push(this.createContent('image', await encodeBase64(image.data), { mimeType: `image/${format}` }));
+      }
+      return this.createResult(images, false, { format, mode, outputs, compositor: compositor.name });
+    }
+
+    const stitched = await stitchImages(captures, { format, quality });
+    const filename = save ? await this.saveCapture(stitched, 'screenshot_all', format) : null;
+
+    return this.toImageResult(stitched, format, args, {
+      ...(filename ? { filename } : {}),
+      format,
+      mode,
+      outputs,
+      compositor: compositor.name
     });
   }
 
+  // maxWidth and crop only shrink what is returned; a saved capture keeps full resolution
+  async postProcess(data, format, { maxWidth, crop, quality = 90 } = {}) {
+    if (!maxWidth && !crop) {
+      return { data };
+    }
+    return processImage(data, { maxWidth, crop, format, quality });
+  }
+
+  async toImageResult(data, format, args, metadata = {}) {
+    const image = await this.postProcess(data, format, args);
+    return this.createImageResult(await encodeBase64(image.data), `image/${format}`, {
+      ...metadata,
+      size: image.data.length,
+      ...(image.width ? { width: image.width, height: image.height, originalSize: data.length } : {})
+    });
+  }
+
+  async saveCapture(data, prefix, format) {
+    await this.quota.ensureCapacity();
+    const filename = this.generateFilename(prefix.replace(/[^\w.-]/g, '_'), format);
+    const filepath = path.join(this.captureDir, filename);
+    await fs.writeFile(filepath, data);
+    await this.enforceFileSize(filepath);
+    return filename;
+  }
+
   async handleSelectionCapture(args) {
     if (!this.availableTools.grim || !this.availableTools.slurp) {
       throw new Error('grim and slurp are required - install with: sudo pacman -S grim slurp');
@@ -410,7 +947,7 @@ export class ScreenCapturePlugin extends BasePlugin {
 
     await this.validateArgs(args, this.tools[2].inputSchema);
     
-    const { format = 'png', filename } = args;
+    const { format = 'png', quality = 90, filename } = args;
     
     const finalFilename = filename ? 
       `${filename}.${format}` : 
@@ -425,18 +962,18 @@ export class ScreenCapturePlugin extends BasePlugin {
     }
     
     const selection = slurpResult.stdout.trim();
-    const result = await this.commandExecutor.execute('grim', ['-g', selection, filepath]);
+    await this.quota.ensureCapacity();
+    const result = await this.commandExecutor.execute('grim', ['-g', selection, ...(format === 'jpg' ? ['-q', String(quality)] : []), filepath]);
     
     if (!result.success) {
       throw new Error(`Selection capture failed: ${result.stderr}`);
     }
     
+    await this.enforceFileSize(filepath);
     const imageData = await fs.readFile(filepath);
-    const base64Data = imageData.toString('base64');
     
-    return this.createImageResult(base64Data, `image/${format}`, {
+    return this.toImageResult(imageData, format, args, {
       filename: finalFilename,
-      size: imageData.length,
       selection
     });
   }
@@ -454,11 +991,31 @@ export class ScreenCapturePlugin extends BasePlugin {
     
     const { 
       output, 
+      region,
+      window: windowSelector,
+      maxDuration = this.maxRecordingDuration,
       audio = false, 
+      audioSource,
       format = 'mp4', 
       fps = 30, 
       filename 
     } = args;
+
+    if ([output, region, windowSelector].filter(Boolean).length > 1) {
+      throw new Error('Use only one of output, region and window');
+    }
+    if (maxDuration > this.maxRecordingDuration) {
+      throw new Error(`maxDuration is limited to ${this.maxRecordingDuration} seconds`);
+    }
+    if (await fs.pathExists(path.join(this.captureDir, '.recording.json'))) {
+      throw new Error('A recording is already running; stop it with stop_recording first');
+    }
+
+    let geometry = region || null;
+    let window = null;
+    if (windowSelector) {
+      ({ geometry, window } = await this.windowGeometry(windowSelector));
+    }
     
     const finalFilename = filename ? 
       `${filename}.${format}` : 
@@ -470,45 +1027,467 @@ export class ScreenCapturePlugin extends BasePlugin {
     if (output) {
       recordingArgs.push('-o', output);
     }
-    
-    if (audio) {
-      recordingArgs.push('-a');
+
+    if (geometry) {
+      recordingArgs.push('-g', geometry);
     }
     
+    // Resolved before anything is loaded, so an unknown source fails cleanly
+    const audioSources = audioSource ? await this.audioSources.resolve(audioSource) : [];
+    let recordedAudio = audio ? { selection: 'default', sources: ['default'] } : null;
+    
     recordingArgs.push('-r', fps.toString());
     recordingArgs.push('-f', filepath);
     
+    await this.quota.ensureCapacity();
+
+    if (audioSources.length > 1) {
+      const mix = await this.audioSources.createMix(audioSources);
+      recordedAudio = { selection: audioSource, sources: audioSources, device: mix.source, modules: mix.modules };
+    } else if (audioSources.length === 1) {
+      recordedAudio = { selection: audioSource, sources: audioSources, device: audioSources[0] };
+    }
+
+    if (recordedAudio) {
+      recordingArgs.unshift(recordedAudio.device ? `--audio=${recordedAudio.device}` : '-a');
+    }
+
     // Start recording in background
-    const child = await this.commandExecutor.execute('wf-recorder', recordingArgs, {
-      captureOutput: false
-    });
+    let child;
+    try {
+      child = await this.commandExecutor.execute('wf-recorder', recordingArgs, {
+        captureOutput: false
+      });
+    } catch (error) {
+      await this.audioSources.unload(recordedAudio?.modules);
+      throw error;
+    }
     
     // Save recording info
+    const startTime = new Date();
     const recordingInfo = {
       filename: finalFilename,
       filepath,
-      startTime: new Date().toISOString(),
-      pid: child.pid
+      startTime: startTime.toISOString(),
+      stopAt: new Date(startTime.getTime() + maxDuration * 1000).toISOString(),
+      pid: child.pid,
+      ...(geometry ? { geometry } : {}),
+      ...(window ? { window } : {}),
+      ...(recordedAudio ? { audio: recordedAudio } : {})
     };
     
     const recordingInfoPath = path.join(this.captureDir, '.recording.json');
     await fs.writeJson(recordingInfoPath, recordingInfo);
+    this.scheduleRecordingStop(recordingInfo);
+
+    // Screen locking mid-recording would capture the lock screen; held until stop_recording
+    if (this.security?.autoInhibitIdle) {
+      await this.security.idle.acquire('Screen recording', { what: 'idle', key: 'recording' }).catch(error => {
+        this.logger.warn(`Could not inhibit idle during recording: ${error.message}`);
+      });
+    }
     
     return this.createTextResult(`Recording started: ${finalFilename}`, {
       filename: finalFilename,
       format,
       fps,
-      audio
+      audio: this.describeAudio(recordedAudio),
+      stopAt: recordingInfo.stopAt,
+      ...(geometry ? { geometry } : {}),
+      ...(window ? { window } : {})
     });
   }
 
+  // What a recording captures, without the module ids of a mix
+  describeAudio(recordedAudio) {
+    if (!recordedAudio) {
+      return false;
+    }
+    const { modules, ...audio } = recordedAudio;
+    return audio;
+  }
+
+  async handleListAudioSources(args) {
+    await this.validateArgs(args, this.tools[12].inputSchema);
+    return this.createTextResult(JSON.stringify(await this.audioSources.list(), null, 2));
+  }
+
+  async handlePickColor(args) {
+    await this.validateArgs(args, this.tools[13].inputSchema);
+
+    const { mode = 'point', x, y } = args;
+    if (mode === 'interactive') {
+      if (!this.availableTools.hyprpicker) {
+        throw new Error('hyprpicker is not available - install with: sudo pacman -S hyprpicker');
+      }
+      const result = await this.commandExecutor.execute('hyprpicker', ['--format=hex', '--no-fancy']);
+      const hex = result.stdout.match(/#?([0-9a-fA-F]{6})\b/)?.[1];
+      if (!result.success || !hex) {
+        throw new Error(`Color picking cancelled or failed: ${result.stderr || result.stdout}`);
+      }
+      const color = describeColor({ r: parseInt(hex.slice(0, 2), 16), g: parseInt(hex.slice(2, 4), 16), b: parseInt(hex.slice(4, 6), 16) });
+      return this.createTextResult(JSON.stringify({ mode, ...color }, null, 2), color);
+    }
+
+    if (!Number.isInteger(x) || !Number.isInteger(y)) {
+      throw new Error('x and y are required in point mode');
+    }
+    if (!this.availableTools.grim) {
+      throw new Error('grim is not available - install with: sudo pacman -S grim');
+    }
+    // On a scaled output one logical pixel is several physical ones; the first stands for it
+    const color = describeColor(readPpmPixel(await this.runCapture('grim', ['-t', 'ppm', '-g', `${x},${y} 1x1`], '-')));
+    return this.createTextResult(JSON.stringify({ mode, x, y, ...color }, null, 2), color);
+  }
+
+  async handleDecodeQr(args) {
+    await this.validateArgs(args, this.tools[14].inputSchema);
+
+    if (!this.availableTools.zbarimg) {
+      throw new Error('zbarimg is not available - install with: sudo pacman -S zbar');
+    }
+
+    const { filename, output, region } = args;
+    let image;
+    let scratch = null;
+    if (filename) {
+      image = path.resolve(this.captureDir, filename);
+      if (!image.startsWith(path.resolve(this.captureDir) + path.sep)) {
+        throw new Error('Invalid file path');
+      }
+      assertWithinRoots(image);
+      if (!await fs.pathExists(image)) {
+        throw new Error(`File not found: ${filename}`);
+      }
+    } else {
+      // The screenshot is only needed for the scan, so it stays out of the capture directory
+      const { x, y, width, height } = region || {};
+      const data = this.backend === 'maim'
+        ? await this.runCapture('maim', ['-f', 'png', ...(region ? ['-g', `${width}x${height}+${x}+${y}`] : [])], null)
+        : await this.runCapture('grim', ['-t', 'png', ...(output && output !== 'all' ? ['-o', output] : []),
+          ...(region ? ['-g', `${x},${y} ${width}x${height}`] : [])], '-');
+      scratch = await fs.mkdtemp(path.join(os.tmpdir(), 'mcp-decode-'));
+      image = path.join(scratch, 'screen.png');
+      await fs.writeFile(image, data);
+    }
+
+    try {
+      const result = await this.commandExecutor.execute('zbarimg', ['--quiet', '--xml', image]);
+      // Exit status 4 means the image was scanned and nothing was found
+      if (!result.success && result.exitCode !== 4) {
+        throw new Error(`Scanning failed: ${result.stderr}`);
+      }
+
+      const codes = parseZbarXml(result.stdout).map(code => {
+        const wifi = parseWifiPayload(code.data);
+        return wifi ? { ...code, wifi } : code;
+      });
+      return this.createTextResult(JSON.stringify(codes, null, 2), {
+        found: codes.length,
+        source: filename || (region ? 'region' : output || 'screen')
+      });
+    } finally {
+      if (scratch) {
+        await fs.remove(scratch);
+      }
+    }
+  }
+
+  // grim and wf-recorder only know screen regions, so a window becomes its current geometry
+  async windowGeometry(selector) {
+    const compositor = await detectCompositor({
+      type: pluginConfig(this.config, 'compositor').type || 'auto',
+      socketPath: pluginConfig(this.config, 'compositor').socketPath || null
+    });
+    if (!compositor) {
+      throw new Error('Window targeting requires Hyprland or Sway');
+    }
+
+    const window = await compositor.findWindow(selector);
+    if (!window) {
+      throw new Error(`No window matches: ${selector}`);
+    }
+
+    const { x, y, width, height } = window.geometry;
+    return {
+      geometry: `${x},${y} ${width}x${height}`,
+      window: { id: window.id, class: window.class, title: window.title }
+    };
+  }
+
+  // Forgotten recordings fill the disk; each one stops at its stopAt even across restarts
+  scheduleRecordingStop(recordingInfo) {
+    clearTimeout(this.recordingTimer);
+    if (!recordingInfo.stopAt) {
+      return;
+    }
+
+    const delay = Math.max(new Date(recordingInfo.stopAt) - Date.now(), 0);
+    this.recordingTimer = setTimeout(async () => {
+      this.recordingTimer = null;
+      try {
+        const stopped = await this.stopRecording();
+        this.logger.warn(`Recording ${stopped.filename} reached its maximum duration and was stopped`);
+        this.broadcast({ level: 'warning', message: { event: 'recording_stopped', reason: 'maxDuration', ...stopped } });
+      } catch (error) {
+        this.logger.error(`Stopping recording at its maximum duration failed: ${error.message}`);
+      }
+    }, delay);
+    this.recordingTimer.unref?.();
+  }
+
+  async subscribeResource(uri, listener) {
+    if (uri !== 'capture://status') {
+      return () => {};
+    }
+    this.listeners.add(listener);
+    return () => this.listeners.delete(listener);
+  }
+
+  broadcast(update) {
+    for (const listener of this.listeners) {
+      listener(update);
+    }
+  }
+
+  async cleanup() {
+    clearTimeout(this.recordingTimer);
+    for (const label of [...this.series.keys()]) {
+      await this.finishSeries(label, 'interrupted');
+    }
+    await super.cleanup();
+  }
+
+  async handleCaptureAfterDelay(args) {
+    await this.validateArgs(args, this.tools[9].inputSchema);
+
+    const { delay, ...screenshot } = args;
+    for (let remaining = delay; remaining > 0; remaining--) {
+      reportProgress(delay - remaining, { total: delay, message: `capturing in ${remaining}s` });
+      await new Promise(resolve => setTimeout(resolve, 1000));
+    }
+    return this.handleScreenshot(screenshot);
+  }
+
+  seriesDir(label) {
+    if (!SERIES_LABEL.test(label)) {
+      throw new Error(`Invalid series label: ${label}`);
+    }
+    return path.join(this.captureDir, 'series', label);
+  }
+
+  async handleCaptureInterval(args) {
+    await this.validateArgs(args, this.tools[10].inputSchema);
+
+    const { label, interval, duration, output, region, format = 'png' } = args;
+    if (this.backend === 'maim' ? !this.availableTools.maim : !this.availableTools.grim) {
+      throw new Error(`${this.backend === 'maim' ? 'maim' : 'grim'} is not available`);
+    }
+
+    const dir = this.seriesDir(label);
+    if (await fs.pathExists(dir)) {
+      throw new Error(`Series ${label} already exists; pick another label or delete it with delete_capture (series/${label})`);
+    }
+
+    const frames = Math.min(Math.floor(duration * 60 / interval) + 1, MAX_SERIES_FRAMES);
+    const startedAt = new Date();
+    const manifest = {
+      label,
+      interval,
+      duration,
+      format,
+      ...(output ? { output } : {}),
+      ...(region ? { region } : {}),
+      status: 'running',
+      startedAt: startedAt.toISOString(),
+      endsAt: new Date(startedAt.getTime() + (frames - 1) * interval * 1000).toISOString(),
+      plannedFrames: frames,
+      frames: []
+    };
+    await fs.ensureDir(dir);
+    await fs.writeJson(path.join(dir, 'series.json'), manifest, { spaces: 2 });
+
+    const run = { manifest, busy: false, timer: null };
+    this.series.set(label, run);
+    run.timer = setInterval(() => this.captureSeriesFrame(label).catch(error => {
+      this.logger.error(`Interval capture ${label} failed: ${error.message}`);
+    }), interval * 1000);
+    run.timer.unref?.();
+    await this.captureSeriesFrame(label);
+
+    return this.createTextResult(`Capturing ${frames} frames into series ${label}, one every ${interval}s until ${manifest.endsAt}`, {
+      label,
+      uri: `capture://series/${label}`,
+      plannedFrames: frames,
+      endsAt: manifest.endsAt
+    });
+  }
+
+  // Frames that come due while the previous one is still being written are skipped
+  async captureSeriesFrame(label) {
+    const run = this.series.get(label);
+    if (!run || run.busy) {
+      return;
+    }
+    run.busy = true;
+
+    const { manifest } = run;
+    const index = manifest.frames.length + 1;
+    const file = `frame_${String(index).padStart(4, '0')}.${manifest.format}`;
+    const target = path.join(this.seriesDir(label), file);
+    try {
+      if (this.backend === 'maim') {
+        const { x, y, width, height } = manifest.region || {};
+        await this.runCapture('maim', ['-f', manifest.format, ...(manifest.region ? ['-g', `${width}x${height}+${x}+${y}`] : [])], target);
+      } else {
+        const { x, y, width, height } = manifest.region || {};
+        await this.runCapture('grim', [
+          ...(manifest.output ? ['-o', manifest.output] : []),
+          ...(manifest.region ? ['-g', `${x},${y} ${width}x${height}`] : [])
+        ], target);
+      }
+      manifest.frames.push({ file, time: new Date().toISOString() });
+    } catch (error) {
+      run.busy = false;
+      this.logger.warn(`Interval capture ${label} stopped: ${error.message}`);
+      await this.finishSeries(label, 'failed', error.message);
+      return;
+    }
+    run.busy = false;
+
+    if (manifest.frames.length >= manifest.plannedFrames) {
+      await this.finishSeries(label, 'completed');
+    } else {
+      await fs.writeJson(path.join(this.seriesDir(label), 'series.json'), manifest, { spaces: 2 });
+    }
+  }
+
+  async finishSeries(label, status, error = null) {
+    const run = this.series.get(label);
+    if (!run) {
+      return null;
+    }
+    clearInterval(run.timer);
+    this.series.delete(label);
+
+    Object.assign(run.manifest, { status, finishedAt: new Date().toISOString(), ...(error ? { error } : {}) });
+    await fs.writeJson(path.join(this.seriesDir(label), 'series.json'), run.manifest, { spaces: 2 });
+    this.broadcast({ level: status === 'failed' ? 'warning' : 'info', message: { event: 'series_finished', label, status, frames: run.manifest.frames.length } });
+    return run.manifest;
+  }
+
+  async handleCaptureIntervalStop(args) {
+    await this.validateArgs(args, this.tools[11].inputSchema);
+
+    const manifest = await this.finishSeries(args.label, 'stopped');
+    if (!manifest) {
+      throw new Error(`No interval capture running with label ${args.label}`);
+    }
+    return this.createTextResult(`Stopped series ${args.label} after ${manifest.frames.length} frames`, {
+      label: args.label,
+      frames: manifest.frames.length
+    });
+  }
+
+  // A series left "running" by an earlier process was cut short by the restart
+  async readSeries(label) {
+    const file = path.join(this.seriesDir(label), 'series.json');
+    assertWithinRoots(file);
+    if (!await fs.pathExists(file)) {
+      throw new Error(`Unknown series: ${label}`);
+    }
+
+    const manifest = await fs.readJson(file);
+    if (manifest.status === 'running' && !this.series.has(label)) {
+      manifest.status = 'interrupted';
+    }
+    return {
+      ...manifest,
+      frames: manifest.frames.map(frame => ({ ...frame, uri: `capture://series/${label}/${frame.file}` }))
+    };
+  }
+
+  async listSeries() {
+    const labels = await fs.readdir(path.join(this.captureDir, 'series')).catch(() => []);
+    const series = [];
+    for (const label of labels.filter(name => SERIES_LABEL.test(name)).sort()) {
+      const { frames, ...manifest } = await this.readSeries(label).catch(() => ({ label, status: 'unreadable', frames: [] }));
+      series.push({ ...manifest, frameCount: frames.length, uri: `capture://series/${label}` });
+    }
+    return series;
+  }
+
+  // Captured when read and kept for a few seconds. A window on a hidden workspace cannot be
+  // captured, so its last thumbnail is returned if there is one
+  async getWindowThumbnail(address) {
+    const compositor = await detectCompositor({
+      type: pluginConfig(this.config, 'compositor').type || 'auto',
+      socketPath: pluginConfig(this.config, 'compositor').socketPath || null
+    });
+    if (!compositor) {
+      throw new Error('Window thumbnails require Hyprland or Sway');
+    }
+
+    const [windows, monitors] = await Promise.all([compositor.windows(), compositor.monitors()]);
+    for (const id of this.thumbnails.keys()) {
+      if (!windows.some(window => String(window.id) === id)) {
+        this.thumbnails.delete(id);
+      }
+    }
+
+    const window = windows.find(candidate => String(candidate.id) === address);
+    if (!window) {
+      throw new Error(`No window with id ${address}`);
+    }
+
+    const { x, y, width, height } = window.geometry;
+    const geometry = `${x},${y} ${width}x${height}`;
+    const cached = this.thumbnails.get(address);
+    const visible = monitors.some(monitor => monitor.workspace === window.workspace);
+    if (cached && (!visible || (cached.geometry === geometry && Date.now() - cached.capturedAt < THUMBNAIL_MAX_AGE))) {
+      return { blob: cached.data.toString('base64'), mimeType: 'image/jpeg' };
+    }
+    if (!visible) {
+      throw new Error(`Window ${address} is on workspace ${window.workspace}, which no monitor shows`);
+    }
+    if (!this.availableTools.grim) {
+      throw new Error('grim is not available - install with: sudo pacman -S grim');
+    }
+
+    const capture = await this.runCapture('grim', ['-t', 'png', '-g', geometry], '-');
+    const { data } = await processImage(capture, { maxWidth: THUMBNAIL_WIDTH, format: 'jpg', quality: 70 });
+    this.thumbnails.set(address, { data, geometry, capturedAt: Date.now() });
+    return { blob: data.toString('base64'), mimeType: 'image/jpeg' };
+  }
+
+  async getSeriesFrame(label, frame) {
+    const dir = path.resolve(this.seriesDir(label));
+    const filepath = path.resolve(dir, frame);
+    if (path.dirname(filepath) !== dir || !/^frame_\d+\.\w+$/.test(frame)) {
+      throw new Error('Invalid file path');
+    }
+    assertWithinRoots(filepath);
+
+    if (!await fs.pathExists(filepath)) {
+      throw new Error(`File not found: ${label}/${frame}`);
+    }
+    return { blob: await encodeFileBase64(filepath), mimeType: this.captureMimeType(frame) };
+  }
+
   async handleStopRecording(args) {
+    const stopped = await this.stopRecording();
+    return this.createTextResult(`Recording stopped: ${stopped.filename}`, stopped);
+  }
+
+  async stopRecording() {
     const recordingInfoPath = path.join(this.captureDir, '.recording.json');
     
     if (!await fs.pathExists(recordingInfoPath)) {
       throw new Error('No active recording found');
     }
     
+    clearTimeout(this.recordingTimer);
+    this.recordingTimer = null;
     const recordingInfo = await fs.readJson(recordingInfoPath);
     
     // Stop recording by sending SIGINT
@@ -520,15 +1499,18 @@ export class ScreenCapturePlugin extends BasePlugin {
     
     // Clean up recording info
     await fs.remove(recordingInfoPath);
+    this.security?.idle?.release('recording');
+    await this.audioSources.unload(recordingInfo.audio?.modules);
     
     // Check if file exists and get info
     const stats = await fs.stat(recordingInfo.filepath);
     
-    return this.createTextResult(`Recording stopped: ${recordingInfo.filename}`, {
+    return {
       filename: recordingInfo.filename,
       duration: new Date() - new Date(recordingInfo.startTime),
-      size: stats.size
-    });
+      size: stats.size,
+      audio: this.describeAudio(recordingInfo.audio)
+    };
   }
 
   async handleListCaptures(args) {
@@ -543,6 +1525,7 @@ export class ScreenCapturePlugin extends BasePlugin {
       if (file.startsWith('.')) continue; // Skip hidden files
       
       const filepath = path.join(this.captureDir, file);
+      if (!isWithinRoots(filepath)) continue;
       const stats = await fs.stat(filepath);
       const ext = path.extname(file).toLowerCase();
       
@@ -587,10 +1570,17 @@ export class ScreenCapturePlugin extends BasePlugin {
     if (!resolvedPath.startsWith(resolvedCaptureDir)) {
       throw new Error('Invalid file path');
     }
+    assertWithinRoots(resolvedPath);
     
     if (!await fs.pathExists(filepath)) {
       throw new Error(`File not found: ${filename}`);
     }
+
+    // Deleting a series that is still being captured ends it first
+    const series = filename.match(/^series\/([^/]+)\/?$/)?.[1];
+    if (series && this.series.has(series)) {
+      await this.finishSeries(series, 'stopped');
+    }
     
     await fs.remove(filepath);
     
@@ -610,22 +1600,15 @@ export class ScreenCapturePlugin extends BasePlugin {
     if (!resolvedPath.startsWith(resolvedCaptureDir)) {
       throw new Error('Invalid file path');
     }
+    assertWithinRoots(resolvedPath);
     
     if (!await fs.pathExists(filepath)) {
       throw new Error(`File not found: ${filename}`);
     }
     
-    const data = await fs.readFile(filepath);
-    const base64Data = data.toString('base64');
+    const base64Data = await encodeFileBase64(filepath);
     const stats = await fs.stat(filepath);
-    const ext = path.extname(filename).toLowerCase();
-    
-    let mimeType = 'application/octet-stream';
-    if (['.png'].includes(ext)) mimeType = 'image/png';
-    else if (['.jpg', '.jpeg'].includes(ext)) mimeType = 'image/jpeg';
-    else if (['.webp'].includes(ext)) mimeType = 'image/webp';
-    else if (['.mp4'].includes(ext)) mimeType = 'video/mp4';
-    else if (['.webm'].includes(ext)) mimeType = 'video/webm';
+    const mimeType = this.captureMimeType(filename);
     
     return this.createImageResult(base64Data, mimeType, {
       filename,
@@ -639,6 +1622,33 @@ export class ScreenCapturePlugin extends BasePlugin {
     return { content: result.content[0].text };
   }
 
+  captureMimeType(filename) {
+    const ext = path.extname(filename).toLowerCase();
+    
+    let mimeType = 'application/octet-stream';
+    if (['.png'].includes(ext)) mimeType = 'image/png';
+    else if (['.jpg', '.jpeg'].includes(ext)) mimeType = 'image/jpeg';
+    else if (['.webp'].includes(ext)) mimeType = 'image/webp';
+    else if (['.mp4'].includes(ext)) mimeType = 'video/mp4';
+    else if (['.webm'].includes(ext)) mimeType = 'video/webm';
+    else if (['.mkv'].includes(ext)) mimeType = 'video/x-matroska';
+    return mimeType;
+  }
+
+  async getCaptureFile(id) {
+    const filepath = path.resolve(this.captureDir, id);
+    if (path.dirname(filepath) !== path.resolve(this.captureDir) || id.startsWith('.')) {
+      throw new Error('Invalid file path');
+    }
+    assertWithinRoots(filepath);
+    
+    if (!await fs.pathExists(filepath)) {
+      throw new Error(`File not found: ${id}`);
+    }
+    
+    return { blob: await encodeFileBase64(filepath), mimeType: this.captureMimeType(id) };
+  }
+
   async getLatestCapture() {
     try {
       const files = await fs.readdir(this.captureDir);
@@ -649,7 +1659,9 @@ export class ScreenCapturePlugin extends BasePlugin {
         if (file.startsWith('.')) continue;
         
         const filepath = path.join(this.captureDir, file);
+        if (!isWithinRoots(filepath)) continue;
         const stats = await fs.stat(filepath);
+        if (stats.isDirectory()) continue;
         
         if (stats.birthtime.getTime() > latestTime) {
           latestTime = stats.birthtime.getTime();
@@ -670,8 +1682,10 @@ export class ScreenCapturePlugin extends BasePlugin {
   async getCaptureStatus() {
     const status = {
       captureDir: this.captureDir,
+      backend: this.backend,
       availableTools: this.availableTools,
       allowRecording: this.allowRecording,
+      maxRecordingDuration: this.maxRecordingDuration,
       maxFileSize: this.maxFileSize
     };
     
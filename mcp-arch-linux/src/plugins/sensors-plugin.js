@@ -0,0 +1,97 @@
+import { BasePlugin, pluginConfig } from './base-plugin.js';
+import { SensorMonitor } from '../system/sensors.js';
+
+const CURRENT_URI = 'sensors://current';
+
+// hwmon temperatures, fans and voltages; subscribing to sensors://current polls
+// them and notifies clients when a limit is crossed or the CPU throttles
+export class SensorsPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('sensors', config, logger, security);
+    this.description = 'Temperature, fan and voltage sensors with over-temperature alerts';
+
+    const settings = pluginConfig(config, 'sensors');
+    this.monitor = new SensorMonitor({
+      interval: settings.interval || 5000,
+      limits: settings.limits || {},
+      hysteresis: settings.hysteresis ?? 5
+    });
+    this.listeners = new Set();
+
+    this.monitor.on('alert', alert => {
+      this.logger.warn('Sensor alert', alert);
+      this.broadcast({ level: 'warning', message: { event: 'alert', ...alert } });
+    });
+    this.monitor.on('recovered', alert => {
+      this.broadcast({ level: 'info', message: { event: 'recovered', ...alert } });
+    });
+    this.monitor.on('error', error => {
+      this.logger.warn('Reading sensors failed:', error.message);
+    });
+
+    this.initializeResources();
+  }
+
+  initializeResources() {
+    this.resources = [
+      this.createResource(
+        CURRENT_URI,
+        'Sensor Readings',
+        'hwmon temperatures (with limits), fan speeds, voltages and CPU throttling; subscribe for over-temperature alerts',
+        'application/json'
+      )
+    ];
+  }
+
+  async readResource(uri) {
+    return this.withErrorHandling(async () => {
+      switch (uri) {
+        case CURRENT_URI:
+          return this.getCurrent();
+        default:
+          throw new Error(`Unknown resource: ${uri}`);
+      }
+    }, 'readResource');
+  }
+
+  async subscribeResource(uri, listener) {
+    if (uri !== CURRENT_URI) {
+      return () => {};
+    }
+
+    this.listeners.add(listener);
+    this.monitor.start();
+    this.logger.info('Sensor monitoring started', { interval: this.monitor.interval });
+
+    return () => {
+      this.listeners.delete(listener);
+      if (this.listeners.size === 0) {
+        this.monitor.stop();
+        this.logger.info('Sensor monitoring stopped');
+      }
+    };
+  }
+
+  broadcast(update) {
+    for (const listener of this.listeners) {
+      listener(update);
+    }
+  }
+
+  async getCurrent() {
+    const reading = await this.monitor.read();
+    return {
+      content: JSON.stringify({
+        ...reading,
+        monitoring: this.monitor.running,
+        alerts: [...this.monitor.hot.values()]
+      }, null, 2)
+    };
+  }
+
+  async cleanup() {
+    this.monitor.stop();
+    this.listeners.clear();
+    await super.cleanup();
+  }
+}
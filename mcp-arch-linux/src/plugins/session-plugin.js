@@ -0,0 +1,413 @@
+import { BasePlugin } from './base-plugin.js';
+import { LockKeys } from '../security/lock-manager.js';
+import { lookupUser } from '../system/user-account.js';
+import { readLines, writeUserFile, upsertLine, getIniValues } from '../system/user-files.js';
+import fs from 'fs-extra';
+import path from 'path';
+
+const MARKER = '# mcp-arch-linux autostart:';
+const ENV_FILE = '60-mcp-arch-linux.conf';
+const ENTRY_NAME = /^[A-Za-z0-9_.-]+$/;
+const ENV_NAME = /^[A-Za-z_][A-Za-z0-9_]*$/;
+
+// Login-time apps and session environment, for XDG sessions and Hyprland
+export class SessionPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('session', config, logger, security);
+    this.description = 'Autostart entries, session environment variables and user presence';
+
+    this.initializeTools();
+    this.initializeResources();
+  }
+
+  initializeTools() {
+    const user = {
+      type: 'string',
+      description: 'User whose session is configured'
+    };
+
+    this.tools = [
+      this.createTool(
+        'autostart_list',
+        'List XDG autostart entries and Hyprland exec-once lines',
+        {
+          type: 'object',
+          properties: { user },
+          required: ['user']
+        }
+      ),
+
+      this.createTool(
+        'autostart_add',
+        'Start an application at login',
+        {
+          type: 'object',
+          properties: {
+            user,
+            name: {
+              type: 'string',
+              description: 'Entry name (letters, digits, ".", "_", "-")'
+            },
+            command: {
+              type: 'string',
+              description: 'Command line to run'
+            },
+            method: {
+              type: 'string',
+              enum: ['xdg', 'hyprland'],
+              description: '~/.config/autostart/<name>.desktop or an exec-once line in hyprland.conf',
+              default: 'xdg'
+            }
+          },
+          required: ['user', 'name', 'command']
+        }
+      ),
+
+      this.createTool(
+        'autostart_remove',
+        'Remove an autostart entry added by name',
+        {
+          type: 'object',
+          properties: {
+            user,
+            name: {
+              type: 'string',
+              description: 'Entry name as shown by autostart_list'
+            },
+            method: {
+              type: 'string',
+              enum: ['xdg', 'hyprland'],
+              default: 'xdg'
+            }
+          },
+          required: ['user', 'name']
+        }
+      ),
+
+      this.createTool(
+        'session_env_list',
+        'List session environment variables from environment.d and Hyprland env keywords',
+        {
+          type: 'object',
+          properties: { user },
+          required: ['user']
+        }
+      ),
+
+      this.createTool(
+        'session_env_set',
+        'Set a session environment variable (applies at next login)',
+        {
+          type: 'object',
+          properties: {
+            user,
+            name: {
+              type: 'string',
+              description: 'Variable name'
+            },
+            value: {
+              type: 'string',
+              description: 'Variable value'
+            },
+            method: {
+              type: 'string',
+              enum: ['environment.d', 'hyprland'],
+              description: '~/.config/environment.d or an env keyword in hyprland.conf',
+              default: 'environment.d'
+            }
+          },
+          required: ['user', 'name', 'value']
+        }
+      ),
+
+      this.createTool(
+        'session_env_unset',
+        'Remove a session environment variable',
+        {
+          type: 'object',
+          properties: {
+            user,
+            name: {
+              type: 'string',
+              description: 'Variable name'
+            },
+            method: {
+              type: 'string',
+              enum: ['environment.d', 'hyprland'],
+              default: 'environment.d'
+            }
+          },
+          required: ['user', 'name']
+        }
+      )
+    ];
+  }
+
+  initializeResources() {
+    this.resources = [
+      this.createResource(
+        'session://presence',
+        'User Presence',
+        'Whether someone is actively using a seat, from logind sessions with their idle and lock state',
+        'application/json'
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'autostart_list':
+          return this.handleAutostartList(args);
+        case 'autostart_add':
+          return this.handleAutostartAdd(args);
+        case 'autostart_remove':
+          return this.handleAutostartRemove(args);
+        case 'session_env_list':
+          return this.handleEnvList(args);
+        case 'session_env_set':
+          return this.handleEnvSet(args);
+        case 'session_env_unset':
+          return this.handleEnvUnset(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  async readResource(uri) {
+    return this.withErrorHandling(async () => {
+      switch (uri) {
+        case 'session://presence':
+          return { content: JSON.stringify(await this.security.presence.status(), null, 2) };
+        default:
+          throw new Error(`Unknown resource: ${uri}`);
+      }
+    }, 'readResource');
+  }
+
+  // Setting or unsetting a variable converges; autostart_add would append a duplicate entry
+  isIdempotent(toolName) {
+    return ['autostart_list', 'session_env_list', 'session_env_set', 'session_env_unset'].includes(toolName);
+  }
+
+  isReadOnly(toolName) {
+    return ['autostart_list', 'session_env_list'].includes(toolName);
+  }
+
+  getToolLocks(toolName, args = {}) {
+    if (args.method === 'hyprland' && !toolName.endsWith('_list')) {
+      return [LockKeys.hyprlandConfig()];
+    }
+    return [];
+  }
+
+  async getAffectedPaths(toolName, args = {}) {
+    if (toolName.endsWith('_list') || !args.user) {
+      return [];
+    }
+
+    try {
+      const paths = this.paths(await lookupUser(args.user));
+      if (args.method === 'hyprland') return [paths.hyprland];
+      if (toolName.startsWith('autostart_')) return [paths.autostart];
+      return [paths.environmentD];
+    } catch {
+      return [];
+    }
+  }
+
+  paths(account) {
+    const configHome = path.join(account.home, '.config');
+    return {
+      autostart: path.join(configHome, 'autostart'),
+      environmentD: path.join(configHome, 'environment.d'),
+      hyprland: path.join(configHome, 'hypr', 'hyprland.conf')
+    };
+  }
+
+  checkName(name, pattern, kind) {
+    if (!pattern.test(name)) {
+      throw new Error(`Invalid ${kind} name: ${name}`);
+    }
+  }
+
+  checkLine(field, value) {
+    if (/[\r\n]/.test(value)) {
+      throw new Error(`${field} must be a single line`);
+    }
+  }
+
+  async handleAutostartList(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const account = await lookupUser(args.user);
+    const paths = this.paths(account);
+    const entries = [];
+
+    for (const file of (await fs.readdir(paths.autostart).catch(() => [])).filter(name => name.endsWith('.desktop'))) {
+      const fields = getIniValues(await readLines(path.join(paths.autostart, file)), 'Desktop Entry');
+      entries.push({
+        method: 'xdg',
+        name: file.replace(/\.desktop$/, ''),
+        command: fields.Exec || null,
+        enabled: fields.Hidden !== 'true' && fields['X-GNOME-Autostart-enabled'] !== 'false',
+        file: path.join(paths.autostart, file)
+      });
+    }
+
+    const lines = await readLines(paths.hyprland);
+    lines.forEach((line, index) => {
+      const match = line.match(/^\s*exec-once\s*=\s*(.+)$/);
+      if (match) {
+        const marker = lines[index - 1]?.startsWith(MARKER) ? lines[index - 1].slice(MARKER.length).trim() : null;
+        entries.push({ method: 'hyprland', name: marker, command: match[1].trim(), enabled: true, file: paths.hyprland });
+      }
+    });
+
+    return this.createTextResult(JSON.stringify(entries, null, 2), { count: entries.length });
+  }
+
+  async handleAutostartAdd(args) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+
+    const { name, command, method = 'xdg' } = args;
+    this.checkName(name, ENTRY_NAME, 'entry');
+    this.checkLine('command', command);
+
+    const account = await lookupUser(args.user);
+    const paths = this.paths(account);
+
+    if (method === 'xdg') {
+      const file = path.join(paths.autostart, `${name}.desktop`);
+      await writeUserFile(file, [
+        '[Desktop Entry]',
+        'Type=Application',
+        `Name=${name}`,
+        `Exec=${command}`,
+        'X-GNOME-Autostart-enabled=true',
+        ''
+      ].join('\n'), account);
+      return this.createTextResult(`Autostart entry written: ${file}`);
+    }
+
+    const lines = await readLines(paths.hyprland);
+    const existing = lines.findIndex(line => line === `${MARKER} ${name}`);
+    if (existing >= 0) {
+      lines[existing + 1] = `exec-once = ${command}`;
+    } else {
+      if (lines[lines.length - 1] === '') lines.pop();
+      lines.push(`${MARKER} ${name}`, `exec-once = ${command}`, '');
+    }
+    await writeUserFile(paths.hyprland, lines.join('\n'), account);
+    return this.createTextResult(`exec-once ${existing >= 0 ? 'updated' : 'added'} in ${paths.hyprland}`);
+  }
+
+  async handleAutostartRemove(args) {
+    await this.validateArgs(args, this.tools[2].inputSchema);
+
+    const { name, method = 'xdg' } = args;
+    this.checkName(name, ENTRY_NAME, 'entry');
+
+    const account = await lookupUser(args.user);
+    const paths = this.paths(account);
+
+    if (method === 'xdg') {
+      const file = path.join(paths.autostart, `${name}.desktop`);
+      if (!await fs.pathExists(file)) {
+        throw new Error(`No autostart entry named ${name}`);
+      }
+      await fs.remove(file);
+      return this.createTextResult(`Removed ${file}`);
+    }
+
+    const lines = await readLines(paths.hyprland);
+    const index = lines.findIndex(line => line === `${MARKER} ${name}`);
+    if (index < 0) {
+      throw new Error(`No exec-once entry named ${name} in ${paths.hyprland}`);
+    }
+    lines.splice(index, /^\s*exec-once\s*=/.test(lines[index + 1] || '') ? 2 : 1);
+    await writeUserFile(paths.hyprland, lines.join('\n'), account);
+    return this.createTextResult(`Removed exec-once ${name} from ${paths.hyprland}`);
+  }
+
+  async handleEnvList(args) {
+    await this.validateArgs(args, this.tools[3].inputSchema);
+
+    const account = await lookupUser(args.user);
+    const paths = this.paths(account);
+    const variables = [];
+
+    // environment.d files apply in lexical order
+    for (const file of (await fs.readdir(paths.environmentD).catch(() => [])).filter(name => name.endsWith('.conf')).sort()) {
+      for (const line of await readLines(path.join(paths.environmentD, file))) {
+        const match = line.match(/^\s*([A-Za-z_][A-Za-z0-9_]*)=(.*)$/);
+        if (match) {
+          variables.push({ method: 'environment.d', name: match[1], value: match[2], file: path.join(paths.environmentD, file) });
+        }
+      }
+    }
+
+    for (const line of await readLines(paths.hyprland)) {
+      const match = line.match(/^\s*env\s*=\s*([^,]+),(.*)$/);
+      if (match) {
+        variables.push({ method: 'hyprland', name: match[1].trim(), value: match[2].trim(), file: paths.hyprland });
+      }
+    }
+
+    return this.createTextResult(JSON.stringify(variables, null, 2), { count: variables.length });
+  }
+
+  async handleEnvSet(args) {
+    await this.validateArgs(args, this.tools[4].inputSchema);
+
+    const { name, value, method = 'environment.d' } = args;
+    this.checkName(name, ENV_NAME, 'variable');
+    this.checkLine('value', value);
+
+    const account = await lookupUser(args.user);
+    const paths = this.paths(account);
+    const file = method === 'hyprland' ? paths.hyprland : path.join(paths.environmentD, ENV_FILE);
+    const line = method === 'hyprland' ? `env = ${name},${value}` : `${name}=${value}`;
+    const pattern = method === 'hyprland'
+      ? new RegExp(`^\\s*env\\s*=\\s*${name}\\s*,`)
+      : new RegExp(`^\\s*${name}=`);
+
+    await writeUserFile(file, upsertLine(await readLines(file), pattern, line).join('\n'), account);
+    return this.createTextResult(`${name} set in ${file}; takes effect at next login`);
+  }
+
+  async handleEnvUnset(args) {
+    await this.validateArgs(args, this.tools[5].inputSchema);
+
+    const { name, method = 'environment.d' } = args;
+    this.checkName(name, ENV_NAME, 'variable');
+
+    const account = await lookupUser(args.user);
+    const paths = this.paths(account);
+
+    const files = method === 'hyprland'
+      ? [paths.hyprland]
+      : (await fs.readdir(paths.environmentD).catch(() => []))
+        .filter(file => file.endsWith('.conf'))
+        .map(file => path.join(paths.environmentD, file));
+    const pattern = method === 'hyprland'
+      ? new RegExp(`^\\s*env\\s*=\\s*${name}\\s*,`)
+      : new RegExp(`^\\s*${name}=`);
+
+    const changed = [];
+    for (const file of files) {
+      const lines = await readLines(file);
+      const kept = lines.filter(line => !pattern.test(line));
+      if (kept.length !== lines.length) {
+        await writeUserFile(file, kept.join('\n'), account);
+        changed.push(file);
+      }
+    }
+
+    if (changed.length === 0) {
+      throw new Error(`${name} is not set via ${method}`);
+    }
+    return this.createTextResult(`${name} removed from ${changed.join(', ')}`);
+  }
+}
@@ -0,0 +1,341 @@
+import { BasePlugin } from './base-plugin.js';
+import { CommandExecutor } from '../system/command-executor.js';
+import { Git } from '../system/git.js';
+import { LockKeys } from '../security/lock-manager.js';
+import { lookupUser } from '../system/user-account.js';
+import { readLines, writeUserFile, upsertLine } from '../system/user-files.js';
+import { previewDiff } from '../system/kernel-params.js';
+import {
+  SHELLS,
+  OH_MY_ZSH,
+  DEFAULT_ALIASES,
+  STARTUP_ERROR,
+  checkAliases,
+  renderShellConfig,
+  sourceLine,
+  shellPaths
+} from '../system/shell-config.js';
+import fs from 'fs-extra';
+import path from 'path';
+
+const VERIFY_TIMEOUT = 15000;
+
+// Login shells and their interactive config; every change is tried in a real terminal first
+export class ShellPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('shell', config, logger, security);
+    this.description = 'Login shell, shell frameworks and interactive shell config';
+    this.dependencies = ['pacman', 'usermod', 'script'];
+
+    this.commandExecutor = new CommandExecutor(
+      config.get?.('security') || config.security || {},
+      logger,
+      security
+    );
+    this.git = new Git(this.commandExecutor, logger);
+
+    this.initializeTools();
+  }
+
+  initializeTools() {
+    const user = {
+      type: 'string',
+      description: 'User whose shell is configured'
+    };
+    const shell = {
+      type: 'string',
+      enum: Object.keys(SHELLS)
+    };
+
+    this.tools = [
+      this.createTool(
+        'shell_status',
+        'Show a user\'s login shell, installed shells, frameworks and the managed config',
+        {
+          type: 'object',
+          properties: { user },
+          required: ['user']
+        }
+      ),
+
+      this.createTool(
+        'shell_setup',
+        'Install a shell with a curated config or framework (aliases, prompt, completions), verified in a terminal',
+        {
+          type: 'object',
+          properties: {
+            user,
+            shell,
+            framework: {
+              type: 'string',
+              enum: ['none', 'oh-my-zsh'],
+              description: 'oh-my-zsh is cloned into ~/.oh-my-zsh (zsh only)',
+              default: 'none'
+            },
+            prompt: {
+              type: 'string',
+              enum: ['default', 'starship'],
+              default: 'default'
+            },
+            aliases: {
+              type: 'object',
+              description: 'name -> command; added to the default ls/ll/la/grep aliases'
+            },
+            setLogin: {
+              type: 'boolean',
+              description: 'Make it the login shell once it starts cleanly',
+              default: false
+            },
+            dryRun: {
+              type: 'boolean',
+              default: false
+            }
+          },
+          required: ['user', 'shell']
+        }
+      ),
+
+      this.createTool(
+        'shell_change',
+        'Change a user\'s login shell after checking it is installed, listed in /etc/shells and starts cleanly',
+        {
+          type: 'object',
+          properties: {
+            user,
+            shell: {
+              type: 'string',
+              description: `${Object.keys(SHELLS).join(', ')} or an absolute path listed in /etc/shells`
+            }
+          },
+          required: ['user', 'shell']
+        }
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'shell_status':
+          return this.handleStatus(args);
+        case 'shell_setup':
+          return this.handleSetup(args);
+        case 'shell_change':
+          return this.handleChange(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  getToolLocks(toolName, args = {}) {
+    return this.affectsPackages(toolName, args) ? [LockKeys.pacmanDb()] : [];
+  }
+
+  affectsPackages(toolName, args = {}) {
+    return toolName === 'shell_setup' && !args.dryRun;
+  }
+
+  isIdempotent() {
+    return true;
+  }
+
+  isReadOnly(toolName, args = {}) {
+    return ['shell_status'].includes(toolName) || super.isReadOnly(toolName, args);
+  }
+
+  async getAffectedPaths(toolName, args = {}) {
+    if (args.dryRun || !args.user) {
+      return [];
+    }
+    try {
+      const account = await lookupUser(args.user);
+      const login = toolName === 'shell_change' || args.setLogin ? ['/etc/passwd'] : [];
+      if (toolName === 'shell_setup' && SHELLS[args.shell]) {
+        const paths = shellPaths(args.shell, account);
+        return [paths.managed, ...(paths.rc ? [paths.rc] : []), ...login];
+      }
+      return login;
+    } catch {
+      return [];
+    }
+  }
+
+  async handleStatus(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const account = await lookupUser(args.user);
+    const listed = await this.listedShells();
+    const shells = {};
+    for (const [name, definition] of Object.entries(SHELLS)) {
+      const paths = shellPaths(name, account);
+      shells[name] = {
+        installed: await fs.pathExists(definition.path),
+        inEtcShells: listed.includes(definition.path),
+        managedConfig: await fs.pathExists(paths.managed) ? paths.managed : null
+      };
+    }
+
+    return this.createTextResult(JSON.stringify({
+      user: account.name,
+      loginShell: account.shell,
+      shells,
+      frameworks: {
+        'oh-my-zsh': await fs.pathExists(path.join(account.home, OH_MY_ZSH.dir))
+      },
+      starship: await fs.pathExists('/usr/bin/starship')
+    }, null, 2));
+  }
+
+  async handleSetup(args) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+
+    const { shell, prompt = 'default', aliases = {}, setLogin = false, dryRun = false } = args;
+    const framework = args.framework === 'none' ? null : args.framework || null;
+    const definition = SHELLS[shell];
+    if (framework && !definition.frameworks.includes(framework)) {
+      throw new Error(`${framework} is not available for ${shell}`);
+    }
+    checkAliases(aliases);
+    const account = await lookupUser(args.user);
+
+    const packages = [definition.package, ...definition.extras, ...(prompt === 'starship' ? ['starship'] : [])];
+    const paths = shellPaths(shell, account);
+    const edits = [await this.planEdit(paths.managed, renderShellConfig(shell, { framework, prompt, aliases: { ...DEFAULT_ALIASES, ...aliases } }))];
+    if (paths.rc) {
+      const line = sourceLine(shell);
+      const lines = await readLines(paths.rc);
+      edits.push(await this.planEdit(paths.rc, upsertLine(lines, new RegExp(definition.managed.replace(/\./g, '\\.')), line).join('\n')));
+    }
+    const frameworkDir = framework ? path.join(account.home, OH_MY_ZSH.dir) : null;
+    const cloneFramework = frameworkDir && !await fs.pathExists(frameworkDir);
+
+    const steps = [
+      `pacman -S --needed --noconfirm ${packages.join(' ')}`,
+      ...(cloneFramework ? [`git clone --depth 1 ${OH_MY_ZSH.url} ${frameworkDir}`] : []),
+      ...edits.map(edit => previewDiff(edit.file, edit.before, edit.after)).filter(Boolean),
+      `verify: ${definition.path} -i in a terminal`,
+      ...(setLogin ? [`usermod --shell ${definition.path} ${account.name}`] : [])
+    ];
+    if (dryRun) {
+      return this.createTextResult(steps.join('\n'), { dryRun });
+    }
+
+    const install = await this.commandExecutor.executeWithSudo('pacman', ['-S', '--needed', '--noconfirm', ...packages], { timeout: 900000 });
+    if (!install.success) {
+      throw new Error(`pacman failed: ${install.stderr.trim().split('\n').slice(-5).join('\n')}`);
+    }
+    if (cloneFramework) {
+      await this.git.clone(OH_MY_ZSH.url, frameworkDir, { depth: 1, account });
+    }
+
+    for (const edit of edits) {
+      if (edit.before !== edit.after) {
+        await writeUserFile(edit.file, edit.after, account);
+      }
+    }
+
+    // A config that breaks the shell is put back the way it was
+    const verification = await this.verifyShell(account, definition.path);
+    if (!verification.ok) {
+      for (const edit of edits) {
+        if (edit.before === null) {
+          await fs.remove(edit.file);
+        } else if (edit.before !== edit.after) {
+          await writeUserFile(edit.file, edit.before, account);
+        }
+      }
+      throw new Error(`${shell} did not start cleanly with the new config, so it was reverted: ${verification.reason}`);
+    }
+
+    if (setLogin) {
+      await this.setLoginShell(account, definition.path);
+    }
+
+    this.logger.audit('shell_setup', { user: account.name, shell, framework, prompt, aliases: Object.keys(aliases), setLogin });
+    return this.createTextResult(JSON.stringify({
+      user: account.name,
+      shell,
+      config: paths.managed,
+      changed: edits.filter(edit => edit.before !== edit.after).map(edit => edit.file),
+      verified: true,
+      loginShell: setLogin ? definition.path : account.shell,
+      ...(setLogin ? { note: 'The new login shell applies to new logins' } : {})
+    }, null, 2));
+  }
+
+  async handleChange(args) {
+    await this.validateArgs(args, this.tools[2].inputSchema);
+
+    const account = await lookupUser(args.user);
+    const shellPath = SHELLS[args.shell] ? SHELLS[args.shell].path : args.shell;
+    if (!path.isAbsolute(shellPath)) {
+      throw new Error(`Unknown shell ${args.shell}; give one of ${Object.keys(SHELLS).join(', ')} or an absolute path`);
+    }
+    if (!await fs.pathExists(shellPath)) {
+      throw new Error(`${shellPath} is not installed${SHELLS[args.shell] ? `; install it with shell_setup or pacman -S ${SHELLS[args.shell].package}` : ''}`);
+    }
+    if (account.shell === shellPath) {
+      return this.createTextResult(`${account.name} already uses ${shellPath}`);
+    }
+
+    const verification = await this.verifyShell(account, shellPath);
+    if (!verification.ok) {
+      throw new Error(`${shellPath} did not start cleanly for ${account.name}, login shell left unchanged: ${verification.reason}`);
+    }
+    await this.setLoginShell(account, shellPath);
+
+    this.logger.audit('shell_change', { user: account.name, from: account.shell, to: shellPath });
+    return this.createTextResult(`Login shell of ${account.name}: ${account.shell} -> ${shellPath} (applies to new logins)`, { shell: shellPath });
+  }
+
+  async planEdit(file, after) {
+    const before = await fs.readFile(file, 'utf8').catch(() => null);
+    return { file, before, after };
+  }
+
+  async listedShells() {
+    return (await readLines('/etc/shells')).map(line => line.trim()).filter(line => line && !line.startsWith('#'));
+  }
+
+  // login(1) and chsh refuse shells missing from /etc/shells, so check before switching
+  async setLoginShell(account, shellPath) {
+    if (!(await this.listedShells()).includes(shellPath)) {
+      throw new Error(`${shellPath} is not listed in /etc/shells; its package normally adds it`);
+    }
+    const result = await this.commandExecutor.executeWithSudo('usermod', ['--shell', shellPath, account.name], { retry: false });
+    if (!result.success) {
+      throw new Error(`usermod --shell failed: ${result.stderr.trim()}`);
+    }
+  }
+
+  // Starts an interactive shell as the user inside a pseudo-terminal (script from
+  // util-linux), so rc files load as they would in a terminal emulator
+  async verifyShell(account, shellPath) {
+    const command = `${shellPath} -i -c 'echo mcp-shell-ok'`;
+    const scriptArgs = ['-q', '-e', '-c', command, '/dev/null'];
+    const env = {
+      ...process.env,
+      HOME: account.home,
+      USER: account.name,
+      SHELL: shellPath,
+      TERM: 'xterm-256color'
+    };
+    const result = process.getuid && process.getuid() === 0 && account.uid !== 0
+      ? await this.commandExecutor.execute('runuser', ['-u', account.name, '--', 'script', ...scriptArgs], { env, timeout: VERIFY_TIMEOUT, retry: false })
+      : await this.commandExecutor.execute('script', scriptArgs, { env, timeout: VERIFY_TIMEOUT, retry: false });
+
+    const output = `${result.stdout}\n${result.stderr}`;
+    if (!result.success) {
+      return { ok: false, reason: `exit code ${result.exitCode}${output.trim() ? `: ${output.trim().split('\n').slice(-5).join(' | ')}` : ''}` };
+    }
+    if (!output.includes('mcp-shell-ok')) {
+      return { ok: false, reason: 'the shell never ran the test command (interactive prompt or hang?)' };
+    }
+    const errors = output.split('\n').filter(line => STARTUP_ERROR.test(line));
+    if (errors.length > 0) {
+      return { ok: false, reason: errors.slice(0, 5).join(' | ').trim() };
+    }
+    return { ok: true };
+  }
+}
@@ -0,0 +1,399 @@
+import { BasePlugin, pluginConfig } from './base-plugin.js';
+import { CommandExecutor } from '../system/command-executor.js';
+import { getContext } from '../core/request-context.js';
+import {
+  BootCmdline,
+  normalizeSysctlKey,
+  parseCmdline,
+  previewDiff,
+  readPersistedSysctl,
+  setSysctlLines
+} from '../system/kernel-params.js';
+import fs from 'fs-extra';
+import path from 'path';
+
+const PARAM_PATTERN = '^[a-zA-Z0-9_.-]+(=("[^"]*"|\\S*))?$';
+
+// Runtime sysctl values, their persistence in sysctl.d, and the kernel command line.
+// Changes are previewed as diffs, and the automatic snapshot taken before each one is
+// what kernel_params_rollback restores.
+export class SysctlPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('sysctl', config, logger, security);
+    this.description = 'sysctl values and kernel command line parameters';
+    const settings = pluginConfig(config, 'sysctl');
+    this.dropInFile = settings.dropInFile || '/etc/sysctl.d/90-mcp-arch-linux.conf';
+
+    this.commandExecutor = new CommandExecutor(
+      config.get?.('security') || config.security || {},
+      logger,
+      security
+    );
+    this.bootCmdline = new BootCmdline(this.commandExecutor, logger, { bootloader: settings.bootloader || 'auto' });
+    this.dependencies = ['sysctl', 'bootctl', 'grub-mkconfig', 'mkinitcpio'];
+    this.optionalDependencies = ['bootctl', 'grub-mkconfig', 'mkinitcpio'];
+
+    this.initializeTools();
+    this.initializeResources();
+  }
+
+  initializeTools() {
+    this.tools = [
+      this.createTool(
+        'sysctl_get',
+        'Read sysctl values and where each one is persisted',
+        {
+          type: 'object',
+          properties: {
+            keys: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Keys or prefixes (e.g., vm.swappiness, net.ipv4.tcp)'
+            }
+          },
+          required: ['keys']
+        }
+      ),
+
+      this.createTool(
+        'sysctl_set',
+        'Set sysctl values now and persist them in sysctl.d, with a diff preview',
+        {
+          type: 'object',
+          properties: {
+            values: {
+              type: 'object',
+              description: 'Key to value, e.g. {"vm.swappiness": "10"}'
+            },
+            persist: {
+              type: 'boolean',
+              description: 'Write the values to plugins.sysctl.dropInFile so they survive a reboot',
+              default: true
+            },
+            dryRun: {
+              type: 'boolean',
+              description: 'Show the runtime changes and file diff without applying',
+              default: false
+            }
+          },
+          required: ['values']
+        }
+      ),
+
+      this.createTool(
+        'kernel_cmdline',
+        'List the running and configured kernel command line, or add/remove parameters and regenerate the boot configuration',
+        {
+          type: 'object',
+          properties: {
+            action: {
+              type: 'string',
+              enum: ['list', 'add', 'remove'],
+              default: 'list'
+            },
+            params: {
+              type: 'array',
+              items: { type: 'string', pattern: PARAM_PATTERN },
+              description: 'add: name or name=value (replaces an existing value); remove: name (any value) or name=value'
+            },
+            dryRun: {
+              type: 'boolean',
+              description: 'Show the diff without writing or regenerating',
+              default: false
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'kernel_params_rollback',
+        'Undo a sysctl_set or kernel_cmdline change from its automatic snapshot and re-apply the restored settings',
+        {
+          type: 'object',
+          properties: {
+            operationId: {
+              type: 'string',
+              description: 'Operation to undo (default: the most recent sysctl_set or kernel_cmdline change)'
+            },
+            dryRun: {
+              type: 'boolean',
+              default: false
+            }
+          }
+        }
+      )
+    ];
+  }
+
+  initializeResources() {
+    this.resources = [
+      this.createResource(
+        'kernel://cmdline',
+        'Kernel Command Line',
+        'Parameters the running kernel booted with, those configured for the next boot, and the difference',
+        'application/json'
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'sysctl_get':
+          return this.handleGet(args);
+        case 'sysctl_set':
+          return this.handleSet(args);
+        case 'kernel_cmdline':
+          return this.handleCmdline(args);
+        case 'kernel_params_rollback':
+          return this.handleRollback(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  async readResource(uri) {
+    return this.withErrorHandling(async () => {
+      switch (uri) {
+        case 'kernel://cmdline':
+          return { content: JSON.stringify(await this.cmdlineStatus(), null, 2) };
+        default:
+          throw new Error(`Unknown resource: ${uri}`);
+      }
+    }, 'readResource');
+  }
+
+  isIdempotent(toolName, args = {}) {
+    if (toolName === 'sysctl_get') {
+      return true;
+    }
+    if (toolName === 'kernel_cmdline') {
+      return ['list', undefined].includes(args.action) || Boolean(args.dryRun);
+    }
+    return Boolean(args.dryRun);
+  }
+
+  isReadOnly(toolName, args = {}) {
+    if (toolName === 'sysctl_get') {
+      return true;
+    }
+    if (toolName === 'kernel_cmdline') {
+      return ['list', undefined].includes(args.action) || Boolean(args.dryRun);
+    }
+    return super.isReadOnly(toolName, args);
+  }
+
+  async getAffectedPaths(toolName, args = {}) {
+    if (args.dryRun) {
+      return [];
+    }
+    if (toolName === 'sysctl_set' && args.persist !== false) {
+      return [this.dropInFile];
+    }
+    if (toolName === 'kernel_cmdline' && ['add', 'remove'].includes(args.action)) {
+      return this.bootCmdline.affectedPaths().catch(() => []);
+    }
+    return [];
+  }
+
+  async handleGet(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const keys = args.keys.map(normalizeSysctlKey);
+    const runtime = await this.readRuntime();
+    const persisted = await readPersistedSysctl();
+
+    const values = {};
+    for (const [key, value] of runtime) {
+      if (keys.some(wanted => key === wanted || key.startsWith(`${wanted}.`))) {
+        values[key] = { value, persisted: persisted.get(key) || null };
+      }
+    }
+    if (Object.keys(values).length === 0) {
+      throw new Error(`No sysctl keys match ${keys.join(', ')}`);
+    }
+    return this.createTextResult(JSON.stringify(values, null, 2), { count: Object.keys(values).length });
+  }
+
+  async handleSet(args) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+
+    const { persist = true, dryRun = false } = args;
+    const values = {};
+    for (const [key, value] of Object.entries(args.values)) {
+      const text = String(value);
+      if (/[\n#;]/.test(text)) {
+        throw new Error(`Invalid value for ${key}: ${text}`);
+      }
+      values[normalizeSysctlKey(key)] = text.trim();
+    }
+    if (Object.keys(values).length === 0) {
+      throw new Error('values is empty');
+    }
+
+    const runtime = await this.readRuntime();
+    const changes = Object.entries(values).map(([key, value]) => {
+      if (!runtime.has(key)) {
+        throw new Error(`Unknown sysctl key: ${key}`);
+      }
+      return { key, from: runtime.get(key), to: value };
+    });
+
+    const before = await fs.readFile(this.dropInFile, 'utf8').catch(() => null);
+    const after = persist ? setSysctlLines(before || '', values) : before;
+    const diff = persist ? previewDiff(this.dropInFile, before, after) : '';
+
+    // A file sorting after the drop-in would win at the next boot
+    const persisted = await readPersistedSysctl();
+    const overridden = persist ? changes
+      .map(change => ({ key: change.key, file: persisted.get(change.key)?.file }))
+      .filter(entry => entry.file && entry.file !== this.dropInFile &&
+        path.basename(entry.file) > path.basename(this.dropInFile)) : [];
+
+    const preview = [
+      ...changes.map(change => `${change.key}: ${change.from} -> ${change.to}`),
+      ...overridden.map(entry => `warning: ${entry.key} is also set in ${entry.file}, which is applied later`),
+      diff ? `\n${diff}` : null
+    ].filter(Boolean).join('\n');
+
+    if (dryRun) {
+      return this.createTextResult(preview, { dryRun, changes, diff });
+    }
+
+    for (const change of changes) {
+      const result = await this.commandExecutor.executeWithSudo('sysctl', ['-w', `${change.key}=${change.to}`], { retry: false });
+      if (!result.success) {
+        throw new Error(`sysctl -w ${change.key}=${change.to} failed: ${result.stderr.trim()}`);
+      }
+    }
+    if (persist && diff) {
+      await fs.ensureDir(path.dirname(this.dropInFile));
+      await fs.writeFile(this.dropInFile, after, { mode: 0o644 });
+    }
+
+    this.logger.audit('sysctl_set', { values, persist });
+    return this.createTextResult(`${preview}\n\nUndo with kernel_params_rollback`, {
+      changes,
+      diff,
+      persisted: persist,
+      operationId: getContext().operationId || null
+    });
+  }
+
+  async handleCmdline(args) {
+    await this.validateArgs(args, this.tools[2].inputSchema);
+
+    const { action = 'list', params = [], dryRun = false } = args;
+    if (action === 'list') {
+      return this.createTextResult(JSON.stringify(await this.cmdlineStatus(), null, 2));
+    }
+    if (params.length === 0) {
+      throw new Error(`params required for ${action}`);
+    }
+    if (params.some(param => /^(root|rw|ro|initrd)(=|$)/.test(param)) && action === 'remove') {
+      throw new Error('Refusing to remove root, rw/ro or initrd; the system would not boot');
+    }
+
+    const current = await this.bootCmdline.read();
+    const changes = this.bootCmdline.plan(current, action === 'add' ? { add: params } : { remove: params });
+    const diff = changes.map(change => previewDiff(change.path, change.before, change.after)).join('\n');
+
+    if (changes.length === 0) {
+      return this.createTextResult(`Nothing to change in the ${current.type} configuration`, { bootloader: current.type, changed: false });
+    }
+    if (dryRun) {
+      return this.createTextResult(diff, { bootloader: current.type, dryRun, diff });
+    }
+
+    for (const change of changes) {
+      await fs.writeFile(change.path, change.after);
+    }
+    const regenerated = await this.bootCmdline.regenerate(current.type);
+
+    this.logger.audit('kernel_cmdline', { action, params, bootloader: current.type });
+    return this.createTextResult(
+      `${diff}\n\n${regenerated ? `Ran ${regenerated}. ` : ''}Takes effect at the next boot; undo with kernel_params_rollback`,
+      {
+        bootloader: current.type,
+        files: changes.map(change => change.path),
+        regenerated,
+        operationId: getContext().operationId || null
+      }
+    );
+  }
+
+  async handleRollback(args) {
+    await this.validateArgs(args, this.tools[3].inputSchema);
+
+    const { operationId = null, dryRun = false } = args;
+    const snapshot = (await this.security.listSnapshots()).find(candidate =>
+      ['sysctl_set', 'kernel_cmdline'].includes(candidate.operation?.tool) &&
+      (!operationId || candidate.operation.id === operationId));
+    if (!snapshot) {
+      throw new Error(operationId
+        ? `No sysctl_set or kernel_cmdline snapshot for operation ${operationId}`
+        : 'No sysctl_set or kernel_cmdline snapshot to roll back');
+    }
+
+    const plan = await this.security.restoreSnapshot(snapshot.id, { dryRun });
+    const metadata = { snapshotId: snapshot.id, operation: snapshot.operation, dryRun, plan };
+    if (dryRun) {
+      return this.createTextResult(JSON.stringify(plan, null, 2), metadata);
+    }
+
+    // Restoring the files is only half of it: reload sysctl.d or rebuild the boot config
+    let reapplied;
+    if (snapshot.operation.tool === 'sysctl_set') {
+      const result = await this.commandExecutor.executeWithSudo('sysctl', ['--system'], { retry: false });
+      if (!result.success) {
+        throw new Error(`Files restored, but sysctl --system failed: ${result.stderr.trim()}`);
+      }
+      reapplied = 'sysctl --system';
+    } else {
+      reapplied = await this.bootCmdline.regenerate(await this.bootCmdline.detect());
+    }
+
+    this.logger.audit('kernel_params_rollback', { snapshotId: snapshot.id, operation: snapshot.operation });
+    return this.createTextResult(
+      `Rolled back ${snapshot.operation.tool} (operation ${snapshot.operation.id}) from snapshot ${snapshot.id}${reapplied ? `; ran ${reapplied}` : ''}`,
+      { ...metadata, reapplied }
+    );
+  }
+
+  async readRuntime() {
+    const result = await this.commandExecutor.execute('sysctl', ['-a'], { retry: false });
+    if (!result.stdout) {
+      throw new Error(`sysctl -a failed: ${result.stderr.trim()}`);
+    }
+    // Some keys are unreadable without root; sysctl -a still prints the rest
+    return new Map(result.stdout.split('\n')
+      .map(line => line.match(/^([^=\s]+)\s*=\s?(.*)$/))
+      .filter(Boolean)
+      .map(match => [match[1], match[2].trim()]));
+  }
+
+  async cmdlineStatus() {
+    const running = parseCmdline((await fs.readFile('/proc/cmdline', 'utf8')).trim());
+    let configured = null;
+    try {
+      configured = await this.bootCmdline.read();
+    } catch (error) {
+      return { running, configured: null, error: error.message };
+    }
+
+    // Entries differ in root= and initrd=, and GRUB adds root and rw itself, so those are
+    // left out of the comparison
+    const next = Array.from(new Set(configured.files.flatMap(file => file.params)));
+    const bootParam = /^(BOOT_IMAGE|initrd|root)=|^(rw|ro)$/;
+    return {
+      running,
+      bootloader: configured.type,
+      configured: configured.files.map(file => ({ file: file.path, params: file.params })),
+      pendingReboot: {
+        added: next.filter(param => !running.includes(param) && !bootParam.test(param)),
+        removed: running.filter(param => !next.includes(param) && !bootParam.test(param))
+      }
+    };
+  }
+}
@@ -1,16 +1,51 @@
-import { BasePlugin } from './base-plugin.js';
+import { BasePlugin, pluginConfig } from './base-plugin.js';
 import { CommandExecutor } from '../system/command-executor.js';
+import { getEnvironmentInfo } from '../system/environment-info.js';
+import { getToolResolver } from '../system/tool-resolver.js';
+import { LockKeys } from '../security/lock-manager.js';
+import { getQuotaRegistry } from '../system/disk-quota.js';
+import { PackageState } from '../system/package-state.js';
+import { SNAPSHOT_ID_PATTERN } from '../system/rollback-manager.js';
+import { SystemUpdate } from '../system/system-update.js';
+import { PacmanProgress } from '../system/pacman-progress.js';
+import { reportProgress } from '../core/request-context.js';
+import { renderUnit, normalizeUnitName, HARDENING_PRESETS } from '../system/unit-file.js';
+import { getTargetRegistry, targetPath, describeTarget } from '../system/target.js';
+import { StateApplier, normalizeState, describeChange } from '../system/state-apply.js';
+import { PacmanKeyring, KEYRING_DIR } from '../system/keyring.js';
+import { PACMAN_CONF, OPTION_TYPES, parsePacmanConf, parseRepos, setOptions, addRepo, removeRepo } from '../system/pacman-conf.js';
+import { previewDiff } from '../system/kernel-params.js';
+import fs from 'fs-extra';
+import os from 'os';
+import path from 'path';
 import si from 'systeminformation';
+import YAML from 'yaml';
+
+const TARGET_PROPERTY = {
+  type: 'string',
+  description: 'Where to act: host, chroot:<dir>, container:<machine> or a named target such as "install" (default: the target set with system_target)'
+};
 
 export class SystemPlugin extends BasePlugin {
   constructor(config, logger, security) {
     super('system', config, logger, security);
     this.description = 'System management and monitoring plugin';
     this.commandExecutor = new CommandExecutor(
-      config.security || {},
+      config.get?.('security') || config.security || {},
       logger,
       security
     );
+    this.environment = getEnvironmentInfo(logger);
+    this.systemUpdate = new SystemUpdate(
+      this.commandExecutor,
+      new PackageState(this.commandExecutor, logger),
+      logger,
+      { newsUrl: pluginConfig(config, 'system').newsFeed }
+    );
+    this.stateApplier = new StateApplier(this.commandExecutor, logger);
+    this.keyring = new PacmanKeyring(this.commandExecutor, logger);
+    this.dependencies = ['systemctl', 'pacman', 'journalctl', 'ps', 'kill', 'checkupdates'];
+    this.optionalDependencies = ['checkupdates'];
     
     this.initializeTools();
     this.initializeResources();
@@ -43,7 +78,8 @@ export class SystemPlugin extends BasePlugin {
               type: 'number',
               description: 'Timeout in milliseconds',
               default: 300000
-            }
+            },
+            target: TARGET_PROPERTY
           },
           required: ['command']
         }
@@ -78,7 +114,8 @@ export class SystemPlugin extends BasePlugin {
             service: {
               type: 'string',
               description: 'Service name (required for actions other than list)'
-            }
+            },
+            target: TARGET_PROPERTY
           },
           required: ['action']
         }
@@ -104,7 +141,8 @@ export class SystemPlugin extends BasePlugin {
               type: 'boolean',
               description: 'Skip confirmation prompts',
               default: false
-            }
+            },
+            target: TARGET_PROPERTY
           },
           required: ['action']
         }
@@ -123,7 +161,7 @@ export class SystemPlugin extends BasePlugin {
             files: {
               type: 'array',
               items: { type: 'string' },
-              description: 'Files to include in snapshot',
+              description: 'Files or directories to include in snapshot (directories are captured recursively)',
               default: [
                 '/etc/pacman.conf',
                 '/etc/fstab',
@@ -131,6 +169,28 @@ export class SystemPlugin extends BasePlugin {
                 '/etc/hosts',
                 '/etc/locale.conf'
               ]
+            },
+            include: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Glob patterns files inside snapshotted directories must match (e.g. "*.conf")',
+              default: []
+            },
+            exclude: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Glob patterns to skip inside snapshotted directories (e.g. "**/cache/**")',
+              default: []
+            },
+            compress: {
+              type: 'boolean',
+              description: 'Gzip file contents in the snapshot',
+              default: true
+            },
+            packages: {
+              type: 'boolean',
+              description: 'Also record installed packages and versions so they can be rolled back',
+              default: false
             }
           },
           required: ['description']
@@ -145,13 +205,115 @@ export class SystemPlugin extends BasePlugin {
           properties: {
             snapshotId: {
               type: 'string',
-              description: 'Snapshot ID to rollback to'
+              description: 'Snapshot ID to rollback to',
+              pattern: SNAPSHOT_ID_PATTERN
+            },
+            files: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Only restore these paths (directories include their contents, globs allowed); defaults to all files',
+              default: []
+            },
+            scope: {
+              type: 'string',
+              enum: ['all', 'files', 'services', 'packages'],
+              description: 'Restore everything, or only files, service states or packages',
+              default: 'all'
+            },
+            dryRun: {
+              type: 'boolean',
+              description: 'Return the planned changes without restoring anything',
+              default: false
             }
           },
           required: ['snapshotId']
         }
       ),
 
+      this.createTool(
+        'snapshot_delete',
+        'Delete a system snapshot, or prune snapshots according to the retention policy',
+        {
+          type: 'object',
+          properties: {
+            snapshotId: {
+              type: 'string',
+              description: 'Snapshot ID to delete',
+              pattern: SNAPSHOT_ID_PATTERN
+            },
+            prune: {
+              type: 'boolean',
+              description: 'Apply the retention policy instead of deleting a single snapshot',
+              default: false
+            },
+            dryRun: {
+              type: 'boolean',
+              description: 'With prune, only report which snapshots would be removed',
+              default: false
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'system_undo',
+        'Undo a previous tool call by restoring the snapshot taken automatically before it ran',
+        {
+          type: 'object',
+          properties: {
+            operationId: {
+              type: 'string',
+              description: 'Audit operation ID to undo (defaults to the most recent snapshotted operation)',
+              pattern: SNAPSHOT_ID_PATTERN
+            },
+            dryRun: {
+              type: 'boolean',
+              description: 'Show what would be restored without changing anything',
+              default: false
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'etc_history',
+        'Show the version history of /etc, including the operation that made each change',
+        {
+          type: 'object',
+          properties: {
+            file: {
+              type: 'string',
+              description: 'Only show commits touching this file (relative to /etc or absolute)'
+            },
+            limit: {
+              type: 'number',
+              description: 'Maximum number of commits',
+              default: 20
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'etc_revert',
+        'Revert a single /etc commit, leaving later changes in place',
+        {
+          type: 'object',
+          properties: {
+            commit: {
+              type: 'string',
+              description: 'Commit hash from etc_history'
+            },
+            dryRun: {
+              type: 'boolean',
+              description: 'Show the reverse diff without changing anything',
+              default: false
+            }
+          },
+          required: ['commit']
+        }
+      ),
+
       this.createTool(
         'system_process',
         'Manage system processes',
@@ -179,6 +341,347 @@ export class SystemPlugin extends BasePlugin {
           },
           required: ['action']
         }
+      ),
+
+      this.createTool(
+        'update_system',
+        'Full system upgrade with Arch news check, keyring refresh, automatic snapshot, .pacnew and reboot detection',
+        {
+          type: 'object',
+          properties: {
+            acknowledgeNews: {
+              type: 'boolean',
+              description: 'Proceed even though Arch news since the last upgrade asks for manual intervention',
+              default: false
+            },
+            refreshKeyring: {
+              type: 'boolean',
+              description: 'Update archlinux-keyring before the other packages',
+              default: true
+            },
+            dryRun: {
+              type: 'boolean',
+              description: 'Only check news and list pending updates',
+              default: false
+            }
+          }
+        },
+        { timeout: 3600000 }
+      ),
+
+      this.createTool(
+        'create_systemd_unit',
+        'Write a service unit from structured fields, verify it with systemd-analyze, daemon-reload and optionally enable it',
+        {
+          type: 'object',
+          properties: {
+            name: {
+              type: 'string',
+              description: 'Unit name (".service" is appended if missing)'
+            },
+            description: {
+              type: 'string',
+              description: 'Unit description'
+            },
+            execStart: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'ExecStart command lines (more than one only for Type=oneshot)'
+            },
+            execStartPre: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'ExecStartPre command lines'
+            },
+            type: {
+              type: 'string',
+              enum: ['simple', 'exec', 'forking', 'oneshot', 'notify'],
+              default: 'simple'
+            },
+            user: {
+              type: 'string',
+              description: 'Account the service runs as'
+            },
+            group: {
+              type: 'string'
+            },
+            workingDirectory: {
+              type: 'string'
+            },
+            environment: {
+              type: 'object',
+              description: 'Environment variables',
+              additionalProperties: { type: 'string' }
+            },
+            restart: {
+              type: 'string',
+              enum: ['no', 'on-success', 'on-failure', 'on-abnormal', 'on-abort', 'always'],
+              default: 'on-failure'
+            },
+            restartSec: {
+              type: 'string',
+              description: 'Delay before restarting (e.g. "5s")'
+            },
+            after: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Units to order after (e.g. network-online.target)'
+            },
+            wants: {
+              type: 'array',
+              items: { type: 'string' }
+            },
+            wantedBy: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Install targets',
+              default: ['multi-user.target']
+            },
+            hardening: {
+              type: 'string',
+              enum: Object.keys(HARDENING_PRESETS),
+              description: 'Sandboxing preset',
+              default: 'basic'
+            },
+            serviceOptions: {
+              type: 'object',
+              description: 'Extra [Service] directives, overriding the preset (e.g. {"ReadWritePaths": "/var/lib/app"})',
+              additionalProperties: { type: 'string' }
+            },
+            enable: {
+              type: 'boolean',
+              description: 'Enable the unit after writing it',
+              default: false
+            },
+            start: {
+              type: 'boolean',
+              description: 'Start (or restart) the unit after writing it',
+              default: false
+            },
+            overwrite: {
+              type: 'boolean',
+              description: 'Replace an existing unit file',
+              default: false
+            },
+            dryRun: {
+              type: 'boolean',
+              description: 'Render and verify without installing',
+              default: false
+            },
+            target: TARGET_PROPERTY
+          },
+          required: ['name', 'description', 'execStart']
+        }
+      ),
+
+      this.createTool(
+        'check_arch_news',
+        'Fetch the Arch Linux news feed and flag items published since the last system upgrade',
+        {
+          type: 'object',
+          properties: {
+            unreadOnly: {
+              type: 'boolean',
+              description: 'Only return items newer than the last full upgrade',
+              default: true
+            },
+            limit: {
+              type: 'number',
+              description: 'Maximum number of items',
+              default: 10
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'merge_pacnew',
+        'Merge a .pacnew file into the live config: the connected model proposes a merge (via sampling), or pass your own',
+        {
+          type: 'object',
+          properties: {
+            path: {
+              type: 'string',
+              description: 'Config file or its .pacnew (e.g., /etc/pacman.conf)'
+            },
+            merged: {
+              type: 'string',
+              description: 'Merged content to use instead of asking the client\'s model'
+            },
+            instructions: {
+              type: 'string',
+              description: 'Extra guidance for the proposed merge (e.g., "keep my mirror settings")'
+            },
+            apply: {
+              type: 'boolean',
+              description: 'Write the merge to the config and remove the .pacnew',
+              default: false
+            }
+          },
+          required: ['path']
+        }
+      ),
+
+      this.createTool(
+        'system_target',
+        'List, define or select the targets system tools act on: the live host, a chroot such as the install mount, or a container',
+        {
+          type: 'object',
+          properties: {
+            action: {
+              type: 'string',
+              enum: ['list', 'set', 'define', 'remove'],
+              description: 'set makes target the default; define and remove manage named targets',
+              default: 'list'
+            },
+            target: {
+              type: 'string',
+              description: 'host, chroot:<dir>, container:<machine> or a named target'
+            },
+            name: {
+              type: 'string',
+              description: 'Name to define or remove',
+              pattern: '^[a-zA-Z0-9][a-zA-Z0-9_-]*$'
+            }
+          }
+        }
+      ),
+
+      this.createTool(
+        'apply_state',
+        'Converge the system to a declared state (packages, users, files, services) and report what changed',
+        {
+          type: 'object',
+          properties: {
+            state: {
+              type: 'object',
+              description: 'packages {present, absent}, users [{name, groups, shell, home, system}], files [{path, content, mode, owner, group, state}], services {enabled, disabled, running, stopped}',
+              properties: {
+                packages: { type: ['object', 'array'] },
+                users: { type: 'array' },
+                files: { type: 'array' },
+                services: { type: ['object', 'array'] }
+              }
+            },
+            stateFile: {
+              type: 'string',
+              description: 'YAML or JSON file with the same structure; state overrides its top-level keys'
+            },
+            check: {
+              type: 'boolean',
+              description: 'Only report what would change',
+              default: false
+            },
+            target: TARGET_PROPERTY
+          }
+        }
+      ),
+
+      this.createTool(
+        'pacman_keyring',
+        'Show, initialize or refresh the pacman keyring, or locally sign a key',
+        {
+          type: 'object',
+          properties: {
+            action: {
+              type: 'string',
+              enum: ['status', 'init', 'refresh', 'sign'],
+              description: 'init: pacman-key --init and --populate; refresh: update archlinux-keyring and repopulate; sign: receive and locally sign keyId',
+              default: 'status'
+            },
+            keyId: {
+              type: 'string',
+              description: 'Key ID or fingerprint to sign',
+              pattern: '^(0x)?[0-9A-Fa-f]{8,40}$'
+            },
+            keyserver: {
+              type: 'string',
+              description: 'Keyserver to receive the key from (default: gpg.conf in the keyring)'
+            },
+            full: {
+              type: 'boolean',
+              description: 'refresh: also run pacman-key --refresh-keys (slow)',
+              default: false
+            },
+            target: TARGET_PROPERTY
+          }
+        }
+      ),
+
+      this.createTool(
+        'diagnose_signatures',
+        'Work out why pacman rejected a signature: keyring state, clock skew, outdated archlinux-keyring, expired or unknown keys',
+        {
+          type: 'object',
+          properties: {
+            error: {
+              type: 'string',
+              description: 'pacman\'s error output, so the keys and packages it names are checked'
+            },
+            target: TARGET_PROPERTY
+          }
+        }
+      ),
+
+      this.createTool(
+        'pacman_repos',
+        'List, add or remove pacman repositories (custom, self-hosted, multilib) and set pacman.conf options, checked with pacman-conf before saving',
+        {
+          type: 'object',
+          properties: {
+            action: {
+              type: 'string',
+              enum: ['list', 'add', 'remove', 'options'],
+              default: 'list'
+            },
+            name: {
+              type: 'string',
+              description: 'Repository name, e.g. multilib or a custom repository',
+              pattern: '^[A-Za-z0-9._-]+$'
+            },
+            servers: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'add: Server URLs ($repo and $arch are expanded by pacman); not needed to enable a shipped repository such as multilib'
+            },
+            include: {
+              type: 'string',
+              description: 'add: mirrorlist file to Include instead of (or besides) servers'
+            },
+            sigLevel: {
+              type: 'string',
+              description: 'add: SigLevel for the repository, e.g. "Required DatabaseOptional" (default: the global one)'
+            },
+            before: {
+              type: 'string',
+              description: 'add: insert before this repository instead of at the end; pacman uses the first repository that has a package'
+            },
+            keyId: {
+              type: 'string',
+              description: 'add: signing key of the repository to receive and locally sign first',
+              pattern: '^(0x)?[0-9A-Fa-f]{8,40}$'
+            },
+            keyserver: {
+              type: 'string',
+              description: 'Keyserver to receive keyId from (default: gpg.conf in the keyring)'
+            },
+            options: {
+              type: 'object',
+              description: `options: [options] settings to change, e.g. {"ParallelDownloads": 10, "Color": true, "IgnorePkg": ["linux"]}; false, null or [] comments a setting out. Supported: ${Object.keys(OPTION_TYPES).join(', ')}`
+            },
+            upgrade: {
+              type: 'boolean',
+              description: 'add/remove: run pacman -Syu afterwards so the sync databases match the repositories',
+              default: false
+            },
+            dryRun: {
+              type: 'boolean',
+              description: 'Only show the diff',
+              default: false
+            },
+            target: TARGET_PROPERTY
+          }
+        }
       )
     ];
   }
@@ -204,18 +707,57 @@ export class SystemPlugin extends BasePlugin {
         'application/json'
       ),
       this.createResource(
-        'system://snapshots',
-        'System Snapshots',
-        'List of available system snapshots',
+        'system://snapshots',
+        'System Snapshots',
+        'List of available system snapshots',
+        'application/json'
+      ),
+      this.createResource(
+        'system://processes',
+        'Running Processes',
+        'List of currently running processes',
+        'application/json'
+      ),
+      this.createResource(
+        'system://environment',
+        'Environment',
+        'Detected session type, compositor, init system, hardware vendors, virtualization and boot firmware',
+        'application/json'
+      ),
+      this.createResource(
+        'tools://dependencies',
+        'Tool Dependencies',
+        'External binaries each plugin resolved and where they were found',
+        'application/json'
+      ),
+      this.createResource(
+        'quota://warnings',
+        'Disk Quota Warnings',
+        'Snapshot and capture directory usage with any crossed quota thresholds',
         'application/json'
       ),
       this.createResource(
-        'system://processes',
-        'Running Processes',
-        'List of currently running processes',
+        'arch://news',
+        'Arch Linux News',
+        'Recent Arch news; items newer than the last upgrade are marked unread and manual-intervention items flagged',
         'application/json'
       )
     ];
+
+    this.resourceTemplates = [
+      this.createResourceTemplate(
+        'journal://{unit}/{lines}',
+        'Unit Journal',
+        'The last {lines} journal lines (at most 10000) of a systemd unit',
+        'text/plain'
+      ),
+      this.createResourceTemplate(
+        'pacman://package/{name}',
+        'Package Information',
+        'pacman -Qi for an installed package, or -Si from the sync databases',
+        'text/plain'
+      )
+    ];
   }
 
   async executeTool(toolName, args) {
@@ -233,8 +775,34 @@ export class SystemPlugin extends BasePlugin {
           return this.handleSystemSnapshot(args);
         case 'system_rollback':
           return this.handleSystemRollback(args);
+        case 'snapshot_delete':
+          return this.handleSnapshotDelete(args);
+        case 'system_undo':
+          return this.handleSystemUndo(args);
+        case 'etc_history':
+          return this.handleEtcHistory(args);
+        case 'etc_revert':
+          return this.handleEtcRevert(args);
         case 'system_process':
           return this.handleSystemProcess(args);
+        case 'update_system':
+          return this.handleUpdateSystem(args);
+        case 'create_systemd_unit':
+          return this.handleCreateSystemdUnit(args);
+        case 'check_arch_news':
+          return this.handleCheckArchNews(args);
+        case 'merge_pacnew':
+          return this.handleMergePacnew(args);
+        case 'system_target':
+          return this.handleSystemTarget(args);
+        case 'apply_state':
+          return this.handleApplyState(args);
+        case 'pacman_keyring':
+          return this.handlePacmanKeyring(args);
+        case 'diagnose_signatures':
+          return this.handleDiagnoseSignatures(args);
+        case 'pacman_repos':
+          return this.handlePacmanRepos(args);
         default:
           throw new Error(`Unknown tool: ${toolName}`);
       }
@@ -254,12 +822,185 @@ export class SystemPlugin extends BasePlugin {
           return this.getSnapshots();
         case 'system://processes':
           return this.getProcesses();
+        case 'system://environment':
+          return this.getEnvironment();
+        case 'tools://dependencies':
+          return this.getToolDependencies();
+        case 'quota://warnings':
+          return this.getQuotaWarnings();
+        case 'arch://news':
+          return this.getArchNews();
         default:
           throw new Error(`Unknown resource: ${uri}`);
       }
     }, 'readResource');
   }
 
+  async readResourceTemplate(uriTemplate, params) {
+    return this.withErrorHandling(async () => {
+      switch (uriTemplate) {
+        case 'journal://{unit}/{lines}':
+          return this.getUnitJournal(params);
+        case 'pacman://package/{name}':
+          return this.getPackageInfo(params);
+        default:
+          throw new Error(`Unknown resource template: ${uriTemplate}`);
+      }
+    }, 'readResourceTemplate');
+  }
+
+  getToolLocks(toolName, args = {}) {
+    if (this.affectsPackages(toolName, args)) {
+      return [LockKeys.pacmanDb(this.resolveTarget(args.target).root)];
+    }
+    // Package rollback runs pacman, so it contends with package tools
+    if (['system_rollback', 'system_undo'].includes(toolName) && !args.dryRun &&
+      ['all', 'packages', undefined].includes(args.scope)) {
+      return [LockKeys.pacmanDb()];
+    }
+    if (toolName === 'etc_revert' && !args.dryRun) {
+      return [LockKeys.gitRepo(this.security.etc.repoDir)];
+    }
+    // pacman verifies signatures against the keyring while it holds its lock
+    if (toolName === 'pacman_keyring' && ['init', 'sign'].includes(args.action)) {
+      return [LockKeys.pacmanDb(this.resolveTarget(args.target).root)];
+    }
+    // pacman reads its configuration at the start of every transaction
+    if (toolName === 'pacman_repos' && !['list', undefined].includes(args.action) && !args.dryRun) {
+      return [LockKeys.pacmanDb(this.resolveTarget(args.target).root)];
+    }
+    return [];
+  }
+
+  affectsPackages(toolName, args = {}) {
+    if (toolName === 'update_system') {
+      return !args.dryRun;
+    }
+    if (toolName === 'apply_state') {
+      return !args.check && Boolean(args.stateFile || args.state?.packages);
+    }
+    if (toolName === 'pacman_keyring') {
+      return args.action === 'refresh';
+    }
+    if (toolName === 'pacman_repos') {
+      return ['add', 'remove'].includes(args.action) && Boolean(args.upgrade) && !args.dryRun;
+    }
+    return toolName === 'system_package' && ['update', 'upgrade', 'install', 'remove'].includes(args.action);
+  }
+
+  isIdempotent(toolName, args = {}) {
+    switch (toolName) {
+      case 'system_info':
+      case 'etc_history':
+      case 'check_arch_news':
+        return true;
+      case 'system_target':
+        return ['list', undefined].includes(args.action);
+      case 'system_services':
+        return ['list', 'status'].includes(args.action);
+      case 'system_package':
+        return ['search', 'info'].includes(args.action);
+      case 'system_process':
+        return ['list', 'info'].includes(args.action);
+      case 'update_system':
+      case 'create_systemd_unit':
+        return Boolean(args.dryRun);
+      case 'apply_state':
+        return Boolean(args.check);
+      case 'pacman_keyring':
+        return ['status', undefined].includes(args.action);
+      case 'diagnose_signatures':
+        return true;
+      case 'pacman_repos':
+        return ['list', undefined].includes(args.action) || Boolean(args.dryRun);
+      default:
+        return false;
+    }
+  }
+
+  isReadOnly(toolName, args = {}) {
+    switch (toolName) {
+      case 'system_info':
+      case 'etc_history':
+      case 'check_arch_news':
+      case 'diagnose_signatures':
+        return true;
+      case 'system_target':
+        return ['list', undefined].includes(args.action);
+      case 'system_services':
+        return ['list', 'status'].includes(args.action);
+      case 'system_package':
+        return ['search', 'info'].includes(args.action);
+      case 'system_process':
+        return ['list', 'info'].includes(args.action);
+      case 'pacman_keyring':
+        return ['status', undefined].includes(args.action);
+      case 'apply_state':
+        return Boolean(args.check);
+      case 'pacman_repos':
+        return ['list', undefined].includes(args.action) || Boolean(args.dryRun);
+      case 'merge_pacnew':
+        return !args.apply;
+      default:
+        return super.isReadOnly(toolName, args);
+    }
+  }
+
+  hasReadOnlyCalls(toolName) {
+    return ['system_services', 'system_package', 'system_process', 'apply_state'].includes(toolName) ||
+      super.hasReadOnlyCalls(toolName);
+  }
+
+  isDestructive(toolName, args = {}) {
+    if (toolName === 'system_package') {
+      return args.action === 'remove';
+    }
+    return !args.dryRun && ['update_system', 'system_rollback', 'system_undo', 'etc_revert', 'snapshot_delete'].includes(toolName);
+  }
+
+  // Snapshots and rollbacks copy whole directory trees
+  inhibitsIdle(toolName, args = {}) {
+    return toolName === 'system_snapshot' || (['system_rollback', 'system_undo'].includes(toolName) && !args.dryRun);
+  }
+
+  async getAffectedPaths(toolName, args = {}) {
+    if (toolName === 'system_services' && ['enable', 'disable'].includes(args.action)) {
+      return [targetPath(this.resolveTarget(args.target), '/etc/systemd/system')];
+    }
+    if (toolName === 'create_systemd_unit' && !args.dryRun) {
+      return [targetPath(this.resolveTarget(args.target), '/etc/systemd/system')];
+    }
+    if (toolName === 'etc_revert' && !args.dryRun && args.commit) {
+      return this.security.etc.changedFiles(args.commit).catch(() => []);
+    }
+    if (toolName === 'apply_state' && !args.check) {
+      const state = await this.loadState(args).catch(() => null);
+      if (!state) {
+        return [];
+      }
+      const target = this.resolveTarget(args.target);
+      const files = state.files.map(file => file.path);
+      if (state.users.length > 0) {
+        files.push('/etc/passwd', '/etc/group', '/etc/shadow', '/etc/gshadow');
+      }
+      if (state.services.enabled.length > 0 || state.services.disabled.length > 0) {
+        files.push('/etc/systemd/system');
+      }
+      return files.map(file => targetPath(target, file));
+    }
+    if (toolName === 'pacman_keyring' && ['init', 'sign'].includes(args.action)) {
+      return [targetPath(this.resolveTarget(args.target), KEYRING_DIR)];
+    }
+    if (toolName === 'pacman_repos' && !['list', undefined].includes(args.action) && !args.dryRun) {
+      return [targetPath(this.resolveTarget(args.target), PACMAN_CONF), ...(args.keyId ? [targetPath(this.resolveTarget(args.target), KEYRING_DIR)] : [])];
+    }
+    if (toolName === 'merge_pacnew' && args.apply && args.path) {
+      const file = args.path.replace(/\.pacnew$/, '');
+      return [file, `${file}.pacnew`];
+    }
+    return [];
+  }
+
   async handleSystemExec(args) {
     await this.validateArgs(args, this.tools[0].inputSchema);
     
@@ -267,7 +1008,8 @@ export class SystemPlugin extends BasePlugin {
     
     const result = await this.commandExecutor.execute(command, cmdArgs, {
       requireRoot,
-      timeout
+      timeout,
+      target: this.resolveTarget(args.target)
     });
 
     return this.createTextResult(
@@ -310,26 +1052,33 @@ export class SystemPlugin extends BasePlugin {
     await this.validateArgs(args, this.tools[2].inputSchema);
     
     const { action, service } = args;
+    const target = this.resolveTarget(args.target);
+    
+    // Nothing runs inside a chroot, so only unit files can be inspected and linked there
+    if (target.type === 'chroot' && !['list', 'enable', 'disable'].includes(action)) {
+      throw new Error(`${action} needs a running systemd; ${describeTarget(target)} only supports list, enable and disable`);
+    }
     
     let result;
     switch (action) {
       case 'list':
-        result = await this.commandExecutor.execute('systemctl', ['list-units', '--type=service']);
+        result = await this.commandExecutor.execute('systemctl', target.type === 'chroot' ?
+          ['list-unit-files', '--type=service'] : ['list-units', '--type=service'], { target });
         break;
       case 'status':
         if (!service) throw new Error('Service name required for status action');
-        result = await this.commandExecutor.execute('systemctl', ['status', service]);
+        result = await this.commandExecutor.execute('systemctl', ['status', service], { target });
         break;
       case 'start':
       case 'stop':
       case 'restart':
         if (!service) throw new Error(`Service name required for ${action} action`);
-        result = await this.commandExecutor.executeWithSudo('systemctl', [action, service]);
+        result = await this.commandExecutor.executeWithSudo('systemctl', [action, service], { target });
         break;
       case 'enable':
       case 'disable':
         if (!service) throw new Error(`Service name required for ${action} action`);
-        result = await this.commandExecutor.executeWithSudo('systemctl', [action, service]);
+        result = await this.commandExecutor.executeWithSudo('systemctl', [action, service], { target });
         break;
       default:
         throw new Error(`Unknown action: ${action}`);
@@ -342,6 +1091,7 @@ export class SystemPlugin extends BasePlugin {
     await this.validateArgs(args, this.tools[3].inputSchema);
     
     const { action, packages = [], noconfirm = false } = args;
+    const target = this.resolveTarget(args.target);
     
     const pacmanArgs = [];
     if (noconfirm) pacmanArgs.push('--noconfirm');
@@ -349,26 +1099,26 @@ export class SystemPlugin extends BasePlugin {
     let result;
     switch (action) {
       case 'update':
-        result = await this.commandExecutor.executeWithSudo('pacman', ['-Sy', ...pacmanArgs]);
+        result = await this.commandExecutor.executeWithSudo('pacman', ['-Sy', ...pacmanArgs], { target });
         break;
       case 'upgrade':
-        result = await this.commandExecutor.executeWithSudo('pacman', ['-Syu', ...pacmanArgs]);
+        result = await this.commandExecutor.executeWithSudo('pacman', ['-Syu', ...pacmanArgs], { target });
         break;
       case 'install':
         if (packages.length === 0) throw new Error('Package names required for install');
-        result = await this.commandExecutor.executeWithSudo('pacman', ['-S', ...pacmanArgs, ...packages]);
+        result = await this.commandExecutor.executeWithSudo('pacman', ['-S', ...pacmanArgs, ...packages], { target });
         break;
       case 'remove':
         if (packages.length === 0) throw new Error('Package names required for remove');
-        result = await this.commandExecutor.executeWithSudo('pacman', ['-R', ...pacmanArgs, ...packages]);
+        result = await this.commandExecutor.executeWithSudo('pacman', ['-R', ...pacmanArgs, ...packages], { target });
         break;
       case 'search':
         if (packages.length === 0) throw new Error('Search term required');
-        result = await this.commandExecutor.execute('pacman', ['-Ss', packages[0]]);
+        result = await this.commandExecutor.execute('pacman', ['-Ss', packages[0]], { target });
         break;
       case 'info':
         if (packages.length === 0) throw new Error('Package name required for info');
-        result = await this.commandExecutor.execute('pacman', ['-Si', packages[0]]);
+        result = await this.commandExecutor.execute('pacman', ['-Si', packages[0]], { target });
         break;
       default:
         throw new Error(`Unknown action: ${action}`);
@@ -380,9 +1130,9 @@ export class SystemPlugin extends BasePlugin {
   async handleSystemSnapshot(args) {
     await this.validateArgs(args, this.tools[4].inputSchema);
     
-    const { description, files } = args;
+    const { description, files, include = [], exclude = [], compress = true, packages = false } = args;
     
-    const snapshotId = await this.security.createSnapshot(description, files);
+    const snapshotId = await this.security.createSnapshot(description, files, { include, exclude, compress, packages });
     
     return this.createTextResult(`Snapshot created successfully: ${snapshotId}`, {
       snapshotId
@@ -392,16 +1142,95 @@ export class SystemPlugin extends BasePlugin {
   async handleSystemRollback(args) {
     await this.validateArgs(args, this.tools[5].inputSchema);
     
-    const { snapshotId } = args;
+    const { snapshotId, files = [], scope = 'all', dryRun = false } = args;
+    
+    const plan = await this.security.restoreSnapshot(snapshotId, { files, scope, dryRun });
     
-    await this.security.restoreSnapshot(snapshotId);
+    if (dryRun) {
+      return this.createTextResult(JSON.stringify(plan, null, 2), { snapshotId, dryRun });
+    }
+    
+    const failed = plan.files.filter(file => file.error);
+    const restored = plan.files.filter(file => file.restored).length;
+    const summary = `Rolled back to snapshot ${snapshotId}: ${restored} path(s) restored` +
+      (failed.length > 0 ? `, ${failed.length} failed` : '') +
+      (plan.packages.length > 0 ? `, ${plan.packages.length} package change(s)` : '') +
+      (plan.packageResult?.errors.length > 0 ? ' (package rollback failed)' : '');
     
-    return this.createTextResult(`Successfully rolled back to snapshot: ${snapshotId}`);
+    return this.createTextResult(summary, { snapshotId, plan });
   }
 
-  async handleSystemProcess(args) {
+  async handleSnapshotDelete(args) {
     await this.validateArgs(args, this.tools[6].inputSchema);
     
+    const { snapshotId, prune = false, dryRun = false } = args;
+    
+    if (prune) {
+      const result = await this.security.pruneSnapshots({ dryRun });
+      const verb = dryRun ? 'Would prune' : 'Pruned';
+      return this.createTextResult(`${verb} ${result.pruned.length} snapshot(s), keeping ${result.kept.length}`, result);
+    }
+    
+    if (!snapshotId) {
+      throw new Error('Either snapshotId or prune must be provided');
+    }
+    
+    await this.security.deleteSnapshot(snapshotId);
+    
+    return this.createTextResult(`Snapshot deleted: ${snapshotId}`, { snapshotId });
+  }
+
+  async handleSystemUndo(args) {
+    await this.validateArgs(args, this.tools[7].inputSchema);
+    
+    const { operationId, dryRun = false } = args;
+    
+    const snapshot = await this.security.findOperationSnapshot(operationId);
+    if (!snapshot) {
+      throw new Error(operationId ?
+        `No automatic snapshot found for operation ${operationId}` :
+        'No automatic snapshot available to undo');
+    }
+    
+    // Only what the undone tool touched: its files, and packages if it changed them
+    const plan = await this.security.restoreSnapshot(snapshot.id, { dryRun, services: false });
+    const metadata = { snapshotId: snapshot.id, operation: snapshot.operation, dryRun, plan };
+    
+    if (dryRun) {
+      return this.createTextResult(JSON.stringify(plan, null, 2), metadata);
+    }
+    
+    return this.createTextResult(
+      `Undid ${snapshot.operation.tool} (operation ${snapshot.operation.id}) from snapshot ${snapshot.id}`,
+      metadata
+    );
+  }
+
+  async handleEtcHistory(args) {
+    await this.validateArgs(args, this.tools[8].inputSchema);
+    
+    const { file = null, limit = 20 } = args;
+    const history = await this.security.etc.history({ file, limit });
+    
+    return this.createTextResult(JSON.stringify(history, null, 2), { count: history.length });
+  }
+
+  async handleEtcRevert(args) {
+    await this.validateArgs(args, this.tools[9].inputSchema);
+    
+    const { commit, dryRun = false } = args;
+    const result = await this.security.etc.revert(commit, { dryRun });
+    
+    if (dryRun) {
+      return this.createTextResult(result.diff, { commit, dryRun });
+    }
+    
+    return this.createTextResult(`Reverted ${commit} as ${result.revertCommit}`, result);
+  }
+
+  async handleSystemProcess(args) {
+    await this.validateArgs(args, this.tools[10].inputSchema);
+    
     const { action, pid, signal = 'TERM', filter } = args;
     
     let result;
@@ -433,6 +1262,421 @@ export class SystemPlugin extends BasePlugin {
     return this.createTextResult(result.stdout || result.stderr);
   }
 
+  async handleUpdateSystem(args) {
+    await this.validateArgs(args, this.tools[11].inputSchema);
+    
+    const { acknowledgeNews = false, refreshKeyring = true, dryRun = false } = args;
+    
+    // pacman output goes to the client as progress notifications while the upgrade runs
+    const progress = new PacmanProgress();
+    const onOutput = (text) => progress.feed(text)
+      .forEach(update => reportProgress(update.percent, { total: 100, message: update.message, details: update }));
+    
+    const report = await this.systemUpdate.run({ acknowledgeNews, refreshKeyring, dryRun, onOutput });
+    
+    let summary;
+    if (report.aborted) {
+      summary = `Update aborted: ${report.reason}`;
+    } else if (dryRun) {
+      summary = `${report.pending.length} update(s) pending, ${report.news.items.length} news item(s) since last upgrade`;
+    } else {
+      summary = `Upgraded ${report.upgraded.length} package(s)` +
+        (report.pacnew.new.length > 0 ? `, ${report.pacnew.new.length} new .pacnew file(s) to merge` : '') +
+        (report.reboot.required ? `; reboot required (${report.reboot.reasons.join(', ')})` : '; no reboot required');
+    }
+    
+    return this.createTextResult(`${summary}\n\n${JSON.stringify(report, null, 2)}`, report);
+  }
+
+  async handleCreateSystemdUnit(args) {
+    await this.validateArgs(args, this.tools[12].inputSchema);
+    
+    const { enable = false, start = false, overwrite = false, dryRun = false } = args;
+    const target = this.resolveTarget(args.target);
+    const unitName = normalizeUnitName(args.name);
+    const content = renderUnit({ hardening: 'basic', ...args });
+    const unitPath = targetPath(target, path.join('/etc/systemd/system', unitName));
+    
+    if (start && target.type === 'chroot') {
+      throw new Error(`Cannot start ${unitName} in ${describeTarget(target)}: nothing runs there; enable it instead`);
+    }
+    
+    if (!dryRun && !overwrite && await fs.pathExists(unitPath)) {
+      throw new Error(`${unitPath} already exists; set overwrite to replace it`);
+    }
+    
+    // Verify a private copy so a broken unit never reaches /etc
+    const verifyDir = await fs.mkdtemp(path.join(os.tmpdir(), 'mcp-unit-'));
+    let verify;
+    try {
+      await fs.writeFile(path.join(verifyDir, unitName), content);
+      verify = await this.commandExecutor.execute('systemd-analyze', ['verify', path.join(verifyDir, unitName)]);
+    } finally {
+      await fs.remove(verifyDir);
+    }
+    
+    const warnings = verify.stderr.split('\n').filter(Boolean);
+    if (!verify.success) {
+      throw new Error(`systemd-analyze verify failed:\n${warnings.join('\n')}\n\n${content}`);
+    }
+    
+    if (dryRun) {
+      return this.createTextResult(content, { unit: unitName, path: unitPath, dryRun, warnings });
+    }
+    
+    await fs.writeFile(unitPath, content, { mode: 0o644 });
+    const steps = ['written'];
+    if (target.type !== 'chroot') {
+      await this.commandExecutor.executeWithSudo('systemctl', ['daemon-reload'], { target });
+      steps.push('daemon-reload');
+    }
+    
+    if (enable) {
+      const result = await this.commandExecutor.executeWithSudo('systemctl', ['enable', unitName], { target });
+      if (!result.success) throw new Error(`Enable failed: ${result.stderr}`);
+      steps.push('enabled');
+    }
+    if (start) {
+      const result = await this.commandExecutor.executeWithSudo('systemctl', ['restart', unitName], { target });
+      if (!result.success) throw new Error(`Start failed: ${result.stderr}`);
+      steps.push('started');
+    }
+    
+    return this.createTextResult(`${unitPath}: ${steps.join(', ')}\n\n${content}`, {
+      unit: unitName,
+      path: unitPath,
+      steps,
+      warnings
+    });
+  }
+
+  async handleCheckArchNews(args) {
+    await this.validateArgs(args, this.tools[13].inputSchema);
+    
+    const { unreadOnly = true, limit = 10 } = args;
+    const news = await this.systemUpdate.news({ refresh: true });
+    const items = news.items.filter(item => !unreadOnly || item.unread).slice(0, limit);
+    const manualIntervention = news.items.filter(item => item.unread && item.manualIntervention);
+    
+    const summary = manualIntervention.length > 0
+      ? `${manualIntervention.length} unread item(s) require manual intervention before upgrading: ${manualIntervention.map(item => item.title).join('; ')}`
+      : `${news.items.filter(item => item.unread).length} unread item(s) since ${news.since}`;
+    
+    return this.createTextResult(`${summary}\n\n${JSON.stringify({ ...news, items }, null, 2)}`, {
+      unread: news.items.filter(item => item.unread).length,
+      manualIntervention: manualIntervention.length
+    });
+  }
+
+  async handleMergePacnew(args) {
+    await this.validateArgs(args, this.tools[14].inputSchema);
+    
+    const { merged, instructions, apply = false } = args;
+    const file = path.resolve(args.path.replace(/\.pacnew$/, ''));
+    const pacnew = `${file}.pacnew`;
+    
+    if (!this.systemUpdate.pacnewRoots.some(root => file.startsWith(`${root}/`))) {
+      throw new Error(`${file} is not under ${this.systemUpdate.pacnewRoots.join(', ')}`);
+    }
+    if (!await fs.pathExists(pacnew)) {
+      throw new Error(`No ${pacnew} to merge`);
+    }
+    
+    const [current, incoming] = await Promise.all([fs.readFile(file, 'utf8').catch(() => ''), fs.readFile(pacnew, 'utf8')]);
+    
+    let proposal = merged;
+    let model = null;
+    if (proposal === undefined) {
+      // Without sampling the caller gets the diff and can merge it itself
+      if (!this.canSample()) {
+        const diff = await this.commandExecutor.execute('git', ['diff', '--no-index', '--no-color', '--', file, pacnew]);
+        return this.createTextResult(
+          `The client does not support sampling; merge by hand and call merge_pacnew again with merged.\n\n${diff.stdout}`,
+          { file, pacnew, proposed: false }
+        );
+      }
+      
+      const response = await this.createMessage({
+        systemPrompt: 'You merge Arch Linux configuration files after package upgrades. Keep every local customization from the current file, adopt new defaults, options and comments from the .pacnew, and drop options the .pacnew removed. Reply with only the merged file content, no explanation and no code fences.',
+        messages: [{
+          role: 'user',
+          content: {
+            type: 'text',
+            text: [
+              `Current ${file}:`, '```', current, '```',
+              `New ${pacnew}:`, '```', incoming, '```',
+              instructions ? `Additional instructions: ${instructions}` : null
+            ].filter(line => line !== null).join('\n')
+          }
+        }],
+        includeContext: 'none',
+        temperature: 0
+      });
+      
+      if (response?.content?.type !== 'text') {
+        throw new Error('The client returned no text merge');
+      }
+      proposal = response.content.text.replace(/^```[^\n]*\n/, '').replace(/\n```\s*$/, '\n');
+      model = response.model || null;
+    }
+    
+    if (!apply) {
+      return this.createTextResult(proposal, { file, pacnew, proposed: true, model });
+    }
+    
+    // Writing in place keeps the config's owner and mode
+    await fs.writeFile(file, proposal.endsWith('\n') ? proposal : `${proposal}\n`);
+    await fs.remove(pacnew);
+    this.logger.audit('merge_pacnew', { file, model });
+    
+    return this.createTextResult(`Merged ${pacnew} into ${file}`, { file, applied: true, model });
+  }
+
+  async handleSystemTarget(args) {
+    await this.validateArgs(args, this.tools[15].inputSchema);
+    
+    const { action = 'list', target, name } = args;
+    const registry = getTargetRegistry();
+    
+    switch (action) {
+      case 'list':
+        break;
+      case 'set':
+        if (!target) throw new Error('target required for set');
+        registry.setDefault(target);
+        this.logger.info(`System tools now act on ${describeTarget(registry.resolve())}`);
+        break;
+      case 'define':
+        if (!name || !target) throw new Error('name and target required for define');
+        registry.define(name, target);
+        break;
+      case 'remove':
+        if (!name) throw new Error('name required for remove');
+        registry.remove(name);
+        break;
+      default:
+        throw new Error(`Unknown action: ${action}`);
+    }
+    
+    return this.createTextResult(JSON.stringify(registry.list(), null, 2));
+  }
+
+  async handleApplyState(args) {
+    await this.validateArgs(args, this.tools[16].inputSchema);
+    
+    const { check = false } = args;
+    const target = this.resolveTarget(args.target);
+    const state = await this.loadState(args);
+    
+    const changes = await this.stateApplier.plan(state, target);
+    // File content can be large or secret; the report names the fields that differ
+    const report = (change) => {
+      const { spec, ...rest } = change;
+      return { ...rest, description: describeChange(change) };
+    };
+    
+    if (check || changes.length === 0) {
+      const lines = changes.map(change => `~ ${describeChange(change)}`);
+      return this.createTextResult(
+        changes.length === 0 ? `${describeTarget(target)} already matches the state` : `${changes.length} change(s) needed on ${describeTarget(target)}:\n${lines.join('\n')}`,
+        { check, changed: changes.length, changes: changes.map(report) }
+      );
+    }
+    
+    const results = await this.stateApplier.apply(changes, target);
+    const marks = { changed: '+', failed: '!', skipped: '-' };
+    const lines = results.map(result => `${marks[result.status]} ${describeChange(result)}${result.error ? `: ${result.error}` : ''}`);
+    const failed = results.find(result => result.status === 'failed');
+    const changed = results.filter(result => result.status === 'changed').length;
+    
+    this.logger.audit('apply_state', { target: describeTarget(target), changed, failed: Boolean(failed) });
+    
+    const metadata = { check, changed, changes: results.map(report) };
+    return failed
+      ? this.createErrorResult(`apply_state stopped after ${changed} of ${results.length} change(s):\n${lines.join('\n')}`, metadata)
+      : this.createTextResult(`Applied ${changed} change(s) on ${describeTarget(target)}:\n${lines.join('\n')}`, metadata);
+  }
+
+  async handlePacmanKeyring(args) {
+    await this.validateArgs(args, this.tools[17].inputSchema);
+    
+    const { action = 'status', keyserver = null, full = false } = args;
+    const target = this.resolveTarget(args.target);
+    
+    let result;
+    switch (action) {
+      case 'status':
+        result = await this.keyring.status(target);
+        break;
+      case 'init':
+        result = await this.keyring.init(target);
+        break;
+      case 'refresh':
+        result = await this.keyring.refresh(target, { full });
+        break;
+      case 'sign': {
+        if (!args.keyId) throw new Error('keyId required for sign');
+        if (keyserver && !/^(hkps?|hkp|ldap):\/\/[a-zA-Z0-9.-]+(:\d+)?\/?$/.test(keyserver)) {
+          throw new Error(`Invalid keyserver: ${keyserver}`);
+        }
+        const keyId = args.keyId.replace(/^0x/, '').toUpperCase();
+        result = await this.keyring.sign(keyId, target, { keyserver });
+        this.logger.audit('pacman_keyring_sign', { keyId, uids: result.uids || [], target: describeTarget(target) });
+        break;
+      }
+      default:
+        throw new Error(`Unknown action: ${action}`);
+    }
+    
+    return this.createTextResult(JSON.stringify(result, null, 2));
+  }
+
+  async handleDiagnoseSignatures(args) {
+    await this.validateArgs(args, this.tools[18].inputSchema);
+    
+    const report = await this.keyring.diagnose(this.resolveTarget(args.target), { error: args.error || '' });
+    return this.createTextResult(JSON.stringify(report, null, 2), {
+      healthy: report.healthy,
+      suspectedCauses: report.suspectedCauses
+    });
+  }
+
+  async handlePacmanRepos(args) {
+    await this.validateArgs(args, this.tools[19].inputSchema);
+    
+    const { action = 'list', name, servers = [], include = null, sigLevel = null, before = null, keyserver = null, upgrade = false, dryRun = false } = args;
+    const target = this.resolveTarget(args.target);
+    const file = targetPath(target, PACMAN_CONF);
+    const current = await fs.readFile(file, 'utf8');
+    
+    if (action === 'list') {
+      const parsed = parsePacmanConf(current);
+      const repos = parseRepos(current).map(repo => ({
+        name: repo.name,
+        enabled: repo.enabled,
+        ...(repo.enabled ? parsed.repos.find(candidate => candidate.name === repo.name) : {})
+      }));
+      return this.createTextResult(JSON.stringify({ file, options: parsed.options, repos }, null, 2), {
+        enabled: repos.filter(repo => repo.enabled).map(repo => repo.name)
+      });
+    }
+    
+    let after;
+    switch (action) {
+      case 'add': {
+        if (!name) throw new Error('name required for add');
+        const invalid = servers.filter(server => !/^(https?|ftp|file):\/\/[^\s#]+$/.test(server));
+        if (invalid.length > 0) {
+          throw new Error(`Invalid server URLs: ${invalid.join(', ')}`);
+        }
+        if (include && !/^\/[^\s#]+$/.test(include)) {
+          throw new Error(`Include must be an absolute path: ${include}`);
+        }
+        if (include && !await fs.pathExists(targetPath(target, include))) {
+          throw new Error(`Include file not found: ${targetPath(target, include)}`);
+        }
+        if (sigLevel && !/^((Package|Database)?(Never|Optional|Required|TrustedOnly|TrustAll)\s*)+$/.test(sigLevel)) {
+          throw new Error(`Invalid SigLevel: ${sigLevel}`);
+        }
+        if (keyserver && !/^(hkps?|hkp|ldap):\/\/[a-zA-Z0-9.-]+(:\d+)?\/?$/.test(keyserver)) {
+          throw new Error(`Invalid keyserver: ${keyserver}`);
+        }
+        after = addRepo(current, { name, servers, include, sigLevel: sigLevel?.trim() || null, before });
+        break;
+      }
+      case 'remove':
+        if (!name) throw new Error('name required for remove');
+        after = removeRepo(current, name);
+        break;
+      case 'options':
+        if (!args.options || Object.keys(args.options).length === 0) {
+          throw new Error('options required, e.g. {"ParallelDownloads": 10}');
+        }
+        after = setOptions(current, args.options);
+        break;
+      default:
+        throw new Error(`Unknown action: ${action}`);
+    }
+    
+    const diff = previewDiff(file, current, after);
+    if (current === after) {
+      return this.createTextResult(`${file} already ${action === 'remove' ? `has no ${name}` : 'matches'}; nothing to change`, { changed: false });
+    }
+    if (dryRun) {
+      return this.createTextResult(diff, { dryRun, changed: true });
+    }
+    
+    // The repository's key goes in first so its database verifies on the first sync
+    let key = null;
+    if (action === 'add' && args.keyId) {
+      const keyId = args.keyId.replace(/^0x/, '').toUpperCase();
+      key = await this.keyring.sign(keyId, target, { keyserver });
+      this.logger.audit('pacman_keyring_sign', { keyId, uids: key.uids || [], target: describeTarget(target) });
+    }
+    
+    await this.checkPacmanConf(target, after, { repo: ['add', 'remove'].includes(action) ? name : null, present: action === 'add', options: args.options });
+    await fs.writeFile(file, after);
+    this.logger.audit('pacman_repos', { action, name, options: args.options ? Object.keys(args.options) : undefined, target: describeTarget(target) });
+    
+    let upgraded = false;
+    if (upgrade && action !== 'options') {
+      const result = await this.commandExecutor.executeWithSudo('pacman', ['-Syu', '--noconfirm'], { target, retry: false, timeout: 1800000 });
+      if (!result.success) {
+        throw new Error(`${file} saved, but pacman -Syu failed: ${result.stderr.trim().split('\n').slice(-5).join('\n')}`);
+      }
+      upgraded = true;
+    }
+    
+    const note = action === 'add' && !upgraded ? '\nRun a full upgrade (pacman -Syu) before installing from it' : '';
+    return this.createTextResult(`${diff}${note}`, { changed: true, key, upgraded });
+  }
+
+  // pacman-conf parses a candidate exactly as pacman would before it replaces the real
+  // file; options read back to what was set, and the repository list shows the change
+  async checkPacmanConf(target, content, { repo = null, present = true, options = null } = {}) {
+    const candidate = `${PACMAN_CONF}.mcp-check`;
+    const pacmanConf = (extra) => this.commandExecutor.execute('pacman-conf', ['--config', candidate, ...extra], { target, retry: false });
+    await fs.writeFile(targetPath(target, candidate), content);
+    try {
+      const list = await pacmanConf(['--repo-list']);
+      if (!list.success) {
+        throw new Error(`The edited pacman.conf does not parse: ${list.stderr.trim()}`);
+      }
+      if (repo && list.stdout.split('\n').includes(repo) !== present) {
+        throw new Error(`pacman-conf ${present ? 'does not list' : 'still lists'} ${repo} in the edited pacman.conf`);
+      }
+      for (const [key, value] of Object.entries(options || {})) {
+        // Flags print nothing to compare, SigLevel comes back expanded and Architecture resolved
+        if (OPTION_TYPES[key] === 'flag' || /SigLevel$|^Architecture$/.test(key) || value === false || value === null) {
+          continue;
+        }
+        const shown = await pacmanConf([key]);
+        const expected = [].concat(value).map(String);
+        const actual = shown.stdout.split('\n').map(line => line.trim()).filter(Boolean);
+        if (!shown.success || (expected.length > 0 && actual.join(' ') !== expected.join(' '))) {
+          throw new Error(`pacman-conf reads ${key} as ${JSON.stringify(actual.join(' '))} in the edited pacman.conf, not ${JSON.stringify(expected.join(' '))}`);
+        }
+      }
+    } finally {
+      await fs.remove(targetPath(target, candidate));
+    }
+  }
+
+  async loadState({ state = {}, stateFile }) {
+    let fileState = {};
+    if (stateFile) {
+      if (!await fs.pathExists(stateFile)) {
+        throw new Error(`State file not found: ${stateFile}`);
+      }
+      fileState = YAML.parse(await fs.readFile(stateFile, 'utf8')) || {};
+    }
+    return normalizeState({ ...fileState, ...state });
+  }
+
+  resolveTarget(spec) {
+    return getTargetRegistry().resolve(spec ?? undefined);
+  }
+
   async getSystemInfo() {
     const info = await si.osInfo();
     return { content: JSON.stringify(info, null, 2) };
@@ -457,4 +1701,58 @@ export class SystemPlugin extends BasePlugin {
     const result = await this.commandExecutor.execute('ps', ['aux']);
     return { content: result.stdout };
   }
-}
\ No newline at end of file
+
+  async getEnvironment() {
+    const environment = await this.environment.get();
+    return { content: JSON.stringify(environment, null, 2) };
+  }
+
+  async getToolDependencies() {
+    const report = getToolResolver().getDependencyReport();
+    return { content: JSON.stringify(report, null, 2) };
+  }
+
+  async getQuotaWarnings() {
+    const report = await getQuotaRegistry().getWarnings();
+    return { content: JSON.stringify(report, null, 2) };
+  }
+
+  async getUnitJournal({ unit, lines }) {
+    if (!/^[A-Za-z0-9@._:-]+$/.test(unit) || !/^\d+$/.test(lines) || Number(lines) < 1 || Number(lines) > 10000) {
+      throw new Error(`Invalid journal request: ${unit}/${lines}`);
+    }
+    
+    const result = await this.commandExecutor.execute('journalctl', ['-u', unit, '-n', lines, '--no-pager']);
+    if (!result.success) {
+      throw new Error(`journalctl failed: ${result.stderr.trim()}`);
+    }
+    return { content: result.stdout };
+  }
+
+  async getPackageInfo({ name }) {
+    if (!/^[a-zA-Z0-9@._+-]+$/.test(name)) {
+      throw new Error(`Invalid package name: ${name}`);
+    }
+    
+    const local = await this.commandExecutor.execute('pacman', ['-Qi', name]);
+    if (local.success) {
+      return { content: local.stdout };
+    }
+    const sync = await this.commandExecutor.execute('pacman', ['-Si', name]);
+    if (!sync.success) {
+      throw new Error(`Package not found: ${name}`);
+    }
+    return { content: sync.stdout };
+  }
+
+  async getArchNews() {
+    const news = await this.systemUpdate.news();
+    return {
+      content: JSON.stringify({
+        ...news,
+        unread: news.items.filter(item => item.unread).length,
+        manualIntervention: news.items.filter(item => item.unread && item.manualIntervention)
+      }, null, 2)
+    };
+  }
+}
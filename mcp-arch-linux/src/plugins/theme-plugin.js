@@ -0,0 +1,351 @@
+import { BasePlugin, pluginConfig } from './base-plugin.js';
+import { CommandExecutor } from '../system/command-executor.js';
+import { LockKeys } from '../security/lock-manager.js';
+import { lookupUser, currentUser } from '../system/user-account.js';
+import { readLines, writeUserFile, upsertLine, setIniValues, getIniValues } from '../system/user-files.js';
+import { GENERIC_FAMILIES, fontconfigPath, parseFontAliases, renderFontAliases } from '../system/fontconfig.js';
+import fs from 'fs-extra';
+import path from 'path';
+
+const ENV_FILE = '60-mcp-arch-linux.conf';
+const GSETTINGS_SCHEMA = 'org.gnome.desktop.interface';
+const PACKAGE_NAME = /^[a-z0-9@._+-]+$/;
+
+// GTK, Qt, cursor and font settings applied consistently across toolkits
+export class ThemePlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('theme', config, logger, security);
+    this.description = 'GTK, icon, cursor, Qt and font appearance';
+    this.defaultUser = pluginConfig(config, 'theme').user || null;
+    this.dependencies = ['gsettings', 'pacman'];
+    this.optionalDependencies = ['gsettings'];
+
+    this.commandExecutor = new CommandExecutor(
+      config.get?.('security') || config.security || {},
+      logger,
+      security
+    );
+
+    this.initializeTools();
+    this.initializeResources();
+  }
+
+  initializeTools() {
+    this.tools = [
+      this.createTool(
+        'theme_install',
+        'Install theme, icon, cursor or font packages from the repositories',
+        {
+          type: 'object',
+          properties: {
+            packages: {
+              type: 'array',
+              items: { type: 'string' },
+              description: 'Packages (e.g. arc-gtk-theme, papirus-icon-theme, ttf-jetbrains-mono)'
+            }
+          },
+          required: ['packages']
+        }
+      ),
+
+      this.createTool(
+        'theme_apply',
+        'Apply GTK/icon/cursor themes, dark preference, Qt platform theme and fonts for a user',
+        {
+          type: 'object',
+          properties: {
+            user: {
+              type: 'string',
+              description: 'User to configure'
+            },
+            gtkTheme: {
+              type: 'string',
+              description: 'GTK theme name (directory in /usr/share/themes or ~/.themes)'
+            },
+            iconTheme: {
+              type: 'string',
+              description: 'Icon theme name'
+            },
+            cursorTheme: {
+              type: 'string',
+              description: 'Cursor theme name'
+            },
+            cursorSize: {
+              type: 'number',
+              description: 'Cursor size in pixels'
+            },
+            colorScheme: {
+              type: 'string',
+              enum: ['prefer-dark', 'prefer-light', 'default'],
+              description: 'Dark/light preference advertised to applications'
+            },
+            qtPlatformTheme: {
+              type: 'string',
+              enum: ['qt5ct', 'qt6ct', 'gtk3', 'kde', 'none'],
+              description: 'QT_QPA_PLATFORMTHEME for the session'
+            },
+            font: {
+              type: 'string',
+              description: 'Interface font with size (e.g. "Inter 10")'
+            },
+            fonts: {
+              type: 'object',
+              description: 'Preferred families for fontconfig aliases',
+              properties: {
+                sansSerif: { type: 'string' },
+                serif: { type: 'string' },
+                monospace: { type: 'string' }
+              }
+            }
+          },
+          required: ['user']
+        }
+      )
+    ];
+  }
+
+  initializeResources() {
+    this.resources = [
+      this.createResource(
+        'theme://current',
+        'Active Theme',
+        'GTK 3/4 settings, gsettings, cursor, Qt platform theme, fontconfig aliases and installed themes',
+        'application/json'
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'theme_install':
+          return this.handleInstall(args);
+        case 'theme_apply':
+          return this.handleApply(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  async readResource(uri) {
+    return this.withErrorHandling(async () => {
+      switch (uri) {
+        case 'theme://current':
+          return this.getCurrentTheme();
+        default:
+          throw new Error(`Unknown resource: ${uri}`);
+      }
+    }, 'readResource');
+  }
+
+  getToolLocks(toolName) {
+    return toolName === 'theme_install' ? [LockKeys.pacmanDb()] : [];
+  }
+
+  affectsPackages(toolName) {
+    return toolName === 'theme_install';
+  }
+
+  isIdempotent(toolName) {
+    return toolName === 'theme_apply';
+  }
+
+  async getAffectedPaths(toolName, args = {}) {
+    if (toolName !== 'theme_apply' || !args.user) {
+      return [];
+    }
+
+    try {
+      return Object.values(this.paths(await lookupUser(args.user)));
+    } catch {
+      return [];
+    }
+  }
+
+  paths(account) {
+    const configHome = path.join(account.home, '.config');
+    return {
+      gtk3: path.join(configHome, 'gtk-3.0', 'settings.ini'),
+      gtk4: path.join(configHome, 'gtk-4.0', 'settings.ini'),
+      cursor: path.join(account.home, '.icons', 'default', 'index.theme'),
+      environment: path.join(configHome, 'environment.d', ENV_FILE),
+      fontconfig: fontconfigPath(account)
+    };
+  }
+
+  async handleInstall(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const { packages } = args;
+    const invalid = packages.filter(name => !PACKAGE_NAME.test(name));
+    if (packages.length === 0 || invalid.length > 0) {
+      throw new Error(invalid.length > 0 ? `Invalid package names: ${invalid.join(', ')}` : 'No packages given');
+    }
+
+    const result = await this.commandExecutor.executeWithSudo('pacman', ['-S', '--needed', '--noconfirm', ...packages]);
+    if (!result.success) {
+      throw new Error(`Installing themes failed: ${result.stderr}`);
+    }
+    return this.createTextResult(result.stdout || `Installed: ${packages.join(', ')}`);
+  }
+
+  async handleApply(args) {
+    await this.validateArgs(args, this.tools[1].inputSchema);
+
+    const account = await lookupUser(args.user);
+    const paths = this.paths(account);
+    const { gtkTheme, iconTheme, cursorTheme, cursorSize, colorScheme, qtPlatformTheme, font, fonts } = args;
+
+    for (const [field, value] of Object.entries({ gtkTheme, iconTheme, cursorTheme, font, ...(fonts || {}) })) {
+      if (value !== undefined && /[\r\n<>&]/.test(String(value))) {
+        throw new Error(`Invalid ${field}: ${value}`);
+      }
+    }
+
+    const applied = [];
+
+    // GTK 3 and 4 read settings.ini; the dark preference is a separate boolean there
+    const gtkSettings = {};
+    if (gtkTheme) gtkSettings['gtk-theme-name'] = gtkTheme;
+    if (iconTheme) gtkSettings['gtk-icon-theme-name'] = iconTheme;
+    if (cursorTheme) gtkSettings['gtk-cursor-theme-name'] = cursorTheme;
+    if (cursorSize) gtkSettings['gtk-cursor-theme-size'] = cursorSize;
+    if (font) gtkSettings['gtk-font-name'] = font;
+    if (colorScheme) gtkSettings['gtk-application-prefer-dark-theme'] = colorScheme === 'prefer-dark' ? 1 : 0;
+
+    if (Object.keys(gtkSettings).length > 0) {
+      // GTK 4 apps are styled by libadwaita; forcing a GTK 3 theme name there breaks them
+      const gtk4Settings = Object.fromEntries(Object.entries(gtkSettings).filter(([key]) => key !== 'gtk-theme-name'));
+      for (const [file, settings] of [[paths.gtk3, gtkSettings], [paths.gtk4, gtk4Settings]]) {
+        if (Object.keys(settings).length > 0) {
+          await writeUserFile(file, setIniValues(await readLines(file), 'Settings', settings).join('\n'), account);
+        }
+      }
+      applied.push('gtk-settings');
+    }
+
+    // Wayland GTK apps and portals read gsettings rather than settings.ini
+    const gsettings = {};
+    if (gtkTheme) gsettings['gtk-theme'] = gtkTheme;
+    if (iconTheme) gsettings['icon-theme'] = iconTheme;
+    if (cursorTheme) gsettings['cursor-theme'] = cursorTheme;
+    if (cursorSize) gsettings['cursor-size'] = String(cursorSize);
+    if (font) gsettings['font-name'] = font;
+    if (fonts?.monospace) gsettings['monospace-font-name'] = fonts.monospace;
+    if (colorScheme) gsettings['color-scheme'] = colorScheme;
+
+    const gsettingsErrors = [];
+    for (const [key, value] of Object.entries(gsettings)) {
+      const result = await this.runGsettings(account, ['set', GSETTINGS_SCHEMA, key, value]).catch(error => ({ success: false, stderr: error.message }));
+      if (!result.success) gsettingsErrors.push(`${key}: ${result.stderr}`);
+    }
+    if (Object.keys(gsettings).length > 0 && gsettingsErrors.length < Object.keys(gsettings).length) {
+      applied.push('gsettings');
+    }
+
+    // X11 and XWayland fall back to the "default" cursor theme and XCURSOR_* variables
+    if (cursorTheme) {
+      await writeUserFile(paths.cursor, `[Icon Theme]\nInherits=${cursorTheme}\n`, account);
+      applied.push('cursor-default');
+    }
+
+    const environment = {};
+    if (cursorTheme) environment.XCURSOR_THEME = cursorTheme;
+    if (cursorSize) environment.XCURSOR_SIZE = cursorSize;
+    if (qtPlatformTheme) environment.QT_QPA_PLATFORMTHEME = qtPlatformTheme === 'none' ? null : qtPlatformTheme;
+
+    if (Object.keys(environment).length > 0) {
+      let lines = await readLines(paths.environment);
+      for (const [name, value] of Object.entries(environment)) {
+        const pattern = new RegExp(`^\\s*${name}=`);
+        lines = value === null ? lines.filter(line => !pattern.test(line)) : upsertLine(lines, pattern, `${name}=${value}`);
+      }
+      await writeUserFile(paths.environment, lines.join('\n'), account);
+      applied.push('environment.d');
+    }
+
+    if (fonts && Object.keys(fonts).length > 0) {
+      // Keep aliases set by font_defaults (e.g. emoji) that this call does not mention
+      const aliases = parseFontAliases(await fs.readFile(paths.fontconfig, 'utf8').catch(() => ''));
+      for (const [key, family] of Object.entries(fonts)) {
+        if (GENERIC_FAMILIES[key] && family) aliases[GENERIC_FAMILIES[key]] = family;
+      }
+      await writeUserFile(paths.fontconfig, renderFontAliases(aliases), account);
+      applied.push('fontconfig');
+    }
+
+    return this.createTextResult(JSON.stringify({
+      user: account.name,
+      applied,
+      ...(gsettingsErrors.length > 0 ? { gsettingsErrors } : {}),
+      note: 'Running applications may need a restart; environment changes apply at next login'
+    }, null, 2));
+  }
+
+  // gsettings writes to the user's dconf over their session bus
+  async runGsettings(account, args) {
+    const env = {
+      ...process.env,
+      HOME: account.home,
+      DBUS_SESSION_BUS_ADDRESS: `unix:path=/run/user/${account.uid}/bus`
+    };
+
+    if (process.getuid && process.getuid() === 0 && account.uid !== 0) {
+      return this.commandExecutor.execute('runuser', ['-u', account.name, '--', 'gsettings', ...args], { env });
+    }
+    return this.commandExecutor.execute('gsettings', args, { env });
+  }
+
+  async listThemes(account) {
+    const list = async (dirs, marker) => {
+      const names = new Set();
+      for (const dir of dirs) {
+        for (const name of await fs.readdir(dir).catch(() => [])) {
+          if (await fs.pathExists(path.join(dir, name, marker))) names.add(name);
+        }
+      }
+      return [...names].sort();
+    };
+
+    const share = path.join(account.home, '.local', 'share');
+    const iconDirs = ['/usr/share/icons', path.join(account.home, '.icons'), path.join(share, 'icons')];
+    return {
+      gtk: await list(['/usr/share/themes', path.join(account.home, '.themes'), path.join(share, 'themes')], 'gtk-3.0'),
+      icons: await list(iconDirs, 'index.theme'),
+      cursors: await list(iconDirs, 'cursors')
+    };
+  }
+
+  async getCurrentTheme() {
+    const account = this.defaultUser ? await lookupUser(this.defaultUser) : await currentUser();
+    const paths = this.paths(account);
+
+    const gsettings = {};
+    for (const key of ['gtk-theme', 'icon-theme', 'cursor-theme', 'cursor-size', 'font-name', 'monospace-font-name', 'color-scheme']) {
+      const result = await this.runGsettings(account, ['get', GSETTINGS_SCHEMA, key]).catch(() => null);
+      if (result?.success) gsettings[key] = result.stdout.replace(/^'|'$/g, '');
+    }
+
+    const environment = {};
+    for (const line of await readLines(paths.environment)) {
+      const match = line.match(/^(XCURSOR_THEME|XCURSOR_SIZE|QT_QPA_PLATFORMTHEME)=(.*)$/);
+      if (match) environment[match[1]] = match[2];
+    }
+
+    const aliases = parseFontAliases(await fs.readFile(paths.fontconfig, 'utf8').catch(() => ''));
+
+    return {
+      content: JSON.stringify({
+        user: account.name,
+        gtk3: getIniValues(await readLines(paths.gtk3), 'Settings'),
+        gtk4: getIniValues(await readLines(paths.gtk4), 'Settings'),
+        gsettings,
+        cursor: getIniValues(await readLines(paths.cursor), 'Icon Theme').Inherits || null,
+        environment,
+        fonts: aliases,
+        installed: await this.listThemes(account)
+      }, null, 2)
+    };
+  }
+}
@@ -0,0 +1,112 @@
+import { BasePlugin } from './base-plugin.js';
+import { getContext } from '../core/request-context.js';
+
+// Exposes the security manager's transcript of tool calls so an agent (or the user
+// afterwards) can review everything that changed during a session
+export class TranscriptPlugin extends BasePlugin {
+  constructor(config, logger, security) {
+    super('transcript', config, logger, security);
+    this.description = 'Chronological report of tool calls, snapshots and agent plans';
+
+    this.initializeTools();
+    this.initializeResources();
+  }
+
+  initializeTools() {
+    this.tools = [
+      this.createTool(
+        'transcript_note',
+        'Record a plan or note in the session transcript so the report explains why changes were made',
+        {
+          type: 'object',
+          properties: {
+            text: {
+              type: 'string',
+              description: 'Markdown text of the plan or note'
+            },
+            kind: {
+              type: 'string',
+              enum: ['plan', 'note'],
+              description: 'Entry type',
+              default: 'note'
+            }
+          },
+          required: ['text']
+        }
+      )
+    ];
+  }
+
+  initializeResources() {
+    this.resources = [
+      this.createResource(
+        'session://transcript',
+        'Session Transcript',
+        'Every tool call since the server started with redacted arguments, results, snapshots and /etc commits',
+        'application/json'
+      ),
+      this.createResource(
+        'session://transcript.md',
+        'Session Change Report',
+        'The session transcript exported as a Markdown report',
+        'text/markdown'
+      )
+    ];
+  }
+
+  async executeTool(toolName, args) {
+    return this.withErrorHandling(async () => {
+      switch (toolName) {
+        case 'transcript_note':
+          return this.handleNote(args);
+        default:
+          throw new Error(`Unknown tool: ${toolName}`);
+      }
+    }, toolName);
+  }
+
+  async readResource(uri) {
+    return this.withErrorHandling(async () => {
+      switch (uri) {
+        case 'session://transcript':
+          return this.getTranscript();
+        case 'session://transcript.md':
+          return { content: this.security.transcript.toMarkdown() };
+        default:
+          throw new Error(`Unknown resource: ${uri}`);
+      }
+    }, 'readResource');
+  }
+
+  // Notes only add to the transcript, so an audit can still record its findings
+  isReadOnly() {
+    return true;
+  }
+
+  async handleNote(args) {
+    await this.validateArgs(args, this.tools[0].inputSchema);
+
+    const { text, kind = 'note' } = args;
+    if (!text.trim()) {
+      throw new Error('text must not be empty');
+    }
+    if (!this.security.transcript.enabled) {
+      throw new Error('The transcript is disabled (security.transcript.enabled)');
+    }
+
+    await this.security.transcript.recordNote({ sessionId: getContext().sessionId, text, kind });
+    return this.createTextResult(`Recorded ${kind} in the session transcript`);
+  }
+
+  async getTranscript() {
+    const { transcript } = this.security;
+    return {
+      content: JSON.stringify({
+        enabled: transcript.enabled,
+        startedAt: transcript.startedAt,
+        file: transcript.file,
+        entries: transcript.list()
+      }, null, 2)
+    };
+  }
+}
@@ -0,0 +1,128 @@
+import crypto from 'crypto';
+import fs from 'fs-extra';
+import path from 'path';
+import { redact } from './transcript.js';
+import { errorOutcome } from '../core/errors.js';
+
+// Hash of what a tool returned, so a replay can tell whether the outcome changed
+export function digestResult(result) {
+  return `sha256:${crypto.createHash('sha256').update(JSON.stringify(result?.content ?? null)).digest('hex')}`;
+}
+
+// Key order does not make two argument objects different
+function canonical(value) {
+  if (Array.isArray(value)) {
+    return value.map(canonical);
+  }
+  if (value && typeof value === 'object') {
+    return Object.fromEntries(Object.keys(value).sort().map(key => [key, canonical(value[key])]));
+  }
+  return value;
+}
+
+// Completed tool calls kept across restarts in a JSONL file, for history/list and
+// history/replay. Arguments are stored redacted, so calls with secrets cannot be replayed.
+export class ToolHistory {
+  constructor(config = {}, logger) {
+    this.logger = logger;
+    this.enabled = config.enabled ?? true;
+    this.file = config.file || '/var/lib/mcp-arch-linux/history.jsonl';
+    this.maxEntries = config.maxEntries || 5000;
+    this.entries = [];
+    this.lines = 0;
+  }
+
+  async load() {
+    if (!this.enabled || !await fs.pathExists(this.file)) {
+      return;
+    }
+
+    const lines = (await fs.readFile(this.file, 'utf8')).split('\n').filter(Boolean);
+    this.entries = lines.flatMap(line => {
+      try {
+        return [JSON.parse(line)];
+      } catch {
+        return [];
+      }
+    }).slice(-this.maxEntries);
+    this.lines = lines.length;
+    this.logger.debug(`Loaded ${this.entries.length} tool calls from ${this.file}`);
+  }
+
+  async record({ operationId, sessionId, tool, args, result, error, snapshotId, etcCommit, hosts, replayOf, idempotencyKey, jobId, startTime }) {
+    if (!this.enabled) {
+      return null;
+    }
+
+    const entry = {
+      id: operationId,
+      timestamp: new Date(startTime).toISOString(),
+      sessionId: sessionId || null,
+      tool,
+      arguments: redact(args || {}),
+      status: error ? 'failed' : result?.isError ? 'error' : 'success',
+      duration: Date.now() - startTime,
+      snapshots: snapshotId ? [snapshotId] : [],
+      ...(etcCommit ? { etcCommit } : {}),
+      ...(hosts ? { hosts } : {}),
+      ...(replayOf ? { replayOf } : {}),
+      ...(jobId ? { jobId } : {}),
+      ...(error ? { error: errorOutcome(error) } : { digest: digestResult(result) }),
+      // The full result is kept only where a retry with the same key has to return it
+      ...(idempotencyKey ? { idempotencyKey, ...(error ? {} : { result }) } : {})
+    };
+
+    this.entries.push(entry);
+    if (this.entries.length > this.maxEntries) {
+      this.entries.shift();
+    }
+
+    try {
+      await fs.ensureDir(path.dirname(this.file));
+      // Rewrite instead of appending once the file holds twice what is kept
+      if (++this.lines > this.maxEntries * 2) {
+        await fs.writeFile(this.file, this.entries.map(item => `${JSON.stringify(item)}\n`).join(''), { mode: 0o600 });
+        this.lines = this.entries.length;
+      } else {
+        await fs.appendFile(this.file, `${JSON.stringify(entry)}\n`, { mode: 0o600 });
+      }
+    } catch (error) {
+      this.logger.warn('Could not write tool history entry:', error.message);
+    }
+    return entry;
+  }
+
+  get(id) {
+    return this.entries.find(entry => entry.id === id) || null;
+  }
+
+  // Newest call made with an idempotency key
+  findByIdempotencyKey(key) {
+    return this.entries.findLast(entry => entry.idempotencyKey === key) || null;
+  }
+
+  // A key may only be reused for the same tool with the same arguments
+  matchesCall(entry, tool, args) {
+    return entry.tool === tool &&
+      JSON.stringify(canonical(entry.arguments)) === JSON.stringify(canonical(redact(args || {})));
+  }
+
+  // Newest first
+  list({ tool = null, sessionId = null, since = null, until = null, status = null, errorKind = null, limit = 50 } = {}) {
+    const from = since ? Date.parse(since) : -Infinity;
+    const to = until ? Date.parse(until) : Infinity;
+    return this.entries
+      .filter(entry => (!tool || entry.tool === tool) &&
+        (!sessionId || entry.sessionId === sessionId) &&
+        (!status || entry.status === status) &&
+        (!errorKind || entry.error?.kind === errorKind) &&
+        Date.parse(entry.timestamp) >= from && Date.parse(entry.timestamp) <= to)
+      .reverse()
+      .slice(0, limit);
+  }
+
+  // Redacted values cannot be sent again
+  isReplayable(entry) {
+    return !JSON.stringify(entry.arguments).includes('"[redacted]"');
+  }
+}
@@ -0,0 +1,98 @@
+import { EventEmitter } from 'events';
+import path from 'path';
+import { ResourceLockedError } from '../core/errors.js';
+
+// Lock keys for the resources conflicting tools contend on
+export const LockKeys = {
+  device: (device) => `device:${baseDevice(device)}`,
+  mount: (target) => `mount:${path.resolve(target)}`,
+  // Each system root has its own package database
+  pacmanDb: (root = '/') => root === '/' ? 'pacman:db' : `pacman:db:${path.resolve(root)}`,
+  hyprlandConfig: () => 'hyprland:config',
+  gitRepo: (repoDir) => `git:${path.resolve(repoDir)}`
+};
+
+// Map partitions to their parent disk so /dev/sda1 and /dev/sda conflict
+export function baseDevice(device) {
+  const normalized = path.normalize(device);
+  if (/^\/dev\/(nvme\d+n\d+|mmcblk\d+|loop\d+)p\d+$/.test(normalized)) {
+    return normalized.replace(/p\d+$/, '');
+  }
+  if (/^\/dev\/(sd|vd|hd|xvd)[a-z]+\d+$/.test(normalized)) {
+    return normalized.replace(/\d+$/, '');
+  }
+  return normalized;
+}
+
+export class LockManager {
+  constructor(logger, options = {}) {
+    this.logger = logger;
+    this.waitTimeout = options.waitTimeout ?? 30000;
+    this.locks = new Map();
+    this.events = new EventEmitter();
+    this.events.setMaxListeners(0);
+  }
+
+  // Acquire every key or none; waits for conflicting holders up to the timeout
+  async acquire(keys, holder = {}, { waitTimeout = this.waitTimeout } = {}) {
+    const uniqueKeys = Array.from(new Set(keys)).sort();
+    if (uniqueKeys.length === 0) {
+      return () => {};
+    }
+
+    const deadline = Date.now() + waitTimeout;
+    const lockHolder = { ...holder, acquiredAt: null };
+
+    while (true) {
+      const blocking = uniqueKeys.find(key => this.locks.has(key));
+
+      if (!blocking) {
+        lockHolder.acquiredAt = new Date().toISOString();
+        for (const key of uniqueKeys) {
+          this.locks.set(key, lockHolder);
+        }
+        this.logger.debug('Acquired resource locks', { keys: uniqueKeys, holder: lockHolder });
+        return () => this.release(uniqueKeys, lockHolder);
+      }
+
+      const remaining = deadline - Date.now();
+      if (remaining <= 0) {
+        throw new ResourceLockedError(blocking, this.locks.get(blocking));
+      }
+
+      this.logger.debug(`Waiting for resource lock: ${blocking}`, {
+        holder: this.locks.get(blocking),
+        waiter: holder
+      });
+      await this.waitForRelease(remaining);
+    }
+  }
+
+  release(keys, holder) {
+    for (const key of keys) {
+      if (this.locks.get(key) === holder) {
+        this.locks.delete(key);
+      }
+    }
+    this.logger.debug('Released resource locks', { keys });
+    this.events.emit('released');
+  }
+
+  waitForRelease(timeout) {
+    return new Promise(resolve => {
+      const onRelease = () => {
+        clearTimeout(timer);
+        resolve();
+      };
+      const timer = setTimeout(() => {
+        this.events.off('released', onRelease);
+        resolve();
+      }, timeout);
+      this.events.once('released', onRelease);
+    });
+  }
+
+  getLocks() {
+    return Array.from(this.locks.entries()).map(([resource, holder]) => ({ resource, ...holder }));
+  }
+}
@@ -0,0 +1,234 @@
+import fs from 'fs-extra';
+import path from 'path';
+import { v4 as uuidv4 } from 'uuid';
+import { redact } from './transcript.js';
+import { errorOutcome } from '../core/errors.js';
+
+const DAYS = ['sun', 'mon', 'tue', 'wed', 'thu', 'fri', 'sat'];
+const DAY = `(?:${DAYS.join('|')})`;
+
+// "02:00-05:00" or "sat,sun 10:00-18:00" / "mon-fri 22:00-06:00"; a window may wrap past
+// midnight and its days are the days it starts on
+export const WINDOW_PATTERN = new RegExp(
+  `^(?:(${DAY}(?:[,-]${DAY})*)\\s+)?([01]?\\d|2[0-3]):([0-5]\\d)-([01]?\\d|2[0-4]):([0-5]\\d)$`, 'i');
+
+const FINISHED_JOBS_KEPT = 100;
+
+function parseDays(spec) {
+  const days = new Set();
+  for (const part of spec.toLowerCase().split(',')) {
+    const [from, to = from] = part.split('-').map(day => DAYS.indexOf(day));
+    for (let day = from; ; day = (day + 1) % 7) {
+      days.add(day);
+      if (day === to) break;
+    }
+  }
+  return days;
+}
+
+export function parseWindow(spec) {
+  const match = WINDOW_PATTERN.exec(spec.trim());
+  if (!match) {
+    throw new Error(`Invalid maintenance window: ${spec} (expected e.g. "02:00-05:00" or "sat,sun 10:00-18:00")`);
+  }
+  const [, days, startHour, startMinute, endHour, endMinute] = match;
+  return {
+    spec,
+    days: days ? parseDays(days) : null,
+    start: Number(startHour) * 60 + Number(startMinute),
+    end: Number(endHour) * 60 + Number(endMinute)
+  };
+}
+
+function startsOn(window, day) {
+  return !window.days || window.days.has(day);
+}
+
+export function inWindow(window, date) {
+  const minutes = date.getHours() * 60 + date.getMinutes();
+  const day = date.getDay();
+  if (window.start < window.end) {
+    return startsOn(window, day) && minutes >= window.start && minutes < window.end;
+  }
+  // Wraps past midnight (or, with equal times, lasts the whole day)
+  return (startsOn(window, day) && minutes >= window.start) ||
+    (startsOn(window, (day + 6) % 7) && minutes < window.end);
+}
+
+// Earliest start of any window after date, within the next week
+export function nextOpening(windows, date) {
+  let next = null;
+  for (let offset = 0; offset <= 7; offset++) {
+    for (const window of windows) {
+      const start = new Date(date);
+      start.setDate(start.getDate() + offset);
+      start.setHours(Math.floor(window.start / 60), window.start % 60, 0, 0);
+      if (start > date && startsOn(window, start.getDay()) && (!next || start < next)) {
+        next = start;
+      }
+    }
+  }
+  return next;
+}
+
+// When destructive tools may run: inside one of the configured time windows and, if
+// required, while logind reports no active user session. Calls outside are rejected or
+// kept as pending jobs that run once the window opens.
+export class MaintenanceWindow {
+  constructor(config = {}, logger, presence) {
+    this.logger = logger;
+    this.presence = presence;
+    this.enabled = config.enabled ?? false;
+    this.windows = (config.windows || []).map(parseWindow);
+    this.requireNoActiveSession = config.requireNoActiveSession ?? false;
+    this.outsideWindow = config.outsideWindow || 'reject';
+    this.tools = config.tools || [];
+    this.file = config.jobsFile || '/var/lib/mcp-arch-linux/jobs.json';
+    this.checkInterval = config.checkInterval || 60000;
+    this.jobs = [];
+    this.timer = null;
+    this.running = false;
+    this.saving = Promise.resolve();
+  }
+
+  async load() {
+    if (!this.enabled || !await fs.pathExists(this.file)) {
+      return;
+    }
+
+    this.jobs = await fs.readJson(this.file);
+    // Arguments are stored redacted; a job whose secrets were dropped cannot run after a restart
+    for (const job of this.jobs) {
+      if (['pending', 'running'].includes(job.status) && JSON.stringify(job.arguments).includes('"[redacted]"')) {
+        this.finish(job, 'failed', { error: { kind: 'tool_failed', message: 'The server restarted and the redacted arguments are gone' } });
+      } else if (job.status === 'running') {
+        this.finish(job, 'failed', { error: { kind: 'tool_failed', message: 'The server stopped while the job was running' } });
+      }
+    }
+    this.logger.debug(`Loaded ${this.jobs.length} maintenance jobs from ${this.file}`);
+  }
+
+  async status(now = new Date()) {
+    if (this.windows.length > 0 && !this.windows.some(window => inWindow(window, now))) {
+      return {
+        open: false,
+        reason: `outside the maintenance window${this.windows.length > 1 ? 's' : ''} ${this.windows.map(window => window.spec).join(', ')}`,
+        opensAt: nextOpening(this.windows, now)?.toISOString() ?? null
+      };
+    }
+
+    if (this.requireNoActiveSession) {
+      const sessions = await this.presence.activeSessions();
+      if (sessions.length > 0) {
+        return {
+          open: false,
+          reason: `user session${sessions.length > 1 ? 's' : ''} active: ${sessions.map(session => session.user).join(', ')}`,
+          sessions
+        };
+      }
+    }
+
+    return { open: true };
+  }
+
+  enqueue(tool, args, { sessionId = null, reason = null } = {}) {
+    const job = {
+      id: uuidv4(),
+      tool,
+      arguments: redact(args || {}),
+      status: 'pending',
+      sessionId,
+      queuedAt: new Date().toISOString(),
+      reason
+    };
+    // The real arguments only live in memory
+    Object.defineProperty(job, 'args', { value: args || {}, writable: true, enumerable: false });
+    this.jobs.push(job);
+    this.save();
+    this.logger.info(`Queued ${tool} until the maintenance window opens`, { jobId: job.id, reason });
+    return job;
+  }
+
+  get(id) {
+    return this.jobs.find(job => job.id === id) || null;
+  }
+
+  // Newest first
+  list({ status = null } = {}) {
+    return this.jobs.filter(job => !status || job.status === status).slice().reverse();
+  }
+
+  cancel(id) {
+    const job = this.get(id);
+    if (!job) {
+      throw new Error(`Unknown job: ${id}`);
+    }
+    if (job.status !== 'pending') {
+      throw new Error(`Job ${id} is ${job.status} and can no longer be cancelled`);
+    }
+    this.finish(job, 'cancelled');
+    return job;
+  }
+
+  finish(job, status, fields = {}) {
+    Object.assign(job, { status, finishedAt: new Date().toISOString(), ...fields });
+    const finished = this.jobs.filter(item => !['pending', 'running'].includes(item.status));
+    const drop = new Set(finished.slice(0, Math.max(finished.length - FINISHED_JOBS_KEPT, 0)));
+    this.jobs = this.jobs.filter(item => !drop.has(item));
+    this.save();
+  }
+
+  // Runs pending jobs in the order they were queued, rechecking the window before each
+  start(run) {
+    if (!this.enabled || this.timer) {
+      return;
+    }
+    this.timer = setInterval(() => this.runPending(run), this.checkInterval);
+    this.timer.unref?.();
+  }
+
+  stop() {
+    clearInterval(this.timer);
+    this.timer = null;
+  }
+
+  async runPending(run) {
+    if (this.running || !this.jobs.some(job => job.status === 'pending')) {
+      return;
+    }
+
+    this.running = true;
+    try {
+      let job;
+      while ((job = this.jobs.find(item => item.status === 'pending'))) {
+        const window = await this.status();
+        if (!window.open) {
+          break;
+        }
+
+        Object.assign(job, { status: 'running', startedAt: new Date().toISOString() });
+        this.save();
+        try {
+          const result = await run(job);
+          this.finish(job, result?.isError ? 'error' : 'done');
+        } catch (error) {
+          this.finish(job, 'failed', { error: errorOutcome(error) });
+        }
+      }
+    } catch (error) {
+      this.logger.warn('Running maintenance jobs failed:', error.message);
+    } finally {
+      this.running = false;
+    }
+  }
+
+  // Writes are chained so an older state never overwrites a newer one
+  save() {
+    const contents = JSON.stringify(this.jobs, null, 2);
+    this.saving = this.saving
+      .then(() => fs.ensureDir(path.dirname(this.file)))
+      .then(() => fs.writeFile(this.file, contents, { mode: 0o600 }))
+      .catch(error => this.logger.warn('Could not write maintenance jobs:', error.message));
+    return this.saving;
+  }
+}
@@ -0,0 +1,110 @@
+import crypto from 'crypto';
+
+// crypt(3) hashes in the server, so a plaintext password never reaches a command line,
+// a shell string or a log. libcrypt verifies SHA-512-crypt ($6$) on every distribution,
+// also where new passwords default to yescrypt, which Node cannot produce.
+const ITOA64 = './0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz';
+const DEFAULT_ROUNDS = 5000;
+// passwd's own default is 5000; more rounds make offline guessing proportionally slower
+export const SHA512_ROUNDS = 100000;
+
+// Hashes in the formats /etc/shadow accepts as they are: yescrypt, gost-yescrypt, SHA-512,
+// SHA-256 and bcrypt
+export const CRYPT_HASH = /^\$(y|gy|6|5|2[abxy])\$[./0-9A-Za-z$=,]+$/;
+
+function sha512(...parts) {
+  const hash = crypto.createHash('sha512');
+  for (const part of parts) hash.update(part);
+  return hash.digest();
+}
+
+// Repeats a digest to the given length, as the SHA-crypt spec does with P and S
+function stretch(digest, length) {
+  const output = Buffer.alloc(length);
+  for (let offset = 0; offset < length; offset += digest.length) {
+    digest.copy(output, offset, 0, Math.min(digest.length, length - offset));
+  }
+  return output;
+}
+
+function encode(bytes) {
+  const groups = [];
+  for (let index = 0; index < 21; index++) {
+    groups.push([index * 22 % 63, (index * 22 + 21) % 63, (index * 22 + 42) % 63]);
+  }
+  let output = '';
+  const put = (value, count) => {
+    for (let index = 0; index < count; index++) {
+      output += ITOA64[value & 0x3f];
+      value >>= 6;
+    }
+  };
+  for (const [a, b, c] of groups) {
+    put((bytes[a] << 16) | (bytes[b] << 8) | bytes[c], 4);
+  }
+  put(bytes[63], 2);
+  return output;
+}
+
+// Ulrich Drepper's SHA-512-crypt, as glibc and libxcrypt implement it
+export function sha512Crypt(password, { salt = null, rounds = SHA512_ROUNDS } = {}) {
+  const key = Buffer.from(password, 'utf8');
+  const saltText = (salt ?? Array.from(crypto.randomBytes(16), byte => ITOA64[byte & 0x3f]).join('')).slice(0, 16);
+  if (!/^[./0-9A-Za-z]+$/.test(saltText)) {
+    throw new Error('Salt characters must be from [./0-9A-Za-z]');
+  }
+  const saltBytes = Buffer.from(saltText);
+  const count = Math.min(Math.max(rounds, 1000), 999999999);
+
+  const alternate = sha512(key, saltBytes, key);
+  const initial = crypto.createHash('sha512').update(key).update(saltBytes).update(stretch(alternate, key.length));
+  for (let length = key.length; length > 0; length >>= 1) {
+    initial.update(length & 1 ? alternate : key);
+  }
+  let digest = initial.digest();
+
+  const keyDigest = sha512(...Array(key.length).fill(key));
+  const p = stretch(keyDigest, key.length);
+  const saltDigest = sha512(...Array(16 + digest[0]).fill(saltBytes));
+  const s = saltDigest.subarray(0, saltBytes.length);
+
+  for (let round = 0; round < count; round++) {
+    const hash = crypto.createHash('sha512');
+    hash.update(round & 1 ? p : digest);
+    if (round % 3) hash.update(s);
+    if (round % 7) hash.update(p);
+    hash.update(round & 1 ? digest : p);
+    digest = hash.digest();
+  }
+
+  const prefix = count === DEFAULT_ROUNDS ? '$6$' : `$6$rounds=${count}$`;
+  return `${prefix}${saltText}$${encode(digest)}`;
+}
+
+// Sets the password field of one account in /etc/shadow content, and the last change
+// date so password aging counts from now; other fields and lines stay as they are
+export function setShadowPassword(content, user, hash, { now = Date.now() } = {}) {
+  if (!CRYPT_HASH.test(hash)) {
+    throw new Error(`Not a crypt(3) hash for ${user}`);
+  }
+  let found = false;
+  const lines = content.split('\n').map(line => {
+    const fields = line.split(':');
+    if (fields[0] !== user || fields.length < 3) {
+      return line;
+    }
+    found = true;
+    fields[1] = hash;
+    fields[2] = String(Math.floor(now / 86400000));
+    return fields.join(':');
+  });
+  if (!found) {
+    throw new Error(`${user} has no entry in /etc/shadow`);
+  }
+  return lines.join('\n');
+}
+
+// Whether a shadow password field lets the account log in with a password
+export function hasUsablePassword(field) {
+  return Boolean(field) && !field.startsWith('!') && !field.startsWith('*');
+}
@@ -0,0 +1,26 @@
+import path from 'path';
+import { fileURLToPath } from 'url';
+import { getContext } from '../core/request-context.js';
+
+// Client roots arrive as file:// URIs; other schemes say nothing about local paths
+export function rootPaths(roots = []) {
+  return roots
+    .filter(root => typeof root?.uri === 'string' && root.uri.startsWith('file://'))
+    .map(root => path.resolve(fileURLToPath(root.uri)));
+}
+
+// Roots only ever narrow what the server allows; a client that lists none is not restricted
+export function isWithinRoots(file, roots = getContext().roots) {
+  if (!roots || roots.length === 0) {
+    return true;
+  }
+
+  const resolved = path.resolve(file);
+  return roots.some(root => resolved === root || resolved.startsWith(root.endsWith('/') ? root : `${root}/`));
+}
+
+export function assertWithinRoots(file, roots = getContext().roots) {
+  if (!isWithinRoots(file, roots)) {
+    throw new Error(`${file} is outside the client's roots (${roots.join(', ')})`);
+  }
+}
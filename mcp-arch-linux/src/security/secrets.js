@@ -0,0 +1,26 @@
+import fs from 'fs-extra';
+
+// Credentials are referenced from config, never written into it: "env:NAME" reads an
+// environment variable, "file:/path" the first line of a file readable only by the server
+export async function resolveSecret(ref) {
+  if (!ref) {
+    return null;
+  }
+
+  const [, scheme, value] = /^(env|file):(.+)$/.exec(ref) || [];
+  switch (scheme) {
+    case 'env':
+      if (process.env[value] === undefined) {
+        throw new Error(`Secret ${ref} is not set`);
+      }
+      return process.env[value];
+    case 'file':
+      try {
+        return (await fs.readFile(value, 'utf8')).split('\n')[0].trim();
+      } catch (error) {
+        throw new Error(`Cannot read secret ${ref}: ${error.message}`);
+      }
+    default:
+      throw new Error(`Secret references must be env:<NAME> or file:<path>, got ${ref}`);
+  }
+}
@@ -1,7 +1,17 @@
 import crypto from 'crypto';
-import fs from 'fs-extra';
 import path from 'path';
 import { v4 as uuidv4 } from 'uuid';
+import { LockManager } from './lock-manager.js';
+import { Transcript, redact } from './transcript.js';
+import { ToolHistory } from './history.js';
+import { RollbackManager } from '../system/rollback-manager.js';
+import { EtcVersioning } from '../system/etc-versioning.js';
+import { IdleInhibitor } from '../system/idle-inhibitor.js';
+import { MaintenanceWindow } from './maintenance-window.js';
+import { UserPresence } from '../system/presence.js';
+import { CommandExecutor } from '../system/command-executor.js';
+import { getContext } from '../core/request-context.js';
+import { errorOutcome } from '../core/errors.js';
 
 export class SecurityManager {
   constructor(config, logger) {
@@ -10,14 +20,48 @@ export class SecurityManager {
     this.operationCount = 0;
     this.maxConcurrentOperations = config.maxConcurrentOperations || 10;
     this.auditAll = config.auditAll || true;
+    this.autoSnapshot = config.autoSnapshot ?? true;
+    this.autoInhibitIdle = config.autoInhibitIdle ?? true;
+    this.readOnly = config.readOnly ?? false;
     this.activeOperations = new Map();
+    this.locks = new LockManager(logger, {
+      waitTimeout: config.lockWaitTimeout ?? 30000
+    });
+    this.rollback = new RollbackManager(config, logger);
+    this.etc = new EtcVersioning(config.etcVersioning || {}, logger, new CommandExecutor(config, logger));
+    this.idle = new IdleInhibitor(new CommandExecutor(config, logger), logger);
+    this.transcript = new Transcript(config.transcript || {}, logger);
+    this.history = new ToolHistory(config.history || {}, logger);
+    this.presence = new UserPresence(config.presence || {}, new CommandExecutor(config, logger), logger);
+    this.maintenance = new MaintenanceWindow(config.maintenance || {}, logger, this.presence);
+    // Set by the server so server-side callers (automation rules) run tools the way clients do
+    this.toolRunner = null;
   }
 
   async initialize() {
+    this.rollback.startPruning();
+
+    await this.history.load().catch(error => {
+      this.logger.warn('Could not load tool history:', error.message);
+    });
+
+    await this.maintenance.load().catch(error => {
+      this.logger.warn('Could not load maintenance jobs:', error.message);
+    });
+
+    // Import /etc before any tool touches it so the first change gets its own commit
+    if (this.etc.enabled) {
+      await this.etc.ensureRepo().catch(error => {
+        this.logger.warn('Could not initialize /etc version history:', error.message);
+      });
+    }
+
     this.logger.info('Security manager initialized', {
       requireAuth: this.config.requireAuth,
       maxConcurrentOperations: this.maxConcurrentOperations,
-      auditAll: this.auditAll
+      auditAll: this.auditAll,
+      readOnly: this.readOnly,
+      maintenanceWindows: this.maintenance.enabled ? this.maintenance.windows.map(window => window.spec) : null
     });
   }
 
@@ -42,25 +86,27 @@ export class SecurityManager {
         this.logger.audit('operation_start', {
           operationId,
           type: operationType,
-          context,
+          context: redact(context),
           timestamp: new Date().toISOString()
         });
       }
 
       // Execute operation
-      const result = await operation();
+      const result = await operation(operationId);
       
       // Log successful completion
       if (this.auditAll) {
         this.logger.audit('operation_success', {
           operationId,
           type: operationType,
-          context,
+          context: redact(context),
+          snapshotId: this.activeOperations.get(operationId)?.snapshotId,
           duration: Date.now() - startTime,
           timestamp: new Date().toISOString()
         });
       }
 
+      await this.recordTranscript(operationType, operationId, context, startTime, { result });
       return result;
 
     } catch (error) {
@@ -69,13 +115,16 @@ export class SecurityManager {
         this.logger.audit('operation_failure', {
           operationId,
           type: operationType,
-          context,
-          error: error.message,
+          context: redact(context),
+          snapshotId: this.activeOperations.get(operationId)?.snapshotId,
+          error: errorOutcome(error),
           duration: Date.now() - startTime,
           timestamp: new Date().toISOString()
         });
       }
-      
+
+      await this.recordTranscript(operationType, operationId, context, startTime, { error });
+
       throw error;
       
     } finally {
@@ -83,171 +132,99 @@ export class SecurityManager {
     }
   }
 
-  async createSnapshot(description, files = []) {
-    const snapshotId = uuidv4();
-    const timestamp = new Date().toISOString();
-    
-    this.logger.info(`Creating system snapshot: ${snapshotId}`);
-
-    const snapshot = {
-      id: snapshotId,
-      description,
-      timestamp,
-      files: [],
-      services: [],
-      metadata: {
-        hostname: await this.getHostname(),
-        user: process.env.USER || 'unknown',
-        node: process.version
-      }
-    };
-
-    // Backup specified files
-    for (const filePath of files) {
-      try {
-        if (await fs.pathExists(filePath)) {
-          const stats = await fs.stat(filePath);
-          const content = await fs.readFile(filePath, 'utf8');
-          
-          snapshot.files.push({
-            path: filePath,
-            content,
-            mode: stats.mode,
-            size: stats.size,
-            mtime: stats.mtime
-          });
-        }
-      } catch (error) {
-        this.logger.warn(`Failed to backup file ${filePath}:`, error.message);
-      }
-    }
-
-    // Get systemd service states
-    try {
-      const services = await this.getServiceStates();
-      snapshot.services = services;
-    } catch (error) {
-      this.logger.warn('Failed to capture service states:', error.message);
+  // Only tool calls change the machine; resource reads and reloads stay out of the report and history
+  async recordTranscript(operationType, operationId, context, startTime, { result = null, error = null }) {
+    if (operationType !== 'tool_call') {
+      return;
     }
 
-    // Save snapshot
-    const snapshotPath = path.join(
-      this.config.snapshotDir || '/var/lib/mcp-arch-linux/snapshots',
-      `${snapshotId}.json`
-    );
-    
-    await fs.ensureDir(path.dirname(snapshotPath));
-    await fs.writeJson(snapshotPath, snapshot, { spaces: 2 });
-
-    this.logger.audit('snapshot_created', {
-      snapshotId,
-      description,
-      fileCount: snapshot.files.length,
-      serviceCount: snapshot.services.length
-    });
-
-    return snapshotId;
+    const active = this.activeOperations.get(operationId);
+    const call = {
+      sessionId: getContext().sessionId,
+      operationId,
+      tool: context.tool,
+      args: context.arguments,
+      result,
+      error,
+      snapshotId: active?.snapshotId,
+      etcCommit: active?.etcCommit,
+      hosts: context.hosts,
+      startTime
+    };
+    await this.transcript.recordToolCall(call);
+    await this.history.record({ ...call, replayOf: context.replayOf, idempotencyKey: context.idempotencyKey, jobId: context.jobId });
   }
 
-  async restoreSnapshot(snapshotId) {
-    this.logger.info(`Restoring system snapshot: ${snapshotId}`);
-
-    const snapshotPath = path.join(
-      this.config.snapshotDir || '/var/lib/mcp-arch-linux/snapshots',
-      `${snapshotId}.json`
-    );
+  async createSnapshot(description, files = [], options = {}) {
+    return this.rollback.createSnapshot(description, files, options);
+  }
 
-    if (!await fs.pathExists(snapshotPath)) {
-      throw new Error(`Snapshot not found: ${snapshotId}`);
+  // Snapshot the paths a tool declared as affected, tagged with the audit operation id
+  async createOperationSnapshot(operationId, toolName, paths, { packages = false } = {}) {
+    if (!this.autoSnapshot || (paths.length === 0 && !packages)) {
+      return null;
     }
 
-    const snapshot = await fs.readJson(snapshotPath);
-
-    // Restore files
-    for (const file of snapshot.files) {
-      try {
-        await fs.ensureDir(path.dirname(file.path));
-        await fs.writeFile(file.path, file.content);
-        await fs.chmod(file.path, file.mode);
-        this.logger.debug(`Restored file: ${file.path}`);
-      } catch (error) {
-        this.logger.error(`Failed to restore file ${file.path}:`, error.message);
+    try {
+      const snapshotId = await this.rollback.createSnapshot(
+        `Automatic snapshot before ${toolName}`,
+        paths,
+        { operation: { id: operationId, tool: toolName }, packages }
+      );
+
+      const active = this.activeOperations.get(operationId);
+      if (active) {
+        active.snapshotId = snapshotId;
       }
+
+      this.logger.audit('operation_snapshot', { operationId, tool: toolName, snapshotId });
+      return snapshotId;
+    } catch (error) {
+      this.logger.warn(`Automatic snapshot before ${toolName} failed:`, error.message);
+      return null;
     }
+  }
 
-    // Restore services (basic implementation)
-    for (const service of snapshot.services) {
-      try {
-        if (service.enabled !== service.currentEnabled) {
-          const action = service.enabled ? 'enable' : 'disable';
-          // This would need CommandExecutor integration
-          this.logger.debug(`Would ${action} service: ${service.name}`);
-        }
-        
-        if (service.active !== service.currentActive) {
-          const action = service.active ? 'start' : 'stop';
-          this.logger.debug(`Would ${action} service: ${service.name}`);
+  // Commit tool changes under /etc so they can be reviewed and reverted individually
+  async commitEtcChanges(operationId, toolName, paths) {
+    try {
+      const commit = await this.etc.commitOperation(operationId, toolName, paths);
+      if (commit) {
+        this.logger.audit('operation_etc_commit', { operationId, tool: toolName, commit });
+
+        const active = this.activeOperations.get(operationId);
+        if (active) {
+          active.etcCommit = commit;
         }
-      } catch (error) {
-        this.logger.error(`Failed to restore service ${service.name}:`, error.message);
       }
+      return commit;
+    } catch (error) {
+      this.logger.warn(`Committing /etc changes after ${toolName} failed:`, error.message);
+      return null;
     }
+  }
 
-    this.logger.audit('snapshot_restored', {
-      snapshotId,
-      fileCount: snapshot.files.length,
-      serviceCount: snapshot.services.length
-    });
+  // Most recent automatic snapshot, optionally for a specific audit operation id
+  async findOperationSnapshot(operationId = null) {
+    const snapshots = await this.rollback.listSnapshots();
+    return snapshots.find(snapshot => snapshot.operation &&
+      (!operationId || snapshot.operation.id === operationId)) || null;
+  }
 
-    return true;
+  async restoreSnapshot(snapshotId, options = {}) {
+    return this.rollback.restoreSnapshot(snapshotId, options);
   }
 
   async listSnapshots() {
-    const snapshotDir = this.config.snapshotDir || '/var/lib/mcp-arch-linux/snapshots';
-    
-    if (!await fs.pathExists(snapshotDir)) {
-      return [];
-    }
-
-    const files = await fs.readdir(snapshotDir);
-    const snapshots = [];
-
-    for (const file of files) {
-      if (path.extname(file) === '.json') {
-        try {
-          const snapshotPath = path.join(snapshotDir, file);
-          const snapshot = await fs.readJson(snapshotPath);
-          snapshots.push({
-            id: snapshot.id,
-            description: snapshot.description,
-            timestamp: snapshot.timestamp,
-            fileCount: snapshot.files.length,
-            serviceCount: snapshot.services.length
-          });
-        } catch (error) {
-          this.logger.warn(`Failed to read snapshot ${file}:`, error.message);
-        }
-      }
-    }
-
-    return snapshots.sort((a, b) => new Date(b.timestamp) - new Date(a.timestamp));
+    return this.rollback.listSnapshots();
   }
 
   async deleteSnapshot(snapshotId) {
-    const snapshotPath = path.join(
-      this.config.snapshotDir || '/var/lib/mcp-arch-linux/snapshots',
-      `${snapshotId}.json`
-    );
-
-    if (!await fs.pathExists(snapshotPath)) {
-      throw new Error(`Snapshot not found: ${snapshotId}`);
-    }
+    return this.rollback.deleteSnapshot(snapshotId);
+  }
 
-    await fs.remove(snapshotPath);
-    
-    this.logger.audit('snapshot_deleted', { snapshotId });
-    
-    return true;
+  async pruneSnapshots(options = {}) {
+    return this.rollback.pruneSnapshots(options);
   }
 
   async validateInput(data, rules) {
@@ -295,30 +272,20 @@ export class SecurityManager {
     return crypto.createHash('sha256').update(JSON.stringify(data)).digest('hex');
   }
 
-  async getHostname() {
-    try {
-      const { CommandExecutor } = await import('./command-executor.js');
-      const executor = new CommandExecutor(this.config, this.logger, this);
-      const result = await executor.execute('hostname');
-      return result.stdout;
-    } catch {
-      return 'unknown';
-    }
-  }
-
-  async getServiceStates() {
-    // This would integrate with CommandExecutor to get actual service states
-    // For now, return empty array
-    return [];
-  }
-
   getActiveOperations() {
     return Array.from(this.activeOperations.values());
   }
 
+  getHeldLocks() {
+    return this.locks.getLocks();
+  }
+
   async cleanup() {
     // Cancel any active operations if needed
     this.activeOperations.clear();
+    this.rollback.stopPruning();
+    this.maintenance.stop();
+    this.idle.releaseAll();
     this.logger.info('Security manager cleaned up');
   }
 }
\ No newline at end of file
@@ -0,0 +1,134 @@
+import fs from 'fs-extra';
+import path from 'path';
+import { errorOutcome } from '../core/errors.js';
+
+const SECRET_KEY = /pass(word|wd|phrase)?(hash)?$|secret|token|credential|private.?key|api.?key|^key$/i;
+const RESULT_LIMIT = 4000;
+
+// Replaces values under secret-looking keys so arguments can be logged and shown
+export function redact(value) {
+  if (Array.isArray(value)) {
+    return value.map(redact);
+  }
+  if (value && typeof value === 'object') {
+    return Object.fromEntries(Object.entries(value).map(([key, inner]) =>
+      [key, SECRET_KEY.test(key) && inner !== null && typeof inner !== 'object' ? '[redacted]' : redact(inner)]));
+  }
+  return value;
+}
+
+function summarizeResult(result) {
+  const text = (result?.content || [])
+    .map(item => item.type === 'text' ? item.text : `[${item.type}${item.mimeType ? ` ${item.mimeType}` : ''}]`)
+    .join('\n');
+  return text.length > RESULT_LIMIT ? `${text.slice(0, RESULT_LIMIT)}\n... (${text.length - RESULT_LIMIT} more characters)` : text;
+}
+
+// Chronological record of tool calls and agent notes for this server run, appended
+// to a JSONL file so the report survives restarts
+export class Transcript {
+  constructor(config = {}, logger) {
+    this.logger = logger;
+    this.enabled = config.enabled ?? true;
+    this.file = config.file || '/var/lib/mcp-arch-linux/transcript.jsonl';
+    this.maxEntries = config.maxEntries || 1000;
+    this.startedAt = new Date().toISOString();
+    this.entries = [];
+  }
+
+  async append(entry) {
+    if (!this.enabled) {
+      return null;
+    }
+
+    this.entries.push(entry);
+    if (this.entries.length > this.maxEntries) {
+      this.entries.shift();
+    }
+
+    try {
+      await fs.ensureDir(path.dirname(this.file));
+      await fs.appendFile(this.file, `${JSON.stringify(entry)}\n`, { mode: 0o600 });
+    } catch (error) {
+      this.logger.warn('Could not write transcript entry:', error.message);
+    }
+    return entry;
+  }
+
+  recordToolCall({ sessionId, operationId, tool, args, result, error, snapshotId, etcCommit, hosts, startTime }) {
+    return this.append({
+      type: 'tool_call',
+      timestamp: new Date(startTime).toISOString(),
+      sessionId: sessionId || null,
+      operationId,
+      tool,
+      arguments: redact(args || {}),
+      status: error ? 'failed' : result?.isError ? 'error' : 'success',
+      duration: Date.now() - startTime,
+      ...(snapshotId ? { snapshotId } : {}),
+      ...(etcCommit ? { etcCommit } : {}),
+      ...(hosts ? { hosts } : {}),
+      ...(error ? { error: errorOutcome(error) } : { result: summarizeResult(result) })
+    });
+  }
+
+  recordNote({ sessionId, text, kind = 'note' }) {
+    return this.append({
+      type: kind,
+      timestamp: new Date().toISOString(),
+      sessionId: sessionId || null,
+      text
+    });
+  }
+
+  list({ sessionId = null } = {}) {
+    return sessionId ? this.entries.filter(entry => entry.sessionId === sessionId) : [...this.entries];
+  }
+
+  toMarkdown({ sessionId = null } = {}) {
+    const entries = this.list({ sessionId });
+    const calls = entries.filter(entry => entry.type === 'tool_call');
+    const lines = [
+      '# System Change Report',
+      '',
+      `- Host: ${process.env.HOSTNAME || 'localhost'}`,
+      `- Server started: ${this.startedAt}`,
+      ...(sessionId ? [`- Session: ${sessionId}`] : []),
+      `- Tool calls: ${calls.length} (${calls.filter(entry => entry.status !== 'success').length} failed)`,
+      `- Snapshots: ${calls.filter(entry => entry.snapshotId).map(entry => `\`${entry.snapshotId}\``).join(', ') || 'none'}`,
+      ''
+    ];
+
+    entries.forEach((entry, index) => {
+      if (entry.type !== 'tool_call') {
+        lines.push(`## ${index + 1}. ${entry.type === 'plan' ? 'Plan' : 'Note'} — ${entry.timestamp}`, '', entry.text, '');
+        return;
+      }
+
+      lines.push(
+        `## ${index + 1}. \`${entry.tool}\` — ${entry.status} — ${entry.timestamp}`,
+        '',
+        `- Duration: ${entry.duration} ms`,
+        ...(entry.hosts ? [`- Hosts: ${entry.hosts.join(', ')}`] : []),
+        ...(entry.snapshotId ? [`- Snapshot: \`${entry.snapshotId}\` (undo with \`system_undo\`)`] : []),
+        ...(entry.etcCommit ? [`- /etc commit: \`${entry.etcCommit}\``] : []),
+        '',
+        '```json',
+        JSON.stringify(entry.arguments, null, 2),
+        '```',
+        ''
+      );
+
+      // Entries written before errors were recorded as { kind, message } hold a plain string
+      const output = entry.error ? entry.error.message ?? entry.error : entry.result;
+      if (output) {
+        // A fence longer than any backtick run in the output keeps it from closing early
+        const longest = Math.max(0, ...(output.match(/`+/g) || []).map(run => run.length));
+        const fence = '`'.repeat(Math.max(3, longest + 1));
+        lines.push(entry.error ? `**Error${entry.error.kind ? ` (${entry.error.kind})` : ''}:**` : '**Result:**', '', fence, output, fence, '');
+      }
+    });
+
+    return lines.join('\n');
+  }
+}
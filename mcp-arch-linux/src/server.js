@@ -5,6 +5,12 @@ import { createServer } from './core/mcp-server.js';
 import { Logger } from './core/logger.js';
 import { Config } from './core/config.js';
 import { SecurityManager } from './security/security-manager.js';
+import { runCall, runRepl } from './cli/client-commands.js';
+import { runSelftest } from './cli/selftest.js';
+import { SystemdNotifier, getListenFds } from './system/systemd-notify.js';
+import { StdioTransport } from './core/stdio-transport.js';
+import { UnixSocketListener, parseUnixAddress } from './core/unix-socket.js';
+import { parseSize } from './system/disk-quota.js';
 
 const program = new Command();
 
@@ -12,32 +18,80 @@ program
   .name('mcp-arch-server')
   .description('MCP server for Arch Linux system control')
   .version('1.0.0')
-  .option('-c, --config <path>', 'config file path', './config/server.yaml')
-  .option('-p, --port <number>', 'server port', '8080')
-  .option('-h, --host <address>', 'server host', 'localhost')
+  .option('-c, --config <path>', 'config file path (default: search /etc/mcp-arch-linux and $XDG_CONFIG_HOME/mcp-arch-linux)')
+  .option('-p, --port <number>', 'server port')
+  .option('-h, --host <address>', 'server host, or unix:///path/to.sock for a local socket')
+  .option('--stdio', 'talk MCP over stdin/stdout instead of HTTP, for clients that start the server themselves')
   .option('--debug', 'enable debug logging')
   .option('--no-auth', 'disable authentication (development only)')
-  .parse();
+  .option('--simulate', 'never run commands; answer them from simulation rules (development and CI)')
+  .option('--read-only', 'only allow tools that inspect the system (and dry runs)')
+  .action(() => main());
 
-const options = program.opts();
+const collect = (value, previous) => [...previous, value];
+
+program
+  .command('call <tool>')
+  .description('call a tool on a running server and print the result')
+  .option('-a, --arg <key=value>', 'tool argument, repeatable; values are parsed as JSON when possible', collect, [])
+  .option('--json <object>', 'tool arguments as a JSON object')
+  .option('--url <url>', 'server endpoint (default: from the config file)')
+  .option('--raw', 'print the raw JSON result')
+  .option('--timeout <ms>', 'request timeout', (value) => parseInt(value), 300000)
+  .action(async (tool, commandOptions) => {
+    process.exitCode = await runCall(tool, { ...program.opts(), ...commandOptions });
+  });
+
+program
+  .command('repl')
+  .description('interactive session against a running server')
+  .option('--url <url>', 'server endpoint (default: from the config file)')
+  .option('--timeout <ms>', 'request timeout', (value) => parseInt(value), 300000)
+  .action(async (commandOptions) => {
+    process.exitCode = await runRepl({ ...program.opts(), ...commandOptions });
+  });
+
+program
+  .command('selftest')
+  .description('check every registered tool on this machine: schemas, external binaries and a dry-run or read-only call')
+  .option('--no-run', 'only check schemas and binaries, do not call any tool')
+  .option('--json', 'print the readiness matrix as JSON')
+  .action(async (commandOptions) => {
+    process.exitCode = await runSelftest({ ...program.opts(), ...commandOptions });
+  });
+
+let options = {};
 
 async function main() {
+  options = program.opts();
+
   try {
+    // CLI options are the top configuration layer, validated along with everything else
+    const overrides = {};
+    if (options.port) overrides.server = { ...overrides.server, port: parseInt(options.port) };
+    if (options.host) overrides.server = { ...overrides.server, host: options.host };
+    if (options.stdio) overrides.server = { ...overrides.server, transport: 'stdio' };
+    if (options.debug) overrides.logging = { level: 'debug' };
+    if (options.auth === false) overrides.security = { ...overrides.security, requireAuth: false };
+    if (options.readOnly) overrides.security = { ...overrides.security, readOnly: true };
+    if (options.simulate) overrides.simulation = { enabled: true };
+
     // Initialize configuration
     const config = new Config(options.config);
-    await config.load();
-    
-    // Override config with CLI options
-    if (options.port) config.set('server.port', parseInt(options.port));
-    if (options.host) config.set('server.host', options.host);
-    if (options.debug) config.set('logging.level', 'debug');
-    if (options.noAuth) config.set('security.requireAuth', false);
+    await config.load({ overrides });
     
-    // Initialize logger
-    const logger = new Logger(config.get('logging'));
+    const stdio = config.get('server.transport') === 'stdio';
+
+    // Initialize logger; stdout belongs to the protocol under the stdio transport
+    const logger = new Logger({ ...config.get('logging'), stderr: stdio });
     
     // Initialize security manager
-    const security = new SecurityManager(config.get('security'), logger);
+    const security = new SecurityManager({
+      ...config.get('security'),
+      snapshotDir: config.get('plugins.system.snapshotDir'),
+      retention: config.get('plugins.system.retention'),
+      storage: config.get('storage')
+    }, logger);
     await security.initialize();
     
     // Create and start server
@@ -45,15 +99,49 @@ async function main() {
     
     const host = config.get('server.host');
     const port = config.get('server.port');
+    const socketPath = parseUnixAddress(host);
+    
+    const notifier = new SystemdNotifier(logger);
+    const [listenFd] = getListenFds();
     
     logger.info(`Starting MCP Arch Linux Server v${program.version()}`);
-    logger.info(`Listening on ${host}:${port}`);
     
-    await server.listen({ host, port });
+    const unixSocket = stdio ? null : new UnixSocketListener(server, { ...config.get('server.socket'), socketPath, logger });
+    await unixSocket?.prepare();
+
+    let transport = null;
+    if (stdio) {
+      await server.ready();
+      transport = new StdioTransport({
+        protocol: server.mcpProtocol,
+        logger,
+        maxRequestSize: parseSize(config.get('server.limits.maxRequestSize') ?? '1MB')
+      });
+      transport.start();
+    } else if (listenFd !== undefined) {
+      // Socket activation: serve on the fd inherited from the systemd socket unit
+      logger.info(`Listening on socket-activated fd ${listenFd}`);
+      await server.ready();
+      await new Promise((resolve, reject) => {
+        server.server.once('error', reject);
+        server.server.listen({ fd: listenFd }, resolve);
+      });
+    } else if (socketPath) {
+      logger.info(`Listening on ${socketPath}`);
+      await unixSocket.listen();
+    } else {
+      logger.info(`Listening on ${host}:${port}`);
+      await server.listen({ host, port });
+    }
+    
+    const endpoint = stdio ? 'stdin/stdout' : listenFd !== undefined ? `fd ${listenFd}` : socketPath || `${host}:${port}`;
+    await notifier.ready(`Serving MCP on ${endpoint}`);
+    notifier.startWatchdog(() => (transport ? transport.open : server.server.listening));
     
     // Graceful shutdown
     const shutdown = async (signal) => {
       logger.info(`Received ${signal}, shutting down gracefully...`);
+      await notifier.stopping();
       try {
         await server.close();
         await security.cleanup();
@@ -67,6 +155,21 @@ async function main() {
     
     process.on('SIGTERM', () => shutdown('SIGTERM'));
     process.on('SIGINT', () => shutdown('SIGINT'));
+    // A stdio client ends the server by closing its end of the pipe
+    transport?.closed.then(() => shutdown('end of stdin'));
+    
+    process.on('SIGHUP', async () => {
+      logger.info('Received SIGHUP, reloading configuration');
+      await notifier.notify('RELOADING=1');
+      try {
+        const { applied, requiresRestart } = await server.reloadConfig();
+        logger.info('Configuration reloaded', { applied, requiresRestart });
+        await notifier.ready('Configuration reloaded');
+      } catch (error) {
+        logger.error('Configuration reload failed, keeping current settings:', error.message);
+        await notifier.ready('Configuration reload failed');
+      }
+    });
     
   } catch (error) {
     console.error('Failed to start server:', error);
@@ -86,4 +189,7 @@ process.on('uncaughtException', (error) => {
   process.exit(1);
 });
 
-main().catch(console.error);
\ No newline at end of file
+program.parseAsync().catch((error) => {
+  console.error(error);
+  process.exit(1);
+});
\ No newline at end of file
@@ -0,0 +1,56 @@
+import fs from 'fs-extra';
+
+export const NEWS_FEED_URL = 'https://archlinux.org/feeds/news/';
+
+// Arch news titles that precede breaking upgrades use this wording
+const MANUAL_INTERVENTION = /manual intervention|intervention required|action required|requires? manual/i;
+
+function decodeEntities(text) {
+  return text
+    .replace(/<!\[CDATA\[([\s\S]*?)\]\]>/g, '$1')
+    .replace(/&lt;/g, '<')
+    .replace(/&gt;/g, '>')
+    .replace(/&quot;/g, '"')
+    .replace(/&#39;|&#x27;/g, '\'')
+    .replace(/&amp;/g, '&');
+}
+
+function tag(xml, name) {
+  const match = xml.match(new RegExp(`<${name}[^>]*>([\\s\\S]*?)</${name}>`));
+  return match ? decodeEntities(match[1]).trim() : null;
+}
+
+export function parseNewsFeed(xml) {
+  return [...xml.matchAll(/<item>([\s\S]*?)<\/item>/g)].map(([, item]) => {
+    const title = tag(item, 'title') || '';
+    const published = tag(item, 'pubDate');
+    return {
+      title,
+      link: tag(item, 'link'),
+      published: published ? new Date(published).toISOString() : null,
+      summary: (tag(item, 'description') || '').replace(/<[^>]+>/g, ' ').replace(/\s+/g, ' ').trim().slice(0, 500),
+      manualIntervention: MANUAL_INTERVENTION.test(title)
+    };
+  });
+}
+
+export async function fetchArchNews(url = NEWS_FEED_URL, { timeout = 15000 } = {}) {
+  const response = await fetch(url, { signal: AbortSignal.timeout(timeout) });
+  if (!response.ok) {
+    throw new Error(`Arch news feed returned HTTP ${response.status}`);
+  }
+  return parseNewsFeed(await response.text());
+}
+
+// Time of the last full upgrade according to pacman's own log
+export async function lastUpgradeTime(logPath = '/var/log/pacman.log') {
+  const log = await fs.readFile(logPath, 'utf8').catch(() => '');
+  const starts = [...log.matchAll(/^\[([^\]]+)\] \[PACMAN\] starting full system upgrade$/gm)];
+  if (starts.length === 0) {
+    return null;
+  }
+
+  const stamp = starts[starts.length - 1][1].replace(/([+-]\d{2})(\d{2})$/, '$1:$2');
+  const date = new Date(stamp);
+  return isNaN(date) ? null : date;
+}
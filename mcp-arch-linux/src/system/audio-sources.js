@@ -0,0 +1,81 @@
+// Audio sources as PulseAudio (or pipewire-pulse) names them; wf-recorder records from
+// one source, so desktop sound and a microphone are mixed into a null sink first
+const MIX_SINK = 'mcp_recording_mix';
+
+export class AudioSources {
+  constructor(commandExecutor) {
+    this.commandExecutor = commandExecutor;
+  }
+
+  async pactl(args) {
+    const result = await this.commandExecutor.execute('pactl', args);
+    if (!result.success) {
+      throw new Error(`pactl ${args[0]} failed: ${result.stderr.trim()}`);
+    }
+    return result.stdout.trim();
+  }
+
+  // Monitors of output sinks carry desktop sound; the rest are microphones and line inputs
+  async list() {
+    const [sources, defaultSink, defaultSource] = await Promise.all([
+      this.pactl(['-f', 'json', 'list', 'sources']).then(JSON.parse),
+      this.pactl(['get-default-sink']).catch(() => null),
+      this.pactl(['get-default-source']).catch(() => null)
+    ]);
+
+    return sources
+      .filter(source => !source.name.startsWith(MIX_SINK))
+      .map(source => {
+        const monitorOf = source.monitor_of_sink && source.monitor_of_sink !== 'n/a' ? source.monitor_of_sink : null;
+        return {
+          name: source.name,
+          description: source.description,
+          kind: monitorOf ? 'desktop' : 'microphone',
+          monitorOf,
+          state: source.state,
+          default: monitorOf ? monitorOf === defaultSink : source.name === defaultSource
+        };
+      });
+  }
+
+  // 'desktop', 'microphone', 'mix' or a source name, to the source names it stands for
+  async resolve(selection) {
+    if (selection === 'desktop') {
+      return [`${await this.pactl(['get-default-sink'])}.monitor`];
+    }
+    if (selection === 'microphone') {
+      return [await this.pactl(['get-default-source'])];
+    }
+    if (selection === 'mix') {
+      return [...await this.resolve('desktop'), ...await this.resolve('microphone')];
+    }
+    if (!(await this.list()).some(source => source.name === selection)) {
+      throw new Error(`Unknown audio source: ${selection} (see list_audio_sources)`);
+    }
+    return [selection];
+  }
+
+  // Loops each source into a fresh null sink; record from the returned source and pass the
+  // module ids to unload() afterwards
+  async createMix(sources) {
+    const modules = [];
+    try {
+      modules.push(await this.pactl(['load-module', 'module-null-sink', `sink_name=${MIX_SINK}`,
+        `sink_properties=device.description=${MIX_SINK}`]));
+      for (const source of sources) {
+        modules.push(await this.pactl(['load-module', 'module-loopback', `source=${source}`, `sink=${MIX_SINK}`, 'latency_msec=20']));
+      }
+    } catch (error) {
+      await this.unload(modules);
+      throw error;
+    }
+    return { source: `${MIX_SINK}.monitor`, modules };
+  }
+
+  // Loopbacks go before the sink they feed
+  async unload(modules = []) {
+    for (const id of [...modules].reverse()) {
+      await this.pactl(['unload-module', String(id)]).catch(() => {});
+    }
+  }
+}
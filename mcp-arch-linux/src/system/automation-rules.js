@@ -0,0 +1,184 @@
+import fs from 'fs-extra';
+import path from 'path';
+import { hyprlandCommandLine } from './compositor.js';
+
+// Fields of each Hyprland event a rule can match and use as {placeholders}; the last
+// field takes the rest of the line, since titles may contain commas
+export const EVENT_FIELDS = {
+  openwindow: ['address', 'workspace', 'class', 'title'],
+  closewindow: ['address'],
+  movewindow: ['address', 'workspace'],
+  activewindow: ['class', 'title'],
+  fullscreen: ['state'],
+  workspace: ['workspace'],
+  createworkspace: ['workspace'],
+  destroyworkspace: ['workspace'],
+  focusedmon: ['monitor', 'workspace'],
+  monitoradded: ['monitor'],
+  monitorremoved: ['monitor']
+};
+
+const RULE_NAME = /^[A-Za-z0-9_.-]+$/;
+// A rule whose actions keep triggering itself is held back instead of looping
+const MAX_FIRINGS_PER_MINUTE = 30;
+
+export function parseEvent(name, data) {
+  const fields = EVENT_FIELDS[name];
+  if (!fields) {
+    return null;
+  }
+  const parts = data.split(',');
+  const values = Object.fromEntries(fields.map((field, index) =>
+    [field, index === fields.length - 1 ? parts.slice(index).join(',') : parts[index] ?? '']));
+  // The event socket leaves off the 0x that window selectors expect
+  if (values.address) {
+    values.address = `0x${values.address}`;
+  }
+  return values;
+}
+
+function fill(value, fields) {
+  if (typeof value === 'string') {
+    return value.replace(/\{(\w+)\}/g, (match, field) => fields[field] ?? match);
+  }
+  if (Array.isArray(value)) {
+    return value.map(item => fill(item, fields));
+  }
+  if (value && typeof value === 'object') {
+    return Object.fromEntries(Object.entries(value).map(([key, item]) => [key, fill(item, fields)]));
+  }
+  return value;
+}
+
+// Rules react to Hyprland events: { name, on, match, actions }. match maps event fields to
+// regular expressions; actions are dispatchers, keywords or tool calls, with {field}
+// placeholders filled from the event. Rules from the config are fixed; rules added by
+// tools are kept in rulesFile.
+export class AutomationRules {
+  constructor(config = {}, logger) {
+    this.logger = logger;
+    this.file = config.rulesFile || '/var/lib/mcp-arch-linux/hyprland-rules.json';
+    this.configRules = (config.rules || []).map(rule => this.validate(rule, 'config'));
+    this.added = [];
+    this.stats = new Map();
+  }
+
+  async load() {
+    if (!await fs.pathExists(this.file)) {
+      return;
+    }
+    this.added = [];
+    for (const rule of await fs.readJson(this.file)) {
+      try {
+        this.added.push(this.validate(rule, 'tool'));
+      } catch (error) {
+        this.logger.warn(`Skipping automation rule from ${this.file}: ${error.message}`);
+      }
+    }
+  }
+
+  validate(rule, source) {
+    const { name, on, match = {}, actions } = rule || {};
+    if (typeof name !== 'string' || !RULE_NAME.test(name)) {
+      throw new Error(`Invalid rule name: ${name} (letters, digits, ".", "_", "-")`);
+    }
+    if (!EVENT_FIELDS[on]) {
+      throw new Error(`Rule ${name}: unknown event ${on} (known: ${Object.keys(EVENT_FIELDS).join(', ')})`);
+    }
+    for (const [field, pattern] of Object.entries(match)) {
+      if (!EVENT_FIELDS[on].includes(field)) {
+        throw new Error(`Rule ${name}: ${on} has no field ${field} (fields: ${EVENT_FIELDS[on].join(', ')})`);
+      }
+      try {
+        new RegExp(pattern);
+      } catch (error) {
+        throw new Error(`Rule ${name}: invalid pattern for ${field}: ${error.message}`);
+      }
+    }
+    if (!Array.isArray(actions) || actions.length === 0) {
+      throw new Error(`Rule ${name} needs at least one action`);
+    }
+    actions.forEach((action, index) => {
+      if (action?.type === 'tool') {
+        if (typeof action.name !== 'string' || !action.name) {
+          throw new Error(`Rule ${name}: actions[${index}] needs a tool name`);
+        }
+      } else {
+        hyprlandCommandLine(action, `Rule ${name}: actions[${index}]`);
+      }
+    });
+    return { name, on, match, actions, source };
+  }
+
+  list() {
+    return [...this.configRules, ...this.added].map(rule => {
+      const { recent, ...stats } = this.stats.get(rule.name) || { fired: 0 };
+      return { ...rule, ...stats };
+    });
+  }
+
+  async add(rule) {
+    const validated = this.validate(rule, 'tool');
+    if (this.configRules.some(existing => existing.name === validated.name)) {
+      throw new Error(`Rule ${validated.name} comes from the config and cannot be replaced`);
+    }
+    this.added = [...this.added.filter(existing => existing.name !== validated.name), validated];
+    await this.save();
+    return validated;
+  }
+
+  async remove(name) {
+    if (this.configRules.some(rule => rule.name === name)) {
+      throw new Error(`Rule ${name} comes from the config; remove it there`);
+    }
+    if (!this.added.some(rule => rule.name === name)) {
+      throw new Error(`Unknown rule: ${name}`);
+    }
+    this.added = this.added.filter(rule => rule.name !== name);
+    this.stats.delete(name);
+    await this.save();
+  }
+
+  async save() {
+    await fs.ensureDir(path.dirname(this.file));
+    await fs.writeFile(this.file, JSON.stringify(this.added.map(({ source, ...rule }) => rule), null, 2), { mode: 0o600 });
+  }
+
+  // Rules matching an event, with their actions filled in from the event fields
+  match(event) {
+    const fields = parseEvent(event.name, event.data);
+    if (!fields) {
+      return [];
+    }
+
+    const now = Date.now();
+    const matched = [];
+    for (const rule of [...this.configRules, ...this.added]) {
+      if (rule.on !== event.name ||
+          !Object.entries(rule.match).every(([field, pattern]) => new RegExp(pattern).test(fields[field]))) {
+        continue;
+      }
+
+      const stats = this.stats.get(rule.name) || { fired: 0, recent: [] };
+      stats.recent = stats.recent.filter(time => now - time < 60000);
+      if (stats.recent.length >= MAX_FIRINGS_PER_MINUTE) {
+        this.logger.warn(`Automation rule ${rule.name} fired ${MAX_FIRINGS_PER_MINUTE} times in a minute; skipping`);
+        continue;
+      }
+      stats.recent.push(now);
+      stats.fired++;
+      stats.lastFired = new Date(now).toISOString();
+      this.stats.set(rule.name, stats);
+
+      matched.push({ rule, fields, actions: fill(rule.actions, fields) });
+    }
+    return matched;
+  }
+
+  recordOutcome(name, error = null) {
+    const stats = this.stats.get(name);
+    if (stats) {
+      stats.lastError = error ? error.message : null;
+    }
+  }
+}
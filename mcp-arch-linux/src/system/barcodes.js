@@ -0,0 +1,38 @@
+// zbarimg --xml output: one <symbol type=... quality=...><data><![CDATA[...]]></data></symbol>
+// per code found; XML keeps payloads with newlines intact, unlike the plain output
+export function parseZbarXml(xml) {
+  const symbols = [];
+  for (const match of xml.matchAll(/<symbol\s([^>]*)>[\s\S]*?<data(?:\s[^>]*)?>(?:<!\[CDATA\[([\s\S]*?)\]\]>|([^<]*))<\/data>/g)) {
+    const attributes = Object.fromEntries([...match[1].matchAll(/(\w+)=['"]([^'"]*)['"]/g)].map(([, key, value]) => [key, value]));
+    symbols.push({
+      type: attributes.type,
+      quality: attributes.quality ? Number(attributes.quality) : null,
+      data: match[2] ?? unescapeXml(match[3] ?? '')
+    });
+  }
+  return symbols;
+}
+
+function unescapeXml(text) {
+  return text.replace(/&lt;/g, '<').replace(/&gt;/g, '>').replace(/&quot;/g, '"').replace(/&apos;/g, "'").replace(/&amp;/g, '&');
+}
+
+// WIFI:T:WPA;S:name;P:secret;H:true;; as phones show it; \ escapes ;,:" and \ in values
+export function parseWifiPayload(data) {
+  if (!data.startsWith('WIFI:')) {
+    return null;
+  }
+  const fields = {};
+  for (const match of data.slice(5).matchAll(/([A-Z]+):((?:\\.|[^;\\])*);/g)) {
+    fields[match[1]] = match[2].replace(/\\(.)/g, '$1');
+  }
+  if (!fields.S) {
+    return null;
+  }
+  return {
+    ssid: fields.S,
+    security: fields.T || 'nopass',
+    ...(fields.P !== undefined ? { password: fields.P } : {}),
+    hidden: fields.H === 'true'
+  };
+}
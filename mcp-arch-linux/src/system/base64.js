@@ -0,0 +1,38 @@
+import fs from 'fs-extra';
+import { setImmediate as yieldToEventLoop } from 'timers/promises';
+
+// A multiple of 3, so per-chunk encodings concatenate into one valid base64 string
+export const CHUNK_SIZE = 3 * 256 * 1024;
+
+// Encodes in slices and yields to the event loop between them. Joining the slices into
+// one string is still a single step (about 25 ms for 30MB, see scripts/bench-base64.js),
+// but the encoding itself no longer blocks other requests
+export async function encodeBase64(data) {
+  if (data.length <= CHUNK_SIZE) {
+    return data.toString('base64');
+  }
+
+  const parts = [];
+  for (let offset = 0; offset < data.length; offset += CHUNK_SIZE) {
+    parts.push(data.subarray(offset, offset + CHUNK_SIZE).toString('base64'));
+    await yieldToEventLoop();
+  }
+  return parts.join('');
+}
+
+// Encodes a file while it is streamed in, without first reading it whole into memory
+export async function encodeFileBase64(file) {
+  const parts = [];
+  let carry = Buffer.alloc(0);
+
+  for await (const chunk of fs.createReadStream(file, { highWaterMark: CHUNK_SIZE })) {
+    const data = carry.length > 0 ? Buffer.concat([carry, chunk]) : chunk;
+    const usable = data.length - (data.length % 3);
+    parts.push(data.subarray(0, usable).toString('base64'));
+    carry = data.subarray(usable);
+    await yieldToEventLoop();
+  }
+
+  parts.push(carry.toString('base64'));
+  return parts.join('');
+}
@@ -2,15 +2,31 @@ import { spawn } from 'child_process';
 import { promisify } from 'util';
 import fs from 'fs-extra';
 import path from 'path';
+import { RetryPolicy } from './retry-policy.js';
+import { getToolResolver } from './tool-resolver.js';
+import { getSimulator } from './simulator.js';
+import { targetCommand } from './target.js';
+import { recordCommandFailure } from '../core/request-context.js';
+import { CommandNotAllowedError, CommandTimeoutError } from '../core/errors.js';
+
+const STDERR_TAIL_LINES = 20;
 
 export class CommandExecutor {
   constructor(config, logger, security) {
     this.config = config;
     this.logger = logger;
     this.security = security;
+    this.runningProcesses = new Map();
+    this.toolResolver = getToolResolver();
+    this.updateConfig(config);
+  }
+
+  // Also called on config reload so allowlist and timeouts change without a restart
+  updateConfig(config) {
+    this.config = config;
     this.allowedCommands = config.allowedCommands || [];
     this.timeout = config.commandTimeout || 300000; // 5 minutes
-    this.runningProcesses = new Map();
+    this.retryPolicy = new RetryPolicy(config.retry || {});
   }
 
   async execute(command, args = [], options = {}) {
@@ -20,17 +36,34 @@ export class CommandExecutor {
       requireRoot = false,
       timeout = this.timeout,
       input = null,
-      captureOutput = true
+      captureOutput = true,
+      elevated = false,
+      onOutput = null,
+      binary = false,
+      target = null
     } = options;
 
+    // Entering a chroot or container always needs root
+    if (target && target.type !== 'host') {
+      return this.executeWithSudo(command, args, { ...options, retry: false });
+    }
+
     // Security checks
-    this.validateCommand(command);
+    this.validateCommand(command, { elevated });
+
+    // Simulation mode: nothing is spawned, a rule supplies the result
+    const simulator = getSimulator();
+    if (simulator.enabled) {
+      this.logger.debug(`Simulating command: ${command} ${args.join(' ')}`);
+      return this.noteFailure(command, args, await simulator.run(command, args, { input, onOutput, binary }), elevated);
+    }
     
     if (requireRoot && process.getuid && process.getuid() !== 0) {
       throw new Error('Root privileges required for this operation');
     }
 
     const processId = this.generateProcessId();
+    const executable = await this.toolResolver.resolve(command) || command;
     
     try {
       this.logger.debug(`Executing command: ${command} ${args.join(' ')}`, {
@@ -39,13 +72,15 @@ export class CommandExecutor {
         requireRoot
       });
 
-      const result = await this.spawnProcess(command, args, {
+      const result = await this.spawnProcess(executable, args, {
         cwd,
         env,
         timeout,
         input,
         captureOutput,
-        processId
+        processId,
+        onOutput,
+        binary
       });
 
       this.logger.debug(`Command completed: ${command}`, {
@@ -54,7 +89,7 @@ export class CommandExecutor {
         duration: result.duration
       });
 
-      return result;
+      return this.noteFailure(command, args, result, elevated);
 
     } catch (error) {
       this.logger.error(`Command failed: ${command}`, {
@@ -67,35 +102,82 @@ export class CommandExecutor {
     }
   }
 
+  // Remembers a non-zero exit for the current request so a tool error can name the command
+  noteFailure(command, args, result, elevated) {
+    if (!result.success) {
+      // sudo -n <executable> ...
+      const name = elevated ? path.basename(args[1]) : command;
+      const failedArgs = elevated ? args.slice(2) : args;
+      recordCommandFailure({
+        command: name,
+        args: failedArgs,
+        exitCode: result.exitCode,
+        stderr: (result.stderr || '').split('\n').slice(-STDERR_TAIL_LINES).join('\n'),
+        retryable: this.retryPolicy.isRetryable(name, result)
+      });
+    }
+    return result;
+  }
+
   async executeScript(script, options = {}) {
     return this.execute('bash', ['-c', script], options);
   }
 
   async executeWithSudo(command, args = [], options = {}) {
-    // Check if we're already root
-    if (process.getuid && process.getuid() === 0) {
-      return this.execute(command, args, options);
+    const { retry = {}, isApplied = null, target = null, ...execOptions } = options;
+    const requested = command;
+
+    // The command itself must be allowed; the chroot or container wrapper runs it
+    if (target && target.type !== 'host') {
+      this.validateCommand(command);
+      ({ command, args } = targetCommand(target, command, args));
+    }
+
+    const run = () => {
+      // Check if we're already root; simulated commands are matched without the sudo wrapper
+      if ((process.getuid && process.getuid() === 0) || getSimulator().enabled) {
+        return this.execute(command, args, execOptions);
+      }
+
+      // Use sudo, honoring configured absolute tool paths; the allowlist applies to the wrapped command
+      this.validateCommand(command);
+      const executable = this.toolResolver.configuredPaths[command] || command;
+      const sudoArgs = ['-n', executable, ...args]; // -n for non-interactive
+      return this.execute('sudo', sudoArgs, { ...execOptions, requireRoot: false, elevated: true });
+    };
+
+    if (retry === false) {
+      return run();
     }
 
-    // Use sudo
-    const sudoArgs = ['-n', command, ...args]; // -n for non-interactive
-    return this.execute('sudo', sudoArgs, { ...options, requireRoot: false });
+    // Retry transient failures (db locks, network, busy devices) with backoff
+    const policy = Object.keys(retry).length > 0 ? this.retryPolicy.withOverrides(retry) : this.retryPolicy;
+    return policy.run(requested, run, {
+      isApplied,
+      onRetry: ({ attempt, delay, reason }) => {
+        this.logger.warn(`Retrying ${requested} after transient failure`, {
+          attempt,
+          delay,
+          reason: reason?.split('\n').slice(-3).join('\n')
+        });
+      }
+    });
   }
 
-  validateCommand(command) {
+  validateCommand(command, { elevated = false } = {}) {
     // Check if command is in allowed list
-    if (this.allowedCommands.length > 0 && !this.allowedCommands.includes(command)) {
-      throw new Error(`Command not allowed: ${command}`);
+    if (!elevated && this.allowedCommands.length > 0 && !this.allowedCommands.includes(command)) {
+      throw new CommandNotAllowedError(`Command not allowed: ${command}`, command);
     }
 
     // Prevent command injection
     if (command.includes(';') || command.includes('&&') || command.includes('||') || command.includes('|')) {
-      throw new Error('Command injection detected');
+      throw new CommandNotAllowedError('Command injection detected', command);
     }
 
     // Check for path traversal
     if (command.includes('..') || command.includes('~')) {
-      throw new Error('Path traversal detected');
+      throw new CommandNotAllowedError('Path traversal detected', command);
     }
   }
 
@@ -105,21 +187,31 @@ export class CommandExecutor {
       const child = spawn(command, args, {
         cwd: options.cwd,
         env: options.env,
-        stdio: options.captureOutput ? ['pipe', 'pipe', 'pipe'] : 'inherit'
+        // Uncaptured output goes to stderr: under the stdio transport, stdin and stdout
+        // carry JSON-RPC and must not be shared with children
+        stdio: options.captureOutput ? ['pipe', 'pipe', 'pipe'] : ['ignore', 2, 2]
       });
 
       this.runningProcesses.set(options.processId, child);
 
       let stdout = '';
       let stderr = '';
+      const chunks = [];
 
       if (options.captureOutput) {
         child.stdout.on('data', (data) => {
+          // Binary output (images piped to stdout) is returned as an untrimmed Buffer
+          if (options.binary) {
+            chunks.push(data);
+            return;
+          }
           stdout += data.toString();
+          options.onOutput?.(data.toString(), 'stdout');
         });
 
         child.stderr.on('data', (data) => {
           stderr += data.toString();
+          options.onOutput?.(data.toString(), 'stderr');
         });
       }
 
@@ -130,7 +222,9 @@ export class CommandExecutor {
       }
 
       // Set timeout
+      let timedOut = false;
       const timeoutHandle = setTimeout(() => {
+        timedOut = true;
         child.kill('SIGTERM');
         setTimeout(() => {
           if (!child.killed) {
@@ -143,12 +237,14 @@ export class CommandExecutor {
         clearTimeout(timeoutHandle);
         const duration = Date.now() - startTime;
 
-        if (signal) {
+        if (timedOut) {
+          reject(new CommandTimeoutError(path.basename(command), options.timeout));
+        } else if (signal) {
           reject(new Error(`Process killed with signal ${signal}`));
         } else {
           resolve({
             exitCode: code,
-            stdout: stdout.trim(),
+            stdout: options.binary ? Buffer.concat(chunks) : stdout.trim(),
             stderr: stderr.trim(),
             duration,
             success: code === 0
@@ -189,13 +285,8 @@ export class CommandExecutor {
   }
 
   // Convenience methods for common operations
-  async checkCommandExists(command) {
-    try {
-      const result = await this.execute('which', [command]);
-      return result.success;
-    } catch {
-      return false;
-    }
+  async checkCommandExists(command, pluginName = null) {
+    return (await this.toolResolver.resolveFor(pluginName, command)) !== null;
   }
 
   async getSystemInfo() {
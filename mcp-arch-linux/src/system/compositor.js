@@ -0,0 +1,387 @@
+import { Socket } from 'net';
+import fs from 'fs-extra';
+import path from 'path';
+
+const DIRECTIONS = ['left', 'right', 'up', 'down'];
+
+// One dispatch or keyword line for the Hyprland socket, from { type, name, args }
+export function hyprlandCommandLine(command, label = 'command') {
+  const { type, name, args = '' } = command || {};
+  if (!['dispatch', 'keyword'].includes(type)) {
+    throw new Error(`${label}.type must be dispatch or keyword`);
+  }
+  if (typeof name !== 'string' || !/^[\w:.\-[\]]+$/.test(name)) {
+    throw new Error(`${label}.name is not a dispatcher or keyword name`);
+  }
+  // ";" separates batch items, so it cannot appear inside one
+  if (typeof args !== 'string' || /[;\r\n]/.test(args)) {
+    throw new Error(`${label}.args must be a single line without ";"`);
+  }
+  if (type === 'keyword' && !args) {
+    throw new Error(`${label} needs a value for keyword ${name}`);
+  }
+  return args ? `${type} ${name} ${args}` : `${type} ${name}`;
+}
+
+// A [[BATCH]] reply is every item's reply concatenated, "ok" for each success. Successes
+// are peeled off both ends; a single item left in between gets the remaining text as its
+// error, several cannot be told apart
+export function splitBatchReply(reply, count) {
+  const replies = new Array(count).fill(null);
+  let rest = reply.trim();
+  let first = 0;
+  let last = count - 1;
+  while (first <= last && rest.startsWith('ok')) {
+    replies[first++] = 'ok';
+    rest = rest.slice(2).trimStart();
+  }
+  while (last >= first && rest.endsWith('ok')) {
+    replies[last--] = 'ok';
+    rest = rest.slice(0, -2).trimEnd();
+  }
+  if (first === last) {
+    replies[first] = rest;
+  }
+  return { replies, unattributed: first < last ? rest : null };
+}
+
+// Common surface for window/workspace/monitor queries; results are normalized so
+// tools and window-targeted capture do not care which compositor is running
+export class Compositor {
+  constructor(name, socketPath) {
+    this.name = name;
+    this.socketPath = socketPath;
+  }
+
+  async isAvailable() {
+    if (!this.socketPath) return false;
+
+    try {
+      await fs.access(this.socketPath);
+      await this.version();
+      return true;
+    } catch {
+      return false;
+    }
+  }
+
+  async activeWindow() {
+    return (await this.windows()).find(window => window.focused) || null;
+  }
+
+  // Matches "active", a window id, or a case-insensitive class/title substring
+  async findWindow(selector) {
+    if (!selector || selector === 'active') {
+      return this.activeWindow();
+    }
+
+    const needle = selector.toLowerCase();
+    const windows = await this.windows();
+    return windows.find(window => String(window.id) === selector) ||
+      windows.find(window => (window.class || '').toLowerCase() === needle) ||
+      windows.find(window => (window.title || '').toLowerCase().includes(needle)) ||
+      null;
+  }
+}
+
+export class HyprlandCompositor extends Compositor {
+  constructor(socketPath) {
+    super('hyprland', socketPath);
+  }
+
+  static detectSocket() {
+    const runtimeDir = process.env.XDG_RUNTIME_DIR;
+    const instance = process.env.HYPRLAND_INSTANCE_SIGNATURE;
+
+    if (runtimeDir && instance) {
+      return path.join(runtimeDir, 'hypr', instance, '.socket.sock');
+    }
+    return null;
+  }
+
+  async send(command) {
+    return new Promise((resolve, reject) => {
+      const socket = new Socket();
+      let data = '';
+
+      socket.connect(this.socketPath, () => {
+        socket.write(command);
+      });
+
+      socket.on('data', (chunk) => {
+        data += chunk.toString();
+      });
+
+      socket.on('end', () => {
+        resolve(data.trim());
+      });
+
+      socket.on('error', (error) => {
+        reject(new Error(`Hyprland socket error: ${error.message}`));
+      });
+
+      socket.setTimeout(5000, () => {
+        socket.destroy();
+        reject(new Error('Hyprland command timeout'));
+      });
+    });
+  }
+
+  // Replies to unknown requests are plain text such as "unknown request"
+  async query(command) {
+    const reply = await this.send(`j/${command}`);
+    try {
+      return JSON.parse(reply || 'null');
+    } catch {
+      throw new Error(`Hyprland rejected ${command}: ${reply}`);
+    }
+  }
+
+  async version() {
+    return this.send('version');
+  }
+
+  // Commands run in order within one request, so no other client's command lands in between
+  async batch(commands) {
+    const { replies, unattributed } = splitBatchReply(await this.send(`[[BATCH]]${commands.join(';')}`), commands.length);
+    return commands.map((command, index) => ({
+      command,
+      ok: replies[index] === null ? null : replies[index] === 'ok',
+      reply: replies[index] ?? unattributed
+    }));
+  }
+
+  async windows() {
+    const [clients, active] = await Promise.all([this.query('clients'), this.query('activewindow')]);
+    return (clients || []).map(client => ({
+      id: client.address,
+      title: client.title,
+      class: client.class,
+      workspace: client.workspace?.name,
+      focused: client.address === active?.address,
+      floating: client.floating,
+      geometry: { x: client.at[0], y: client.at[1], width: client.size[0], height: client.size[1] }
+    }));
+  }
+
+  async workspaces() {
+    const [workspaces, active] = await Promise.all([this.query('workspaces'), this.query('activeworkspace')]);
+    return (workspaces || []).map(workspace => ({
+      id: workspace.id,
+      name: workspace.name,
+      monitor: workspace.monitor,
+      windows: workspace.windows,
+      focused: workspace.id === active?.id
+    }));
+  }
+
+  async monitors() {
+    return ((await this.query('monitors')) || []).map(monitor => ({
+      name: monitor.name,
+      description: monitor.description,
+      focused: monitor.focused,
+      scale: monitor.scale,
+      workspace: monitor.activeWorkspace?.name,
+      geometry: { x: monitor.x, y: monitor.y, width: monitor.width, height: monitor.height }
+    }));
+  }
+
+  selectorFor(target) {
+    return /^0x[0-9a-f]+$/i.test(target) ? `address:${target}` : target;
+  }
+
+  async windowAction(action, target = '', args = '') {
+    const commands = {
+      focus: DIRECTIONS.includes(target) ? `movefocus ${target[0]}` : `focuswindow ${this.selectorFor(target)}`,
+      move: DIRECTIONS.includes(target) ? `movewindow ${target[0]}` : `movetoworkspace ${target}`,
+      resize: `resizeactive ${args || '10 10'}`,
+      close: 'killactive',
+      float: 'togglefloating',
+      fullscreen: 'fullscreen'
+    };
+
+    if (!commands[action]) {
+      throw new Error(`Unknown window action: ${action}`);
+    }
+    return this.send(`dispatch ${commands[action]}`);
+  }
+
+  async reload() {
+    return this.send('reload');
+  }
+}
+
+const I3_MAGIC = Buffer.from('i3-ipc');
+const I3_MESSAGE = { RUN_COMMAND: 0, GET_WORKSPACES: 1, GET_OUTPUTS: 3, GET_TREE: 4, GET_VERSION: 7 };
+
+// Sway speaks the i3 IPC protocol: magic, payload length, message type, JSON payload
+export class SwayCompositor extends Compositor {
+  constructor(socketPath) {
+    super('sway', socketPath);
+  }
+
+  static detectSocket() {
+    return process.env.SWAYSOCK || process.env.I3SOCK || null;
+  }
+
+  async send(type, payload = '') {
+    return new Promise((resolve, reject) => {
+      const socket = new Socket();
+      const body = Buffer.from(payload);
+      const header = Buffer.alloc(14);
+      I3_MAGIC.copy(header, 0);
+      header.writeUInt32LE(body.length, 6);
+      header.writeUInt32LE(type, 10);
+
+      let data = Buffer.alloc(0);
+
+      socket.connect(this.socketPath, () => {
+        socket.write(Buffer.concat([header, body]));
+      });
+
+      socket.on('data', (chunk) => {
+        data = Buffer.concat([data, chunk]);
+        if (data.length >= 14) {
+          const length = data.readUInt32LE(6);
+          if (data.length >= 14 + length) {
+            socket.end();
+            try {
+              resolve(JSON.parse(data.subarray(14, 14 + length).toString('utf8')));
+            } catch (error) {
+              reject(new Error(`Invalid sway IPC reply: ${error.message}`));
+            }
+          }
+        }
+      });
+
+      socket.on('error', (error) => {
+        reject(new Error(`Sway socket error: ${error.message}`));
+      });
+
+      socket.setTimeout(5000, () => {
+        socket.destroy();
+        reject(new Error('Sway command timeout'));
+      });
+    });
+  }
+
+  async command(command) {
+    const results = await this.send(I3_MESSAGE.RUN_COMMAND, command);
+    const failed = results.find(result => !result.success);
+    if (failed) {
+      throw new Error(failed.error || `Sway command failed: ${command}`);
+    }
+    return '';
+  }
+
+  async version() {
+    return (await this.send(I3_MESSAGE.GET_VERSION)).human_readable;
+  }
+
+  async windows() {
+    const tree = await this.send(I3_MESSAGE.GET_TREE);
+    const windows = [];
+
+    const walk = (node, workspace) => {
+      const current = node.type === 'workspace' ? node.name : workspace;
+      const children = [...(node.nodes || []), ...(node.floating_nodes || [])];
+
+      if (children.length === 0 && (node.type === 'con' || node.type === 'floating_con') && node.pid) {
+        windows.push({
+          id: String(node.id),
+          title: node.name,
+          class: node.app_id || node.window_properties?.class,
+          workspace: current,
+          focused: node.focused,
+          floating: node.type === 'floating_con',
+          geometry: { x: node.rect.x, y: node.rect.y, width: node.rect.width, height: node.rect.height }
+        });
+      }
+
+      for (const child of children) {
+        walk(child, current);
+      }
+    };
+
+    walk(tree, null);
+    return windows;
+  }
+
+  async workspaces() {
+    return (await this.send(I3_MESSAGE.GET_WORKSPACES)).map(workspace => ({
+      id: workspace.num,
+      name: workspace.name,
+      monitor: workspace.output,
+      focused: workspace.focused
+    }));
+  }
+
+  async monitors() {
+    return (await this.send(I3_MESSAGE.GET_OUTPUTS)).map(output => ({
+      name: output.name,
+      description: [output.make, output.model].filter(Boolean).join(' '),
+      focused: output.focused,
+      scale: output.scale,
+      workspace: output.current_workspace,
+      geometry: { x: output.rect.x, y: output.rect.y, width: output.rect.width, height: output.rect.height }
+    }));
+  }
+
+  async selectorFor(target) {
+    if (/^\d+$/.test(target)) {
+      return `[con_id=${target}]`;
+    }
+
+    const window = await this.findWindow(target);
+    if (!window) {
+      throw new Error(`No window matches: ${target}`);
+    }
+    return `[con_id=${window.id}]`;
+  }
+
+  async windowAction(action, target = '', args = '') {
+    switch (action) {
+      case 'focus':
+        return this.command(DIRECTIONS.includes(target) ? `focus ${target}` : `${await this.selectorFor(target)} focus`);
+      case 'move':
+        return this.command(DIRECTIONS.includes(target) ? `move ${target}` : `move container to workspace ${target}`);
+      case 'resize': {
+        const [width = '10', height = '10'] = (args || '').split(/\s+/).filter(Boolean);
+        return this.command(`resize grow width ${width} px, resize grow height ${height} px`);
+      }
+      case 'close':
+        return this.command('kill');
+      case 'float':
+        return this.command('floating toggle');
+      case 'fullscreen':
+        return this.command('fullscreen toggle');
+      default:
+        throw new Error(`Unknown window action: ${action}`);
+    }
+  }
+
+  async reload() {
+    return this.command('reload');
+  }
+}
+
+const COMPOSITORS = { hyprland: HyprlandCompositor, sway: SwayCompositor };
+
+// Picks the configured compositor, or the first one whose IPC socket answers
+export async function detectCompositor({ type = 'auto', socketPath = null } = {}) {
+  const candidates = type === 'auto' ? Object.keys(COMPOSITORS) : [type];
+
+  for (const name of candidates) {
+    const Implementation = COMPOSITORS[name];
+    if (!Implementation) {
+      throw new Error(`Unsupported compositor: ${name}`);
+    }
+
+    const compositor = new Implementation(socketPath || Implementation.detectSocket());
+    if (await compositor.isAvailable()) {
+      return compositor;
+    }
+  }
+
+  return null;
+}
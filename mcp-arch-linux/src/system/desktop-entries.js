@@ -0,0 +1,183 @@
+import fs from 'fs-extra';
+import path from 'path';
+import { readLines, getIniValues } from './user-files.js';
+
+export const MIME_TYPE_PATTERN = /^[a-z0-9][a-z0-9.+-]*\/[a-zA-Z0-9][a-zA-Z0-9.+_-]*$/;
+const SCHEME_PATTERN = /^[a-z][a-z0-9+.-]*$/;
+export const DESKTOP_ID_PATTERN = /^[A-Za-z0-9_.-]+\.desktop$/;
+
+// Highest priority first, as the XDG desktop entry and mimeapps specs order them
+export function applicationDirs(account) {
+  return [
+    path.join(account.home, '.local', 'share', 'applications'),
+    path.join(account.home, '.local', 'share', 'flatpak', 'exports', 'share', 'applications'),
+    '/var/lib/flatpak/exports/share/applications',
+    '/usr/local/share/applications',
+    '/usr/share/applications'
+  ];
+}
+
+// The user's file comes first and is the only one the tools write
+export function mimeappsFiles(account) {
+  return [
+    path.join(account.home, '.config', 'mimeapps.list'),
+    '/etc/xdg/mimeapps.list',
+    path.join(account.home, '.local', 'share', 'applications', 'mimeapps.list'),
+    '/usr/local/share/applications/mimeapps.list',
+    '/usr/share/applications/mimeapps.list'
+  ];
+}
+
+// "image/png" stays as is; a bare URL scheme such as "https" becomes x-scheme-handler/https
+export function normalizeMimeType(value) {
+  if (SCHEME_PATTERN.test(value)) {
+    return `x-scheme-handler/${value}`;
+  }
+  if (!MIME_TYPE_PATTERN.test(value)) {
+    throw new Error(`Invalid MIME type or URL scheme: ${value}`);
+  }
+  return value;
+}
+
+function splitList(value) {
+  return (value || '').split(';').map(item => item.trim()).filter(Boolean);
+}
+
+// Desktop file ID: path below the applications dir with / replaced by -
+async function scanDir(dir, prefix = '') {
+  const found = [];
+  for (const entry of await fs.readdir(dir, { withFileTypes: true }).catch(() => [])) {
+    if (entry.isDirectory()) {
+      found.push(...await scanDir(path.join(dir, entry.name), `${prefix}${entry.name}-`));
+    } else if (entry.name.endsWith('.desktop')) {
+      found.push({ id: `${prefix}${entry.name}`, file: path.join(dir, entry.name) });
+    }
+  }
+  return found;
+}
+
+// Installed applications by desktop ID; an entry in a higher-priority dir shadows the
+// others, and a shadowing entry with Hidden=true removes the application altogether
+export async function listDesktopEntries(account) {
+  const entries = new Map();
+  const seen = new Set();
+
+  for (const dir of applicationDirs(account)) {
+    for (const { id, file } of await scanDir(dir)) {
+      if (seen.has(id)) {
+        continue;
+      }
+      seen.add(id);
+
+      const fields = getIniValues(await readLines(file), 'Desktop Entry');
+      if (fields.Hidden === 'true' || (fields.Type && fields.Type !== 'Application')) {
+        continue;
+      }
+      entries.set(id, {
+        id,
+        name: fields.Name || id.replace(/\.desktop$/, ''),
+        exec: fields.Exec || null,
+        tryExec: fields.TryExec || null,
+        mimeTypes: splitList(fields.MimeType),
+        noDisplay: fields.NoDisplay === 'true',
+        file
+      });
+    }
+  }
+  return entries;
+}
+
+// The program a desktop entry starts: TryExec, else the first word of Exec after any env prefix
+export function entryExecutable(entry) {
+  if (entry.tryExec) {
+    return entry.tryExec;
+  }
+  const words = (entry.exec || '').split(/\s+/).filter(Boolean);
+  let index = 0;
+  if (words[index] === 'env') {
+    index++;
+    while (words[index]?.includes('=')) index++;
+  }
+  return words[index]?.replace(/^"|"$/g, '') || null;
+}
+
+export function parseMimeapps(lines) {
+  const sections = {
+    defaults: getIniValues(lines, 'Default Applications'),
+    added: getIniValues(lines, 'Added Associations'),
+    removed: getIniValues(lines, 'Removed Associations')
+  };
+  return Object.fromEntries(Object.entries(sections).map(([name, values]) =>
+    [name, Object.fromEntries(Object.entries(values).map(([type, ids]) => [type, splitList(ids)]))]));
+}
+
+// Every mimeapps.list that exists, in priority order
+export async function readMimeapps(account) {
+  const files = [];
+  for (const file of mimeappsFiles(account)) {
+    if (await fs.pathExists(file)) {
+      files.push({ file, ...parseMimeapps(await readLines(file)) });
+    }
+  }
+  return files;
+}
+
+// First installed application listed as default, then added associations, then any
+// application advertising the type; mirrors what xdg-open and GIO pick
+export function resolveDefault(type, mimeapps, entries) {
+  const removed = new Set(mimeapps.flatMap(file => file.removed[type] || []));
+
+  for (const file of mimeapps) {
+    const id = (file.defaults[type] || []).find(candidate => entries.has(candidate));
+    if (id) {
+      return { handler: id, source: file.file };
+    }
+  }
+  for (const file of mimeapps) {
+    const id = (file.added[type] || []).find(candidate => entries.has(candidate) && !removed.has(candidate));
+    if (id) {
+      return { handler: id, source: `${file.file} [Added Associations]` };
+    }
+  }
+  const advertised = handlersFor(type, entries).find(id => !removed.has(id));
+  return advertised ? { handler: advertised, source: 'MimeType= of the desktop entry' } : { handler: null, source: null };
+}
+
+export function handlersFor(type, entries) {
+  return Array.from(entries.values()).filter(entry => entry.mimeTypes.includes(type)).map(entry => entry.id);
+}
+
+// Rewrites keys of one section; a null or empty list drops the key
+export function setMimeappsEntries(lines, section, updates) {
+  const result = [...lines];
+  let start = result.findIndex(line => line.trim() === `[${section}]`);
+  if (start < 0) {
+    while (result.length > 0 && result[result.length - 1] === '') result.pop();
+    if (result.length > 0) result.push('');
+    result.push(`[${section}]`);
+    start = result.length - 1;
+  }
+  let end = result.findIndex((line, index) => index > start && /^\s*\[.*\]\s*$/.test(line));
+  if (end < 0) end = result.length;
+
+  for (const [type, ids] of Object.entries(updates)) {
+    const index = result.findIndex((line, i) => i > start && i < end && line.split('=')[0].trim() === type);
+    const line = ids && ids.length > 0 ? `${type}=${ids.join(';')};` : null;
+    if (index >= 0) {
+      if (line) {
+        result[index] = line;
+      } else {
+        result.splice(index, 1);
+        end--;
+      }
+    } else if (line) {
+      let insertAt = end;
+      while (insertAt > start + 1 && result[insertAt - 1].trim() === '') insertAt--;
+      result.splice(insertAt, 0, line);
+      end++;
+    }
+  }
+
+  if (result[result.length - 1] !== '') result.push('');
+  return result;
+}
@@ -0,0 +1,266 @@
+import fs from 'fs-extra';
+import path from 'path';
+
+// How wipe_device erases a device: TRIM everything (fast, but leaves data recoverable
+// from the flash until the firmware reclaims it), secure TRIM, overwrite with shred
+// (slow, works on any device), or an NVMe format with user-data or crypto erase
+export const WIPE_METHODS = ['discard', 'secure-discard', 'overwrite', 'nvme-user-data', 'nvme-crypto'];
+
+// Mounts whose disks count as the system disk
+const SYSTEM_MOUNTS = ['/', '/usr', '/boot', '/efi', '/boot/efi', '/var'];
+
+// NVMe Identify Controller FNA bits
+const FNA_ALL_NAMESPACES = 0x1;
+const FNA_CRYPTO_ERASE = 0x4;
+
+const OVERWRITE_TIMEOUT = 48 * 60 * 60 * 1000;
+
+// Mount table lines as { majMin, mountpoint, source }
+export function parseMountinfo(content) {
+  return content.split('\n').filter(Boolean).map(line => {
+    const [before, after = ''] = line.split(' - ');
+    const fields = before.split(' ');
+    const unescape = text => text.replace(/\\(\d{3})/g, (_, octal) => String.fromCharCode(parseInt(octal, 8)));
+    return { majMin: fields[2], mountpoint: unescape(fields[4] || ''), source: unescape(after.split(' ')[1] || '') };
+  });
+}
+
+// shred -v prints "shred: /dev/sdb: pass 2/3 (random)...1.2GiB/8.0GiB 15%" per step;
+// returns overall progress in percent of all passes
+export function parseShredProgress(text) {
+  const matches = [...text.matchAll(/pass (\d+)\/(\d+) \(([^)]+)\)\.\.\.(?:\S+ )?(\d+)%/g)];
+  if (matches.length === 0) {
+    return null;
+  }
+  const [, pass, passes, pattern, percent] = matches[matches.length - 1];
+  return {
+    progress: (Number(pass) - 1) * 100 + Number(percent),
+    total: Number(passes) * 100,
+    message: `pass ${pass}/${passes} (${pattern})`
+  };
+}
+
+// Block devices as a flat map by path, each with its parents and children, from
+// lsblk's tree (which repeats a RAID or LVM child under every member)
+export function indexBlockDevices(tree) {
+  const nodes = new Map();
+  const visit = (node, parent) => {
+    const key = node.path || node.name;
+    if (!nodes.has(key)) {
+      nodes.set(key, { ...node, path: key, children: [], parents: [] });
+    }
+    const entry = nodes.get(key);
+    if (parent && !entry.parents.includes(parent)) {
+      entry.parents.push(parent);
+      nodes.get(parent).children.push(key);
+    }
+    for (const child of node.children || []) {
+      visit(child, key);
+    }
+  };
+  for (const node of tree.blockdevices || []) {
+    visit(node, null);
+  }
+  return nodes;
+}
+
+function related(nodes, start, direction) {
+  const found = new Set();
+  const walk = (key) => {
+    for (const next of nodes.get(key)?.[direction] || []) {
+      if (!found.has(next)) {
+        found.add(next);
+        walk(next);
+      }
+    }
+  };
+  walk(start);
+  return [...found].map(key => nodes.get(key));
+}
+
+export const descendants = (nodes, key) => related(nodes, key, 'children');
+export const ancestors = (nodes, key) => related(nodes, key, 'parents');
+
+export class DeviceWipe {
+  constructor(commandExecutor, logger) {
+    this.commandExecutor = commandExecutor;
+    this.logger = logger;
+  }
+
+  async blockDevices() {
+    const result = await this.commandExecutor.execute('lsblk', [
+      '-J', '-b', '-p', '-o', 'NAME,PATH,MAJ:MIN,SIZE,TYPE,RM,HOTPLUG,TRAN,MODEL,MOUNTPOINTS,FSTYPE,DISC-MAX'
+    ]);
+    if (!result.success) {
+      throw new Error(`lsblk failed: ${result.stderr}`);
+    }
+    return indexBlockDevices(JSON.parse(result.stdout));
+  }
+
+  // Mounts of this namespace plus those only visible to other processes (containers,
+  // private mount namespaces of services), which lsblk does not see
+  async allMounts() {
+    const own = parseMountinfo(await fs.readFile('/proc/self/mountinfo', 'utf8'));
+    const seen = new Set(own.map(mount => `${mount.majMin} ${mount.source} ${mount.mountpoint}`));
+    const others = [];
+    for (const pid of (await fs.readdir('/proc')).filter(name => /^\d+$/.test(name))) {
+      const content = await fs.readFile(`/proc/${pid}/mountinfo`, 'utf8').catch(() => '');
+      for (const mount of parseMountinfo(content)) {
+        const key = `${mount.majMin} ${mount.source} ${mount.mountpoint}`;
+        if (!seen.has(key)) {
+          seen.add(key);
+          others.push({ ...mount, pid: Number(pid) });
+        }
+      }
+    }
+    return { own, others };
+  }
+
+  async activeSwaps() {
+    const content = await fs.readFile('/proc/swaps', 'utf8').catch(() => '');
+    return Promise.all(content.split('\n').slice(1).filter(Boolean)
+      .map(line => line.split(/\s+/)[0])
+      .map(file => fs.realpath(file).catch(() => file)));
+  }
+
+  // What the device is and what uses it: whether it is (on) the system disk, removable,
+  // mounted in any namespace, active swap or held by a mapping or array built on it
+  async classify(device) {
+    const resolved = await fs.realpath(device).catch(() => device);
+    const nodes = await this.blockDevices();
+    const node = nodes.get(resolved);
+    if (!node) {
+      throw new Error(`${device} is not a block device`);
+    }
+
+    const { own, others } = await this.allMounts();
+    const swaps = await this.activeSwaps();
+    const affected = [node, ...descendants(nodes, node.path)];
+    const matches = (entry, mount) => mount.majMin === entry['maj:min'] || mount.source === entry.path;
+
+    const inUse = [];
+    for (const entry of affected) {
+      for (const mount of own.filter(candidate => matches(entry, candidate))) {
+        inUse.push(`${entry.path} is mounted on ${mount.mountpoint}`);
+      }
+      for (const mount of others.filter(candidate => matches(entry, candidate))) {
+        inUse.push(`${entry.path} is mounted on ${mount.mountpoint} in another mount namespace (pid ${mount.pid})`);
+      }
+      if (swaps.includes(entry.path)) {
+        inUse.push(`${entry.path} is active swap`);
+      }
+      if (entry !== node && entry.type !== 'part') {
+        inUse.push(`${entry.path} (${entry.type}) is built on it`);
+      }
+    }
+
+    // The disks under /, /usr, /boot, the ESP and swap
+    const systemSources = own
+      .filter(mount => SYSTEM_MOUNTS.includes(mount.mountpoint))
+      .map(mount => nodes.get(mount.source.replace(/\[.*\]$/, '')) ||
+        [...nodes.values()].find(entry => entry['maj:min'] === mount.majMin))
+      .concat(swaps.map(file => nodes.get(file)))
+      .filter(Boolean);
+    const systemDisks = new Set(systemSources
+      .flatMap(source => [source, ...ancestors(nodes, source.path)])
+      .filter(entry => entry.parents.length === 0)
+      .map(entry => entry.path));
+
+    const disks = node.parents.length === 0 ? [node] : ancestors(nodes, node.path).filter(entry => entry.parents.length === 0);
+    const flag = value => value === true || value === '1' || value === 1;
+    return {
+      device: node.path,
+      type: node.type,
+      size: Number(node.size),
+      model: disks[0]?.model?.trim() || null,
+      transport: disks[0]?.tran || null,
+      fstype: node.fstype || null,
+      disks: disks.map(entry => entry.path),
+      system: disks.some(entry => systemDisks.has(entry.path)),
+      removable: disks.length > 0 && disks.every(entry => flag(entry.rm) || flag(entry.hotplug) || entry.tran === 'usb'),
+      discard: Number(node['disc-max']) > 0,
+      inUse
+    };
+  }
+
+  // The command for a method, after checking the device supports it
+  async plan(info, { method, passes = 1, zero = true }) {
+    switch (method) {
+      case 'discard':
+      case 'secure-discard':
+        if (!info.discard) {
+          throw new Error(`${info.device} does not support discard; use method overwrite`);
+        }
+        // Our own in-use checks stand in for blkdiscard's, which refuses any formatted
+        // device when there is no terminal to ask on
+        return {
+          command: 'blkdiscard',
+          args: ['--force', '--verbose', '--step', String(1024 ** 3), ...(method === 'secure-discard' ? ['--secure'] : []), info.device],
+          affected: [info.device],
+          size: info.size
+        };
+      case 'overwrite':
+        return {
+          command: 'shred',
+          args: ['--verbose', '--iterations', String(passes), ...(zero ? ['--zero'] : []), info.device],
+          affected: [info.device],
+          timeout: OVERWRITE_TIMEOUT
+        };
+      case 'nvme-user-data':
+      case 'nvme-crypto':
+        return this.planNvmeFormat(info, method);
+      default:
+        throw new Error(`Unknown wipe method: ${method} (supported: ${WIPE_METHODS.join(', ')})`);
+    }
+  }
+
+  async planNvmeFormat(info, method) {
+    const namespace = /^\/dev\/(nvme\d+)n\d+$/.exec(info.device);
+    if (!namespace || info.type !== 'disk') {
+      throw new Error(`${info.device} is not an NVMe namespace; ${method} formats whole namespaces only`);
+    }
+    const result = await this.commandExecutor.executeWithSudo('nvme', ['id-ctrl', info.device, '--output-format=json'], { retry: false });
+    if (!result.success) {
+      throw new Error(`Could not identify the controller of ${info.device}: ${result.stderr}`);
+    }
+    const fna = Number(JSON.parse(result.stdout).fna) || 0;
+    if (method === 'nvme-crypto' && !(fna & FNA_CRYPTO_ERASE)) {
+      throw new Error(`The controller of ${info.device} does not support cryptographic erase; use nvme-user-data`);
+    }
+
+    // Some controllers can only format all their namespaces at once
+    const affected = fna & FNA_ALL_NAMESPACES
+      ? [...(await this.blockDevices()).values()]
+        .filter(entry => entry.type === 'disk' && new RegExp(`^/dev/${namespace[1]}n\\d+$`).test(entry.path))
+        .map(entry => entry.path)
+      : [info.device];
+    return {
+      command: 'nvme',
+      args: ['format', info.device, `--ses=${method === 'nvme-crypto' ? 2 : 1}`, '--force'],
+      affected
+    };
+  }
+
+  async run(plan, { onProgress = null } = {}) {
+    const result = await this.commandExecutor.executeWithSudo(plan.command, plan.args, {
+      retry: false,
+      timeout: plan.timeout ?? 4 * 60 * 60 * 1000,
+      onOutput: (text) => {
+        if (plan.command === 'shred') {
+          const progress = parseShredProgress(text);
+          if (progress) onProgress?.(progress);
+        } else if (plan.command === 'blkdiscard') {
+          const matches = [...text.matchAll(/Discarded (\d+) bytes from the offset (\d+)/g)];
+          if (matches.length > 0) {
+            const [, bytes, offset] = matches[matches.length - 1];
+            onProgress?.({ progress: Number(offset) + Number(bytes), total: plan.size, message: `discarding ${path.basename(plan.args[plan.args.length - 1])}` });
+          }
+        }
+      }
+    });
+    if (!result.success) {
+      throw new Error(`${plan.command} failed: ${result.stderr.split(/\r|\n/).filter(Boolean).pop()}`);
+    }
+    return result;
+  }
+}
@@ -0,0 +1,196 @@
+import fs from 'fs-extra';
+import { statfs } from 'fs/promises';
+import path from 'path';
+import { MCPError } from '../core/errors.js';
+
+const SIZE_UNITS = {
+  b: 1,
+  k: 1024,
+  kb: 1024,
+  m: 1024 ** 2,
+  mb: 1024 ** 2,
+  g: 1024 ** 3,
+  gb: 1024 ** 3,
+  t: 1024 ** 4,
+  tb: 1024 ** 4
+};
+
+// Parse human sizes like "50MB" or "2G" into bytes
+export function parseSize(value) {
+  if (value === null || value === undefined) return null;
+  if (typeof value === 'number') return value;
+
+  const match = String(value).trim().match(/^(\d+(?:\.\d+)?)\s*([a-z]*)$/i);
+  if (!match) {
+    throw new Error(`Invalid size: ${value}`);
+  }
+
+  const unit = match[2].toLowerCase() || 'b';
+  if (!(unit in SIZE_UNITS)) {
+    throw new Error(`Invalid size unit: ${value}`);
+  }
+
+  return Math.round(parseFloat(match[1]) * SIZE_UNITS[unit]);
+}
+
+export class QuotaExceededError extends MCPError {
+  constructor(message, details) {
+    super(message, -30002, details);
+    this.name = 'QuotaExceededError';
+  }
+}
+
+export class DiskQuota {
+  constructor(name, directory, options = {}, logger = null) {
+    this.name = name;
+    this.directory = directory;
+    this.maxSize = parseSize(options.maxSize ?? null);
+    this.minFreePercent = options.minFreePercent ?? 5;
+    this.warnPercent = options.warnPercent ?? 80;
+    this.logger = logger;
+    this.warning = null;
+  }
+
+  async directorySize(dir = this.directory) {
+    let total = 0;
+
+    let entries;
+    try {
+      entries = await fs.readdir(dir, { withFileTypes: true });
+    } catch {
+      return 0;
+    }
+
+    for (const entry of entries) {
+      const entryPath = path.join(dir, entry.name);
+      if (entry.isDirectory()) {
+        total += await this.directorySize(entryPath);
+      } else if (entry.isFile()) {
+        try {
+          total += (await fs.stat(entryPath)).size;
+        } catch {
+          // File removed while scanning
+        }
+      }
+    }
+
+    return total;
+  }
+
+  async filesystemUsage() {
+    try {
+      const stats = await statfs(this.directory);
+      const total = stats.blocks * stats.bsize;
+      const free = stats.bavail * stats.bsize;
+      return {
+        total,
+        free,
+        freePercent: total > 0 ? (free / total) * 100 : 100
+      };
+    } catch {
+      return null;
+    }
+  }
+
+  async status() {
+    const used = await this.directorySize();
+    const filesystem = await this.filesystemUsage();
+
+    return {
+      name: this.name,
+      directory: this.directory,
+      used,
+      maxSize: this.maxSize,
+      usedPercent: this.maxSize ? (used / this.maxSize) * 100 : null,
+      filesystem,
+      warning: this.warning
+    };
+  }
+
+  // Refuse a write of incomingBytes when it would exceed the quota or fill the filesystem
+  async ensureCapacity(incomingBytes = 0) {
+    const status = await this.status();
+    this.updateWarning(status, incomingBytes);
+
+    if (this.maxSize && status.used + incomingBytes > this.maxSize) {
+      throw new QuotaExceededError(
+        `Quota exceeded for ${this.name}: ${status.used + incomingBytes} bytes would exceed limit of ${this.maxSize}`,
+        { quota: this.name, directory: this.directory, used: status.used, incoming: incomingBytes, maxSize: this.maxSize }
+      );
+    }
+
+    if (status.filesystem) {
+      const freeAfter = status.filesystem.free - incomingBytes;
+      const freePercentAfter = (freeAfter / status.filesystem.total) * 100;
+      if (freePercentAfter < this.minFreePercent) {
+        throw new QuotaExceededError(
+          `Refusing write to ${this.directory}: filesystem would drop below ${this.minFreePercent}% free`,
+          { quota: this.name, directory: this.directory, free: status.filesystem.free, incoming: incomingBytes }
+        );
+      }
+    }
+
+    return status;
+  }
+
+  updateWarning(status, incomingBytes = 0) {
+    const reasons = [];
+
+    if (this.maxSize && ((status.used + incomingBytes) / this.maxSize) * 100 >= this.warnPercent) {
+      reasons.push(`${this.name} directory is above ${this.warnPercent}% of its ${this.maxSize} byte quota`);
+    }
+
+    if (status.filesystem && status.filesystem.freePercent < this.minFreePercent * 2) {
+      reasons.push(`filesystem holding ${this.directory} has only ${status.filesystem.freePercent.toFixed(1)}% free`);
+    }
+
+    const previous = this.warning;
+    this.warning = reasons.length > 0 ? {
+      quota: this.name,
+      reasons,
+      since: previous?.since || new Date().toISOString()
+    } : null;
+
+    if (this.warning && !previous) {
+      this.logger?.warn(`Disk quota threshold crossed: ${this.name}`, this.warning);
+    }
+  }
+}
+
+export class QuotaRegistry {
+  constructor() {
+    this.quotas = new Map();
+  }
+
+  register(quota) {
+    this.quotas.set(quota.name, quota);
+    return quota;
+  }
+
+  get(name) {
+    return this.quotas.get(name);
+  }
+
+  async getWarnings() {
+    const statuses = [];
+    for (const quota of this.quotas.values()) {
+      const status = await quota.status();
+      quota.updateWarning(status);
+      statuses.push({ ...status, warning: quota.warning });
+    }
+
+    return {
+      warnings: statuses.filter(status => status.warning).map(status => status.warning),
+      quotas: statuses
+    };
+  }
+}
+
+let sharedRegistry = null;
+
+export function getQuotaRegistry() {
+  if (!sharedRegistry) {
+    sharedRegistry = new QuotaRegistry();
+  }
+  return sharedRegistry;
+}
@@ -0,0 +1,180 @@
+// Editors and language servers from the official repositories, and the configs the
+// presets generate for them
+
+export const EDITORS = {
+  neovim: {
+    package: 'neovim',
+    binary: 'nvim',
+    configDir: '.config/nvim'
+  },
+  vscode: {
+    package: 'code',
+    binary: 'code',
+    configDir: '.config/Code - OSS/User'
+  }
+};
+
+export const PRESETS = ['minimal', 'ide'];
+
+// name: the server name used in vim.lsp.config; extension: Open VSX ID, null when built in
+export const LANGUAGES = {
+  python: {
+    package: 'pyright',
+    name: 'pyright',
+    cmd: ['pyright-langserver', '--stdio'],
+    filetypes: ['python'],
+    rootMarkers: ['pyproject.toml', 'setup.py', 'requirements.txt', '.git'],
+    extension: 'ms-python.python'
+  },
+  rust: {
+    package: 'rust-analyzer',
+    name: 'rust_analyzer',
+    cmd: ['rust-analyzer'],
+    filetypes: ['rust'],
+    rootMarkers: ['Cargo.toml', '.git'],
+    extension: 'rust-lang.rust-analyzer'
+  },
+  go: {
+    package: 'gopls',
+    name: 'gopls',
+    cmd: ['gopls'],
+    filetypes: ['go', 'gomod', 'gowork'],
+    rootMarkers: ['go.work', 'go.mod', '.git'],
+    extension: 'golang.go'
+  },
+  c: {
+    package: 'clang',
+    name: 'clangd',
+    cmd: ['clangd'],
+    filetypes: ['c', 'cpp', 'objc', 'objcpp'],
+    rootMarkers: ['compile_commands.json', '.clangd', '.git'],
+    extension: 'llvm-vs-code-extensions.vscode-clangd'
+  },
+  typescript: {
+    package: 'typescript-language-server',
+    name: 'ts_ls',
+    cmd: ['typescript-language-server', '--stdio'],
+    filetypes: ['javascript', 'javascriptreact', 'typescript', 'typescriptreact'],
+    rootMarkers: ['tsconfig.json', 'jsconfig.json', 'package.json', '.git'],
+    extension: null
+  },
+  lua: {
+    package: 'lua-language-server',
+    name: 'lua_ls',
+    cmd: ['lua-language-server'],
+    filetypes: ['lua'],
+    rootMarkers: ['.luarc.json', '.stylua.toml', '.git'],
+    extension: 'sumneko.lua'
+  },
+  bash: {
+    package: 'bash-language-server',
+    name: 'bashls',
+    cmd: ['bash-language-server', 'start'],
+    filetypes: ['sh', 'bash'],
+    rootMarkers: ['.git'],
+    extension: 'mads-hartmann.bash-ide-vscode'
+  },
+  yaml: {
+    package: 'yaml-language-server',
+    name: 'yamlls',
+    cmd: ['yaml-language-server', '--stdio'],
+    filetypes: ['yaml'],
+    rootMarkers: ['.git'],
+    extension: 'redhat.vscode-yaml'
+  },
+  markdown: {
+    package: 'marksman',
+    name: 'marksman',
+    cmd: ['marksman', 'server'],
+    filetypes: ['markdown'],
+    rootMarkers: ['.marksman.toml', '.git'],
+    extension: null
+  }
+};
+
+const luaList = (items) => `{ ${items.map(item => `'${item}'`).join(', ')} }`;
+
+// Plain init.lua using the built-in LSP client of Neovim 0.11, so no plugin manager is needed
+export function renderNeovimConfig(preset, languages) {
+  const lines = [
+    '-- Generated by mcp-arch-linux (editor_config_apply)',
+    "vim.g.mapleader = ' '",
+    '',
+    'vim.opt.number = true',
+    'vim.opt.relativenumber = true',
+    'vim.opt.expandtab = true',
+    'vim.opt.shiftwidth = 2',
+    'vim.opt.tabstop = 2',
+    'vim.opt.ignorecase = true',
+    'vim.opt.smartcase = true',
+    'vim.opt.termguicolors = true',
+    'vim.opt.undofile = true',
+    "vim.opt.signcolumn = 'yes'",
+    "vim.opt.clipboard = 'unnamedplus'",
+    '',
+    'vim.diagnostic.config({ virtual_text = true })',
+    ''
+  ];
+
+  if (languages.length > 0) {
+    for (const language of languages) {
+      const server = LANGUAGES[language];
+      lines.push(
+        `vim.lsp.config('${server.name}', {`,
+        `  cmd = ${luaList(server.cmd)},`,
+        `  filetypes = ${luaList(server.filetypes)},`,
+        `  root_markers = ${luaList(server.rootMarkers)},`,
+        '})'
+      );
+    }
+    lines.push(`vim.lsp.enable(${luaList(languages.map(language => LANGUAGES[language].name))})`, '');
+  }
+
+  lines.push(
+    "vim.api.nvim_create_autocmd('LspAttach', {",
+    '  callback = function(event)',
+    '    local client = vim.lsp.get_client_by_id(event.data.client_id)',
+    "    local map = function(keys, fn) vim.keymap.set('n', keys, fn, { buffer = event.buf }) end",
+    "    map('gd', vim.lsp.buf.definition)",
+    "    map('gr', vim.lsp.buf.references)",
+    "    map('<leader>rn', vim.lsp.buf.rename)",
+    "    map('<leader>ca', vim.lsp.buf.code_action)",
+    ...(preset === 'ide' ? [
+      "    if client:supports_method('textDocument/completion') then",
+      '      vim.lsp.completion.enable(true, client.id, event.buf, { autotrigger = true })',
+      '    end',
+      "    if client:supports_method('textDocument/inlayHint') then",
+      '      vim.lsp.inlay_hint.enable(true, { bufnr = event.buf })',
+      '    end',
+      "    if client:supports_method('textDocument/formatting') then",
+      "      vim.api.nvim_create_autocmd('BufWritePre', {",
+      '        buffer = event.buf,',
+      '        callback = function() vim.lsp.buf.format({ bufnr = event.buf, id = client.id }) end,',
+      '      })',
+      '    end'
+    ] : []),
+    '  end,',
+    '})',
+    ''
+  );
+  return lines.join('\n');
+}
+
+// Settings merged over the user's settings.json; keys the preset does not set are kept
+export function vscodeSettings(preset) {
+  return {
+    'telemetry.telemetryLevel': 'off',
+    'editor.rulers': [100],
+    'files.trimTrailingWhitespace': true,
+    'files.insertFinalNewline': true,
+    ...(preset === 'ide' ? {
+      'editor.formatOnSave': true,
+      'editor.inlayHints.enabled': 'onUnlessPressed',
+      'editor.minimap.enabled': false
+    } : {})
+  };
+}
+
+export function vscodeExtensions(languages) {
+  return languages.map(language => LANGUAGES[language].extension).filter(Boolean);
+}
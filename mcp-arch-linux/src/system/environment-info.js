@@ -0,0 +1,207 @@
+import fs from 'fs-extra';
+import path from 'path';
+
+const GPU_VENDORS = {
+  '0x8086': 'intel',
+  '0x1002': 'amd',
+  '0x10de': 'nvidia',
+  '0x1af4': 'virtio',
+  '0x15ad': 'vmware',
+  '0x1234': 'qemu'
+};
+
+const VIRT_SIGNATURES = [
+  { pattern: /kvm/i, name: 'kvm' },
+  { pattern: /qemu/i, name: 'qemu' },
+  { pattern: /virtualbox/i, name: 'oracle' },
+  { pattern: /vmware/i, name: 'vmware' },
+  { pattern: /microsoft/i, name: 'microsoft' },
+  { pattern: /xen/i, name: 'xen' },
+  { pattern: /bochs/i, name: 'bochs' },
+  { pattern: /parallels/i, name: 'parallels' }
+];
+
+export class EnvironmentInfo {
+  constructor(logger = null) {
+    this.logger = logger;
+    this.cache = null;
+    this.pending = null;
+  }
+
+  // Returns the cached environment, detecting it on first use
+  async get({ refresh = false } = {}) {
+    if (this.cache && !refresh) {
+      return this.cache;
+    }
+
+    if (!this.pending) {
+      this.pending = this.detect()
+        .then(info => {
+          this.cache = info;
+          return info;
+        })
+        .finally(() => {
+          this.pending = null;
+        });
+    }
+
+    return this.pending;
+  }
+
+  async detect() {
+    const [initSystem, cpuVendor, gpuVendors, virtualization, bootFirmware] = await Promise.all([
+      this.detectInitSystem(),
+      this.detectCpuVendor(),
+      this.detectGpuVendors(),
+      this.detectVirtualization(),
+      this.detectBootFirmware()
+    ]);
+
+    const info = {
+      sessionType: this.detectSessionType(),
+      compositor: this.detectCompositor(),
+      initSystem,
+      cpuVendor,
+      gpuVendors,
+      virtualization,
+      bootFirmware,
+      detectedAt: new Date().toISOString()
+    };
+
+    this.logger?.debug('Environment detected', info);
+    return info;
+  }
+
+  detectSessionType() {
+    const sessionType = process.env.XDG_SESSION_TYPE;
+    if (sessionType && ['wayland', 'x11', 'tty'].includes(sessionType)) {
+      return sessionType;
+    }
+
+    if (process.env.WAYLAND_DISPLAY) return 'wayland';
+    if (process.env.DISPLAY) return 'x11';
+    return 'tty';
+  }
+
+  detectCompositor() {
+    if (process.env.HYPRLAND_INSTANCE_SIGNATURE) return 'hyprland';
+    if (process.env.SWAYSOCK) return 'sway';
+
+    const desktop = (process.env.XDG_CURRENT_DESKTOP || '').toLowerCase();
+    if (!desktop) return null;
+
+    const known = ['hyprland', 'sway', 'river', 'wayfire', 'gnome', 'kde', 'xfce', 'i3'];
+    return known.find(name => desktop.includes(name)) || desktop.split(':')[0];
+  }
+
+  async detectInitSystem() {
+    try {
+      const comm = (await fs.readFile('/proc/1/comm', 'utf8')).trim();
+      if (comm === 'systemd') return 'systemd';
+      if (comm === 'init') {
+        if (await fs.pathExists('/run/openrc')) return 'openrc';
+        if (await fs.pathExists('/run/runit')) return 'runit';
+        return 'sysvinit';
+      }
+      return comm || 'unknown';
+    } catch {
+      return await fs.pathExists('/run/systemd/system') ? 'systemd' : 'unknown';
+    }
+  }
+
+  async detectCpuVendor() {
+    try {
+      const cpuinfo = await fs.readFile('/proc/cpuinfo', 'utf8');
+      const match = cpuinfo.match(/^vendor_id\s*:\s*(.+)$/m);
+      if (!match) return 'unknown';
+
+      const vendor = match[1].trim();
+      if (vendor === 'GenuineIntel') return 'intel';
+      if (vendor === 'AuthenticAMD') return 'amd';
+      return vendor;
+    } catch {
+      return 'unknown';
+    }
+  }
+
+  async detectGpuVendors() {
+    const drmDir = '/sys/class/drm';
+    const vendors = new Set();
+
+    try {
+      const entries = await fs.readdir(drmDir);
+      for (const entry of entries) {
+        if (!/^card\d+$/.test(entry)) continue;
+
+        try {
+          const vendorId = (await fs.readFile(path.join(drmDir, entry, 'device', 'vendor'), 'utf8')).trim();
+          vendors.add(GPU_VENDORS[vendorId] || vendorId);
+        } catch {
+          // Card without PCI vendor information (e.g. simpledrm)
+        }
+      }
+    } catch {
+      // No DRM subsystem available
+    }
+
+    return Array.from(vendors);
+  }
+
+  async detectVirtualization() {
+    if (await fs.pathExists('/.dockerenv')) return 'docker';
+
+    try {
+      const container = (await fs.readFile('/run/systemd/container', 'utf8')).trim();
+      if (container) return container;
+    } catch {
+      // Not a systemd-managed container
+    }
+
+    const dmiFields = ['sys_vendor', 'product_name', 'board_vendor'];
+    for (const field of dmiFields) {
+      try {
+        const value = await fs.readFile(path.join('/sys/class/dmi/id', field), 'utf8');
+        const signature = VIRT_SIGNATURES.find(sig => sig.pattern.test(value));
+        if (signature) return signature.name;
+      } catch {
+        // Field not readable
+      }
+    }
+
+    try {
+      const cpuinfo = await fs.readFile('/proc/cpuinfo', 'utf8');
+      if (/^flags\s*:.*\bhypervisor\b/m.test(cpuinfo)) return 'unknown-vm';
+    } catch {
+      // Ignore
+    }
+
+    return 'none';
+  }
+
+  async detectBootFirmware() {
+    return await fs.pathExists('/sys/firmware/efi') ? 'uefi' : 'bios';
+  }
+
+  // Backend selection helpers consulted by plugins
+  async preferredScreenshotBackend() {
+    const { sessionType } = await this.get();
+    return sessionType === 'x11' ? 'maim' : 'grim';
+  }
+
+  async preferredBootloader() {
+    const { bootFirmware } = await this.get();
+    return bootFirmware === 'uefi' ? 'systemd-boot' : 'grub';
+  }
+}
+
+let sharedEnvironment = null;
+
+// Shared instance so detection only runs once per process
+export function getEnvironmentInfo(logger = null) {
+  if (!sharedEnvironment) {
+    sharedEnvironment = new EnvironmentInfo(logger);
+  } else if (logger && !sharedEnvironment.logger) {
+    sharedEnvironment.logger = logger;
+  }
+  return sharedEnvironment;
+}
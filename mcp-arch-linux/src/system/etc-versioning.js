@@ -0,0 +1,132 @@
+import fs from 'fs-extra';
+import os from 'os';
+import path from 'path';
+import { Git } from './git.js';
+
+// Secrets etckeeper also keeps out of history
+const DEFAULT_IGNORE = ['shadow', 'shadow-', 'gshadow', 'gshadow-', 'ssh/ssh_host_*_key', '*.pacsave', 'mcp-arch-linux/helper.token'];
+
+const COMMIT_HASH = /^[0-9a-f]{7,40}$/i;
+
+// etckeeper-style history for /etc: every tool that declares paths under it gets a commit
+export class EtcVersioning {
+  constructor(config = {}, logger, commandExecutor) {
+    this.enabled = config.enabled ?? true;
+    this.repoDir = config.repoDir || '/etc';
+    this.ignore = config.ignore || DEFAULT_IGNORE;
+    this.logger = logger;
+    this.git = new Git(commandExecutor, logger);
+    this.env = {
+      GIT_AUTHOR_NAME: 'mcp-arch-linux',
+      GIT_AUTHOR_EMAIL: `root@${os.hostname()}`,
+      GIT_COMMITTER_NAME: 'mcp-arch-linux',
+      GIT_COMMITTER_EMAIL: `root@${os.hostname()}`
+    };
+  }
+
+  covers(paths) {
+    const prefix = this.repoDir.endsWith('/') ? this.repoDir : `${this.repoDir}/`;
+    return paths
+      .map(entry => path.resolve(entry))
+      .filter(entry => entry === this.repoDir || entry.startsWith(prefix));
+  }
+
+  // Initializes the repository on first use; an existing etckeeper repo is reused as-is
+  async ensureRepo() {
+    if (await fs.pathExists(path.join(this.repoDir, '.git'))) {
+      return true;
+    }
+    if (process.getuid && process.getuid() !== 0) {
+      return false;
+    }
+
+    await this.git.run(this.repoDir, ['init', '--quiet']);
+    await fs.chmod(path.join(this.repoDir, '.git'), 0o700);
+
+    const ignoreFile = path.join(this.repoDir, '.gitignore');
+    if (!await fs.pathExists(ignoreFile)) {
+      await fs.writeFile(ignoreFile, `${this.ignore.join('\n')}\n`);
+    }
+
+    await this.git.commit(this.repoDir, `Initial import of ${this.repoDir}`, { env: this.env });
+    this.logger.info(`Initialized ${this.repoDir} version history`);
+    return true;
+  }
+
+  async commitOperation(operationId, toolName, paths) {
+    const covered = this.covers(paths);
+    if (!this.enabled || covered.length === 0 || !await this.ensureRepo()) {
+      return null;
+    }
+
+    const relative = covered.map(entry => path.relative(this.repoDir, entry) || '.');
+    const message = `${toolName}: automatic commit\n\nOperation-Id: ${operationId}`;
+    return this.git.commit(this.repoDir, message, { paths: relative, env: this.env });
+  }
+
+  async history({ file = null, limit = 20 } = {}) {
+    if (!await fs.pathExists(path.join(this.repoDir, '.git'))) {
+      return [];
+    }
+
+    const args = [
+      'log',
+      `-n${limit}`,
+      '--name-only',
+      '--format=%x1e%H%x1f%aI%x1f%an%x1f%s%x1f%(trailers:key=Operation-Id,valueonly,separator=%x2C)%x1f'
+    ];
+    if (file) {
+      args.push('--', path.relative(this.repoDir, path.resolve(this.repoDir, file)));
+    }
+
+    const result = await this.git.run(this.repoDir, args);
+    return result.stdout
+      .split('\x1e')
+      .filter(record => record.trim())
+      .map(record => {
+        const [commit, date, author, subject, operationId, files = ''] = record.split('\x1f');
+        return {
+          commit,
+          date,
+          author,
+          subject,
+          operationId: operationId.trim() || null,
+          files: files.split('\n').filter(Boolean)
+        };
+      });
+  }
+
+  async changedFiles(commit) {
+    this.assertCommit(commit);
+    const result = await this.git.run(this.repoDir, ['show', '--name-only', '--format=', commit]);
+    return result.stdout.split('\n').filter(Boolean).map(file => path.join(this.repoDir, file));
+  }
+
+  async revert(commit, { dryRun = false } = {}) {
+    this.assertCommit(commit);
+
+    // Reverting the import commit would delete every tracked file
+    const parents = (await this.git.run(this.repoDir, ['rev-list', '--parents', '-n1', commit])).stdout.split(' ');
+    if (parents.length < 2) {
+      throw new Error(`Cannot revert the initial import commit ${commit}`);
+    }
+
+    if (dryRun) {
+      return {
+        commit,
+        dryRun,
+        diff: (await this.git.run(this.repoDir, ['show', '--no-color', '-R', '--format=%H %s', commit])).stdout
+      };
+    }
+
+    await this.git.run(this.repoDir, ['revert', '--no-edit', commit], { env: this.env });
+    const head = (await this.git.run(this.repoDir, ['rev-parse', 'HEAD'])).stdout;
+    return { commit, dryRun, revertCommit: head };
+  }
+
+  assertCommit(commit) {
+    if (!COMMIT_HASH.test(commit)) {
+      throw new Error(`Invalid commit: ${commit}`);
+    }
+  }
+}
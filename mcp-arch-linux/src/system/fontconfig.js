@@ -0,0 +1,34 @@
+import path from 'path';
+
+// Per-user fontconfig file shared by theme_apply and font_defaults
+export const FONTCONFIG_FILE = '60-mcp-arch-linux.conf';
+
+// Tool parameter names for fontconfig's generic families
+export const GENERIC_FAMILIES = {
+  sansSerif: 'sans-serif',
+  serif: 'serif',
+  monospace: 'monospace',
+  emoji: 'emoji'
+};
+
+export function fontconfigPath(account) {
+  return path.join(account.home, '.config', 'fontconfig', 'conf.d', FONTCONFIG_FILE);
+}
+
+// Generic family -> preferred family, as written by renderFontAliases
+export function parseFontAliases(content) {
+  return Object.fromEntries([...content.matchAll(/<family>([^<]+)<\/family>\s*<prefer><family>([^<]+)<\/family>/g)]
+    .map(([, generic, family]) => [generic, family]));
+}
+
+export function renderFontAliases(aliases) {
+  return [
+    '<?xml version="1.0"?>',
+    '<!DOCTYPE fontconfig SYSTEM "urn:fontconfig:fonts.dtd">',
+    '<fontconfig>',
+    ...Object.entries(aliases).filter(([, family]) => family).map(([generic, family]) =>
+      `  <alias>\n    <family>${generic}</family>\n    <prefer><family>${family}</family></prefer>\n  </alias>`),
+    '</fontconfig>',
+    ''
+  ].join('\n');
+}
@@ -0,0 +1,78 @@
+// Thin git wrapper; commands run as the repository's owner so root never leaves
+// root-owned files inside a user's checkout
+export class Git {
+  constructor(commandExecutor, logger) {
+    this.commandExecutor = commandExecutor;
+    this.logger = logger;
+  }
+
+  async run(repoDir, args, { account = null, timeout, allowFailure = false, env: extraEnv = {} } = {}) {
+    const gitArgs = repoDir ? ['-C', repoDir, ...args] : args;
+    const env = { ...process.env, ...extraEnv, GIT_TERMINAL_PROMPT: '0' };
+
+    const dropPrivileges = account && process.getuid && process.getuid() === 0 && account.uid !== 0;
+    const result = dropPrivileges
+      ? await this.commandExecutor.execute('runuser', ['-u', account.name, '--', 'git', ...gitArgs], {
+        env: { ...env, HOME: account.home },
+        timeout
+      })
+      : await this.commandExecutor.execute('git', gitArgs, { env, timeout });
+
+    if (!result.success && !allowFailure) {
+      throw new Error(`git ${args[0]} failed: ${result.stderr || result.stdout}`);
+    }
+    return result;
+  }
+
+  async clone(url, targetDir, { branch = null, depth = null, account = null } = {}) {
+    const args = ['clone'];
+    if (branch) args.push('--branch', branch);
+    if (depth) args.push('--depth', String(depth));
+    args.push('--', url, targetDir);
+    return this.run(null, args, { account });
+  }
+
+  async pull(repoDir, options = {}) {
+    return this.run(repoDir, ['pull', '--ff-only'], options);
+  }
+
+  // Porcelain v1 parsed into { branch, files: [{ path, index, worktree }] }
+  async status(repoDir, options = {}) {
+    const result = await this.run(repoDir, ['status', '--porcelain=v1', '--branch'], options);
+    const lines = result.stdout.split('\n').filter(Boolean);
+    const branchLine = lines[0]?.startsWith('## ') ? lines.shift().slice(3) : null;
+
+    return {
+      branch: branchLine,
+      clean: lines.length === 0,
+      files: lines.map(line => ({
+        index: line[0],
+        worktree: line[1],
+        path: line.slice(3)
+      }))
+    };
+  }
+
+  async diff(repoDir, { staged = false, paths = [], ...options } = {}) {
+    const args = ['diff', '--no-color'];
+    if (staged) args.push('--cached');
+    if (paths.length > 0) args.push('--', ...paths);
+    return (await this.run(repoDir, args, options)).stdout;
+  }
+
+  async commit(repoDir, message, { paths = [], all = true, ...options } = {}) {
+    await this.run(repoDir, paths.length > 0 ? ['add', '-A', '--', ...paths] : all ? ['add', '-A'] : ['add', '-u'], options);
+
+    const staged = await this.run(repoDir, ['diff', '--cached', '--quiet'], { ...options, allowFailure: true });
+    if (staged.success) {
+      return null; // Nothing to commit
+    }
+
+    await this.run(repoDir, ['commit', '-m', message], options);
+    return (await this.run(repoDir, ['rev-parse', 'HEAD'], options)).stdout;
+  }
+
+  async push(repoDir, options = {}) {
+    return this.run(repoDir, ['push'], options);
+  }
+}
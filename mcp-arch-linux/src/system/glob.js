@@ -0,0 +1,50 @@
+// Minimal glob support for include/exclude filters: *, ?, ** and {a,b}
+export function globToRegExp(pattern) {
+  let regex = '';
+  let inGroup = false;
+
+  for (let i = 0; i < pattern.length; i++) {
+    const char = pattern[i];
+
+    if (char === '*') {
+      if (pattern[i + 1] === '*') {
+        // "**/" matches zero or more directories
+        if (pattern[i + 2] === '/') {
+          regex += '(?:.*/)?';
+          i += 2;
+        } else {
+          regex += '.*';
+          i += 1;
+        }
+      } else {
+        regex += '[^/]*';
+      }
+    } else if (char === '?') {
+      regex += '[^/]';
+    } else if (char === '{') {
+      inGroup = true;
+      regex += '(?:';
+    } else if (char === '}' && inGroup) {
+      inGroup = false;
+      regex += ')';
+    } else if (char === ',' && inGroup) {
+      regex += '|';
+    } else if ('\\^$+.()|[]'.includes(char)) {
+      regex += `\\${char}`;
+    } else {
+      regex += char;
+    }
+  }
+
+  return new RegExp(`^${regex}$`);
+}
+
+// Patterns without a slash match the basename, like .gitignore entries
+export function matchesGlob(filePath, pattern) {
+  const target = pattern.includes('/') ? filePath : filePath.split('/').pop();
+  return globToRegExp(pattern).test(target);
+}
+
+export function matchesAny(filePath, patterns = []) {
+  return patterns.some(pattern => matchesGlob(filePath, pattern));
+}
@@ -0,0 +1,17 @@
+// Hibernation writes memory to swap, so swap should hold all of RAM; the kernel compresses
+// the image, but a full image is what a busy system needs
+export function hibernationSwapSize(totalBytes) {
+  return `${Math.ceil(totalBytes / 1024 ** 3)}G`;
+}
+
+// resume_offset is the physical offset of the swap file's first extent, in filesystem
+// blocks, as filefrag -v prints it:
+//  ext:     logical_offset:        physical_offset: length:   expected: flags:
+//    0:        0..   32767:      34816..     67583:  32768:
+export function parseFilefragOffset(output) {
+  const match = output.match(/^\s*0:\s+\d+\.\.\s*\d+:\s+(\d+)\.\./m);
+  if (!match) {
+    throw new Error('Could not find the first extent in the filefrag output');
+  }
+  return Number(match[1]);
+}
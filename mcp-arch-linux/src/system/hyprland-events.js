@@ -0,0 +1,66 @@
+import { EventEmitter } from 'events';
+import { Socket } from 'net';
+import path from 'path';
+
+const RECONNECT_DELAY = 5000;
+
+// Hyprland's event socket (.socket2.sock next to the request socket) sends one
+// "EVENT>>DATA" line per event. Reconnects after Hyprland restarts until stopped.
+export class HyprlandEventStream extends EventEmitter {
+  constructor(requestSocketPath, logger) {
+    super();
+    this.socketPath = path.join(path.dirname(requestSocketPath), '.socket2.sock');
+    this.logger = logger;
+    this.socket = null;
+    this.timer = null;
+    this.stopped = true;
+  }
+
+  start() {
+    if (!this.stopped) {
+      return;
+    }
+    this.stopped = false;
+    this.connect();
+  }
+
+  connect() {
+    let buffer = '';
+    const socket = new Socket();
+    this.socket = socket;
+
+    socket.connect(this.socketPath, () => {
+      this.logger.debug(`Listening to Hyprland events on ${this.socketPath}`);
+    });
+
+    socket.on('data', (chunk) => {
+      buffer += chunk.toString();
+      const lines = buffer.split('\n');
+      buffer = lines.pop();
+      for (const line of lines) {
+        const separator = line.indexOf('>>');
+        if (separator > 0) {
+          this.emit('event', { name: line.slice(0, separator), data: line.slice(separator + 2) });
+        }
+      }
+    });
+
+    socket.on('error', (error) => {
+      this.logger.debug(`Hyprland event socket error: ${error.message}`);
+    });
+
+    socket.on('close', () => {
+      if (!this.stopped) {
+        this.timer = setTimeout(() => this.connect(), RECONNECT_DELAY);
+        this.timer.unref?.();
+      }
+    });
+  }
+
+  stop() {
+    this.stopped = true;
+    clearTimeout(this.timer);
+    this.socket?.destroy();
+    this.socket = null;
+  }
+}
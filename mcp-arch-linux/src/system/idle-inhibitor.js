@@ -0,0 +1,109 @@
+import { spawn } from 'child_process';
+import { v4 as uuidv4 } from 'uuid';
+import { getSimulator } from './simulator.js';
+
+const WHAT = /^(idle|sleep|shutdown|handle-lid-switch|handle-power-key|handle-suspend-key)(:(idle|sleep|shutdown|handle-lid-switch|handle-power-key|handle-suspend-key))*$/;
+
+// logind inhibitor locks held by systemd-inhibit child processes. Compositor idle
+// daemons that honour logind (hypridle by default) also stop locking the screen.
+export class IdleInhibitor {
+  constructor(commandExecutor, logger) {
+    this.commandExecutor = commandExecutor;
+    this.logger = logger;
+    this.active = new Map();
+  }
+
+  async acquire(reason, { what = 'idle:sleep', key = null, owner = 'mcp-arch-linux' } = {}) {
+    if (!WHAT.test(what)) {
+      throw new Error(`Invalid inhibitor type: ${what}`);
+    }
+
+    if (key) {
+      const existing = [...this.active.values()].find(entry => entry.key === key);
+      if (existing) return existing.id;
+    }
+
+    this.commandExecutor.validateCommand('systemd-inhibit');
+
+    // Simulation mode holds no logind lock
+    if (getSimulator().enabled) {
+      const id = uuidv4();
+      this.active.set(id, { id, key, reason, what, since: new Date().toISOString(), pid: null, child: null });
+      return id;
+    }
+
+    const executable = await this.commandExecutor.toolResolver.resolve('systemd-inhibit');
+    if (!executable) {
+      throw new Error('systemd-inhibit is not available');
+    }
+
+    // cat exits when our end of its stdin closes, so the lock cannot outlive the server
+    const child = spawn(executable, [
+      `--what=${what}`,
+      `--who=${owner}`,
+      `--why=${reason}`,
+      '--mode=block',
+      'cat'
+    ], { stdio: ['pipe', 'ignore', 'pipe'] });
+
+    const id = uuidv4();
+    const entry = { id, key, reason, what, since: new Date().toISOString(), pid: child.pid, child };
+
+    await new Promise((resolve, reject) => {
+      let stderr = '';
+      child.stderr.on('data', data => { stderr += data; });
+      child.once('error', reject);
+      child.once('exit', code => {
+        this.active.delete(id);
+        reject(new Error(`systemd-inhibit exited with code ${code}: ${stderr.trim()}`));
+      });
+      // logind grants the lock before running the command; a failure exits almost immediately
+      setTimeout(resolve, 200);
+    });
+
+    child.removeAllListeners('exit');
+    child.once('exit', () => this.active.delete(id));
+    this.active.set(id, entry);
+
+    this.logger.info('Idle inhibitor acquired', { id, reason, what });
+    return id;
+  }
+
+  release(idOrKey) {
+    const entry = this.active.get(idOrKey) || [...this.active.values()].find(candidate => candidate.key === idOrKey);
+    if (!entry) {
+      return false;
+    }
+
+    this.active.delete(entry.id);
+    entry.child?.stdin.end();
+    this.logger.info('Idle inhibitor released', { id: entry.id, reason: entry.reason });
+    return true;
+  }
+
+  releaseAll() {
+    for (const id of [...this.active.keys()]) {
+      this.release(id);
+    }
+  }
+
+  list() {
+    return [...this.active.values()].map(({ child, ...entry }) => entry);
+  }
+
+  // Every inhibitor logind knows about, including those held by other programs
+  async listSystem() {
+    const result = await this.commandExecutor.execute('systemd-inhibit', ['--list', '--no-pager', '--no-legend']);
+    if (!result.success) {
+      throw new Error(`Listing inhibitors failed: ${result.stderr}`);
+    }
+
+    return result.stdout.split('\n').filter(line => line.trim()).map(line => {
+      // Columns are padded with at least two spaces; WHY may contain single spaces
+      const [who, uid, user, pid, comm, what, why, mode] = line.trim().split(/\s{2,}/);
+      return mode
+        ? { who, uid: Number(uid), user, pid: Number(pid), comm, what, why, mode }
+        : { raw: line.trim() };
+    });
+  }
+}
@@ -0,0 +1,108 @@
+const SHARP_FORMATS = { png: 'png', jpg: 'jpeg', webp: 'webp' };
+
+let sharpModule = null;
+
+// sharp is loaded on first use so plain captures keep working where its native build is missing
+async function loadSharp() {
+  if (!sharpModule) {
+    try {
+      sharpModule = (await import('sharp')).default;
+    } catch (error) {
+      throw new Error(`Image processing requires sharp: ${error.message}`);
+    }
+  }
+  return sharpModule;
+}
+
+// Crop (in image pixels), then shrink to maxWidth keeping the aspect ratio, then re-encode
+export async function processImage(data, { maxWidth = null, crop = null, format = 'png', quality = 90 } = {}) {
+  const sharp = await loadSharp();
+  let image = sharp(data);
+
+  if (crop) {
+    const { width, height } = await image.metadata();
+    const left = Math.max(0, Math.min(crop.x, width - 1));
+    const top = Math.max(0, Math.min(crop.y, height - 1));
+    image = image.extract({
+      left,
+      top,
+      width: Math.max(1, Math.min(crop.width, width - left)),
+      height: Math.max(1, Math.min(crop.height, height - top))
+    });
+  }
+  if (maxWidth) {
+    image = image.resize({ width: maxWidth, withoutEnlargement: true });
+  }
+
+  const { data: output, info } = await image
+    .toFormat(SHARP_FORMATS[format] || 'png', { quality })
+    .toBuffer({ resolveWithObject: true });
+  return { data: output, width: info.width, height: info.height };
+}
+
+// Places per-output captures at their layout position. Coordinates are logical, so each
+// capture is scaled down by its output scale; gaps between outputs stay transparent (black for jpg).
+export async function stitchImages(tiles, { format = 'png', quality = 90 } = {}) {
+  const sharp = await loadSharp();
+  const originX = Math.min(...tiles.map(tile => tile.geometry.x));
+  const originY = Math.min(...tiles.map(tile => tile.geometry.y));
+
+  const placed = await Promise.all(tiles.map(async (tile) => {
+    const { width, height } = await sharp(tile.data).metadata();
+    const logicalWidth = Math.round(width / (tile.scale || 1));
+    const logicalHeight = Math.round(height / (tile.scale || 1));
+    return {
+      input: await sharp(tile.data).resize(logicalWidth, logicalHeight, { fit: 'fill' }).toBuffer(),
+      left: tile.geometry.x - originX,
+      top: tile.geometry.y - originY,
+      width: logicalWidth,
+      height: logicalHeight
+    };
+  }));
+
+  const canvas = {
+    width: Math.max(...placed.map(tile => tile.left + tile.width)),
+    height: Math.max(...placed.map(tile => tile.top + tile.height)),
+    channels: 4,
+    background: { r: 0, g: 0, b: 0, alpha: 0 }
+  };
+
+  return sharp({ create: canvas })
+    .composite(placed.map(({ input, left, top }) => ({ input, left, top })))
+    .toFormat(SHARP_FORMATS[format] || 'png', { quality })
+    .toBuffer();
+}
+
+// First pixel of a binary PPM (P6), which grim writes without needing an image library
+export function readPpmPixel(data) {
+  const header = data.subarray(0, 64).toString('latin1').match(/^P6\s+(\d+)\s+(\d+)\s+(\d+)\s/);
+  if (!header || Number(header[3]) > 255) {
+    throw new Error('Unexpected image data: expected an 8-bit binary PPM');
+  }
+  const [r, g, b] = data.subarray(header[0].length, header[0].length + 3);
+  return { r, g, b };
+}
+
+// The notations theming configs use; Hyprland takes rgb(rrggbb) and rgba(rrggbbaa)
+export function describeColor({ r, g, b }) {
+  const hex = [r, g, b].map(value => value.toString(16).padStart(2, '0')).join('');
+  const max = Math.max(r, g, b) / 255;
+  const min = Math.min(r, g, b) / 255;
+  const lightness = (max + min) / 2;
+  const delta = max - min;
+  let hue = 0;
+  if (delta > 0) {
+    const [rn, gn, bn] = [r / 255, g / 255, b / 255];
+    hue = max === rn ? ((gn - bn) / delta) % 6 : max === gn ? (bn - rn) / delta + 2 : (rn - gn) / delta + 4;
+    hue = Math.round(hue * 60 + 360) % 360;
+  }
+  const saturation = delta === 0 ? 0 : delta / (1 - Math.abs(2 * lightness - 1));
+
+  return {
+    hex: `#${hex}`,
+    rgb: { r, g, b },
+    css: `rgb(${r}, ${g}, ${b})`,
+    hsl: { h: hue, s: Math.round(saturation * 100), l: Math.round(lightness * 100) },
+    hyprland: `rgb(${hex})`
+  };
+}
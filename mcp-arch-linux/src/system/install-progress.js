@@ -0,0 +1,166 @@
+import fs from 'fs-extra';
+import path from 'path';
+import { errorOutcome } from '../core/errors.js';
+
+// The steps of an install in order, with the tool that carries each out; optional ones
+// do not hold up the steps after them, and partitioning again wipes what they did
+export const INSTALL_STEPS = [
+  { id: 'partition', tool: 'arch_partition_disk', title: 'Partition disk', resetsLater: true },
+  { id: 'mount', tool: 'arch_mount_system', title: 'Mount system' },
+  { id: 'base', tool: 'arch_install_base', title: 'Install base system' },
+  { id: 'configure', tool: 'arch_configure_system', title: 'Configure system' },
+  { id: 'bootloader', tool: 'arch_install_bootloader', title: 'Install bootloader' },
+  { id: 'hibernation', tool: 'arch_configure_hibernation', title: 'Configure hibernation', optional: true },
+  { id: 'verify', tool: 'arch_install_verify', title: 'Verify installation', optional: true },
+  { id: 'complete', tool: 'arch_complete_installation', title: 'Complete installation' }
+];
+
+function pendingStep({ id, tool, title, optional = false }) {
+  return { id, tool, title, optional, status: 'pending', startedAt: null, finishedAt: null, attempts: 0, error: null, details: null };
+}
+
+// Where an install stands, step by step (pending, running, succeeded or failed, with
+// times and the last error), kept in a file so it outlives the server and the session
+// that started it
+export class InstallProgress {
+  constructor(file, logger) {
+    this.file = file;
+    this.logger = logger;
+    this.listeners = new Set();
+    this.saving = Promise.resolve();
+    this.state = this.fresh({});
+  }
+
+  fresh(context) {
+    const now = new Date().toISOString();
+    return { startedAt: now, updatedAt: now, context, steps: INSTALL_STEPS.map(pendingStep) };
+  }
+
+  async load() {
+    if (!this.file || !await fs.pathExists(this.file)) {
+      return;
+    }
+    try {
+      const saved = await fs.readJson(this.file);
+      this.state = {
+        ...saved,
+        steps: INSTALL_STEPS.map(step => ({ ...pendingStep(step), ...saved.steps?.find(entry => entry.id === step.id) }))
+      };
+    } catch (error) {
+      this.logger.warn(`Ignoring unreadable install state ${this.file}: ${error.message}`);
+      return;
+    }
+    // Nothing finishes a step the previous server process was in the middle of
+    for (const step of this.state.steps.filter(entry => entry.status === 'running')) {
+      this.finish(step, 'failed', { error: { kind: 'tool_failed', message: 'The server stopped while this step was running' } });
+    }
+  }
+
+  step(id) {
+    const step = this.state.steps.find(entry => entry.id === id);
+    if (!step) {
+      throw new Error(`Unknown install step: ${id}`);
+    }
+    return step;
+  }
+
+  // A new install (another target or disk) starts every step over
+  reset(context = {}) {
+    this.state = this.fresh(context);
+    this.changed();
+  }
+
+  // Where the install goes (target mount, image), shown alongside the steps
+  setContext(fields) {
+    if (Object.entries(fields).some(([key, value]) => this.state.context[key] !== value)) {
+      Object.assign(this.state.context, fields);
+      this.changed();
+    }
+  }
+
+  // A new partition table needs mounting, pacstrap and so on again; other steps can be
+  // redone (a remount, a second configure) without undoing the ones after them
+  start(id, details = null) {
+    const index = INSTALL_STEPS.findIndex(step => step.id === id);
+    const step = this.step(id);
+    if (INSTALL_STEPS[index].resetsLater) {
+      for (const later of this.state.steps.slice(index + 1).filter(entry => entry.status !== 'pending')) {
+        Object.assign(later, pendingStep(INSTALL_STEPS.find(entry => entry.id === later.id)), { attempts: later.attempts });
+      }
+    }
+    Object.assign(step, { status: 'running', startedAt: new Date().toISOString(), finishedAt: null, error: null, details });
+    step.attempts += 1;
+    this.changed();
+  }
+
+  // Only a step that was started finishes; calls for another target are not tracked
+  succeed(id, details = undefined) {
+    const step = this.step(id);
+    if (step.status === 'running') {
+      this.finish(step, 'succeeded', details === undefined ? {} : { details: { ...step.details, ...details } });
+    }
+  }
+
+  fail(id, error) {
+    const step = this.step(id);
+    if (step.status === 'running') {
+      this.finish(step, 'failed', { error: errorOutcome(error) });
+    }
+  }
+
+  // A tool that throws leaves the step it started running; this marks it failed
+  failRunning(error) {
+    for (const step of this.state.steps.filter(entry => entry.status === 'running')) {
+      this.finish(step, 'failed', { error: errorOutcome(error) });
+    }
+  }
+
+  finish(step, status, fields = {}) {
+    Object.assign(step, { status, finishedAt: new Date().toISOString(), ...fields });
+    this.changed();
+  }
+
+  // The step in progress, else the first required one not done yet
+  current() {
+    return this.state.steps.find(step => step.status === 'running') ||
+      this.state.steps.find(step => !step.optional && step.status !== 'succeeded') || null;
+  }
+
+  completed() {
+    return this.step('complete').status === 'succeeded';
+  }
+
+  snapshot() {
+    const current = this.current();
+    return {
+      ...this.state,
+      currentStep: current ? { id: current.id, status: current.status } : null,
+      completed: this.completed()
+    };
+  }
+
+  subscribe(listener) {
+    this.listeners.add(listener);
+    return () => this.listeners.delete(listener);
+  }
+
+  changed() {
+    this.state.updatedAt = new Date().toISOString();
+    this.save();
+    for (const listener of this.listeners) {
+      listener({});
+    }
+  }
+
+  save() {
+    if (!this.file) {
+      return this.saving;
+    }
+    const contents = JSON.stringify(this.state, null, 2);
+    this.saving = this.saving
+      .then(() => fs.ensureDir(path.dirname(this.file)))
+      .then(() => fs.writeFile(this.file, contents, { mode: 0o600 }))
+      .catch(error => this.logger.warn('Could not write install state:', error.message));
+    return this.saving;
+  }
+}
@@ -0,0 +1,164 @@
+import crypto from 'crypto';
+import fs from 'fs-extra';
+import path from 'path';
+import { Readable, Transform } from 'stream';
+import { pipeline } from 'stream/promises';
+
+// Checksums and signatures come from archlinux.org; the image itself from a mirror
+export const ISO_MIRROR = 'https://geo.mirror.pkgbuild.com/iso/latest/';
+export const ISO_RELEASE_URL = 'https://archlinux.org/iso/latest/';
+
+const ISO_NAME = /^archlinux-\d{4}\.\d{2}\.\d{2}-x86_64\.iso$/;
+
+export function parseChecksums(text) {
+  return Object.fromEntries(text.split('\n')
+    .map(line => line.trim().match(/^([0-9a-f]{64})\s+\*?(\S+)$/))
+    .filter(Boolean)
+    .map(([, hash, name]) => [name, hash]));
+}
+
+// Hashes a file or the first `length` bytes of a block device
+export async function sha256(file, { length = null, onProgress = null } = {}) {
+  const hash = crypto.createHash('sha256');
+  let done = 0;
+  const stream = fs.createReadStream(file, length ? { start: 0, end: length - 1 } : {});
+  for await (const chunk of stream) {
+    hash.update(chunk);
+    done += chunk.length;
+    onProgress?.(done);
+  }
+  return hash.digest('hex');
+}
+
+const withSlash = url => url.endsWith('/') ? url : `${url}/`;
+
+export class IsoMedia {
+  constructor(commandExecutor, logger, options = {}) {
+    this.commandExecutor = commandExecutor;
+    this.logger = logger;
+    this.mirror = withSlash(options.isoMirror || ISO_MIRROR);
+    this.releaseUrl = withSlash(options.isoReleaseUrl || ISO_RELEASE_URL);
+    this.isoDir = options.isoDir || '/var/lib/mcp-arch-linux/iso';
+  }
+
+  async fetchText(url) {
+    const response = await fetch(url, { signal: AbortSignal.timeout(30000) });
+    if (!response.ok) {
+      throw new Error(`${url} returned HTTP ${response.status}`);
+    }
+    return response.text();
+  }
+
+  async latestRelease() {
+    const checksums = parseChecksums(await this.fetchText(`${this.releaseUrl}sha256sums.txt`));
+    const name = Object.keys(checksums).find(candidate => ISO_NAME.test(candidate));
+    if (!name) {
+      throw new Error('No versioned ISO listed in sha256sums.txt');
+    }
+    return { name, sha256: checksums[name] };
+  }
+
+  async download(url, dest, { onProgress = null } = {}) {
+    const response = await fetch(url);
+    if (!response.ok) {
+      throw new Error(`${url} returned HTTP ${response.status}`);
+    }
+
+    const total = Number(response.headers.get('content-length')) || null;
+    let received = 0;
+    const counter = new Transform({
+      transform(chunk, encoding, callback) {
+        received += chunk.length;
+        onProgress?.(received, total);
+        callback(null, chunk);
+      }
+    });
+
+    // A partial file never takes the final name
+    const partial = `${dest}.part`;
+    await pipeline(Readable.fromWeb(response.body), counter, fs.createWriteStream(partial));
+    await fs.move(partial, dest, { overwrite: true });
+    return { bytes: received };
+  }
+
+  // Downloads the ISO and its signature into isoDir, reusing a complete earlier download
+  async fetchIso({ onProgress = null } = {}) {
+    const release = await this.latestRelease();
+    await fs.ensureDir(this.isoDir);
+
+    const isoPath = path.join(this.isoDir, release.name);
+    const sigPath = `${isoPath}.sig`;
+
+    const cached = await fs.pathExists(isoPath) && await sha256(isoPath) === release.sha256;
+    if (!cached) {
+      await this.download(`${this.mirror}${release.name}`, isoPath, { onProgress });
+    }
+    await this.download(`${this.releaseUrl}${release.name}.sig`, sigPath);
+
+    return { ...release, path: isoPath, signature: sigPath, cached };
+  }
+
+  // pacman's keyring already trusts the release engineers' keys; plain gpg needs them imported
+  async verifySignature(isoPath, sigPath = `${isoPath}.sig`) {
+    if (!await fs.pathExists(sigPath)) {
+      throw new Error(`Signature not found: ${sigPath}`);
+    }
+
+    const usePacmanKey = await this.commandExecutor.checkCommandExists('pacman-key');
+    const result = usePacmanKey
+      ? await this.commandExecutor.execute('pacman-key', ['--verify', sigPath, isoPath])
+      : await this.commandExecutor.execute('gpg', ['--auto-key-locate', 'clear,wkd', '--auto-key-retrieve', '--verify', sigPath, isoPath]);
+
+    const signer = `${result.stdout}\n${result.stderr}`.match(/Good signature from "([^"]+)"/);
+    return {
+      valid: result.success,
+      method: usePacmanKey ? 'pacman-key' : 'gpg',
+      signer: signer ? signer[1] : null,
+      ...(result.success ? {} : { output: result.stderr.trim() })
+    };
+  }
+
+  async verifyIso(isoPath, { expectedSha256 = null } = {}) {
+    let expected = expectedSha256;
+    if (!expected) {
+      const checksums = parseChecksums(await this.fetchText(`${this.releaseUrl}sha256sums.txt`));
+      expected = checksums[path.basename(isoPath)];
+      if (!expected) {
+        throw new Error(`${path.basename(isoPath)} is not listed in the current sha256sums.txt; pass sha256 explicitly`);
+      }
+    }
+
+    const actual = await sha256(isoPath);
+    return {
+      checksum: { valid: actual === expected, expected, actual },
+      signature: await this.verifySignature(isoPath)
+    };
+  }
+
+  // dd reports "<bytes> bytes (...) copied" on stderr with status=progress
+  async writeImage(isoPath, device, { onProgress = null } = {}) {
+    const { size } = await fs.stat(isoPath);
+    const result = await this.commandExecutor.executeWithSudo('dd', [
+      `if=${isoPath}`,
+      `of=${device}`,
+      'bs=4M',
+      'conv=fsync',
+      'oflag=direct',
+      'status=progress'
+    ], {
+      retry: false,
+      timeout: 60 * 60 * 1000,
+      onOutput: (text, stream) => {
+        const matches = stream === 'stderr' ? [...text.matchAll(/(\d+) bytes/g)] : [];
+        if (matches.length > 0) {
+          onProgress?.(Number(matches[matches.length - 1][1]), size);
+        }
+      }
+    });
+
+    if (!result.success) {
+      throw new Error(`Writing ${device} failed: ${result.stderr.split(/\r|\n/).filter(Boolean).pop()}`);
+    }
+    return { bytes: size };
+  }
+}
@@ -0,0 +1,90 @@
+import fs from 'fs-extra';
+import path from 'path';
+
+export const MODULE_NAME_PATTERN = /^[a-zA-Z0-9_-]+$/;
+export const MODULES_LOAD_FILE = '/etc/modules-load.d/mcp-arch-linux.conf';
+export const MODPROBE_FILE = '/etc/modprobe.d/mcp-arch-linux.conf';
+const MODPROBE_DIRS = ['/etc/modprobe.d', '/usr/lib/modprobe.d'];
+const MODULES_LOAD_DIRS = ['/etc/modules-load.d', '/usr/lib/modules-load.d'];
+
+// modprobe treats - and _ in module names as the same character
+export function normalizeModuleName(name) {
+  if (!MODULE_NAME_PATTERN.test(name)) {
+    throw new Error(`Invalid module name: ${name}`);
+  }
+  return name.replace(/-/g, '_');
+}
+
+// /proc/modules: name size refcount users state address
+export function parseProcModules(content) {
+  return content.split('\n').filter(Boolean).map(line => {
+    const [name, size, refcount, users, state] = line.split(/\s+/);
+    return {
+      name,
+      size: parseInt(size, 10),
+      refcount: parseInt(refcount, 10),
+      usedBy: users === '-' ? [] : users.split(',').filter(Boolean),
+      state
+    };
+  });
+}
+
+// Containers without their own kernel have no /proc/modules
+export async function loadedModules() {
+  return parseProcModules(await fs.readFile('/proc/modules', 'utf8').catch(() => ''));
+}
+
+async function confFiles(dirs) {
+  const files = [];
+  for (const dir of dirs) {
+    for (const name of (await fs.readdir(dir).catch(() => [])).filter(candidate => candidate.endsWith('.conf'))) {
+      files.push(path.join(dir, name));
+    }
+  }
+  return files;
+}
+
+// blacklist, options and install lines from modprobe.d, plus what modules-load.d loads at boot
+export async function persistedModuleConfig() {
+  const config = { blacklisted: {}, options: {}, install: {}, loadAtBoot: {} };
+
+  for (const file of await confFiles(MODPROBE_DIRS)) {
+    for (const line of (await fs.readFile(file, 'utf8').catch(() => '')).split('\n')) {
+      const [directive, module, ...rest] = line.trim().split(/\s+/);
+      if (!module || directive.startsWith('#')) {
+        continue;
+      }
+      const name = module.replace(/-/g, '_');
+      if (directive === 'blacklist') {
+        config.blacklisted[name] = file;
+      } else if (directive === 'options') {
+        config.options[name] = { options: rest.join(' '), file };
+      } else if (directive === 'install') {
+        config.install[name] = { command: rest.join(' '), file };
+      }
+    }
+  }
+
+  for (const file of await confFiles(MODULES_LOAD_DIRS)) {
+    for (const line of (await fs.readFile(file, 'utf8').catch(() => '')).split('\n')) {
+      const name = line.trim();
+      if (name && !name.startsWith('#') && !name.startsWith(';')) {
+        config.loadAtBoot[name.replace(/-/g, '_')] = file;
+      }
+    }
+  }
+  return config;
+}
+
+// Rewrites the lines of a config file this server owns: drops lines matching `remove`,
+// then appends `add` lines that are not there yet
+export function editConfLines(content, { add = [], remove = () => false }) {
+  const lines = (content || '').split('\n').filter((line, index, all) => !(index === all.length - 1 && line === ''));
+  const kept = lines.filter(line => !remove(line.trim()));
+  for (const line of add) {
+    if (!kept.includes(line)) {
+      kept.push(line);
+    }
+  }
+  return kept.length > 0 ? `${kept.join('\n')}\n` : '';
+}
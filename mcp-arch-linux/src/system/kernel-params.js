@@ -0,0 +1,283 @@
+import fs from 'fs-extra';
+import path from 'path';
+
+export const SYSCTL_KEY_PATTERN = /^[a-zA-Z0-9_][a-zA-Z0-9_.\/-]*$/;
+// Read in this order; a file name in an earlier directory masks the same name later
+const SYSCTL_DIRS = ['/etc/sysctl.d', '/run/sysctl.d', '/usr/local/lib/sysctl.d', '/usr/lib/sysctl.d'];
+
+export const GRUB_DEFAULTS = '/etc/default/grub';
+export const GRUB_CONFIG = '/boot/grub/grub.cfg';
+export const KERNEL_CMDLINE = '/etc/kernel/cmdline';
+
+// sysctl accepts / as well as . between components
+export function normalizeSysctlKey(key) {
+  if (!SYSCTL_KEY_PATTERN.test(key)) {
+    throw new Error(`Invalid sysctl key: ${key}`);
+  }
+  return key.replace(/\//g, '.');
+}
+
+export function parseSysctlLines(content) {
+  const entries = [];
+  for (const line of content.split('\n')) {
+    const match = line.match(/^\s*-?([^#;=\s][^=]*?)\s*=\s*(.*?)\s*$/);
+    if (match) {
+      entries.push({ key: normalizeSysctlKey(match[1]), value: match[2] });
+    }
+  }
+  return entries;
+}
+
+// Effective persisted settings: files sorted by name across the sysctl.d directories,
+// later files overriding earlier ones, as systemd-sysctl applies them
+export async function readPersistedSysctl() {
+  const files = new Map();
+  for (const dir of SYSCTL_DIRS) {
+    const names = await fs.readdir(dir).catch(() => []);
+    for (const name of names.filter(candidate => candidate.endsWith('.conf'))) {
+      if (!files.has(name)) {
+        files.set(name, path.join(dir, name));
+      }
+    }
+  }
+
+  const ordered = Array.from(files.keys()).sort().map(name => files.get(name));
+  if (await fs.pathExists('/etc/sysctl.conf')) {
+    ordered.push('/etc/sysctl.conf');
+  }
+
+  const settings = new Map();
+  for (const file of ordered) {
+    for (const entry of parseSysctlLines(await fs.readFile(file, 'utf8').catch(() => ''))) {
+      settings.set(entry.key, { value: entry.value, file });
+    }
+  }
+  return settings;
+}
+
+// Replaces each key's line in a drop-in file, appending keys it does not have yet
+export function setSysctlLines(content, values) {
+  const lines = content ? content.replace(/\n$/, '').split('\n') : [];
+  const pending = new Map(Object.entries(values));
+
+  const updated = lines.map(line => {
+    const [entry] = parseSysctlLines(line);
+    if (entry && pending.has(entry.key)) {
+      const value = pending.get(entry.key);
+      pending.delete(entry.key);
+      return `${entry.key} = ${value}`;
+    }
+    return line;
+  });
+
+  for (const [key, value] of pending) {
+    updated.push(`${key} = ${value}`);
+  }
+  return `${updated.join('\n')}\n`;
+}
+
+// Splits a kernel command line, keeping quoted values such as foo="a b" together
+export function parseCmdline(cmdline) {
+  return (cmdline.match(/(?:[^\s"]+|"[^"]*")+/g) || []);
+}
+
+function paramName(param) {
+  return param.split('=')[0];
+}
+
+// add replaces a parameter with the same name (quiet, loglevel=3); remove matches the
+// exact parameter or, given a bare name, every value of it
+export function editCmdline(params, { add = [], remove = [] }) {
+  let result = params.filter(param => !remove.some(target =>
+    target === param || (!target.includes('=') && paramName(param) === target)));
+
+  for (const param of add) {
+    const index = result.findIndex(existing => paramName(existing) === paramName(param));
+    if (index >= 0) {
+      result[index] = param;
+    } else {
+      result = [...result, param];
+    }
+  }
+  return result;
+}
+
+// Minimal unified diff for previews: one hunk spanning the first to the last changed line
+export function previewDiff(file, before, after) {
+  if (before === after) {
+    return '';
+  }
+  const a = before ? before.replace(/\n$/, '').split('\n') : [];
+  const b = after ? after.replace(/\n$/, '').split('\n') : [];
+
+  let start = 0;
+  while (start < a.length && start < b.length && a[start] === b[start]) {
+    start++;
+  }
+  let endA = a.length;
+  let endB = b.length;
+  while (endA > start && endB > start && a[endA - 1] === b[endB - 1]) {
+    endA--;
+    endB--;
+  }
+
+  const context = 2;
+  const from = Math.max(0, start - context);
+  const toA = Math.min(a.length, endA + context);
+  const toB = Math.min(b.length, endB + context);
+
+  return [
+    `--- ${before === null ? '/dev/null' : file}`,
+    `+++ ${file}`,
+    `@@ -${a.length > 0 ? from + 1 : 0},${toA - from} +${b.length > 0 ? from + 1 : 0},${toB - from} @@`,
+    ...a.slice(from, start).map(line => ` ${line}`),
+    ...a.slice(start, endA).map(line => `-${line}`),
+    ...b.slice(start, endB).map(line => `+${line}`),
+    ...a.slice(endA, toA).map(line => ` ${line}`)
+  ].join('\n');
+}
+
+// Where the kernel command line is configured and how to regenerate what the bootloader reads:
+//   grub: GRUB_CMDLINE_LINUX_DEFAULT in /etc/default/grub, then grub-mkconfig
+//   uki: /etc/kernel/cmdline, baked into unified kernel images by mkinitcpio -P
+//   systemd-boot: the options line of each loader entry, read directly at boot
+// root points at another system's tree, such as an install target; regenerating is then
+// up to the caller, inside a chroot
+export class BootCmdline {
+  constructor(executor, logger, { bootloader = 'auto', root = '/' } = {}) {
+    this.executor = executor;
+    this.logger = logger;
+    this.bootloader = bootloader;
+    this.root = root;
+  }
+
+  resolve(file) {
+    return path.join(this.root, file);
+  }
+
+  async detect() {
+    if (this.bootloader !== 'auto') {
+      return this.bootloader;
+    }
+    if (await fs.pathExists(this.resolve(KERNEL_CMDLINE))) {
+      return 'uki';
+    }
+    if (await fs.pathExists(this.resolve(GRUB_DEFAULTS)) && await fs.pathExists(this.resolve(GRUB_CONFIG))) {
+      return 'grub';
+    }
+    if (await this.entriesDir()) {
+      return 'systemd-boot';
+    }
+    throw new Error('No supported bootloader configuration found (GRUB, systemd-boot entries or /etc/kernel/cmdline); set plugins.sysctl.bootloader');
+  }
+
+  async entriesDir() {
+    // bootctl only knows the running system's ESP
+    const result = this.root === '/' ? await this.executor.execute('bootctl', ['--print-esp-path']) : { success: false };
+    const candidates = [result.success ? result.stdout.trim() : null, '/boot', '/efi', '/boot/efi'].filter(Boolean);
+    for (const esp of candidates) {
+      const dir = this.resolve(path.join(esp, 'loader/entries'));
+      if (await fs.pathExists(dir)) {
+        return dir;
+      }
+    }
+    return null;
+  }
+
+  // Files that hold the command line, and the parameters each one sets
+  async read() {
+    const type = await this.detect();
+    switch (type) {
+      case 'grub': {
+        const content = await fs.readFile(this.resolve(GRUB_DEFAULTS), 'utf8');
+        const match = content.match(/^GRUB_CMDLINE_LINUX_DEFAULT=(["']?)(.*)\1\s*$/m);
+        return { type, files: [{ path: this.resolve(GRUB_DEFAULTS), content, params: parseCmdline(match ? match[2] : '') }] };
+      }
+      case 'uki': {
+        const content = await fs.readFile(this.resolve(KERNEL_CMDLINE), 'utf8');
+        return { type, files: [{ path: this.resolve(KERNEL_CMDLINE), content, params: parseCmdline(content.trim()) }] };
+      }
+      case 'systemd-boot': {
+        const dir = await this.entriesDir();
+        if (!dir) {
+          throw new Error('No systemd-boot loader/entries directory found');
+        }
+        const names = (await fs.readdir(dir)).filter(name => name.endsWith('.conf')).sort();
+        const files = [];
+        for (const name of names) {
+          const content = await fs.readFile(path.join(dir, name), 'utf8');
+          const options = [...content.matchAll(/^options\s+(.*)$/gm)].map(match => match[1]).join(' ');
+          files.push({ path: path.join(dir, name), content, params: parseCmdline(options) });
+        }
+        return { type, files };
+      }
+      default:
+        throw new Error(`Unsupported bootloader: ${type}`);
+    }
+  }
+
+  // New content for each file; unchanged files are left out
+  plan(current, edit) {
+    return current.files.map(file => {
+      const params = editCmdline(file.params, edit);
+      const joined = params.join(' ');
+      let content;
+      switch (current.type) {
+        case 'grub':
+          content = /^GRUB_CMDLINE_LINUX_DEFAULT=/m.test(file.content)
+            ? file.content.replace(/^GRUB_CMDLINE_LINUX_DEFAULT=.*$/m, `GRUB_CMDLINE_LINUX_DEFAULT="${joined}"`)
+            : `${file.content.replace(/\n?$/, '\n')}GRUB_CMDLINE_LINUX_DEFAULT="${joined}"\n`;
+          break;
+        case 'uki':
+          content = `${joined}\n`;
+          break;
+        default: {
+          // Several options lines are concatenated at boot; collapse them into one
+          let replaced = false;
+          content = file.content.split('\n').flatMap(line => {
+            if (!/^options\s/.test(line)) {
+              return [line];
+            }
+            if (replaced) {
+              return [];
+            }
+            replaced = true;
+            return [`options ${joined}`];
+          }).join('\n');
+          if (!replaced) {
+            content = `${content.replace(/\n?$/, '\n')}options ${joined}\n`;
+          }
+        }
+      }
+      return { path: file.path, before: file.content, after: content, params };
+    }).filter(change => change.before !== change.after);
+  }
+
+  async regenerate(type) {
+    let result;
+    switch (type) {
+      case 'grub':
+        result = await this.executor.executeWithSudo('grub-mkconfig', ['-o', GRUB_CONFIG], { timeout: 300000 });
+        break;
+      case 'uki':
+        result = await this.executor.executeWithSudo('mkinitcpio', ['-P'], { timeout: 900000 });
+        break;
+      default:
+        return null;
+    }
+    if (!result.success) {
+      throw new Error(`Regenerating the ${type} boot configuration failed: ${result.stderr.trim().split('\n').slice(-5).join('\n')}`);
+    }
+    return type === 'grub' ? `grub-mkconfig -o ${GRUB_CONFIG}` : 'mkinitcpio -P';
+  }
+
+  // Files a change touches, for snapshots: the edited files plus what regeneration rewrites
+  async affectedPaths() {
+    const current = await this.read();
+    const paths = current.files.map(file => file.path);
+    if (current.type === 'grub') {
+      paths.push(GRUB_CONFIG);
+    }
+    return paths;
+  }
+}
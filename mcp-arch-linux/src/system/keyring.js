@@ -0,0 +1,221 @@
+import fs from 'fs-extra';
+import { targetPath } from './target.js';
+
+export const KEYRING_DIR = '/etc/pacman.d/gnupg';
+const TIME_URL = 'https://archlinux.org';
+// gpg refuses signatures and keys from the future; a few seconds of drift is harmless
+const MAX_CLOCK_SKEW_SECONDS = 60;
+
+// gpg --with-colons validity letters
+const VALIDITY = {
+  e: 'expired',
+  r: 'revoked',
+  d: 'disabled',
+  i: 'invalid',
+  n: 'untrusted',
+  m: 'marginal',
+  f: 'full',
+  u: 'ultimate',
+  q: 'unknown',
+  '-': 'unknown',
+  o: 'unknown'
+};
+
+// The key IDs and signer names pacman mentions when a signature check fails
+export function parseSignatureErrors(text = '') {
+  const keys = new Set([...text.matchAll(/key "?([0-9A-Fa-f]{8,40})"?/g)].map(match => match[1].toUpperCase()));
+  const signers = new Set([...text.matchAll(/signature from "([^"]+)"/g)].map(match => match[1]));
+  const packages = new Set([...text.matchAll(/error: ([^:\s]+): signature from/g)].map(match => match[1]));
+
+  return {
+    keys: Array.from(keys),
+    signers: Array.from(signers),
+    packages: Array.from(packages),
+    unknownTrust: /is unknown trust/.test(text),
+    marginalTrust: /is marginal trust/.test(text),
+    invalidSignature: /signature from "[^"]+" is invalid/.test(text) || /invalid or corrupted package \(PGP signature\)/.test(text),
+    unknownKey: /key "?[0-9A-Fa-f]+"? is unknown|could not be looked up remotely/.test(text),
+    expired: /expired/i.test(text),
+    future: /in the future|time warp|clock problem/i.test(text),
+    noKeyring: /keyring .* does not exist|keyring is not writable|no public key|trustdb .* not found/i.test(text)
+  };
+}
+
+// Keys from `gpg --with-colons --list-keys`
+export function parseColonKeys(output) {
+  const keys = [];
+  for (const line of output.split('\n')) {
+    const fields = line.split(':');
+    if (fields[0] === 'pub') {
+      keys.push({
+        id: fields[4],
+        validity: VALIDITY[fields[1]] || fields[1] || 'unknown',
+        created: fields[5] ? new Date(Number(fields[5]) * 1000).toISOString() : null,
+        expires: fields[6] ? new Date(Number(fields[6]) * 1000).toISOString() : null,
+        fingerprint: null,
+        uids: []
+      });
+    } else if (fields[0] === 'fpr' && keys.length > 0 && !keys[keys.length - 1].fingerprint) {
+      keys[keys.length - 1].fingerprint = fields[9];
+    } else if (fields[0] === 'uid' && keys.length > 0) {
+      keys[keys.length - 1].uids.push(fields[9]);
+    }
+  }
+  return keys;
+}
+
+// pacman's keyring: setup, refresh, local signatures, and working out why a package
+// signature was rejected
+export class PacmanKeyring {
+  constructor(executor, logger) {
+    this.executor = executor;
+    this.logger = logger;
+  }
+
+  async status(target) {
+    const dir = targetPath(target, KEYRING_DIR);
+    const initialized = await fs.pathExists(`${dir}/pubring.gpg`) || await fs.pathExists(`${dir}/pubring.kbx`);
+    const installed = await this.executor.execute('pacman', ['-Q', 'archlinux-keyring'], { target });
+    const available = await this.executor.execute('pacman', ['-Si', 'archlinux-keyring'], { target });
+
+    return {
+      directory: dir,
+      initialized,
+      trustdb: await fs.pathExists(`${dir}/trustdb.gpg`),
+      installedVersion: installed.success ? installed.stdout.trim().split(/\s+/)[1] : null,
+      availableVersion: available.success ? available.stdout.match(/^Version\s*:\s*(\S+)/m)?.[1] || null : null
+    };
+  }
+
+  // --init creates the local master key; --populate signs the packager keys shipped in
+  // archlinux-keyring with it
+  async init(target) {
+    await this.run(['--init'], target);
+    await this.run(['--populate', 'archlinux'], target);
+    return this.status(target);
+  }
+
+  // Newer archlinux-keyring first (it carries new packagers and revocations); refreshing
+  // every key from WKD/keyservers is slow and only needed when that is not enough
+  async refresh(target, { full = false } = {}) {
+    const steps = [];
+    const sync = await this.executor.executeWithSudo('pacman', ['-Sy', '--needed', '--noconfirm', 'archlinux-keyring'], { target, timeout: 600000 });
+    if (!sync.success) {
+      throw new Error(`Updating archlinux-keyring failed: ${sync.stderr.trim()}`);
+    }
+    steps.push('archlinux-keyring updated');
+
+    await this.run(['--populate', 'archlinux'], target);
+    steps.push('populated');
+
+    if (full) {
+      await this.run(['--refresh-keys'], target, { timeout: 1800000 });
+      steps.push('refreshed all keys');
+    }
+    return { steps, ...(await this.status(target)) };
+  }
+
+  // Trusts a key outside archlinux-keyring, e.g. for an unofficial repository
+  async sign(keyId, target, { keyserver = null } = {}) {
+    await this.run(['--recv-keys', keyId, ...(keyserver ? ['--keyserver', keyserver] : [])], target, { timeout: 120000 });
+    await this.run(['--lsign-key', keyId], target);
+    return (await this.lookupKeys([keyId], target))[0] || { id: keyId };
+  }
+
+  async lookupKeys(ids, target) {
+    if (ids.length === 0) {
+      return [];
+    }
+    const result = await this.executor.executeWithSudo('gpg', [
+      '--homedir', targetPath(target, KEYRING_DIR), '--batch', '--with-colons', '--list-keys', ...ids
+    ], { retry: false });
+    return parseColonKeys(result.stdout || '');
+  }
+
+  // Local clock against the Date header of archlinux.org, in seconds (positive: ahead)
+  async clockSkew() {
+    try {
+      const response = await fetch(TIME_URL, { method: 'HEAD', signal: AbortSignal.timeout(10000) });
+      const remote = Date.parse(response.headers.get('date'));
+      if (Number.isNaN(remote)) {
+        return { checked: false, error: 'No Date header' };
+      }
+      return { checked: true, seconds: Math.round((Date.now() - remote) / 1000), reference: TIME_URL };
+    } catch (error) {
+      return { checked: false, error: error.cause?.code || error.message };
+    }
+  }
+
+  async diagnose(target, { error = '' } = {}) {
+    const parsed = parseSignatureErrors(error);
+    const report = {
+      keyring: await this.status(target),
+      clock: await this.clockSkew(),
+      error: error ? parsed : null,
+      keys: []
+    };
+
+    const ntp = await this.executor.execute('timedatectl', ['show', '--property=NTPSynchronized', '--value']);
+    report.clock.ntpSynchronized = ntp.success ? ntp.stdout.trim() === 'yes' : null;
+
+    if (report.keyring.initialized) {
+      const found = await this.lookupKeys([...parsed.keys, ...parsed.signers.map(signer => signer.match(/<([^>]+)>/)?.[1]).filter(Boolean)], target);
+      report.keys = found;
+      report.missingKeys = parsed.keys.filter(id => !found.some(key => key.fingerprint?.endsWith(id) || key.id === id));
+    }
+
+    report.suspectedCauses = this.suspectCauses(report);
+    report.healthy = report.suspectedCauses.length === 0;
+    return report;
+  }
+
+  suspectCauses(report) {
+    const causes = [];
+    const { keyring, clock, error } = report;
+
+    if (!keyring.initialized) {
+      causes.push(`The keyring in ${keyring.directory} is not initialized; run pacman_keyring with action init`);
+      return causes;
+    }
+
+    if (clock.checked && Math.abs(clock.seconds) > MAX_CLOCK_SKEW_SECONDS) {
+      causes.push(`The clock is ${Math.abs(clock.seconds)}s ${clock.seconds > 0 ? 'ahead' : 'behind'}${clock.ntpSynchronized === false ? ' and not NTP-synchronized (timedatectl set-ntp true)' : ''}; keys and signatures look ${clock.seconds > 0 ? 'expired' : 'not yet valid'}`);
+    } else if (error?.future) {
+      causes.push('gpg reports keys or signatures from the future; check the clock and time zone');
+    }
+
+    if (!keyring.installedVersion) {
+      causes.push('archlinux-keyring is not installed');
+    } else if (keyring.availableVersion && keyring.availableVersion !== keyring.installedVersion) {
+      causes.push(`archlinux-keyring ${keyring.installedVersion} is older than ${keyring.availableVersion}; run pacman_keyring with action refresh`);
+    }
+
+    for (const key of report.keys) {
+      if (['expired', 'revoked', 'disabled'].includes(key.validity)) {
+        causes.push(`Key ${key.id} (${key.uids[0] || 'no uid'}) is ${key.validity}${key.validity === 'expired' ? '; a refresh may bring an extended expiry' : ''}`);
+      } else if (['unknown', 'untrusted', 'marginal'].includes(key.validity)) {
+        causes.push(`Key ${key.id} (${key.uids[0] || 'no uid'}) has ${key.validity} trust; refresh the keyring, or sign it locally if it is not an Arch packager key`);
+      }
+    }
+    for (const id of report.missingKeys || []) {
+      causes.push(`Key ${id} is not in the keyring; refresh archlinux-keyring (official packages) or import and sign it (third-party repositories)`);
+    }
+
+    if (error?.invalidSignature && !error.unknownTrust && !error.unknownKey && causes.length === 0) {
+      causes.push(`The signature does not match the package, usually a corrupt or truncated download; delete ${error.packages.length > 0 ? error.packages.join(', ') : 'the package'} from /var/cache/pacman/pkg and retry`);
+    }
+    if (error?.unknownTrust && causes.length === 0) {
+      causes.push('The signer is not trusted by the local keyring; run pacman_keyring with action refresh');
+    }
+
+    return causes;
+  }
+
+  async run(args, target, options = {}) {
+    const result = await this.executor.executeWithSudo('pacman-key', args, { target, ...options });
+    if (!result.success) {
+      throw new Error(`pacman-key ${args.join(' ')} failed: ${result.stderr.trim()}`);
+    }
+    return result;
+  }
+}
@@ -0,0 +1,138 @@
+import fs from 'fs-extra';
+import path from 'path';
+import { readLines, getIniValues, setIniValues } from './user-files.js';
+import { UDEV_RULES_DIR, RULE_PREFIX } from './udev-rules.js';
+
+const POWER_SUPPLY_DIR = '/sys/class/power_supply';
+export const LOGIND_DROP_IN = '/etc/systemd/logind.conf.d/90-mcp-arch-linux.conf';
+export const CHARGE_RULE_FILE = path.join(UDEV_RULES_DIR, `${RULE_PREFIX}charge-thresholds.rules`);
+const BATTERY_NAME = /^[A-Za-z0-9_-]+$/;
+
+// The two supported backends; both drive CPU and platform power settings, so only one may run
+export const POWER_BACKENDS = {
+  tlp: {
+    package: 'tlp',
+    service: 'tlp.service',
+    // TLP switches radios itself and asks for systemd's rfkill state saving to be masked
+    mask: ['systemd-rfkill.service', 'systemd-rfkill.socket']
+  },
+  'power-profiles-daemon': {
+    package: 'power-profiles-daemon',
+    service: 'power-profiles-daemon.service',
+    mask: []
+  }
+};
+
+// Other tuning daemons that fight with either backend
+export const CONFLICTING_SERVICES = ['auto-cpufreq.service', 'tuned.service', 'tuned-ppd.service', 'laptop-mode.service'];
+
+export const LID_ACTIONS = ['ignore', 'poweroff', 'reboot', 'halt', 'suspend', 'hibernate', 'hybrid-sleep', 'suspend-then-hibernate', 'lock'];
+
+// Tool parameter -> logind.conf [Login] key
+export const LOGIND_KEYS = {
+  lidSwitch: 'HandleLidSwitch',
+  lidSwitchExternalPower: 'HandleLidSwitchExternalPower',
+  lidSwitchDocked: 'HandleLidSwitchDocked',
+  powerKey: 'HandlePowerKey',
+  suspendKey: 'HandleSuspendKey',
+  idleAction: 'IdleAction',
+  idleActionSec: 'IdleActionSec'
+};
+
+async function readAttribute(dir, name) {
+  const value = await fs.readFile(path.join(dir, name), 'utf8').catch(() => null);
+  return value === null ? null : value.trim();
+}
+
+// Batteries from sysfs; thresholds are supported when the kernel exposes the generic
+// charge_control_* attributes (ThinkPad, ASUS, Dell, Framework, Huawei, ...)
+export async function listBatteries() {
+  const batteries = [];
+  for (const name of (await fs.readdir(POWER_SUPPLY_DIR).catch(() => [])).sort()) {
+    const dir = path.join(POWER_SUPPLY_DIR, name);
+    if (await readAttribute(dir, 'type') !== 'Battery') {
+      continue;
+    }
+    const start = await readAttribute(dir, 'charge_control_start_threshold');
+    const end = await readAttribute(dir, 'charge_control_end_threshold');
+    batteries.push({
+      name,
+      capacity: parseInt(await readAttribute(dir, 'capacity'), 10) || null,
+      status: await readAttribute(dir, 'status'),
+      thresholds: {
+        supported: end !== null,
+        start: start === null ? null : parseInt(start, 10),
+        end: end === null ? null : parseInt(end, 10)
+      },
+      dir
+    });
+  }
+  return batteries;
+}
+
+export function checkBatteryName(name) {
+  if (!BATTERY_NAME.test(name)) {
+    throw new Error(`Invalid battery name: ${name}`);
+  }
+  return name;
+}
+
+// Writes the attributes in an order the firmware accepts: start may never exceed end
+export async function writeThresholds(battery, { start, end }) {
+  const lowersEndBelowStart = end !== undefined && battery.thresholds.start !== null && end <= battery.thresholds.start;
+  if (lowersEndBelowStart && start === undefined) {
+    throw new Error(`end ${end} must be above the current start threshold ${battery.thresholds.start}`);
+  }
+
+  const writes = [];
+  if (end !== undefined) writes.push(['charge_control_end_threshold', end]);
+  if (start !== undefined) writes.push(['charge_control_start_threshold', start]);
+  if (lowersEndBelowStart) {
+    writes.reverse();
+  }
+  for (const [attribute, value] of writes) {
+    await fs.writeFile(path.join(battery.dir, attribute), `${value}\n`);
+  }
+}
+
+// One udev line per battery, re-applied whenever the battery (re)appears
+export function renderChargeRule(content, name, { start, end }) {
+  const lines = (content || '').split('\n').filter(line => line && !line.startsWith('#') && !line.includes(`KERNEL=="${name}"`));
+  const assignments = [
+    start !== undefined ? `ATTR{charge_control_start_threshold}="${start}"` : null,
+    end !== undefined ? `ATTR{charge_control_end_threshold}="${end}"` : null
+  ].filter(Boolean);
+  lines.push(['SUBSYSTEM=="power_supply"', `KERNEL=="${name}"`, ...assignments].join(', '));
+  return `# Managed by mcp-arch-linux (charge thresholds)\n${lines.join('\n')}\n`;
+}
+
+// Effective [Login] settings: logind.conf, then drop-ins in lexical order
+export async function readLogindSettings() {
+  const files = ['/etc/systemd/logind.conf'];
+  const dropIns = [];
+  for (const dir of ['/usr/lib/systemd/logind.conf.d', '/etc/systemd/logind.conf.d']) {
+    for (const name of await fs.readdir(dir).catch(() => [])) {
+      if (name.endsWith('.conf')) {
+        dropIns.push({ name, file: path.join(dir, name) });
+      }
+    }
+  }
+  // A file in /etc shadows one with the same name in /usr/lib
+  const byName = new Map(dropIns.map(dropIn => [dropIn.name, dropIn.file]));
+  files.push(...Array.from(byName.keys()).sort().map(name => byName.get(name)));
+
+  const settings = {};
+  for (const file of files) {
+    for (const [key, value] of Object.entries(getIniValues(await readLines(file), 'Login'))) {
+      settings[key] = { value, file };
+    }
+  }
+  return settings;
+}
+
+export async function renderLogindDropIn(values) {
+  const before = await fs.readFile(LOGIND_DROP_IN, 'utf8').catch(() => null);
+  const lines = before === null ? ['# Managed by mcp-arch-linux'] : before.split('\n');
+  const after = setIniValues(lines, 'Login', values).join('\n');
+  return { file: LOGIND_DROP_IN, before, after };
+}
@@ -0,0 +1,201 @@
+import fs from 'fs-extra';
+import path from 'path';
+
+// Categories locale.conf may set besides LANG; LC_ALL is for overriding at runtime only
+export const LC_VARIABLES = [
+  'LC_CTYPE', 'LC_NUMERIC', 'LC_TIME', 'LC_COLLATE', 'LC_MONETARY', 'LC_MESSAGES', 'LC_PAPER',
+  'LC_NAME', 'LC_ADDRESS', 'LC_TELEPHONE', 'LC_MEASUREMENT', 'LC_IDENTIFICATION'
+];
+
+const KBD_DIR = 'usr/share/kbd';
+const VALUE = /^[A-Za-z0-9_.@+:,-]+$/;
+
+function checkValue(name, value) {
+  if (!VALUE.test(value)) {
+    throw new Error(`Invalid ${name}: ${value}`);
+  }
+  return value;
+}
+
+// Uncomments each locale's line in locale.gen (it carries the right charset), or appends
+// "<locale> UTF-8" for a .UTF-8 locale the file does not list; other lines stay as they are
+export function enableLocales(content, locales) {
+  const lines = content.split('\n');
+  for (const locale of locales) {
+    checkValue('locale', locale);
+    const escaped = locale.replace(/[.+]/g, '\\$&');
+    if (lines.some(line => new RegExp(`^\\s*${escaped}\\s`).test(line))) {
+      continue;
+    }
+    const commented = lines.findIndex(line => new RegExp(`^#\\s*${escaped}\\s+\\S+\\s*$`).test(line));
+    if (commented !== -1) {
+      lines[commented] = lines[commented].replace(/^#\s*/, '');
+    } else if (/\.UTF-8$/i.test(locale)) {
+      while (lines.length > 0 && lines[lines.length - 1] === '') lines.pop();
+      lines.push(`${locale} UTF-8`, '');
+    } else {
+      throw new Error(`${locale} is not in locale.gen and its charset is unknown; use the UTF-8 variant`);
+    }
+  }
+  return lines.join('\n');
+}
+
+export function renderLocaleConf(lang, overrides = {}) {
+  const lines = [`LANG=${checkValue('locale', lang)}`];
+  for (const [name, value] of Object.entries(overrides)) {
+    if (!LC_VARIABLES.includes(name)) {
+      throw new Error(`Unsupported locale.conf variable: ${name} (supported: ${LC_VARIABLES.join(', ')})`);
+    }
+    lines.push(`${name}=${checkValue(name, value)}`);
+  }
+  return `${lines.join('\n')}\n`;
+}
+
+export function renderVconsole({ keymap, font = null, fontMap = null }) {
+  return [
+    `KEYMAP=${checkValue('keymap', keymap)}`,
+    ...(font ? [`FONT=${checkValue('font', font)}`] : []),
+    ...(fontMap ? [`FONT_MAP=${checkValue('font map', fontMap)}`] : [])
+  ].join('\n') + '\n';
+}
+
+// The file localectl set-x11-keymap writes: Xorg reads it, and systemd-localed hands
+// it to GNOME, KDE and display managers, on Wayland too
+export function renderX11Keyboard({ layout, model = null, variant = null, options = null }) {
+  const entries = [['XkbLayout', layout], ['XkbModel', model], ['XkbVariant', variant], ['XkbOptions', options]]
+    .filter(([, value]) => value)
+    .map(([name, value]) => `        Option "${name}" "${checkValue(name, value)}"`);
+  return [
+    '# Written by arch_configure_system, in the format of localectl set-x11-keymap',
+    'Section "InputClass"',
+    '        Identifier "system-keyboard"',
+    '        MatchIsKeyboard "on"',
+    ...entries,
+    'EndSection',
+    ''
+  ].join('\n');
+}
+
+// The same keyboard as XKB_DEFAULT_* in /etc/environment, which libxkbcommon falls back to
+// in compositors that do not ask localed (sway and other wlroots ones)
+export function setXkbEnvironment(content, { layout, model = null, variant = null, options = null }) {
+  const values = { XKB_DEFAULT_LAYOUT: layout, XKB_DEFAULT_MODEL: model, XKB_DEFAULT_VARIANT: variant, XKB_DEFAULT_OPTIONS: options };
+  const lines = content.split('\n').filter(line => !/^\s*XKB_DEFAULT_[A-Z]+=/.test(line));
+  while (lines.length > 0 && lines[lines.length - 1] === '') lines.pop();
+  for (const [name, value] of Object.entries(values)) {
+    if (value) lines.push(`${name}=${checkValue(name, value)}`);
+  }
+  return `${lines.join('\n')}\n`;
+}
+
+// A console keymap or font in the target's kbd data, searched recursively since keymaps
+// sit in per-architecture and per-layout subdirectories
+export async function findKbdFile(root, kind, name) {
+  const extensions = kind === 'keymaps' ? ['.map.gz', '.map'] : ['.psfu.gz', '.psf.gz', '.psfu', '.psf', '.gz', ''];
+  const search = async (dir) => {
+    const entries = await fs.readdir(dir, { withFileTypes: true }).catch(() => []);
+    for (const entry of entries) {
+      if (entry.isFile() && extensions.some(extension => entry.name === `${name}${extension}`)) {
+        return path.join(dir, entry.name);
+      }
+    }
+    for (const entry of entries.filter(candidate => candidate.isDirectory())) {
+      const found = await search(path.join(dir, entry.name));
+      if (found) return found;
+    }
+    return null;
+  };
+  return search(path.join(root, KBD_DIR, kind));
+}
+
+const ZONEINFO_DIR = 'usr/share/zoneinfo';
+const SUPPORTED_FILE = 'usr/share/i18n/SUPPORTED';
+
+// Zone names as tzdata installs them: every path component capitalized, which leaves out
+// posix/ and right/, the .tab and .zi tables, leapseconds and posixrules
+export async function listTimezones(root = '/') {
+  const base = path.join(root, ZONEINFO_DIR);
+  if (!await fs.pathExists(base)) {
+    return null;
+  }
+  const zones = [];
+  const walk = async (dir, prefix) => {
+    for (const entry of await fs.readdir(dir, { withFileTypes: true })) {
+      if (!/^[A-Z][A-Za-z0-9_+-]*$/.test(entry.name) || entry.name === 'SECURITY') {
+        continue;
+      }
+      const name = prefix ? `${prefix}/${entry.name}` : entry.name;
+      if (entry.isDirectory()) {
+        await walk(path.join(dir, entry.name), name);
+      } else {
+        zones.push(name);
+      }
+    }
+  };
+  await walk(base, '');
+  return zones.sort();
+}
+
+// Locales glibc can generate, from the SUPPORTED list locale.gen is built from
+export async function listSupportedLocales(root = '/') {
+  const content = await fs.readFile(path.join(root, SUPPORTED_FILE), 'utf8').catch(() => null);
+  if (content === null) {
+    return null;
+  }
+  const locales = content.split('\n')
+    .map(line => line.trim().split(/\s+/)[0])
+    .filter(name => name && !name.startsWith('#') && name !== 'SUPPORTED-LOCALES=\\');
+  return ['C', 'POSIX', ...locales.map(name => name.replace(/\/.*$/, ''))];
+}
+
+function distance(a, b) {
+  let previous = Array.from({ length: b.length + 1 }, (_, index) => index);
+  for (let i = 1; i <= a.length; i++) {
+    const current = [i];
+    for (let j = 1; j <= b.length; j++) {
+      current[j] = Math.min(previous[j] + 1, current[j - 1] + 1, previous[j - 1] + (a[i - 1] === b[j - 1] ? 0 : 1));
+    }
+    previous = current;
+  }
+  return previous[b.length];
+}
+
+// Candidates close to a mistyped value: the same name in other case or spelling of the
+// charset first ("en_us.utf8" for en_US.UTF-8), then those a few edits away
+export function closestMatches(value, candidates, { limit = 3 } = {}) {
+  const normalize = name => name.toLowerCase().replace(/utf-?8/, 'utf8');
+  const same = candidates.filter(candidate => normalize(candidate) === normalize(value));
+  if (same.length > 0) {
+    return same.slice(0, limit);
+  }
+  const lower = value.toLowerCase();
+  const threshold = Math.max(2, Math.floor(value.length / 4));
+  return candidates
+    .map(candidate => ({ candidate, score: distance(lower, candidate.toLowerCase()) }))
+    .filter(({ score }) => score <= threshold)
+    .sort((a, b) => a.score - b.score || a.candidate.localeCompare(b.candidate))
+    .slice(0, limit)
+    .map(({ candidate }) => candidate);
+}
+
+// Problems with a timezone and locales against the databases under root; a database that
+// is not there (no tzdata or glibc yet) is not checked
+export async function checkLocaleSettings(root, { timezone = null, locales = [] }) {
+  const problems = [];
+  const describe = (kind, value, candidates) => {
+    const similar = closestMatches(value, candidates);
+    return `Unknown ${kind} ${value}${similar.length > 0 ? ` (did you mean ${similar.join(', ')}?)` : ''}`;
+  };
+
+  const zones = timezone ? await listTimezones(root) : null;
+  if (zones && !zones.includes(timezone)) {
+    problems.push(describe('timezone', timezone, zones));
+  }
+  const supported = locales.length > 0 ? await listSupportedLocales(root) : null;
+  for (const locale of supported ? [...new Set(locales)] : []) {
+    if (!supported.includes(locale)) {
+      problems.push(describe('locale', locale, supported));
+    }
+  }
+  return problems;
+}
@@ -0,0 +1,87 @@
+import fs from 'fs-extra';
+import path from 'path';
+
+const COMPRESSORS = {
+  zstd: { command: 'zstd', args: ['-T0', '-q', '-f', '--rm'], extension: '.zst' },
+  xz: { command: 'xz', args: ['-T0', '-f'], extension: '.xz' },
+  gzip: { command: 'gzip', args: ['-f'], extension: '.gz' }
+};
+
+// Partitions of loop, NVMe and MMC devices carry a "p" before the number
+export function partitionPath(device, number) {
+  return /\d$/.test(device) ? `${device}p${number}` : `${device}${number}`;
+}
+
+// Disk image files attached as loop devices, so the install pipeline can partition,
+// format and pacstrap into a file instead of a real disk
+export class LoopImage {
+  constructor(commandExecutor, logger) {
+    this.commandExecutor = commandExecutor;
+    this.logger = logger;
+  }
+
+  // Sparse file: only blocks that are written take space
+  async create(file, size) {
+    await fs.ensureDir(path.dirname(file));
+    const result = await this.commandExecutor.execute('truncate', ['-s', size, file]);
+    if (!result.success) {
+      throw new Error(`Failed to create ${file}: ${result.stderr}`);
+    }
+    this.logger.info(`Created disk image ${file} (${size})`);
+  }
+
+  async attach(file) {
+    const existing = await this.find(file);
+    if (existing) {
+      return existing;
+    }
+
+    // --partscan makes the kernel create loopNpM nodes after partitioning
+    const result = await this.commandExecutor.executeWithSudo('losetup', ['--find', '--show', '--partscan', file]);
+    const device = result.stdout.trim();
+    if (!result.success || !device.startsWith('/dev/loop')) {
+      throw new Error(`Failed to attach ${file}: ${result.stderr || result.stdout}`);
+    }
+    this.logger.info(`Attached ${file} as ${device}`);
+    return device;
+  }
+
+  async find(file) {
+    const result = await this.commandExecutor.execute('losetup', ['--noheadings', '--output', 'NAME', '--associated', path.resolve(file)]);
+    return result.success ? result.stdout.split('\n')[0].trim() || null : null;
+  }
+
+  async detach(device) {
+    const result = await this.commandExecutor.executeWithSudo('losetup', ['--detach', device], { retry: false });
+    if (!result.success) {
+      throw new Error(`Failed to detach ${device}: ${result.stderr}`);
+    }
+    this.logger.info(`Detached ${device}`);
+  }
+
+  // qcow2 only stores written clusters, so a mostly empty image shrinks considerably
+  async convert(file, output, format) {
+    const result = await this.commandExecutor.execute('qemu-img', ['convert', '-f', 'raw', '-O', format, file, output], {
+      timeout: 1800000
+    });
+    if (!result.success) {
+      throw new Error(`Failed to convert ${file} to ${format}: ${result.stderr}`);
+    }
+  }
+
+  // Replaces the file with its compressed form and returns the new path
+  async compress(file, method) {
+    const compressor = COMPRESSORS[method];
+    if (!compressor) {
+      throw new Error(`Unknown compression: ${method}`);
+    }
+
+    const result = await this.commandExecutor.execute(compressor.command, [...compressor.args, file], {
+      timeout: 3600000
+    });
+    if (!result.success) {
+      throw new Error(`${compressor.command} failed for ${file}: ${result.stderr}`);
+    }
+    return `${file}${compressor.extension}`;
+  }
+}
@@ -0,0 +1,20 @@
+// The HOOKS array of mkinitcpio.conf; order matters, since each hook runs after the ones
+// before it in the initramfs
+export function parseHooks(content) {
+  const match = content.match(/^HOOKS=\((.*)\)/m);
+  return match ? match[1].split(/\s+/).filter(Boolean) : [];
+}
+
+// Inserts a hook in front of another (usually filesystems, so it runs before the root
+// filesystem is mounted); content is returned unchanged when the hook is already there
+export function addHook(content, hook, { before = 'filesystems' } = {}) {
+  return content.replace(/^HOOKS=\((.*)\)/m, (line, value) => {
+    const hooks = value.split(/\s+/).filter(Boolean);
+    if (hooks.includes(hook)) {
+      return line;
+    }
+    const index = hooks.indexOf(before);
+    hooks.splice(index === -1 ? hooks.length : index, 0, hook);
+    return `HOOKS=(${hooks.join(' ')})`;
+  });
+}
@@ -0,0 +1,252 @@
+import dns from 'dns';
+import fs from 'fs-extra';
+import path from 'path';
+
+export const PORTAL_CHECK_URL = 'http://ping.archlinux.org/nm-check.txt';
+export const PORTAL_CHECK_EXPECT = 'NetworkManager is online';
+
+const WEAK_SIGNAL_DBM = -75;
+const HIGH_LOSS_PERCENT = 10;
+
+const readValue = async (file) => {
+  const text = await fs.readFile(file, 'utf8').catch(() => null);
+  return text === null ? null : text.trim();
+};
+
+// Layered checks (link, route, gateway, internet, DNS, portal) so the first failing
+// layer points at the cause
+export class NetworkDiagnostics {
+  constructor(commandExecutor, options = {}) {
+    this.commandExecutor = commandExecutor;
+    this.targets = options.targets || ['1.1.1.1', '9.9.9.9'];
+    this.dnsNames = options.dnsNames || ['archlinux.org'];
+    this.portalUrl = options.portalUrl || PORTAL_CHECK_URL;
+    this.portalExpect = options.portalExpect || PORTAL_CHECK_EXPECT;
+  }
+
+  async interfaces() {
+    const base = '/sys/class/net';
+    const result = [];
+
+    for (const name of (await fs.readdir(base).catch(() => [])).sort()) {
+      if (name === 'lo') continue;
+      const dir = path.join(base, name);
+      const driver = await fs.readlink(path.join(dir, 'device', 'driver', 'module')).catch(() => null)
+        || await fs.readlink(path.join(dir, 'device', 'driver')).catch(() => null);
+
+      result.push({
+        name,
+        state: await readValue(path.join(dir, 'operstate')),
+        carrier: (await readValue(path.join(dir, 'carrier'))) === '1',
+        wireless: await fs.pathExists(path.join(dir, 'wireless')) || await fs.pathExists(path.join(dir, 'phy80211')),
+        mac: await readValue(path.join(dir, 'address')),
+        driver: driver ? path.basename(driver) : null
+      });
+    }
+
+    return result;
+  }
+
+  async rfkill() {
+    const base = '/sys/class/rfkill';
+    const result = [];
+    for (const entry of await fs.readdir(base).catch(() => [])) {
+      const dir = path.join(base, entry);
+      result.push({
+        name: await readValue(path.join(dir, 'name')),
+        type: await readValue(path.join(dir, 'type')),
+        softBlocked: (await readValue(path.join(dir, 'soft'))) === '1',
+        hardBlocked: (await readValue(path.join(dir, 'hard'))) === '1'
+      });
+    }
+    return result;
+  }
+
+  async addresses() {
+    const result = await this.commandExecutor.execute('ip', ['-j', 'address', 'show']);
+    if (!result.success) return [];
+    return JSON.parse(result.stdout || '[]')
+      .filter(link => link.ifname !== 'lo')
+      .map(link => ({
+        interface: link.ifname,
+        addresses: (link.addr_info || []).map(addr => `${addr.local}/${addr.prefixlen}`)
+      }));
+  }
+
+  async defaultRoute() {
+    const result = await this.commandExecutor.execute('ip', ['-j', 'route', 'show', 'default']);
+    if (!result.success) return null;
+    const [route] = JSON.parse(result.stdout || '[]');
+    return route ? { gateway: route.gateway || null, interface: route.dev, metric: route.metric ?? null } : null;
+  }
+
+  async nameservers() {
+    const resolvConf = await fs.readFile('/etc/resolv.conf', 'utf8').catch(() => '');
+    return [...resolvConf.matchAll(/^\s*nameserver\s+(\S+)/gm)].map(([, server]) => server);
+  }
+
+  // iw reports what the driver negotiated; /proc/net/wireless covers drivers without nl80211
+  async wifiLink(iface) {
+    const link = { interface: iface, connected: false };
+
+    const iw = await this.commandExecutor.execute('iw', ['dev', iface, 'link']).catch(() => null);
+    if (iw?.success && !/Not connected/.test(iw.stdout)) {
+      const field = (pattern) => iw.stdout.match(pattern)?.[1]?.trim() ?? null;
+      Object.assign(link, {
+        connected: true,
+        ssid: field(/SSID:\s*(.+)/),
+        bssid: field(/Connected to ([0-9a-f:]{17})/i),
+        frequency: Number(field(/freq:\s*([\d.]+)/)) || null,
+        signal: Number(field(/signal:\s*(-?\d+)/)) || null,
+        txBitrate: field(/tx bitrate:\s*(.+)/),
+        rxBitrate: field(/rx bitrate:\s*(.+)/)
+      });
+    }
+
+    const wireless = await fs.readFile('/proc/net/wireless', 'utf8').catch(() => '');
+    const row = wireless.split('\n').find(line => line.trim().startsWith(`${iface}:`));
+    if (row) {
+      const [, , quality, level] = row.trim().split(/\s+/);
+      link.quality = parseFloat(quality);
+      if (!link.signal) link.signal = parseFloat(level);
+    }
+
+    return link;
+  }
+
+  async ping(target, { count = 4 } = {}) {
+    const result = await this.commandExecutor.execute('ping', ['-n', '-q', '-c', String(count), '-W', '2', target], {
+      timeout: (count + 5) * 2000
+    }).catch(error => ({ success: false, stdout: '', stderr: error.message }));
+
+    const loss = result.stdout.match(/([\d.]+)% packet loss/);
+    const rtt = result.stdout.match(/= ([\d.]+)\/([\d.]+)\/([\d.]+)/);
+    return {
+      target,
+      reachable: result.success,
+      loss: loss ? parseFloat(loss[1]) : 100,
+      rtt: rtt ? { min: parseFloat(rtt[1]), avg: parseFloat(rtt[2]), max: parseFloat(rtt[3]) } : null,
+      ...(result.success || loss ? {} : { error: result.stderr.trim() })
+    };
+  }
+
+  // Per-hop loss shows where along the path packets disappear
+  async trace(target) {
+    const result = await this.commandExecutor.execute('mtr', ['-n', '-j', '-c', '5', target], { timeout: 60000 });
+    if (!result.success) {
+      throw new Error(`mtr failed: ${result.stderr}`);
+    }
+    const hubs = JSON.parse(result.stdout).report?.hubs || [];
+    return hubs.map(hub => ({ hop: hub.count, host: hub.host, loss: hub['Loss%'], avg: hub.Avg }));
+  }
+
+  async resolve(name) {
+    const start = Date.now();
+    try {
+      const addresses = await dns.promises.lookup(name, { all: true });
+      return { name, resolved: true, addresses: addresses.map(entry => entry.address), ms: Date.now() - start };
+    } catch (error) {
+      return { name, resolved: false, error: error.code || error.message, ms: Date.now() - start };
+    }
+  }
+
+  // A portal answers the plain-HTTP probe with a redirect or its own login page
+  async captivePortal() {
+    try {
+      const response = await fetch(this.portalUrl, { redirect: 'manual', signal: AbortSignal.timeout(5000) });
+      const body = response.status === 200 ? (await response.text()).trim() : '';
+      const detected = response.status !== 200 || !body.startsWith(this.portalExpect);
+      return {
+        detected,
+        status: response.status,
+        ...(detected && response.headers.get('location') ? { redirect: response.headers.get('location') } : {})
+      };
+    } catch (error) {
+      return { detected: false, error: error.cause?.code || error.message };
+    }
+  }
+
+  async diagnose({ targets = this.targets, dnsNames = this.dnsNames, trace = false } = {}) {
+    const report = {
+      interfaces: await this.interfaces(),
+      rfkill: (await this.rfkill()).filter(entry => entry.type === 'wlan'),
+      addresses: await this.addresses().catch(() => []),
+      route: await this.defaultRoute().catch(() => null),
+      nameservers: await this.nameservers()
+    };
+
+    const wireless = report.interfaces.filter(iface => iface.wireless);
+    report.wifi = await Promise.all(wireless.map(iface => this.wifiLink(iface.name)));
+
+    report.gateway = report.route?.gateway ? await this.ping(report.route.gateway) : null;
+    report.internet = await Promise.all(targets.map(target => this.ping(target)));
+    report.dns = await Promise.all(dnsNames.map(name => this.resolve(name)));
+    report.captivePortal = report.internet.some(result => result.reachable) ? await this.captivePortal() : null;
+
+    if (trace && targets.length > 0) {
+      report.trace = await this.trace(targets[0]).catch(error => ({ error: error.message }));
+    }
+
+    report.suspectedCauses = this.suspectCauses(report);
+    report.healthy = report.suspectedCauses.length === 0;
+    return report;
+  }
+
+  suspectCauses(report) {
+    const causes = [];
+    const up = report.interfaces.filter(iface => iface.carrier && iface.state !== 'down');
+
+    if (report.rfkill.some(entry => entry.hardBlocked)) {
+      causes.push('Wireless is hard-blocked (hardware switch or BIOS setting)');
+    } else if (report.rfkill.some(entry => entry.softBlocked)) {
+      causes.push('Wireless is soft-blocked; run: rfkill unblock wlan');
+    }
+
+    if (up.length === 0) {
+      causes.push(report.interfaces.length === 0
+        ? 'No network interfaces found (missing driver or firmware?)'
+        : 'No interface has a link (cable unplugged or Wi-Fi not associated)');
+      return causes;
+    }
+
+    for (const iface of report.interfaces.filter(candidate => candidate.wireless && !candidate.driver)) {
+      causes.push(`${iface.name} has no driver bound (check firmware with dmesg)`);
+    }
+
+    for (const link of report.wifi.filter(candidate => candidate.connected && candidate.signal <= WEAK_SIGNAL_DBM)) {
+      causes.push(`Weak Wi-Fi signal on ${link.interface} (${link.signal} dBm)`);
+    }
+
+    if (!report.route) {
+      causes.push('No default route; DHCP may have failed or no network manager is running');
+      return causes;
+    }
+
+    if (report.gateway && !report.gateway.reachable) {
+      causes.push(`Gateway ${report.route.gateway} does not answer ping (link or router problem)`);
+    } else if (report.gateway?.loss >= HIGH_LOSS_PERCENT) {
+      causes.push(`${report.gateway.loss}% packet loss to the gateway (local link quality)`);
+    }
+
+    const reachable = report.internet.filter(result => result.reachable);
+    if (reachable.length === 0) {
+      causes.push(report.gateway?.reachable
+        ? 'Gateway is reachable but the internet is not (upstream/ISP outage or firewall)'
+        : 'Internet targets are unreachable');
+    } else if (reachable.some(result => result.loss >= HIGH_LOSS_PERCENT)) {
+      causes.push('High packet loss to internet targets');
+    }
+
+    if (reachable.length > 0 && report.dns.length > 0 && report.dns.every(result => !result.resolved)) {
+      causes.push(report.nameservers.length === 0
+        ? 'DNS fails: /etc/resolv.conf lists no nameservers'
+        : `DNS fails while IP connectivity works (nameservers: ${report.nameservers.join(', ')})`);
+    }
+
+    if (report.captivePortal?.detected) {
+      causes.push(`Captive portal detected${report.captivePortal.redirect ? ` (login at ${report.captivePortal.redirect})` : ''}`);
+    }
+
+    return causes;
+  }
+}
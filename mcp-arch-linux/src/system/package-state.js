@@ -0,0 +1,135 @@
+import fs from 'fs-extra';
+import path from 'path';
+
+const ARCHIVE_URL = 'https://archive.archlinux.org/packages';
+
+// Reads installed package state straight from the pacman local database
+export class PackageState {
+  constructor(executor, logger, options = {}) {
+    this.executor = executor;
+    this.logger = logger;
+    this.dbPath = options.dbPath || '/var/lib/pacman/local';
+    this.cacheDir = options.cacheDir || '/var/cache/pacman/pkg';
+    this.archiveUrl = options.archiveUrl || ARCHIVE_URL;
+  }
+
+  async capture() {
+    const entries = await fs.readdir(this.dbPath).catch(() => []);
+    const packages = [];
+
+    for (const entry of entries) {
+      const desc = await fs.readFile(path.join(this.dbPath, entry, 'desc'), 'utf8').catch(() => null);
+      if (desc) {
+        packages.push(this.parseDesc(desc));
+      }
+    }
+
+    return packages
+      .filter(pkg => pkg.name && pkg.version)
+      .sort((a, b) => a.name.localeCompare(b.name));
+  }
+
+  parseDesc(content) {
+    const fields = {};
+    let current = null;
+
+    for (const line of content.split('\n')) {
+      const header = line.match(/^%([A-Z0-9]+)%$/);
+      if (header) {
+        current = header[1];
+        fields[current] = [];
+      } else if (current && line.trim()) {
+        fields[current].push(line.trim());
+      }
+    }
+
+    return {
+      name: fields.NAME?.[0],
+      version: fields.VERSION?.[0],
+      arch: fields.ARCH?.[0] || 'x86_64',
+      // %REASON% 1 marks a dependency; explicit installs omit it
+      explicit: fields.REASON?.[0] !== '1'
+    };
+  }
+
+  // Compare a recorded package set with what is installed now
+  async planRestore(recorded) {
+    const current = new Map((await this.capture()).map(pkg => [pkg.name, pkg]));
+    const wanted = new Map(recorded.map(pkg => [pkg.name, pkg]));
+    const actions = [];
+
+    for (const pkg of recorded) {
+      const installed = current.get(pkg.name);
+      if (!installed) {
+        actions.push({ name: pkg.name, action: 'install', version: pkg.version, source: await this.locate(pkg) });
+      } else if (installed.version !== pkg.version) {
+        actions.push({
+          name: pkg.name,
+          action: 'change-version',
+          from: installed.version,
+          version: pkg.version,
+          source: await this.locate(pkg)
+        });
+      }
+    }
+
+    for (const pkg of current.values()) {
+      if (!wanted.has(pkg.name)) {
+        actions.push({ name: pkg.name, action: 'remove', from: pkg.version });
+      }
+    }
+
+    return actions;
+  }
+
+  // Prefer the local pacman cache, fall back to the Arch Linux Archive
+  async locate(pkg) {
+    const prefix = `${pkg.name}-${pkg.version}-`;
+    const cached = (await fs.readdir(this.cacheDir).catch(() => []))
+      .find(file => file.startsWith(prefix) && /\.pkg\.tar\.[a-z0-9]+$/.test(file) &&
+        !file.slice(prefix.length).includes('-'));
+
+    if (cached) {
+      return path.join(this.cacheDir, cached);
+    }
+
+    return `${this.archiveUrl}/${pkg.name[0]}/${pkg.name}/${pkg.name}-${pkg.version}-${pkg.arch}.pkg.tar.zst`;
+  }
+
+  async apply(actions, recorded) {
+    const installs = actions.filter(action => action.action !== 'remove');
+    const removals = actions.filter(action => action.action === 'remove');
+    const explicit = new Map(recorded.map(pkg => [pkg.name, pkg.explicit]));
+    const results = { installed: [], removed: [], errors: [] };
+
+    if (installs.length > 0) {
+      const result = await this.executor.executeWithSudo('pacman', [
+        '-U', '--noconfirm', '--needed', ...installs.map(action => action.source)
+      ]);
+
+      if (result.success) {
+        results.installed = installs.map(action => action.name);
+        const deps = installs.filter(action => explicit.get(action.name) === false).map(action => action.name);
+        if (deps.length > 0) {
+          await this.executor.executeWithSudo('pacman', ['-D', '--asdeps', ...deps]);
+        }
+      } else {
+        results.errors.push(`pacman -U failed: ${result.stderr}`);
+      }
+    }
+
+    if (removals.length > 0) {
+      const result = await this.executor.executeWithSudo('pacman', [
+        '-R', '--noconfirm', ...removals.map(action => action.name)
+      ]);
+
+      if (result.success) {
+        results.removed = removals.map(action => action.name);
+      } else {
+        results.errors.push(`pacman -R failed: ${result.stderr}`);
+      }
+    }
+
+    return results;
+  }
+}
@@ -0,0 +1,316 @@
+export const PACMAN_CONF = '/etc/pacman.conf';
+const MIRRORLIST_INCLUDE = 'Include = /etc/pacman.d/mirrorlist';
+
+// A pacman.conf for installing from local media, derived from the host's: repositories
+// served from a mirror copy (file://), extra cache directories searched before
+// downloading, or, fully offline, a single repository in place of all the others
+export function localInstallConf(content, { mirror = null, cacheDirs = [], offlineRepo = null } = {}) {
+  const output = [];
+  let section = null;
+  for (const line of content.split('\n')) {
+    const header = line.match(/^\s*\[([^\]]+)\]\s*$/);
+    if (header) {
+      section = header[1];
+      if (section !== 'options' && offlineRepo) {
+        continue;
+      }
+      output.push(line);
+      if (section !== 'options' && mirror) {
+        output.push(`Server = file://${mirror.replace(/\/$/, '')}/$repo/os/$arch`);
+      }
+      continue;
+    }
+    if (section !== null && section !== 'options') {
+      if (offlineRepo) {
+        continue;
+      }
+      if (mirror && /^\s*(Include|Server)\s*=/.test(line)) {
+        continue;
+      }
+    }
+    output.push(line);
+  }
+
+  // Extra caches go after the configured one (or the default), which stays first so
+  // downloads still land there and not on the local media
+  if (cacheDirs.length > 0) {
+    const start = output.findIndex(line => /^\s*\[options\]\s*$/.test(line));
+    let end = output.findIndex((line, index) => index > start && /^\s*\[[^\]]+\]\s*$/.test(line));
+    end = end === -1 ? output.length : end;
+    while (end > start + 1 && output[end - 1].trim() === '') end--;
+    const configured = output.slice(start + 1, end).some(line => /^\s*CacheDir\s*=/.test(line));
+    output.splice(end, 0, ...(configured ? [] : ['CacheDir = /var/cache/pacman/pkg/']),
+      ...cacheDirs.map(dir => `CacheDir = ${dir.replace(/\/?$/, '/')}`));
+  }
+
+  if (offlineRepo) {
+    while (output.length > 0 && output[output.length - 1] === '') output.pop();
+    output.push('', `[${offlineRepo.name}]`, `SigLevel = ${offlineRepo.sigLevel}`, `Server = file://${offlineRepo.dir}`, '');
+  }
+  return output.join('\n');
+}
+
+// Repository sections, including ones commented out as shipped in the default pacman.conf
+export function parseRepos(content) {
+  const repos = [];
+  content.split('\n').forEach((line, index) => {
+    const match = line.match(/^\s*(#\s*)?\[([^\]]+)\]\s*$/);
+    if (match && match[2] !== 'options') {
+      repos.push({ name: match[2], enabled: !match[1], line: index });
+    }
+  });
+  return repos;
+}
+
+// Uncomments "#[name]" and the option lines right below it up to the next blank line or
+// section, or appends the section with the mirrorlist when there is none at all
+export function enableRepo(content, name) {
+  const lines = content.split('\n');
+  const repo = parseRepos(content).find(candidate => candidate.name === name);
+  if (repo?.enabled) {
+    return content;
+  }
+
+  if (!repo) {
+    while (lines.length > 0 && lines[lines.length - 1] === '') lines.pop();
+    lines.push('', `[${name}]`, MIRRORLIST_INCLUDE, '');
+    return lines.join('\n');
+  }
+
+  lines[repo.line] = `[${name}]`;
+  for (let index = repo.line + 1; index < lines.length; index++) {
+    const option = lines[index].match(/^\s*#\s*((Include|Server|SigLevel|Usage)\s*=.*)$/);
+    if (!option) {
+      break;
+    }
+    lines[index] = option[1];
+  }
+  if (!/^\s*(Include|Server)\s*=/.test(lines[repo.line + 1] || '')) {
+    lines.splice(repo.line + 1, 0, MIRRORLIST_INCLUDE);
+  }
+  return lines.join('\n');
+}
+
+// [options] settings by how they are written: bare flags, single values, and
+// space-separated lists (which pacman also accepts spread over repeated lines)
+export const OPTION_TYPES = {
+  Color: 'flag',
+  ILoveCandy: 'flag',
+  VerbosePkgLists: 'flag',
+  CheckSpace: 'flag',
+  NoProgressBar: 'flag',
+  DisableDownloadTimeout: 'flag',
+  DisableSandbox: 'flag',
+  ParallelDownloads: 'number',
+  DownloadUser: 'string',
+  Architecture: 'list',
+  IgnorePkg: 'list',
+  IgnoreGroup: 'list',
+  HoldPkg: 'list',
+  NoUpgrade: 'list',
+  NoExtract: 'list',
+  CleanMethod: 'list',
+  SigLevel: 'list',
+  LocalFileSigLevel: 'list',
+  RemoteFileSigLevel: 'list'
+};
+
+// Repositories the stock pacman.conf ships (most of them commented out); removing one
+// comments it out again, and core and extra stay
+const SHIPPED_REPOS = ['core-testing', 'core', 'extra-testing', 'extra', 'multilib-testing', 'multilib', 'gnome-unstable', 'kde-unstable'];
+const REQUIRED_REPOS = ['core', 'extra'];
+
+const SECTION = /^\s*(#\s*)?\[([^\]]+)\]\s*$/;
+const DIRECTIVE = /^\s*([A-Za-z]+)\s*(?:=\s*(.*?))?\s*$/;
+
+// Directives by section, comments skipped; Include is recorded as written, not followed
+export function parsePacmanConf(content) {
+  const options = {};
+  const repos = [];
+  let repo = null;
+  let inOptions = false;
+
+  for (const line of content.split('\n')) {
+    const header = line.match(SECTION);
+    if (header) {
+      if (header[1]) {
+        continue;
+      }
+      inOptions = header[2] === 'options';
+      repo = inOptions ? null : { name: header[2], servers: [], includes: [], sigLevel: null, usage: null };
+      if (repo) repos.push(repo);
+      continue;
+    }
+    if (/^\s*(#|$)/.test(line)) {
+      continue;
+    }
+    const directive = line.match(DIRECTIVE);
+    if (!directive) {
+      continue;
+    }
+    const [, key, value = null] = directive;
+    if (inOptions) {
+      if (OPTION_TYPES[key] === 'list') {
+        options[key] = [...(options[key] || []), ...(value ? value.split(/\s+/) : [])];
+      } else if (key === 'CacheDir' || key === 'HookDir') {
+        options[key] = [...(options[key] || []), value];
+      } else {
+        options[key] = value === null ? true : OPTION_TYPES[key] === 'number' ? Number(value) : value;
+      }
+    } else if (repo) {
+      if (key === 'Server') repo.servers.push(value);
+      else if (key === 'Include') repo.includes.push(value);
+      else if (key === 'SigLevel') repo.sigLevel = value;
+      else if (key === 'Usage') repo.usage = value;
+    }
+  }
+  return { options, repos };
+}
+
+function optionLine(key, value) {
+  switch (OPTION_TYPES[key]) {
+    case 'flag':
+      return key;
+    case 'list':
+      return `${key} = ${(Array.isArray(value) ? value : [value]).join(' ')}`;
+    default:
+      return `${key} = ${value}`;
+  }
+}
+
+// Sets [options] entries in place: an active line is rewritten (repeats of it dropped),
+// a commented default such as "#Color" is uncommented where it stands, anything else is
+// appended to the section. false or null comments the setting out; an empty list too.
+export function setOptions(content, values) {
+  const lines = content.split('\n');
+  if (!lines.some(line => /^\s*\[options\]\s*$/.test(line))) {
+    lines.unshift('[options]', '');
+  }
+
+  for (const [key, value] of Object.entries(values)) {
+    if (!OPTION_TYPES[key]) {
+      throw new Error(`Unsupported pacman.conf option: ${key} (supported: ${Object.keys(OPTION_TYPES).join(', ')})`);
+    }
+    const unset = value === false || value === null || (Array.isArray(value) && value.length === 0);
+    if (!unset) {
+      const type = OPTION_TYPES[key];
+      const valid = type === 'flag' ? value === true
+        : type === 'number' ? Number.isInteger(value) && value > 0
+          : type === 'list' ? [].concat(value).every(item => typeof item === 'string' && /^[^\s#]+$/.test(item))
+            : typeof value === 'string' && /^[^\s#]+$/.test(value);
+      if (!valid) {
+        throw new Error(`Invalid value for ${key}: ${JSON.stringify(value)} (expected ${type === 'flag' ? 'true or false' : type === 'number' ? 'a positive integer' : type === 'list' ? 'a list of words' : 'a single word'})`);
+      }
+    }
+
+    const start = lines.findIndex(line => /^\s*\[options\]\s*$/.test(line));
+    let end = lines.findIndex((line, index) => index > start && SECTION.test(line));
+    end = end === -1 ? lines.length : end;
+    const pattern = new RegExp(`^\\s*(#\\s*)?${key}(\\s*=.*)?\\s*$`);
+    const active = [];
+    let commented = -1;
+    for (let index = start + 1; index < end; index++) {
+      const match = lines[index].match(pattern);
+      if (match && !match[1]) active.push(index);
+      else if (match && commented === -1) commented = index;
+    }
+
+    if (unset) {
+      for (const index of active) {
+        lines[index] = `#${lines[index].trim()}`;
+      }
+      continue;
+    }
+    const line = optionLine(key, value);
+    if (active.length > 0) {
+      lines[active[0]] = line;
+      for (const index of active.slice(1).reverse()) {
+        lines.splice(index, 1);
+      }
+    } else if (commented !== -1) {
+      lines[commented] = line;
+    } else {
+      let at = end;
+      while (at > start + 1 && lines[at - 1].trim() === '') at--;
+      lines.splice(at, 0, line);
+    }
+  }
+  return lines.join('\n');
+}
+
+// Body of a repository section: the option lines below its header, active or
+// commented, up to a blank line or the next section
+function repoBody(lines, header) {
+  let end = header + 1;
+  while (end < lines.length && lines[end].trim() !== '' && !SECTION.test(lines[end]) &&
+    /^\s*#?\s*(Include|Server|SigLevel|Usage|CacheServer)\s*=/.test(lines[end])) {
+    end++;
+  }
+  return end;
+}
+
+// Adds a repository section, at the end or right before an existing repository (pacman
+// takes a package from the first repository that has it). A shipped section that is
+// only commented out is enabled in place when no servers are given.
+export function addRepo(content, { name, servers = [], include = null, sigLevel = null, usage = null, before = null }) {
+  const existing = parseRepos(content).find(repo => repo.name === name);
+  if (existing?.enabled) {
+    throw new Error(`Repository ${name} is already configured; remove it first to change it`);
+  }
+  if (existing && servers.length === 0 && !include && !sigLevel && !usage && !before) {
+    return enableRepo(content, name);
+  }
+  if (servers.length === 0 && !include) {
+    throw new Error(`Repository ${name} needs a server or an Include file`);
+  }
+
+  let lines = content.split('\n');
+  if (existing) {
+    lines.splice(existing.line, repoBody(lines, existing.line) - existing.line);
+  }
+  const section = [
+    `[${name}]`,
+    ...(sigLevel ? [`SigLevel = ${sigLevel}`] : []),
+    ...(usage ? [`Usage = ${usage}`] : []),
+    ...servers.map(server => `Server = ${server}`),
+    ...(include ? [`Include = ${include}`] : [])
+  ];
+
+  if (before) {
+    const anchor = parseRepos(lines.join('\n')).find(repo => repo.name === before && repo.enabled);
+    if (!anchor) {
+      throw new Error(`No enabled repository ${before} to insert ${name} before`);
+    }
+    // Keep the comment block that introduces the anchor with it
+    let at = anchor.line;
+    while (at > 0 && /^\s*#/.test(lines[at - 1]) && !SECTION.test(lines[at - 1])) at--;
+    lines.splice(at, 0, ...section, '');
+  } else {
+    while (lines.length > 0 && lines[lines.length - 1] === '') lines.pop();
+    lines = [...lines, '', ...section, ''];
+  }
+  return lines.join('\n');
+}
+
+// Drops a repository section; shipped ones are commented out instead, as they come
+export function removeRepo(content, name) {
+  if (REQUIRED_REPOS.includes(name)) {
+    throw new Error(`Refusing to remove ${name}: Arch does not work without it`);
+  }
+  const repo = parseRepos(content).find(candidate => candidate.name === name && candidate.enabled);
+  if (!repo) {
+    return content;
+  }
+
+  const lines = content.split('\n');
+  const end = repoBody(lines, repo.line);
+  if (SHIPPED_REPOS.includes(name)) {
+    for (let index = repo.line; index < end; index++) {
+      lines[index] = /^\s*#/.test(lines[index]) ? lines[index] : `#${lines[index].trim()}`;
+    }
+    return lines.join('\n');
+  }
+  const blankAfter = lines[end] === '' && (repo.line === 0 || lines[repo.line - 1] === '') ? 1 : 0;
+  lines.splice(repo.line, end - repo.line + blankAfter);
+  return lines.join('\n');
+}
@@ -0,0 +1,138 @@
+// Turns the output of pacman (and pacstrap, which runs it) into progress: the phase of the
+// transaction, the package being worked on and an overall percentage. Output arrives in
+// arbitrary chunks, and without a terminal pacman prints one line per step:
+//   Packages (152) acl-2.3.2-1  archlinux-keyring-20240520-1  ...
+//   Total Download Size:   498.12 MiB
+//   :: Retrieving packages...
+//    acl-2.3.2-1-x86_64 downloading...
+//   checking package integrity...
+//   :: Processing package changes...
+//   installing acl...
+//   :: Running post-transaction hooks...
+//   (1/21) Creating system user accounts...
+
+// Where each phase starts on the 0-100 scale; downloading and installing take the most time
+const PHASE_RANGES = {
+  prepare: [0, 0],
+  sync: [0, 2],
+  resolve: [2, 2],
+  download: [2, 40],
+  verify: [40, 45],
+  install: [45, 95],
+  hooks: [95, 100]
+};
+
+const VERIFY_STEP = /^(checking keyring|checking package integrity|loading package files|checking for file conflicts|checking available disk space)/;
+const PACKAGE_STEP = /^(?:\((\d+)\/(\d+)\) )?(installing|upgrading|reinstalling|downgrading|removing) (\S+?)(?:\.\.\.)?$/;
+
+export class PacmanProgress {
+  constructor() {
+    this.phase = 'prepare';
+    this.total = null;
+    this.current = 0;
+    this.downloaded = 0;
+    this.downloadSize = null;
+    this.installSize = null;
+    this.package = null;
+    this.percent = 0;
+    this.pending = '';
+  }
+
+  // Complete lines only; the rest waits for the next chunk. Progress bars redraw with \r
+  feed(chunk) {
+    const lines = (this.pending + chunk).split(/\r\n|\r|\n/);
+    this.pending = lines.pop();
+    return lines.map(line => this.parseLine(line.trim())).filter(Boolean);
+  }
+
+  parseLine(line) {
+    let match;
+    if (!line) {
+      return null;
+    }
+    if (line.startsWith('==> ')) {
+      return this.update('prepare', { message: line.slice(4) });
+    }
+    if (line.startsWith(':: Synchronizing package databases')) {
+      return this.update('sync', { message: 'Synchronizing package databases' });
+    }
+    if ((match = line.match(/^Packages \((\d+)\)/))) {
+      // A new transaction (pacman -Sy then -Su, say) starts counting again
+      this.total = Number(match[1]);
+      this.current = 0;
+      this.downloaded = 0;
+      this.percent = 0;
+      return this.update('resolve', { message: `${this.total} packages` });
+    }
+    if ((match = line.match(/^Total Download Size:\s+(.+)$/))) {
+      this.downloadSize = match[1];
+      return null;
+    }
+    if ((match = line.match(/^Total Installed Size:\s+(.+)$/))) {
+      this.installSize = match[1];
+      return null;
+    }
+    if (line.startsWith(':: Retrieving packages')) {
+      return this.update('download', { message: `Downloading ${this.downloadSize || 'packages'}` });
+    }
+    if ((match = line.match(/^(\S+) downloading\.\.\.$/))) {
+      // Sync databases download the same way before any package does
+      if (this.phase === 'sync') {
+        return this.update('sync', { message: `Synchronizing ${match[1]}` });
+      }
+      this.downloaded++;
+      this.package = match[1];
+      return this.update('download', { message: `Downloading ${match[1]}` });
+    }
+    if ((match = line.match(VERIFY_STEP))) {
+      return this.update('verify', { message: match[1] });
+    }
+    if (line.startsWith(':: Processing package changes')) {
+      this.package = null;
+      return this.update('install', { message: 'Installing packages' });
+    }
+    if ((match = line.match(PACKAGE_STEP))) {
+      this.current = match[1] ? Number(match[1]) : this.current + 1;
+      this.total = match[2] ? Number(match[2]) : this.total;
+      this.package = match[4];
+      return this.update('install', { message: `${match[3]} ${match[4]}` });
+    }
+    if (line.startsWith(':: Running post-transaction hooks')) {
+      return this.update('hooks', { message: 'Running post-transaction hooks', hook: [0, null] });
+    }
+    if (this.phase === 'hooks' && (match = line.match(/^\((\d+)\/(\d+)\) (.+?)\.*$/))) {
+      return this.update('hooks', { message: match[3], hook: [Number(match[1]), Number(match[2])] });
+    }
+    return null;
+  }
+
+  update(phase, { message, hook = null }) {
+    this.phase = phase;
+    const [start, end] = PHASE_RANGES[phase];
+    let fraction = 0;
+    if (phase === 'download' && this.total) {
+      fraction = this.downloaded / this.total;
+    } else if (phase === 'install' && this.total) {
+      fraction = this.current / this.total;
+    } else if (hook?.[1]) {
+      fraction = hook[0] / hook[1];
+    }
+    // Cached packages are never downloaded, so phases can end early but never go back
+    this.percent = Math.max(this.percent, Math.min(100, Math.round(start + (end - start) * Math.min(1, fraction))));
+
+    return {
+      phase,
+      percent: this.percent,
+      message,
+      package: phase === 'download' || phase === 'install' ? this.package : null,
+      current: phase === 'install' ? this.current : phase === 'download' ? this.downloaded : null,
+      total: this.total,
+      downloadSize: this.downloadSize
+    };
+  }
+
+  // What the transaction amounted to, for the final result
+  summary() {
+    return { packages: this.total, downloadSize: this.downloadSize, installSize: this.installSize };
+  }
+}
@@ -0,0 +1,325 @@
+import crypto from 'crypto';
+import { parseSize } from './disk-quota.js';
+import { partitionPath } from './loop-image.js';
+
+// GPT type codes as sgdisk takes them, the type GUIDs they stand for, and the MBR ids
+// fdisk takes
+export const PARTITION_TYPES = {
+  efi: { gpt: 'ef00', guid: 'C12A7328-F81F-11D2-BA4B-00A0C93EC93B', mbr: 'ef', name: 'EFI System' },
+  swap: { gpt: '8200', guid: '0657FD6D-A4AB-43C4-84E5-0933C84B4F4F', mbr: '82', name: 'Linux swap' },
+  linux: { gpt: '8300', guid: '0FC63DAF-8483-4772-8E79-3D69D8477DE4', mbr: '83', name: 'Linux filesystem' },
+  raid: { gpt: 'fd00', guid: 'A19D880F-05FC-4D3B-A006-743F0F84911E', mbr: 'fd', name: 'Linux RAID' }
+};
+
+// The array a RAID install builds, and the room mdadm keeps for its superblock and data
+// offset on each member
+export const RAID_DEVICE = '/dev/md0';
+export const RAID_LEVELS = ['raid1', 'raid0'];
+const RAID_RESERVED_BYTES = 130 * 1024 ** 2;
+
+// How each filesystem is created and mounted (genfstab copies the mount options into
+// fstab), the package the installed system needs to check and repair it, and the kernel
+// module the live system needs to mount it
+export const FILESYSTEMS = {
+  vfat: { mkfs: 'mkfs.fat', args: ['-F32'], mountOptions: ['umask=0077'], package: 'dosfstools', module: 'vfat' },
+  swap: { mkfs: 'mkswap', args: [], mountOptions: [], package: null, module: null },
+  ext4: { mkfs: 'mkfs.ext4', args: ['-F'], mountOptions: ['noatime'], package: null, module: 'ext4' },
+  btrfs: { mkfs: 'mkfs.btrfs', args: ['-f'], mountOptions: ['noatime', 'compress=zstd'], package: 'btrfs-progs', module: 'btrfs' },
+  xfs: { mkfs: 'mkfs.xfs', args: ['-f'], mountOptions: ['noatime'], package: 'xfsprogs', module: 'xfs' },
+  f2fs: { mkfs: 'mkfs.f2fs', args: ['-f'], mountOptions: ['noatime'], package: 'f2fs-tools', module: 'f2fs' }
+};
+
+// Filesystems root and other data partitions can use
+export const DATA_FILESYSTEMS = ['ext4', 'btrfs', 'xfs', 'f2fs'];
+
+// The first partition starts at 1 MiB and GPT keeps a backup table at the end of the disk
+const RESERVED_BYTES = 2 * 1024 ** 2;
+// fdisk here only creates primary partitions
+const MAX_MBR_PARTITIONS = 3;
+
+// The fixed layout the installer has always used: EFI (UEFI only), swap, root
+export function defaultLayout(scheme, { swapSize = '4G', rootSize = 'remaining', filesystem = 'ext4' } = {}) {
+  return [
+    ...(scheme === 'uefi' ? [{ type: 'efi', size: '512M', label: 'EFI', filesystem: 'vfat', mountpoint: '/boot/efi' }] : []),
+    { type: 'swap', size: swapSize, label: 'swap', filesystem: 'swap', mountpoint: null },
+    { type: 'linux', size: rootSize, label: 'root', filesystem, mountpoint: '/' }
+  ];
+}
+
+// A layout from mount points and sizes, e.g. / 60G, /home remaining, /var 20G, swap 8G.
+// UEFI gets its EFI partition first, and the entry taking the remaining space moves to
+// the end, where it has to be.
+export function customLayout(scheme, entries, { filesystem = 'ext4' } = {}) {
+  if (!entries.some(entry => entry.mountpoint === '/')) {
+    throw new Error('The layout needs a / partition');
+  }
+  const seen = new Set();
+  const layout = entries.map(entry => {
+    const { mountpoint, size } = entry;
+    if (mountpoint !== 'swap' && !/^\/([\w.-]+(\/[\w.-]+)*)?$/.test(mountpoint || '')) {
+      throw new Error(`Invalid mount point: ${mountpoint} (an absolute path, or "swap")`);
+    }
+    if (mountpoint === '/boot/efi') {
+      throw new Error('/boot/efi is created for the uefi scheme and cannot be part of the layout');
+    }
+    if (seen.has(mountpoint)) {
+      throw new Error(`${mountpoint} appears more than once in the layout`);
+    }
+    seen.add(mountpoint);
+    if (size !== 'remaining' && !/^\d+(\.\d+)?[KMGT]$/i.test(size || '')) {
+      throw new Error(`Invalid size for ${mountpoint}: ${size} (e.g. 20G, or "remaining")`);
+    }
+    if (mountpoint === 'swap') {
+      return { type: 'swap', size, label: 'swap', filesystem: 'swap', mountpoint: null };
+    }
+    const fs = entry.filesystem || filesystem;
+    if (!DATA_FILESYSTEMS.includes(fs)) {
+      throw new Error(`Unsupported filesystem for ${mountpoint}: ${fs}`);
+    }
+    return {
+      type: 'linux',
+      size,
+      label: mountpoint === '/' ? 'root' : mountpoint.slice(1).replace(/\//g, '-'),
+      filesystem: fs,
+      mountpoint
+    };
+  });
+
+  const remaining = layout.filter(entry => entry.size === 'remaining');
+  if (remaining.length > 1) {
+    throw new Error('Only one partition can take the remaining space');
+  }
+  return [
+    ...(scheme === 'uefi' ? [{ type: 'efi', size: '512M', label: 'EFI', filesystem: 'vfat', mountpoint: '/boot/efi' }] : []),
+    ...layout.filter(entry => entry.size !== 'remaining'),
+    ...remaining
+  ];
+}
+
+// Parents before children, so /var is mounted before /var/log
+export function mountOrder(partitions) {
+  return partitions
+    .filter(partition => partition.mountpoint)
+    .sort((a, b) => (a.mountpoint === '/' ? 0 : a.mountpoint.split('/').length) - (b.mountpoint === '/' ? 0 : b.mountpoint.split('/').length));
+}
+
+// Arguments for mount -o, or none when the filesystem mounts with the defaults
+export function mountArgs(filesystem, options = FILESYSTEMS[filesystem]?.mountOptions || []) {
+  return options.length ? ['-o', options.join(',')] : [];
+}
+
+// Everything partitioning a disk will do: the resulting table and the exact commands, in
+// order. The id covers the disk's identity and the commands, so a confirmed plan only runs
+// against the disk and layout that were previewed.
+export function buildPartitionPlan(device, scheme, layout, { diskSize = null, identity = null, table = scheme === 'uefi' ? 'gpt' : 'dos' } = {}) {
+  if (table === 'dos' && layout.length > MAX_MBR_PARTITIONS) {
+    throw new Error(`A BIOS (MBR) layout can have at most ${MAX_MBR_PARTITIONS} partitions; use the uefi scheme for more`);
+  }
+
+  const fixed = layout.slice(0, -1).find(entry => entry.size === 'remaining');
+  if (fixed) {
+    throw new Error(`Only the last partition can take the remaining space (${fixed.mountpoint || fixed.type} does)`);
+  }
+
+  let used = RESERVED_BYTES;
+  const partitions = layout.map((entry, index) => {
+    const remaining = entry.size === 'remaining';
+    const bytes = remaining ? null : parseSize(entry.size);
+    used += bytes || 0;
+    if (!PARTITION_TYPES[entry.type]) {
+      throw new Error(`Unknown partition type: ${entry.type}`);
+    }
+    // RAID members carry no filesystem of their own
+    if (!FILESYSTEMS[entry.filesystem] && !(entry.type === 'raid' && entry.filesystem === null)) {
+      throw new Error(`Unsupported filesystem: ${entry.filesystem}`);
+    }
+    if (entry.mountpoint === '/' && !DATA_FILESYSTEMS.includes(entry.filesystem)) {
+      throw new Error(`The root filesystem must be one of ${DATA_FILESYSTEMS.join(', ')}`);
+    }
+    return {
+      number: index + 1,
+      path: partitionPath(device, index + 1),
+      size: entry.size,
+      bytes,
+      type: entry.type,
+      typeCode: PARTITION_TYPES[entry.type][table === 'gpt' ? 'gpt' : 'mbr'],
+      typeName: PARTITION_TYPES[entry.type].name,
+      label: table === 'gpt' ? entry.label || null : null,
+      filesystem: entry.filesystem,
+      mountpoint: entry.mountpoint ?? null,
+      mountOptions: entry.mountOptions || FILESYSTEMS[entry.filesystem]?.mountOptions || []
+    };
+  });
+
+  if (diskSize) {
+    if (used > diskSize) {
+      throw new Error(`The layout needs ${used} bytes but ${device} has ${diskSize}`);
+    }
+    const last = partitions[partitions.length - 1];
+    if (last.bytes === null) {
+      last.bytes = diskSize - used;
+    }
+  }
+
+  const commands = [
+    { command: 'wipefs', args: ['-a', device] },
+    ...(table === 'gpt' ? gptCommands(device, partitions) : [mbrCommand(device, partitions)]),
+    ...partitions.filter(partition => partition.filesystem).map(partition => ({
+      command: FILESYSTEMS[partition.filesystem].mkfs,
+      args: [...FILESYSTEMS[partition.filesystem].args, partition.path]
+    }))
+  ];
+
+  const id = crypto.createHash('sha256')
+    .update(JSON.stringify({ device, identity, commands }))
+    .digest('hex')
+    .slice(0, 12);
+
+  const filesystems = [...new Set(partitions.map(partition => partition.filesystem).filter(Boolean))];
+  return {
+    id,
+    device,
+    scheme,
+    table,
+    diskSize,
+    partitions,
+    commands,
+    // What pacstrap adds so the installed system can check and repair its filesystems
+    packages: filesystems.map(filesystem => FILESYSTEMS[filesystem].package).filter(Boolean)
+  };
+}
+
+// Every member disk gets the same table: an EFI partition of its own (UEFI only) and a RAID
+// partition over the rest. The array is then partitioned with the layout like a disk.
+// The EFI partitions are nofail, so the system still boots when a member is gone.
+export function buildRaidPlan(devices, scheme, level, layout, { diskSizes = {}, identities = {} } = {}) {
+  if (devices.length < 2) {
+    throw new Error('A RAID install needs at least two disks');
+  }
+  if (new Set(devices).size !== devices.length) {
+    throw new Error('Each RAID member disk can only be listed once');
+  }
+  if (!RAID_LEVELS.includes(level)) {
+    throw new Error(`Unsupported RAID level: ${level}`);
+  }
+
+  const memberPlans = devices.map((device, index) => buildPartitionPlan(device, scheme, [
+    ...(scheme === 'uefi' ? [{
+      type: 'efi',
+      size: '512M',
+      label: index ? `EFI-${index + 1}` : 'EFI',
+      filesystem: 'vfat',
+      mountpoint: index ? `/boot/efi-${index + 1}` : '/boot/efi',
+      mountOptions: [...FILESYSTEMS.vfat.mountOptions, 'nofail']
+    }] : []),
+    { type: 'raid', size: 'remaining', label: 'raid', filesystem: null, mountpoint: null }
+  ], { diskSize: diskSizes[device] || null }));
+
+  const members = memberPlans.map(plan => plan.partitions[plan.partitions.length - 1]);
+  // RAID1 is as large as its smallest member, RAID0 the sum of them
+  const memberBytes = members.every(member => member.bytes)
+    ? Math.min(...members.map(member => member.bytes)) - RAID_RESERVED_BYTES
+    : null;
+  const arraySize = memberBytes && (level === 'raid1' ? memberBytes : memberBytes * members.length);
+  const arrayPlan = buildPartitionPlan(RAID_DEVICE, scheme, layout, { diskSize: arraySize, table: 'gpt' });
+
+  const commands = [
+    ...memberPlans.flatMap(plan => plan.commands),
+    {
+      command: 'mdadm',
+      args: ['--create', RAID_DEVICE, '--run', `--level=${level.slice(4)}`, `--raid-devices=${members.length}`,
+        '--metadata=1.2', ...members.map(member => member.path)]
+    },
+    ...arrayPlan.commands
+  ];
+
+  const id = crypto.createHash('sha256')
+    .update(JSON.stringify({ devices, identities, commands }))
+    .digest('hex')
+    .slice(0, 12);
+
+  return {
+    id,
+    device: RAID_DEVICE,
+    devices,
+    scheme,
+    table: memberPlans[0].table,
+    raid: { level, device: RAID_DEVICE, members: members.map(member => member.path) },
+    diskSize: arraySize,
+    partitions: [...memberPlans.flatMap(plan => plan.partitions), ...arrayPlan.partitions],
+    commands,
+    packages: [...new Set([...memberPlans.flatMap(plan => plan.packages), ...arrayPlan.packages, 'mdadm'])]
+  };
+}
+
+function gptCommands(device, partitions) {
+  return [
+    { command: 'sgdisk', args: ['-o', device] },
+    ...partitions.map(({ number, size, typeCode, label }) => ({
+      command: 'sgdisk',
+      args: [
+        '-n', `${number}:0:${size === 'remaining' ? '0' : `+${size}`}`,
+        '-t', `${number}:${typeCode}`,
+        ...(label ? ['-c', `${number}:${label}`] : []),
+        device
+      ]
+    }))
+  ];
+}
+
+// One fdisk session fed on stdin; with a single partition fdisk does not ask which one to retype
+function mbrCommand(device, partitions) {
+  const script = ['o'];
+  for (const { number, size, typeCode } of partitions) {
+    script.push('n', 'p', String(number), '', size === 'remaining' ? '' : `+${size}`);
+    if (typeCode !== PARTITION_TYPES.linux.mbr) {
+      script.push('t', ...(number > 1 ? [String(number)] : []), typeCode);
+    }
+  }
+  script.push('w');
+  return { command: 'fdisk', args: [device], input: `${script.join('\n')}\n` };
+}
+
+const quote = arg => /^[\w@%+=:,./-]+$/.test(arg) ? arg : `'${arg.replace(/'/g, `'\\''`)}'`;
+
+export function commandLine({ command, args, input }) {
+  const line = [command, ...args].map(quote).join(' ');
+  return input ? `${line} <<'EOF'\n${input}EOF` : line;
+}
+
+const formatBytes = bytes => bytes === null ? 'rest of disk' : `${(bytes / 1024 ** 3).toFixed(bytes >= 10 * 1024 ** 3 ? 0 : 1)} GiB`;
+
+// Markdown for people: the resulting table, then the commands as they will run
+export function renderPartitionPlan(plan) {
+  const rows = plan.partitions.map(partition => `| ${[
+    partition.number,
+    partition.path,
+    `${partition.size === 'remaining' ? 'remaining' : partition.size} (${formatBytes(partition.bytes)})`,
+    `${partition.typeName} (${partition.typeCode})`,
+    partition.label || '',
+    partition.filesystem || (partition.type === 'raid' ? `member of ${plan.raid.device}` : ''),
+    partition.mountpoint || (partition.filesystem === 'swap' ? '[swap]' : '')
+  ].join(' | ')} |`);
+
+  return [
+    plan.raid
+      ? `Partition plan ${plan.id} for ${plan.raid.level} array ${plan.device} over ${plan.devices.join(', ')} (${plan.table === 'gpt' ? 'GPT' : 'MBR'} members, ${plan.scheme})`
+      : `Partition plan ${plan.id} for ${plan.device} (${plan.table === 'gpt' ? 'GPT' : 'MBR'}, ${plan.scheme})`,
+    '',
+    '| # | Device | Size | Type | Label | Filesystem | Mount |',
+    '|---|--------|------|------|-------|------------|-------|',
+    ...rows,
+    '',
+    ...(plan.checks?.length ? [
+      '| Check | Result |',
+      '|-------|--------|',
+      ...plan.checks.map(check => `| ${check.name} | ${check.ok ? 'ok' : `FAILED: ${check.detail}`} |`),
+      ''
+    ] : []),
+    '```sh',
+    ...plan.commands.map(commandLine),
+    '```',
+    '',
+    `WARNING: This will destroy all data on ${(plan.devices || [plan.device]).join(', ')}`
+  ].join('\n');
+}
@@ -0,0 +1,277 @@
+import fs from 'fs-extra';
+import os from 'os';
+import path from 'path';
+import { parseSize } from './disk-quota.js';
+import { PARTITION_TYPES } from './partition-plan.js';
+
+const MIB = 1024 ** 2;
+
+// Types partition_set accepts by name besides the ones the installer creates, with the
+// GPT type GUID and the MBR id (null where MBR has none)
+export const MORE_PARTITION_TYPES = {
+  'bios-boot': { guid: '21686148-6449-6E6F-744E-656564454649', mbr: null, name: 'BIOS boot' },
+  xbootldr: { guid: 'BC13C2FF-59E6-4262-A352-B275FD6F7172', mbr: 'ea', name: 'Linux extended boot' },
+  'linux-root-x86-64': { guid: '4F68BCE3-E8CD-4DB1-96E7-FBCAF984B709', mbr: '83', name: 'Linux root (x86-64)' },
+  'linux-home': { guid: '933AC7E1-2EB4-4F13-B844-0E14E2AEF915', mbr: '83', name: 'Linux home' },
+  'linux-lvm': { guid: 'E6D6D379-F507-44C2-A23C-238F2A3DF928', mbr: '8e', name: 'Linux LVM' },
+  'linux-luks': { guid: 'CA7D7CCB-63ED-4C53-861C-1742536059CC', mbr: '83', name: 'Linux LUKS' },
+  'microsoft-basic-data': { guid: 'EBD0A0A2-B9E5-4433-87C0-68B6B72699C7', mbr: '07', name: 'Microsoft basic data' },
+  'microsoft-reserved': { guid: 'E3C9E316-0B5C-4DB8-817D-F92DF00215AE', mbr: null, name: 'Microsoft reserved' },
+  'windows-recovery': { guid: 'DE94BBA4-06D1-4D40-A16A-BFD50179D6AC', mbr: '27', name: 'Windows recovery environment' }
+};
+
+export const NAMED_PARTITION_TYPES = { ...PARTITION_TYPES, ...MORE_PARTITION_TYPES };
+
+// MBR ids with no named type above
+const MBR_NAMES = { '05': 'Extended', '0f': 'W95 Extended (LBA)', '85': 'Linux extended', '0b': 'W95 FAT32', '0c': 'W95 FAT32 (LBA)', ee: 'GPT protective' };
+const EXTENDED = ['05', '0f', '85'];
+
+export function typeName(label, type) {
+  const value = String(type).toLowerCase();
+  const named = Object.values(NAMED_PARTITION_TYPES)
+    .find(entry => label === 'gpt' ? entry.guid?.toLowerCase() === value : entry.mbr === value.replace(/^0x/, '').padStart(2, '0'));
+  return named?.name || (label === 'dos' ? MBR_NAMES[value.replace(/^0x/, '').padStart(2, '0')] : null) || null;
+}
+
+// A type as sfdisk takes it for this table: a name from NAMED_PARTITION_TYPES, a GPT type
+// GUID or an MBR id in hex
+export function resolvePartitionType(label, type) {
+  const named = NAMED_PARTITION_TYPES[type];
+  if (named) {
+    const value = label === 'gpt' ? named.guid : named.mbr;
+    if (!value) {
+      throw new Error(`${type} has no ${label === 'gpt' ? 'GPT' : 'MBR'} type`);
+    }
+    return value;
+  }
+  if (label === 'gpt' && /^[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$/i.test(type)) {
+    return type.toUpperCase();
+  }
+  if (label === 'dos' && /^(0x)?[0-9a-f]{1,2}$/i.test(type)) {
+    return type.replace(/^0x/i, '').toLowerCase();
+  }
+  throw new Error(`Invalid ${label === 'gpt' ? 'GPT' : 'MBR'} partition type: ${type} (a name: ${Object.keys(NAMED_PARTITION_TYPES).join(', ')}; ` +
+    `${label === 'gpt' ? 'or a type GUID' : 'or a hex id such as 83'})`);
+}
+
+// The disk a partition device belongs to, by name (/dev/sdb2, /dev/nvme0n1p2,
+// /dev/mmcblk0p1, /dev/loop0p1); for locks, which cannot wait for sysfs
+export function parentDisk(partition) {
+  return /\dp\d+$/.test(partition) ? partition.replace(/p\d+$/, '') : partition.replace(/\d+$/, '');
+}
+
+// sfdisk --json output with sizes in bytes, partition numbers and the free gaps of at
+// least 1 MiB between partitions; MBR tables do not give their last usable sector, so
+// that comes from the disk size
+export function describeTable(json, { diskSize = null } = {}) {
+  const table = json.partitiontable;
+  const sectorSize = table.sectorsize || 512;
+  const partitions = (table.partitions || [])
+    .map(entry => ({
+      number: Number(/(\d+)$/.exec(entry.node)[1]),
+      node: entry.node,
+      start: entry.start,
+      end: entry.start + entry.size - 1,
+      sectors: entry.size,
+      size: entry.size * sectorSize,
+      type: entry.type,
+      typeName: typeName(table.label, entry.type),
+      ...(table.label === 'gpt' ? { uuid: entry.uuid || null, name: entry.name || '', attrs: entry.attrs || null } : { bootable: Boolean(entry.bootable) })
+    }))
+    .sort((a, b) => a.start - b.start);
+
+  const first = table.firstlba ?? 2048;
+  const last = table.lastlba ?? (diskSize ? diskSize / sectorSize - 1 : null);
+  const free = [];
+  let next = first;
+  for (const partition of partitions.filter(entry => !EXTENDED.includes(String(entry.type).toLowerCase()))) {
+    if (partition.start > next) free.push({ start: next, end: partition.start - 1 });
+    next = Math.max(next, partition.end + 1);
+  }
+  if (last !== null && last >= next) free.push({ start: next, end: last });
+
+  return {
+    device: table.device,
+    label: table.label,
+    id: table.id,
+    sectorSize,
+    firstLba: first,
+    lastLba: last,
+    partitions,
+    free: free
+      .map(gap => ({ ...gap, size: (gap.end - gap.start + 1) * sectorSize }))
+      .filter(gap => gap.size >= MIB)
+  };
+}
+
+// How each filesystem follows its partition: grown after the partition grows, shrunk
+// before it shrinks, and whether that needs it mounted or not mounted
+export const FILESYSTEM_RESIZE = {
+  ext2: { grow: 'any', shrink: 'unmounted' },
+  ext3: { grow: 'any', shrink: 'unmounted' },
+  ext4: { grow: 'any', shrink: 'unmounted' },
+  btrfs: { grow: 'mounted', shrink: 'mounted' },
+  xfs: { grow: 'mounted', shrink: null },
+  f2fs: { grow: 'unmounted', shrink: null },
+  swap: { grow: 'unmounted', shrink: 'unmounted' }
+};
+
+// The devid btrfs filesystem show gives a member device, which resize needs on
+// multi-device filesystems
+export function btrfsDevid(output, device) {
+  const line = output.split('\n').find(entry => new RegExp(`\\spath ${device.replace(/[.+]/g, '\\$&')}$`).test(entry.trim()));
+  const match = line && /devid\s+(\d+)/.exec(line);
+  return match ? Number(match[1]) : null;
+}
+
+// Commands that bring a filesystem to bytes (null: fill the partition). Offline ext
+// resizes need a forced fsck first; e2fsck exits 1 when it fixed something
+export function filesystemResizeCommands(fstype, { device, mountpoint = null, bytes = null, devid = 1, uuid = null, label = null }) {
+  switch (fstype) {
+    case 'ext2':
+    case 'ext3':
+    case 'ext4':
+      return [
+        ...(mountpoint ? [] : [{ command: 'e2fsck', args: ['-f', '-p', device], okExitCodes: [0, 1] }]),
+        { command: 'resize2fs', args: [device, ...(bytes === null ? [] : [`${Math.floor(bytes / 1024)}K`])] }
+      ];
+    case 'btrfs':
+      return [{ command: 'btrfs', args: ['filesystem', 'resize', `${devid}:${bytes === null ? 'max' : bytes}`, mountpoint] }];
+    case 'xfs':
+      return [{ command: 'xfs_growfs', args: [mountpoint] }];
+    case 'f2fs':
+      return [{ command: 'resize.f2fs', args: [device] }];
+    case 'swap':
+      return [{ command: 'mkswap', args: [...(uuid ? ['-U', uuid] : []), ...(label ? ['-L', label] : []), device] }];
+    default:
+      throw new Error(`Cannot resize ${fstype}`);
+  }
+}
+
+export class PartitionTable {
+  constructor(commandExecutor, logger, { backupDir = null } = {}) {
+    this.commandExecutor = commandExecutor;
+    this.logger = logger;
+    this.backupDir = backupDir;
+  }
+
+  async read(disk) {
+    const result = await this.commandExecutor.executeWithSudo('sfdisk', ['--json', disk], { retry: false });
+    if (!result.success) {
+      if (/does not contain a recognized partition table/i.test(result.stderr)) {
+        return { device: disk, label: null, partitions: [], free: [] };
+      }
+      throw new Error(`sfdisk --json ${disk} failed: ${result.stderr.trim()}`);
+    }
+    const size = await this.commandExecutor.execute('lsblk', ['-b', '-d', '-n', '-o', 'SIZE', disk]);
+    return describeTable(JSON.parse(result.stdout), { diskSize: size.success ? Number(size.stdout.trim()) : null });
+  }
+
+  // Filesystem, label and mounts of each partition, from lsblk
+  async withFilesystems(table) {
+    const result = await this.commandExecutor.execute('lsblk', ['-J', '-b', '-p', '-o', 'PATH,FSTYPE,LABEL,UUID,MOUNTPOINTS', table.device]);
+    const devices = new Map();
+    const walk = node => {
+      devices.set(node.path, node);
+      (node.children || []).forEach(walk);
+    };
+    (result.success ? JSON.parse(result.stdout).blockdevices || [] : []).forEach(walk);
+    return {
+      ...table,
+      partitions: table.partitions.map(partition => {
+        const device = devices.get(partition.node) || {};
+        return {
+          ...partition,
+          fstype: device.fstype || null,
+          fsLabel: device.label || null,
+          fsUuid: device.uuid || null,
+          mountpoints: (device.mountpoints || []).filter(Boolean)
+        };
+      })
+    };
+  }
+
+  async listDisks() {
+    const result = await this.commandExecutor.execute('lsblk', ['-J', '-d', '-p', '-o', 'PATH,TYPE,RO']);
+    if (!result.success) {
+      throw new Error(`lsblk failed: ${result.stderr}`);
+    }
+    return (JSON.parse(result.stdout).blockdevices || [])
+      .filter(disk => disk.type === 'disk' && !/^\/dev\/(zram|ram)/.test(disk.path))
+      .map(disk => disk.path);
+  }
+
+  // The disk and number of a partition device, from sysfs
+  async locate(partition) {
+    const resolved = await fs.realpath(partition).catch(() => partition);
+    const sysfs = await fs.realpath(`/sys/class/block/${path.basename(resolved)}`).catch(() => null);
+    const number = sysfs ? await fs.readFile(path.join(sysfs, 'partition'), 'utf8').catch(() => null) : null;
+    if (!number) {
+      throw new Error(`${partition} is not a partition`);
+    }
+    const disk = `/dev/${path.basename(path.dirname(sysfs))}`;
+    const table = await this.withFilesystems(await this.read(disk));
+    const entry = table.partitions.find(candidate => candidate.number === Number(number));
+    if (!entry) {
+      throw new Error(`Partition ${number.trim()} is not in the partition table of ${disk}`);
+    }
+    return { disk, number: Number(number), table, partition: entry };
+  }
+
+  // The partition's new size in sectors, and the most it can take: up to the next
+  // partition or the end of the usable area
+  sizeFor({ table, partition }, size) {
+    const limit = Math.min(
+      ...table.partitions.filter(entry => entry.start > partition.start).map(entry => entry.start),
+      (table.lastLba ?? partition.end) + 1
+    ) - partition.start;
+    if (size === 'max') {
+      return { sectors: limit, limit };
+    }
+
+    const match = /^([+-]?)(.+)$/.exec(String(size));
+    const bytes = parseSize(match[2]);
+    const wanted = match[1] === '+' ? partition.size + bytes : match[1] === '-' ? partition.size - bytes : bytes;
+    // Whole MiB, so the partition after a later resize stays aligned
+    const sectors = Math.floor(wanted / MIB) * MIB / table.sectorSize;
+    if (sectors <= 0) {
+      throw new Error(`${partition.node} would have no space left`);
+    }
+    if (sectors > limit) {
+      throw new Error(`${partition.node} can grow to at most ${limit * table.sectorSize} bytes here; the next partition or the end of the disk is in the way`);
+    }
+    return { sectors, limit };
+  }
+
+  // The partition table as sfdisk --dump writes it, kept before every edit so
+  // "sfdisk <disk> < file" puts it back
+  async backup(disk) {
+    const result = await this.commandExecutor.executeWithSudo('sfdisk', ['--dump', disk], { retry: false });
+    if (!result.success) {
+      throw new Error(`sfdisk --dump ${disk} failed: ${result.stderr.trim()}`);
+    }
+    const dir = this.backupDir || await fs.mkdtemp(path.join(os.tmpdir(), 'partition-tables-'));
+    await fs.ensureDir(dir);
+    const file = path.join(dir, `${path.basename(disk)}-${new Date().toISOString().replace(/[:.]/g, '-')}.sfdisk`);
+    await fs.writeFile(file, `${result.stdout}\n`, { mode: 0o600 });
+    return file;
+  }
+
+  // sfdisk changes the table on disk only; the kernel learns about it from partx, which
+  // updates partitions in place, mounted ones included
+  async sfdisk(args, input = null) {
+    const result = await this.commandExecutor.executeWithSudo('sfdisk', ['--no-reread', '--no-tell-kernel', ...args], { input, retry: false });
+    if (!result.success) {
+      throw new Error(`sfdisk failed: ${result.stderr.trim().split('\n').slice(-3).join('\n')}`);
+    }
+    return result;
+  }
+
+  // A change event makes udev pick up new types and labels (by-partlabel links) too
+  async rescan(disk, { number = null, partition = null } = {}) {
+    const result = await this.commandExecutor.executeWithSudo('partx', ['--update', ...(number ? ['--nr', String(number)] : []), disk], { retry: false });
+    await this.commandExecutor.executeWithSudo('udevadm', ['trigger', '--action=change', '--settle', partition || disk], { retry: false })
+      .catch(error => this.logger.warn(`udevadm trigger failed: ${error.message}`));
+    return result;
+  }
+}
@@ -0,0 +1,66 @@
+const SESSION_PROPERTIES = ['Name', 'Class', 'Type', 'Seat', 'Active', 'Remote', 'IdleHint', 'IdleSinceHint', 'LockedHint'];
+
+// Whether a human is at the machine, from logind: a local, active user session on a seat
+// that is neither locked nor idle for longer than idleThreshold seconds. Idle times come
+// from the idle hint an idle daemon sets (e.g. swayidle's idlehint); without one a
+// session only counts as away once it is locked.
+export class UserPresence {
+  constructor(config = {}, commandExecutor, logger) {
+    this.commandExecutor = commandExecutor;
+    this.logger = logger;
+    this.guard = config.guard ?? false;
+    this.idleThreshold = config.idleThreshold ?? 300;
+    this.tools = config.tools || [];
+  }
+
+  async sessions() {
+    const list = await this.commandExecutor.execute('loginctl', ['list-sessions', '--no-legend', '--no-pager']);
+    if (!list.success) {
+      throw new Error(`Listing login sessions failed: ${list.stderr}`);
+    }
+
+    const sessions = [];
+    for (const id of list.stdout.split('\n').map(line => line.trim().split(/\s+/)[0]).filter(Boolean)) {
+      const show = await this.commandExecutor.execute('loginctl',
+        ['show-session', id, ...SESSION_PROPERTIES.flatMap(property => ['-p', property])]);
+      const props = Object.fromEntries(show.stdout.split('\n').filter(line => line.includes('='))
+        .map(line => [line.slice(0, line.indexOf('=')), line.slice(line.indexOf('=') + 1)]));
+      // IdleSinceHint is in microseconds since the epoch
+      const idleSince = props.IdleHint === 'yes' && Number(props.IdleSinceHint) > 0
+        ? Math.floor(Number(props.IdleSinceHint) / 1000) : null;
+
+      sessions.push({
+        id,
+        user: props.Name,
+        class: props.Class,
+        type: props.Type,
+        seat: props.Seat || null,
+        active: props.Active === 'yes',
+        remote: props.Remote === 'yes',
+        locked: props.LockedHint === 'yes',
+        idle: props.IdleHint === 'yes',
+        idleSeconds: idleSince ? Math.max(Math.floor((Date.now() - idleSince) / 1000), 0) : 0
+      });
+    }
+    return sessions;
+  }
+
+  // Active user sessions, local or remote; greeters and lock screens are not users
+  async activeSessions() {
+    return (await this.sessions()).filter(session => session.class === 'user' && session.active);
+  }
+
+  async status() {
+    const sessions = await this.sessions();
+    const foreground = sessions.filter(session => session.class === 'user' && session.active && session.seat && !session.remote);
+    const present = foreground.filter(session => !session.locked && session.idleSeconds < this.idleThreshold);
+    return {
+      present: present.length > 0,
+      users: present.map(session => session.user),
+      idleThreshold: this.idleThreshold,
+      guard: this.guard,
+      foreground,
+      sessions
+    };
+  }
+}
@@ -0,0 +1,116 @@
+const NETWORK_ERRORS = [
+  /failed retrieving file/i,
+  /could not resolve host/i,
+  /operation too slow/i,
+  /connection timed out/i,
+  /connection reset by peer/i,
+  /temporary failure in name resolution/i,
+  /failed to synchroni[sz]e all databases/i
+];
+
+const PACMAN_LOCK_ERRORS = [
+  /unable to lock database/i,
+  /could not lock database/i
+];
+
+const BUSY_DEVICE_ERRORS = [
+  /target is busy/i,
+  /device is busy/i,
+  /device or resource busy/i,
+  /resource busy/i
+];
+
+// Per-command classification of transient failures worth retrying
+export const RETRYABLE_ERRORS = {
+  pacman: [...PACMAN_LOCK_ERRORS, ...NETWORK_ERRORS],
+  pacstrap: [...PACMAN_LOCK_ERRORS, ...NETWORK_ERRORS],
+  'arch-chroot': [...PACMAN_LOCK_ERRORS, ...NETWORK_ERRORS],
+  'pacman-key': NETWORK_ERRORS,
+  umount: BUSY_DEVICE_ERRORS,
+  swapoff: BUSY_DEVICE_ERRORS,
+  wipefs: BUSY_DEVICE_ERRORS,
+  sgdisk: BUSY_DEVICE_ERRORS,
+  partprobe: BUSY_DEVICE_ERRORS
+};
+
+export class RetryPolicy {
+  constructor(config = {}) {
+    this.maxAttempts = config.maxAttempts ?? 3;
+    this.initialDelay = config.initialDelay ?? 1000;
+    this.maxDelay = config.maxDelay ?? 15000;
+    this.backoffFactor = config.backoffFactor ?? 2;
+    this.patterns = { ...RETRYABLE_ERRORS, ...(config.patterns || {}) };
+  }
+
+  withOverrides(overrides = {}) {
+    return new RetryPolicy({
+      maxAttempts: this.maxAttempts,
+      initialDelay: this.initialDelay,
+      maxDelay: this.maxDelay,
+      backoffFactor: this.backoffFactor,
+      patterns: this.patterns,
+      ...overrides
+    });
+  }
+
+  // A failure is retryable when its output matches a known transient pattern
+  isRetryable(command, failure) {
+    const patterns = this.patterns[command];
+    if (!patterns || patterns.length === 0) {
+      return false;
+    }
+
+    const output = failure instanceof Error ?
+      failure.message :
+      `${failure?.stderr || ''}\n${failure?.stdout || ''}`;
+
+    return patterns.some(pattern => pattern.test(output));
+  }
+
+  delayFor(attempt) {
+    const base = Math.min(this.initialDelay * Math.pow(this.backoffFactor, attempt - 1), this.maxDelay);
+    // Add jitter so concurrent retries don't hit a lock in lockstep
+    return Math.round(base * (0.8 + Math.random() * 0.4));
+  }
+
+  async run(command, operation, { isApplied = null, onRetry = null } = {}) {
+    let lastResult = null;
+    let lastError = null;
+
+    for (let attempt = 1; attempt <= this.maxAttempts; attempt++) {
+      // Skip the retry if a previous attempt already took effect
+      if (attempt > 1 && isApplied && await isApplied()) {
+        return lastResult ? { ...lastResult, success: true, alreadyApplied: true, attempts: attempt - 1 } :
+          { exitCode: 0, stdout: '', stderr: '', success: true, alreadyApplied: true, attempts: attempt - 1 };
+      }
+
+      try {
+        lastResult = await operation(attempt);
+        lastError = null;
+
+        if (lastResult.success || !this.isRetryable(command, lastResult)) {
+          return { ...lastResult, attempts: attempt };
+        }
+      } catch (error) {
+        lastError = error;
+        if (!this.isRetryable(command, error)) {
+          throw error;
+        }
+      }
+
+      if (attempt < this.maxAttempts) {
+        const delay = this.delayFor(attempt);
+        if (onRetry) {
+          onRetry({ attempt, delay, reason: lastError ? lastError.message : lastResult.stderr });
+        }
+        await new Promise(resolve => setTimeout(resolve, delay));
+      }
+    }
+
+    if (lastError) {
+      throw lastError;
+    }
+
+    return { ...lastResult, attempts: this.maxAttempts };
+  }
+}
@@ -0,0 +1,628 @@
+import { EventEmitter } from 'events';
+import fs from 'fs-extra';
+import os from 'os';
+import path from 'path';
+import crypto from 'crypto';
+import zlib from 'zlib';
+import { promisify } from 'util';
+import { v4 as uuidv4 } from 'uuid';
+import { DiskQuota, getQuotaRegistry, parseSize } from './disk-quota.js';
+import { matchesAny, matchesGlob } from './glob.js';
+import { CommandExecutor } from './command-executor.js';
+import { PackageState } from './package-state.js';
+import { SnapshotCrypto } from './snapshot-crypto.js';
+
+const gzip = promisify(zlib.gzip);
+const gunzip = promisify(zlib.gunzip);
+
+// Services a rollback must not stop or disable: this server, systemd's own units, the
+// message bus, logins and remote access. They are left out of snapshots
+const PROTECTED_SERVICES = /^(mcp-arch-linux.*|systemd-.*|dbus(-broker)?|(serial-)?getty@|user(-runtime-dir)?@|sshd|polkit|display-manager|gdm|sddm|lightdm|greetd)\.service$/;
+
+// Enabled and active state of services that can be enabled and disabled, from
+// "systemctl list-unit-files" and "systemctl list-units --state=active" output
+export function parseServiceStates(unitFiles, activeUnits) {
+  const active = new Set(activeUnits.split('\n').map(line => line.trim().split(/\s+/)[0]).filter(Boolean));
+  return unitFiles.split('\n')
+    .map(line => line.trim().split(/\s+/))
+    .filter(([name, state]) => name && ['enabled', 'disabled'].includes(state) && !PROTECTED_SERVICES.test(name))
+    .map(([name, state]) => ({ name, enabled: state === 'enabled', active: active.has(name) }))
+    .sort((a, b) => a.name.localeCompare(b.name));
+}
+
+// Snapshot and operation ids are v4 UUIDs; tool schemas take this as their pattern
+export const SNAPSHOT_ID_PATTERN = '^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$';
+
+export class RollbackManager extends EventEmitter {
+  constructor(config, logger) {
+    super();
+    this.config = config;
+    this.logger = logger;
+    this.snapshotDir = config.snapshotDir || '/var/lib/mcp-arch-linux/snapshots';
+    this.compress = config.compressSnapshots ?? true;
+    this.retention = {
+      keepLast: 20,
+      keepDaily: 7,
+      keepWeekly: 4,
+      maxTotalSize: null,
+      pruneInterval: 3600000,
+      ...(config.retention || {})
+    };
+    this.pruneTimer = null;
+    this.commandExecutor = new CommandExecutor(config, logger);
+    this.packageState = new PackageState(this.commandExecutor, logger);
+    this.crypto = new SnapshotCrypto(config.snapshotEncryption || {}, logger);
+    this.allowUnsigned = config.snapshotEncryption?.allowUnsigned ?? false;
+    this.quota = getQuotaRegistry().register(new DiskQuota(
+      'snapshots',
+      this.snapshotDir,
+      {
+        maxSize: config.storage?.quotas?.snapshots,
+        minFreePercent: config.storage?.minFreePercent,
+        warnPercent: config.storage?.warnPercent
+      },
+      logger
+    ));
+  }
+
+  // Ids reach here from tool arguments; anything but a UUID could name a file outside snapshotDir
+  snapshotPath(snapshotId) {
+    if (typeof snapshotId !== 'string' || !new RegExp(SNAPSHOT_ID_PATTERN).test(snapshotId)) {
+      throw new Error(`Invalid snapshot ID: ${snapshotId}`);
+    }
+    const file = path.resolve(this.snapshotDir, `${snapshotId}.json`);
+    if (path.dirname(file) !== path.resolve(this.snapshotDir)) {
+      throw new Error(`Snapshot path ${file} is outside ${this.snapshotDir}`);
+    }
+    return file;
+  }
+
+  async createSnapshot(description, paths = [], options = {}) {
+    const { include = [], exclude = [], compress = this.compress, operation = null, packages = false } = options;
+    const snapshotId = uuidv4();
+    const timestamp = new Date().toISOString();
+
+    this.logger.info(`Creating system snapshot: ${snapshotId}`);
+
+    const snapshot = {
+      id: snapshotId,
+      version: 2,
+      description,
+      timestamp,
+      files: [],
+      services: [],
+      packages: null,
+      operation,
+      metadata: {
+        hostname: os.hostname(),
+        user: process.env.USER || 'unknown',
+        node: process.version
+      }
+    };
+
+    // Backup specified files and directory trees
+    for (const entryPath of paths) {
+      try {
+        await this.collectEntries(path.resolve(entryPath), snapshot.files, { include, exclude, compress });
+      } catch (error) {
+        this.logger.warn(`Failed to backup ${entryPath}:`, error.message);
+      }
+    }
+
+    // Get systemd service states
+    try {
+      snapshot.services = await this.getServiceStates();
+    } catch (error) {
+      this.logger.warn('Failed to capture service states:', error.message);
+    }
+
+    // Record the installed package set so configs and packages can be rolled back together
+    if (packages) {
+      try {
+        snapshot.packages = await this.packageState.capture();
+      } catch (error) {
+        this.logger.warn('Failed to capture package state:', error.message);
+      }
+    }
+
+    // File contents may include secrets; keep only summary fields in the clear
+    if (this.crypto.enabled) {
+      snapshot.fileCount = snapshot.files.length;
+      snapshot.packageCount = snapshot.packages?.length ?? 0;
+      snapshot.encrypted = await this.crypto.encrypt(JSON.stringify({
+        files: snapshot.files,
+        packages: snapshot.packages
+      }));
+      snapshot.files = [];
+      snapshot.packages = null;
+      snapshot.signature = await this.crypto.sign(snapshot);
+    }
+
+    await fs.ensureDir(this.snapshotDir);
+
+    const serialized = JSON.stringify(snapshot, null, 2);
+    await this.quota.ensureCapacity(Buffer.byteLength(serialized));
+    await fs.writeFile(this.snapshotPath(snapshotId), serialized, { encoding: 'utf8', mode: 0o600 });
+
+    this.logger.audit('snapshot_created', {
+      snapshotId,
+      description,
+      operationId: operation?.id,
+      fileCount: snapshot.fileCount ?? snapshot.files.length,
+      serviceCount: snapshot.services.length,
+      packageCount: snapshot.packageCount ?? snapshot.packages?.length ?? 0,
+      encrypted: Boolean(snapshot.encrypted)
+    });
+    this.emit('changed', { action: 'created', snapshotId });
+
+    return snapshotId;
+  }
+
+  async collectEntries(entryPath, entries, options, isRoot = true) {
+    const stats = await fs.lstat(entryPath).catch(() => null);
+    if (!stats) {
+      return;
+    }
+
+    // Explicitly requested paths are always kept; filters apply to directory contents
+    if (!isRoot) {
+      if (options.include.length > 0 && !stats.isDirectory() && !matchesAny(entryPath, options.include)) {
+        return;
+      }
+      if (matchesAny(entryPath, options.exclude)) {
+        return;
+      }
+    }
+
+    const base = {
+      path: entryPath,
+      mode: stats.mode,
+      uid: stats.uid,
+      gid: stats.gid,
+      mtime: stats.mtime
+    };
+
+    if (stats.isSymbolicLink()) {
+      entries.push({ ...base, type: 'symlink', target: await fs.readlink(entryPath) });
+    } else if (stats.isDirectory()) {
+      entries.push({ ...base, type: 'directory' });
+      const children = await fs.readdir(entryPath);
+      for (const child of children.sort()) {
+        await this.collectEntries(path.join(entryPath, child), entries, options, false);
+      }
+    } else if (stats.isFile()) {
+      entries.push({ ...base, type: 'file', ...await this.encodeFile(entryPath, options.compress) });
+    }
+  }
+
+  // Store raw bytes so binary files survive the JSON round trip
+  async encodeFile(filePath, compress) {
+    const data = await fs.readFile(filePath);
+    const payload = compress ? await gzip(data) : data;
+
+    return {
+      size: data.length,
+      sha256: crypto.createHash('sha256').update(data).digest('hex'),
+      encoding: 'base64',
+      compression: compress ? 'gzip' : null,
+      content: payload.toString('base64')
+    };
+  }
+
+  async decodeFile(file) {
+    // Snapshots written before binary support stored plain UTF-8 text
+    if (!file.encoding) {
+      return Buffer.from(file.content, 'utf8');
+    }
+
+    const payload = Buffer.from(file.content, 'base64');
+    const data = file.compression === 'gzip' ? await gunzip(payload) : payload;
+
+    if (file.sha256) {
+      const digest = crypto.createHash('sha256').update(data).digest('hex');
+      if (digest !== file.sha256) {
+        throw new Error(`Checksum mismatch for ${file.path}`);
+      }
+    }
+
+    return data;
+  }
+
+  async loadSnapshot(snapshotId) {
+    const snapshotPath = this.snapshotPath(snapshotId);
+
+    if (!await fs.pathExists(snapshotPath)) {
+      throw new Error(`Snapshot not found: ${snapshotId}`);
+    }
+
+    const snapshot = await fs.readJson(snapshotPath);
+
+    // Reject tampered snapshots before any of their contents are trusted
+    if (snapshot.signature || snapshot.encrypted || (this.crypto.enabled && !this.allowUnsigned)) {
+      if (!await this.crypto.verify(snapshot)) {
+        this.logger.audit('snapshot_verification_failed', { snapshotId });
+        throw new Error(`Snapshot ${snapshotId} failed signature verification`);
+      }
+    }
+
+    if (snapshot.encrypted) {
+      const { files, packages } = JSON.parse(await this.crypto.decrypt(snapshot.encrypted));
+      snapshot.files = files;
+      snapshot.packages = packages;
+    }
+
+    return snapshot;
+  }
+
+  // Restore everything, or only selected paths / only service states; dryRun returns the plan.
+  // Service states are only restored for scope services, or all with no paths selected,
+  // so putting back one file does not also start and stop unrelated services
+  async restoreSnapshot(snapshotId, options = {}) {
+    const { files: selectedPaths = [], scope = 'all', dryRun = false } = options;
+    const restoreServices = options.services ?? (scope === 'services' || (scope === 'all' && selectedPaths.length === 0));
+
+    if (!['all', 'files', 'services', 'packages'].includes(scope)) {
+      throw new Error(`Invalid rollback scope: ${scope}`);
+    }
+
+    this.logger.info(`${dryRun ? 'Planning' : 'Restoring'} system snapshot: ${snapshotId}`, { scope, selectedPaths });
+
+    const snapshot = await this.loadSnapshot(snapshotId);
+
+    const fileEntries = ['all', 'files'].includes(scope) ?
+      snapshot.files.filter(entry => this.isSelected(entry.path, selectedPaths)) : [];
+    let services = restoreServices ? snapshot.services : [];
+    let servicesUnavailable = null;
+    let currentServices = [];
+    if (services.length > 0) {
+      // No systemd to ask in a chroot or rescue shell; files and packages still restore
+      try {
+        currentServices = await this.getServiceStates();
+      } catch (error) {
+        if (scope === 'services') {
+          throw new Error(`Cannot restore service states: ${error.message}`);
+        }
+        servicesUnavailable = error.message;
+        this.logger.warn('Service states unavailable, not restoring them:', error.message);
+        services = [];
+      }
+    }
+    const packages = ['all', 'packages'].includes(scope) && snapshot.packages ?
+      await this.packageState.planRestore(snapshot.packages) : [];
+
+    if (scope === 'services' && snapshot.services.length === 0) {
+      throw new Error(`Snapshot ${snapshotId} does not contain service states`);
+    }
+
+    if (scope === 'packages' && !snapshot.packages) {
+      throw new Error(`Snapshot ${snapshotId} does not contain package state`);
+    }
+
+    if (selectedPaths.length > 0 && fileEntries.length === 0 && ['all', 'files'].includes(scope)) {
+      throw new Error(`None of the requested paths are in snapshot ${snapshotId}`);
+    }
+
+    const plan = {
+      snapshotId,
+      dryRun,
+      scope,
+      files: await Promise.all(fileEntries.map(entry => this.planEntry(entry))),
+      services: services.map(service => this.planService(service, currentServices.find(entry => entry.name === service.name))),
+      ...(servicesUnavailable ? { servicesUnavailable } : {}),
+      packages
+    };
+
+    if (dryRun) {
+      return plan;
+    }
+
+    // Restore files
+    for (const [index, entry] of fileEntries.entries()) {
+      if (plan.files[index].action === 'unchanged') {
+        continue;
+      }
+
+      try {
+        await this.restoreEntry(entry);
+        plan.files[index].restored = true;
+        this.logger.debug(`Restored ${entry.type || 'file'}: ${entry.path}`);
+      } catch (error) {
+        plan.files[index].error = error.message;
+        this.logger.error(`Failed to restore ${entry.path}:`, error.message);
+      }
+    }
+
+    // Return packages to the recorded set before services are touched
+    if (packages.length > 0) {
+      plan.packageResult = await this.packageState.apply(packages, snapshot.packages);
+      for (const error of plan.packageResult.errors) {
+        this.logger.error('Package rollback failed:', error);
+      }
+    }
+
+    // Restore services once their unit files and packages are back, against the states
+    // those restores left behind
+    if (services.length > 0) {
+      try {
+        await this.commandExecutor.executeWithSudo('systemctl', ['daemon-reload'], { retry: false });
+        const restoredServices = await this.getServiceStates();
+        plan.services = services.map(service => this.planService(service, restoredServices.find(entry => entry.name === service.name)));
+      } catch (error) {
+        plan.servicesUnavailable = error.message;
+        plan.services = [];
+        this.logger.warn('Service states unavailable, not restoring them:', error.message);
+      }
+    }
+    for (const service of plan.services) {
+      for (const action of service.actions) {
+        const result = await this.commandExecutor.executeWithSudo('systemctl', [action, service.name], { retry: false });
+        if (!result.success) {
+          service.error = `systemctl ${action} failed: ${result.stderr.trim()}`;
+          this.logger.error(`Failed to ${action} ${service.name}:`, result.stderr.trim());
+          break;
+        }
+        this.logger.debug(`${action} ${service.name}`);
+      }
+      service.restored = service.actions.length > 0 && !service.error;
+    }
+
+    this.logger.audit('snapshot_restored', {
+      snapshotId,
+      scope,
+      selectedPaths,
+      fileCount: plan.files.filter(file => file.restored).length,
+      serviceCount: plan.services.filter(service => service.restored).length,
+      packageCount: packages.length
+    });
+
+    return plan;
+  }
+
+  // A selection matches the exact path, anything beneath a selected directory, or a glob
+  isSelected(entryPath, selectedPaths) {
+    if (selectedPaths.length === 0) {
+      return true;
+    }
+
+    return selectedPaths.some(selected => {
+      const resolved = path.resolve(selected);
+      return entryPath === resolved ||
+        entryPath.startsWith(`${resolved}/`) ||
+        (/[*?{]/.test(selected) && matchesGlob(entryPath, selected));
+    });
+  }
+
+  async planEntry(entry) {
+    const type = entry.type || 'file';
+    const planned = { path: entry.path, type };
+    const current = await fs.lstat(entry.path).catch(() => null);
+
+    if (!current) {
+      return { ...planned, action: 'create' };
+    }
+
+    let contentChanged = false;
+    if (type === 'file') {
+      if (!current.isFile()) {
+        contentChanged = true;
+      } else {
+        const data = await fs.readFile(entry.path);
+        const digest = crypto.createHash('sha256').update(data).digest('hex');
+        const expected = entry.sha256 ||
+          crypto.createHash('sha256').update(await this.decodeFile(entry)).digest('hex');
+        contentChanged = digest !== expected;
+      }
+    } else if (type === 'symlink') {
+      contentChanged = !current.isSymbolicLink() || await fs.readlink(entry.path) !== entry.target;
+    }
+
+    const modeChanged = (current.mode & 0o7777) !== (entry.mode & 0o7777);
+    const ownerChanged = entry.uid !== undefined && (current.uid !== entry.uid || current.gid !== entry.gid);
+
+    if (!contentChanged && !modeChanged && !ownerChanged) {
+      return { ...planned, action: 'unchanged' };
+    }
+
+    return {
+      ...planned,
+      action: contentChanged ? 'overwrite' : 'update-metadata',
+      changes: { content: contentChanged, mode: modeChanged, owner: ownerChanged }
+    };
+  }
+
+  // A service whose unit file is gone has nothing to enable or start
+  planService(service, current) {
+    if (!current) {
+      return { name: service.name, actions: [], missing: true };
+    }
+    const actions = [];
+    if (service.enabled !== current.enabled) {
+      actions.push(service.enabled ? 'enable' : 'disable');
+    }
+    if (service.active !== current.active) {
+      actions.push(service.active ? 'start' : 'stop');
+    }
+    return { name: service.name, enabled: service.enabled, active: service.active, actions };
+  }
+
+  async restoreEntry(entry) {
+    const type = entry.type || 'file';
+
+    if (type === 'directory') {
+      await fs.ensureDir(entry.path);
+    } else if (type === 'symlink') {
+      await fs.ensureDir(path.dirname(entry.path));
+      await fs.remove(entry.path);
+      await fs.symlink(entry.target, entry.path);
+      return;
+    } else {
+      await fs.ensureDir(path.dirname(entry.path));
+      await fs.writeFile(entry.path, await this.decodeFile(entry));
+    }
+
+    await fs.chmod(entry.path, entry.mode & 0o7777);
+
+    if (entry.uid !== undefined && entry.gid !== undefined) {
+      try {
+        await fs.chown(entry.path, entry.uid, entry.gid);
+      } catch (error) {
+        this.logger.warn(`Could not restore ownership of ${entry.path}:`, error.message);
+      }
+    }
+  }
+
+  async listSnapshots() {
+    if (!await fs.pathExists(this.snapshotDir)) {
+      return [];
+    }
+
+    const files = await fs.readdir(this.snapshotDir);
+    const snapshots = [];
+
+    for (const file of files) {
+      if (path.extname(file) === '.json') {
+        try {
+          const snapshotPath = path.join(this.snapshotDir, file);
+          const snapshot = await fs.readJson(snapshotPath);
+          const stats = await fs.stat(snapshotPath);
+          snapshots.push({
+            id: snapshot.id,
+            description: snapshot.description,
+            timestamp: snapshot.timestamp,
+            fileCount: snapshot.fileCount ?? snapshot.files.length,
+            serviceCount: snapshot.services.length,
+            packageCount: snapshot.packageCount ?? snapshot.packages?.length ?? 0,
+            encrypted: Boolean(snapshot.encrypted),
+            operation: snapshot.operation || null,
+            size: stats.size
+          });
+        } catch (error) {
+          this.logger.warn(`Failed to read snapshot ${file}:`, error.message);
+        }
+      }
+    }
+
+    return snapshots.sort((a, b) => new Date(b.timestamp) - new Date(a.timestamp));
+  }
+
+  async deleteSnapshot(snapshotId) {
+    const snapshotPath = this.snapshotPath(snapshotId);
+
+    if (!await fs.pathExists(snapshotPath)) {
+      throw new Error(`Snapshot not found: ${snapshotId}`);
+    }
+
+    await fs.remove(snapshotPath);
+
+    this.logger.audit('snapshot_deleted', { snapshotId });
+    this.emit('changed', { action: 'deleted', snapshotId });
+
+    return true;
+  }
+
+  // Decide which snapshots survive: last N, newest per day/week bucket, within the size cap
+  planRetention(snapshots, policy = this.retention) {
+    const sorted = [...snapshots].sort((a, b) => new Date(b.timestamp) - new Date(a.timestamp));
+    const keep = new Set(sorted.slice(0, Math.max(policy.keepLast, 1)).map(snapshot => snapshot.id));
+
+    const keepBuckets = (count, bucketOf) => {
+      const seen = new Set();
+      for (const snapshot of sorted) {
+        if (seen.size >= count) break;
+        const bucket = bucketOf(new Date(snapshot.timestamp));
+        if (!seen.has(bucket)) {
+          seen.add(bucket);
+          keep.add(snapshot.id);
+        }
+      }
+    };
+
+    keepBuckets(policy.keepDaily, date => date.toISOString().slice(0, 10));
+    keepBuckets(policy.keepWeekly, date => this.isoWeek(date));
+
+    const maxTotalSize = parseSize(policy.maxTotalSize);
+    if (maxTotalSize) {
+      let total = 0;
+      for (const [index, snapshot] of sorted.entries()) {
+        if (!keep.has(snapshot.id)) continue;
+        total += snapshot.size || 0;
+        // The newest snapshot is always kept, even if it alone exceeds the cap
+        if (total > maxTotalSize && index > 0) {
+          keep.delete(snapshot.id);
+        }
+      }
+    }
+
+    return {
+      keep: sorted.filter(snapshot => keep.has(snapshot.id)),
+      prune: sorted.filter(snapshot => !keep.has(snapshot.id))
+    };
+  }
+
+  isoWeek(date) {
+    const day = new Date(Date.UTC(date.getUTCFullYear(), date.getUTCMonth(), date.getUTCDate()));
+    const weekday = day.getUTCDay() || 7;
+    day.setUTCDate(day.getUTCDate() + 4 - weekday);
+    const yearStart = new Date(Date.UTC(day.getUTCFullYear(), 0, 1));
+    const week = Math.ceil(((day - yearStart) / 86400000 + 1) / 7);
+    return `${day.getUTCFullYear()}-W${String(week).padStart(2, '0')}`;
+  }
+
+  async pruneSnapshots({ dryRun = false } = {}) {
+    const snapshots = await this.listSnapshots();
+    const { keep, prune } = this.planRetention(snapshots);
+
+    if (!dryRun) {
+      for (const snapshot of prune) {
+        try {
+          await fs.remove(this.snapshotPath(snapshot.id));
+        } catch (error) {
+          this.logger.warn(`Failed to prune snapshot ${snapshot.id}:`, error.message);
+        }
+      }
+
+      if (prune.length > 0) {
+        this.logger.audit('snapshots_pruned', {
+          pruned: prune.map(snapshot => snapshot.id),
+          kept: keep.length
+        });
+        this.emit('changed', { action: 'pruned', snapshotIds: prune.map(snapshot => snapshot.id) });
+      }
+    }
+
+    return {
+      dryRun,
+      kept: keep.map(snapshot => snapshot.id),
+      pruned: prune.map(snapshot => ({ id: snapshot.id, timestamp: snapshot.timestamp, size: snapshot.size }))
+    };
+  }
+
+  startPruning() {
+    if (this.pruneTimer || !this.retention.pruneInterval) {
+      return;
+    }
+
+    this.pruneTimer = setInterval(() => {
+      this.pruneSnapshots().catch(error => {
+        this.logger.error('Snapshot pruning failed:', error.message);
+      });
+    }, this.retention.pruneInterval);
+    this.pruneTimer.unref();
+  }
+
+  stopPruning() {
+    if (this.pruneTimer) {
+      clearInterval(this.pruneTimer);
+      this.pruneTimer = null;
+    }
+  }
+
+  async getServiceStates() {
+    const unitFiles = await this.commandExecutor.execute('systemctl', ['list-unit-files', '--type=service', '--no-legend', '--no-pager']);
+    const activeUnits = await this.commandExecutor.execute('systemctl', ['list-units', '--type=service', '--state=active', '--no-legend', '--no-pager', '--plain']);
+    if (!unitFiles.success || !activeUnits.success) {
+      throw new Error(`systemctl failed: ${(unitFiles.stderr || activeUnits.stderr).trim()}`);
+    }
+    return parseServiceStates(unitFiles.stdout, activeUnits.stdout);
+  }
+}
@@ -0,0 +1,190 @@
+import { EventEmitter } from 'events';
+import fs from 'fs-extra';
+import path from 'path';
+
+const readValue = async (file) => {
+  const text = await fs.readFile(file, 'utf8').catch(() => null);
+  return text === null ? null : text.trim();
+};
+
+const readNumber = async (file, scale = 1) => {
+  const value = await readValue(file);
+  return value === null || value === '' || Number.isNaN(Number(value)) ? null : Number(value) / scale;
+};
+
+// Reads the same hwmon attributes lm_sensors does: temperatures in degC, fans in RPM, voltages in V
+export async function readHwmon(root = '/sys') {
+  const base = path.join(root, 'class', 'hwmon');
+  const chips = [];
+
+  for (const entry of (await fs.readdir(base).catch(() => [])).sort()) {
+    const dir = path.join(base, entry);
+    const files = await fs.readdir(dir).catch(() => []);
+    const chip = { id: entry, name: await readValue(path.join(dir, 'name')) || entry, temperatures: [], fans: [], voltages: [] };
+
+    for (const file of files.filter(name => /^temp\d+_input$/.test(name))) {
+      const prefix = path.join(dir, file.replace('_input', ''));
+      chip.temperatures.push({
+        sensor: file.replace('_input', ''),
+        label: await readValue(`${prefix}_label`),
+        current: await readNumber(`${prefix}_input`, 1000),
+        max: await readNumber(`${prefix}_max`, 1000),
+        critical: await readNumber(`${prefix}_crit`, 1000),
+        alarm: (await readValue(`${prefix}_alarm`)) === '1' || (await readValue(`${prefix}_crit_alarm`)) === '1'
+      });
+    }
+
+    for (const file of files.filter(name => /^fan\d+_input$/.test(name))) {
+      const prefix = path.join(dir, file.replace('_input', ''));
+      chip.fans.push({
+        sensor: file.replace('_input', ''),
+        label: await readValue(`${prefix}_label`),
+        rpm: await readNumber(`${prefix}_input`),
+        min: await readNumber(`${prefix}_min`)
+      });
+    }
+
+    for (const file of files.filter(name => /^in\d+_input$/.test(name))) {
+      const prefix = path.join(dir, file.replace('_input', ''));
+      chip.voltages.push({
+        sensor: file.replace('_input', ''),
+        label: await readValue(`${prefix}_label`),
+        volts: await readNumber(`${prefix}_input`, 1000)
+      });
+    }
+
+    const bySensor = (a, b) => a.sensor.localeCompare(b.sensor, undefined, { numeric: true });
+    chip.temperatures.sort(bySensor);
+    chip.fans.sort(bySensor);
+    chip.voltages.sort(bySensor);
+    chips.push(chip);
+  }
+
+  return chips;
+}
+
+// Throttle counters only ever increase; a rising count means the CPU is being slowed down
+export async function readThrottling(root = '/sys') {
+  const base = path.join(root, 'devices', 'system', 'cpu');
+  const cpus = (await fs.readdir(base).catch(() => [])).filter(name => /^cpu\d+$/.test(name));
+
+  let packageThrottles = 0;
+  let coreThrottles = 0;
+  let available = false;
+  const frequencies = [];
+
+  for (const cpu of cpus) {
+    const throttleDir = path.join(base, cpu, 'thermal_throttle');
+    const packageCount = await readNumber(path.join(throttleDir, 'package_throttle_count'));
+    const coreCount = await readNumber(path.join(throttleDir, 'core_throttle_count'));
+    if (packageCount !== null || coreCount !== null) {
+      available = true;
+      // Package counters repeat on every core of the package; keep the largest
+      packageThrottles = Math.max(packageThrottles, packageCount || 0);
+      coreThrottles += coreCount || 0;
+    }
+
+    const current = await readNumber(path.join(base, cpu, 'cpufreq', 'scaling_cur_freq'), 1000);
+    const max = await readNumber(path.join(base, cpu, 'cpufreq', 'cpuinfo_max_freq'), 1000);
+    if (current !== null && max) {
+      frequencies.push(current / max);
+    }
+  }
+
+  return {
+    available,
+    packageThrottles,
+    coreThrottles,
+    frequencyRatio: frequencies.length > 0
+      ? Math.round(frequencies.reduce((sum, ratio) => sum + ratio, 0) / frequencies.length * 100) / 100
+      : null
+  };
+}
+
+// Polls sensors while anyone is subscribed and emits 'alert'/'recovered' when a temperature
+// crosses its limit; hysteresis keeps a sensor hovering at the limit from flapping
+export class SensorMonitor extends EventEmitter {
+  constructor({ root = '/sys', interval = 5000, limits = {}, hysteresis = 5 } = {}) {
+    super();
+    this.root = root;
+    this.interval = interval;
+    this.limits = { default: 85, ...limits };
+    this.hysteresis = hysteresis;
+    this.timer = null;
+    this.hot = new Map();
+    this.lastThrottles = null;
+  }
+
+  // Configured per-chip limit, then the chip's own max, then the configured default
+  limitFor(chip, temperature) {
+    return this.limits[chip.name] ?? temperature.max ?? temperature.critical ?? this.limits.default;
+  }
+
+  async read() {
+    const chips = await readHwmon(this.root);
+    for (const chip of chips) {
+      for (const temperature of chip.temperatures) {
+        temperature.limit = this.limitFor(chip, temperature);
+        temperature.overLimit = temperature.current !== null && temperature.current >= temperature.limit;
+      }
+    }
+    return { timestamp: new Date().toISOString(), chips, throttling: await readThrottling(this.root) };
+  }
+
+  async check() {
+    const reading = await this.read();
+
+    for (const chip of reading.chips) {
+      for (const temperature of chip.temperatures) {
+        if (temperature.current === null) continue;
+
+        const key = `${chip.id}/${temperature.sensor}`;
+        const details = {
+          chip: chip.name,
+          sensor: temperature.label || temperature.sensor,
+          current: temperature.current,
+          limit: temperature.limit
+        };
+
+        if (temperature.overLimit && !this.hot.has(key)) {
+          this.hot.set(key, details);
+          this.emit('alert', { type: 'temperature', ...details });
+        } else if (this.hot.has(key) && temperature.current < temperature.limit - this.hysteresis) {
+          this.hot.delete(key);
+          this.emit('recovered', { type: 'temperature', ...details });
+        }
+      }
+    }
+
+    const { throttling } = reading;
+    if (throttling.available) {
+      const total = throttling.packageThrottles + throttling.coreThrottles;
+      if (this.lastThrottles !== null && total > this.lastThrottles) {
+        this.emit('alert', { type: 'throttling', events: total - this.lastThrottles, frequencyRatio: throttling.frequencyRatio });
+      }
+      this.lastThrottles = total;
+    }
+
+    return reading;
+  }
+
+  get running() {
+    return this.timer !== null;
+  }
+
+  start() {
+    if (this.timer) return;
+
+    const poll = () => this.check().catch(error => this.emit('error', error));
+    poll();
+    this.timer = setInterval(poll, this.interval);
+    this.timer.unref();
+  }
+
+  stop() {
+    clearInterval(this.timer);
+    this.timer = null;
+    this.hot.clear();
+    this.lastThrottles = null;
+  }
+}
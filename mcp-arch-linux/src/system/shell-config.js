@@ -0,0 +1,152 @@
+import path from 'path';
+
+export const ALIAS_NAME = /^[A-Za-z0-9_.-]+$/;
+
+// The managed file holds everything this server writes; the shell's own rc file only
+// gains one line sourcing it (fish reads conf.d by itself)
+export const SHELLS = {
+  bash: {
+    package: 'bash',
+    path: '/usr/bin/bash',
+    rc: '.bashrc',
+    managed: '.config/bash/mcp-arch-linux.bash',
+    extras: ['bash-completion'],
+    frameworks: []
+  },
+  zsh: {
+    package: 'zsh',
+    path: '/usr/bin/zsh',
+    rc: '.zshrc',
+    managed: '.config/zsh/mcp-arch-linux.zsh',
+    extras: ['zsh-completions', 'zsh-autosuggestions', 'zsh-syntax-highlighting'],
+    frameworks: ['oh-my-zsh']
+  },
+  fish: {
+    package: 'fish',
+    path: '/usr/bin/fish',
+    rc: null,
+    managed: '.config/fish/conf.d/mcp-arch-linux.fish',
+    extras: [],
+    frameworks: []
+  }
+};
+
+export const OH_MY_ZSH = {
+  url: 'https://github.com/ohmyzsh/ohmyzsh.git',
+  dir: '.oh-my-zsh'
+};
+
+export const DEFAULT_ALIASES = {
+  ls: 'ls --color=auto',
+  ll: 'ls -lh',
+  la: 'ls -lah',
+  grep: 'grep --color=auto'
+};
+
+const ZSH_PLUGINS = '/usr/share/zsh/plugins';
+
+// POSIX and fish single quoting differ: fish allows \' inside quotes, POSIX shells do not
+function quote(shell, value) {
+  return shell === 'fish'
+    ? `'${value.replace(/\\/g, '\\\\').replace(/'/g, "\\'")}'`
+    : `'${value.replace(/'/g, "'\\''")}'`;
+}
+
+export function checkAliases(aliases) {
+  for (const [name, command] of Object.entries(aliases)) {
+    if (!ALIAS_NAME.test(name)) {
+      throw new Error(`Invalid alias name: ${name}`);
+    }
+    if (typeof command !== 'string' || !command || /[\r\n]/.test(command)) {
+      throw new Error(`Alias ${name} must be a single-line command`);
+    }
+  }
+}
+
+export function renderShellConfig(shell, { framework = null, prompt = 'default', aliases = {} } = {}) {
+  const aliasLines = Object.entries(aliases).map(([name, command]) => shell === 'fish'
+    ? `  alias ${name} ${quote(shell, command)}`
+    : `alias ${name}=${quote(shell, command)}`);
+  const header = '# Managed by mcp-arch-linux (shell_setup); rewritten on every run';
+
+  if (shell === 'fish') {
+    return [
+      header,
+      'if status is-interactive',
+      '  set -g fish_greeting',
+      ...aliasLines,
+      ...(prompt === 'starship' ? ['  starship init fish | source'] : []),
+      'end',
+      ''
+    ].join('\n');
+  }
+
+  if (shell === 'bash') {
+    return [
+      header,
+      '[[ $- != *i* ]] && return',
+      '',
+      'HISTSIZE=10000',
+      'HISTCONTROL=ignoreboth',
+      'shopt -s histappend checkwinsize',
+      '[ -r /usr/share/bash-completion/bash_completion ] && . /usr/share/bash-completion/bash_completion',
+      '',
+      ...aliasLines,
+      '',
+      prompt === 'starship' ? 'eval "$(starship init bash)"' : "PS1='\\[\\e[34m\\]\\w\\[\\e[0m\\] \\$ '",
+      ''
+    ].join('\n');
+  }
+
+  const lines = [
+    header,
+    'HISTFILE=~/.zsh_history',
+    'HISTSIZE=10000',
+    'SAVEHIST=10000',
+    'setopt share_history hist_ignore_dups autocd',
+    ''
+  ];
+  if (framework === 'oh-my-zsh') {
+    lines.push(
+      `export ZSH="$HOME/${OH_MY_ZSH.dir}"`,
+      `ZSH_THEME="${prompt === 'starship' ? '' : 'robbyrussell'}"`,
+      'plugins=(git)',
+      'source "$ZSH/oh-my-zsh.sh"'
+    );
+  } else {
+    lines.push(
+      'bindkey -e',
+      'autoload -Uz compinit && compinit',
+      "zstyle ':completion:*' menu select",
+      ...(prompt === 'starship' ? [] : ["PROMPT='%F{blue}%~%f %# '"])
+    );
+  }
+  lines.push('', ...aliasLines, '');
+  if (prompt === 'starship') {
+    lines.push('eval "$(starship init zsh)"');
+  }
+  // syntax-highlighting has to come last
+  lines.push(
+    `[ -r ${ZSH_PLUGINS}/zsh-autosuggestions/zsh-autosuggestions.zsh ] && source ${ZSH_PLUGINS}/zsh-autosuggestions/zsh-autosuggestions.zsh`,
+    `[ -r ${ZSH_PLUGINS}/zsh-syntax-highlighting/zsh-syntax-highlighting.zsh ] && source ${ZSH_PLUGINS}/zsh-syntax-highlighting/zsh-syntax-highlighting.zsh`,
+    ''
+  );
+  return lines.join('\n');
+}
+
+// The line added to ~/.bashrc or ~/.zshrc
+export function sourceLine(shell) {
+  const managed = `~/${SHELLS[shell].managed}`;
+  return `[ -r ${managed} ] && source ${managed}`;
+}
+
+export function shellPaths(shell, account) {
+  const definition = SHELLS[shell];
+  return {
+    managed: path.join(account.home, definition.managed),
+    rc: definition.rc ? path.join(account.home, definition.rc) : null
+  };
+}
+
+// Output of a shell start that went wrong, as far as it can be told from the terminal
+export const STARTUP_ERROR = /command not found|no such file or directory|parse error|syntax error|unknown command|unmatched|error:|insecure directories/i;
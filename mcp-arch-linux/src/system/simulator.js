@@ -0,0 +1,77 @@
+import fs from 'fs-extra';
+import YAML from 'yaml';
+
+// Stand-in for process execution when simulation.enabled is set: nothing is spawned,
+// and the first rule whose `match` regex finds "command arg1 arg2 ..." supplies the
+// result. Lets plugins and CI drive the whole JSON-RPC surface on machines where
+// partitioning or pacstrap must never run.
+export class Simulator {
+  constructor() {
+    this.enabled = false;
+    this.rules = [];
+    this.fallback = { exitCode: 0, stdout: '', stderr: '' };
+    this.calls = [];
+    this.maxCalls = 1000;
+  }
+
+  async configure(options = {}, logger = null) {
+    this.enabled = Boolean(options.enabled);
+    this.calls = [];
+    if (!this.enabled) {
+      this.rules = [];
+      return;
+    }
+
+    let fileRules = [];
+    if (options.rulesFile) {
+      if (!await fs.pathExists(options.rulesFile)) {
+        throw new Error(`Simulation rules file not found: ${options.rulesFile}`);
+      }
+      fileRules = YAML.parse(await fs.readFile(options.rulesFile, 'utf8'))?.rules || [];
+    }
+
+    // Inline rules come first so a config can override a shared rules file
+    this.rules = [...(options.rules || []), ...fileRules].map(rule => ({ ...rule, pattern: new RegExp(rule.match) }));
+    this.fallback = { exitCode: 0, stdout: '', stderr: '', ...options.default };
+    logger?.warn(`Simulation mode: commands are not executed (${this.rules.length} rules)`);
+  }
+
+  async run(command, args = [], { input = null, onOutput = null, binary = false } = {}) {
+    const line = [command, ...args].join(' ');
+    const rule = this.rules.find(candidate => candidate.pattern.test(line)) || this.fallback;
+    const startTime = Date.now();
+
+    if (rule.delay) {
+      await new Promise(resolve => setTimeout(resolve, rule.delay));
+    }
+
+    const exitCode = rule.exitCode ?? 0;
+    const stdout = String(rule.stdout ?? '');
+    const stderr = String(rule.stderr ?? '');
+    if (stdout) onOutput?.(stdout, 'stdout');
+    if (stderr) onOutput?.(stderr, 'stderr');
+
+    this.calls.push({ command: line, input: input ? String(input) : null, rule: rule.match ?? null, exitCode, timestamp: new Date().toISOString() });
+    if (this.calls.length > this.maxCalls) {
+      this.calls.shift();
+    }
+
+    return {
+      exitCode,
+      stdout: binary ? Buffer.from(stdout) : stdout.trim(),
+      stderr: stderr.trim(),
+      duration: Date.now() - startTime,
+      success: exitCode === 0,
+      simulated: true
+    };
+  }
+}
+
+let sharedSimulator = null;
+
+export function getSimulator() {
+  if (!sharedSimulator) {
+    sharedSimulator = new Simulator();
+  }
+  return sharedSimulator;
+}
@@ -0,0 +1,86 @@
+import crypto from 'crypto';
+import fs from 'fs-extra';
+
+const ALGORITHM = 'aes-256-gcm';
+
+// AES-256-GCM payload encryption plus an HMAC over the whole snapshot document
+export class SnapshotCrypto {
+  constructor(config = {}, logger = null) {
+    this.enabled = config.enabled ?? false;
+    this.keyFile = config.keyFile || null;
+    this.keyEnv = config.keyEnv || 'MCP_SNAPSHOT_KEY';
+    this.logger = logger;
+    this.keys = null;
+  }
+
+  async loadKeys() {
+    if (this.keys) {
+      return this.keys;
+    }
+
+    let material = process.env[this.keyEnv] || null;
+    if (!material && this.keyFile) {
+      material = (await fs.readFile(this.keyFile, 'utf8')).trim();
+    }
+
+    if (!material) {
+      throw new Error(`Snapshot encryption is enabled but no key was found in $${this.keyEnv}${this.keyFile ? ` or ${this.keyFile}` : ''}`);
+    }
+
+    const master = /^[0-9a-f]{64}$/i.test(material) ? Buffer.from(material, 'hex') : Buffer.from(material, 'utf8');
+
+    // Separate keys for encryption and authentication
+    this.keys = {
+      encryption: Buffer.from(crypto.hkdfSync('sha256', master, Buffer.alloc(0), 'mcp-snapshot-encryption', 32)),
+      signing: Buffer.from(crypto.hkdfSync('sha256', master, Buffer.alloc(0), 'mcp-snapshot-signing', 32))
+    };
+
+    return this.keys;
+  }
+
+  async encrypt(plaintext) {
+    const { encryption } = await this.loadKeys();
+    const iv = crypto.randomBytes(12);
+    const cipher = crypto.createCipheriv(ALGORITHM, encryption, iv);
+    const data = Buffer.concat([cipher.update(plaintext, 'utf8'), cipher.final()]);
+
+    return {
+      algorithm: ALGORITHM,
+      iv: iv.toString('base64'),
+      tag: cipher.getAuthTag().toString('base64'),
+      data: data.toString('base64')
+    };
+  }
+
+  async decrypt(payload) {
+    if (payload.algorithm !== ALGORITHM) {
+      throw new Error(`Unsupported snapshot encryption: ${payload.algorithm}`);
+    }
+
+    const { encryption } = await this.loadKeys();
+    const decipher = crypto.createDecipheriv(ALGORITHM, encryption, Buffer.from(payload.iv, 'base64'));
+    decipher.setAuthTag(Buffer.from(payload.tag, 'base64'));
+
+    try {
+      return Buffer.concat([decipher.update(Buffer.from(payload.data, 'base64')), decipher.final()]).toString('utf8');
+    } catch {
+      throw new Error('Snapshot payload failed authentication');
+    }
+  }
+
+  async sign(document) {
+    const { signing } = await this.loadKeys();
+    const { signature, ...unsigned } = document;
+    return crypto.createHmac('sha256', signing).update(JSON.stringify(unsigned)).digest('hex');
+  }
+
+  async verify(document) {
+    if (!document.signature) {
+      return false;
+    }
+
+    const expected = Buffer.from(await this.sign(document), 'hex');
+    const actual = Buffer.from(document.signature, 'hex');
+    return expected.length === actual.length && crypto.timingSafeEqual(expected, actual);
+  }
+}
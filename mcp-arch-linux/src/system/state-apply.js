@@ -0,0 +1,325 @@
+import fs from 'fs-extra';
+import path from 'path';
+import { targetPath } from './target.js';
+
+const NAME_PATTERN = /^[a-zA-Z0-9@._+-]+$/;
+
+// Parses /etc/passwd or /etc/group into name -> fields
+async function readDatabase(file) {
+  const content = await fs.readFile(file, 'utf8').catch(() => '');
+  return new Map(content.split('\n').filter(Boolean).map(line => {
+    const fields = line.split(':');
+    return [fields[0], fields];
+  }));
+}
+
+function parseMode(mode) {
+  if (mode === undefined || mode === null) {
+    return null;
+  }
+  const value = typeof mode === 'number' ? mode : parseInt(String(mode), 8);
+  if (!Number.isInteger(value) || value < 0 || value > 0o7777) {
+    throw new Error(`Invalid file mode: ${mode}`);
+  }
+  return value;
+}
+
+function formatMode(mode) {
+  return `0${mode.toString(8).padStart(3, '0')}`;
+}
+
+function checkName(kind, name) {
+  if (typeof name !== 'string' || !NAME_PATTERN.test(name)) {
+    throw new Error(`Invalid ${kind} name: ${name}`);
+  }
+  return name;
+}
+
+// A declared state: packages present/absent, users that exist, files with content and
+// permissions, and services enabled/running. Anything not mentioned is left alone.
+export function normalizeState(state = {}) {
+  const packages = Array.isArray(state.packages) ? { present: state.packages } : state.packages || {};
+  const services = Array.isArray(state.services) ? { enabled: state.services } : state.services || {};
+
+  return {
+    packages: {
+      present: (packages.present || []).map(name => checkName('package', name)),
+      absent: (packages.absent || []).map(name => checkName('package', name))
+    },
+    users: (state.users || []).map(user => {
+      const spec = typeof user === 'string' ? { name: user } : user;
+      return {
+        name: checkName('user', spec.name),
+        groups: (spec.groups || []).map(name => checkName('group', name)),
+        shell: spec.shell || null,
+        home: spec.home || null,
+        system: Boolean(spec.system)
+      };
+    }),
+    files: (state.files || []).map(file => {
+      if (!file.path || !path.isAbsolute(file.path)) {
+        throw new Error(`File paths must be absolute: ${file.path}`);
+      }
+      return {
+        path: path.resolve(file.path),
+        state: file.state || 'present',
+        content: file.content ?? null,
+        mode: parseMode(file.mode),
+        owner: file.owner ? checkName('user', file.owner) : null,
+        group: file.group ? checkName('group', file.group) : null
+      };
+    }),
+    services: {
+      enabled: (services.enabled || []).map(name => checkName('service', name)),
+      disabled: (services.disabled || []).map(name => checkName('service', name)),
+      running: (services.running || []).map(name => checkName('service', name)),
+      stopped: (services.stopped || []).map(name => checkName('service', name))
+    }
+  };
+}
+
+// Compares a declared state with the target and converges it. Steps run in dependency
+// order: packages, then users (files may belong to them), then files, then services
+// (units come from packages and read the files).
+export class StateApplier {
+  constructor(executor, logger) {
+    this.executor = executor;
+    this.logger = logger;
+  }
+
+  async plan(state, target) {
+    const changes = [];
+
+    if (state.packages.present.length > 0 || state.packages.absent.length > 0) {
+      const result = await this.executor.execute('pacman', ['-Qq'], { target });
+      if (!result.success) {
+        throw new Error(`Cannot list installed packages: ${result.stderr.trim()}`);
+      }
+      const installed = new Set(result.stdout.split('\n').map(line => line.trim()).filter(Boolean));
+      const install = state.packages.present.filter(name => !installed.has(name));
+      const remove = state.packages.absent.filter(name => installed.has(name));
+      if (install.length > 0) {
+        changes.push({ type: 'packages', action: 'install', names: install });
+      }
+      if (remove.length > 0) {
+        changes.push({ type: 'packages', action: 'remove', names: remove });
+      }
+    }
+
+    const passwd = await readDatabase(targetPath(target, '/etc/passwd'));
+    const groups = await readDatabase(targetPath(target, '/etc/group'));
+    const memberOf = (user) => Array.from(groups.values())
+      .filter(fields => (fields[3] || '').split(',').includes(user) || fields[2] === passwd.get(user)?.[3])
+      .map(fields => fields[0]);
+
+    for (const user of state.users) {
+      const entry = passwd.get(user.name);
+      if (!entry) {
+        changes.push({ type: 'user', action: 'create', name: user.name, spec: user });
+        continue;
+      }
+      const differences = {};
+      const missingGroups = user.groups.filter(group => !memberOf(user.name).includes(group));
+      if (missingGroups.length > 0) {
+        differences.groups = missingGroups;
+      }
+      if (user.shell && entry[6] !== user.shell) {
+        differences.shell = user.shell;
+      }
+      if (Object.keys(differences).length > 0) {
+        changes.push({ type: 'user', action: 'update', name: user.name, ...differences });
+      }
+    }
+
+    for (const file of state.files) {
+      const change = await this.planFile(file, target, passwd, groups);
+      if (change) {
+        changes.push(change);
+      }
+    }
+
+    const unitState = async (verb, name) =>
+      (await this.executor.execute('systemctl', [verb, name], { target })).stdout.trim();
+
+    for (const name of state.services.enabled) {
+      if (await unitState('is-enabled', name) !== 'enabled') {
+        changes.push({ type: 'service', action: 'enable', name });
+      }
+    }
+    for (const name of state.services.disabled) {
+      if (await unitState('is-enabled', name) === 'enabled') {
+        changes.push({ type: 'service', action: 'disable', name });
+      }
+    }
+
+    if (target.type === 'chroot' && (state.services.running.length > 0 || state.services.stopped.length > 0)) {
+      throw new Error('Nothing runs in a chroot; services can only be enabled or disabled there');
+    }
+    for (const name of state.services.running) {
+      if (await unitState('is-active', name) !== 'active') {
+        changes.push({ type: 'service', action: 'start', name });
+      }
+    }
+    for (const name of state.services.stopped) {
+      if (await unitState('is-active', name) === 'active') {
+        changes.push({ type: 'service', action: 'stop', name });
+      }
+    }
+
+    return changes;
+  }
+
+  async planFile(file, target, passwd, groups) {
+    const hostPath = targetPath(target, file.path);
+    const stat = await fs.stat(hostPath).catch(() => null);
+
+    if (file.state === 'absent') {
+      return stat ? { type: 'file', action: 'remove', path: file.path } : null;
+    }
+    if (stat && !stat.isFile()) {
+      throw new Error(`${file.path} exists and is not a regular file`);
+    }
+    if (!stat && file.content === null) {
+      throw new Error(`${file.path} does not exist and no content was given`);
+    }
+
+    // Owners that do not exist yet may be created by this same state
+    const uid = file.owner ? passwd.get(file.owner)?.[2] : undefined;
+    const gid = file.group ? groups.get(file.group)?.[2] : undefined;
+
+    const differences = [];
+    if (file.content !== null && (!stat || await fs.readFile(hostPath, 'utf8') !== file.content)) {
+      differences.push('content');
+    }
+    if (file.mode !== null && (!stat || (stat.mode & 0o7777) !== file.mode)) {
+      differences.push('mode');
+    }
+    if (file.owner && (!stat || uid === undefined || stat.uid !== Number(uid))) {
+      differences.push('owner');
+    }
+    if (file.group && (!stat || gid === undefined || stat.gid !== Number(gid))) {
+      differences.push('group');
+    }
+
+    if (differences.length === 0) {
+      return null;
+    }
+    return {
+      type: 'file',
+      action: stat ? 'update' : 'create',
+      path: file.path,
+      fields: differences,
+      ...(file.mode !== null ? { mode: formatMode(file.mode) } : {}),
+      spec: file
+    };
+  }
+
+  // Applies changes in order and stops at the first failure, since later steps usually
+  // depend on earlier ones. Each change gets a status.
+  async apply(changes, target) {
+    const results = [];
+    let failed = false;
+
+    for (const change of changes) {
+      if (failed) {
+        results.push({ ...change, status: 'skipped' });
+        continue;
+      }
+      try {
+        await this.applyChange(change, target);
+        results.push({ ...change, status: 'changed' });
+      } catch (error) {
+        failed = true;
+        this.logger.error(`apply_state: ${describeChange(change)} failed`, error);
+        results.push({ ...change, status: 'failed', error: error.message });
+      }
+    }
+    return results;
+  }
+
+  async applyChange(change, target) {
+    switch (change.type) {
+      case 'packages':
+        return this.run('pacman', change.action === 'install'
+          ? ['-S', '--needed', '--noconfirm', ...change.names]
+          : ['-Rns', '--noconfirm', ...change.names], target, { timeout: 1800000 });
+      case 'user':
+        if (change.action === 'create') {
+          const { spec } = change;
+          return this.run('useradd', [
+            spec.system ? '--system' : '--create-home',
+            ...(spec.shell ? ['--shell', spec.shell] : []),
+            ...(spec.home ? ['--home-dir', spec.home] : []),
+            ...(spec.groups.length > 0 ? ['--groups', spec.groups.join(',')] : []),
+            change.name
+          ], target);
+        }
+        return this.run('usermod', [
+          ...(change.shell ? ['--shell', change.shell] : []),
+          ...(change.groups ? ['--append', '--groups', change.groups.join(',')] : []),
+          change.name
+        ], target);
+      case 'file':
+        return this.applyFile(change, target);
+      case 'service':
+        return this.run('systemctl', [change.action, change.name], target);
+      default:
+        throw new Error(`Unknown change type: ${change.type}`);
+    }
+  }
+
+  async applyFile(change, target) {
+    const hostPath = targetPath(target, change.path);
+    if (change.action === 'remove') {
+      await fs.remove(hostPath);
+      return;
+    }
+
+    const { spec } = change;
+    if (change.fields.includes('content')) {
+      await fs.ensureDir(path.dirname(hostPath));
+      await fs.writeFile(hostPath, spec.content, spec.mode !== null ? { mode: spec.mode } : {});
+    }
+    if (spec.mode !== null) {
+      await fs.chmod(hostPath, spec.mode);
+    }
+    if (spec.owner || spec.group) {
+      // Re-read: users created earlier in this run are not in the planned view
+      const passwd = await readDatabase(targetPath(target, '/etc/passwd'));
+      const groups = await readDatabase(targetPath(target, '/etc/group'));
+      const stat = await fs.stat(hostPath);
+      const uid = spec.owner ? passwd.get(spec.owner)?.[2] : stat.uid;
+      const gid = spec.group ? groups.get(spec.group)?.[2] : stat.gid;
+      if (uid === undefined || gid === undefined) {
+        throw new Error(`Unknown ${uid === undefined ? `user ${spec.owner}` : `group ${spec.group}`} for ${change.path}`);
+      }
+      await fs.chown(hostPath, Number(uid), Number(gid));
+    }
+  }
+
+  async run(command, args, target, options = {}) {
+    const result = await this.executor.executeWithSudo(command, args, { target, retry: false, ...options });
+    if (!result.success) {
+      throw new Error(`${command} ${args.join(' ')}: ${result.stderr.trim() || `exit ${result.exitCode}`}`);
+    }
+  }
+}
+
+export function describeChange(change) {
+  switch (change.type) {
+    case 'packages':
+      return `${change.action} packages ${change.names.join(' ')}`;
+    case 'user':
+      return change.action === 'create'
+        ? `create user ${change.name}`
+        : `update user ${change.name} (${[change.groups && `groups +${change.groups.join(',')}`, change.shell && `shell ${change.shell}`].filter(Boolean).join(', ')})`;
+    case 'file':
+      return change.action === 'remove'
+        ? `remove ${change.path}`
+        : `${change.action} ${change.path} (${change.fields.join(', ')})`;
+    case 'service':
+      return `${change.action} ${change.name}`;
+    default:
+      return JSON.stringify(change);
+  }
+}
@@ -0,0 +1,164 @@
+import fs from 'fs-extra';
+import os from 'os';
+import path from 'path';
+import { fetchArchNews, lastUpgradeTime, NEWS_FEED_URL } from './arch-news.js';
+
+// Upgrading any of these means the running system is out of date until a reboot
+const REBOOT_PACKAGES = /^(linux(-lts|-zen|-hardened|-rt)?|glibc|systemd|intel-ucode|amd-ucode|nvidia(-lts|-open|-dkms)?)$/;
+
+export class SystemUpdate {
+  constructor(commandExecutor, packageState, logger, options = {}) {
+    this.commandExecutor = commandExecutor;
+    this.packageState = packageState;
+    this.logger = logger;
+    this.newsUrl = options.newsUrl || NEWS_FEED_URL;
+    this.newsCacheTtl = options.newsCacheTtl ?? 10 * 60 * 1000;
+    this.newsCache = null;
+    this.pacnewRoots = options.pacnewRoots || ['/etc'];
+  }
+
+  // The feed changes a few times a month; resource reads reuse a recent fetch
+  async fetchNews({ refresh = false } = {}) {
+    if (!refresh && this.newsCache && Date.now() - this.newsCache.fetchedAt < this.newsCacheTtl) {
+      return this.newsCache.items;
+    }
+    const items = await fetchArchNews(this.newsUrl);
+    this.newsCache = { items, fetchedAt: Date.now() };
+    return items;
+  }
+
+  // Whole feed with items since the last full upgrade (or the last 30 days on a fresh system) flagged unread
+  async news({ refresh = false } = {}) {
+    const lastUpgrade = await lastUpgradeTime();
+    const since = lastUpgrade || new Date(Date.now() - 30 * 24 * 3600 * 1000);
+    const items = (await this.fetchNews({ refresh })).map(item => ({
+      ...item,
+      unread: Boolean(item.published && new Date(item.published) > since)
+    }));
+
+    return {
+      lastUpgrade: lastUpgrade ? lastUpgrade.toISOString() : null,
+      since: since.toISOString(),
+      items
+    };
+  }
+
+  // Unread news only; update_system refuses to run past unread manual-intervention items
+  async checkNews() {
+    const { since, items } = await this.news({ refresh: true });
+    const unread = items.filter(item => item.unread).map(({ unread, ...item }) => item);
+
+    return {
+      since,
+      items: unread,
+      manualIntervention: unread.filter(item => item.manualIntervention)
+    };
+  }
+
+  async pendingUpdates() {
+    // checkupdates syncs into a temporary database, so it is safe without root
+    const tool = await this.commandExecutor.checkCommandExists('checkupdates') ? 'checkupdates' : null;
+    const result = tool
+      ? await this.commandExecutor.execute('checkupdates', [])
+      : await this.commandExecutor.execute('pacman', ['-Qu']);
+
+    return result.stdout.split('\n').filter(Boolean).map(line => {
+      const [name, from, , to] = line.split(/\s+/);
+      return { name, from, to };
+    });
+  }
+
+  async findPacnew() {
+    const found = [];
+    const walk = async (dir) => {
+      for (const entry of await fs.readdir(dir, { withFileTypes: true }).catch(() => [])) {
+        const full = path.join(dir, entry.name);
+        if (entry.isDirectory() && !entry.isSymbolicLink()) {
+          await walk(full);
+        } else if (entry.name.endsWith('.pacnew')) {
+          found.push(full);
+        }
+      }
+    };
+
+    for (const root of this.pacnewRoots) {
+      await walk(root);
+    }
+    return found.sort();
+  }
+
+  changedPackages(before, after) {
+    const previous = new Map(before.map(pkg => [pkg.name, pkg.version]));
+    return after
+      .filter(pkg => previous.get(pkg.name) !== pkg.version)
+      .map(pkg => ({ name: pkg.name, from: previous.get(pkg.name) || null, to: pkg.version }));
+  }
+
+  async rebootRequired(changed) {
+    const reasons = changed.filter(pkg => REBOOT_PACKAGES.test(pkg.name)).map(pkg => pkg.name);
+
+    // The running kernel's modules disappear when its package is replaced
+    if (!await fs.pathExists(path.join('/usr/lib/modules', os.release()))) {
+      reasons.push(`running kernel ${os.release()} no longer installed`);
+    }
+
+    return { required: reasons.length > 0, reasons: [...new Set(reasons)] };
+  }
+
+  async run({ acknowledgeNews = false, refreshKeyring = true, dryRun = false, onOutput = null } = {}) {
+    const report = { dryRun, news: null, aborted: false };
+
+    try {
+      report.news = await this.checkNews();
+    } catch (error) {
+      report.news = { error: error.message, items: [], manualIntervention: [] };
+      if (!acknowledgeNews) {
+        return { ...report, aborted: true, reason: `Could not check Arch news: ${error.message}` };
+      }
+    }
+
+    if (report.news.manualIntervention.length > 0 && !acknowledgeNews) {
+      return {
+        ...report,
+        aborted: true,
+        reason: 'Arch news requires manual intervention; read it and retry with acknowledgeNews'
+      };
+    }
+
+    if (dryRun) {
+      return { ...report, pending: await this.pendingUpdates(), pacnew: await this.findPacnew() };
+    }
+
+    const before = await this.packageState.capture();
+    const pacnewBefore = new Set(await this.findPacnew());
+    const stream = onOutput ? { onOutput } : {};
+
+    // New packager keys must be in place before packages signed with them are verified
+    if (refreshKeyring) {
+      const keyring = await this.commandExecutor.executeWithSudo(
+        'pacman', ['-Sy', '--needed', '--noconfirm', 'archlinux-keyring'], stream);
+      if (!keyring.success) {
+        throw new Error(`Keyring refresh failed: ${keyring.stderr}`);
+      }
+    }
+
+    const upgrade = await this.commandExecutor.executeWithSudo(
+      'pacman', [refreshKeyring ? '-Su' : '-Syu', '--noconfirm'], stream);
+    if (!upgrade.success) {
+      throw new Error(`Upgrade failed: ${upgrade.stderr || upgrade.stdout}`);
+    }
+
+    const changed = this.changedPackages(before, await this.packageState.capture());
+    const pacnew = await this.findPacnew();
+
+    return {
+      ...report,
+      upgraded: changed,
+      pacnew: {
+        all: pacnew,
+        new: pacnew.filter(file => !pacnewBefore.has(file))
+      },
+      reboot: await this.rebootRequired(changed)
+    };
+  }
+}
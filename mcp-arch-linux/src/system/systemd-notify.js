@@ -0,0 +1,84 @@
+import { execFile } from 'child_process';
+
+const SD_LISTEN_FDS_START = 3;
+
+// Listening fds passed by a systemd socket unit, if they were meant for this process
+export function getListenFds(env = process.env) {
+  if (!env.LISTEN_FDS || parseInt(env.LISTEN_PID, 10) !== process.pid) {
+    return [];
+  }
+
+  const count = parseInt(env.LISTEN_FDS, 10) || 0;
+  return Array.from({ length: count }, (_, index) => SD_LISTEN_FDS_START + index);
+}
+
+// sd_notify via systemd-notify(1); Node has no AF_UNIX datagram sockets, so the unit needs NotifyAccess=all
+export class SystemdNotifier {
+  constructor(logger, env = process.env) {
+    this.logger = logger;
+    this.socket = env.NOTIFY_SOCKET || null;
+    this.watchdogUsec = parseInt(env.WATCHDOG_USEC, 10) || 0;
+    this.watchdogPid = env.WATCHDOG_PID ? parseInt(env.WATCHDOG_PID, 10) : process.pid;
+    this.watchdogTimer = null;
+  }
+
+  get enabled() {
+    return Boolean(this.socket);
+  }
+
+  notify(...states) {
+    if (!this.enabled) {
+      return Promise.resolve(false);
+    }
+
+    return new Promise((resolve) => {
+      execFile('systemd-notify', [`--pid=${process.pid}`, ...states], (error) => {
+        if (error) {
+          this.logger.warn('sd_notify failed:', error.message);
+          resolve(false);
+        } else {
+          resolve(true);
+        }
+      });
+    });
+  }
+
+  ready(status = 'Ready') {
+    return this.notify('READY=1', `STATUS=${status}`);
+  }
+
+  stopping() {
+    this.stopWatchdog();
+    return this.notify('STOPPING=1');
+  }
+
+  status(text) {
+    return this.notify(`STATUS=${text}`);
+  }
+
+  // Ping at half the configured interval, as sd_watchdog_enabled(3) recommends
+  startWatchdog(isHealthy = () => true) {
+    if (!this.enabled || !this.watchdogUsec || this.watchdogPid !== process.pid || this.watchdogTimer) {
+      return;
+    }
+
+    const interval = Math.max(Math.floor(this.watchdogUsec / 2000), 1000);
+    this.watchdogTimer = setInterval(async () => {
+      if (await isHealthy()) {
+        this.notify('WATCHDOG=1');
+      } else {
+        this.logger.warn('Skipping watchdog ping: server unhealthy');
+      }
+    }, interval);
+    this.watchdogTimer.unref();
+
+    this.logger.info(`systemd watchdog enabled (ping every ${interval}ms)`);
+  }
+
+  stopWatchdog() {
+    if (this.watchdogTimer) {
+      clearInterval(this.watchdogTimer);
+      this.watchdogTimer = null;
+    }
+  }
+}
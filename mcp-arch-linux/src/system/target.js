@@ -0,0 +1,132 @@
+import path from 'path';
+
+// Where arch-install mounts the new system unless told otherwise
+export const DEFAULT_INSTALL_ROOT = '/mnt';
+// systemd-nspawn and machinectl look for container trees here
+export const MACHINES_DIR = '/var/lib/machines';
+
+// Target specs: "host", "chroot:<dir>", "container:<machine>", or a name defined in the
+// registry ("install" follows the arch-install mount point)
+export function parseTarget(spec) {
+  if (!spec || spec === 'host') {
+    return { type: 'host', root: '/' };
+  }
+  if (typeof spec === 'object') {
+    return spec;
+  }
+
+  const [type, ...rest] = spec.split(':');
+  const value = rest.join(':');
+  switch (type) {
+    case 'chroot':
+      if (!path.isAbsolute(value)) {
+        throw new Error(`chroot target needs an absolute directory: ${spec}`);
+      }
+      return { type: 'chroot', root: path.resolve(value) };
+    case 'container':
+      if (!/^[a-zA-Z0-9][a-zA-Z0-9_.-]*$/.test(value)) {
+        throw new Error(`Invalid container name: ${spec}`);
+      }
+      return { type: 'container', machine: value, root: path.join(MACHINES_DIR, value) };
+    default:
+      return null;
+  }
+}
+
+export function describeTarget(target) {
+  switch (target.type) {
+    case 'chroot':
+      return `chroot:${target.root}`;
+    case 'container':
+      return `container:${target.machine}`;
+    default:
+      return 'host';
+  }
+}
+
+// Command line that runs `command args` inside the target. Entering a chroot or a
+// container needs root, so callers run the result with sudo.
+export function targetCommand(target, command, args = []) {
+  switch (target?.type) {
+    case 'chroot':
+      return { command: 'arch-chroot', args: [target.root, command, ...args] };
+    case 'container':
+      return {
+        command: 'systemd-run',
+        args: [`--machine=${target.machine}`, '--quiet', '--wait', '--pipe', '--collect', command, ...args]
+      };
+    default:
+      return { command, args };
+  }
+}
+
+// Host path of a file inside the target's filesystem
+export function targetPath(target, file) {
+  return target && target.type !== 'host' ? path.join(target.root, file) : file;
+}
+
+export class TargetRegistry {
+  constructor() {
+    this.named = new Map();
+    this.defaultSpec = 'host';
+  }
+
+  configure(options = {}) {
+    this.named.clear();
+    for (const [name, spec] of Object.entries(options.named || {})) {
+      this.define(name, spec);
+    }
+    this.setDefault(options.default || 'host');
+  }
+
+  define(name, spec) {
+    if (['host', 'chroot', 'container'].includes(name)) {
+      throw new Error(`Reserved target name: ${name}`);
+    }
+    const target = parseTarget(spec);
+    if (!target) {
+      throw new Error(`Invalid target: ${spec}`);
+    }
+    this.named.set(name, target);
+  }
+
+  remove(name) {
+    this.named.delete(name);
+    if (this.defaultSpec === name) {
+      this.defaultSpec = 'host';
+    }
+  }
+
+  setDefault(spec) {
+    this.resolve(spec);
+    this.defaultSpec = spec;
+  }
+
+  // Tools fall back to the default target when called without one
+  resolve(spec = this.defaultSpec) {
+    const target = this.named.get(spec) || parseTarget(spec);
+    if (!target) {
+      throw new Error(`Unknown target: ${spec} (use host, chroot:<dir>, container:<machine> or one of: ${Array.from(this.named.keys()).join(', ') || 'none defined'})`);
+    }
+    return target;
+  }
+
+  list() {
+    return {
+      default: this.defaultSpec,
+      targets: [
+        { name: 'host', ...parseTarget('host') },
+        ...Array.from(this.named, ([name, target]) => ({ name, ...target, spec: describeTarget(target) }))
+      ]
+    };
+  }
+}
+
+let sharedRegistry = null;
+
+export function getTargetRegistry() {
+  if (!sharedRegistry) {
+    sharedRegistry = new TargetRegistry();
+  }
+  return sharedRegistry;
+}
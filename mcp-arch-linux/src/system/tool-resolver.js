@@ -0,0 +1,105 @@
+import fs from 'fs-extra';
+import path from 'path';
+
+export class ToolResolver {
+  constructor(paths = {}) {
+    this.configuredPaths = { ...paths };
+    this.cache = new Map();
+    this.dependencies = new Map();
+    this.simulated = false;
+  }
+
+  // Apply configured absolute tool paths (e.g. a custom grim build)
+  configure(paths = {}) {
+    this.configuredPaths = { ...paths };
+    this.cache.clear();
+  }
+
+  clearCache() {
+    this.cache.clear();
+  }
+
+  async resolve(command) {
+    if (this.cache.has(command)) {
+      return this.cache.get(command);
+    }
+
+    const resolved = await this.lookup(command);
+    this.cache.set(command, resolved);
+    return resolved;
+  }
+
+  async lookup(command) {
+    const configured = this.configuredPaths[command];
+    if (configured) {
+      return await this.isExecutable(configured) ? configured : null;
+    }
+
+    if (path.isAbsolute(command)) {
+      return await this.isExecutable(command) ? command : null;
+    }
+
+    const searchPath = (process.env.PATH || '/usr/local/sbin:/usr/local/bin:/usr/bin:/usr/sbin:/bin:/sbin')
+      .split(path.delimiter)
+      .filter(Boolean);
+
+    for (const dir of searchPath) {
+      const candidate = path.join(dir, command);
+      if (await this.isExecutable(candidate)) {
+        return candidate;
+      }
+    }
+
+    // In simulation mode every tool counts as installed, since none is actually run
+    return this.simulated ? path.join('/usr/bin', command) : null;
+  }
+
+  async isExecutable(filePath) {
+    try {
+      const stats = await fs.stat(filePath);
+      if (!stats.isFile()) return false;
+      await fs.access(filePath, fs.constants.X_OK);
+      return true;
+    } catch {
+      return false;
+    }
+  }
+
+  // Resolve a binary on behalf of a plugin and remember the outcome for reporting
+  async resolveFor(pluginName, command) {
+    const resolved = await this.resolve(command);
+
+    if (pluginName) {
+      if (!this.dependencies.has(pluginName)) {
+        this.dependencies.set(pluginName, new Map());
+      }
+      this.dependencies.get(pluginName).set(command, resolved);
+    }
+
+    return resolved;
+  }
+
+  getDependencyReport() {
+    const report = {};
+    for (const [pluginName, commands] of this.dependencies.entries()) {
+      report[pluginName] = {};
+      for (const [command, resolved] of commands.entries()) {
+        report[pluginName][command] = {
+          available: resolved !== null,
+          path: resolved,
+          configured: Boolean(this.configuredPaths[command])
+        };
+      }
+    }
+    return report;
+  }
+}
+
+let sharedResolver = null;
+
+export function getToolResolver() {
+  if (!sharedResolver) {
+    sharedResolver = new ToolResolver();
+  }
+  return sharedResolver;
+}
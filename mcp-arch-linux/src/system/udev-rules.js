@@ -0,0 +1,162 @@
+import fs from 'fs-extra';
+
+export const UDEV_RULES_DIR = '/etc/udev/rules.d';
+export const RULE_NAME_PATTERN = /^[a-zA-Z0-9][a-zA-Z0-9_-]{0,63}$/;
+// Rules written by this server; the prefix keeps them apart from hand-written ones
+export const RULE_PREFIX = '70-mcp-';
+
+const USB_ID = /^[0-9a-fA-F]{4}$/;
+const MODE = /^0?[0-7]{3}$/;
+const GROUP = /^[a-z_][a-z0-9_-]{0,31}$/;
+const SYMLINK = /^[a-zA-Z0-9_.-]{1,64}$/;
+const MAC = /^([0-9a-fA-F]{2}:){5}[0-9a-fA-F]{2}$/;
+const IFNAME = /^[a-zA-Z0-9_-]{1,15}$/;
+
+const FIELDS = {
+  vendorId: { pattern: USB_ID, description: 'USB vendor ID (lsusb: the part before the colon)' },
+  productId: { pattern: USB_ID, description: 'USB product ID' },
+  mode: { pattern: MODE, description: 'Device node permissions, e.g. 0660' },
+  group: { pattern: GROUP, description: 'Group owning the device node (e.g. uucp, plugdev)' },
+  uaccess: { type: 'boolean', description: 'Give the logged-in seat user access (TAG+="uaccess")' },
+  symlink: { pattern: SYMLINK, description: 'Stable name under /dev' },
+  mac: { pattern: MAC, description: 'Interface MAC address' },
+  interfaceName: { pattern: IFNAME, description: 'New interface name' }
+};
+
+// Each template names its fields, the subsystem to re-trigger, and how to render a rule
+export const UDEV_TEMPLATES = {
+  'usb-device': {
+    description: 'Permissions for a USB device, e.g. a flashing tool, programmer or SDR',
+    required: ['vendorId', 'productId'],
+    optional: ['mode', 'group', 'uaccess', 'symlink'],
+    subsystem: 'usb',
+    render: (p) => [
+      'SUBSYSTEM=="usb"',
+      `ATTRS{idVendor}=="${p.vendorId.toLowerCase()}"`,
+      `ATTRS{idProduct}=="${p.productId.toLowerCase()}"`,
+      ...permissions(p)
+    ]
+  },
+  'serial-device': {
+    description: 'Stable /dev name and permissions for a USB serial adapter or microcontroller',
+    required: ['vendorId', 'productId'],
+    optional: ['mode', 'group', 'uaccess', 'symlink'],
+    subsystem: 'tty',
+    render: (p) => [
+      'SUBSYSTEM=="tty"',
+      `ATTRS{idVendor}=="${p.vendorId.toLowerCase()}"`,
+      `ATTRS{idProduct}=="${p.productId.toLowerCase()}"`,
+      ...permissions(p)
+    ]
+  },
+  hidraw: {
+    description: 'Raw HID access, e.g. for keyboard configurators and security keys',
+    required: ['vendorId', 'productId'],
+    optional: ['mode', 'group', 'uaccess'],
+    subsystem: 'hidraw',
+    render: (p) => [
+      'KERNEL=="hidraw*"',
+      `ATTRS{idVendor}=="${p.vendorId.toLowerCase()}"`,
+      `ATTRS{idProduct}=="${p.productId.toLowerCase()}"`,
+      ...permissions(p)
+    ]
+  },
+  'network-name': {
+    description: 'Fixed name for a network interface by MAC address',
+    required: ['mac', 'interfaceName'],
+    optional: [],
+    subsystem: 'net',
+    render: (p) => [
+      'SUBSYSTEM=="net"',
+      'ACTION=="add"',
+      `ATTR{address}=="${p.mac.toLowerCase()}"`,
+      `NAME="${p.interfaceName}"`
+    ]
+  }
+};
+
+function permissions(p) {
+  return [
+    p.mode ? `MODE="${p.mode.padStart(4, '0')}"` : null,
+    p.group ? `GROUP="${p.group}"` : null,
+    p.uaccess ? 'TAG+="uaccess"' : null,
+    p.symlink ? `SYMLINK+="${p.symlink}"` : null
+  ].filter(Boolean);
+}
+
+export function ruleFile(name) {
+  if (!RULE_NAME_PATTERN.test(name)) {
+    throw new Error(`Invalid rule name: ${name}`);
+  }
+  return `${UDEV_RULES_DIR}/${RULE_PREFIX}${name}.rules`;
+}
+
+// Checks every parameter against its field pattern so nothing but the template's own
+// keys and validated values reaches the rule file
+async function checkParams(templateName, params) {
+  const template = UDEV_TEMPLATES[templateName];
+  if (!template) {
+    throw new Error(`Unknown udev template: ${templateName} (available: ${Object.keys(UDEV_TEMPLATES).join(', ')})`);
+  }
+
+  const allowed = [...template.required, ...template.optional];
+  for (const key of Object.keys(params)) {
+    if (!allowed.includes(key)) {
+      throw new Error(`${templateName} does not take ${key} (allowed: ${allowed.join(', ')})`);
+    }
+  }
+  for (const key of template.required) {
+    if (params[key] === undefined) {
+      throw new Error(`${templateName} needs ${key}: ${FIELDS[key].description}`);
+    }
+  }
+  for (const [key, value] of Object.entries(params)) {
+    const field = FIELDS[key];
+    if (field.type === 'boolean' ? typeof value !== 'boolean' : typeof value !== 'string' || !field.pattern.test(value)) {
+      throw new Error(`Invalid ${key}: ${value} (${field.description})`);
+    }
+  }
+
+  if (params.group) {
+    const groups = await fs.readFile('/etc/group', 'utf8').catch(() => '');
+    if (!groups.split('\n').some(line => line.split(':')[0] === params.group)) {
+      throw new Error(`Group ${params.group} does not exist`);
+    }
+  }
+  if (['usb-device', 'serial-device', 'hidraw'].includes(templateName) && !params.mode && !params.group && !params.uaccess && !params.symlink) {
+    throw new Error(`${templateName} needs at least one of mode, group, uaccess or symlink`);
+  }
+  return template;
+}
+
+export async function renderRule(templateName, params = {}) {
+  const template = await checkParams(templateName, params);
+  return {
+    subsystem: template.subsystem,
+    content: `# Managed by mcp-arch-linux (template ${templateName})\n${template.render(params).join(', ')}\n`
+  };
+}
+
+// Several devices sharing one template, one rule line each, in a single file
+export async function renderRuleSet(templateName, paramsList) {
+  const lines = [];
+  let subsystem = null;
+  for (const params of paramsList) {
+    const template = await checkParams(templateName, params);
+    subsystem = template.subsystem;
+    lines.push(template.render(params).join(', '));
+  }
+  return {
+    subsystem,
+    content: `# Managed by mcp-arch-linux (template ${templateName})\n${lines.join('\n')}\n`
+  };
+}
+
+export function describeTemplates() {
+  return Object.fromEntries(Object.entries(UDEV_TEMPLATES).map(([name, template]) => [name, {
+    description: template.description,
+    required: template.required,
+    optional: template.optional,
+    fields: Object.fromEntries([...template.required, ...template.optional].map(key => [key, FIELDS[key].description]))
+  }]));
+}
@@ -0,0 +1,103 @@
+// Structured systemd unit rendering; values are validated so a field cannot inject extra directives
+
+export const HARDENING_PRESETS = {
+  none: {},
+  basic: {
+    NoNewPrivileges: 'yes',
+    PrivateTmp: 'yes',
+    ProtectSystem: 'full',
+    ProtectHome: 'read-only'
+  },
+  strict: {
+    NoNewPrivileges: 'yes',
+    PrivateTmp: 'yes',
+    PrivateDevices: 'yes',
+    ProtectSystem: 'strict',
+    ProtectHome: 'yes',
+    ProtectKernelTunables: 'yes',
+    ProtectKernelModules: 'yes',
+    ProtectControlGroups: 'yes',
+    RestrictSUIDSGID: 'yes',
+    RestrictRealtime: 'yes',
+    LockPersonality: 'yes',
+    SystemCallArchitectures: 'native'
+  }
+};
+
+const UNIT_NAME = /^[a-zA-Z0-9:_.@-]+\.service$/;
+const KEY = /^[A-Z][A-Za-z0-9]+$/;
+
+export function normalizeUnitName(name) {
+  const unitName = name.endsWith('.service') ? name : `${name}.service`;
+  if (!UNIT_NAME.test(unitName)) {
+    throw new Error(`Invalid unit name: ${name}`);
+  }
+  return unitName;
+}
+
+function value(field, raw) {
+  const text = String(raw);
+  if (/[\r\n]/.test(text)) {
+    throw new Error(`${field} must be a single line`);
+  }
+  return text;
+}
+
+function quoteEnvironment(name, raw) {
+  if (!/^[A-Za-z_][A-Za-z0-9_]*$/.test(name)) {
+    throw new Error(`Invalid environment variable name: ${name}`);
+  }
+  const text = value(`Environment ${name}`, raw).replace(/\\/g, '\\\\').replace(/"/g, '\\"');
+  return `"${name}=${text}"`;
+}
+
+export function renderUnit(spec) {
+  const lines = [];
+  const section = (title, entries) => {
+    const present = entries.filter(([, v]) => v !== undefined && v !== null && v !== '');
+    if (present.length === 0) return;
+    lines.push(`[${title}]`);
+    for (const [key, v] of present) {
+      lines.push(`${key}=${v}`);
+    }
+    lines.push('');
+  };
+  const many = (field, key, list = []) => list.map(item => [key, value(field, item)]);
+
+  section('Unit', [
+    ['Description', value('description', spec.description)],
+    ['Documentation', spec.documentation && value('documentation', spec.documentation)],
+    ...many('after', 'After', spec.after),
+    ...many('wants', 'Wants', spec.wants),
+    ...many('requires', 'Requires', spec.requires)
+  ]);
+
+  const execStart = Array.isArray(spec.execStart) ? spec.execStart : [spec.execStart];
+  const hardening = {
+    ...HARDENING_PRESETS[spec.hardening || 'none'],
+    ...(spec.serviceOptions || {})
+  };
+
+  section('Service', [
+    ['Type', spec.type || 'simple'],
+    ['User', spec.user && value('user', spec.user)],
+    ['Group', spec.group && value('group', spec.group)],
+    ['WorkingDirectory', spec.workingDirectory && value('workingDirectory', spec.workingDirectory)],
+    ...Object.entries(spec.environment || {}).map(([name, v]) => ['Environment', quoteEnvironment(name, v)]),
+    ...many('execStartPre', 'ExecStartPre', spec.execStartPre),
+    ...many('execStart', 'ExecStart', execStart),
+    ['ExecReload', spec.execReload && value('execReload', spec.execReload)],
+    ['Restart', spec.restart || 'on-failure'],
+    ['RestartSec', spec.restartSec !== undefined ? value('restartSec', spec.restartSec) : undefined],
+    ...Object.entries(hardening).map(([key, v]) => {
+      if (!KEY.test(key)) {
+        throw new Error(`Invalid unit directive: ${key}`);
+      }
+      return [key, value(key, v)];
+    })
+  ]);
+
+  section('Install', (spec.wantedBy ?? ['multi-user.target']).map(target => ['WantedBy', value('wantedBy', target)]));
+
+  return lines.join('\n');
+}
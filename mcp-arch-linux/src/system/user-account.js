@@ -0,0 +1,45 @@
+import fs from 'fs-extra';
+
+// Resolves a login name to uid/gid/home from /etc/passwd
+export async function lookupUser(name) {
+  const entry = (await fs.readFile('/etc/passwd', 'utf8'))
+    .split('\n')
+    .map(line => line.split(':'))
+    .find(([user]) => user === name);
+
+  if (!entry) {
+    throw new Error(`Unknown user: ${name}`);
+  }
+
+  return {
+    name,
+    uid: parseInt(entry[2], 10),
+    gid: parseInt(entry[3], 10),
+    home: entry[5],
+    shell: entry[6]
+  };
+}
+
+export async function lookupUid(uid) {
+  const entry = (await fs.readFile('/etc/passwd', 'utf8'))
+    .split('\n')
+    .map(line => line.split(':'))
+    .find(fields => parseInt(fields[2], 10) === uid);
+
+  if (!entry) {
+    throw new Error(`Unknown uid: ${uid}`);
+  }
+  return lookupUser(entry[0]);
+}
+
+// Name of the account the server runs as
+export async function currentUser() {
+  return process.getuid ? lookupUid(process.getuid()) : lookupUser(process.env.USER);
+}
+
+// Files written as root into a user's home must end up owned by that user
+export async function chownToUser(target, account) {
+  if (process.getuid && process.getuid() === 0 && account.uid !== 0) {
+    await fs.lchown(target, account.uid, account.gid);
+  }
+}
@@ -0,0 +1,78 @@
+import fs from 'fs-extra';
+import path from 'path';
+import { chownToUser } from './user-account.js';
+
+// Helpers for editing config files in a user's home while running as root
+
+export async function readLines(file) {
+  return (await fs.readFile(file, 'utf8').catch(() => '')).split('\n');
+}
+
+export async function writeUserFile(file, content, account) {
+  await fs.ensureDir(path.dirname(file));
+  await fs.writeFile(file, content);
+
+  // Created parent directories and the file itself belong to the user
+  for (let dir = path.dirname(file); dir.startsWith(account.home) && dir !== account.home; dir = path.dirname(dir)) {
+    await chownToUser(dir, account);
+  }
+  await chownToUser(file, account);
+}
+
+// Replace the line matching pattern, or append it
+export function upsertLine(lines, pattern, line) {
+  const result = [...lines];
+  const existing = result.findIndex(current => pattern.test(current));
+  if (existing >= 0) {
+    result[existing] = line;
+  } else {
+    if (result[result.length - 1] === '') result.pop();
+    result.push(line, '');
+  }
+  return result;
+}
+
+// Sets keys inside one [section] of an ini file, creating the section if needed
+export function setIniValues(lines, section, values) {
+  const result = [...lines];
+  let start = result.findIndex(line => line.trim() === `[${section}]`);
+  if (start < 0) {
+    if (result[result.length - 1] === '') result.pop();
+    if (result.length > 0) result.push('');
+    result.push(`[${section}]`);
+    start = result.length - 1;
+  }
+
+  let end = result.findIndex((line, index) => index > start && /^\s*\[.*\]\s*$/.test(line));
+  if (end < 0) end = result.length;
+
+  for (const [key, value] of Object.entries(values)) {
+    const index = result.findIndex((line, i) => i > start && i < end && line.split('=')[0].trim() === key);
+    if (index >= 0) {
+      result[index] = `${key}=${value}`;
+    } else {
+      // Insert after the last non-blank line of the section
+      let insertAt = end;
+      while (insertAt > start + 1 && result[insertAt - 1].trim() === '') insertAt--;
+      result.splice(insertAt, 0, `${key}=${value}`);
+      end++;
+    }
+  }
+
+  if (result[result.length - 1] !== '') result.push('');
+  return result;
+}
+
+export function getIniValues(lines, section) {
+  const values = {};
+  let inSection = false;
+  for (const line of lines) {
+    if (/^\s*\[.*\]\s*$/.test(line)) {
+      inSection = line.trim() === `[${section}]`;
+    } else if (inSection && line.includes('=')) {
+      const index = line.indexOf('=');
+      values[line.slice(0, index).trim()] = line.slice(index + 1).trim();
+    }
+  }
+  return values;
+}
@@ -0,0 +1,76 @@
+import fs from 'fs-extra';
+import path from 'path';
+
+const DRM_DIR = '/sys/class/drm';
+export const ICD_DIRS = ['/usr/share/vulkan/icd.d', '/etc/vulkan/icd.d'];
+
+const VENDORS = {
+  '0x1002': 'amd',
+  '0x10de': 'nvidia',
+  '0x8086': 'intel'
+};
+
+// Vulkan drivers per kernel driver; 32-bit ones are what Steam and Proton games load
+export const VULKAN_DRIVERS = {
+  amdgpu: ['vulkan-radeon', 'lib32-vulkan-radeon'],
+  radeon: ['vulkan-radeon', 'lib32-vulkan-radeon'],
+  i915: ['vulkan-intel', 'lib32-vulkan-intel'],
+  xe: ['vulkan-intel', 'lib32-vulkan-intel'],
+  nvidia: ['nvidia-utils', 'lib32-nvidia-utils'],
+  nouveau: ['vulkan-nouveau', 'lib32-vulkan-nouveau']
+};
+
+// One entry per GPU (DRM card), from sysfs so no lspci is needed
+export async function listGpus() {
+  const gpus = [];
+  for (const name of (await fs.readdir(DRM_DIR).catch(() => [])).filter(entry => /^card\d+$/.test(entry)).sort()) {
+    const device = path.join(DRM_DIR, name, 'device');
+    const vendorId = (await fs.readFile(path.join(device, 'vendor'), 'utf8').catch(() => '')).trim();
+    const deviceId = (await fs.readFile(path.join(device, 'device'), 'utf8').catch(() => '')).trim();
+    const driverLink = await fs.readlink(path.join(device, 'driver')).catch(() => null);
+    const driver = driverLink ? path.basename(driverLink) : null;
+    gpus.push({
+      card: name,
+      vendor: VENDORS[vendorId] || vendorId || null,
+      pciId: vendorId && deviceId ? `${vendorId.slice(2)}:${deviceId.slice(2)}` : null,
+      driver,
+      vulkanPackages: VULKAN_DRIVERS[driver] || []
+    });
+  }
+  return gpus;
+}
+
+// Installed client drivers (ICDs); the loader picks from these
+export async function listIcds() {
+  const icds = [];
+  for (const dir of ICD_DIRS) {
+    for (const file of (await fs.readdir(dir).catch(() => [])).filter(name => name.endsWith('.json'))) {
+      let library = null;
+      try {
+        library = JSON.parse(await fs.readFile(path.join(dir, file), 'utf8')).ICD?.library_path || null;
+      } catch {
+        library = null;
+      }
+      icds.push({ file: path.join(dir, file), library, arch: /i686|32/.test(file) ? 'i686' : 'x86_64' });
+    }
+  }
+  return icds;
+}
+
+// vulkaninfo --summary: one deviceName/driverName block per physical device
+export function parseVulkanSummary(output) {
+  const devices = [];
+  let current = null;
+  for (const line of output.split('\n')) {
+    if (/^GPU\d+:/.test(line.trim())) {
+      current = {};
+      devices.push(current);
+      continue;
+    }
+    const match = line.match(/^\s*(deviceName|driverName|driverInfo|apiVersion|deviceType)\s*=\s*(.+)$/);
+    if (current && match) {
+      current[match[1]] = match[2].trim();
+    }
+  }
+  return devices;
+}
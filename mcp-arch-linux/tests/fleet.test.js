@@ -0,0 +1,110 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import fs from 'fs/promises';
+import os from 'os';
+import path from 'path';
+import { Fleet } from '../src/core/fleet.js';
+import { resolveSecret } from '../src/security/secrets.js';
+
+const logger = { debug() {}, info() {}, warn() {}, error() {}, audit() {} };
+
+async function fleetWith(inventoryHosts = []) {
+  const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'fleet-'));
+  const inventoryFile = path.join(dir, 'inventory.json');
+  await fs.writeFile(inventoryFile, JSON.stringify({ hosts: inventoryHosts }));
+
+  const fleet = new Fleet();
+  await fleet.configure({
+    inventoryFile,
+    hosts: [
+      { name: 'web1', url: 'http://web1.lan:3000/mcp', token: 'env:WEB1_TOKEN', tags: ['web'] },
+      { name: 'db1', url: 'http://db1.lan:3000/mcp', tags: ['db'] }
+    ]
+  }, logger);
+  return { fleet, inventoryFile };
+}
+
+test('select takes a host name, tag:<tag> or all', async () => {
+  const { fleet } = await fleetWith([{ name: 'web2', url: 'http://web2.lan:3000/mcp', tags: ['web'] }]);
+
+  assert.deepEqual(fleet.select('db1').map(host => host.name), ['db1']);
+  assert.deepEqual(fleet.select('tag:web').map(host => host.name), ['web1', 'web2']);
+  assert.deepEqual(fleet.select('all').map(host => host.name), ['web1', 'db1', 'web2']);
+  assert.throws(() => fleet.select('tag:mail'), /No host matches tag:mail/);
+  assert.throws(() => fleet.select('web3'), /known hosts: web1, db1, web2/);
+});
+
+test('config hosts shadow inventory hosts and cannot be added or removed', async () => {
+  process.env.MAIL1_TOKEN = 'mail1-bearer-secret';
+  const { fleet, inventoryFile } = await fleetWith([{ name: 'web1', url: 'http://elsewhere:3000/mcp' }]);
+
+  assert.equal(fleet.select('web1')[0].url, 'http://web1.lan:3000/mcp');
+  await assert.rejects(fleet.add({ name: 'web1', url: 'http://other:3000/mcp' }), /defined in the config file/);
+  await assert.rejects(fleet.remove('db1'), /defined in the config file/);
+  await assert.rejects(fleet.remove('mail1'), /Unknown host: mail1/);
+
+  await fleet.add({ name: 'mail1', url: 'http://mail1.lan:3000/mcp', token: 'env:MAIL1_TOKEN', tags: ['mail'] });
+  let saved = JSON.parse(await fs.readFile(inventoryFile, 'utf8')).hosts;
+  assert.deepEqual(saved, [{ name: 'mail1', url: 'http://mail1.lan:3000/mcp', token: 'env:MAIL1_TOKEN', tags: ['mail'] }]);
+
+  await fleet.remove('mail1');
+  saved = JSON.parse(await fs.readFile(inventoryFile, 'utf8')).hosts;
+  assert.deepEqual(saved, []);
+  assert.deepEqual(fleet.list().map(host => host.name), ['web1', 'db1']);
+});
+
+test('add rejects literal tokens and bad host names', async () => {
+  const { fleet } = await fleetWith();
+
+  await assert.rejects(fleet.add({ name: 'web2', url: 'http://web2.lan:3000/mcp', token: 's3cr3t-token' }), /must be env:<NAME> or file:<path>/);
+  await assert.rejects(fleet.add({ name: '../web2', url: 'http://web2.lan:3000/mcp' }), /Invalid host name/);
+  assert.throws(() => fleet.select('web2'), /No host matches/);
+});
+
+test('list shows which credential scheme a host uses, not the token', async () => {
+  process.env.WEB1_TOKEN = 'web1-bearer-secret';
+  const { fleet } = await fleetWith();
+
+  const hosts = fleet.list();
+  assert.equal(hosts.find(host => host.name === 'web1').credentials, 'env');
+  assert.equal(hosts.find(host => host.name === 'db1').credentials, null);
+  const output = JSON.stringify(hosts);
+  assert.ok(!output.includes('WEB1_TOKEN') && !output.includes('web1-bearer-secret'));
+  assert.deepEqual(fleet.list({ tag: 'db' }).map(host => host.name), ['db1']);
+});
+
+test('resolveSecret reads env: and file: references only', async () => {
+  const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'secret-'));
+  const file = path.join(dir, 'token');
+  await fs.writeFile(file, 'file-token\nignored\n');
+  process.env.FLEET_TEST_TOKEN = 'env-token';
+
+  assert.equal(await resolveSecret(null), null);
+  assert.equal(await resolveSecret('env:FLEET_TEST_TOKEN'), 'env-token');
+  assert.equal(await resolveSecret(`file:${file}`), 'file-token');
+  await assert.rejects(resolveSecret('env:FLEET_TEST_UNSET'), /is not set/);
+  await assert.rejects(resolveSecret(`file:${dir}/missing`), /Cannot read secret/);
+  await assert.rejects(resolveSecret('plain-token'), /must be env:<NAME> or file:<path>/);
+  await assert.rejects(resolveSecret('https://vault/token'), /must be env:<NAME> or file:<path>/);
+});
+
+test('calls on several hosts are merged and labelled by host', async () => {
+  const { fleet } = await fleetWith();
+  fleet.clients.set('web1', { callTool: async () => ({ content: [{ type: 'text', text: 'up 3 days' }, { type: 'image', data: 'x' }] }) });
+  fleet.clients.set('db1', { callTool: async () => { throw new Error('connection refused'); } });
+
+  const result = await fleet.callTool(fleet.select('all'), 'system_info', {});
+  assert.deepEqual(result.content, [
+    { type: 'text', text: '[web1] up 3 days' },
+    { type: 'image', data: 'x' },
+    { type: 'text', text: '[db1] connection refused' }
+  ]);
+  assert.equal(result.isError, true);
+  assert.deepEqual(result.hosts, [{ name: 'web1', isError: false }, { name: 'db1', isError: true }]);
+  // The failed connection is dropped so the next call reconnects
+  assert.ok(fleet.clients.has('web1') && !fleet.clients.has('db1'));
+
+  // A single host's failure is thrown, named after the host
+  fleet.clients.set('db1', { callTool: async () => { throw new Error('timeout'); } });
+  await assert.rejects(fleet.callTool(fleet.select('db1'), 'system_info', {}), /^Error: db1: timeout$/);
+});
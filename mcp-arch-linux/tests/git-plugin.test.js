@@ -0,0 +1,30 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import fs from 'fs';
+import os from 'os';
+import path from 'path';
+import { GitPlugin } from '../src/plugins/git-plugin.js';
+
+const logger = { debug() {}, info() {}, warn() {}, error() {}, audit() {} };
+
+// Reads like Config: settings only through get()
+const configWith = settings => ({ get: keyPath => keyPath.split('.').reduce((value, key) => value?.[key], settings) });
+
+test('a configured allowedPaths entry is accepted', async () => {
+  const base = fs.realpathSync(fs.mkdtempSync(path.join(os.tmpdir(), 'git-plugin-')));
+  const repo = path.join(base, 'alice', 'dotfiles');
+  fs.mkdirSync(repo, { recursive: true });
+  try {
+    const plugin = new GitPlugin(configWith({ security: {}, plugins: { git: { allowedPaths: [`${base}/*/dotfiles`] } } }), logger, {});
+    assert.equal(await plugin.resolveAllowed(repo), repo);
+    await assert.rejects(plugin.resolveAllowed('/etc'), /Path not allowed/);
+  } finally {
+    fs.rmSync(base, { recursive: true, force: true });
+  }
+});
+
+test('without configured paths only /etc is allowed', async () => {
+  const plugin = new GitPlugin(configWith({ security: {} }), logger, {});
+  assert.deepEqual(plugin.allowedPaths, ['/etc']);
+  await assert.rejects(plugin.resolveAllowed(os.tmpdir()), /Path not allowed/);
+});
@@ -0,0 +1,28 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import fs from 'fs/promises';
+import os from 'os';
+import path from 'path';
+import { HelperServer, HelperClient } from '../src/core/helper-channel.js';
+
+const logger = { debug() {}, info() {}, warn() {}, error() {}, audit() {} };
+
+test('the helper refuses request bodies over the size limit and drops the connection', async () => {
+  const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'helper-'));
+  const options = { socketPath: path.join(dir, 'helper.sock'), tokenFile: path.join(dir, 'token') };
+  const server = new HelperServer({ pluginManager: {}, logger, security: {}, ...options, maxRequestSize: 1024 });
+  await server.start();
+  const client = new HelperClient(options);
+
+  try {
+    // Within the limit the request reaches the protocol (which has no such method)
+    await assert.rejects(client.request('ping'), { code: -32601 });
+
+    await assert.rejects(client.request('ping', { padding: 'x'.repeat(4096) }), { code: -32600, message: /exceeds 1024 bytes/ });
+    await new Promise(resolve => setImmediate(resolve));
+    const open = await new Promise(resolve => server.server.getConnections((error, count) => resolve(count)));
+    assert.equal(open, 0);
+  } finally {
+    await server.stop();
+  }
+});
@@ -0,0 +1,22 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import { MCPProtocol } from '../src/core/mcp-protocol.js';
+
+const logger = { debug() {}, info() {}, warn() {}, error() {}, audit() {} };
+
+test('only the session a server request went to can answer it', async () => {
+  const protocol = new MCPProtocol({}, logger, {});
+  const sent = [];
+  protocol.attach('victim', message => sent.push(message));
+  protocol.attach('other', () => {});
+
+  const answer = protocol.sendRequest('roots/list', {}, { sessionId: 'victim', timeout: 1000 });
+  const [{ id }] = sent;
+  assert.match(id, /^srv-[0-9a-f-]{36}$/);
+
+  await protocol.handleRequest({ jsonrpc: '2.0', id, result: { roots: [{ uri: 'file:///' }] } }, { sessionId: 'other' });
+  assert.ok(protocol.pendingRequests.has(id));
+
+  await protocol.handleRequest({ jsonrpc: '2.0', id, result: { roots: [{ uri: 'file:///home/alice' }] } }, { sessionId: 'victim' });
+  assert.deepEqual(await answer, { roots: [{ uri: 'file:///home/alice' }] });
+});
@@ -0,0 +1,43 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import { pluginConfig } from '../src/plugins/base-plugin.js';
+import { SensorsPlugin } from '../src/plugins/sensors-plugin.js';
+import { DevicesPlugin } from '../src/plugins/devices-plugin.js';
+
+// Reads like Config: settings only through get(), nothing under .plugins
+class ConfigStub {
+  constructor(config) {
+    this.config = config;
+  }
+
+  get(keyPath) {
+    return keyPath.split('.').reduce((value, key) => value?.[key], this.config);
+  }
+}
+
+const logger = { debug() {}, info() {}, warn() {}, error() {}, audit() {} };
+
+test('plugin sections are read through Config.get and from plain objects', () => {
+  const settings = { plugins: { sensors: { interval: 1500 } } };
+  assert.deepEqual(pluginConfig(new ConfigStub(settings), 'sensors'), { interval: 1500 });
+  assert.deepEqual(pluginConfig(settings, 'sensors'), { interval: 1500 });
+  assert.deepEqual(pluginConfig(new ConfigStub({}), 'sensors'), {});
+});
+
+test('non-default plugin settings reach the plugins', () => {
+  const config = new ConfigStub({
+    security: {},
+    plugins: {
+      sensors: { interval: 1500, hysteresis: 2, limits: { default: 70 } },
+      devices: { partitionBackupDir: '/srv/partition-backups' }
+    }
+  });
+
+  const sensors = new SensorsPlugin(config, logger, {});
+  assert.equal(sensors.monitor.interval, 1500);
+  assert.equal(sensors.monitor.hysteresis, 2);
+  assert.equal(sensors.monitor.limits.default, 70);
+
+  const devices = new DevicesPlugin(config, logger, {});
+  assert.equal(devices.partitionTable.backupDir, '/srv/partition-backups');
+});
@@ -0,0 +1,46 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import { PluginManager } from '../src/core/plugin-manager.js';
+
+function recordingLogger() {
+  const lines = [];
+  const record = level => (message, meta) => lines.push(`${level} ${message} ${JSON.stringify(meta ?? null)}`);
+  return { lines, debug: record('debug'), info: record('info'), warn: record('warn'), error: record('error'), audit: record('audit') };
+}
+
+const security = {
+  readOnly: false,
+  autoInhibitIdle: false,
+  presence: { guard: false },
+  maintenance: { enabled: false },
+  locks: { acquire: async () => () => {} }
+};
+
+const installPlugin = {
+  name: 'arch-install',
+  getTools: async () => ['arch_configure_system', 'arch_install_target'].map(name => ({ name, inputSchema: { type: 'object' } })),
+  getResources: async () => [],
+  executeTool: async () => ({ content: [{ type: 'text', text: 'configured' }] })
+};
+
+test('install passwords are not logged at debug level', async () => {
+  const logger = recordingLogger();
+  const manager = new PluginManager(logger, security);
+  await manager.register(installPlugin);
+
+  await manager.executeTool('arch_configure_system', {
+    hostname: 'arch',
+    rootPassword: 'root-secret-1',
+    users: [{ name: 'alice', password: 'user-secret-2', groups: ['wheel'] }]
+  });
+  await manager.executeTool('arch_install_target', {
+    config: { rootPassword: 'root-secret-3', users: [{ name: 'bob', password: 'user-secret-4' }] }
+  });
+
+  const log = logger.lines.join('\n');
+  assert.match(log, /debug Executing tool: arch_configure_system/);
+  assert.match(log, /"hostname":"arch"/);
+  for (const secret of ['root-secret-1', 'user-secret-2', 'root-secret-3', 'user-secret-4']) {
+    assert.ok(!log.includes(secret), `${secret} was logged`);
+  }
+});
@@ -0,0 +1,34 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import fs from 'fs/promises';
+import net from 'net';
+import os from 'os';
+import path from 'path';
+import { UnixSocketListener } from '../src/core/unix-socket.js';
+
+const logger = { debug() {}, info() {}, warn() {}, error() {}, audit() {} };
+
+test('the socket is owner-only until its configured mode is set', async () => {
+  const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'unix-socket-'));
+  const server = net.createServer();
+  let modeAtBind = null;
+  const fastify = {
+    listen: ({ path: socketPath }) => new Promise((resolve) => {
+      server.listen(socketPath, async () => {
+        modeAtBind = (await fs.stat(socketPath)).mode & 0o777;
+        resolve();
+      });
+    })
+  };
+
+  const umask = process.umask();
+  const listener = new UnixSocketListener(fastify, { socketPath: path.join(dir, 'server.sock'), mode: '0660', logger });
+  try {
+    await listener.listen();
+    assert.equal(modeAtBind & 0o077, 0);
+    assert.equal((await fs.stat(listener.socketPath)).mode & 0o777, 0o660);
+    assert.equal(process.umask(), umask);
+  } finally {
+    server.close();
+  }
+});